@@ -0,0 +1,11 @@
+fn main() {
+    // no system protoc required
+    std::env::set_var(
+        "PROTOC",
+        protoc_bin_vendored::protoc_bin_path().expect("No bundled protoc for this platform"),
+    );
+    tonic_build::configure()
+        .build_client(false)
+        .compile_protos(&["proto/control.proto"], &["proto"])
+        .expect("Can't compile control protocol");
+}
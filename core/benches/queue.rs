@@ -0,0 +1,85 @@
+//! Playlist and queue operations on large libraries, run with `cargo bench -p audio-wrench-core`
+
+use std::collections::HashSet;
+
+use audio_wrench_core::{playlist, queue};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use rand::prelude::*;
+
+const ENTRIES: usize = 100_000;
+
+fn tracks(prefix: &str) -> Vec<String> {
+    (0..ENTRIES)
+        .map(|i| {
+            format!(
+                "/music/{}/Artist {}/Album/{:05} Track.flac",
+                prefix,
+                i % 500,
+                i
+            )
+        })
+        .collect()
+}
+
+fn playlists(c: &mut Criterion) {
+    let tracks = tracks("a");
+    let m3u: String = tracks.iter().map(|t| format!("{}\n", t)).collect();
+    c.bench_function("decode m3u 100k", |b| {
+        b.iter(|| playlist_decoder::decode(black_box(&m3u)).unwrap())
+    });
+
+    let file = std::env::temp_dir().join("audio_wrench_bench.xspf");
+    let file = file.to_str().unwrap();
+    c.bench_function("write xspf 100k", |b| {
+        b.iter(|| playlist::write_playlist(tracks.iter(), "bench", file).unwrap())
+    });
+    let xspf = std::fs::read_to_string(file).unwrap();
+    c.bench_function("decode xspf 100k", |b| {
+        b.iter(|| playlist_decoder::decode(black_box(&xspf)).unwrap())
+    });
+    let _ = std::fs::remove_file(file);
+}
+
+fn queues(c: &mut Criterion) {
+    let tracks = tracks("a");
+    let mut other = self::tracks("b");
+    // half of the second list is shared
+    other[..ENTRIES / 2].clone_from_slice(&tracks[ENTRIES / 2..]);
+    let broken: HashSet<String> = HashSet::new();
+
+    c.bench_function("insert next 1k into 100k", |b| {
+        b.iter_batched(
+            || (tracks.clone(), other[..1000].to_vec()),
+            |(mut queue, new)| queue::insert_next(&mut queue, true, new),
+            BatchSize::LargeInput,
+        )
+    });
+    c.bench_function("play next 100k", |b| {
+        b.iter_batched(
+            || tracks.clone(),
+            |mut queue| {
+                queue::pop_head(&mut queue, |t| broken.contains(t));
+                // only the last entry matches the filter
+                queue::promote(&mut queue, |t| t.contains("99999"))
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    c.bench_function("shuffle 100k", |b| {
+        let mut rng = thread_rng();
+        b.iter_batched(
+            || tracks.clone(),
+            |mut queue| queue.shuffle(&mut rng),
+            BatchSize::LargeInput,
+        )
+    });
+    c.bench_function("dedup merge 100k", |b| {
+        b.iter(|| {
+            let diff = playlist::Diff::new(black_box(&tracks), black_box(&other));
+            diff.merged(&tracks)
+        })
+    });
+}
+
+criterion_group!(benches, playlists, queues);
+criterion_main!(benches);
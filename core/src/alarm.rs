@@ -0,0 +1,187 @@
+//! Volume by local time of day: alarms starting a playlist with the volume fading in
+//! and quiet hours capping the volume.
+
+use std::{path::PathBuf, time::Duration};
+
+use chrono::{Datelike, NaiveTime};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+pub use chrono::{NaiveDateTime, Weekday};
+
+/// Time a snoozed alarm rings again after
+pub const SNOOZE: Duration = Duration::from_secs(9 * 60);
+
+pub const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Alarm {
+    /// Local time of day like "07:30"
+    #[serde(
+        serialize_with = "serialize_time",
+        deserialize_with = "deserialize_time"
+    )]
+    pub time: NaiveTime,
+    /// Days like "Mon" it rings on, every day if not set and never if empty
+    #[serde(default = "all_weekdays")]
+    pub weekdays: Vec<Weekday>,
+    /// Playlist file started
+    pub playlist: PathBuf,
+    /// Volume reached at the end of the fade in
+    #[serde(default = "default_volume")]
+    pub volume: u8,
+    /// Seconds the volume rises from zero
+    #[serde(default = "default_fade_in")]
+    pub fade_in: u64,
+}
+
+fn all_weekdays() -> Vec<Weekday> {
+    WEEKDAYS.to_vec()
+}
+
+fn default_volume() -> u8 {
+    50
+}
+
+fn default_fade_in() -> u64 {
+    60
+}
+
+fn serialize_time<S: Serializer>(time: &NaiveTime, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&time.format("%H:%M").to_string())
+}
+
+fn deserialize_time<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveTime, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    NaiveTime::parse_from_str(value.trim(), "%H:%M").map_err(serde::de::Error::custom)
+}
+
+impl Alarm {
+    /// Whether the alarm rings after local time `from` up to and including `to`
+    pub fn rings_between(&self, from: NaiveDateTime, to: NaiveDateTime) -> bool {
+        let mut date = from.date();
+        while date <= to.date() {
+            let at = date.and_time(self.time);
+            if at > from && at <= to && self.weekdays.contains(&date.weekday()) {
+                return true;
+            }
+            date += chrono::Duration::days(1);
+        }
+        false
+    }
+
+    /// Volume `elapsed` after the alarm started
+    pub fn volume_at(&self, elapsed: Duration) -> u8 {
+        let fade_in = Duration::from_secs(self.fade_in);
+        if elapsed >= fade_in {
+            return self.volume;
+        }
+        (self.volume as f64 * elapsed.as_secs_f64() / fade_in.as_secs_f64()).round() as u8
+    }
+}
+
+/// Volume ceiling for a time span, e.g. at night
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    /// Local time like "22:00"
+    #[serde(
+        serialize_with = "serialize_time",
+        deserialize_with = "deserialize_time"
+    )]
+    pub start: NaiveTime,
+    /// Local time like "07:00", the span passes midnight if before `start`
+    #[serde(
+        serialize_with = "serialize_time",
+        deserialize_with = "deserialize_time"
+    )]
+    pub end: NaiveTime,
+    pub max_volume: u8,
+}
+
+impl QuietHours {
+    pub fn active(&self, now: NaiveTime) -> bool {
+        match self.start <= self.end {
+            true => now >= self.start && now < self.end,
+            false => now >= self.start || now < self.end,
+        }
+    }
+}
+
+/// Current local time, alarms are compared against
+pub fn local_now() -> NaiveDateTime {
+    chrono::Local::now().naive_local()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        // 2024-01-01 was a Monday
+        NaiveDate::from_ymd(2024, 1, day).and_hms(hour, minute, 0)
+    }
+
+    #[test]
+    fn test_rings_between() {
+        let alarm: Alarm = serde_json::from_str(
+            r#"{"time": "07:30", "weekdays": ["Mon", "Wed"], "playlist": "/wake.m3u"}"#,
+        )
+        .unwrap();
+        assert_eq!(alarm.volume, 50);
+        assert!(alarm.rings_between(at(1, 7, 29), at(1, 7, 30)));
+        assert!(!alarm.rings_between(at(1, 7, 30), at(1, 7, 31)));
+        // tuesday
+        assert!(!alarm.rings_between(at(2, 7, 29), at(2, 7, 31)));
+        // asleep from monday evening until wednesday noon
+        assert!(alarm.rings_between(at(1, 20, 0), at(3, 12, 0)));
+        // clock set back
+        assert!(!alarm.rings_between(at(3, 7, 31), at(3, 7, 29)));
+        let json = serde_json::to_value(&alarm).unwrap();
+        assert_eq!(json["time"], "07:30");
+
+        let daily: Alarm =
+            serde_json::from_str(r#"{"time": "7:05", "playlist": "/wake.m3u"}"#).unwrap();
+        assert!(daily.rings_between(at(2, 7, 0), at(2, 7, 5)));
+    }
+
+    #[test]
+    fn test_quiet_hours() {
+        let quiet: QuietHours =
+            serde_json::from_str(r#"{"start": "22:00", "end": "07:00", "max_volume": 40}"#)
+                .unwrap();
+        let time = |h, m| NaiveTime::from_hms(h, m, 0);
+        assert!(quiet.active(time(23, 0)));
+        assert!(quiet.active(time(6, 59)));
+        assert!(!quiet.active(time(7, 0)));
+        assert!(!quiet.active(time(12, 0)));
+        let lunch = QuietHours {
+            start: time(12, 0),
+            end: time(13, 0),
+            ..quiet
+        };
+        assert!(lunch.active(time(12, 30)));
+        assert!(!lunch.active(time(23, 0)));
+    }
+
+    #[test]
+    fn test_volume_at() {
+        let alarm = Alarm {
+            time: NaiveTime::from_hms(7, 0, 0),
+            weekdays: WEEKDAYS.to_vec(),
+            playlist: PathBuf::from("/wake.m3u"),
+            volume: 80,
+            fade_in: 60,
+        };
+        assert_eq!(alarm.volume_at(Duration::from_secs(0)), 0);
+        assert_eq!(alarm.volume_at(Duration::from_secs(30)), 40);
+        assert_eq!(alarm.volume_at(Duration::from_secs(600)), 80);
+    }
+}
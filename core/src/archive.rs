@@ -0,0 +1,159 @@
+//! Settings archive for export, import and syncing through a shared folder
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::store::Statistics;
+
+/// Archive format version
+const VERSION: u32 = 1;
+/// Archive name inside the sync folder
+const SYNC_FILE: &str = "audio_wrench-sync.json";
+
+/// Config with all playlists and favorites and the statistics of the database,
+/// tagged with its origin
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Archive {
+    version: u32,
+    /// Unix time in seconds
+    pub saved: u64,
+    /// Installation that wrote the archive
+    pub device: u64,
+    pub config: serde_json::Value,
+    /// None without database and in archives of older versions
+    #[serde(default)]
+    pub statistics: Option<Statistics>,
+}
+
+/// Result of syncing to the shared folder
+#[derive(Debug, PartialEq)]
+pub enum SyncResult {
+    Written,
+    /// Another device synced since our last sync, ours was written next to it
+    Conflict(PathBuf),
+}
+
+impl Archive {
+    pub fn new(config: serde_json::Value, statistics: Option<Statistics>, device: u64) -> Self {
+        Self {
+            version: VERSION,
+            saved: now(),
+            device,
+            config,
+            statistics,
+        }
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let data = fs::read_to_string(path).wrap_err("Can't read archive")?;
+        let archive: Self = serde_json::from_str(&data).wrap_err("Invalid archive")?;
+        if archive.version > VERSION {
+            return Err(eyre!(
+                "Archive version {} is not supported",
+                archive.version
+            ));
+        }
+        Ok(archive)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let data = serde_json::to_string(self)?;
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, data).wrap_err("Can't write archive")?;
+        fs::rename(&tmp, path).wrap_err("Can't move archive")?;
+        Ok(())
+    }
+
+    /// Whether this archive was synced by another device after `last_sync`
+    fn is_newer(&self, device: u64, last_sync: u64) -> bool {
+        self.device != device && self.saved > last_sync
+    }
+}
+
+/// Write `archive` to the sync folder, unless another device synced since `last_sync`
+pub fn sync(folder: &Path, archive: &Archive, last_sync: u64) -> Result<SyncResult> {
+    let path = folder.join(SYNC_FILE);
+    if path.is_file() {
+        let other = Archive::read(&path)?;
+        if other.is_newer(archive.device, last_sync) {
+            let path = folder.join(format!(
+                "audio_wrench-sync.conflict-{:x}.json",
+                archive.device
+            ));
+            archive.write(&path)?;
+            return Ok(SyncResult::Conflict(path));
+        }
+    }
+    archive.write(&path)?;
+    Ok(SyncResult::Written)
+}
+
+/// Archive in the sync folder written by another device after `last_sync`
+pub fn newer_synced(folder: &Path, device: u64, last_sync: u64) -> Option<Archive> {
+    let path = folder.join(SYNC_FILE);
+    if !path.is_file() {
+        return None;
+    }
+    match Archive::read(&path) {
+        Ok(v) if v.is_newer(device, last_sync) => Some(v),
+        Ok(_) => None,
+        Err(e) => {
+            warn!("Can't read synced archive {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+/// Current unix time in seconds
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |v| v.as_secs())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sync_conflict() {
+        let folder = std::env::temp_dir().join(format!("audio_wrench_sync_{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let mut ours = Archive::new(serde_json::json!({"volume": 10}), None, 1);
+        let mut theirs = Archive::new(serde_json::json!({"volume": 20}), None, 2);
+
+        assert_eq!(sync(&folder, &ours, 0).unwrap(), SyncResult::Written);
+        // our own archive is never newer
+        assert!(newer_synced(&folder, 1, 0).is_none());
+        let last_sync = ours.saved;
+
+        // the other device imported ours before syncing
+        theirs.saved = last_sync + 10;
+        assert_eq!(
+            sync(&folder, &theirs, last_sync).unwrap(),
+            SyncResult::Written
+        );
+        let newer = newer_synced(&folder, 1, last_sync).unwrap();
+        assert_eq!(newer.device, 2);
+
+        ours.saved = last_sync + 20;
+        match sync(&folder, &ours, last_sync).unwrap() {
+            SyncResult::Conflict(path) => assert!(path.is_file()),
+            v => panic!("expected conflict, got {:?}", v),
+        }
+        // their archive is kept
+        assert_eq!(Archive::read(&folder.join(SYNC_FILE)).unwrap().device, 2);
+        // after taking theirs into account ours replaces it
+        assert_eq!(
+            sync(&folder, &ours, theirs.saved).unwrap(),
+            SyncResult::Written
+        );
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
@@ -0,0 +1,496 @@
+//! Audio output behind the player core: decoding, DSP pipeline and sinks
+
+use std::{
+    io::{Read, Seek},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::Sender,
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use rodio::{OutputStream, OutputStreamHandle, Sink, Source};
+use url::Url;
+
+use crate::decoders;
+use crate::dsp::{
+    self, BoxSource, ChannelMapping, DspStage, EqPreset, Levels, Meter, Preamp, PreampControl,
+    Resampling,
+};
+use crate::jack_output::JackStream;
+use crate::output::{self, AudioRouting, BufferedStream, NetworkOutput, NetworkStream, OutputZone};
+use crate::player::PlayerStatus;
+use crate::prelude::*;
+use crate::readahead::ReadAhead;
+use crate::remote;
+
+/// Started track
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+    /// Displayed name
+    pub name: String,
+    pub length: Option<Duration>,
+    pub source_rate: u32,
+    pub output_rate: u32,
+    pub channels: u16,
+}
+
+/// Audio output used by the player, replaced by a mock in tests
+pub trait AudioBackend {
+    /// Replace the current track with `origin_path`, starting at `position`
+    fn play(&mut self, origin_path: &str, volume: u8, position: Duration) -> Result<Track>;
+    fn set_paused(&mut self, paused: bool);
+    /// Stop the current track without it counting as finished
+    fn stop(&mut self);
+    fn set_volume(&mut self, volume: u8);
+    /// Whether the current track played completely, false without track or after replacing it
+    fn is_finished(&self) -> bool;
+    fn levels(&self) -> Levels;
+    /// Whether samples clipped since the last call
+    fn take_clipped(&self) -> bool;
+    /// Output underruns since start, None if the output doesn't report them
+    fn underruns(&self) -> Option<u64>;
+    fn set_preamp(&mut self, db: i16);
+    /// Applies from the next track on
+    fn set_channels(&mut self, mapping: ChannelMapping);
+    /// Applies from the next track on
+    fn set_equalizer(&mut self, preset: Option<EqPreset>);
+    /// Applies from the next track on
+    fn set_dsp_chain(&mut self, chain: Vec<DspStage>);
+    fn set_zone_volume(&mut self, zone: usize, volume: u8);
+    /// Follow the system default device from now on, unless a device is set in the routing
+    fn set_follow_default(&mut self, follow: bool);
+    /// Reopen the main output if it follows the system default device and that changed.
+    /// Returns whether it was reopened, the current track has to be restarted then.
+    fn follow_default_device(&mut self) -> bool;
+    /// Credentials for fetching tracks of remote libraries
+    fn set_credentials(&mut self, credentials: remote::Credentials);
+}
+
+/// Output of the main sink
+enum MainOutput {
+    Device(OutputStreamHandle),
+    /// Device opened with a configured buffer size
+    Buffered(BufferedStream),
+    Jack(JackStream),
+}
+
+pub struct RodioBackend {
+    /// Kept for the output to stay open
+    _streams: Vec<OutputStream>,
+    /// Stream of the main device output, replaced when following the default device
+    _main_stream: Option<OutputStream>,
+    /// Main output is on a configured device or JACK, it can't follow the default device
+    pinned: bool,
+    /// Name of the default device the main output was opened on, None if not following it
+    default_device: Option<String>,
+    buffer_frames: Option<u32>,
+    sink: Option<Sink>,
+    /// Decremented by the source of the current track once it runs out
+    done: Option<Arc<AtomicUsize>>,
+    main: MainOutput,
+    /// Additional outputs playing the same source
+    zones: Vec<OutputZone>,
+    network: Option<NetworkOutput>,
+    network_stream: Option<NetworkStream>,
+    resampling: Resampling,
+    channels: ChannelMapping,
+    eq: Option<EqPreset>,
+    dsp_chain: Vec<DspStage>,
+    /// Levels of the current track, updated by the metering tap
+    levels: Arc<Mutex<Levels>>,
+    preamp: Arc<PreampControl>,
+    /// Credentials of the remote libraries, added when fetching their tracks
+    credentials: remote::Credentials,
+    /// Download of the last remote track, to avoid downloading it again on seek
+    remote_cache: Option<(String, remote::Download)>,
+    state_tx: Sender<PlayerStatus>,
+}
+
+impl RodioBackend {
+    /// Open outputs, has to be called on the thread using the backend
+    pub fn new(routing: &AudioRouting, state_tx: Sender<PlayerStatus>) -> Result<Self> {
+        // can't initialize audio on same thread due to "OleInitialize failed! Result was: `RPC_E_CHANGED_MODE"
+        let jack = routing
+            .jack
+            .as_ref()
+            .and_then(|jack| match JackStream::start(jack) {
+                Ok(v) => Some(v),
+                Err(e) => {
+                    let msg = format!("JACK output failed, using the output device: {}", e);
+                    warn!("{}", msg);
+                    let _ = state_tx.send(PlayerStatus::OutputError(msg));
+                    None
+                }
+            });
+        let (streams, zones) = output::open_zones(routing, &state_tx);
+        let buffered = match (&jack, routing.buffer_frames) {
+            (None, Some(frames)) => {
+                match BufferedStream::start(routing.device.as_deref(), frames) {
+                    Ok(v) => Some(v),
+                    Err(e) => {
+                        let msg = format!("{:#}, using the default buffer size", e);
+                        warn!("{}", msg);
+                        let _ = state_tx.send(PlayerStatus::OutputError(msg));
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+        let mut configured = routing.device.clone();
+        let mut main_stream = None;
+        let main = match (jack, buffered) {
+            (Some(v), _) => MainOutput::Jack(v),
+            (None, Some(v)) => MainOutput::Buffered(v),
+            (None, None) => {
+                let (stream, handle) = match output::open_output(configured.as_deref()) {
+                    Ok(v) => v,
+                    // e.g. unplugged, play on the default device instead of not at all
+                    Err(e) if configured.is_some() => {
+                        let msg = format!("{:#}, using the default output device", e);
+                        warn!("{}", msg);
+                        let _ = state_tx.send(PlayerStatus::OutputError(msg));
+                        configured = None;
+                        output::open_output(None)?
+                    }
+                    Err(e) => return Err(e),
+                };
+                main_stream = Some(stream);
+                MainOutput::Device(handle)
+            }
+        };
+        let pinned = configured.is_some() || matches!(main, MainOutput::Jack(_));
+        let device = match main {
+            MainOutput::Jack(_) => Some(String::from("JACK")),
+            _ => configured.or_else(output::default_device_name),
+        };
+        if let Some(device) = device {
+            let _ = state_tx.send(PlayerStatus::OutputDevice(device));
+        }
+        Ok(Self {
+            _streams: streams,
+            _main_stream: main_stream,
+            pinned,
+            default_device: match routing.follow_default && !pinned {
+                true => output::default_device_name(),
+                false => None,
+            },
+            buffer_frames: match main {
+                MainOutput::Buffered(_) => routing.buffer_frames,
+                _ => None,
+            },
+            sink: None,
+            done: None,
+            main,
+            zones,
+            network: routing.network.clone(),
+            network_stream: None,
+            resampling: routing.resampling,
+            channels: routing.channels,
+            eq: None,
+            dsp_chain: Vec::new(),
+            levels: Default::default(),
+            preamp: Default::default(),
+            credentials: remote::Credentials::default(),
+            remote_cache: None,
+            state_tx,
+        })
+    }
+}
+
+impl AudioBackend for RodioBackend {
+    fn play(&mut self, origin_path: &str, volume: u8, position: Duration) -> Result<Track> {
+        self.stop();
+        let Opened {
+            name,
+            source: input,
+            length,
+        } = open_track(
+            origin_path,
+            position,
+            &mut self.credentials,
+            &mut self.remote_cache,
+        )?;
+        let source_rate = input.sample_rate();
+        let channels = input.channels();
+        let input = dsp::map_channels(input, self.channels);
+        let input = dsp::resample(input, self.resampling);
+        let input = dsp::equalize(input, self.eq);
+        let input = dsp::apply_chain(input, &self.dsp_chain, &self.state_tx);
+        *self.levels.lock().expect("Can't lock levels") = Levels::default();
+        let input = Preamp::new(input, self.preamp.clone());
+        let input = Meter::new(input, self.levels.clone());
+        let output_rate = input.sample_rate();
+        // decode once, share the decoded frames with all zones
+        let input = input.buffered();
+        for zone in self.zones.iter_mut() {
+            let handle = match zone.handle {
+                Some(ref v) => v,
+                None => continue,
+            };
+            match Sink::try_new(handle) {
+                Ok(sink) => {
+                    sink.set_volume(calc_volume(zone.volume));
+                    sink.append(input.clone());
+                    zone.sink = Some(sink);
+                }
+                Err(e) => warn!("Can't open playback-sink for zone: {}", e),
+            }
+        }
+        if let Some(ref network) = self.network {
+            match NetworkStream::start(network, Box::new(input.clone())) {
+                Ok(v) => self.network_stream = Some(v),
+                Err(e) => {
+                    warn!("Can't start network output: {}", e);
+                    self.state_tx
+                        .send(PlayerStatus::OutputError(format!(
+                            "Network output failed: {}",
+                            e
+                        )))
+                        .expect("Can't send playback status!");
+                }
+            }
+        }
+        let new_sink = match self.main {
+            MainOutput::Device(ref handle) => {
+                Sink::try_new(handle).expect("Can't open new playback-sink!")
+            }
+            MainOutput::Buffered(ref stream) => {
+                let (sink, queue) = Sink::new_idle();
+                stream.set_source(queue);
+                sink
+            }
+            MainOutput::Jack(ref jack) => {
+                let (sink, queue) = Sink::new_idle();
+                jack.set_source(queue);
+                sink
+            }
+        };
+        new_sink.set_volume(calc_volume(volume));
+        let done = Arc::new(AtomicUsize::new(1));
+        new_sink.append(rodio::source::Done::new(input, done.clone()));
+        self.sink = Some(new_sink);
+        self.done = Some(done);
+        Ok(Track {
+            name,
+            length,
+            source_rate,
+            output_rate,
+            channels,
+        })
+    }
+
+    fn set_paused(&mut self, paused: bool) {
+        let sinks = self
+            .sink
+            .iter()
+            .chain(self.zones.iter().filter_map(|z| z.sink.as_ref()));
+        for sink in sinks {
+            match paused {
+                true => sink.pause(),
+                false => sink.play(),
+            }
+        }
+        if let Some(ref stream) = self.network_stream {
+            stream.set_paused(paused);
+        }
+    }
+
+    fn stop(&mut self) {
+        // a stopped source never runs out, drop the signal anyway so it can't be mistaken
+        self.done = None;
+        if let Some(ref v) = self.sink {
+            v.stop();
+        }
+        for zone in self.zones.iter_mut() {
+            if let Some(sink) = zone.sink.take() {
+                sink.stop();
+            }
+        }
+        self.network_stream = None;
+    }
+
+    fn set_volume(&mut self, volume: u8) {
+        if let Some(ref sink) = self.sink {
+            sink.set_volume(calc_volume(volume));
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.done
+            .as_ref()
+            .is_some_and(|v| v.load(Ordering::Relaxed) == 0)
+    }
+
+    fn levels(&self) -> Levels {
+        *self.levels.lock().expect("Can't lock levels")
+    }
+
+    fn take_clipped(&self) -> bool {
+        self.preamp.take_clipped()
+    }
+
+    fn underruns(&self) -> Option<u64> {
+        match self.main {
+            MainOutput::Device(_) => None,
+            MainOutput::Buffered(ref stream) => Some(stream.underruns()),
+            MainOutput::Jack(ref jack) => Some(jack.underruns()),
+        }
+    }
+
+    fn set_preamp(&mut self, db: i16) {
+        self.preamp.set_gain(db);
+    }
+
+    fn set_channels(&mut self, mapping: ChannelMapping) {
+        self.channels = mapping;
+    }
+
+    fn set_equalizer(&mut self, preset: Option<EqPreset>) {
+        self.eq = preset;
+    }
+
+    fn set_dsp_chain(&mut self, chain: Vec<DspStage>) {
+        self.dsp_chain = chain;
+    }
+
+    fn set_zone_volume(&mut self, zone: usize, volume: u8) {
+        if let Some(zone) = self.zones.get_mut(zone) {
+            zone.volume = volume;
+            if let Some(ref sink) = zone.sink {
+                sink.set_volume(calc_volume(volume));
+            }
+        }
+    }
+
+    fn set_follow_default(&mut self, follow: bool) {
+        self.default_device = match follow && !self.pinned {
+            true => output::default_device_name(),
+            false => None,
+        };
+    }
+
+    fn set_credentials(&mut self, credentials: remote::Credentials) {
+        self.remote_cache = None;
+        self.credentials = credentials;
+    }
+
+    fn follow_default_device(&mut self) -> bool {
+        if self.default_device.is_none() {
+            return false;
+        }
+        let name = match output::default_device_name() {
+            Some(v) if Some(&v) != self.default_device.as_ref() => v,
+            _ => return false,
+        };
+        info!("Default output device changed to {}", name);
+        // not retried until the default changes again
+        self.default_device = Some(name.clone());
+        self.stop();
+        let reopened = match self.buffer_frames {
+            Some(frames) => {
+                BufferedStream::start(None, frames).map(|v| self.main = MainOutput::Buffered(v))
+            }
+            None => output::open_output(None).map(|(stream, handle)| {
+                self._main_stream = Some(stream);
+                self.main = MainOutput::Device(handle);
+            }),
+        };
+        match reopened {
+            Ok(()) => {
+                let _ = self.state_tx.send(PlayerStatus::OutputDevice(name));
+                true
+            }
+            Err(e) => {
+                let msg = format!("Can't switch to the new default output device: {}", e);
+                warn!("{}", msg);
+                let _ = self.state_tx.send(PlayerStatus::OutputError(msg));
+                false
+            }
+        }
+    }
+}
+
+/// Readable and seekable track data
+pub trait ReadSeek: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+/// Opened track, positioned at the requested start
+struct Opened {
+    /// Displayed name
+    name: String,
+    source: BoxSource,
+    length: Option<Duration>,
+}
+
+/// Open track for decoding, starting at `position`.
+/// Paths and file URLs are read from disk, remote tracks are streamed from a download
+/// which is kept in `cache`. Formats rodio can't decode are streamed from ffmpeg.
+fn open_track(
+    origin_path: &str,
+    position: Duration,
+    credentials: &mut remote::Credentials,
+    cache: &mut Option<(String, remote::Download)>,
+) -> Result<Opened> {
+    if let Some(request) = credentials.track_request(origin_path) {
+        let download = match cache {
+            Some((track, download)) if track == origin_path => download.clone(),
+            _ => {
+                let download =
+                    remote::Download::start(request?, origin_path).inspect_err(|_| {
+                        // log in again next time in case the session expired
+                        credentials.forget_sessions()
+                    })?;
+                *cache = Some((origin_path.to_string(), download.clone()));
+                download
+            }
+        };
+        return decode(origin_path.to_string(), download.reader(), position);
+    }
+    let path = match Url::parse(origin_path) {
+        Ok(v) => v
+            .to_file_path()
+            .map_err(|_| eyre!("Unsupported URL {}", origin_path))?,
+        Err(_e) => origin_path.into(),
+    };
+    let name = path.to_string_lossy().into_owned();
+    if decoders::needs_ffmpeg(&path) {
+        let source = decoders::decode(&path, position)?;
+        // the stream of ffmpeg has no length, only reads the header
+        let length = decoders::probe(&path)
+            .map_err(|e| warn!("{:?}", e))
+            .ok()
+            .and_then(|v| v.length);
+        return Ok(Opened {
+            name,
+            source: Box::new(source),
+            length,
+        });
+    }
+    let file = ReadAhead::open(&path)?;
+    if file.is_empty() {
+        return Err(eyre!("Empty file"));
+    }
+    decode(name, file, position)
+}
+
+/// Decode `data` with rodio, skipping everything up to `position`
+fn decode(name: String, data: impl ReadSeek + 'static, position: Duration) -> Result<Opened> {
+    let input = rodio::Decoder::new(data)
+        .map_err(|e| eyre!("Unsupported format, corrupt or copy protected ({})", e))?;
+    let length = input.total_duration();
+    debug!("size_hint {:?}", input.size_hint());
+    Ok(Opened {
+        name,
+        // decodes and drops everything up to the position
+        source: Box::new(input.skip_duration(position)),
+        length,
+    })
+}
+
+fn calc_volume(v: u8) -> f32 {
+    (v as f32) / 100.0
+}
@@ -0,0 +1,339 @@
+//! Audio CDs: drives, the table of contents and track names from MusicBrainz.
+//! Tracks are played from the GVFS `cdda` mount, which offers them as WAV files,
+//! the TOC is read with `cd-discid` for the disc id.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use base64::Engine;
+use serde::Deserialize;
+
+use crate::prelude::*;
+use crate::remote;
+
+/// Sectors per second of audio
+const SECTORS_PER_SECOND: u32 = 75;
+/// Time until a failed lookup of a disc is tried again
+const LOOKUP_RETRY: Duration = Duration::from_secs(300);
+
+/// CD drive and the folder its disc is mounted at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Drive {
+    /// Device name like `sr0`
+    pub name: String,
+    pub device: PathBuf,
+    /// GVFS mount with the tracks, None without audio CD
+    pub mount: Option<PathBuf>,
+}
+
+impl Drive {
+    /// Track files of the disc in playing order
+    pub fn tracks(&self) -> Result<Vec<PathBuf>> {
+        let mount = self
+            .mount
+            .as_ref()
+            .ok_or_else(|| eyre!("No audio CD in {}", self.name))?;
+        let mut tracks: Vec<(u32, PathBuf)> = std::fs::read_dir(mount)
+            .wrap_err_with(|| format!("Can't read {:?}", mount))?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter_map(|path| Some((track_number(&path)?, path)))
+            .collect();
+        tracks.sort();
+        Ok(tracks.into_iter().map(|(_, path)| path).collect())
+    }
+}
+
+impl std::fmt::Display for Drive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+/// Number of a GVFS track file named like `Track 7.wav`
+fn track_number(path: &Path) -> Option<u32> {
+    path.file_stem()?
+        .to_str()?
+        .strip_prefix("Track ")?
+        .parse()
+        .ok()
+}
+
+/// CD drives of the system
+#[cfg(target_os = "linux")]
+pub fn drives() -> Vec<Drive> {
+    use std::os::unix::fs::MetadataExt;
+
+    let gvfs = std::fs::metadata("/proc/self")
+        .map(|v| PathBuf::from(format!("/run/user/{}/gvfs", v.uid())))
+        .ok();
+    let mut drives: Vec<Drive> = match std::fs::read_dir("/sys/block") {
+        Ok(v) => v
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.file_name().to_str().map(str::to_string))
+            .filter(|name| name.starts_with("sr"))
+            .map(|name| Drive {
+                device: Path::new("/dev").join(&name),
+                mount: gvfs
+                    .as_ref()
+                    .map(|v| v.join(format!("cdda:host={}", name)))
+                    .filter(|v| v.is_dir()),
+                name,
+            })
+            .collect(),
+        Err(e) => {
+            warn!("Can't list block devices: {}", e);
+            Vec::new()
+        }
+    };
+    drives.sort_by(|a, b| a.name.cmp(&b.name));
+    drives
+}
+
+// TODO: needs the Windows MCI or IOCTL_CDROM APIs and the macOS DiskArbitration framework
+#[cfg(not(target_os = "linux"))]
+pub fn drives() -> Vec<Drive> {
+    Vec::new()
+}
+
+/// Table of contents of an audio CD
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Toc {
+    /// Start sector of every track, including the 150 sectors of lead-in
+    pub offsets: Vec<u32>,
+    pub lead_out: u32,
+}
+
+impl Toc {
+    /// Read the TOC of the disc in `device`
+    pub fn read(device: &Path) -> Result<Toc> {
+        let output = Command::new("cd-discid")
+            .arg("--musicbrainz")
+            .arg(device)
+            .output()
+            .wrap_err("Can't run cd-discid")?;
+        if !output.status.success() {
+            return Err(eyre!(
+                "cd-discid failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Self::parse(&String::from_utf8_lossy(&output.stdout))
+            .ok_or_else(|| eyre!("Invalid TOC of {:?}", device))
+    }
+
+    /// `cd-discid --musicbrainz` output: track count, offsets and lead-out
+    fn parse(output: &str) -> Option<Toc> {
+        let values = output
+            .split_whitespace()
+            .map(|v| v.parse().ok())
+            .collect::<Option<Vec<u32>>>()?;
+        let (count, rest) = values.split_first()?;
+        let (lead_out, offsets) = rest.split_last()?;
+        match offsets.len() == *count as usize && !offsets.is_empty() && offsets.len() < 100 {
+            true => Some(Toc {
+                offsets: offsets.to_vec(),
+                lead_out: *lead_out,
+            }),
+            false => None,
+        }
+    }
+
+    /// Length of track `index`, counting from 0
+    pub fn length(&self, index: usize) -> Option<Duration> {
+        let start = *self.offsets.get(index)?;
+        let end = self
+            .offsets
+            .get(index + 1)
+            .copied()
+            .unwrap_or(self.lead_out);
+        let sectors = end.checked_sub(start)?;
+        Some(Duration::from_millis(
+            sectors as u64 * 1000 / SECTORS_PER_SECOND as u64,
+        ))
+    }
+
+    /// Disc id of MusicBrainz: SHA-1 of the TOC in hex, in URL safe base64 of their own
+    pub fn musicbrainz_id(&self) -> String {
+        let mut toc = format!("{:02X}{:02X}{:08X}", 1, self.offsets.len(), self.lead_out);
+        for i in 0..99 {
+            toc.push_str(&format!(
+                "{:08X}",
+                self.offsets.get(i).copied().unwrap_or(0)
+            ));
+        }
+        let digest = sha1::Sha1::from(toc.as_bytes()).digest().bytes();
+        base64::engine::general_purpose::STANDARD
+            .encode(digest)
+            .replace('+', ".")
+            .replace('/', "_")
+            .replace('=', "-")
+    }
+}
+
+/// Names of a disc from MusicBrainz
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disc {
+    pub artist: String,
+    pub title: String,
+    pub tracks: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Lookup {
+    #[serde(default)]
+    releases: Vec<Release>,
+}
+
+#[derive(Deserialize)]
+struct Release {
+    title: String,
+    #[serde(rename = "artist-credit", default)]
+    artist_credit: Vec<Credit>,
+    #[serde(default)]
+    media: Vec<Medium>,
+}
+
+#[derive(Deserialize)]
+struct Credit {
+    name: String,
+    #[serde(default)]
+    joinphrase: String,
+}
+
+#[derive(Deserialize)]
+struct Medium {
+    #[serde(default)]
+    discs: Vec<DiscId>,
+    #[serde(default)]
+    tracks: Vec<Track>,
+}
+
+#[derive(Deserialize)]
+struct DiscId {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct Track {
+    title: String,
+}
+
+/// Outcome of the lookup of a disc
+#[derive(Debug, Clone)]
+enum Looked {
+    Disc(Option<Disc>),
+    /// Failed at this time, not tried again until [LOOKUP_RETRY] passed
+    Failed(Instant),
+}
+
+/// Looked up discs by disc id, shared between the threads looking them up.
+/// Lookups block, they're meant to run on a worker.
+#[derive(Debug, Default, Clone)]
+pub struct Lookups(Arc<Mutex<HashMap<String, Looked>>>);
+
+impl Lookups {
+    /// Names of the disc with `toc`, None if unknown or the lookup failed
+    pub fn disc(&self, toc: &Toc) -> Option<Disc> {
+        let id = toc.musicbrainz_id();
+        // held while looking up, the same disc isn't looked up twice at once
+        let mut discs = self.0.lock().expect("Can't lock discs");
+        match discs.get(&id) {
+            Some(Looked::Disc(disc)) => return disc.clone(),
+            Some(Looked::Failed(at)) if at.elapsed() < LOOKUP_RETRY => return None,
+            _ => (),
+        }
+        let looked = match lookup(toc) {
+            Ok(v) => {
+                debug!("Disc {} is {:?}", id, v);
+                Looked::Disc(v)
+            }
+            Err(e) => {
+                warn!("{:?}", e);
+                Looked::Failed(Instant::now())
+            }
+        };
+        discs.insert(id, looked.clone());
+        match looked {
+            Looked::Disc(v) => v,
+            Looked::Failed(_) => None,
+        }
+    }
+}
+
+/// Look up the disc of `toc`, None if MusicBrainz doesn't know it
+pub fn lookup(toc: &Toc) -> Result<Option<Disc>> {
+    let id = toc.musicbrainz_id();
+    let url = format!(
+        "https://musicbrainz.org/ws/2/discid/{}?inc=artist-credits+recordings&fmt=json",
+        id
+    );
+    let response = match remote::agent().get(&url).call() {
+        Ok(v) => v,
+        Err(ureq::Error::Status(404, _)) => return Ok(None),
+        Err(e) => return Err(e).wrap_err("Can't look up the disc"),
+    };
+    let lookup: Lookup = response
+        .into_json()
+        .wrap_err("Invalid MusicBrainz answer")?;
+    Ok(parse_lookup(lookup, &id))
+}
+
+/// First release with a medium of disc `id`
+fn parse_lookup(lookup: Lookup, id: &str) -> Option<Disc> {
+    lookup.releases.into_iter().find_map(|release| {
+        let medium = release
+            .media
+            .into_iter()
+            .find(|m| m.discs.iter().any(|d| d.id == id))?;
+        Some(Disc {
+            artist: release
+                .artist_credit
+                .iter()
+                .map(|c| format!("{}{}", c.name, c.joinphrase))
+                .collect(),
+            title: release.title,
+            tracks: medium.tracks.into_iter().map(|t| t.title).collect(),
+        })
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_toc() {
+        let toc = Toc::parse(
+            "12 150 22767 41887 58317 72102 91375 104652 115380 132165 143932 159870 174597 267257",
+        )
+        .unwrap();
+        assert_eq!(toc.musicbrainz_id(), "I5l9cCSFccLKFEKS.7wqSZAorPU-");
+        assert_eq!(toc.length(0), Some(Duration::from_millis(301560)));
+        assert_eq!(toc.length(12), None);
+        assert_eq!(Toc::parse("2 150 1000"), None);
+        assert_eq!(
+            track_number(Path::new("/cdda:host=sr0/Track 12.wav")),
+            Some(12)
+        );
+
+        let lookup: Lookup = serde_json::from_str(
+            r#"{"releases":[{"title":"Album","artist-credit":[{"name":"A","joinphrase":" & "},{"name":"B"}],
+            "media":[{"discs":[{"id":"other"}],"tracks":[]},{"discs":[{"id":"49HH"}],"tracks":[{"title":"One"},{"title":"Two"}]}]}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            parse_lookup(lookup, "49HH"),
+            Some(Disc {
+                artist: String::from("A & B"),
+                title: String::from("Album"),
+                tracks: vec![String::from("One"), String::from("Two")],
+            })
+        );
+    }
+}
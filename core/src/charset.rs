@@ -0,0 +1,154 @@
+//! Text encodings of playlist files, many older ones were written by Windows players
+//! in the legacy code page instead of UTF-8.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Charset {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// Western European Windows code page, what most players wrote .m3u files in
+    Windows1252,
+    /// ISO-8859-1
+    Latin1,
+    /// Cyrillic Windows code page
+    Windows1251,
+}
+
+/// Windows-1252 characters of 0x80..=0x9F, the rest matches Latin-1.
+/// Undefined bytes map to the control character of the same value, like Windows does.
+const WINDOWS_1252: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+];
+
+/// Windows-1251 characters of 0x80..=0xBF, 0xC0..=0xFF is А..=я
+const WINDOWS_1251: [char; 64] = [
+    'Ђ', 'Ѓ', '‚', 'ѓ', '„', '…', '†', '‡', '€', '‰', 'Љ', '‹', 'Њ', 'Ќ', 'Ћ', 'Џ', 'ђ', '‘', '’',
+    '“', '”', '•', '–', '—', '\u{98}', '™', 'љ', '›', 'њ', 'ќ', 'ћ', 'џ', '\u{A0}', 'Ў', 'ў', 'Ј',
+    '¤', 'Ґ', '¦', '§', 'Ё', '©', 'Є', '«', '¬', '\u{AD}', '®', 'Ї', '°', '±', 'І', 'і', 'ґ', 'µ',
+    '¶', '·', 'ё', '№', 'є', '»', 'ј', 'Ѕ', 'ѕ', 'ї',
+];
+
+impl Charset {
+    pub const ALL: [Charset; 6] = [
+        Charset::Utf8,
+        Charset::Utf16Le,
+        Charset::Utf16Be,
+        Charset::Windows1252,
+        Charset::Latin1,
+        Charset::Windows1251,
+    ];
+
+    /// Guess the charset of `data`, from its byte order mark or content
+    pub fn detect(data: &[u8]) -> Charset {
+        if data.starts_with(&[0xFF, 0xFE]) {
+            return Charset::Utf16Le;
+        }
+        if data.starts_with(&[0xFE, 0xFF]) {
+            return Charset::Utf16Be;
+        }
+        if std::str::from_utf8(data).is_ok() {
+            return Charset::Utf8;
+        }
+        // cyrillic words are runs of high bytes, while accented latin letters
+        // mostly stand alone between ASCII ones
+        let letter = |b: &u8| *b >= 0xC0;
+        let letters = data.iter().filter(|b| letter(b)).count();
+        let runs = data
+            .windows(2)
+            .filter(|w| letter(&w[0]) && letter(&w[1]))
+            .count();
+        match runs * 2 >= letters {
+            true => Charset::Windows1251,
+            false => Charset::Windows1252,
+        }
+    }
+
+    /// Decode `data`, invalid sequences are replaced and a byte order mark is dropped
+    pub fn decode(self, data: &[u8]) -> String {
+        let single_byte = |map: fn(u8) -> char| data.iter().map(|b| map(*b)).collect();
+        let text: String = match self {
+            Charset::Utf8 => String::from_utf8_lossy(data).into_owned(),
+            Charset::Utf16Le => utf16(data, u16::from_le_bytes),
+            Charset::Utf16Be => utf16(data, u16::from_be_bytes),
+            Charset::Latin1 => single_byte(char::from),
+            Charset::Windows1252 => single_byte(|b| match b {
+                0x80..=0x9F => WINDOWS_1252[b as usize - 0x80],
+                _ => char::from(b),
+            }),
+            Charset::Windows1251 => single_byte(|b| match b {
+                0x80..=0xBF => WINDOWS_1251[b as usize - 0x80],
+                0xC0..=0xFF => char::from_u32(0x0410 + (b - 0xC0) as u32).expect("cyrillic letter"),
+                _ => char::from(b),
+            }),
+        };
+        match text.strip_prefix('\u{FEFF}') {
+            Some(v) => v.to_string(),
+            None => text,
+        }
+    }
+}
+
+fn utf16(data: &[u8], from_bytes: fn([u8; 2]) -> u16) -> String {
+    let units: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|v| from_bytes([v[0], v[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+impl std::fmt::Display for Charset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Charset::Utf8 => "UTF-8",
+            Charset::Utf16Le => "UTF-16 LE",
+            Charset::Utf16Be => "UTF-16 BE",
+            Charset::Windows1252 => "Windows-1252",
+            Charset::Latin1 => "ISO-8859-1",
+            Charset::Windows1251 => "Windows-1251",
+        })
+    }
+}
+
+/// Read a text file in `charset`, detected if None
+pub fn read(path: &Path, charset: Option<Charset>) -> Result<String> {
+    let data = std::fs::read(path).wrap_err_with(|| format!("Can't read {:?}", path))?;
+    let charset = charset.unwrap_or_else(|| Charset::detect(&data));
+    debug!("Reading {:?} as {}", path, charset);
+    Ok(charset.decode(&data))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let utf8 = "C:\\Musik\\Ölgemälde – Café.mp3".as_bytes();
+        assert_eq!(Charset::detect(utf8), Charset::Utf8);
+        let western = b"C:\\Musik\\\xD6lgem\xE4lde \x96 Caf\xE9.mp3";
+        assert_eq!(Charset::detect(western), Charset::Windows1252);
+        assert_eq!(
+            Charset::Windows1252.decode(western),
+            "C:\\Musik\\Ölgemälde – Café.mp3"
+        );
+        let cyrillic = b"D:\\\xcc\xf3\xe7\xfb\xea\xe0\\\xca\xe8\xed\xee.mp3";
+        assert_eq!(Charset::detect(cyrillic), Charset::Windows1251);
+        assert_eq!(
+            Charset::Windows1251.decode(cyrillic),
+            "D:\\Музыка\\Кино.mp3"
+        );
+        assert_eq!(Charset::Latin1.decode(b"\x80\xE9"), "\u{80}é");
+
+        let utf16 = [0xFF, 0xFE, b'a', 0, 0xE4, 0];
+        assert_eq!(Charset::detect(&utf16), Charset::Utf16Le);
+        assert_eq!(Charset::Utf16Le.decode(&utf16), "aä");
+        assert_eq!(Charset::Utf8.decode(b"\xEF\xBB\xBFa"), "a");
+    }
+}
@@ -0,0 +1,105 @@
+//! Playback position tracking independent of the audio output
+
+use std::time::{Duration, Instant};
+
+/// Played time of a track, only counting time while running.
+/// Takes the current time as argument, to be testable.
+#[derive(Debug, Clone, Copy)]
+pub struct PlaybackClock {
+    /// Played time up to `running_since`, or in total while paused
+    played: Duration,
+    running_since: Option<Instant>,
+}
+
+impl PlaybackClock {
+    /// Clock running from `position` on
+    pub fn start(position: Duration, now: Instant) -> Self {
+        Self {
+            played: position,
+            running_since: Some(now),
+        }
+    }
+
+    pub fn pause(&mut self, now: Instant) {
+        if let Some(since) = self.running_since.take() {
+            self.played += now.saturating_duration_since(since);
+        }
+    }
+
+    pub fn resume(&mut self, now: Instant) {
+        if self.running_since.is_none() {
+            self.running_since = Some(now);
+        }
+    }
+
+    pub fn position(&self, now: Instant) -> Duration {
+        match self.running_since {
+            Some(since) => self.played + now.saturating_duration_since(since),
+            None => self.played,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::prelude::*;
+
+    #[test]
+    fn test_pause_resume() {
+        let base = Instant::now();
+        let at = |ms| base + Duration::from_millis(ms);
+        let mut clock = PlaybackClock::start(Duration::ZERO, at(0));
+        assert_eq!(clock.position(at(100)), Duration::from_millis(100));
+        clock.pause(at(100));
+        assert_eq!(clock.position(at(500)), Duration::from_millis(100));
+        // repeated pause doesn't count twice
+        clock.pause(at(600));
+        clock.resume(at(1000));
+        clock.resume(at(1100));
+        assert_eq!(clock.position(at(1200)), Duration::from_millis(300));
+        // time going backwards doesn't underflow
+        assert_eq!(clock.position(at(0)), Duration::from_millis(100));
+    }
+
+    /// Compare against a model advancing in fixed steps for random operations
+    #[test]
+    fn test_random_operations() {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        for _ in 0..200 {
+            let base = Instant::now();
+            let mut now_ms = 0u64;
+            let mut model_ms = 0u64;
+            let mut running = true;
+            let mut clock = PlaybackClock::start(Duration::ZERO, base);
+            for _ in 0..50 {
+                let step = rng.gen_range(0..1000);
+                now_ms += step;
+                if running {
+                    model_ms += step;
+                }
+                let now = base + Duration::from_millis(now_ms);
+                match rng.gen_range(0..4) {
+                    0 => {
+                        clock.pause(now);
+                        running = false;
+                    }
+                    1 => {
+                        clock.resume(now);
+                        running = true;
+                    }
+                    2 => {
+                        // seeking restarts the clock, keeping the pause state
+                        model_ms = rng.gen_range(0..100_000);
+                        clock = PlaybackClock::start(Duration::from_millis(model_ms), now);
+                        if !running {
+                            clock.pause(now);
+                        }
+                    }
+                    _ => (),
+                }
+                assert_eq!(clock.position(now), Duration::from_millis(model_ms));
+            }
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! Content filter keeping tracks out of shuffle and auto-fill by title patterns and tags.
+//! Turning it off can be protected by a password, stored as salted hash.
+
+use std::collections::BTreeSet;
+
+use rand::{distributions::Alphanumeric, Rng};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::metadata::TrackInfo;
+use crate::prelude::*;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentFilter {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Regular expressions matched case insensitive against title, artist, album and path
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    /// User tags of blocked tracks
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Block tracks tagged explicit
+    #[serde(default)]
+    pub explicit: bool,
+    /// "salt:sha1" of the password needed to turn the filter off
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+impl ContentFilter {
+    /// Compile the patterns, invalid ones are skipped with an error
+    pub fn matcher(&self) -> Matcher {
+        let patterns = self
+            .patterns
+            .iter()
+            .filter_map(|p| {
+                RegexBuilder::new(p)
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|e| error!("Invalid content filter pattern {:?}: {}", p, e))
+                    .ok()
+            })
+            .collect();
+        Matcher {
+            patterns,
+            tags: self.tags.clone(),
+            explicit: self.explicit,
+        }
+    }
+
+    /// Whether `password` unlocks the filter, always if none is set
+    pub fn unlocks(&self, password: &str) -> bool {
+        match self.password.as_ref().and_then(|v| v.split_once(':')) {
+            Some((salt, hash)) => hash_password(salt, password) == hash,
+            None => self.password.is_none(),
+        }
+    }
+
+    /// Require `password` to turn the filter off, none if empty
+    pub fn set_password(&mut self, password: &str) {
+        self.password = match password.is_empty() {
+            true => None,
+            false => {
+                let salt: String = rand::thread_rng()
+                    .sample_iter(&Alphanumeric)
+                    .take(16)
+                    .map(char::from)
+                    .collect();
+                let hash = hash_password(&salt, password);
+                Some(format!("{}:{}", salt, hash))
+            }
+        };
+    }
+}
+
+fn hash_password(salt: &str, password: &str) -> String {
+    let mut hasher = sha1::Sha1::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(password.as_bytes());
+    hasher.digest().to_string()
+}
+
+/// Compiled [`ContentFilter`]
+#[derive(Debug, Clone)]
+pub struct Matcher {
+    patterns: Vec<Regex>,
+    tags: Vec<String>,
+    explicit: bool,
+}
+
+impl Matcher {
+    /// Whether `track` is filtered, `info` and `tags` are its metadata and user tags if known
+    pub fn blocks(
+        &self,
+        track: &str,
+        info: Option<&TrackInfo>,
+        tags: Option<&BTreeSet<String>>,
+    ) -> bool {
+        if self.explicit && info.is_some_and(|v| v.explicit) {
+            return true;
+        }
+        if tags.is_some_and(|tags| self.tags.iter().any(|t| tags.contains(t))) {
+            return true;
+        }
+        let mut texts = info
+            .into_iter()
+            .flat_map(|v| [&v.title, &v.artist, &v.album])
+            .flatten()
+            .map(String::as_str)
+            .chain(std::iter::once(track));
+        texts.any(|text| self.patterns.iter().any(|p| p.is_match(text)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_blocks() {
+        let filter = ContentFilter {
+            enabled: true,
+            patterns: vec![String::from(r"\bparty\b"), String::from("(invalid")],
+            tags: vec![String::from("kids-no")],
+            explicit: true,
+            password: None,
+        };
+        let matcher = filter.matcher();
+        let info = |title: &str, explicit| TrackInfo {
+            title: Some(title.to_string()),
+            explicit,
+            ..Default::default()
+        };
+        assert!(matcher.blocks("/a.mp3", Some(&info("PARTY time", false)), None));
+        assert!(matcher.blocks("/party/a.mp3", None, None));
+        assert!(matcher.blocks("/a.mp3", Some(&info("Song", true)), None));
+        let tags: BTreeSet<String> = std::iter::once(String::from("kids-no")).collect();
+        assert!(matcher.blocks("/a.mp3", None, Some(&tags)));
+        assert!(!matcher.blocks("/partying.mp3", Some(&info("Song", false)), None));
+    }
+
+    #[test]
+    fn test_password() {
+        let mut filter = ContentFilter::default();
+        assert!(filter.unlocks(""));
+        filter.set_password("secret");
+        assert!(filter.unlocks("secret"));
+        assert!(!filter.unlocks("guess"));
+        assert!(!filter.unlocks(""));
+        filter.set_password("");
+        assert!(filter.unlocks("anything"));
+    }
+}
@@ -0,0 +1,241 @@
+//! On-disk cache of cover art thumbnails, keyed by album.
+//! Covers are taken from the tags or found next to the track, downscaled once and stored.
+//! The least recently used thumbnails are removed when the cache grows past its limit.
+
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver, Sender, TryIter},
+    thread,
+    time::SystemTime,
+};
+
+use crate::prelude::*;
+
+/// Longest side of stored thumbnails in pixels
+pub const THUMBNAIL_SIZE: u32 = 256;
+/// Cover images looked for next to the track
+const FOLDER_NAMES: &[&str] = &[
+    "cover.jpg",
+    "cover.jpeg",
+    "cover.png",
+    "folder.jpg",
+    "folder.jpeg",
+    "folder.png",
+];
+
+struct Entry {
+    size: u64,
+    used: SystemTime,
+}
+
+pub struct CoverCache {
+    dir: PathBuf,
+    /// Maximum size of all thumbnails in bytes
+    limit: u64,
+    entries: HashMap<String, Entry>,
+    total: u64,
+}
+
+impl CoverCache {
+    /// Open the cache in `dir`, thumbnails of earlier runs are kept
+    pub fn open(dir: PathBuf, limit: u64) -> Result<Self> {
+        fs::create_dir_all(&dir).wrap_err_with(|| format!("Can't create cover cache {:?}", dir))?;
+        let mut entries = HashMap::new();
+        for entry in fs::read_dir(&dir)?.flatten() {
+            let path = entry.path();
+            let key = match path.file_stem().and_then(|v| v.to_str()) {
+                Some(v) if path.extension().is_some_and(|v| v == "png") => v.to_string(),
+                _ => continue,
+            };
+            if let Ok(meta) = entry.metadata() {
+                let used = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                entries.insert(
+                    key,
+                    Entry {
+                        size: meta.len(),
+                        used,
+                    },
+                );
+            }
+        }
+        let total = entries.values().map(|v| v.size).sum();
+        let mut cache = Self {
+            dir,
+            limit,
+            entries,
+            total,
+        };
+        // the limit may have been lowered
+        cache.evict();
+        Ok(cache)
+    }
+
+    /// PNG thumbnail of the cover of `track`, extracted on first use of its album
+    pub fn cover(&mut self, track: &str) -> Option<Vec<u8>> {
+        let key = album_key(track)?;
+        if let Some(v) = self.get(&key) {
+            return Some(v);
+        }
+        let thumbnail = match thumbnail(&extract(track)?) {
+            Ok(v) => v,
+            Err(e) => {
+                debug!("Can't decode cover of {}: {}", track, e);
+                return None;
+            }
+        };
+        if let Err(e) = self.insert(&key, &thumbnail) {
+            warn!("Can't cache cover: {}", e);
+        }
+        Some(thumbnail)
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.png", key))
+    }
+
+    fn get(&mut self, key: &str) -> Option<Vec<u8>> {
+        let path = self.path(key);
+        let entry = self.entries.get_mut(key)?;
+        let data = fs::read(&path).ok()?;
+        entry.used = SystemTime::now();
+        // the modification time keeps the order across restarts
+        if let Err(e) = File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|f| f.set_modified(entry.used))
+        {
+            trace!("Can't touch {:?}: {}", path, e);
+        }
+        Some(data)
+    }
+
+    fn insert(&mut self, key: &str, data: &[u8]) -> Result<()> {
+        fs::write(self.path(key), data)?;
+        let size = data.len() as u64;
+        let used = SystemTime::now();
+        if let Some(old) = self.entries.insert(key.to_string(), Entry { size, used }) {
+            self.total -= old.size;
+        }
+        self.total += size;
+        self.evict();
+        Ok(())
+    }
+
+    /// Remove least recently used thumbnails until the cache fits its limit
+    fn evict(&mut self) {
+        while self.total > self.limit {
+            let oldest = match self.entries.iter().min_by_key(|(_, v)| v.used) {
+                Some((key, _)) => key.clone(),
+                None => return,
+            };
+            if let Some(entry) = self.entries.remove(&oldest) {
+                self.total -= entry.size;
+                trace!("Evicting cover {}", oldest);
+                if let Err(e) = fs::remove_file(self.path(&oldest)) {
+                    warn!("Can't remove cached cover: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Cache key of the album of a local track, its folder
+pub fn album_key(track: &str) -> Option<String> {
+    let folder = Path::new(track).parent()?;
+    if !folder.is_dir() {
+        return None;
+    }
+    let mut hasher = sha1::Sha1::new();
+    hasher.update(folder.to_string_lossy().as_bytes());
+    Some(hasher.digest().to_string())
+}
+
+/// Embedded front cover or an image file next to `track`
+fn extract(track: &str) -> Option<Vec<u8>> {
+    let path = Path::new(track);
+    if let Ok(tag) = id3::Tag::read_from_path(path) {
+        let mut pictures: Vec<_> = tag.pictures().collect();
+        pictures.sort_by_key(|v| v.picture_type != id3::frame::PictureType::CoverFront);
+        if let Some(picture) = pictures.first() {
+            return Some(picture.data.clone());
+        }
+    }
+    fs::read_dir(path.parent()?)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.file_name()
+                .and_then(|v| v.to_str())
+                .is_some_and(|name| FOLDER_NAMES.iter().any(|v| v.eq_ignore_ascii_case(name)))
+        })
+        .and_then(|path| fs::read(path).ok())
+}
+
+fn thumbnail(data: &[u8]) -> Result<Vec<u8>> {
+    let image = image::load_from_memory(data)?.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+    let mut png = Vec::new();
+    image.write_to(&mut png, image::ImageOutputFormat::Png)?;
+    Ok(png)
+}
+
+/// Looks up covers on a background thread
+pub struct Loader {
+    tx: Sender<String>,
+    rx: Receiver<(String, Option<Vec<u8>>)>,
+}
+
+impl Loader {
+    pub fn spawn(mut cache: CoverCache) -> Result<Self> {
+        let (tx, jobs) = channel::<String>();
+        let (cover_tx, rx) = channel();
+        thread::Builder::new()
+            .name("cover loader".into())
+            .spawn(move || {
+                for track in jobs {
+                    let cover = cache.cover(&track);
+                    if cover_tx.send((track, cover)).is_err() {
+                        return;
+                    }
+                }
+            })
+            .wrap_err("Can't start cover loader")?;
+        Ok(Self { tx, rx })
+    }
+
+    pub fn request(&self, track: &str) {
+        let _ = self.tx.send(track.to_string());
+    }
+
+    /// Covers looked up since the last call, None if a track has none
+    pub fn loaded(&self) -> TryIter<'_, (String, Option<Vec<u8>>)> {
+        self.rx.try_iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_eviction() {
+        let dir = std::env::temp_dir().join("audio_wrench_test_covers");
+        let _ = fs::remove_dir_all(&dir);
+        let mut cache = CoverCache::open(dir.clone(), 25).unwrap();
+        cache.insert("a", &[0; 10]).unwrap();
+        cache.insert("b", &[1; 10]).unwrap();
+        assert_eq!(cache.get("a").unwrap(), vec![0; 10]);
+        // b is least recently used
+        cache.insert("c", &[2; 10]).unwrap();
+        assert!(cache.get("b").is_none());
+        assert!(!dir.join("b.png").exists());
+        assert_eq!(cache.total, 20);
+
+        let mut cache = CoverCache::open(dir.clone(), 15).unwrap();
+        assert_eq!(cache.entries.len(), 1);
+        assert!(cache.get("c").is_some());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,193 @@
+//! Cue points of tracks, exported as sidecar files for DJ software
+
+use std::{
+    fs::File,
+    io::{Cursor, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use quick_xml::{
+    events::{BytesDecl, BytesEnd, BytesStart, Event},
+    Writer,
+};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{notes::escape, prelude::*};
+
+/// Cues exported as hot cues, the remaining ones become memory cues
+pub const HOT_CUES: usize = 8;
+
+/// Marked position in a track
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cue {
+    #[serde(rename = "position_ms", with = "millis")]
+    pub position: Duration,
+    pub label: String,
+}
+
+/// Sidecar format written next to the track
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CueFormat {
+    #[default]
+    Csv,
+    Json,
+    /// Rekordbox collection XML with the cues as position marks
+    Rekordbox,
+}
+
+impl CueFormat {
+    pub const ALL: [CueFormat; 3] = [CueFormat::Csv, CueFormat::Json, CueFormat::Rekordbox];
+
+    fn extension(self) -> &'static str {
+        match self {
+            CueFormat::Csv => "cues.csv",
+            CueFormat::Json => "cues.json",
+            CueFormat::Rekordbox => "rekordbox.xml",
+        }
+    }
+}
+
+impl std::fmt::Display for CueFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CueFormat::Csv => "CSV",
+            CueFormat::Json => "JSON",
+            CueFormat::Rekordbox => "Rekordbox XML",
+        })
+    }
+}
+
+/// Sidecar path of `track` for `format`, e.g. `song.mp3.cues.csv`
+pub fn sidecar_path(track: &Path, format: CueFormat) -> PathBuf {
+    let mut name = track.as_os_str().to_owned();
+    name.push(".");
+    name.push(format.extension());
+    PathBuf::from(name)
+}
+
+/// Write `cues` of the local `track` next to it, returns the written file
+pub fn export(track: &Path, cues: &[Cue], format: CueFormat) -> Result<PathBuf> {
+    let data = match format {
+        CueFormat::Csv => to_csv(cues).into_bytes(),
+        CueFormat::Json => serde_json::to_vec_pretty(cues)?,
+        CueFormat::Rekordbox => to_rekordbox(track, cues)?,
+    };
+    let path = sidecar_path(track, format);
+    File::create(&path)
+        .and_then(|mut f| f.write_all(&data))
+        .wrap_err_with(|| format!("Can't write cues to {:?}", path))?;
+    Ok(path)
+}
+
+/// One line per cue with the position in seconds and the label
+fn to_csv(cues: &[Cue]) -> String {
+    let mut csv = String::from("position,label\n");
+    for cue in cues {
+        csv.push_str(&format!("{:.3},", cue.position.as_secs_f64()));
+        csv.push_str(&escape(&cue.label));
+        csv.push('\n');
+    }
+    csv
+}
+
+fn to_rekordbox(track: &Path, cues: &[Cue]) -> Result<Vec<u8>> {
+    let location = Url::from_file_path(track)
+        .map_err(|_| eyre!("Can't export cues of {:?}, not an absolute path", track))?;
+    // rekordbox expects file://localhost/ locations
+    let location = location.as_str().replacen("file://", "file://localhost", 1);
+    let name = track
+        .file_stem()
+        .map_or_else(String::new, |v| v.to_string_lossy().into_owned());
+
+    let mut buf = Vec::new();
+    let mut writer = Writer::new_with_indent(Cursor::new(&mut buf), b' ', 2);
+    writer.write_event(Event::Decl(BytesDecl::new(b"1.0", Some(b"UTF-8"), None)))?;
+    let mut root = BytesStart::borrowed_name(b"DJ_PLAYLISTS");
+    root.push_attribute(("Version", "1.0.0"));
+    writer.write_event(Event::Start(root))?;
+    let mut product = BytesStart::borrowed_name(b"PRODUCT");
+    product.push_attribute(("Name", "audio_wrench"));
+    writer.write_event(Event::Empty(product))?;
+    let mut collection = BytesStart::borrowed_name(b"COLLECTION");
+    collection.push_attribute(("Entries", "1"));
+    writer.write_event(Event::Start(collection))?;
+    let mut entry = BytesStart::borrowed_name(b"TRACK");
+    entry.push_attribute(("TrackID", "1"));
+    entry.push_attribute(("Name", name.as_str()));
+    entry.push_attribute(("Location", location.as_str()));
+    writer.write_event(Event::Start(entry))?;
+    for (i, cue) in cues.iter().enumerate() {
+        let start = format!("{:.3}", cue.position.as_secs_f64());
+        // -1 is a memory cue
+        let num = match i < HOT_CUES {
+            true => i.to_string(),
+            false => String::from("-1"),
+        };
+        let mut mark = BytesStart::borrowed_name(b"POSITION_MARK");
+        mark.push_attribute(("Name", cue.label.as_str()));
+        mark.push_attribute(("Type", "0"));
+        mark.push_attribute(("Start", start.as_str()));
+        mark.push_attribute(("Num", num.as_str()));
+        writer.write_event(Event::Empty(mark))?;
+    }
+    writer.write_event(Event::End(BytesEnd::borrowed(b"TRACK")))?;
+    writer.write_event(Event::End(BytesEnd::borrowed(b"COLLECTION")))?;
+    writer.write_event(Event::End(BytesEnd::borrowed(b"DJ_PLAYLISTS")))?;
+    writer.write_event(Event::Eof)?;
+    Ok(buf)
+}
+
+mod millis {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(value.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cues() -> Vec<Cue> {
+        vec![
+            Cue {
+                position: Duration::from_millis(1_500),
+                label: String::from("Intro"),
+            },
+            Cue {
+                position: Duration::from_millis(62_250),
+                label: String::from("Drop \"1\""),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_export_formats() {
+        assert_eq!(
+            to_csv(&cues()),
+            "position,label\n1.500,Intro\n62.250,\"Drop \"\"1\"\"\"\n"
+        );
+        assert_eq!(
+            serde_json::to_string(&cues()[0]).unwrap(),
+            r#"{"position_ms":1500,"label":"Intro"}"#
+        );
+        let xml =
+            String::from_utf8(to_rekordbox(Path::new("/m/a b.mp3"), &cues()).unwrap()).unwrap();
+        assert!(xml.contains(r#"Location="file://localhost/m/a%20b.mp3""#));
+        assert!(xml.contains(r#"<POSITION_MARK Name="Intro" Type="0" Start="1.500" Num="0"/>"#));
+        assert!(xml.contains(r#"Name="Drop &quot;1&quot;""#));
+        assert_eq!(
+            sidecar_path(Path::new("/m/a.mp3"), CueFormat::Rekordbox),
+            Path::new("/m/a.mp3.rekordbox.xml")
+        );
+    }
+}
@@ -0,0 +1,333 @@
+//! Decoders of the supported audio formats.
+//! rodio decodes the common formats itself, older lossless formats are streamed from an
+//! `ffmpeg` process while playing, their tags and lengths come from `ffprobe`.
+
+use std::{
+    io::{BufReader, Read},
+    path::Path,
+    process::{Child, ChildStdout, Command, Stdio},
+    sync::OnceLock,
+    time::Duration,
+};
+
+use rodio::Source;
+
+use crate::prelude::*;
+
+/// Bytes of decoded audio buffered from ffmpeg
+const PIPE_BUFFER: usize = 64 * 1024;
+
+/// Decoder backend of a format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decoder {
+    Rodio,
+    /// Needs `ffmpeg` and `ffprobe`
+    Ffmpeg,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Format {
+    pub name: &'static str,
+    pub extensions: &'static [&'static str],
+    pub decoder: Decoder,
+}
+
+impl Format {
+    /// Whether files of this format can be played here
+    pub fn available(&self) -> bool {
+        match self.decoder {
+            Decoder::Rodio => true,
+            Decoder::Ffmpeg => ffmpeg_available(),
+        }
+    }
+}
+
+pub const FORMATS: &[Format] = &[
+    Format {
+        name: "MP3",
+        extensions: &["mp3"],
+        decoder: Decoder::Rodio,
+    },
+    Format {
+        name: "WAV",
+        extensions: &["wav"],
+        decoder: Decoder::Rodio,
+    },
+    Format {
+        name: "Ogg Vorbis",
+        extensions: &["ogg"],
+        decoder: Decoder::Rodio,
+    },
+    Format {
+        name: "FLAC",
+        extensions: &["flac"],
+        decoder: Decoder::Rodio,
+    },
+    Format {
+        name: "WavPack",
+        extensions: &["wv"],
+        decoder: Decoder::Ffmpeg,
+    },
+    Format {
+        name: "Monkey's Audio",
+        extensions: &["ape"],
+        decoder: Decoder::Ffmpeg,
+    },
+    Format {
+        name: "TTA",
+        extensions: &["tta"],
+        decoder: Decoder::Ffmpeg,
+    },
+    // rodio has no Opus decoder, tags and lengths are read from the Ogg headers
+    Format {
+        name: "Opus",
+        extensions: &["opus"],
+        decoder: Decoder::Ffmpeg,
+    },
+];
+
+/// Format of `path` by its extension
+pub fn format(path: &Path) -> Option<&'static Format> {
+    let ext = path.extension()?.to_str()?;
+    FORMATS
+        .iter()
+        .find(|f| f.extensions.iter().any(|v| v.eq_ignore_ascii_case(ext)))
+}
+
+/// Whether `path` is decoded with ffmpeg
+pub fn needs_ffmpeg(path: &Path) -> bool {
+    format(path).is_some_and(|f| f.decoder == Decoder::Ffmpeg)
+}
+
+/// Whether `ffmpeg` can be run, checked once
+pub fn ffmpeg_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        let available = Command::new("ffmpeg")
+            .arg("-version")
+            .output()
+            .is_ok_and(|v| v.status.success());
+        if !available {
+            info!("ffmpeg not found, WavPack, Monkey's Audio, TTA and Opus can't be played");
+        }
+        available
+    })
+}
+
+/// Decode `path` with ffmpeg from `start` on, ffmpeg seeks itself so seeking needs no
+/// decoding from the start. The audio is read while ffmpeg decodes.
+pub fn decode(path: &Path, start: Duration) -> Result<FfmpegSource> {
+    let mut child = Command::new("ffmpeg")
+        .args(["-nostdin", "-loglevel", "error", "-ss"])
+        .arg(format!("{:.3}", start.as_secs_f64()))
+        .arg("-i")
+        .arg(path)
+        // 16 bit WAV on stdout, cover art streams left out
+        .args([
+            "-vn",
+            "-map_metadata",
+            "-1",
+            "-c:a",
+            "pcm_s16le",
+            "-f",
+            "wav",
+            "pipe:1",
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .wrap_err("Can't run ffmpeg")?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let mut source = FfmpegSource {
+        child,
+        output: BufReader::with_capacity(PIPE_BUFFER, stdout),
+        channels: 0,
+        sample_rate: 0,
+    };
+    let (channels, sample_rate) = read_wav_header(&mut source.output)
+        .wrap_err_with(|| format!("ffmpeg can't decode {:?}", path))?;
+    source.channels = channels;
+    source.sample_rate = sample_rate;
+    Ok(source)
+}
+
+/// Read the header of 16 bit WAV data up to the samples, returns channels and sample rate.
+/// The chunk sizes are ignored, they aren't known when writing to a pipe.
+fn read_wav_header(input: &mut impl Read) -> Result<(u16, u32)> {
+    let mut riff = [0; 12];
+    input.read_exact(&mut riff).wrap_err("No output")?;
+    if &riff[0..4] != b"RIFF" || &riff[8..12] != b"WAVE" {
+        return Err(eyre!("Output isn't WAV"));
+    }
+    let mut format = None;
+    loop {
+        let mut header = [0; 8];
+        input.read_exact(&mut header)?;
+        let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as u64;
+        match &header[0..4] {
+            b"data" => break,
+            b"fmt " => {
+                let mut fmt = vec![0; len as usize];
+                input.read_exact(&mut fmt)?;
+                if fmt.len() < 16 {
+                    return Err(eyre!("Invalid WAV format"));
+                }
+                let channels = u16::from_le_bytes([fmt[2], fmt[3]]);
+                let rate = u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]);
+                let bits = u16::from_le_bytes([fmt[14], fmt[15]]);
+                if bits != 16 || channels == 0 || rate == 0 {
+                    return Err(eyre!("Unexpected WAV format"));
+                }
+                format = Some((channels, rate));
+            }
+            // chunks are padded to an even length
+            _ => {
+                std::io::copy(&mut input.take(len + len % 2), &mut std::io::sink())?;
+            }
+        }
+    }
+    format.ok_or_else(|| eyre!("No WAV format"))
+}
+
+/// Samples decoded by a running ffmpeg process, which is stopped when dropped
+pub struct FfmpegSource {
+    child: Child,
+    output: BufReader<ChildStdout>,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl Iterator for FfmpegSource {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        let mut sample = [0; 2];
+        // ends with the output of ffmpeg, errors included
+        self.output.read_exact(&mut sample).ok()?;
+        Some(i16::from_le_bytes(sample))
+    }
+}
+
+impl Source for FfmpegSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl Drop for FfmpegSource {
+    fn drop(&mut self) {
+        // stopped early on skip or seek, reap it either way
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Tags and length read by ffprobe
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Probe {
+    pub length: Option<Duration>,
+    /// Tag names in lowercase and values
+    pub tags: Vec<(String, String)>,
+}
+
+impl Probe {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Read tags and length of `path` with ffprobe
+pub fn probe(path: &Path) -> Result<Probe> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-show_entries",
+            "format=duration:format_tags",
+        ])
+        .args(["-of", "default=noprint_wrappers=1"])
+        .arg(path)
+        .output()
+        .wrap_err("Can't run ffprobe")?;
+    if !output.status.success() {
+        return Err(eyre!(
+            "ffprobe failed on {:?}: {}",
+            path,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(parse_probe(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// `key=value` lines of ffprobe, tags are prefixed with `TAG:`
+fn parse_probe(output: &str) -> Probe {
+    let mut probe = Probe::default();
+    for (key, value) in output.lines().filter_map(|l| l.split_once('=')) {
+        match key.strip_prefix("TAG:") {
+            Some(tag) => probe
+                .tags
+                .push((tag.to_ascii_lowercase(), value.trim().to_string())),
+            None if key == "duration" => {
+                probe.length = value
+                    .trim()
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|v| v.is_finite() && *v > 0.0)
+                    .map(Duration::from_secs_f64)
+            }
+            None => (),
+        }
+    }
+    probe
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        assert_eq!(
+            format(Path::new("/a/b.WV")).map(|f| f.name),
+            Some("WavPack")
+        );
+        assert!(needs_ffmpeg(Path::new("album.ape")));
+        assert!(!needs_ffmpeg(Path::new("song.flac")));
+        assert!(needs_ffmpeg(Path::new("voice.opus")));
+        assert_eq!(format(Path::new("cover.jpg")), None);
+
+        let probe =
+            parse_probe("duration=245.500000\nTAG:TITLE=Song\nTAG:Artist=A = B\nTAG:album=Album\n");
+        assert_eq!(probe.length, Some(Duration::from_millis(245_500)));
+        assert_eq!(probe.tag("artist"), Some("A = B"));
+        assert_eq!(probe.tag("Title"), Some("Song"));
+        assert_eq!(parse_probe("duration=N/A\n"), Probe::default());
+
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF\xff\xff\xff\xffWAVE");
+        wav.extend_from_slice(b"LIST\x03\0\0\0abc\0");
+        wav.extend_from_slice(b"fmt \x10\0\0\0\x01\0\x02\0\x44\xac\0\0");
+        wav.extend_from_slice(&[0x10, 0xb1, 0x02, 0, 4, 0, 16, 0]);
+        wav.extend_from_slice(b"data\xff\xff\xff\xff\x01\0");
+        let mut input = &wav[..];
+        assert_eq!(read_wav_header(&mut input).unwrap(), (2, 44100));
+        assert_eq!(input, &[1, 0]);
+        assert!(read_wav_header(&mut &b"garbage"[..]).is_err());
+    }
+}
@@ -0,0 +1,1013 @@
+//! Source adapters forming the playback pipeline between decoder and sink
+
+use std::{
+    io::{BufReader, BufWriter, Read, Write},
+    process::{Child, ChildStdout, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+
+use crate::player::PlayerStatus;
+use crate::prelude::*;
+
+/// Decoded audio as passed through the pipeline
+pub type BoxSource = Box<dyn Source<Item = i16> + Send>;
+
+/// Output sample rate handling
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Resampling {
+    /// Pass the source rate through, the mixer converts to the device rate if required
+    #[default]
+    FollowSource,
+    /// Convert to a fixed rate using linear interpolation
+    Fixed(u32),
+    /// Convert to a fixed rate using cubic interpolation
+    HighQuality(u32),
+}
+
+/// Add resampling stage according to `mode`
+pub fn resample(input: BoxSource, mode: Resampling) -> BoxSource {
+    match mode {
+        Resampling::FollowSource => input,
+        Resampling::Fixed(rate) => {
+            let channels = input.channels();
+            Box::new(rodio::source::UniformSourceIterator::new(
+                input, channels, rate,
+            ))
+        }
+        Resampling::HighQuality(rate) => {
+            if input.sample_rate() == rate {
+                input
+            } else {
+                Box::new(CubicResampler::new(input, rate))
+            }
+        }
+    }
+}
+
+/// Resampler using catmull-rom interpolation between frames
+pub struct CubicResampler<I> {
+    input: I,
+    channels: u16,
+    rate: u32,
+    /// Input frames advanced per output frame
+    step: f64,
+    /// Position between window[1] and window[2]
+    frac: f64,
+    window: [Vec<f32>; 4],
+    /// Real (not padded) frames in window[1..]
+    valid: usize,
+    out: Vec<i16>,
+    out_pos: usize,
+}
+
+impl<I> CubicResampler<I>
+where
+    I: Source<Item = i16>,
+{
+    pub fn new(mut input: I, rate: u32) -> Self {
+        let channels = input.channels();
+        let step = input.sample_rate() as f64 / rate as f64;
+        let mut valid = 0;
+        let first = read_frame(&mut input, channels);
+        if first.is_some() {
+            valid += 1;
+        }
+        let first = first.unwrap_or_else(|| vec![0.0; channels as usize]);
+        let mut next = || match read_frame(&mut input, channels) {
+            Some(v) => {
+                valid += 1;
+                v
+            }
+            None => first.clone(),
+        };
+        let second = next();
+        let third = next();
+        Self {
+            window: [first.clone(), first, second, third],
+            input,
+            channels,
+            rate,
+            step,
+            frac: 0.0,
+            valid,
+            out: Vec::with_capacity(channels as usize),
+            out_pos: 0,
+        }
+    }
+
+    fn advance(&mut self) {
+        self.window.rotate_left(1);
+        self.valid = self.valid.saturating_sub(1);
+        match read_frame(&mut self.input, self.channels) {
+            Some(v) => {
+                self.valid += 1;
+                self.window[3] = v;
+            }
+            None => self.window[3] = self.window[2].clone(),
+        }
+    }
+
+    /// Compute next output frame, returns false at the end of input
+    fn fill(&mut self) -> bool {
+        while self.frac >= 1.0 {
+            self.advance();
+            self.frac -= 1.0;
+        }
+        if self.valid == 0 {
+            return false;
+        }
+        let t = self.frac as f32;
+        let [p0, p1, p2, p3] = &self.window;
+        self.out.clear();
+        for c in 0..self.channels as usize {
+            let v = catmull_rom(p0[c], p1[c], p2[c], p3[c], t);
+            self.out
+                .push(v.clamp(i16::MIN as f32, i16::MAX as f32) as i16);
+        }
+        self.out_pos = 0;
+        self.frac += self.step;
+        true
+    }
+}
+
+fn read_frame<I: Iterator<Item = i16>>(input: &mut I, channels: u16) -> Option<Vec<f32>> {
+    let first = input.next()?;
+    let mut frame = Vec::with_capacity(channels as usize);
+    frame.push(first as f32);
+    for _ in 1..channels {
+        frame.push(input.next().unwrap_or(0) as f32);
+    }
+    Some(frame)
+}
+
+fn catmull_rom(p0: f32, p1: f32, p2: f32, p3: f32, t: f32) -> f32 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * ((2.0 * p1)
+        + (-p0 + p2) * t
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+        + (-p0 + 3.0 * p1 - 3.0 * p2 + p3) * t3)
+}
+
+impl<I> Iterator for CubicResampler<I>
+where
+    I: Source<Item = i16>,
+{
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if self.out_pos >= self.out.len() && !self.fill() {
+            return None;
+        }
+        let v = self.out[self.out_pos];
+        self.out_pos += 1;
+        Some(v)
+    }
+}
+
+impl<I> Source for CubicResampler<I>
+where
+    I: Source<Item = i16>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
+
+/// Frames per level measurement
+const METER_BLOCK: usize = 2048;
+
+/// Peak and RMS level of left and right channel, linear 0..=1
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Levels {
+    pub peak: [f32; 2],
+    pub rms: [f32; 2],
+}
+
+/// Convert linear level to dBFS
+pub fn to_db(v: f32) -> f32 {
+    20.0 * v.max(1e-6).log10()
+}
+
+/// Metering tap, publishes levels of the passed through audio every [METER_BLOCK] frames.
+/// Mono is reported on both channels, channels beyond stereo alternate between left and right.
+pub struct Meter<I> {
+    input: I,
+    levels: Arc<Mutex<Levels>>,
+    channel: u16,
+    frames: usize,
+    peak: [f32; 2],
+    sum: [f32; 2],
+    count: [usize; 2],
+}
+
+impl<I> Meter<I>
+where
+    I: Source<Item = i16>,
+{
+    pub fn new(input: I, levels: Arc<Mutex<Levels>>) -> Self {
+        Self {
+            input,
+            levels,
+            channel: 0,
+            frames: 0,
+            peak: [0.0; 2],
+            sum: [0.0; 2],
+            count: [0; 2],
+        }
+    }
+
+    fn measure(&mut self, side: usize, v: f32) {
+        self.peak[side] = self.peak[side].max(v);
+        self.sum[side] += v * v;
+        self.count[side] += 1;
+    }
+
+    fn publish(&mut self) {
+        let mut levels = Levels::default();
+        for side in 0..2 {
+            levels.peak[side] = self.peak[side];
+            if self.count[side] > 0 {
+                levels.rms[side] = (self.sum[side] / self.count[side] as f32).sqrt();
+            }
+        }
+        *self.levels.lock().expect("Can't lock levels") = levels;
+        self.peak = [0.0; 2];
+        self.sum = [0.0; 2];
+        self.count = [0; 2];
+        self.frames = 0;
+    }
+}
+
+impl<I> Iterator for Meter<I>
+where
+    I: Source<Item = i16>,
+{
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        let sample = match self.input.next() {
+            Some(v) => v,
+            None => {
+                if self.frames > 0 {
+                    self.publish();
+                }
+                return None;
+            }
+        };
+        let v = (sample as f32 / i16::MAX as f32).abs();
+        let channels = self.input.channels().max(1);
+        if channels == 1 {
+            self.measure(0, v);
+            self.measure(1, v);
+        } else {
+            self.measure((self.channel % 2) as usize, v);
+        }
+        self.channel += 1;
+        if self.channel >= channels {
+            self.channel = 0;
+            self.frames += 1;
+            if self.frames >= METER_BLOCK {
+                self.publish();
+            }
+        }
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> Source for Meter<I>
+where
+    I: Source<Item = i16>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
+
+/// Preamp gain range in dB
+pub const PREAMP_RANGE: std::ops::RangeInclusive<i16> = -12..=12;
+
+/// Preamp settings shared between player and pipeline
+#[derive(Debug)]
+pub struct PreampControl {
+    /// Linear gain as f32 bits
+    gain: AtomicU32,
+    /// Set when a sample had to be clipped
+    clipped: AtomicBool,
+}
+
+impl PreampControl {
+    pub fn new(db: i16) -> Self {
+        let control = Self {
+            gain: AtomicU32::new(1f32.to_bits()),
+            clipped: AtomicBool::new(false),
+        };
+        control.set_gain(db);
+        control
+    }
+
+    /// Set gain in dB, clamped to [PREAMP_RANGE]
+    pub fn set_gain(&self, db: i16) {
+        let db = db.clamp(*PREAMP_RANGE.start(), *PREAMP_RANGE.end());
+        let gain = 10f32.powf(db as f32 / 20.0);
+        self.gain.store(gain.to_bits(), Ordering::Relaxed);
+    }
+
+    fn gain(&self) -> f32 {
+        f32::from_bits(self.gain.load(Ordering::Relaxed))
+    }
+
+    /// Returns whether clipping occurred since the last call
+    pub fn take_clipped(&self) -> bool {
+        self.clipped.swap(false, Ordering::Relaxed)
+    }
+}
+
+impl Default for PreampControl {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+/// Gain stage before the volume, clips at full scale
+pub struct Preamp<I> {
+    input: I,
+    control: Arc<PreampControl>,
+}
+
+impl<I> Preamp<I>
+where
+    I: Source<Item = i16>,
+{
+    pub fn new(input: I, control: Arc<PreampControl>) -> Self {
+        Self { input, control }
+    }
+}
+
+impl<I> Iterator for Preamp<I>
+where
+    I: Source<Item = i16>,
+{
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        let v = self.input.next()? as f32 * self.control.gain();
+        if v > i16::MAX as f32 || v < i16::MIN as f32 {
+            self.control.clipped.store(true, Ordering::Relaxed);
+        }
+        Some(v.clamp(i16::MIN as f32, i16::MAX as f32) as i16)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> Source for Preamp<I>
+where
+    I: Source<Item = i16>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
+
+/// Channel mapping options, applied per track
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ChannelMapping {
+    /// Play mono sources on both channels
+    #[serde(default)]
+    pub mono_to_stereo: bool,
+    /// Swap left and right channel of stereo output
+    #[serde(default)]
+    pub swap: bool,
+    /// Downmix 5.1 sources to stereo
+    #[serde(default)]
+    pub downmix: bool,
+}
+
+impl ChannelMapping {
+    fn is_passthrough(&self) -> bool {
+        !(self.mono_to_stereo || self.swap || self.downmix)
+    }
+}
+
+/// Add channel mapping stage if required
+pub fn map_channels(input: BoxSource, mapping: ChannelMapping) -> BoxSource {
+    if mapping.is_passthrough() {
+        input
+    } else {
+        Box::new(ChannelMapper::new(input, mapping))
+    }
+}
+
+/// Attenuation of center and surround channels on downmix
+const DOWNMIX_SIDE: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// Remaps channels frame by frame.
+/// 5.1 input is expected in WAV/FLAC order: L, R, C, LFE, Ls, Rs. LFE is dropped on downmix.
+pub struct ChannelMapper<I> {
+    input: I,
+    mapping: ChannelMapping,
+    in_channels: u16,
+    out_channels: u16,
+    frame: Vec<i16>,
+    out: Vec<i16>,
+    out_pos: usize,
+}
+
+impl<I> ChannelMapper<I>
+where
+    I: Source<Item = i16>,
+{
+    pub fn new(input: I, mapping: ChannelMapping) -> Self {
+        let in_channels = input.channels();
+        let out_channels = match in_channels {
+            1 if mapping.mono_to_stereo => 2,
+            6 if mapping.downmix => 2,
+            v => v,
+        };
+        Self {
+            input,
+            mapping,
+            in_channels,
+            out_channels,
+            frame: Vec::with_capacity(in_channels as usize),
+            out: Vec::with_capacity(out_channels as usize),
+            out_pos: 0,
+        }
+    }
+
+    /// Map next input frame, returns false at the end of input
+    fn fill(&mut self) -> bool {
+        self.frame.clear();
+        for i in 0..self.in_channels {
+            match self.input.next() {
+                Some(v) => self.frame.push(v),
+                None if i == 0 => return false,
+                None => self.frame.push(0),
+            }
+        }
+        self.out.clear();
+        match self.in_channels {
+            1 if self.out_channels == 2 => {
+                self.out.push(self.frame[0]);
+                self.out.push(self.frame[0]);
+            }
+            6 if self.out_channels == 2 => {
+                let f: Vec<f32> = self.frame.iter().map(|v| *v as f32).collect();
+                let norm = 1.0 / (1.0 + 2.0 * DOWNMIX_SIDE);
+                let center = f[2] * DOWNMIX_SIDE;
+                let left = (f[0] + center + f[4] * DOWNMIX_SIDE) * norm;
+                let right = (f[1] + center + f[5] * DOWNMIX_SIDE) * norm;
+                self.out.push(left as i16);
+                self.out.push(right as i16);
+            }
+            _ => self.out.extend_from_slice(&self.frame),
+        }
+        if self.mapping.swap && self.out_channels == 2 {
+            self.out.swap(0, 1);
+        }
+        self.out_pos = 0;
+        true
+    }
+}
+
+impl<I> Iterator for ChannelMapper<I>
+where
+    I: Source<Item = i16>,
+{
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if self.out_pos >= self.out.len() && !self.fill() {
+            return None;
+        }
+        let v = self.out[self.out_pos];
+        self.out_pos += 1;
+        Some(v)
+    }
+}
+
+impl<I> Source for ChannelMapper<I>
+where
+    I: Source<Item = i16>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.out_channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
+
+/// Center frequencies of the equalizer bands
+pub const EQ_BANDS: [f32; 5] = [60.0, 250.0, 1000.0, 4000.0, 12000.0];
+/// Quality factor of all equalizer bands
+const EQ_Q: f32 = 1.0;
+
+/// Equalizer gains in dB per band of [EQ_BANDS]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct EqPreset {
+    pub gains: [i8; 5],
+}
+
+impl EqPreset {
+    fn is_flat(&self) -> bool {
+        self.gains.iter().all(|v| *v == 0)
+    }
+}
+
+/// Add equalizer stage if the preset isn't flat
+pub fn equalize(input: BoxSource, preset: Option<EqPreset>) -> BoxSource {
+    match preset {
+        Some(preset) if !preset.is_flat() => Box::new(Equalizer::new(input, preset)),
+        _ => input,
+    }
+}
+
+/// Peaking biquad filter, RBJ audio EQ cookbook
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+}
+
+impl Biquad {
+    fn peaking(rate: u32, freq: f32, gain_db: f32) -> Self {
+        let a = 10f32.powf(gain_db / 40.0);
+        let w0 = 2.0 * std::f32::consts::PI * freq.min(rate as f32 * 0.45) / rate as f32;
+        let alpha = w0.sin() / (2.0 * EQ_Q);
+        let cos = w0.cos();
+        let a0 = 1.0 + alpha / a;
+        Self {
+            b0: (1.0 + alpha * a) / a0,
+            b1: (-2.0 * cos) / a0,
+            b2: (1.0 - alpha * a) / a0,
+            a1: (-2.0 * cos) / a0,
+            a2: (1.0 - alpha / a) / a0,
+        }
+    }
+}
+
+/// Filter state of one band on one channel
+#[derive(Debug, Clone, Copy, Default)]
+struct BiquadState {
+    x1: f32,
+    x2: f32,
+    y1: f32,
+    y2: f32,
+}
+
+impl BiquadState {
+    fn process(&mut self, f: &Biquad, x: f32) -> f32 {
+        let y = f.b0 * x + f.b1 * self.x1 + f.b2 * self.x2 - f.a1 * self.y1 - f.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+        y
+    }
+}
+
+/// Equalizer with one peaking filter per band
+pub struct Equalizer<I> {
+    input: I,
+    filters: Vec<Biquad>,
+    /// Filter states, per channel per band
+    states: Vec<Vec<BiquadState>>,
+    channel: usize,
+}
+
+impl<I> Equalizer<I>
+where
+    I: Source<Item = i16>,
+{
+    pub fn new(input: I, preset: EqPreset) -> Self {
+        let rate = input.sample_rate();
+        let filters: Vec<Biquad> = EQ_BANDS
+            .iter()
+            .zip(preset.gains.iter())
+            .filter(|(_, gain)| **gain != 0)
+            .map(|(freq, gain)| Biquad::peaking(rate, *freq, *gain as f32))
+            .collect();
+        let states = vec![vec![BiquadState::default(); filters.len()]; input.channels() as usize];
+        Self {
+            input,
+            filters,
+            states,
+            channel: 0,
+        }
+    }
+}
+
+impl<I> Iterator for Equalizer<I>
+where
+    I: Source<Item = i16>,
+{
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        let mut v = self.input.next()? as f32;
+        if let Some(states) = self.states.get_mut(self.channel) {
+            for (state, filter) in states.iter_mut().zip(self.filters.iter()) {
+                v = state.process(filter, v);
+            }
+        }
+        self.channel = (self.channel + 1) % self.states.len().max(1);
+        Some(v.clamp(i16::MIN as f32, i16::MAX as f32) as i16)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> Source for Equalizer<I>
+where
+    I: Source<Item = i16>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
+
+/// User configured DSP stage
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DspStage {
+    /// Pipe interleaved s16le PCM through an external command via stdin/stdout.
+    /// The command has to output the same channel count and sample rate.
+    /// `{rate}` and `{channels}` in args are replaced with the stream format.
+    External { command: String, args: Vec<String> },
+}
+
+/// Add all stages of the DSP chain, failing stages are reported on `status` and left out
+// LV2/LADSPA plugins aren't hosted, they need C bindings; run them through an external host
+pub fn apply_chain(
+    mut input: BoxSource,
+    chain: &[DspStage],
+    status: &Sender<PlayerStatus>,
+) -> BoxSource {
+    for stage in chain {
+        input = match stage {
+            DspStage::External { command, args } => {
+                let rate = input.sample_rate().to_string();
+                let channels = input.channels().to_string();
+                let args: Vec<String> = args
+                    .iter()
+                    .map(|v| v.replace("{rate}", &rate).replace("{channels}", &channels))
+                    .collect();
+                match ExternalProcess::new(input, command, &args, status.clone()) {
+                    Ok(v) => Box::new(v),
+                    Err((input, e)) => {
+                        let msg = format!("Can't start DSP command {}: {}", command, e);
+                        error!("{}", msg);
+                        let _ = status.send(PlayerStatus::OutputError(msg));
+                        input
+                    }
+                }
+            }
+        };
+    }
+    input
+}
+
+/// Audio processed by an external command.
+/// Plays the rest of the input without it if the command stops early.
+pub struct ExternalProcess {
+    child: Child,
+    command: String,
+    output: BufReader<ChildStdout>,
+    /// Input not processed when the command stopped, with the count of samples taken from it
+    returned: Receiver<(BoxSource, usize)>,
+    /// Samples read from the command
+    read: usize,
+    /// Rest of the input after the command stopped
+    bypass: Option<BoxSource>,
+    status: Sender<PlayerStatus>,
+    channels: u16,
+    sample_rate: u32,
+    total_duration: Option<Duration>,
+}
+
+impl ExternalProcess {
+    /// Spawn command and start feeding `input`. Returns input back on failure.
+    pub fn new(
+        input: BoxSource,
+        command: &str,
+        args: &[String],
+        status: Sender<PlayerStatus>,
+    ) -> std::result::Result<Self, (BoxSource, std::io::Error)> {
+        let mut child = match Command::new(command)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+        {
+            Ok(v) => v,
+            Err(e) => return Err((input, e)),
+        };
+        let channels = input.channels();
+        let sample_rate = input.sample_rate();
+        let total_duration = input.total_duration();
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let output = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        let (tx, returned) = channel();
+        std::thread::Builder::new()
+            .name("dsp feeder".to_string())
+            .spawn(move || {
+                let mut input = input;
+                let mut writer = BufWriter::new(stdin);
+                let mut taken = 0;
+                while let Some(sample) = input.next() {
+                    taken += 1;
+                    if let Err(e) = writer.write_all(&sample.to_le_bytes()) {
+                        debug!("DSP command closed input: {}", e);
+                        let _ = tx.send((input, taken));
+                        return;
+                    }
+                }
+            })
+            .expect("Can't spawn DSP feeder thread");
+        Ok(Self {
+            child,
+            command: command.to_string(),
+            output,
+            returned,
+            read: 0,
+            bypass: None,
+            status,
+            channels,
+            sample_rate,
+            total_duration,
+        })
+    }
+
+    /// Continue with the input after the command ended its output, None if all of it was processed
+    fn start_bypass(&mut self) -> Option<BoxSource> {
+        let exit = self.child.try_wait();
+        // a command still running without output would block the feeder forever
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let (mut input, mut taken) = self.returned.recv().ok()?;
+        let msg = match exit {
+            Ok(Some(exit)) => format!(
+                "DSP command {} stopped ({}), playing without it",
+                self.command, exit
+            ),
+            _ => format!(
+                "DSP command {} closed its output, playing without it",
+                self.command
+            ),
+        };
+        error!("{}", msg);
+        let _ = self.status.send(PlayerStatus::OutputError(msg));
+        // samples in the pipes are lost, continue with the channel the output expects next
+        let channels = self.channels.max(1) as usize;
+        while taken % channels != self.read % channels {
+            input.next()?;
+            taken += 1;
+        }
+        Some(input)
+    }
+}
+
+impl Drop for ExternalProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Iterator for ExternalProcess {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if let Some(input) = self.bypass.as_mut() {
+            return input.next();
+        }
+        let mut buf = [0u8; 2];
+        match self.output.read_exact(&mut buf) {
+            Ok(_) => {
+                self.read += 1;
+                return Some(i16::from_le_bytes(buf));
+            }
+            Err(e) => {
+                if e.kind() != std::io::ErrorKind::UnexpectedEof {
+                    warn!("Can't read from DSP command: {}", e);
+                }
+            }
+        }
+        self.bypass = self.start_bypass();
+        self.bypass.as_mut()?.next()
+    }
+}
+
+impl Source for ExternalProcess {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.total_duration
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    #[test]
+    fn test_resample_constant() {
+        let input = SamplesBuffer::new(2, 22050, vec![1000i16; 2 * 100]);
+        let out: Vec<i16> = CubicResampler::new(input, 44100).collect();
+        assert_eq!(out.len(), 2 * 200);
+        assert!(out.iter().all(|v| *v == 1000));
+    }
+
+    #[test]
+    fn test_resample_down() {
+        let input = SamplesBuffer::new(1, 48000, (0..480).map(|v| v as i16).collect::<Vec<_>>());
+        let out: Vec<i16> = CubicResampler::new(input, 24000).collect();
+        assert_eq!(out.len(), 240);
+        assert_eq!(out[10], 20);
+    }
+
+    #[test]
+    fn test_meter() {
+        let samples: Vec<i16> = (0..METER_BLOCK)
+            .flat_map(|_| vec![i16::MAX / 2, 0])
+            .collect();
+        let levels = Arc::new(Mutex::new(Levels::default()));
+        let input = SamplesBuffer::new(2, 44100, samples);
+        assert_eq!(Meter::new(input, levels.clone()).count(), METER_BLOCK * 2);
+        let levels = *levels.lock().unwrap();
+        assert!((levels.peak[0] - 0.5).abs() < 0.01);
+        assert!((levels.rms[0] - 0.5).abs() < 0.01);
+        assert_eq!(levels.peak[1], 0.0);
+    }
+
+    #[test]
+    fn test_preamp_clipping() {
+        let control = Arc::new(PreampControl::new(6));
+        let input = SamplesBuffer::new(1, 44100, vec![1000i16, 30000]);
+        let out: Vec<i16> = Preamp::new(input, control.clone()).collect();
+        assert_eq!(out, vec![1995, i16::MAX]);
+        assert!(control.take_clipped());
+        assert!(!control.take_clipped());
+    }
+
+    #[test]
+    fn test_channel_mapping() {
+        let mapping = ChannelMapping {
+            mono_to_stereo: true,
+            swap: false,
+            downmix: true,
+        };
+        let input = SamplesBuffer::new(1, 44100, vec![1i16, 2]);
+        let mapped = ChannelMapper::new(input, mapping);
+        assert_eq!(mapped.channels(), 2);
+        assert_eq!(mapped.collect::<Vec<_>>(), vec![1, 1, 2, 2]);
+
+        let input = SamplesBuffer::new(6, 44100, vec![1000i16, 0, 0, 500, 0, 0]);
+        let out: Vec<i16> = ChannelMapper::new(input, mapping).collect();
+        assert_eq!(out, vec![414, 0]);
+
+        let swap = ChannelMapping {
+            swap: true,
+            ..Default::default()
+        };
+        let input = SamplesBuffer::new(2, 44100, vec![1i16, 2]);
+        let out: Vec<i16> = ChannelMapper::new(input, swap).collect();
+        assert_eq!(out, vec![2, 1]);
+    }
+
+    #[test]
+    fn test_equalizer_outside_band() {
+        // a bass boost shouldn't change a signal far above its band
+        let preset = EqPreset {
+            gains: [6, 0, 0, 0, 0],
+        };
+        let samples: Vec<i16> = (0..4410)
+            .map(|i| if i % 2 == 0 { 10000 } else { -10000 })
+            .collect();
+        let input = SamplesBuffer::new(1, 44100, samples);
+        let out: Vec<i16> = Equalizer::new(input, preset).collect();
+        assert_eq!(out.len(), 4410);
+        assert!(out[4000..].iter().all(|v| (v.abs() - 10000).abs() < 100));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_external_process() {
+        let samples = vec![1i16, -2, 300, i16::MIN];
+        let input: BoxSource = Box::new(SamplesBuffer::new(2, 44100, samples.clone()));
+        let stage = DspStage::External {
+            command: String::from("cat"),
+            args: Vec::new(),
+        };
+        let (tx, status) = channel();
+        let out = apply_chain(input, &[stage], &tx);
+        assert_eq!(out.channels(), 2);
+        assert_eq!(out.collect::<Vec<_>>(), samples);
+        assert!(status.try_recv().is_err());
+
+        // stopping after the first frame leaves the rest to the bypass
+        let samples: Vec<i16> = (0..1_000_000).map(|v| (v % 2) as i16).collect();
+        let input: BoxSource = Box::new(SamplesBuffer::new(2, 44100, samples.clone()));
+        let stage = DspStage::External {
+            command: String::from("head"),
+            args: vec![String::from("-c"), String::from("4")],
+        };
+        let out: Vec<i16> = apply_chain(input, &[stage], &tx).collect();
+        assert_eq!(out[..2], samples[..2]);
+        assert!(out.len() > 2 && out.len() < samples.len());
+        // frames stay intact
+        assert!(out.chunks(2).all(|v| v == [0, 1]));
+        assert!(matches!(
+            status.try_recv(),
+            Ok(PlayerStatus::OutputError(_))
+        ));
+    }
+}
@@ -0,0 +1,111 @@
+//! Background lookup of track lengths, for the total queue time
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::BufReader,
+    path::Path,
+    sync::mpsc::{channel, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+
+use rodio::Source;
+
+use crate::prelude::*;
+
+pub struct Durations {
+    tx: Sender<String>,
+    rx: Receiver<(String, Option<Duration>)>,
+    /// None for requested or unknown lengths
+    known: HashMap<String, Option<Duration>>,
+}
+
+impl Default for Durations {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Durations {
+    pub fn new() -> Self {
+        let (tx, worker_rx) = channel::<String>();
+        let (worker_tx, rx) = channel();
+        thread::Builder::new()
+            .name("duration probe".to_string())
+            .spawn(move || {
+                for track in worker_rx {
+                    let length = probe(&track);
+                    if worker_tx.send((track, length)).is_err() {
+                        return;
+                    }
+                }
+            })
+            .expect("Can't start duration probe");
+        Self {
+            tx,
+            rx,
+            known: HashMap::new(),
+        }
+    }
+
+    /// Store a length known from playback
+    pub fn insert(&mut self, track: &str, length: Option<Duration>) {
+        if length.is_some() {
+            self.known.insert(track.to_string(), length);
+        }
+    }
+
+    /// Mark `tracks` as being probed elsewhere, their lengths follow through [`Durations::insert`]
+    pub fn requested<'a>(&mut self, tracks: impl Iterator<Item = &'a String>) {
+        for track in tracks {
+            self.known.entry(track.clone()).or_insert(None);
+        }
+    }
+
+    /// Total length of `tracks` and whether all lengths are known.
+    /// Unknown tracks are queued for probing.
+    pub fn total<'a>(&mut self, tracks: impl Iterator<Item = &'a String>) -> (Duration, bool) {
+        for (track, length) in self.rx.try_iter() {
+            self.known.insert(track, length);
+        }
+        let mut total = Duration::ZERO;
+        let mut complete = true;
+        for track in tracks {
+            match self.known.get(track) {
+                Some(Some(v)) => total += *v,
+                Some(None) => complete = false,
+                None => {
+                    complete = false;
+                    self.known.insert(track.clone(), None);
+                    let _ = self.tx.send(track.clone());
+                }
+            }
+        }
+        (total, complete)
+    }
+}
+
+/// Length from the headers of local files, remote tracks aren't downloaded
+pub(crate) fn probe(track: &str) -> Option<Duration> {
+    // the Vorbis decoder doesn't know the length, the Ogg headers are faster than ffprobe
+    if crate::ogg::is_ogg(Path::new(track)) {
+        return crate::ogg::length(Path::new(track))
+            .map_err(|e| trace!("Can't probe length of {}: {}", track, e))
+            .ok();
+    }
+    if crate::decoders::needs_ffmpeg(Path::new(track)) {
+        return crate::decoders::probe(Path::new(track))
+            .map_err(|e| trace!("Can't probe length of {}: {}", track, e))
+            .ok()
+            .and_then(|v| v.length);
+    }
+    let file = File::open(track).ok()?;
+    match rodio::Decoder::new(BufReader::new(file)) {
+        Ok(v) => v.total_duration(),
+        Err(e) => {
+            trace!("Can't probe length of {}: {}", track, e);
+            None
+        }
+    }
+}
@@ -0,0 +1,149 @@
+//! Named favorite lists
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use crate::pathutil;
+
+/// List favorites of older configs are moved to
+pub const DEFAULT_LIST: &str = "Favorites";
+
+pub struct Favorites {
+    /// Shared with the saved config, copied only when changed while saving
+    lists: Arc<HashMap<String, HashSet<String>>>,
+    /// Comparison keys of every list
+    keys: HashMap<String, HashSet<String>>,
+}
+
+impl Favorites {
+    /// Create from stored lists, `legacy` favorites are merged into the default list
+    pub fn new(mut lists: Arc<HashMap<String, HashSet<String>>>, legacy: HashSet<String>) -> Self {
+        if !legacy.is_empty() {
+            Arc::make_mut(&mut lists)
+                .entry(DEFAULT_LIST.to_string())
+                .or_default()
+                .extend(legacy);
+        }
+        if lists.is_empty() {
+            Arc::make_mut(&mut lists).insert(DEFAULT_LIST.to_string(), HashSet::new());
+        }
+        let mut favorites = Self {
+            lists,
+            keys: HashMap::new(),
+        };
+        favorites.update_keys();
+        favorites
+    }
+
+    fn update_keys(&mut self) {
+        self.keys = self
+            .lists
+            .iter()
+            .map(|(name, tracks)| {
+                (
+                    name.clone(),
+                    tracks.iter().map(|t| pathutil::key(t)).collect(),
+                )
+            })
+            .collect();
+    }
+
+    pub fn lists(&self) -> &Arc<HashMap<String, HashSet<String>>> {
+        &self.lists
+    }
+
+    /// List names in alphabetical order
+    pub fn names(&self) -> Vec<&String> {
+        let mut names: Vec<_> = self.lists.keys().collect();
+        names.sort();
+        names
+    }
+
+    /// Names of the lists containing `track`, in any stored form
+    pub fn lists_containing(&self, track: &str) -> HashSet<&String> {
+        let key = pathutil::key(track);
+        self.keys
+            .iter()
+            .filter(|(_, v)| v.contains(&key))
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// Whether `track` is in any list
+    pub fn contains_any(&self, track: &str) -> bool {
+        let key = pathutil::key(track);
+        self.keys.values().any(|v| v.contains(&key))
+    }
+
+    /// Add or remove `track` from the list `name`, creating the list if required
+    pub fn set(&mut self, name: &str, track: &str, favorite: bool) {
+        let key = pathutil::key(track);
+        let list = Arc::make_mut(&mut self.lists)
+            .entry(name.to_string())
+            .or_default();
+        let keys = self.keys.entry(name.to_string()).or_default();
+        if favorite {
+            list.insert(track.to_string());
+            keys.insert(key);
+        } else {
+            // may be stored in another form than `track`
+            list.retain(|t| pathutil::key(t) != key);
+            keys.remove(&key);
+        }
+    }
+
+    /// Tracks of all lists, each once
+    pub fn all(&self) -> HashSet<&String> {
+        self.lists.values().flatten().collect()
+    }
+
+    /// Replace tracks by their new location
+    pub fn relocate(&mut self, moved: &HashMap<String, String>) {
+        if moved.is_empty() {
+            return;
+        }
+        for list in Arc::make_mut(&mut self.lists).values_mut() {
+            *list = list
+                .drain()
+                .map(|t| moved.get(&t).cloned().unwrap_or(t))
+                .collect();
+        }
+        self.update_keys();
+    }
+}
+
+/// File name for exporting the list `name`
+pub fn export_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c.is_alphanumeric() || c == '-' || c == '_' {
+            true => c,
+            false => '_',
+        })
+        .collect();
+    format!("favorites-{}.xspf", name)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lists() {
+        let legacy: HashSet<String> = vec![String::from("a.mp3")].into_iter().collect();
+        let mut favorites = Favorites::new(Default::default(), legacy);
+        assert!(favorites
+            .lists_containing("a.mp3")
+            .contains(&DEFAULT_LIST.to_string()));
+        favorites.set("gym", "b.mp3", true);
+        assert_eq!(favorites.names(), vec![DEFAULT_LIST, "gym"]);
+        assert!(favorites.contains_any("b.mp3"));
+        assert_eq!(favorites.lists_containing("b.mp3").len(), 1);
+        favorites.set("gym", "b.mp3", false);
+        assert!(!favorites.contains_any("b.mp3"));
+        assert_eq!(favorites.all().len(), 1);
+        assert_eq!(export_name("to buy/now"), "favorites-to_buy_now.xspf");
+    }
+}
@@ -0,0 +1,134 @@
+//! User defined folders grouping saved playlists in the sidebar
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Folder {
+    pub name: String,
+    pub playlists: Vec<PathBuf>,
+    #[serde(default)]
+    pub collapsed: bool,
+}
+
+/// Folders in display order, playlists in none of them are ungrouped
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PlaylistFolders {
+    folders: Vec<Folder>,
+}
+
+impl PlaylistFolders {
+    pub fn folders(&self) -> &[Folder] {
+        &self.folders
+    }
+
+    /// Add an empty folder, false if the name is empty or taken
+    pub fn add(&mut self, name: &str) -> bool {
+        let name = name.trim();
+        if name.is_empty() || self.folders.iter().any(|f| f.name == name) {
+            return false;
+        }
+        self.folders.push(Folder {
+            name: name.to_string(),
+            playlists: Vec::new(),
+            collapsed: false,
+        });
+        true
+    }
+
+    /// Remove a folder, its playlists become ungrouped
+    pub fn remove(&mut self, name: &str) {
+        self.folders.retain(|f| f.name != name);
+    }
+
+    pub fn toggle(&mut self, name: &str) {
+        if let Some(folder) = self.folders.iter_mut().find(|f| f.name == name) {
+            folder.collapsed = !folder.collapsed;
+        }
+    }
+
+    /// Move `playlist` to the end of `folder`, None ungroups it
+    pub fn move_to(&mut self, playlist: &Path, folder: Option<&str>) {
+        for f in self.folders.iter_mut() {
+            f.playlists.retain(|p| p != playlist);
+        }
+        if let Some(f) = folder.and_then(|name| self.folders.iter_mut().find(|f| f.name == name)) {
+            f.playlists.push(playlist.to_path_buf());
+        }
+    }
+
+    pub fn folder_of(&self, playlist: &Path) -> Option<&str> {
+        self.folders
+            .iter()
+            .find(|f| f.playlists.iter().any(|p| p == playlist))
+            .map(|f| f.name.as_str())
+    }
+
+    /// Existing `playlists` per folder, followed by the sorted ungrouped ones
+    pub fn tree<'a, I>(&'a self, playlists: I) -> Vec<(Option<&'a Folder>, Vec<&'a Path>)>
+    where
+        I: IntoIterator<Item = &'a PathBuf>,
+    {
+        let playlists: Vec<&Path> = playlists.into_iter().map(PathBuf::as_path).collect();
+        let mut tree: Vec<_> = self
+            .folders
+            .iter()
+            .map(|f| {
+                // playlists removed since they were grouped aren't shown
+                let entries = f
+                    .playlists
+                    .iter()
+                    .map(PathBuf::as_path)
+                    .filter(|p| playlists.contains(p))
+                    .collect();
+                (Some(f), entries)
+            })
+            .collect();
+        let mut ungrouped: Vec<&Path> = playlists
+            .into_iter()
+            .filter(|p| self.folder_of(p).is_none())
+            .collect();
+        ungrouped.sort();
+        tree.push((None, ungrouped));
+        tree
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_folders() {
+        let (work, party) = (PathBuf::from("/p/work.m3u"), PathBuf::from("/p/party.m3u"));
+        let (gone, other) = (PathBuf::from("/p/gone.m3u"), PathBuf::from("/p/other.m3u"));
+        let mut folders = PlaylistFolders::default();
+        assert!(folders.add("Work"));
+        assert!(!folders.add(" Work "));
+        assert!(!folders.add(""));
+        folders.add("Party");
+        folders.move_to(&work, Some("Work"));
+        folders.move_to(&gone, Some("Work"));
+        folders.move_to(&party, Some("Work"));
+        folders.move_to(&party, Some("Party"));
+        assert_eq!(folders.folder_of(&party), Some("Party"));
+
+        let existing = [other.clone(), work.clone(), party.clone()];
+        let tree = folders.tree(&existing);
+        let names: Vec<_> = tree
+            .iter()
+            .map(|(f, _)| f.map(|f| f.name.as_str()))
+            .collect();
+        assert_eq!(names, vec![Some("Work"), Some("Party"), None]);
+        assert_eq!(tree[0].1, vec![work.as_path()]);
+        assert_eq!(tree[1].1, vec![party.as_path()]);
+        assert_eq!(tree[2].1, vec![other.as_path()]);
+
+        folders.remove("Party");
+        assert_eq!(folders.folder_of(&party), None);
+        folders.move_to(&work, None);
+        assert_eq!(folders.folders()[0].playlists, vec![gone]);
+    }
+}
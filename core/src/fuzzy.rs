@@ -0,0 +1,105 @@
+//! Fuzzy matching of spoken or typed names, tolerant of case, punctuation and small typos
+
+/// Minimum score of [`score`] for a name to count as match
+const MIN_SCORE: f32 = 0.6;
+
+/// Lowercase words of `text`, punctuation separates words
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Edit distance between `a` and `b` in characters
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitute.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Similarity of two words from 0 to 1
+fn word_similarity(a: &str, b: &str) -> f32 {
+    let len = a.chars().count().max(b.chars().count());
+    match len {
+        0 => 1.0,
+        _ => 1.0 - distance(a, b) as f32 / len as f32,
+    }
+}
+
+/// How well `name` matches `query` from 0 to 1: the similarity of each query word
+/// to its closest word in the name, lowered a little for extra words in the name
+pub fn score(query: &str, name: &str) -> f32 {
+    let query = words(query);
+    let name = words(name);
+    if query.is_empty() || name.is_empty() {
+        return 0.0;
+    }
+    if query == name {
+        return 1.0;
+    }
+    let matched: f32 = query
+        .iter()
+        .map(|q| {
+            name.iter()
+                .map(|n| word_similarity(q, n))
+                .fold(0.0, f32::max)
+        })
+        .sum::<f32>()
+        / query.len() as f32;
+    let extra = name.len().saturating_sub(query.len()) as f32;
+    // still below an exact match, even for a perfect match of all query words
+    matched * 0.95 - extra * 0.02
+}
+
+/// Best matching entry of `names` for `query`, None if none matches well enough
+pub fn best_match<'a, T, I>(query: &str, names: I) -> Option<T>
+where
+    I: IntoIterator<Item = (T, &'a str)>,
+{
+    names
+        .into_iter()
+        .map(|(v, name)| (score(query, name), v))
+        .filter(|(score, _)| *score >= MIN_SCORE)
+        .fold(None, |best: Option<(f32, T)>, (score, v)| match best {
+            Some(best) if best.0 >= score => Some(best),
+            _ => Some((score, v)),
+        })
+        .map(|(_, v)| v)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_best_match() {
+        let names = [
+            "Jazz Classics",
+            "jazz",
+            "Morning_Coffee",
+            "Workout 2020 (Mix)",
+            "Rock",
+        ];
+        let find = |query| best_match(query, names.iter().map(|n| (*n, *n)));
+        assert_eq!(find("jazz"), Some("jazz"));
+        assert_eq!(find("Jazz classics"), Some("Jazz Classics"));
+        assert_eq!(find("morning coffee"), Some("Morning_Coffee"));
+        assert_eq!(find("workout"), Some("Workout 2020 (Mix)"));
+        // small recognition errors
+        assert_eq!(find("morning cofee"), Some("Morning_Coffee"));
+        assert_eq!(find("rok"), Some("Rock"));
+        assert_eq!(find("classical piano"), None);
+        assert_eq!(find(""), None);
+        assert_eq!(distance("kitten", "sitting"), 3);
+    }
+}
@@ -0,0 +1,132 @@
+//! Track identity by content, so data about a track follows the file across renames and moves.
+//! Files are hashed on a background thread, the path to hash mapping is kept in the [`Store`].
+
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver, Sender},
+    thread,
+    time::UNIX_EPOCH,
+};
+
+use crate::prelude::*;
+use crate::store::{Identity, Store};
+
+/// Bytes hashed at the start, middle and end of larger files
+const SAMPLE: u64 = 64 * 1024;
+
+/// Hash of the size and samples of the content, reading at most three samples.
+/// Equal for copies and moves of a file, but changes if tags are rewritten.
+pub fn content_hash<R: Read + Seek>(reader: &mut R, size: u64) -> Result<String> {
+    let mut hasher = sha1::Sha1::new();
+    hasher.update(&size.to_le_bytes());
+    let offsets = match size <= 3 * SAMPLE {
+        true => vec![0],
+        false => vec![0, size / 2 - SAMPLE / 2, size - SAMPLE],
+    };
+    let mut buf = vec![0; SAMPLE.min(size) as usize];
+    for offset in offsets {
+        reader.seek(SeekFrom::Start(offset))?;
+        reader.read_exact(&mut buf)?;
+        hasher.update(&buf);
+    }
+    Ok(hasher.digest().to_string())
+}
+
+/// Hashes queued tracks in the background and reports tracks found under a new path
+pub struct Hasher {
+    tx: Sender<String>,
+    rx: Receiver<(String, String)>,
+}
+
+impl Hasher {
+    /// Start hashing, identities are kept in the database at `db`
+    pub fn spawn(db: PathBuf) -> Result<Self> {
+        let store = Store::open(&db)?;
+        let (tx, jobs) = channel::<String>();
+        let (moved_tx, rx) = channel();
+        thread::Builder::new()
+            .name("track hasher".into())
+            .spawn(move || {
+                for track in jobs {
+                    match identify(&store, &track) {
+                        Ok(Some(old)) => {
+                            info!("{} was moved to {}", old, track);
+                            if moved_tx.send((old, track)).is_err() {
+                                return;
+                            }
+                        }
+                        Ok(None) => (),
+                        // remote and missing tracks have no content to hash
+                        Err(e) => trace!("Can't identify {}: {}", track, e),
+                    }
+                }
+            })
+            .wrap_err("Can't start track hasher")?;
+        Ok(Self { tx, rx })
+    }
+
+    pub fn queue(&self, track: &str) {
+        let _ = self.tx.send(track.to_string());
+    }
+
+    /// Tracks found moved since the last call, by old path
+    pub fn moved(&self) -> HashMap<String, String> {
+        self.rx.try_iter().collect()
+    }
+}
+
+/// Record the identity of `track`, returns its old path if the file was known under another one
+fn identify(store: &Store, track: &str) -> Result<Option<String>> {
+    let meta = fs::metadata(track)?;
+    let modified = meta
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |v| v.as_secs());
+    let size = meta.len();
+    // unchanged since it was hashed
+    if store
+        .identity(track)?
+        .is_some_and(|v| v.size == size && v.modified == modified)
+    {
+        return Ok(None);
+    }
+    let hash = content_hash(&mut File::open(track)?, size)?;
+    let old = store
+        .paths_with_hash(&hash)?
+        .into_iter()
+        .find(|v| v != track && !Path::new(v).exists());
+    if let Some(ref old) = old {
+        store.remove_identity(old)?;
+    }
+    store.set_identity(
+        track,
+        &Identity {
+            hash,
+            size,
+            modified,
+        },
+    )?;
+    Ok(old)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_content_hash() {
+        let data: Vec<u8> = (0..500_000u32).map(|v| (v % 251) as u8).collect();
+        let hash = |v: &[u8]| content_hash(&mut Cursor::new(v), v.len() as u64).unwrap();
+        assert_eq!(hash(&data), hash(&data.clone()));
+        let mut changed = data.clone();
+        changed[data.len() - 1] ^= 1;
+        assert_ne!(hash(&data), hash(&changed));
+        // small files are hashed completely
+        assert_ne!(hash(&data[..1000]), hash(&data[..999]));
+        assert_eq!(hash(&[]), hash(&[]));
+    }
+}
@@ -0,0 +1,387 @@
+//! Import of `iTunes Music Library.xml` and Apple Music `Library.xml` exports
+
+use std::{collections::HashMap, path::Path};
+
+use chrono::DateTime;
+use quick_xml::{events::Event, Reader};
+use url::Url;
+
+use crate::{prelude::*, store::MAX_RATING};
+
+/// Sidebar folder imported playlists are grouped in
+pub const FOLDER: &str = "iTunes";
+/// Bytes read to tell library exports from other XML files
+const SNIFF_LEN: u64 = 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItunesTrack {
+    /// Local path, or the URL if it isn't a file
+    pub location: String,
+    /// From 1 to [`MAX_RATING`], None if unrated
+    pub rating: Option<u8>,
+    pub play_count: u64,
+    /// Unix time in seconds
+    pub last_played: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItunesPlaylist {
+    pub name: String,
+    /// Locations of the entries in order
+    pub tracks: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ItunesLibrary {
+    pub tracks: Vec<ItunesTrack>,
+    /// User playlists, the library and built in media playlists are left out
+    pub playlists: Vec<ItunesPlaylist>,
+}
+
+/// Property list value, only what library exports contain
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Dict(Vec<(String, Value)>),
+    Array(Vec<Value>),
+    String(String),
+    Integer(i64),
+    Bool(bool),
+    Date(String),
+    /// Data and real values aren't needed
+    Other,
+}
+
+impl Value {
+    fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Dict(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn str(&self) -> Option<&str> {
+        match self {
+            Value::String(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn int(&self) -> Option<i64> {
+        match self {
+            Value::Integer(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    fn is_true(&self) -> bool {
+        *self == Value::Bool(true)
+    }
+}
+
+/// Whether `head`, the start of an XML file, is a library export: a property list
+/// starting with the version keys iTunes and Apple Music write first
+pub fn is_library(head: &str) -> bool {
+    head.contains("<plist")
+        && (head.contains("<key>Major Version</key>")
+            || head.contains("<key>Application Version</key>"))
+}
+
+/// Whether the file at `path` is a library export, only its start is read
+pub fn sniff(path: &Path) -> bool {
+    use std::io::Read;
+    let mut head = Vec::new();
+    match std::fs::File::open(path).and_then(|f| f.take(SNIFF_LEN).read_to_end(&mut head)) {
+        Ok(_) => is_library(&String::from_utf8_lossy(&head)),
+        Err(_) => false,
+    }
+}
+
+pub fn read(path: &Path) -> Result<ItunesLibrary> {
+    let data = std::fs::read_to_string(path).wrap_err_with(|| format!("Can't read {:?}", path))?;
+    parse(&data).wrap_err_with(|| format!("Invalid iTunes library {:?}", path))
+}
+
+pub fn parse(data: &str) -> Result<ItunesLibrary> {
+    let mut reader = Reader::from_str(data);
+    reader.trim_text(true);
+    let root = loop {
+        match reader.read_event(&mut Vec::new())? {
+            Event::Start(e) if e.name() == b"dict" => break read_dict(&mut reader)?,
+            Event::Eof => return Err(eyre!("No property list")),
+            _ => (),
+        }
+    };
+    let mut by_id = HashMap::new();
+    let mut tracks = Vec::new();
+    if let Some(Value::Dict(entries)) = root.get("Tracks") {
+        for (_, track) in entries {
+            let (id, location) = match (
+                track.get("Track ID").and_then(Value::int),
+                track.get("Location").and_then(Value::str),
+            ) {
+                (Some(id), Some(location)) => (id, location_path(location)),
+                // streams and cloud tracks without a local copy
+                _ => continue,
+            };
+            let rating = track
+                .get("Rating")
+                .and_then(Value::int)
+                // computed album ratings aren't the ones of the track
+                .filter(|_| !track.get("Rating Computed").is_some_and(Value::is_true))
+                .map(|v| (v / 20).clamp(0, MAX_RATING as i64) as u8)
+                .filter(|v| *v > 0);
+            let last_played = match track.get("Play Date UTC") {
+                Some(Value::Date(v)) => DateTime::parse_from_rfc3339(v)
+                    .ok()
+                    .map(|v| v.timestamp().max(0) as u64),
+                _ => None,
+            };
+            by_id.insert(id, location.clone());
+            tracks.push(ItunesTrack {
+                location,
+                rating,
+                play_count: track
+                    .get("Play Count")
+                    .and_then(Value::int)
+                    .unwrap_or(0)
+                    .max(0) as u64,
+                last_played,
+            });
+        }
+    }
+    let mut playlists = Vec::new();
+    if let Some(Value::Array(entries)) = root.get("Playlists") {
+        for playlist in entries {
+            let builtin =
+                ["Master", "Distinguished Kind", "Folder"]
+                    .iter()
+                    .any(|key| match playlist.get(key) {
+                        Some(Value::Bool(v)) => *v,
+                        Some(_) => true,
+                        None => false,
+                    });
+            let name = match playlist.get("Name").and_then(Value::str) {
+                Some(v) if !builtin => v.to_string(),
+                _ => continue,
+            };
+            let items = match playlist.get("Playlist Items") {
+                Some(Value::Array(v)) => v.as_slice(),
+                _ => &[],
+            };
+            let tracks = items
+                .iter()
+                .filter_map(|item| item.get("Track ID").and_then(Value::int))
+                .filter_map(|id| by_id.get(&id).cloned())
+                .collect();
+            playlists.push(ItunesPlaylist { name, tracks });
+        }
+    }
+    Ok(ItunesLibrary { tracks, playlists })
+}
+
+/// File name of an imported playlist, `name` with unsafe characters replaced
+pub fn playlist_file_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(
+            |c| match c.is_alphanumeric() || c == '-' || c == '_' || c == ' ' {
+                true => c,
+                false => '_',
+            },
+        )
+        .collect();
+    format!("{}.xspf", name.trim())
+}
+
+/// Local path of a `file://` location, other URLs are kept
+fn location_path(location: &str) -> String {
+    match Url::parse(location) {
+        Ok(url) if url.scheme() == "file" => match url.to_file_path() {
+            Ok(v) => v.to_string_lossy().into_owned(),
+            Err(_) => location.to_string(),
+        },
+        _ => location.to_string(),
+    }
+}
+
+/// Read a dict after its start tag, up to and including the end tag
+fn read_dict(reader: &mut Reader<&[u8]>) -> Result<Value> {
+    let mut entries = Vec::new();
+    let mut key = None;
+    loop {
+        match reader.read_event(&mut Vec::new())? {
+            Event::Start(e) if e.name() == b"key" => {
+                key = Some(reader.read_text(b"key", &mut Vec::new())?);
+            }
+            Event::End(e) if e.name() == b"dict" => return Ok(Value::Dict(entries)),
+            Event::Eof => return Err(eyre!("Unterminated dict")),
+            event => {
+                if let Some(value) = read_value(reader, event)? {
+                    let key = key.take().ok_or_else(|| eyre!("Dict value without key"))?;
+                    entries.push((key, value));
+                }
+            }
+        }
+    }
+}
+
+fn read_array(reader: &mut Reader<&[u8]>) -> Result<Value> {
+    let mut values = Vec::new();
+    loop {
+        match reader.read_event(&mut Vec::new())? {
+            Event::End(e) if e.name() == b"array" => return Ok(Value::Array(values)),
+            Event::Eof => return Err(eyre!("Unterminated array")),
+            event => values.extend(read_value(reader, event)?),
+        }
+    }
+}
+
+/// Value starting with `event`, None for anything that isn't one
+fn read_value(reader: &mut Reader<&[u8]>, event: Event<'_>) -> Result<Option<Value>> {
+    let value = match event {
+        Event::Start(e) => match e.name() {
+            b"dict" => read_dict(reader)?,
+            b"array" => read_array(reader)?,
+            b"string" => Value::String(reader.read_text(b"string", &mut Vec::new())?),
+            b"date" => Value::Date(reader.read_text(b"date", &mut Vec::new())?),
+            b"integer" => Value::Integer(
+                reader
+                    .read_text(b"integer", &mut Vec::new())?
+                    .parse()
+                    .wrap_err("Invalid integer")?,
+            ),
+            name => {
+                let name = name.to_vec();
+                reader.read_to_end(&name, &mut Vec::new())?;
+                Value::Other
+            }
+        },
+        Event::Empty(e) => match e.name() {
+            b"true" => Value::Bool(true),
+            b"false" => Value::Bool(false),
+            b"string" => Value::String(String::new()),
+            b"dict" => Value::Dict(Vec::new()),
+            b"array" => Value::Array(Vec::new()),
+            _ => Value::Other,
+        },
+        _ => return Ok(None),
+    };
+    Ok(Some(value))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const LIBRARY: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple Computer//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Major Version</key><integer>1</integer>
+    <key>Music Folder</key><string>file:///Users/a/Music/</string>
+    <key>Tracks</key>
+    <dict>
+        <key>10</key>
+        <dict>
+            <key>Track ID</key><integer>10</integer>
+            <key>Name</key><string>Song &amp; Dance</string>
+            <key>Rating</key><integer>80</integer>
+            <key>Play Count</key><integer>7</integer>
+            <key>Play Date UTC</key><date>2021-03-04T05:06:07Z</date>
+            <key>Compilation</key><true/>
+            <key>Location</key><string>file:///Users/a/Music/Song%20%26%20Dance.mp3</string>
+        </dict>
+        <key>11</key>
+        <dict>
+            <key>Track ID</key><integer>11</integer>
+            <key>Rating</key><integer>60</integer>
+            <key>Rating Computed</key><true/>
+            <key>Location</key><string>file:///Users/a/Music/b.mp3</string>
+        </dict>
+        <key>12</key>
+        <dict>
+            <key>Track ID</key><integer>12</integer>
+            <key>Name</key><string>Cloud only</string>
+        </dict>
+    </dict>
+    <key>Playlists</key>
+    <array>
+        <dict>
+            <key>Name</key><string>Library</string>
+            <key>Master</key><true/>
+            <key>Playlist Items</key>
+            <array>
+                <dict><key>Track ID</key><integer>10</integer></dict>
+            </array>
+        </dict>
+        <dict>
+            <key>Name</key><string>Party</string>
+            <key>Playlist Persistent ID</key><string>ABC</string>
+            <key>Playlist Items</key>
+            <array>
+                <dict><key>Track ID</key><integer>11</integer></dict>
+                <dict><key>Track ID</key><integer>12</integer></dict>
+                <dict><key>Track ID</key><integer>10</integer></dict>
+            </array>
+        </dict>
+        <dict>
+            <key>Name</key><string>Empty</string>
+        </dict>
+    </array>
+</dict>
+</plist>"#;
+
+    #[test]
+    fn test_is_library() {
+        assert!(is_library(&LIBRARY[..SNIFF_LEN as usize]));
+        // XSPF playlists and other property lists are no libraries
+        assert!(!is_library(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<playlist version="1" xmlns="http://xspf.org/ns/0/"><trackList/></playlist>"#
+        ));
+        assert!(!is_library(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0"><dict><key>CFBundleName</key><string>App</string></dict></plist>"#
+        ));
+    }
+
+    #[test]
+    fn test_parse() {
+        let library = parse(LIBRARY).unwrap();
+        let (a, b) = (
+            location_path("file:///Users/a/Music/Song%20%26%20Dance.mp3"),
+            location_path("file:///Users/a/Music/b.mp3"),
+        );
+        assert_eq!(
+            library.tracks,
+            vec![
+                ItunesTrack {
+                    location: a.clone(),
+                    rating: Some(4),
+                    play_count: 7,
+                    last_played: Some(1614834367),
+                },
+                ItunesTrack {
+                    location: b.clone(),
+                    rating: None,
+                    play_count: 0,
+                    last_played: None,
+                },
+            ]
+        );
+        assert_eq!(
+            library.playlists,
+            vec![
+                ItunesPlaylist {
+                    name: String::from("Party"),
+                    tracks: vec![b, a],
+                },
+                ItunesPlaylist {
+                    name: String::from("Empty"),
+                    tracks: Vec::new(),
+                },
+            ]
+        );
+        assert_eq!(playlist_file_name("Rock/Pop: 80s"), "Rock_Pop_ 80s.xspf");
+    }
+}
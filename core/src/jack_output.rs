@@ -0,0 +1,189 @@
+//! Main output through a JACK client with named ports, PipeWire provides the same through pipewire-jack.
+//! libjack is loaded at runtime, it's only required with a JACK output configured.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use jack::{
+    AsyncClient, AudioOut, Client, ClientOptions, Control, NotificationHandler, Port,
+    ProcessHandler, ProcessScope, RingBuffer, RingBufferReader,
+};
+use rodio::{source::UniformSourceIterator, Source};
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/// Buffered frames between the decoder and the JACK process callback
+const BUFFER_FRAMES: usize = 8192;
+/// Frames moved into the buffer at once
+const FEED_FRAMES: usize = 1024;
+/// Interleaved stereo f32 frame
+const FRAME_BYTES: usize = 8;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JackOutput {
+    /// Client name shown in the session graph
+    #[serde(default = "default_client_name")]
+    pub client_name: String,
+    /// Ports the left and right output are connected to on start, e.g. `system:playback_1`
+    #[serde(default)]
+    pub connect: Vec<String>,
+}
+
+fn default_client_name() -> String {
+    String::from("audio_wrench")
+}
+
+type Frames = Box<dyn Iterator<Item = f32> + Send>;
+
+struct Process {
+    left: Port<AudioOut>,
+    right: Port<AudioOut>,
+    buffer: RingBufferReader,
+    /// Set once a track was handed over, silence before isn't an underrun
+    active: Arc<AtomicBool>,
+    underruns: Arc<AtomicU64>,
+}
+
+impl ProcessHandler for Process {
+    fn process(&mut self, _: &Client, scope: &ProcessScope) -> Control {
+        let left = self.left.as_mut_slice(scope);
+        let right = self.right.as_mut_slice(scope);
+        let mut frame = [0u8; FRAME_BYTES];
+        let mut missing = false;
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            // silence on underrun
+            if self.buffer.space() < FRAME_BYTES {
+                *l = 0.0;
+                *r = 0.0;
+                missing = true;
+                continue;
+            }
+            self.buffer.read_buffer(&mut frame);
+            *l = f32::from_ne_bytes([frame[0], frame[1], frame[2], frame[3]]);
+            *r = f32::from_ne_bytes([frame[4], frame[5], frame[6], frame[7]]);
+        }
+        if missing && self.active.load(Ordering::Relaxed) {
+            self.underruns.fetch_add(1, Ordering::Relaxed);
+        }
+        Control::Continue
+    }
+}
+
+/// Counts xruns reported by the server, these include other clients being late
+struct Notifications {
+    underruns: Arc<AtomicU64>,
+}
+
+impl NotificationHandler for Notifications {
+    fn xrun(&mut self, _: &Client) -> Control {
+        self.underruns.fetch_add(1, Ordering::Relaxed);
+        Control::Continue
+    }
+}
+
+/// Active JACK client, closed when dropped
+pub(crate) struct JackStream {
+    _client: AsyncClient<Notifications, Process>,
+    /// Played by the feeding thread, replaced per track
+    source: Arc<Mutex<Option<Frames>>>,
+    stop: Arc<AtomicBool>,
+    active: Arc<AtomicBool>,
+    sample_rate: u32,
+    underruns: Arc<AtomicU64>,
+}
+
+impl JackStream {
+    /// Register the client with its ports and connect them
+    pub fn start(output: &JackOutput) -> Result<Self> {
+        let (client, status) = Client::new(&output.client_name, ClientOptions::NO_START_SERVER)
+            .map_err(|e| eyre!("Can't connect to JACK: {}", e))?;
+        debug!("JACK client {} opened: {:?}", client.name(), status);
+        let left = client.register_port("out_left", AudioOut)?;
+        let right = client.register_port("out_right", AudioOut)?;
+        let ports = [left.name()?, right.name()?];
+        let sample_rate = client.sample_rate() as u32;
+        let (reader, mut writer) =
+            RingBuffer::new(BUFFER_FRAMES * FRAME_BYTES)?.into_reader_writer();
+        let active = Arc::new(AtomicBool::new(false));
+        let underruns = Arc::new(AtomicU64::new(0));
+        let client = client.activate_async(
+            Notifications {
+                underruns: underruns.clone(),
+            },
+            Process {
+                left,
+                right,
+                buffer: reader,
+                active: active.clone(),
+                underruns: underruns.clone(),
+            },
+        )?;
+        for (port, target) in ports.iter().zip(output.connect.iter()) {
+            if let Err(e) = client.as_client().connect_ports_by_name(port, target) {
+                warn!("Can't connect {} to {}: {}", port, target, e);
+            }
+        }
+
+        let source: Arc<Mutex<Option<Frames>>> = Default::default();
+        let stop = Arc::new(AtomicBool::new(false));
+        let (thread_source, thread_stop) = (source.clone(), stop.clone());
+        thread::Builder::new()
+            .name("jack output".to_string())
+            .spawn(move || {
+                let mut chunk = Vec::with_capacity(FEED_FRAMES * FRAME_BYTES);
+                while !thread_stop.load(Ordering::Relaxed) {
+                    chunk.clear();
+                    if writer.space() >= FEED_FRAMES * FRAME_BYTES {
+                        let mut source = thread_source.lock().expect("Can't lock JACK source");
+                        if let Some(source) = source.as_mut() {
+                            for sample in source.by_ref().take(FEED_FRAMES * 2) {
+                                chunk.extend_from_slice(&sample.to_ne_bytes());
+                            }
+                        }
+                    }
+                    match chunk.is_empty() {
+                        true => thread::sleep(Duration::from_millis(5)),
+                        false => {
+                            writer.write_buffer(&chunk);
+                        }
+                    }
+                }
+            })?;
+        Ok(Self {
+            _client: client,
+            source,
+            stop,
+            active,
+            sample_rate,
+            underruns,
+        })
+    }
+
+    /// Play `source` from now on, converted to stereo at the JACK sample rate
+    pub fn set_source<S>(&self, source: S)
+    where
+        S: Source<Item = f32> + Send + 'static,
+    {
+        let frames = UniformSourceIterator::<_, f32>::new(source, 2, self.sample_rate);
+        *self.source.lock().expect("Can't lock JACK source") = Some(Box::new(frames));
+        self.active.store(true, Ordering::Relaxed);
+    }
+
+    /// Process cycles without decoded samples and server xruns
+    pub fn underruns(&self) -> u64 {
+        self.underruns.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for JackStream {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,162 @@
+//! Append-only change journal with one JSON entry per line.
+//! Small changes are appended as they happen and folded into a full save now and then,
+//! instead of rewriting everything on every change.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::prelude::*;
+
+pub struct Journal {
+    path: PathBuf,
+    file: File,
+    entries: usize,
+}
+
+impl Journal {
+    /// Open for appending, creating the file if missing
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .wrap_err_with(|| format!("Can't open journal {:?}", path))?;
+        let data = fs::read(path).unwrap_or_default();
+        let entries = data.iter().filter(|v| **v == b'\n').count();
+        if data.last().is_some_and(|v| *v != b'\n') {
+            // keep a torn last line from swallowing the next entry
+            file.write_all(b"\n")?;
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            entries,
+        })
+    }
+
+    pub fn append<T: Serialize>(&mut self, entry: &T) -> Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        // single write, so a crash can only tear the last line
+        self.file.write_all(&line)?;
+        self.entries += 1;
+        Ok(())
+    }
+
+    /// Entries not yet moved away by [`Journal::rotate`]
+    pub fn len(&self) -> usize {
+        self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries == 0
+    }
+
+    /// Move all entries to a new file next to the journal and start over.
+    /// Returns the moved file, to be removed once a full save containing its changes is written.
+    pub fn rotate(&mut self) -> Result<Option<PathBuf>> {
+        if self.entries == 0 {
+            return Ok(None);
+        }
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos());
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{}", nanos));
+        let rotated = self.path.with_file_name(name);
+        fs::rename(&self.path, &rotated)
+            .wrap_err_with(|| format!("Can't rotate journal {:?}", self.path))?;
+        *self = Self::open(&self.path)?;
+        Ok(Some(rotated))
+    }
+}
+
+/// Rotated files of the journal at `path` from oldest to newest, followed by `path` if present
+pub fn files(path: &Path) -> Vec<PathBuf> {
+    let prefix = format!(
+        "{}.",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    );
+    let folder = path.parent().unwrap_or_else(|| Path::new("."));
+    let mut rotated: Vec<(u128, PathBuf)> = fs::read_dir(folder)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let stamp = name.to_str()?.strip_prefix(&prefix)?.parse().ok()?;
+            Some((stamp, entry.path()))
+        })
+        .collect();
+    rotated.sort();
+    let mut files: Vec<PathBuf> = rotated.into_iter().map(|(_, path)| path).collect();
+    if path.is_file() {
+        files.push(path.to_path_buf());
+    }
+    files
+}
+
+/// All entries of the journal at `path` including rotated files, oldest first.
+/// Unreadable lines are skipped, like one torn by a crash.
+pub fn read<T: DeserializeOwned>(path: &Path) -> Vec<T> {
+    let mut entries = Vec::new();
+    for file in files(path) {
+        let data = match fs::read_to_string(&file) {
+            Ok(v) => v,
+            Err(e) => {
+                warn!("Can't read journal {:?}: {}", file, e);
+                continue;
+            }
+        };
+        for line in data.lines().filter(|v| !v.trim().is_empty()) {
+            match serde_json::from_str(line) {
+                Ok(v) => entries.push(v),
+                Err(e) => warn!("Skipping invalid journal entry in {:?}: {}", file, e),
+            }
+        }
+    }
+    entries
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_journal() {
+        let folder = std::env::temp_dir().join("audio_wrench_test_journal");
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        let path = folder.join("changes.journal");
+
+        let mut journal = Journal::open(&path).unwrap();
+        journal.append(&1).unwrap();
+        journal.append(&2).unwrap();
+        let rotated = journal.rotate().unwrap().unwrap();
+        assert!(journal.is_empty());
+        assert_eq!(journal.rotate().unwrap(), None);
+        journal.append(&3).unwrap();
+        // torn last line of a crash
+        OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"[4")
+            .unwrap();
+        drop(journal);
+        let mut journal = Journal::open(&path).unwrap();
+        journal.append(&5).unwrap();
+        assert_eq!(files(&path), vec![rotated.clone(), path.clone()]);
+        assert_eq!(read::<u32>(&path), vec![1, 2, 3, 5]);
+
+        fs::remove_file(rotated).unwrap();
+        assert_eq!(read::<u32>(&path), vec![3, 5]);
+        let _ = fs::remove_dir_all(&folder);
+    }
+}
@@ -0,0 +1,78 @@
+//! Playback, playlist and library handling of audio_wrench, independent of any frontend.
+//!
+//! A frontend starts the audio thread with [`player::Player::new`], sends it
+//! [`player::PlayerCommand`]s and reads back [`player::PlayerStatus`] updates.
+//! Playlists are read and written through [`playlist`], track metadata
+//! like favorites, notes and tags lives in [`favorites`], [`notes`] and [`tags`].
+
+use std::path::Path;
+
+pub mod prelude {
+    pub use log::{debug, error, info, trace, warn};
+    pub use stable_eyre::eyre::{eyre, Report, WrapErr};
+    pub type Result<T> = std::result::Result<T, Report>;
+}
+
+pub mod alarm;
+pub mod archive;
+pub mod backend;
+pub mod cd;
+pub mod charset;
+pub mod clock;
+pub mod content_filter;
+pub mod covers;
+pub mod cues;
+pub mod decoders;
+pub mod dsp;
+pub mod durations;
+pub mod favorites;
+pub mod folders;
+pub mod fuzzy;
+pub mod identity;
+pub mod itunes;
+pub mod jack_output;
+pub mod journal;
+pub mod library;
+pub mod metadata;
+pub mod mixes;
+pub mod mtp;
+pub mod notes;
+pub mod ogg;
+pub mod output;
+pub mod pathutil;
+pub mod player;
+pub mod playlist;
+pub mod portable;
+pub mod queue;
+pub mod readahead;
+pub mod relocate;
+pub mod remote;
+pub mod rip;
+pub mod scrobbles;
+pub mod selection;
+pub mod store;
+pub mod tags;
+pub mod transfer;
+
+/// File extensions that are enqueued directly instead of being parsed as playlist
+pub const AUDIO_EXTENSIONS: &[&str] = &["mp3", "wav", "ogg", "flac", "wv", "ape", "tta", "opus"];
+/// File extensions loaded as playlist when opened from a source
+pub const PLAYLIST_EXTENSIONS: &[&str] = &["m3u", "m3u8", "pls", "xspf", "asx"];
+
+/// Whether `file` has an extension of a supported playlist format
+pub fn is_playlist_file(file: &Path) -> bool {
+    file.extension()
+        .and_then(|v| v.to_str())
+        .is_some_and(|ext| {
+            PLAYLIST_EXTENSIONS
+                .iter()
+                .any(|v| v.eq_ignore_ascii_case(ext))
+        })
+}
+
+/// Whether `file` has an extension of a supported audio format
+pub fn is_audio_file(file: &Path) -> bool {
+    file.extension()
+        .and_then(|v| v.to_str())
+        .is_some_and(|ext| AUDIO_EXTENSIONS.iter().any(|v| v.eq_ignore_ascii_case(ext)))
+}
@@ -0,0 +1,499 @@
+//! Library index over tracks with read metadata
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::metadata::TrackInfo;
+use crate::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Album {
+    pub artist: String,
+    pub title: String,
+    /// Sorted by path, which follows the track numbers of most libraries
+    pub tracks: Vec<String>,
+}
+
+/// Albums of `tracks` by artist and title, tracks without album tag are left out
+pub fn albums<'a>(tracks: impl Iterator<Item = (&'a String, &'a TrackInfo)>) -> Vec<Album> {
+    let mut albums: BTreeMap<(String, String), Album> = BTreeMap::new();
+    for (track, info) in tracks {
+        let title = match info.album {
+            Some(ref v) if !v.trim().is_empty() => v,
+            _ => continue,
+        };
+        let artist = info.artist.clone().unwrap_or_default();
+        // sort case insensitive, but keep the first spelling seen
+        let key = (artist.to_lowercase(), title.to_lowercase());
+        albums
+            .entry(key)
+            .or_insert_with(|| Album {
+                artist,
+                title: title.clone(),
+                tracks: Vec::new(),
+            })
+            .tracks
+            .push(track.clone());
+    }
+    albums
+        .into_values()
+        .map(|mut album| {
+            album.tracks.sort();
+            album.tracks.dedup();
+            album
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Artist {
+    pub name: String,
+    pub albums: Vec<Album>,
+    /// All tracks including those without album, sorted by path
+    pub tracks: Vec<String>,
+}
+
+/// Albums and tracks of the artist `name` in `tracks`, matched case insensitive
+pub fn artist<'a>(name: &str, tracks: impl Iterator<Item = (&'a String, &'a TrackInfo)>) -> Artist {
+    let name_key = name.to_lowercase();
+    let own: Vec<_> = tracks
+        .filter(|(_, info)| {
+            info.artist
+                .as_ref()
+                .is_some_and(|v| v.to_lowercase() == name_key)
+        })
+        .collect();
+    let mut all: Vec<String> = own.iter().map(|(t, _)| (*t).clone()).collect();
+    all.sort();
+    all.dedup();
+    Artist {
+        name: name.to_string(),
+        albums: albums(own.into_iter()),
+        tracks: all,
+    }
+}
+
+/// Filters of library browsing, unset ones match everything
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Facets {
+    pub genre: Option<String>,
+    /// First year of the decade
+    pub decade: Option<u16>,
+    /// Upper case file extension
+    pub format: Option<String>,
+    /// Matched against path, title, artist and album, ignoring case
+    pub text: String,
+}
+
+impl Facets {
+    pub fn matches(&self, track: &str, info: &TrackInfo) -> bool {
+        let genre = match self.genre {
+            Some(ref genre) => info
+                .genre
+                .as_ref()
+                .is_some_and(|v| v.eq_ignore_ascii_case(genre)),
+            None => true,
+        };
+        let decade = match self.decade {
+            Some(decade) => info.year.is_some_and(|v| v - v % 10 == decade),
+            None => true,
+        };
+        let format_matches = self.format.is_none() || self.format == format(track);
+        let text = self.text.trim().to_lowercase();
+        let text_matches = text.is_empty()
+            || std::iter::once(Some(track))
+                .chain([&info.title, &info.artist, &info.album].map(Option::as_deref))
+                .flatten()
+                .any(|v| v.to_lowercase().contains(&text));
+        genre && decade && format_matches && text_matches
+    }
+}
+
+/// Tracks matching `facets`, sorted by path
+pub fn search<'a>(
+    facets: &Facets,
+    tracks: impl Iterator<Item = (&'a String, &'a TrackInfo)>,
+) -> Vec<String> {
+    let mut found: Vec<String> = tracks
+        .filter(|(track, info)| facets.matches(track, info))
+        .map(|(track, _)| track.clone())
+        .collect();
+    found.sort();
+    found
+}
+
+/// Values present in `tracks` to offer for each facet
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FacetValues {
+    pub genres: Vec<String>,
+    pub decades: Vec<u16>,
+    pub formats: Vec<String>,
+}
+
+pub fn facet_values<'a>(tracks: impl Iterator<Item = (&'a String, &'a TrackInfo)>) -> FacetValues {
+    let mut genres = BTreeSet::new();
+    let mut decades = BTreeSet::new();
+    let mut formats = BTreeSet::new();
+    for (track, info) in tracks {
+        genres.extend(info.genre.clone().filter(|v| !v.trim().is_empty()));
+        decades.extend(info.year.map(|v| v - v % 10));
+        formats.extend(format(track));
+    }
+    FacetValues {
+        genres: genres.into_iter().collect(),
+        decades: decades.into_iter().collect(),
+        formats: formats.into_iter().collect(),
+    }
+}
+
+/// Audio files below `folders`, unreadable folders are skipped
+pub fn scan(folders: &[PathBuf]) -> Vec<String> {
+    let mut pending = folders.to_vec();
+    let mut found = Vec::new();
+    while let Some(folder) = pending.pop() {
+        let entries = match fs::read_dir(&folder) {
+            Ok(v) => v,
+            Err(e) => {
+                warn!("Can't scan {:?}: {}", folder, e);
+                continue;
+            }
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            match entry.file_type() {
+                Ok(t) if t.is_dir() => pending.push(path),
+                Ok(_) if crate::is_audio_file(&path) => {
+                    found.push(path.to_string_lossy().into_owned())
+                }
+                _ => (),
+            }
+        }
+    }
+    found
+}
+
+/// Albums with a track modified at or after unix time `since`, newest first.
+/// Tracks without album tag are grouped by folder.
+pub fn recently_added<'a>(
+    tracks: impl Iterator<Item = (&'a String, &'a TrackInfo)>,
+    since: u64,
+) -> Vec<Album> {
+    let recent: Vec<_> = tracks
+        .filter(|(_, info)| info.modified.is_some_and(|v| v >= since))
+        .collect();
+    let newest: HashMap<&str, u64> = recent
+        .iter()
+        .map(|(t, info)| (t.as_str(), info.modified.unwrap_or_default()))
+        .collect();
+    let mut by_folder: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (track, info) in &recent {
+        if info.album.as_ref().is_none_or(|v| v.trim().is_empty()) {
+            let folder = Path::new(track.as_str())
+                .parent()
+                .and_then(Path::file_name)
+                .map(|v| v.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            by_folder.entry(folder).or_default().push((*track).clone());
+        }
+    }
+    let mut found = albums(recent.into_iter());
+    found.extend(by_folder.into_iter().map(|(title, mut tracks)| {
+        tracks.sort();
+        Album {
+            artist: String::new(),
+            title,
+            tracks,
+        }
+    }));
+    let added = |album: &Album| {
+        album
+            .tracks
+            .iter()
+            .filter_map(|t| newest.get(t.as_str()))
+            .max()
+            .copied()
+            .unwrap_or_default()
+    };
+    found.sort_by_key(|album| std::cmp::Reverse(added(album)));
+    found
+}
+
+/// How endless play picks tracks once the queue ran out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FillStrategy {
+    Random,
+    /// Never played tracks first, then the ones played longest ago
+    LeastRecentlyPlayed,
+    /// Tracks of the genre of `last`, others if not enough are found
+    SameGenre,
+}
+
+/// Pick up to `count` tracks of `tracks` to refill an empty queue, `last` is never picked.
+/// `last_played` holds the unix time tracks were last played at.
+pub fn fill<'a>(
+    strategy: FillStrategy,
+    tracks: impl Iterator<Item = (&'a String, &'a TrackInfo)>,
+    last: Option<&str>,
+    last_played: &HashMap<String, u64>,
+    count: usize,
+    rng: &mut impl Rng,
+) -> Vec<String> {
+    let (previous, mut candidates): (Vec<_>, Vec<_>) =
+        tracks.partition(|(t, _)| Some(t.as_str()) == last);
+    // sorted first so the result only depends on the rng, not the map order
+    candidates.sort_by(|a, b| a.0.cmp(b.0));
+    candidates.shuffle(rng);
+    match strategy {
+        FillStrategy::Random => (),
+        FillStrategy::LeastRecentlyPlayed => {
+            candidates.sort_by_key(|(t, _)| last_played.get(t.as_str()).copied().unwrap_or(0))
+        }
+        FillStrategy::SameGenre => {
+            let genre = previous
+                .first()
+                .and_then(|(_, info)| info.genre.as_ref())
+                .map(|v| v.to_lowercase());
+            // matching genre first, the rest keeps its random order
+            candidates.sort_by_key(|(_, info)| {
+                genre.is_none() || info.genre.as_ref().map(|v| v.to_lowercase()) != genre
+            });
+        }
+    }
+    candidates
+        .into_iter()
+        .take(count)
+        .map(|(t, _)| t.clone())
+        .collect()
+}
+
+/// Relative BPM difference still counted as similar
+const BPM_TOLERANCE: f32 = 0.08;
+
+/// Up to `count` tracks similar to `seed`, most similar first and ties in random order.
+/// Same artist weighs most, then artists of `similar_artists` and the genre, then the BPM.
+/// Tracks in `skip` and ones without anything in common are left out.
+pub fn similar<'a>(
+    seed: &TrackInfo,
+    similar_artists: &[String],
+    tracks: impl Iterator<Item = (&'a String, &'a TrackInfo)>,
+    skip: &HashSet<String>,
+    count: usize,
+    rng: &mut impl Rng,
+) -> Vec<String> {
+    let lower = |v: &Option<String>| v.as_ref().map(|v| v.to_lowercase());
+    let artist = lower(&seed.artist);
+    let genre = lower(&seed.genre);
+    let similar_artists: Vec<String> = similar_artists.iter().map(|v| v.to_lowercase()).collect();
+    let score = |info: &TrackInfo| {
+        let mut score = 0;
+        let track_artist = lower(&info.artist);
+        if artist.is_some() && track_artist == artist {
+            score += 4;
+        } else if track_artist.is_some_and(|v| similar_artists.contains(&v)) {
+            score += 3;
+        }
+        if genre.is_some() && lower(&info.genre) == genre {
+            score += 2;
+        }
+        if let (Some(a), Some(b)) = (seed.bpm, info.bpm) {
+            if (a as f32 - b as f32).abs() <= a as f32 * BPM_TOLERANCE {
+                score += 1;
+            }
+        }
+        score
+    };
+    let mut scored: Vec<_> = tracks
+        .filter(|(t, _)| !skip.contains(*t))
+        .map(|(t, info)| (t, score(info)))
+        .filter(|(_, score)| *score > 0)
+        .collect();
+    scored.sort_by(|a, b| a.0.cmp(b.0));
+    scored.shuffle(rng);
+    scored.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+    scored
+        .into_iter()
+        .take(count)
+        .map(|(t, _)| t.clone())
+        .collect()
+}
+
+/// Upper case file extension of `track`
+pub fn format(track: &str) -> Option<String> {
+    Path::new(track)
+        .extension()
+        .and_then(|v| v.to_str())
+        .map(str::to_ascii_uppercase)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn info(artist: &str, album: Option<&str>) -> TrackInfo {
+        TrackInfo {
+            artist: Some(artist.to_string()),
+            album: album.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_albums() {
+        let tracks = [
+            (String::from("/b/2.mp3"), info("Band", Some("Second"))),
+            (String::from("/a/2.mp3"), info("band", Some("first"))),
+            (String::from("/a/1.mp3"), info("Band", Some("First"))),
+            (String::from("/c/1.mp3"), info("Band", None)),
+            (String::from("/d/1.mp3"), info("Another", Some("Album"))),
+        ];
+        let albums = albums(tracks.iter().map(|(t, i)| (t, i)));
+        assert_eq!(albums.len(), 3);
+        assert_eq!(albums[0].artist, "Another");
+        assert_eq!(albums[1].title, "first");
+        assert_eq!(albums[1].tracks, vec!["/a/1.mp3", "/a/2.mp3"]);
+        assert_eq!(albums[2].tracks, vec!["/b/2.mp3"]);
+
+        let band = artist("BAND", tracks.iter().map(|(t, i)| (t, i)));
+        assert_eq!(band.albums.len(), 2);
+        assert_eq!(
+            band.tracks,
+            vec!["/a/1.mp3", "/a/2.mp3", "/b/2.mp3", "/c/1.mp3"]
+        );
+    }
+
+    #[test]
+    fn test_search() {
+        let mut rock = info("Band", Some("First"));
+        rock.genre = Some(String::from("Rock"));
+        rock.year = Some(1994);
+        let tracks = [
+            (String::from("/a/1.mp3"), rock.clone()),
+            (String::from("/a/2.flac"), rock),
+            (String::from("/b/1.mp3"), info("Other", None)),
+        ];
+        let search = |facets: &Facets| search(facets, tracks.iter().map(|(t, i)| (t, i)));
+        let mut facets = Facets {
+            genre: Some(String::from("rock")),
+            decade: Some(1990),
+            ..Default::default()
+        };
+        assert_eq!(search(&facets), vec!["/a/1.mp3", "/a/2.flac"]);
+        facets.format = Some(String::from("FLAC"));
+        assert_eq!(search(&facets), vec!["/a/2.flac"]);
+        let facets = Facets {
+            text: String::from("oth"),
+            ..Default::default()
+        };
+        assert_eq!(search(&facets), vec!["/b/1.mp3"]);
+
+        let values = facet_values(tracks.iter().map(|(t, i)| (t, i)));
+        assert_eq!(values.genres, vec!["Rock"]);
+        assert_eq!(values.decades, vec![1990]);
+        assert_eq!(values.formats, vec!["FLAC", "MP3"]);
+    }
+
+    #[test]
+    fn test_recently_added() {
+        let info = |album: Option<&str>, modified| TrackInfo {
+            album: album.map(String::from),
+            modified: Some(modified),
+            ..Default::default()
+        };
+        let tracks = [
+            (String::from("/old/1.mp3"), info(Some("Old"), 10)),
+            (String::from("/new/1.mp3"), info(Some("New"), 100)),
+            (String::from("/rip/1.mp3"), info(None, 200)),
+            (String::from("/rip/2.mp3"), info(None, 50)),
+        ];
+        let recent = recently_added(tracks.iter().map(|(t, i)| (t, i)), 50);
+        let titles: Vec<_> = recent.iter().map(|a| a.title.as_str()).collect();
+        assert_eq!(titles, vec!["rip", "New"]);
+        assert_eq!(recent[0].tracks, vec!["/rip/1.mp3", "/rip/2.mp3"]);
+    }
+
+    #[test]
+    fn test_fill() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let genre = |v: &str| TrackInfo {
+            genre: Some(v.to_string()),
+            ..Default::default()
+        };
+        let tracks = [
+            (String::from("/1.mp3"), genre("Jazz")),
+            (String::from("/2.mp3"), genre("Rock")),
+            (String::from("/3.mp3"), genre("rock")),
+            (String::from("/4.mp3"), genre("Pop")),
+        ];
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut fill = |strategy, last, played: &HashMap<String, u64>, count| {
+            fill(
+                strategy,
+                tracks.iter().map(|(t, i)| (t, i)),
+                last,
+                played,
+                count,
+                &mut rng,
+            )
+        };
+        let none = HashMap::new();
+        let random = fill(FillStrategy::Random, Some("/1.mp3"), &none, 10);
+        assert_eq!(random.len(), 3);
+        assert!(!random.contains(&String::from("/1.mp3")));
+
+        let same = fill(FillStrategy::SameGenre, Some("/2.mp3"), &none, 1);
+        assert_eq!(same, vec!["/3.mp3"]);
+
+        let played: HashMap<String, u64> = [("/1.mp3", 5), ("/2.mp3", 1), ("/3.mp3", 9)]
+            .iter()
+            .map(|(t, v)| (t.to_string(), *v))
+            .collect();
+        let least = fill(FillStrategy::LeastRecentlyPlayed, None, &played, 3);
+        assert_eq!(least, vec!["/4.mp3", "/2.mp3", "/1.mp3"]);
+    }
+
+    #[test]
+    fn test_similar() {
+        let track = |artist: &str, genre: &str, bpm| TrackInfo {
+            artist: Some(artist.to_string()),
+            genre: Some(genre.to_string()),
+            bpm,
+            ..Default::default()
+        };
+        let seed = track("Band", "Rock", Some(120));
+        let tracks = [
+            (String::from("/seed.mp3"), seed.clone()),
+            (String::from("/same.mp3"), track("band", "Pop", None)),
+            (String::from("/friend.mp3"), track("Friends", "Jazz", None)),
+            (
+                String::from("/genre.mp3"),
+                track("Other", "rock", Some(200)),
+            ),
+            (
+                String::from("/tempo.mp3"),
+                track("Other", "Jazz", Some(125)),
+            ),
+            (String::from("/none.mp3"), track("Other", "Jazz", Some(60))),
+        ];
+        let skip: HashSet<String> = std::iter::once(String::from("/seed.mp3")).collect();
+        let mut rng = rand::thread_rng();
+        let found = similar(
+            &seed,
+            &[String::from("FRIENDS")],
+            tracks.iter().map(|(t, i)| (t, i)),
+            &skip,
+            10,
+            &mut rng,
+        );
+        assert_eq!(
+            found,
+            vec!["/same.mp3", "/friend.mp3", "/genre.mp3", "/tempo.mp3"]
+        );
+    }
+}
@@ -0,0 +1,276 @@
+//! Track tags and lengths read from the files.
+//! Large playlists are read on a bounded worker pool, results arrive in batches.
+
+use std::{
+    collections::HashSet,
+    convert::TryFrom,
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver, Sender, TryIter},
+    thread,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use id3::TagLike;
+use rayon::prelude::*;
+
+use crate::prelude::*;
+
+/// Tracks read before their results are sent
+const BATCH: usize = 64;
+/// Upper bound of parallel reads, more only compete for the disk
+const MAX_WORKERS: usize = 8;
+/// Tag marking explicit content with "1"
+const ADVISORY: &str = "ITUNESADVISORY";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrackInfo {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub genre: Option<String>,
+    pub year: Option<u16>,
+    /// Beats per minute from the tags
+    pub bpm: Option<u16>,
+    /// Marked as explicit by the iTunes advisory tag
+    pub explicit: bool,
+    pub length: Option<Duration>,
+    /// File modification time in unix seconds, when a rip was added to the library
+    pub modified: Option<u64>,
+}
+
+impl TrackInfo {
+    /// "Artist - Title", None without a title tag
+    pub fn label(&self) -> Option<String> {
+        let title = self.title.as_ref()?;
+        Some(match self.artist {
+            Some(ref artist) => format!("{} - {}", artist, title),
+            None => title.clone(),
+        })
+    }
+}
+
+/// Tags and length of a local file, remote tracks aren't downloaded
+pub fn read(track: &str) -> TrackInfo {
+    let path = Path::new(track);
+    let ext = path
+        .extension()
+        .and_then(|v| v.to_str())
+        .map(str::to_ascii_lowercase);
+    let tags = match ext.as_deref() {
+        Some("mp3") => read_id3(path),
+        Some("flac") => read_flac(path),
+        Some("ogg" | "oga" | "opus") => read_ogg(path),
+        Some("wv" | "ape" | "tta") => read_probe(path),
+        _ => Ok(TrackInfo::default()),
+    };
+    let mut info = tags.unwrap_or_else(|e| {
+        trace!("Can't read tags of {}: {}", track, e);
+        TrackInfo::default()
+    });
+    info.length = crate::durations::probe(track);
+    info.modified = fs::metadata(path)
+        .and_then(|v| v.modified())
+        .ok()
+        .and_then(|v| v.duration_since(UNIX_EPOCH).ok())
+        .map(|v| v.as_secs());
+    info
+}
+
+fn read_id3(path: &Path) -> Result<TrackInfo> {
+    let tag = id3::Tag::read_from_path(path)?;
+    let explicit = tag
+        .extended_texts()
+        .any(|v| v.description.eq_ignore_ascii_case(ADVISORY) && v.value.trim() == "1");
+    Ok(TrackInfo {
+        title: tag.title().map(String::from),
+        artist: tag.artist().map(String::from),
+        album: tag.album().map(String::from),
+        genre: tag.genre_parsed().map(|v| v.into_owned()),
+        year: tag
+            .year()
+            .or_else(|| tag.date_recorded().map(|v| v.year))
+            .and_then(|v| u16::try_from(v).ok()),
+        bpm: tag
+            .get("TBPM")
+            .and_then(|v| v.content().text())
+            .and_then(parse_bpm),
+        explicit,
+        ..Default::default()
+    })
+}
+
+fn read_flac(path: &Path) -> Result<TrackInfo> {
+    let reader = claxon::FlacReader::open(path)?;
+    let tag = |name| reader.get_tag(name).next().map(String::from);
+    Ok(TrackInfo {
+        title: tag("TITLE"),
+        artist: tag("ARTIST"),
+        album: tag("ALBUM"),
+        genre: tag("GENRE"),
+        year: tag("DATE").as_deref().and_then(parse_year),
+        bpm: tag("BPM").as_deref().and_then(parse_bpm),
+        explicit: tag(ADVISORY).is_some_and(|v| v.trim() == "1"),
+        ..Default::default()
+    })
+}
+
+fn read_ogg(path: &Path) -> Result<TrackInfo> {
+    let headers = crate::ogg::headers(path)?;
+    let tag = |name: &str| headers.comment(name).map(String::from);
+    Ok(TrackInfo {
+        title: tag("TITLE"),
+        artist: tag("ARTIST"),
+        album: tag("ALBUM"),
+        genre: tag("GENRE"),
+        year: tag("DATE").as_deref().and_then(parse_year),
+        bpm: tag("BPM").as_deref().and_then(parse_bpm),
+        explicit: tag(ADVISORY).is_some_and(|v| v.trim() == "1"),
+        ..Default::default()
+    })
+}
+
+/// APE tags and others read by ffprobe
+fn read_probe(path: &Path) -> Result<TrackInfo> {
+    let probe = crate::decoders::probe(path)?;
+    let tag = |name: &str| probe.tag(name).map(String::from);
+    Ok(TrackInfo {
+        title: tag("title"),
+        artist: tag("artist"),
+        album: tag("album"),
+        genre: tag("genre"),
+        year: tag("date")
+            .or_else(|| tag("year"))
+            .as_deref()
+            .and_then(parse_year),
+        bpm: tag("bpm").as_deref().and_then(parse_bpm),
+        explicit: tag(ADVISORY).is_some_and(|v| v.trim() == "1"),
+        ..Default::default()
+    })
+}
+
+/// Year at the start of a date tag like "1997" or "1997-05-12"
+fn parse_year(date: &str) -> Option<u16> {
+    date.trim().get(..4)?.parse().ok()
+}
+
+/// BPM tags may be fractional like "120.5"
+fn parse_bpm(bpm: &str) -> Option<u16> {
+    let bpm: f32 = bpm.trim().parse().ok()?;
+    Some(bpm.round() as u16).filter(|v| *v > 0)
+}
+
+enum Job {
+    Tracks(Vec<String>),
+    /// Library folders to scan, skipping tracks already read
+    Folders(Vec<PathBuf>, HashSet<String>),
+}
+
+/// Reads metadata of queued tracks on a worker pool
+pub struct Extractor {
+    tx: Sender<Job>,
+    rx: Receiver<Vec<(String, TrackInfo)>>,
+}
+
+impl Extractor {
+    pub fn new() -> Result<Self> {
+        let workers = thread::available_parallelism().map_or(2, |v| v.get().min(MAX_WORKERS));
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(workers)
+            .thread_name(|i| format!("metadata {}", i))
+            .build()?;
+        let (tx, jobs) = channel::<Job>();
+        let (batch_tx, rx) = channel();
+        thread::Builder::new()
+            .name("metadata".into())
+            .spawn(move || {
+                for job in jobs {
+                    let tracks = match job {
+                        Job::Tracks(v) => v,
+                        Job::Folders(folders, known) => {
+                            let mut found = crate::library::scan(&folders);
+                            found.retain(|t| !known.contains(t));
+                            found
+                        }
+                    };
+                    for chunk in tracks.chunks(BATCH) {
+                        let batch = pool
+                            .install(|| chunk.par_iter().map(|t| (t.clone(), read(t))).collect());
+                        if batch_tx.send(batch).is_err() {
+                            return;
+                        }
+                    }
+                }
+            })
+            .wrap_err("Can't start metadata extraction")?;
+        Ok(Self { tx, rx })
+    }
+
+    /// Read `tracks` in the background, in order
+    pub fn extract(&self, tracks: Vec<String>) {
+        if !tracks.is_empty() {
+            let _ = self.tx.send(Job::Tracks(tracks));
+        }
+    }
+
+    /// Read all audio files below `folders` in the background, except the `known` ones
+    pub fn scan(&self, folders: Vec<PathBuf>, known: HashSet<String>) {
+        if !folders.is_empty() {
+            let _ = self.tx.send(Job::Folders(folders, known));
+        }
+    }
+
+    /// Batches read since the last call
+    pub fn batches(&self) -> TryIter<'_, Vec<(String, TrackInfo)>> {
+        self.rx.try_iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_extractor() {
+        let extractor = Extractor::new().unwrap();
+        let tracks: Vec<String> = (0..100).map(|i| format!("/missing/{}.mp3", i)).collect();
+        extractor.extract(tracks.clone());
+        let mut read = Vec::new();
+        while read.len() < tracks.len() {
+            let batch = extractor.rx.recv().unwrap();
+            assert!(batch.len() <= BATCH);
+            read.extend(batch);
+        }
+        // batches keep the order, missing files have no metadata
+        assert_eq!(
+            read.iter().map(|(t, _)| t).collect::<Vec<_>>(),
+            tracks.iter().collect::<Vec<_>>()
+        );
+        assert!(read.iter().all(|(_, v)| *v == TrackInfo::default()));
+    }
+
+    #[test]
+    fn test_parse_year() {
+        assert_eq!(parse_year("1997-05-12"), Some(1997));
+        assert_eq!(parse_year(" 2001"), Some(2001));
+        assert_eq!(parse_year("97"), None);
+    }
+
+    #[test]
+    fn test_parse_bpm() {
+        assert_eq!(parse_bpm("120.5"), Some(121));
+        assert_eq!(parse_bpm(" 95 "), Some(95));
+        assert_eq!(parse_bpm("0"), None);
+        assert_eq!(parse_bpm("fast"), None);
+    }
+
+    #[test]
+    fn test_label() {
+        let mut info = TrackInfo::default();
+        assert_eq!(info.label(), None);
+        info.title = Some(String::from("Song"));
+        assert_eq!(info.label().unwrap(), "Song");
+        info.artist = Some(String::from("Band"));
+        assert_eq!(info.label().unwrap(), "Band - Song");
+    }
+}
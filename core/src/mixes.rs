@@ -0,0 +1,189 @@
+//! Auto playlists rotating once a day, generated from play statistics and favorites
+
+use std::collections::{HashMap, HashSet};
+
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use crate::metadata::TrackInfo;
+use crate::store::Plays;
+
+const DAY: u64 = 24 * 60 * 60;
+/// Hour of the day in UTC the mixes change at
+const NEW_DAY_HOUR: u64 = 6;
+/// Favorites not played for this long are rediscovered
+pub const REDISCOVER_AFTER: u64 = 90 * DAY;
+/// Tracks per mix
+pub const MIX_LENGTH: usize = 50;
+/// Daily mixes generated, one per most played genre
+pub const DAILY_MIXES: usize = 3;
+
+/// Number of the mix day at unix time `now`, mixes stay the same within a day
+pub fn day(now: u64) -> u64 {
+    now.saturating_sub(NEW_DAY_HOUR * 60 * 60) / DAY
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MixKind {
+    /// Favorites not played recently
+    Rediscover,
+    /// Played and new tracks of one of the most played genres, numbered from 1
+    Daily { number: usize, genre: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mix {
+    pub kind: MixKind,
+    pub tracks: Vec<String>,
+}
+
+/// Mixes of `day`, empty mixes are left out
+pub fn generate(
+    day: u64,
+    now: u64,
+    favorites: &HashSet<&String>,
+    metadata: &HashMap<String, TrackInfo>,
+    plays: &HashMap<String, Plays>,
+) -> Vec<Mix> {
+    let mut mixes = Vec::new();
+    let rediscover = rediscover(day, now, favorites, plays);
+    if !rediscover.is_empty() {
+        mixes.push(Mix {
+            kind: MixKind::Rediscover,
+            tracks: rediscover,
+        });
+    }
+    for (i, (genre, tracks)) in top_genres(metadata, favorites, plays)
+        .into_iter()
+        .enumerate()
+    {
+        let mut rng = StdRng::seed_from_u64(day.wrapping_add(i as u64 + 1));
+        let (mut played, mut fresh): (Vec<_>, Vec<_>) = tracks
+            .into_iter()
+            .partition(|t| plays.contains_key(t.as_str()));
+        played.sort();
+        fresh.sort();
+        played.shuffle(&mut rng);
+        fresh.shuffle(&mut rng);
+        // half known tracks, the rest to discover, filled up by whichever has more
+        let known = (MIX_LENGTH / 2).max(MIX_LENGTH.saturating_sub(fresh.len()));
+        let mut tracks: Vec<String> = played.into_iter().take(known).collect();
+        tracks.extend(fresh.into_iter().take(MIX_LENGTH - tracks.len()));
+        tracks.shuffle(&mut rng);
+        mixes.push(Mix {
+            kind: MixKind::Daily {
+                number: i + 1,
+                genre,
+            },
+            tracks,
+        });
+    }
+    mixes
+}
+
+/// Favorites never played or not within [`REDISCOVER_AFTER`]
+fn rediscover(
+    day: u64,
+    now: u64,
+    favorites: &HashSet<&String>,
+    plays: &HashMap<String, Plays>,
+) -> Vec<String> {
+    let since = now.saturating_sub(REDISCOVER_AFTER);
+    let mut tracks: Vec<String> = favorites
+        .iter()
+        .filter(|t| plays.get(t.as_str()).is_none_or(|p| p.last_played < since))
+        .map(|t| (*t).clone())
+        .collect();
+    tracks.sort();
+    tracks.shuffle(&mut StdRng::seed_from_u64(day));
+    tracks.truncate(MIX_LENGTH);
+    tracks
+}
+
+/// Genres with the most plays, favorites count as one play, with all their tracks
+fn top_genres(
+    metadata: &HashMap<String, TrackInfo>,
+    favorites: &HashSet<&String>,
+    plays: &HashMap<String, Plays>,
+) -> Vec<(String, Vec<String>)> {
+    // by lower case name, keeping the first spelling seen
+    let mut genres: HashMap<String, (String, u64, Vec<String>)> = HashMap::new();
+    for (track, info) in metadata {
+        let genre = match info.genre {
+            Some(ref v) if !v.trim().is_empty() => v,
+            _ => continue,
+        };
+        let weight = plays.get(track).map_or(0, |p| p.count) + favorites.contains(track) as u64;
+        let entry = genres
+            .entry(genre.to_lowercase())
+            .or_insert_with(|| (genre.clone(), 0, Vec::new()));
+        entry.1 += weight;
+        entry.2.push(track.clone());
+    }
+    let mut genres: Vec<_> = genres.into_values().filter(|(_, w, _)| *w > 0).collect();
+    genres.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    genres
+        .into_iter()
+        .take(DAILY_MIXES)
+        .map(|(name, _, tracks)| (name, tracks))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_generate() {
+        let genre = |v: &str| TrackInfo {
+            genre: Some(v.to_string()),
+            ..Default::default()
+        };
+        let mut metadata = HashMap::new();
+        for i in 0..10 {
+            metadata.insert(format!("/rock/{}.mp3", i), genre("Rock"));
+            metadata.insert(format!("/jazz/{}.mp3", i), genre("Jazz"));
+        }
+        metadata.insert(String::from("/pop/1.mp3"), genre("Pop"));
+        let now = 1000 * DAY;
+        let plays: HashMap<String, Plays> = [
+            ("/rock/1.mp3", 5, now),
+            ("/jazz/1.mp3", 1, now - REDISCOVER_AFTER - 1),
+        ]
+        .iter()
+        .map(|(t, count, last_played)| {
+            let plays = Plays {
+                count: *count,
+                last_played: *last_played,
+            };
+            (t.to_string(), plays)
+        })
+        .collect();
+        let rock = String::from("/rock/1.mp3");
+        let jazz = String::from("/jazz/1.mp3");
+        let never = String::from("/pop/1.mp3");
+        let favorites: HashSet<&String> = [&rock, &jazz, &never].iter().copied().collect();
+
+        let mixes = generate(day(now), now, &favorites, &metadata, &plays);
+        assert_eq!(mixes.len(), 4);
+        assert_eq!(mixes[0].kind, MixKind::Rediscover);
+        let mut rediscovered = mixes[0].tracks.clone();
+        rediscovered.sort();
+        assert_eq!(rediscovered, vec!["/jazz/1.mp3", "/pop/1.mp3"]);
+        let genres: Vec<_> = mixes[1..]
+            .iter()
+            .map(|m| match m.kind {
+                MixKind::Daily { ref genre, .. } => genre.as_str(),
+                MixKind::Rediscover => "",
+            })
+            .collect();
+        assert_eq!(genres, vec!["Rock", "Jazz", "Pop"]);
+        assert_eq!(mixes[1].tracks.len(), 10);
+        assert!(mixes[1].tracks.contains(&rock));
+
+        // stable within a day
+        assert_eq!(
+            generate(day(now + 60), now + 60, &favorites, &metadata, &plays),
+            mixes
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! Phones and players connected over MTP, which don't mount as drives. Linux only,
+//! Windows and macOS have no MTP mounts to list.
+//! GVFS mounts them below `/run/user/<uid>/gvfs`, files are listed through that mount
+//! but written with `gio`, since writing through the FUSE mount fails on many devices.
+
+use std::{
+    path::{Component, Path, PathBuf},
+    process::Command,
+};
+
+use url::Url;
+
+use crate::prelude::*;
+
+/// Name prefix of MTP mounts in the GVFS folder
+const MOUNT_PREFIX: &str = "mtp:host=";
+
+/// Storage of a connected device, e.g. the internal storage or an SD card of a phone
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Storage {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl std::fmt::Display for Storage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+/// Storages of all connected MTP devices
+#[cfg(target_os = "linux")]
+pub fn storages() -> Vec<Storage> {
+    use std::os::unix::fs::MetadataExt;
+
+    let uid = match std::fs::metadata("/proc/self") {
+        Ok(v) => v.uid(),
+        Err(e) => {
+            warn!("Can't find own user id: {}", e);
+            return Vec::new();
+        }
+    };
+    let gvfs = PathBuf::from(format!("/run/user/{}/gvfs", uid));
+    let mut storages = Vec::new();
+    for device in read_dir(&gvfs) {
+        let host = match device.file_name().and_then(|v| v.to_str()) {
+            Some(v) if v.starts_with(MOUNT_PREFIX) => v[MOUNT_PREFIX.len()..].replace('_', " "),
+            _ => continue,
+        };
+        for path in read_dir(&device) {
+            let storage = path.file_name().unwrap_or_default().to_string_lossy();
+            storages.push(Storage {
+                name: format!("{} - {}", host, storage),
+                path,
+            });
+        }
+    }
+    storages.sort();
+    storages
+}
+
+/// No devices, MTP is only supported through GVFS
+#[cfg(not(target_os = "linux"))]
+pub fn storages() -> Vec<Storage> {
+    Vec::new()
+}
+
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn read_dir(path: &Path) -> Vec<PathBuf> {
+    match std::fs::read_dir(path) {
+        Ok(v) => v.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+        Err(e) => {
+            debug!("Can't list {:?}: {}", path, e);
+            Vec::new()
+        }
+    }
+}
+
+/// `mtp://` URI of a path in a GVFS MTP mount, None for other paths
+pub fn uri(path: &Path) -> Option<Url> {
+    let mut components = path.components();
+    let host = components.find_map(|c| match c {
+        Component::Normal(v) => v.to_str()?.strip_prefix(MOUNT_PREFIX),
+        _ => None,
+    })?;
+    let mut url = Url::parse(&format!("mtp://{}/", host)).ok()?;
+    url.path_segments_mut()
+        .ok()?
+        .pop_if_empty()
+        .extend(components.map(|c| c.as_os_str().to_string_lossy()));
+    Some(url)
+}
+
+fn gio(args: &[&str]) -> Result<String> {
+    let output = Command::new("gio")
+        .args(args)
+        .output()
+        .wrap_err("Can't run gio")?;
+    if !output.status.success() {
+        return Err(eyre!(
+            "gio {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Copy the local file `source` to `target` on the device
+pub fn put(source: &Path, target: &Url) -> Result<()> {
+    gio(&["copy", &source.to_string_lossy(), target.as_str()]).map(|_| ())
+}
+
+/// Move `source` to `target` on the device, replacing an existing file
+pub fn rename(source: &Url, target: &Url) -> Result<()> {
+    gio(&["move", source.as_str(), target.as_str()]).map(|_| ())
+}
+
+pub fn create_dir_all(target: &Url) -> Result<()> {
+    gio(&["mkdir", "-p", target.as_str()]).map(|_| ())
+}
+
+/// Free bytes of the storage of `target`, None if the device doesn't tell
+pub fn free_space(target: &Url) -> Option<u64> {
+    match gio(&["info", "-f", target.as_str()]) {
+        Ok(v) => parse_free(&v),
+        Err(e) => {
+            warn!("{:?}", e);
+            None
+        }
+    }
+}
+
+/// Free bytes in `gio info -f` output
+fn parse_free(output: &str) -> Option<u64> {
+    output
+        .lines()
+        .find_map(|l| l.trim().strip_prefix("filesystem::free:"))
+        .and_then(|v| v.trim().parse().ok())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_uri() {
+        let path =
+            Path::new("/run/user/1000/gvfs/mtp:host=SAMSUNG_SM-G991B_R5CR/Phone/Music/Car trip");
+        assert_eq!(
+            uri(path).as_ref().map(Url::as_str),
+            Some("mtp://SAMSUNG_SM-G991B_R5CR/Phone/Music/Car%20trip")
+        );
+        assert_eq!(uri(Path::new("/media/stick/Music")), None);
+        let output = "attributes:\n  filesystem::size: 64000000000\n  filesystem::free: 12000000000\n  filesystem::type: mtpfs\n";
+        assert_eq!(parse_free(output), Some(12_000_000_000));
+        assert_eq!(parse_free("attributes:\n"), None);
+    }
+}
@@ -0,0 +1,50 @@
+//! Free-text notes attached to tracks
+
+use std::{collections::HashMap, fs, path::Path};
+
+use crate::prelude::*;
+
+/// Write all notes as CSV with a track and a note column, sorted by track
+pub fn write_csv(notes: &HashMap<String, String>, path: &Path) -> Result<()> {
+    fs::write(path, render_csv(notes)).wrap_err_with(|| format!("Can't write {:?}", path))
+}
+
+fn render_csv(notes: &HashMap<String, String>) -> String {
+    let mut tracks: Vec<_> = notes.iter().collect();
+    tracks.sort();
+    let mut csv = String::from("track,note\n");
+    for (track, note) in tracks {
+        csv.push_str(&escape(track));
+        csv.push(',');
+        csv.push_str(&escape(note));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Quote fields containing separators, quotes or line breaks
+pub(crate) fn escape(field: &str) -> String {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_render_csv() {
+        let mut notes = HashMap::new();
+        notes.insert(
+            String::from("b.mp3"),
+            String::from("clips at 2:31, \"loud\""),
+        );
+        notes.insert(String::from("a.mp3"), String::from("ask about rights"));
+        assert_eq!(
+            render_csv(&notes),
+            "track,note\na.mp3,ask about rights\nb.mp3,\"clips at 2:31, \"\"loud\"\"\"\n"
+        );
+    }
+}
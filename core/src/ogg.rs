@@ -0,0 +1,345 @@
+//! Tags, chapters and lengths of Ogg Vorbis and Opus files.
+//! Only the header packets and the last pages are read, the length comes from the granule
+//! position of the last page, which the decoders don't report.
+
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::Path,
+    time::Duration,
+};
+
+use crate::cues::Cue;
+use crate::prelude::*;
+
+/// Page header size before the segment table
+const HEADER_LEN: usize = 27;
+/// Bytes read from the end of the file to find the last page
+const TAIL_LEN: u64 = 128 * 1024;
+/// Header packets larger than this are considered broken, cover art can be a few MB
+const MAX_PACKET: usize = 32 * 1024 * 1024;
+/// Opus granule positions count samples at 48 kHz
+const OPUS_RATE: u32 = 48_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    Vorbis,
+    Opus,
+}
+
+/// Headers of the first logical stream of an Ogg file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Headers {
+    pub codec: Codec,
+    serial: u32,
+    /// Samples per second of the granule positions
+    rate: u32,
+    /// Opus samples to skip at the start
+    pre_skip: u64,
+    /// Vorbis comments as key and value
+    pub comments: Vec<(String, String)>,
+}
+
+impl Headers {
+    /// First comment with `key`, ignoring case
+    pub fn comment(&self, key: &str) -> Option<&str> {
+        self.comments
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Chapters of the Vorbis chapter extension, `CHAPTER001=00:01:02.500` and
+    /// `CHAPTER001NAME=Intro`, in order
+    pub fn chapters(&self) -> Vec<Cue> {
+        let mut chapters: Vec<(u32, Cue)> = self
+            .comments
+            .iter()
+            .filter_map(|(k, v)| {
+                let number = k
+                    .to_ascii_uppercase()
+                    .strip_prefix("CHAPTER")?
+                    .parse()
+                    .ok()?;
+                let position = parse_timestamp(v)?;
+                let label = self
+                    .comment(&format!("{}NAME", k))
+                    .map(String::from)
+                    .unwrap_or_else(|| format!("{}", number));
+                Some((number, Cue { position, label }))
+            })
+            .collect();
+        chapters.sort_by_key(|(number, _)| *number);
+        chapters.into_iter().map(|(_, cue)| cue).collect()
+    }
+}
+
+/// Whether `path` has the extension of an Ogg Vorbis or Opus file
+pub fn is_ogg(path: &Path) -> bool {
+    path.extension()
+        .and_then(|v| v.to_str())
+        .is_some_and(|ext| {
+            ["ogg", "oga", "opus"]
+                .iter()
+                .any(|v| v.eq_ignore_ascii_case(ext))
+        })
+}
+
+/// Read the headers of the Ogg file at `path`
+pub fn headers(path: &Path) -> Result<Headers> {
+    let mut reader = BufReader::new(File::open(path)?);
+    read_headers(&mut reader)
+}
+
+/// Length of the Ogg file at `path`
+pub fn length(path: &Path) -> Result<Duration> {
+    let mut file = BufReader::new(File::open(path)?);
+    let headers = read_headers(&mut file)?;
+    let end = file.seek(SeekFrom::End(0))?;
+    let start = end.saturating_sub(TAIL_LEN);
+    file.seek(SeekFrom::Start(start))?;
+    let mut tail = Vec::with_capacity((end - start) as usize);
+    file.read_to_end(&mut tail)?;
+    let granule =
+        last_granule(&tail, headers.serial).ok_or_else(|| eyre!("No last page in {:?}", path))?;
+    let samples = granule.saturating_sub(headers.pre_skip);
+    Ok(Duration::from_secs_f64(
+        samples as f64 / headers.rate as f64,
+    ))
+}
+
+struct Page {
+    serial: u32,
+    granule: u64,
+    /// Lengths of the segments, a segment below 255 bytes ends a packet
+    segments: Vec<u8>,
+    body: Vec<u8>,
+}
+
+fn read_page<R: Read>(reader: &mut R) -> Result<Page> {
+    let mut header = [0u8; HEADER_LEN];
+    reader.read_exact(&mut header)?;
+    if &header[0..4] != b"OggS" {
+        return Err(eyre!("Not an Ogg page"));
+    }
+    let mut segments = vec![0u8; header[26] as usize];
+    reader.read_exact(&mut segments)?;
+    let mut body = vec![0u8; segments.iter().map(|v| *v as usize).sum()];
+    reader.read_exact(&mut body)?;
+    Ok(Page {
+        serial: u32_at(&header, 14),
+        granule: u64::from_le_bytes([
+            header[6], header[7], header[8], header[9], header[10], header[11], header[12],
+            header[13],
+        ]),
+        segments,
+        body,
+    })
+}
+
+/// Identification and comment packet of the first stream
+fn read_headers<R: Read>(reader: &mut R) -> Result<Headers> {
+    let mut serial = None;
+    let mut packets: Vec<Vec<u8>> = Vec::new();
+    let mut packet = Vec::new();
+    while packets.len() < 2 {
+        let page = read_page(reader)?;
+        // other streams like a video track are interleaved
+        if *serial.get_or_insert(page.serial) != page.serial {
+            continue;
+        }
+        let mut pos = 0;
+        for len in page.segments {
+            packet.extend_from_slice(&page.body[pos..pos + len as usize]);
+            pos += len as usize;
+            if len < 255 {
+                packets.push(std::mem::take(&mut packet));
+            }
+        }
+        if packet.len() > MAX_PACKET {
+            return Err(eyre!("Ogg header packet too large"));
+        }
+    }
+    let (id, comments) = (&packets[0], &packets[1]);
+    let (codec, rate, pre_skip, comments) = if id.starts_with(b"\x01vorbis") && id.len() >= 16 {
+        let comments = comments
+            .strip_prefix(b"\x03vorbis")
+            .ok_or_else(|| eyre!("Missing Vorbis comments"))?;
+        (Codec::Vorbis, u32_at(id, 12), 0, comments)
+    } else if id.starts_with(b"OpusHead") && id.len() >= 19 {
+        let comments = comments
+            .strip_prefix(b"OpusTags")
+            .ok_or_else(|| eyre!("Missing Opus tags"))?;
+        let pre_skip = u16::from_le_bytes([id[10], id[11]]) as u64;
+        (Codec::Opus, OPUS_RATE, pre_skip, comments)
+    } else {
+        return Err(eyre!("Neither Vorbis nor Opus"));
+    };
+    if rate == 0 {
+        return Err(eyre!("Invalid sample rate"));
+    }
+    Ok(Headers {
+        codec,
+        serial: serial.unwrap_or_default(),
+        rate,
+        pre_skip,
+        comments: parse_comments(comments).ok_or_else(|| eyre!("Invalid comments"))?,
+    })
+}
+
+/// Vorbis comment block: vendor string, count and `KEY=value` entries
+fn parse_comments(data: &[u8]) -> Option<Vec<(String, String)>> {
+    let vendor = u32_at(data.get(0..4)?, 0) as usize;
+    let mut pos = 4 + vendor;
+    let count = u32_at(data.get(pos..pos + 4)?, 0);
+    pos += 4;
+    let mut comments = Vec::new();
+    for _ in 0..count {
+        let len = u32_at(data.get(pos..pos + 4)?, 0) as usize;
+        pos += 4;
+        let comment = String::from_utf8_lossy(data.get(pos..pos + len)?);
+        pos += len;
+        if let Some((key, value)) = comment.split_once('=') {
+            comments.push((key.to_string(), value.to_string()));
+        }
+    }
+    Some(comments)
+}
+
+/// Granule position of the last page of stream `serial` in the end of a file
+fn last_granule(tail: &[u8], serial: u32) -> Option<u64> {
+    (0..tail.len().saturating_sub(HEADER_LEN))
+        .rev()
+        .filter(|i| &tail[*i..*i + 4] == b"OggS")
+        .filter_map(|i| read_page(&mut &tail[i..]).ok())
+        // -1 marks pages without a finished packet
+        .find(|page| page.serial == serial && page.granule != u64::MAX)
+        .map(|page| page.granule)
+}
+
+fn u32_at(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+}
+
+/// Chapter start like `01:02:03.500`
+fn parse_timestamp(value: &str) -> Option<Duration> {
+    let mut parts = value.trim().splitn(3, ':');
+    let hours: u64 = parts.next()?.parse().ok()?;
+    let minutes: u64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    if minutes >= 60 || !(0.0..60.0).contains(&seconds) {
+        return None;
+    }
+    Some(Duration::from_secs(hours * 3600 + minutes * 60) + Duration::from_secs_f64(seconds))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn page(serial: u32, granule: u64, packets: &[&[u8]]) -> Vec<u8> {
+        let mut segments = Vec::new();
+        let mut body = Vec::new();
+        for packet in packets {
+            segments.resize(segments.len() + packet.len() / 255, 255);
+            segments.push((packet.len() % 255) as u8);
+            body.extend_from_slice(packet);
+        }
+        let mut page = b"OggS\0\0".to_vec();
+        page.extend_from_slice(&granule.to_le_bytes());
+        page.extend_from_slice(&serial.to_le_bytes());
+        page.extend_from_slice(&[0; 8]);
+        page.push(segments.len() as u8);
+        page.extend(segments);
+        page.extend(body);
+        page
+    }
+
+    fn comments(prefix: &[u8], entries: &[&str]) -> Vec<u8> {
+        let mut data = prefix.to_vec();
+        data.extend_from_slice(&4u32.to_le_bytes());
+        data.extend_from_slice(b"test");
+        data.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+        for entry in entries {
+            data.extend_from_slice(&(entry.len() as u32).to_le_bytes());
+            data.extend_from_slice(entry.as_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn test_opus() {
+        let mut head = b"OpusHead\x01\x02".to_vec();
+        head.extend_from_slice(&312u16.to_le_bytes());
+        head.extend_from_slice(&[0; 7]);
+        let tags = comments(
+            b"OpusTags",
+            &[
+                "TITLE=Episode 1",
+                "CHAPTER002=00:10:00.000",
+                "CHAPTER001=00:00:00.000",
+                "CHAPTER001NAME=Intro",
+                &format!("COMMENT={}", "x".repeat(300)),
+            ],
+        );
+        let mut file = page(7, 0, &[&head]);
+        // a video stream in between
+        file.extend(page(9, 0, &[b"theora"]));
+        file.extend(page(7, 0, &[&tags]));
+        file.extend(page(7, 48_000 * 90 + 312, &[b"audio"]));
+        file.extend(page(9, 1234, &[b"video"]));
+
+        let headers = read_headers(&mut &file[..]).unwrap();
+        assert_eq!(headers.codec, Codec::Opus);
+        assert_eq!(headers.comment("title"), Some("Episode 1"));
+        assert_eq!(
+            headers.chapters(),
+            vec![
+                Cue {
+                    position: Duration::ZERO,
+                    label: String::from("Intro")
+                },
+                Cue {
+                    position: Duration::from_secs(600),
+                    label: String::from("2")
+                }
+            ]
+        );
+        assert_eq!(last_granule(&file, 7), Some(48_000 * 90 + 312));
+
+        let path = std::env::temp_dir().join("audio_wrench_test.opus");
+        std::fs::write(&path, &file).unwrap();
+        assert_eq!(length(&path).unwrap(), Duration::from_secs(90));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_vorbis() {
+        let mut id = b"\x01vorbis\0\0\0\0\x02".to_vec();
+        id.extend_from_slice(&44_100u32.to_le_bytes());
+        id.extend_from_slice(&[0; 14]);
+        let file = [
+            page(
+                1,
+                0,
+                &[&id, &comments(b"\x03vorbis", &["ARTIST=Band", "broken"])],
+            ),
+            page(1, 44_100 * 3, &[b"audio"]),
+        ]
+        .concat();
+        let headers = read_headers(&mut &file[..]).unwrap();
+        assert_eq!(headers.codec, Codec::Vorbis);
+        assert_eq!(headers.rate, 44_100);
+        assert_eq!(
+            headers.comments,
+            vec![(String::from("ARTIST"), String::from("Band"))]
+        );
+        assert!(headers.chapters().is_empty());
+        assert!(read_headers(&mut &b"RIFF...."[..]).is_err());
+        assert_eq!(
+            parse_timestamp("1:02:03.5"),
+            Some(Duration::from_millis(3_723_500))
+        );
+        assert_eq!(parse_timestamp("00:61:00"), None);
+    }
+}
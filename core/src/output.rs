@@ -0,0 +1,349 @@
+use std::{
+    collections::VecDeque,
+    io::{BufWriter, Write},
+    net::TcpStream,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::Sender,
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use rodio::{OutputStream, OutputStreamHandle, Sink};
+use serde::{Deserialize, Serialize};
+
+use crate::dsp::{BoxSource, ChannelMapping, Resampling};
+use crate::jack_output::JackOutput;
+use crate::player::PlayerStatus;
+use crate::prelude::*;
+
+/// Audio routing configuration, output device and additional zones
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AudioRouting {
+    /// Main output device name, system default if unset
+    #[serde(default)]
+    pub device: Option<String>,
+    #[serde(default)]
+    pub resampling: Resampling,
+    #[serde(default)]
+    pub channels: ChannelMapping,
+    pub zones: Vec<Zone>,
+    /// Network audio output, e.g. a Snapcast server
+    #[serde(default)]
+    pub network: Option<NetworkOutput>,
+    /// Main output through a JACK client instead of the output device
+    #[serde(default)]
+    pub jack: Option<JackOutput>,
+    /// Main device buffer size in frames, host default if unset.
+    /// Larger buffers trade latency for less stutter on slow machines or Bluetooth devices.
+    #[serde(default)]
+    pub buffer_frames: Option<u32>,
+    /// Switch to the new system default device when it changes, if no device is set
+    #[serde(default)]
+    pub follow_default: bool,
+}
+
+/// Additional output device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Zone {
+    /// Device name as reported by the audio host
+    pub device: String,
+    pub volume: u8,
+}
+
+pub(crate) struct OutputZone {
+    /// None if the device couldn't be opened, keeps zone indices aligned with the config
+    pub handle: Option<OutputStreamHandle>,
+    pub volume: u8,
+    pub sink: Option<Sink>,
+}
+
+/// Open output device by name, or the default device for None
+pub fn open_output(device: Option<&str>) -> Result<(OutputStream, OutputStreamHandle)> {
+    let host = cpal::default_host();
+    let device = find_device(&host, device)?;
+    Ok(OutputStream::try_from_device(&device)?)
+}
+
+/// Name of the system default output device
+pub fn default_device_name() -> Option<String> {
+    cpal::default_host()
+        .default_output_device()
+        .and_then(|v| v.name().ok())
+}
+
+fn find_device(host: &cpal::Host, name: Option<&str>) -> Result<cpal::Device> {
+    match name {
+        None => host
+            .default_output_device()
+            .ok_or_else(|| eyre!("No default output device")),
+        Some(name) => host
+            .output_devices()?
+            .find(|d| d.name().is_ok_and(|v| v == name))
+            .ok_or_else(|| eyre!("Output device {} not found", name)),
+    }
+}
+
+/// Device buffers decoded ahead of the output callback
+const AHEAD_BUFFERS: usize = 4;
+
+type Frames = Box<dyn Iterator<Item = f32> + Send>;
+
+/// Main output device opened with a fixed buffer size, stops when dropped.
+/// Decoding runs ahead on a separate thread, so the output callback only copies samples
+/// and can count the callbacks it had nothing to copy for.
+pub(crate) struct BufferedStream {
+    _stream: cpal::Stream,
+    /// Played by the feeding thread, replaced per track
+    source: Arc<Mutex<Option<Frames>>>,
+    stop: Arc<AtomicBool>,
+    channels: u16,
+    sample_rate: u32,
+    underruns: Arc<AtomicU64>,
+}
+
+impl BufferedStream {
+    pub fn start(device: Option<&str>, frames: u32) -> Result<Self> {
+        let host = cpal::default_host();
+        let device = find_device(&host, device)?;
+        let supported = device.default_output_config()?;
+        let mut config = supported.config();
+        config.buffer_size = cpal::BufferSize::Fixed(frames);
+        let channels = config.channels;
+        let sample_rate = config.sample_rate.0;
+        let capacity = frames as usize * channels as usize * AHEAD_BUFFERS;
+        let buffer = Arc::new(Mutex::new(VecDeque::with_capacity(capacity)));
+        let source: Arc<Mutex<Option<Frames>>> = Default::default();
+        let underruns = Arc::new(AtomicU64::new(0));
+        let on_error = |e| warn!("Output stream error: {}", e);
+        let stream = match supported.sample_format() {
+            cpal::SampleFormat::F32 => device.build_output_stream(
+                &config,
+                copy_output::<f32>(buffer.clone(), source.clone(), underruns.clone()),
+                on_error,
+            ),
+            cpal::SampleFormat::I16 => device.build_output_stream(
+                &config,
+                copy_output::<i16>(buffer.clone(), source.clone(), underruns.clone()),
+                on_error,
+            ),
+            cpal::SampleFormat::U16 => device.build_output_stream(
+                &config,
+                copy_output::<u16>(buffer.clone(), source.clone(), underruns.clone()),
+                on_error,
+            ),
+        }
+        .wrap_err_with(|| format!("Can't open output with {} frames buffer", frames))?;
+        stream.play()?;
+        debug!(
+            "Opened output with {} frames buffer, {} ch at {} Hz",
+            frames, channels, sample_rate
+        );
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let (thread_source, thread_stop) = (source.clone(), stop.clone());
+        let feed = frames as usize * channels as usize;
+        thread::Builder::new()
+            .name("output feeder".to_string())
+            .spawn(move || {
+                let mut chunk = Vec::with_capacity(feed);
+                while !thread_stop.load(Ordering::Relaxed) {
+                    chunk.clear();
+                    let space = capacity - buffer.lock().expect("Can't lock output buffer").len();
+                    if space >= feed {
+                        let mut source = thread_source.lock().expect("Can't lock output source");
+                        if let Some(source) = source.as_mut() {
+                            chunk.extend(source.by_ref().take(feed));
+                        }
+                    }
+                    match chunk.is_empty() {
+                        true => thread::sleep(Duration::from_millis(2)),
+                        false => buffer
+                            .lock()
+                            .expect("Can't lock output buffer")
+                            .extend(chunk.iter()),
+                    }
+                }
+            })?;
+        Ok(Self {
+            _stream: stream,
+            source,
+            stop,
+            channels,
+            sample_rate,
+            underruns,
+        })
+    }
+
+    /// Play `source` from now on, converted to the device format
+    pub fn set_source<S>(&self, source: S)
+    where
+        S: rodio::Source<Item = f32> + Send + 'static,
+    {
+        let frames = rodio::source::UniformSourceIterator::<_, f32>::new(
+            source,
+            self.channels,
+            self.sample_rate,
+        );
+        *self.source.lock().expect("Can't lock output source") = Some(Box::new(frames));
+    }
+
+    /// Output callbacks that ran out of decoded samples
+    pub fn underruns(&self) -> u64 {
+        self.underruns.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for BufferedStream {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Output callback copying from `buffer`, silence on underrun
+fn copy_output<T: cpal::Sample>(
+    buffer: Arc<Mutex<VecDeque<f32>>>,
+    source: Arc<Mutex<Option<Frames>>>,
+    underruns: Arc<AtomicU64>,
+) -> impl FnMut(&mut [T], &cpal::OutputCallbackInfo) + Send + 'static {
+    move |data, _| {
+        let mut missing = false;
+        {
+            let mut buffer = buffer.lock().expect("Can't lock output buffer");
+            for out in data.iter_mut() {
+                let sample = buffer.pop_front().unwrap_or_else(|| {
+                    missing = true;
+                    0.0
+                });
+                *out = T::from(&sample);
+            }
+        }
+        // nothing to play before the first track isn't an underrun
+        if missing && source.try_lock().map_or(true, |v| v.is_some()) {
+            underruns.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Open output streams for all configured zones.
+/// Returned streams have to be kept alive for playback.
+pub(crate) fn open_zones(
+    routing: &AudioRouting,
+    status: &Sender<PlayerStatus>,
+) -> (Vec<OutputStream>, Vec<OutputZone>) {
+    let mut streams = Vec::with_capacity(routing.zones.len());
+    let mut zones = Vec::with_capacity(routing.zones.len());
+    for zone in routing.zones.iter() {
+        let handle = match open_output(Some(&zone.device)) {
+            Ok((stream, handle)) => {
+                debug!("Opened zone {}", zone.device);
+                streams.push(stream);
+                Some(handle)
+            }
+            Err(e) => {
+                let msg = format!("Can't open zone {}: {}", zone.device, e);
+                error!("{}", msg);
+                let _ = status.send(PlayerStatus::OutputError(msg));
+                None
+            }
+        };
+        zones.push(OutputZone {
+            handle,
+            volume: zone.volume,
+            sink: None,
+        });
+    }
+    (streams, zones)
+}
+
+/// How far the network output may run ahead of real time
+const NETWORK_AHEAD: Duration = Duration::from_millis(500);
+
+/// PCM output to a network audio server, s16le stereo
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkOutput {
+    /// `tcp://host:port` of a Snapcast tcp source in server mode,
+    /// otherwise the path of a Snapcast pipe source
+    pub target: String,
+    /// Has to match the `sampleformat` configured for the source
+    #[serde(default = "default_network_rate")]
+    pub sample_rate: u32,
+}
+
+fn default_network_rate() -> u32 {
+    48000
+}
+
+/// Running network output of one track, stops when dropped
+pub(crate) struct NetworkStream {
+    stop: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+}
+
+impl NetworkStream {
+    pub fn start(output: &NetworkOutput, source: BoxSource) -> Result<Self> {
+        let writer: Box<dyn Write + Send> = match output.target.strip_prefix("tcp://") {
+            Some(addr) => Box::new(
+                TcpStream::connect(addr).wrap_err_with(|| format!("Can't connect to {}", addr))?,
+            ),
+            None => Box::new(
+                std::fs::OpenOptions::new()
+                    .write(true)
+                    .open(&output.target)
+                    .wrap_err_with(|| format!("Can't open {}", output.target))?,
+            ),
+        };
+        let rate = output.sample_rate;
+        let source = rodio::source::UniformSourceIterator::<_, i16>::new(source, 2, rate);
+        let stop = Arc::new(AtomicBool::new(false));
+        let paused = Arc::new(AtomicBool::new(false));
+        let (thread_stop, thread_paused) = (stop.clone(), paused.clone());
+        thread::Builder::new()
+            .name("network output".to_string())
+            .spawn(move || {
+                let mut writer = BufWriter::new(writer);
+                let mut start = Instant::now();
+                let mut frames: u64 = 0;
+                for (i, sample) in source.enumerate() {
+                    if i % 2 == 0 {
+                        frames += 1;
+                        // pace to real time, the server would drift otherwise
+                        let sent = Duration::from_micros(frames * 1_000_000 / rate as u64);
+                        if sent > start.elapsed() + NETWORK_AHEAD {
+                            let _ = writer.flush();
+                            thread::sleep(sent - start.elapsed() - NETWORK_AHEAD / 2);
+                        }
+                        while thread_paused.load(Ordering::Relaxed)
+                            && !thread_stop.load(Ordering::Relaxed)
+                        {
+                            thread::sleep(Duration::from_millis(50));
+                            start = Instant::now();
+                            frames = 0;
+                        }
+                        if thread_stop.load(Ordering::Relaxed) {
+                            return;
+                        }
+                    }
+                    if let Err(e) = writer.write_all(&sample.to_le_bytes()) {
+                        warn!("Network output closed: {}", e);
+                        return;
+                    }
+                }
+                let _ = writer.flush();
+            })?;
+        Ok(Self { stop, paused })
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+}
+
+impl Drop for NetworkStream {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
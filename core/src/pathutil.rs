@@ -0,0 +1,56 @@
+//! Comparable form of track locations, for matching the same file stored differently
+
+use std::path::{Path, PathBuf};
+
+use url::Url;
+
+/// Key of a track for comparisons, not meant for display or opening.
+/// File URLs are decoded, existing files canonicalized and paths on Windows lowercased.
+/// Remote URLs are kept as they are.
+pub fn key(track: &str) -> String {
+    let path = match Url::parse(track) {
+        Ok(url) if url.scheme() == "file" => match url.to_file_path() {
+            Ok(v) => v,
+            Err(_) => return track.to_string(),
+        },
+        // drive letters are parsed as single letter scheme
+        Ok(url) if url.scheme().len() > 1 => return track.to_string(),
+        _ => PathBuf::from(track),
+    };
+    let path = std::fs::canonicalize(&path).unwrap_or(path);
+    normalize_case(strip_verbatim(&path))
+}
+
+/// Remove the `\\?\` prefix added by canonicalize on Windows
+fn strip_verbatim(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    match path.strip_prefix(r"\\?\") {
+        Some(v) => v.to_string(),
+        None => path.into_owned(),
+    }
+}
+
+#[cfg(windows)]
+fn normalize_case(path: String) -> String {
+    path.to_lowercase()
+}
+
+#[cfg(not(windows))]
+fn normalize_case(path: String) -> String {
+    path
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_key() {
+        assert_eq!(key("src/../Cargo.toml"), key("Cargo.toml"));
+        assert_eq!(key("https://a.b/c%20d.mp3"), "https://a.b/c%20d.mp3");
+        #[cfg(unix)]
+        assert_eq!(key("file:///music/a%20b.mp3"), key("/music/a b.mp3"));
+        #[cfg(windows)]
+        assert_eq!(key(r"C:\Music\A.mp3"), key("file:///c:/music/a.mp3"));
+    }
+}
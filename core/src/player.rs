@@ -0,0 +1,676 @@
+use std::{
+    sync::mpsc::{channel, Receiver, Sender, TryRecvError},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::backend::{AudioBackend, RodioBackend};
+use crate::clock::PlaybackClock;
+use crate::dsp::{ChannelMapping, DspStage, EqPreset, Levels};
+use crate::output::AudioRouting;
+use crate::prelude::*;
+use crate::remote::Credentials;
+
+/// Interval for checking the track end and reporting levels
+const POLL_INTERVAL: Duration = Duration::from_millis(150);
+/// Default interval of progress reports
+pub const PROGRESS_INTERVAL: Duration = Duration::from_millis(150);
+/// Interval for checking whether the system default output device changed
+const DEVICE_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Playback sequencing and timing, audio output is done by the backend
+pub struct Player<B> {
+    backend: B,
+    last_file: String,
+    /// Path or URL of the current track, used for seeking
+    origin_path: String,
+    /// Generation of the current track, echoed in its statuses
+    generation: u64,
+    /// Volume of the main sink
+    volume: u8,
+    state: PlaybackState,
+    length: Option<Duration>,
+    /// None before the first track
+    clock: Option<PlaybackClock>,
+    progress_interval: Duration,
+    last_progress: Option<Instant>,
+    /// Last reported underrun count
+    underruns: Option<u64>,
+    last_device_check: Option<Instant>,
+    rx: Receiver<PlayerCommand>,
+    state_tx: Sender<PlayerStatus>,
+}
+
+impl Player<RodioBackend> {
+    pub fn new(
+        routing: AudioRouting,
+    ) -> Result<(
+        Sender<PlayerCommand>,
+        Receiver<PlayerStatus>,
+        JoinHandle<()>,
+    )> {
+        let (tx, rx) = channel::<PlayerCommand>();
+        let (state_tx, state_rx) = channel::<PlayerStatus>();
+
+        let child = thread::Builder::new()
+            .name("audio controller".to_string())
+            .spawn(move || {
+                let mut reported = false;
+                // commands wait in the channel until a device shows up
+                let backend = loop {
+                    match RodioBackend::new(&routing, state_tx.clone()) {
+                        Ok(v) => break v,
+                        Err(e) if !reported => {
+                            let msg = format!("Can't open output device, retrying: {:#}", e);
+                            error!("{}", msg);
+                            let _ = state_tx.send(PlayerStatus::OutputError(msg));
+                            reported = true;
+                        }
+                        Err(e) => debug!("Can't open output device: {:#}", e),
+                    }
+                    thread::sleep(DEVICE_CHECK_INTERVAL);
+                };
+                Player::with_backend(backend, rx, state_tx).run();
+            })?;
+        Ok((tx, state_rx, child))
+    }
+}
+
+impl<B: AudioBackend> Player<B> {
+    fn with_backend(
+        backend: B,
+        rx: Receiver<PlayerCommand>,
+        state_tx: Sender<PlayerStatus>,
+    ) -> Self {
+        Self {
+            backend,
+            last_file: Default::default(),
+            origin_path: Default::default(),
+            generation: 0,
+            volume: 0,
+            state: PlaybackState::Idle,
+            length: None,
+            clock: None,
+            progress_interval: PROGRESS_INTERVAL,
+            last_progress: None,
+            underruns: None,
+            last_device_check: None,
+            rx,
+            state_tx,
+        }
+    }
+
+    /// Handle player activity until the command channel closes
+    fn run(&mut self) {
+        loop {
+            match self.rx.try_recv() {
+                Ok(msg) => self.handle(msg),
+                Err(TryRecvError::Empty) => {
+                    self.poll();
+                    thread::sleep(self.progress_interval.min(POLL_INTERVAL));
+                }
+                Err(TryRecvError::Disconnected) => {
+                    break;
+                }
+            }
+        }
+    }
+
+    fn handle(&mut self, msg: PlayerCommand) {
+        trace!("Player command: {:?}", msg);
+        match msg {
+            PlayerCommand::Volume(v) => {
+                self.volume = v;
+                self.backend.set_volume(v);
+            }
+            PlayerCommand::Play(origin_path, volume, generation) => {
+                self.generation = generation;
+                self.play(origin_path, volume, Duration::ZERO)
+            }
+            PlayerCommand::Seek(position) => self.seek(position),
+            PlayerCommand::Pause => self.pause(),
+            PlayerCommand::Stop => self.stop(),
+            PlayerCommand::Preamp(db) => self.backend.set_preamp(db),
+            PlayerCommand::ChannelMapping(v) => self.backend.set_channels(v),
+            PlayerCommand::Equalizer(v) => self.backend.set_equalizer(v),
+            PlayerCommand::DspChain(v) => self.backend.set_dsp_chain(v),
+            PlayerCommand::ZoneVolume(zone, v) => self.backend.set_zone_volume(zone, v),
+            PlayerCommand::ProgressInterval(v) => self.progress_interval = v,
+            PlayerCommand::FollowDefaultDevice(v) => self.backend.set_follow_default(v),
+            PlayerCommand::Credentials(v) => self.backend.set_credentials(v),
+        }
+    }
+
+    /// Detect the track end and report progress
+    fn poll(&mut self) {
+        if self.backend.is_finished() && self.transition(Transition::Finished) {
+            self.send(PlayerStatus::Ended(self.generation));
+            return;
+        }
+        let now = Instant::now();
+        let due = self
+            .last_progress
+            .is_none_or(|t| now.saturating_duration_since(t) >= self.progress_interval);
+        if due {
+            self.send_progress(now);
+        }
+        let levels = match self.state {
+            PlaybackState::Paused => Levels::default(),
+            _ => self.backend.levels(),
+        };
+        self.send(PlayerStatus::Levels(levels));
+        if self.backend.take_clipped() {
+            self.send(PlayerStatus::Clipped);
+        }
+        let underruns = self.backend.underruns();
+        if underruns != self.underruns {
+            self.underruns = underruns;
+            if let Some(count) = underruns {
+                self.send(PlayerStatus::Underruns(count));
+            }
+        }
+        let check_device = self
+            .last_device_check
+            .is_none_or(|t| now.saturating_duration_since(t) >= DEVICE_CHECK_INTERVAL);
+        if check_device {
+            self.last_device_check = Some(now);
+            if self.backend.follow_default_device()
+                && matches!(self.state, PlaybackState::Playing | PlaybackState::Paused)
+            {
+                // the sink belonged to the old device
+                let position = self.clock.map_or(Duration::ZERO, |c| c.position(now));
+                self.seek(position);
+            }
+        }
+    }
+
+    fn send_progress(&mut self, now: Instant) {
+        self.last_progress = Some(now);
+        self.send(PlayerStatus::Progress(Progress {
+            position: self.clock.map_or(Duration::ZERO, |c| c.position(now)),
+            duration: self.length,
+            state: self.state,
+        }));
+    }
+
+    fn send(&self, status: PlayerStatus) {
+        self.state_tx
+            .send(status)
+            .expect("Can't send playback status!");
+    }
+
+    /// Apply `event` to the playback state, returns false if it doesn't apply
+    fn transition(&mut self, event: Transition) -> bool {
+        match self.state.next(event) {
+            Some(state) => {
+                trace!("Playback {:?} -> {:?} on {:?}", self.state, state, event);
+                self.state = state;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Restart the current track at `position`, keeping the pause state
+    fn seek(&mut self, position: Duration) {
+        if self.origin_path.is_empty() {
+            return;
+        }
+        let paused = self.state == PlaybackState::Paused;
+        self.play(self.origin_path.clone(), self.volume, position);
+        if paused {
+            self.pause();
+        }
+    }
+
+    fn play(&mut self, origin_path: String, volume: u8, position: Duration) {
+        self.volume = volume;
+        debug!("Starting playback at {:?}", position);
+        match self.backend.play(&origin_path, volume, position) {
+            Ok(track) => {
+                self.last_file = track.name;
+                self.origin_path = origin_path;
+                self.length = track.length;
+                self.transition(Transition::Play);
+                self.send(PlayerStatus::Playing(
+                    self.last_file.clone(),
+                    self.generation,
+                ));
+                self.send(PlayerStatus::Format {
+                    source_rate: track.source_rate,
+                    output_rate: track.output_rate,
+                    channels: track.channels,
+                });
+                let now = Instant::now();
+                self.clock = Some(PlaybackClock::start(position, now));
+                // report the new length right away
+                self.send_progress(now);
+            }
+            Err(e) => {
+                warn!("{:?} {}", origin_path, e);
+                self.transition(Transition::Failed);
+                self.send(PlayerStatus::InvalidFile(
+                    origin_path,
+                    e.to_string(),
+                    self.generation,
+                ));
+            }
+        }
+    }
+
+    fn pause(&mut self) {
+        let resume = self.state == PlaybackState::Paused;
+        if !self.transition(Transition::TogglePause) {
+            return;
+        }
+        let now = Instant::now();
+        if resume {
+            if let Some(ref mut clock) = self.clock {
+                clock.resume(now);
+            }
+            self.backend.set_paused(false);
+            self.send(PlayerStatus::Playing(
+                self.last_file.clone(),
+                self.generation,
+            ));
+        } else {
+            if let Some(ref mut clock) = self.clock {
+                clock.pause(now);
+            }
+            self.backend.set_paused(true);
+            self.send(PlayerStatus::Paused);
+        }
+    }
+
+    /// Stop the current track, it can't be resumed or seeked afterwards
+    fn stop(&mut self) {
+        if !self.transition(Transition::Stop) {
+            return;
+        }
+        self.backend.stop();
+        self.origin_path.clear();
+        self.length = None;
+        self.clock = None;
+        self.send_progress(Instant::now());
+    }
+}
+
+/// State of the player thread
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaybackState {
+    /// Nothing played yet
+    Idle,
+    Playing,
+    Paused,
+    /// Track ended, was stopped or couldn't be played
+    Stopped,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transition {
+    /// New track started
+    Play,
+    TogglePause,
+    /// All sinks ran empty
+    Finished,
+    /// Track couldn't be opened
+    Failed,
+    /// Stopped on request
+    Stop,
+}
+
+impl PlaybackState {
+    /// State after `event`, None if the event doesn't apply in this state
+    fn next(self, event: Transition) -> Option<PlaybackState> {
+        use PlaybackState::*;
+        match (self, event) {
+            (_, Transition::Play) => Some(Playing),
+            (_, Transition::Failed) => Some(Stopped),
+            (Playing, Transition::TogglePause) => Some(Paused),
+            (Paused, Transition::TogglePause) => Some(Playing),
+            (Playing, Transition::Finished) => Some(Stopped),
+            (Playing | Paused, Transition::Stop) => Some(Stopped),
+            (Idle | Stopped, Transition::Stop) => None,
+            (Idle | Stopped, Transition::TogglePause) => None,
+            (Idle | Paused | Stopped, Transition::Finished) => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PlayerCommand {
+    Volume(u8),
+    /// Play track with volume, the generation tells statuses of superseded tracks apart
+    Play(String, u8, u64),
+    Pause,
+    /// Stop playback, the next Play starts from the beginning
+    Stop,
+    /// Set preamp gain in dB
+    Preamp(i16),
+    /// Set channel mapping, applies from the next track on
+    ChannelMapping(ChannelMapping),
+    /// Set equalizer preset, applies from the next track on
+    Equalizer(Option<EqPreset>),
+    /// Set external DSP stages, applies from the next track on
+    DspChain(Vec<DspStage>),
+    /// Set volume of additional zone by index
+    ZoneVolume(usize, u8),
+    /// Restart the current track at the position
+    Seek(Duration),
+    /// Set interval of progress reports
+    ProgressInterval(Duration),
+    /// Switch to the system default output device whenever it changes
+    FollowDefaultDevice(bool),
+    /// Set credentials of the remote libraries, used when fetching their tracks
+    Credentials(Credentials),
+}
+
+/// Position and length of the current track
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    pub position: Duration,
+    pub duration: Option<Duration>,
+    pub state: PlaybackState,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PlayerStatus {
+    /// Track started or resumed, with the generation it was started with
+    Playing(String, u64),
+    /// Track of the generation ran out
+    Ended(u64),
+    /// Track couldn't be played, with the reason
+    InvalidFile(String, String, u64),
+    Paused,
+    Progress(Progress),
+    /// Sample format of the current track, bit depth isn't exposed by the decoders
+    Format {
+        source_rate: u32,
+        output_rate: u32,
+        channels: u16,
+    },
+    /// Output levels of the current track
+    Levels(Levels),
+    /// Preamp clipped samples since the last report
+    Clipped,
+    /// Output underruns since start, only sent by outputs counting them
+    Underruns(u64),
+    /// Output device or DSP problem, playback may continue on a fallback or without the DSP
+    OutputError(String),
+    /// Name of the device the main output plays on, sent on open and on switching
+    OutputDevice(String),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backend::Track;
+
+    #[test]
+    fn test_transitions() {
+        use PlaybackState::*;
+        use Transition::*;
+        let table = [
+            (Idle, Play, Some(Playing)),
+            (Idle, TogglePause, None),
+            // no end event before anything was played
+            (Idle, Finished, None),
+            (Idle, Failed, Some(Stopped)),
+            (Idle, Stop, None),
+            (Playing, Play, Some(Playing)),
+            (Playing, TogglePause, Some(Paused)),
+            (Playing, Finished, Some(Stopped)),
+            (Playing, Failed, Some(Stopped)),
+            (Playing, Stop, Some(Stopped)),
+            (Paused, Play, Some(Playing)),
+            (Paused, TogglePause, Some(Playing)),
+            (Paused, Finished, None),
+            (Paused, Failed, Some(Stopped)),
+            (Paused, Stop, Some(Stopped)),
+            (Stopped, Play, Some(Playing)),
+            (Stopped, TogglePause, None),
+            // only one end event per track
+            (Stopped, Finished, None),
+            (Stopped, Failed, Some(Stopped)),
+            (Stopped, Stop, None),
+        ];
+        for (state, event, expected) in table.iter() {
+            assert_eq!(state.next(*event), *expected, "{:?} on {:?}", state, event);
+        }
+    }
+
+    #[derive(Default)]
+    struct MockBackend {
+        finished: bool,
+        paused: bool,
+        stopped: bool,
+        volume: u8,
+        played: Vec<(String, Duration)>,
+        /// Default device changed since the last check
+        device_changed: bool,
+    }
+
+    impl AudioBackend for MockBackend {
+        fn play(&mut self, origin_path: &str, volume: u8, position: Duration) -> Result<Track> {
+            if origin_path.ends_with(".invalid") {
+                return Err(eyre!("Unsupported format"));
+            }
+            self.finished = false;
+            self.paused = false;
+            self.stopped = false;
+            self.volume = volume;
+            self.played.push((origin_path.to_string(), position));
+            Ok(Track {
+                name: origin_path.to_string(),
+                length: Some(Duration::from_secs(60)),
+                source_rate: 44100,
+                output_rate: 44100,
+                channels: 2,
+            })
+        }
+
+        fn set_paused(&mut self, paused: bool) {
+            self.paused = paused;
+        }
+
+        fn stop(&mut self) {
+            self.stopped = true;
+        }
+
+        fn set_volume(&mut self, volume: u8) {
+            self.volume = volume;
+        }
+
+        fn is_finished(&self) -> bool {
+            self.finished
+        }
+
+        fn levels(&self) -> Levels {
+            Levels::default()
+        }
+
+        fn take_clipped(&self) -> bool {
+            false
+        }
+
+        fn underruns(&self) -> Option<u64> {
+            None
+        }
+
+        fn set_preamp(&mut self, _db: i16) {}
+
+        fn set_channels(&mut self, _mapping: ChannelMapping) {}
+
+        fn set_equalizer(&mut self, _preset: Option<EqPreset>) {}
+
+        fn set_dsp_chain(&mut self, _chain: Vec<DspStage>) {}
+
+        fn set_zone_volume(&mut self, _zone: usize, _volume: u8) {}
+
+        fn set_follow_default(&mut self, _follow: bool) {}
+
+        fn follow_default_device(&mut self) -> bool {
+            std::mem::take(&mut self.device_changed)
+        }
+
+        fn set_credentials(&mut self, _credentials: Credentials) {}
+    }
+
+    fn player() -> (Player<MockBackend>, Receiver<PlayerStatus>) {
+        let (_tx, rx) = channel();
+        let (state_tx, state_rx) = channel();
+        (
+            Player::with_backend(MockBackend::default(), rx, state_tx),
+            state_rx,
+        )
+    }
+
+    /// Statuses without periodic progress reports
+    fn events(rx: &Receiver<PlayerStatus>) -> Vec<PlayerStatus> {
+        rx.try_iter()
+            .filter(|v| !matches!(v, PlayerStatus::Progress(_) | PlayerStatus::Levels(_)))
+            .collect()
+    }
+
+    #[test]
+    fn test_sequence() {
+        let (mut player, rx) = player();
+        // nothing ends before anything played
+        player.poll();
+        assert_eq!(events(&rx), vec![]);
+
+        player.handle(PlayerCommand::Play("a.mp3".into(), 50, 1));
+        assert_eq!(
+            events(&rx),
+            vec![
+                PlayerStatus::Playing("a.mp3".into(), 1),
+                PlayerStatus::Format {
+                    source_rate: 44100,
+                    output_rate: 44100,
+                    channels: 2
+                }
+            ]
+        );
+        assert_eq!(player.backend.volume, 50);
+        player.poll();
+        assert_eq!(events(&rx), vec![]);
+
+        player.backend.finished = true;
+        player.poll();
+        player.poll();
+        assert_eq!(events(&rx), vec![PlayerStatus::Ended(1)]);
+    }
+
+    #[test]
+    fn test_invalid_file() {
+        let (mut player, rx) = player();
+        player.handle(PlayerCommand::Play("b.invalid".into(), 50, 2));
+        player.poll();
+        assert_eq!(
+            events(&rx),
+            vec![PlayerStatus::InvalidFile(
+                "b.invalid".into(),
+                "Unsupported format".into(),
+                2
+            )]
+        );
+        assert_eq!(player.state, PlaybackState::Stopped);
+    }
+
+    #[test]
+    fn test_pause() {
+        let (mut player, rx) = player();
+        // nothing to pause
+        player.handle(PlayerCommand::Pause);
+        assert_eq!(events(&rx), vec![]);
+
+        player.handle(PlayerCommand::Play("a.mp3".into(), 50, 1));
+        events(&rx);
+        player.handle(PlayerCommand::Pause);
+        assert!(player.backend.paused);
+        assert_eq!(events(&rx), vec![PlayerStatus::Paused]);
+        // seeking keeps the pause state
+        player.handle(PlayerCommand::Seek(Duration::from_secs(10)));
+        assert!(player.backend.paused);
+        assert_eq!(
+            player.backend.played.last(),
+            Some(&("a.mp3".to_string(), Duration::from_secs(10)))
+        );
+        events(&rx);
+        player.handle(PlayerCommand::Pause);
+        assert!(!player.backend.paused);
+        assert_eq!(events(&rx), vec![PlayerStatus::Playing("a.mp3".into(), 1)]);
+    }
+
+    #[test]
+    fn test_follow_default_device() {
+        let (mut player, rx) = player();
+        player.handle(PlayerCommand::Play("a.mp3".into(), 50, 1));
+        player.handle(PlayerCommand::Pause);
+        events(&rx);
+        player.backend.device_changed = true;
+        player.poll();
+        // restarted on the new device, still paused
+        assert_eq!(player.backend.played.len(), 2);
+        assert!(player.backend.paused);
+        assert_eq!(player.state, PlaybackState::Paused);
+
+        player.handle(PlayerCommand::Stop);
+        player.backend.device_changed = true;
+        player.last_device_check = None;
+        player.poll();
+        assert_eq!(player.backend.played.len(), 2);
+    }
+
+    #[test]
+    fn test_stop() {
+        let (mut player, rx) = player();
+        player.handle(PlayerCommand::Play("a.mp3".into(), 50, 1));
+        rx.try_iter().count();
+        player.handle(PlayerCommand::Stop);
+        assert!(player.backend.stopped);
+        assert_eq!(
+            rx.try_iter().collect::<Vec<_>>(),
+            vec![PlayerStatus::Progress(Progress {
+                position: Duration::ZERO,
+                duration: None,
+                state: PlaybackState::Stopped,
+            })]
+        );
+        // nothing left to resume, seek or end
+        player.handle(PlayerCommand::Pause);
+        player.handle(PlayerCommand::Seek(Duration::from_secs(10)));
+        player.backend.finished = true;
+        player.poll();
+        assert_eq!(events(&rx), vec![]);
+        assert_eq!(player.backend.played.len(), 1);
+    }
+
+    #[test]
+    fn test_progress() {
+        let (mut player, rx) = player();
+        let progress = |rx: &Receiver<PlayerStatus>| -> Vec<Progress> {
+            rx.try_iter()
+                .filter_map(|v| match v {
+                    PlayerStatus::Progress(p) => Some(p),
+                    _ => None,
+                })
+                .collect()
+        };
+        player.handle(PlayerCommand::ProgressInterval(Duration::from_secs(3600)));
+        player.handle(PlayerCommand::Play("a.mp3".into(), 50, 1));
+        // sent on start regardless of the interval
+        let sent = progress(&rx);
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].duration, Some(Duration::from_secs(60)));
+        assert_eq!(sent[0].state, PlaybackState::Playing);
+        player.poll();
+        assert_eq!(progress(&rx), vec![]);
+        player.handle(PlayerCommand::ProgressInterval(Duration::ZERO));
+        player.handle(PlayerCommand::Pause);
+        player.poll();
+        let sent = progress(&rx);
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].state, PlaybackState::Paused);
+    }
+}
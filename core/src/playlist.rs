@@ -6,11 +6,15 @@ use quick_xml::{
 };
 
 use std::{
+    collections::HashSet,
     fs::File,
     io::{Cursor, Write},
+    path::Path,
 };
 use url::Url;
 
+use crate::charset::{self, Charset};
+use crate::pathutil;
 use crate::prelude::*;
 
 #[test]
@@ -42,7 +46,70 @@ fn test_write() {
         String::from("C:\\asd\\asd.wav"),
         String::from("D:\\\\asd_asd2ü.mp3"),
     ];
-    write_playlist(files.iter(), "../tests/test.xspf").unwrap();
+    let path = std::env::temp_dir().join("audio_wrench_test.xspf");
+    write_playlist(files.iter(), "Test", &path.to_string_lossy()).unwrap();
+}
+
+#[test]
+#[cfg(unix)]
+fn test_diff() {
+    let a: Vec<String> = vec!["/m/1.mp3".into(), "/m/3.mp3".into(), "/m/2.mp3".into()];
+    let b: Vec<String> = vec!["file:///m/3.mp3".into(), "/m/4.mp3".into()];
+    let diff = Diff::new(&a, &b);
+    assert_eq!(diff.only_a, vec!["/m/1.mp3", "/m/2.mp3"]);
+    assert_eq!(diff.only_b, vec!["/m/4.mp3"]);
+    assert_eq!(diff.shared, vec!["/m/3.mp3"]);
+    assert_eq!(
+        diff.merged(&a),
+        vec!["/m/1.mp3", "/m/3.mp3", "/m/2.mp3", "/m/4.mp3"]
+    );
+}
+
+/// Read tracks of a playlist file in any supported format, its encoding is detected if None
+pub fn read_playlist(path: &Path, charset: Option<Charset>) -> Result<Vec<String>> {
+    let data = charset::read(path, charset)?;
+    playlist_decoder::decode(&data).map_err(|e| eyre!("Can't parse {:?}: {}", path, e))
+}
+
+/// Tracks of two playlists, compared by their normalized location
+#[derive(Debug, Default)]
+pub struct Diff {
+    pub only_a: Vec<String>,
+    pub only_b: Vec<String>,
+    /// In order of A
+    pub shared: Vec<String>,
+}
+
+impl Diff {
+    pub fn new(a: &[String], b: &[String]) -> Self {
+        // normalize every track once, this dominates on large playlists
+        let keys_a: Vec<String> = a.iter().map(|t| pathutil::key(t)).collect();
+        let keys_b: Vec<String> = b.iter().map(|t| pathutil::key(t)).collect();
+        let set_a: HashSet<&str> = keys_a.iter().map(String::as_str).collect();
+        let set_b: HashSet<&str> = keys_b.iter().map(String::as_str).collect();
+        let mut diff = Self::default();
+        for (track, key) in a.iter().zip(keys_a.iter()) {
+            match set_b.contains(key.as_str()) {
+                true => diff.shared.push(track.clone()),
+                false => diff.only_a.push(track.clone()),
+            }
+        }
+        diff.only_b = b
+            .iter()
+            .zip(keys_b.iter())
+            .filter(|(_, key)| !set_a.contains(key.as_str()))
+            .map(|(track, _)| track.clone())
+            .collect();
+        diff
+    }
+
+    /// Tracks of `a` this diff was created from, followed by those only in B
+    pub fn merged(&self, a: &[String]) -> Vec<String> {
+        let mut merged = Vec::with_capacity(a.len() + self.only_b.len());
+        merged.extend_from_slice(a);
+        merged.extend(self.only_b.iter().cloned());
+        merged
+    }
 }
 
 enum Track<'a> {
@@ -59,7 +126,7 @@ impl<'a> Track<'a> {
     }
 }
 
-pub fn write_playlist<'a, I>(files: I, write_file: &str) -> Result<()>
+pub fn write_playlist<'a, I>(files: I, title: &str, write_file: &str) -> Result<()>
 where
     I: Iterator<Item = &'a String>,
 {
@@ -72,9 +139,7 @@ where
     playlist.push_attribute(("xmlns", "http://xspf.org/ns/0/"));
     writer.write_event(Event::Start(playlist))?;
     writer.write_event(Event::Start(BytesStart::borrowed_name(b"title")))?;
-    writer.write_event(Event::Text(BytesText::from_plain_str(
-        "Audio-Wrench Favorites",
-    )))?;
+    writer.write_event(Event::Text(BytesText::from_plain_str(title)))?;
     writer.write_event(Event::End(BytesEnd::borrowed(b"title")))?;
     let titles = BytesStart::borrowed_name(b"trackList");
     writer.write_event(Event::Start(titles))?;
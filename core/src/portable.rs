@@ -0,0 +1,332 @@
+//! Playlists with paths relative to a base folder, for copying music and playlist
+//! together to a USB stick or phone where absolute paths don't exist.
+//! The DOS profile is for hardware players and car head units that only read
+//! 8.3 file names, backslashes and CRLF line endings.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::metadata;
+use crate::prelude::*;
+
+/// Playlist format and file naming of an [`export`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Profile {
+    /// UTF-8 `.m3u8` with `/` separators and the original file names
+    #[default]
+    Standard,
+    /// Extended `.M3U` with `\` separators, CRLF and 8.3 file names
+    Dos,
+}
+
+impl Profile {
+    pub const ALL: [Profile; 2] = [Profile::Standard, Profile::Dos];
+
+    fn separator(self) -> &'static str {
+        match self {
+            Profile::Standard => "/",
+            Profile::Dos => "\\",
+        }
+    }
+
+    fn line_end(self) -> &'static str {
+        match self {
+            Profile::Standard => "\n",
+            Profile::Dos => "\r\n",
+        }
+    }
+}
+
+impl std::fmt::Display for Profile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Profile::Standard => "M3U8",
+            Profile::Dos => "DOS M3U (8.3)",
+        })
+    }
+}
+
+/// Outcome of an [`export`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Export {
+    /// Tracks written to the playlist
+    pub tracks: usize,
+    /// Files copied into the base folder
+    pub copied: usize,
+    /// Remote tracks and files outside the base folder that were left out
+    pub skipped: usize,
+}
+
+/// Local file of a track, None for remote URLs
+pub(crate) fn local_file(track: &str) -> Option<PathBuf> {
+    match Url::parse(track) {
+        Ok(url) if url.scheme() == "file" => url.to_file_path().ok(),
+        // drive letters are parsed as single letter scheme
+        Ok(url) if url.scheme().len() > 1 => None,
+        _ => Some(PathBuf::from(track)),
+    }
+}
+
+/// Location of `file` relative to `base` in the form of `profile`. None if it's outside
+/// of `base` or, for the DOS profile, if a part of the path isn't a valid 8.3 name.
+fn relative_location(file: &Path, base: &Path, profile: Profile) -> Option<String> {
+    let relative = file.strip_prefix(base).ok()?;
+    let parts = relative
+        .components()
+        .map(|c| match c {
+            Component::Normal(v) => Some(v.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    if parts.is_empty() {
+        return None;
+    }
+    if profile == Profile::Dos {
+        // FAT looks up short names case insensitive, uppercase is what DOS players expect
+        return parts
+            .iter()
+            .map(|v| is_short(v).then(|| v.to_ascii_uppercase()))
+            .collect::<Option<Vec<_>>>()
+            .map(|v| v.join(profile.separator()));
+    }
+    Some(parts.join(profile.separator()))
+}
+
+/// Character allowed in 8.3 names
+fn is_short_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '~')
+}
+
+/// Whether `name` is a valid 8.3 file name, ignoring case
+fn is_short(name: &str) -> bool {
+    let (stem, extension) = name.rsplit_once('.').unwrap_or((name, ""));
+    (1..=8).contains(&stem.len())
+        && extension.len() <= 3
+        && stem.chars().chain(extension.chars()).all(is_short_char)
+}
+
+/// 8.3 name for `stem` and `extension`, `n` counts up for names starting the same.
+/// Like Windows the stem is cut and numbered with `~` once it doesn't fit.
+fn short_name(stem: &str, extension: &str, n: usize) -> String {
+    let clean = |v: &str| -> String {
+        v.chars()
+            .filter(|c| is_short_char(*c) && *c != '~')
+            .map(|c| c.to_ascii_uppercase())
+            .collect()
+    };
+    let mut short = clean(stem);
+    let extension: String = clean(extension).chars().take(3).collect();
+    let fits = !short.is_empty() && short.len() <= 8 && short.eq_ignore_ascii_case(stem);
+    if n > 1 || !fits {
+        if short.is_empty() {
+            short = String::from("TRACK");
+        }
+        let suffix = format!("~{}", n);
+        short.truncate(8 - suffix.len());
+        short.push_str(&suffix);
+    }
+    match extension.is_empty() {
+        true => short,
+        false => format!("{}.{}", short, extension),
+    }
+}
+
+/// Free file name in `base` for a copy of `source`, an existing file of the same size
+/// counts as earlier copy so exporting again doesn't duplicate everything
+fn copy_target(source: &Path, base: &Path, profile: Profile) -> Result<PathBuf> {
+    let name = source
+        .file_name()
+        .ok_or_else(|| eyre!("No file name in {:?}", source))?;
+    let size = fs::metadata(source)
+        .wrap_err_with(|| format!("Can't read {:?}", source))?
+        .len();
+    let stem = Path::new(name)
+        .file_stem()
+        .unwrap_or(name)
+        .to_string_lossy();
+    let extension = Path::new(name)
+        .extension()
+        .map(|v| v.to_string_lossy())
+        .unwrap_or_default();
+    let file_name = |n: usize| match (profile, n, extension.is_empty()) {
+        (Profile::Dos, _, _) => short_name(&stem, &extension, n),
+        (Profile::Standard, 1, _) => name.to_string_lossy().into_owned(),
+        (Profile::Standard, _, true) => format!("{} ({})", stem, n),
+        (Profile::Standard, _, false) => format!("{} ({}).{}", stem, n, extension),
+    };
+    let mut counter = 1;
+    let mut target = base.join(file_name(counter));
+    while let Ok(existing) = fs::metadata(&target) {
+        if existing.len() == size {
+            break;
+        }
+        counter += 1;
+        target = base.join(file_name(counter));
+    }
+    Ok(target)
+}
+
+/// Write `tracks` as playlist `name` into `base` with paths relative to it.
+/// With `copy` files outside of `base` are copied next to the playlist, otherwise they're left out.
+pub fn export(
+    tracks: &[String],
+    base: &Path,
+    name: &str,
+    copy: bool,
+    profile: Profile,
+) -> Result<Export> {
+    fs::create_dir_all(base).wrap_err_with(|| format!("Can't create {:?}", base))?;
+    let base = fs::canonicalize(base).wrap_err_with(|| format!("Can't open {:?}", base))?;
+    let mut result = Export::default();
+    let mut copies: HashMap<PathBuf, PathBuf> = HashMap::new();
+    let line_end = profile.line_end();
+    let mut content = format!("#EXTM3U{}", line_end);
+    for track in tracks {
+        let file = match local_file(track) {
+            Some(v) => fs::canonicalize(&v).unwrap_or(v),
+            None => {
+                debug!("Not exporting remote track {}", track);
+                result.skipped += 1;
+                continue;
+            }
+        };
+        let location = match relative_location(&file, &base, profile) {
+            Some(v) => v,
+            None if copy => {
+                let target = match copies.get(&file) {
+                    Some(v) => v.clone(),
+                    None => {
+                        let target = copy_target(&file, &base, profile)?;
+                        if !target.exists() {
+                            fs::copy(&file, &target)
+                                .wrap_err_with(|| format!("Can't copy {:?}", file))?;
+                            result.copied += 1;
+                        }
+                        copies.insert(file.clone(), target.clone());
+                        target
+                    }
+                };
+                relative_location(&target, &base, profile)
+                    .ok_or_else(|| eyre!("Copy {:?} is outside of {:?}", target, base))?
+            }
+            None => {
+                debug!("Not exporting {:?}, it's outside of {:?}", file, base);
+                result.skipped += 1;
+                continue;
+            }
+        };
+        if profile == Profile::Dos {
+            content.push_str(&extinf(track, &file));
+            content.push_str(line_end);
+        }
+        content.push_str(&location);
+        content.push_str(line_end);
+        result.tracks += 1;
+    }
+    let playlist = base.join(match profile {
+        Profile::Standard => format!("{}.m3u8", name),
+        Profile::Dos => short_name(name, "m3u", 1),
+    });
+    fs::write(&playlist, content).wrap_err_with(|| format!("Can't write {:?}", playlist))?;
+    Ok(result)
+}
+
+/// `#EXTINF` line with length and label of a track, ASCII only for players without Unicode
+fn extinf(track: &str, file: &Path) -> String {
+    // only probe formats the player decodes, the decoders choke on anything else
+    let info = match crate::is_audio_file(file) {
+        true => metadata::read(track),
+        false => metadata::TrackInfo::default(),
+    };
+    let length = info.length.map_or(-1, |v| v.as_secs() as i64);
+    let label = info.label().unwrap_or_else(|| {
+        file.file_stem()
+            .map(|v| v.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    });
+    let label: String = label
+        .chars()
+        .map(|c| match c.is_ascii() && !c.is_ascii_control() {
+            true => c,
+            false => '_',
+        })
+        .collect();
+    format!("#EXTINF:{},{}", length, label)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_export() {
+        let base = Path::new("/stick");
+        assert_eq!(
+            relative_location(
+                Path::new("/stick/Album/01 Intro.mp3"),
+                base,
+                Profile::Standard
+            ),
+            Some(String::from("Album/01 Intro.mp3"))
+        );
+        assert_eq!(
+            relative_location(Path::new("/stick/Album/Track01.mp3"), base, Profile::Dos),
+            Some(String::from("ALBUM\\TRACK01.MP3"))
+        );
+        assert_eq!(
+            relative_location(Path::new("/stick/Album/01 Intro.mp3"), base, Profile::Dos),
+            None
+        );
+        assert_eq!(
+            relative_location(Path::new("/music/a.mp3"), base, Profile::Standard),
+            None
+        );
+        assert_eq!(relative_location(base, base, Profile::Standard), None);
+        assert_eq!(short_name("song", "mp3", 1), "SONG.MP3");
+        assert_eq!(short_name("01 Intro (Live)", "flac", 1), "01INTR~1.FLA");
+        assert_eq!(short_name("song", "mp3", 12), "SONG~12.MP3");
+        assert_eq!(short_name("Ünïcödé", "ogg", 1), "NCD~1.OGG");
+        assert_eq!(local_file("https://radio.example/stream"), None);
+
+        let dir = std::env::temp_dir().join("audio_wrench_portable_test");
+        let _ = fs::remove_dir_all(&dir);
+        let stick = dir.join("stick");
+        fs::create_dir_all(dir.join("music")).unwrap();
+        let source = dir.join("music").join("song.dat");
+        fs::write(&source, b"data").unwrap();
+        let tracks = vec![
+            source.to_string_lossy().into_owned(),
+            String::from("https://radio.example/stream"),
+            source.to_string_lossy().into_owned(),
+        ];
+        let result = export(&tracks, &stick, "car", true, Profile::Standard).unwrap();
+        assert_eq!(
+            result,
+            Export {
+                tracks: 2,
+                copied: 1,
+                skipped: 1
+            }
+        );
+        let playlist = fs::read_to_string(stick.join("car.m3u8")).unwrap();
+        assert_eq!(playlist, "#EXTM3U\nsong.dat\nsong.dat\n");
+        // exporting again reuses the copies
+        let run = |copy, profile| export(&tracks, &stick, "car", copy, profile).unwrap();
+        assert_eq!(run(true, Profile::Standard).copied, 0);
+        assert_eq!(run(false, Profile::Standard).skipped, 3);
+        // copied once more as SONG.DAT, FAT would find the first copy case insensitive
+        assert_eq!(run(true, Profile::Dos).copied, 1);
+        let playlist = fs::read_to_string(stick.join("CAR.M3U")).unwrap();
+        assert_eq!(
+            playlist,
+            "#EXTM3U\r\n#EXTINF:-1,song\r\nSONG.DAT\r\n#EXTINF:-1,song\r\nSONG.DAT\r\n"
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,135 @@
+//! Operations on play queues, the first entry is the current or next track
+
+use std::collections::HashSet;
+
+/// Remove the played first entry, entries matching `requeue` go back to the end
+pub fn pop_head<F>(queue: &mut Vec<String>, requeue: F) -> Option<String>
+where
+    F: Fn(&String) -> bool,
+{
+    let head = queue.first()?;
+    match requeue(head) {
+        true => {
+            queue.rotate_left(1);
+            None
+        }
+        false => Some(queue.remove(0)),
+    }
+}
+
+/// Move the first entry matching `playable` to the front, keeping the order of all others.
+/// False if none matches.
+pub fn promote<F>(queue: &mut [String], playable: F) -> bool
+where
+    F: Fn(&String) -> bool,
+{
+    match queue.iter().position(playable) {
+        Some(pos) => {
+            // single move instead of removing and inserting
+            queue[..=pos].rotate_right(1);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Insert `tracks` in order after the current entry, or in front if nothing plays
+pub fn insert_next(queue: &mut Vec<String>, playing: bool, tracks: Vec<String>) {
+    let pos = match playing {
+        true => queue.len().min(1),
+        false => 0,
+    };
+    queue.splice(pos..pos, tracks);
+}
+
+/// Position of the current entry among everything taken from a queue so far
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    /// Starting at 1
+    pub track: usize,
+    pub total: usize,
+}
+
+/// Position of the first of `len` remaining entries after `played` were taken from the front
+pub fn position(played: usize, len: usize) -> Option<Position> {
+    match len {
+        0 => None,
+        _ => Some(Position {
+            track: played + 1,
+            total: played + len,
+        }),
+    }
+}
+
+/// Remove the entries at `positions`, returns them in queue order
+pub fn remove_positions(queue: &mut Vec<String>, positions: &[usize]) -> Vec<String> {
+    let positions: HashSet<usize> = positions.iter().copied().collect();
+    let mut removed = Vec::new();
+    let mut pos = 0;
+    queue.retain(|track| {
+        let keep = !positions.contains(&pos);
+        if !keep {
+            removed.push(track.clone());
+        }
+        pos += 1;
+        keep
+    });
+    removed
+}
+
+/// Move the entries at `positions` in their order to be played next
+pub fn move_next(queue: &mut Vec<String>, playing: bool, positions: &[usize]) {
+    // the current track stays where it is
+    let positions: Vec<usize> = positions
+        .iter()
+        .copied()
+        .filter(|pos| !playing || *pos > 0)
+        .collect();
+    let tracks = remove_positions(queue, &positions);
+    insert_next(queue, playing, tracks);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn queue(v: &[&str]) -> Vec<String> {
+        v.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_queue() {
+        let mut v = queue(&["a", "b", "c", "d"]);
+        assert_eq!(pop_head(&mut v, |t| t == "a"), None);
+        assert_eq!(v, queue(&["b", "c", "d", "a"]));
+        assert_eq!(pop_head(&mut v, |_| false), Some(String::from("b")));
+        assert!(promote(&mut v, |t| t == "a"));
+        assert_eq!(v, queue(&["a", "c", "d"]));
+        assert!(!promote(&mut v, |t| t == "x"));
+        insert_next(&mut v, true, queue(&["x", "y"]));
+        assert_eq!(v, queue(&["a", "x", "y", "c", "d"]));
+        insert_next(&mut v, false, queue(&["z"]));
+        assert_eq!(v[0], "z");
+        let mut empty = Vec::new();
+        assert_eq!(pop_head(&mut empty, |_| false), None);
+    }
+
+    #[test]
+    fn test_bulk() {
+        let mut v = queue(&["a", "b", "c", "d", "e"]);
+        move_next(&mut v, true, &[0, 3, 4]);
+        assert_eq!(v, queue(&["a", "d", "e", "b", "c"]));
+        move_next(&mut v, false, &[4]);
+        assert_eq!(v, queue(&["c", "a", "d", "e", "b"]));
+        assert_eq!(remove_positions(&mut v, &[1, 4, 9]), queue(&["a", "b"]));
+        assert_eq!(v, queue(&["c", "d", "e"]));
+        assert_eq!(
+            position(36, 178),
+            Some(Position {
+                track: 37,
+                total: 214
+            })
+        );
+        assert_eq!(position(3, 0), None);
+    }
+}
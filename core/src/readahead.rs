@@ -0,0 +1,181 @@
+//! Chunked reading of local tracks, prefetched on a separate thread.
+//! Keeps memory bounded for huge files and hides the latency of network drives from the decoder.
+
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::mpsc::{sync_channel, Receiver},
+    thread,
+};
+
+/// Bytes read at once, large enough to amortize network round trips
+const CHUNK_SIZE: usize = 256 * 1024;
+/// Chunks read ahead of the decoder
+const AHEAD_CHUNKS: usize = 8;
+
+/// Reader over a file, prefetching the following chunks while the current one is decoded
+pub struct ReadAhead {
+    path: PathBuf,
+    len: u64,
+    /// Current chunk and its offset in the file
+    chunk: Vec<u8>,
+    chunk_start: u64,
+    /// Read position within the current chunk
+    offset: usize,
+    /// Chunks following the current one, None after the end or an error
+    chunks: Option<Receiver<io::Result<Vec<u8>>>>,
+}
+
+impl ReadAhead {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let len = std::fs::metadata(path)?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            len,
+            chunk: Vec::new(),
+            chunk_start: 0,
+            offset: 0,
+            chunks: Some(prefetch(path, 0)?),
+        })
+    }
+
+    /// File size when opened
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn position(&self) -> u64 {
+        self.chunk_start + self.offset as u64
+    }
+}
+
+/// Read the file from `start` on a separate thread, stops once the receiver is dropped
+fn prefetch(path: &Path, start: u64) -> io::Result<Receiver<io::Result<Vec<u8>>>> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    let (tx, rx) = sync_channel(AHEAD_CHUNKS);
+    thread::Builder::new()
+        .name("read ahead".to_string())
+        .spawn(move || loop {
+            let result = read_chunk(&mut file);
+            let end = result.as_ref().map_or(true, |v| v.is_empty());
+            if tx.send(result).is_err() || end {
+                return;
+            }
+        })?;
+    Ok(rx)
+}
+
+/// Next chunk, only shorter than [`CHUNK_SIZE`] at the end of the file
+fn read_chunk(file: &mut File) -> io::Result<Vec<u8>> {
+    let mut chunk = vec![0; CHUNK_SIZE];
+    let mut filled = 0;
+    // network drives may return less than requested before the end
+    while filled < CHUNK_SIZE {
+        match file.read(&mut chunk[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e),
+        }
+    }
+    chunk.truncate(filled);
+    Ok(chunk)
+}
+
+impl Read for ReadAhead {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.offset == self.chunk.len() {
+            let next = match self.chunks {
+                Some(ref rx) => rx.recv().unwrap_or_else(|_| Ok(Vec::new())),
+                None => return Ok(0),
+            };
+            match next {
+                Ok(chunk) if chunk.is_empty() => {
+                    self.chunks = None;
+                    return Ok(0);
+                }
+                Ok(chunk) => {
+                    self.chunk_start += self.chunk.len() as u64;
+                    self.chunk = chunk;
+                    self.offset = 0;
+                }
+                Err(e) => {
+                    self.chunks = None;
+                    return Err(e);
+                }
+            }
+        }
+        let n = buf.len().min(self.chunk.len() - self.offset);
+        buf[..n].copy_from_slice(&self.chunk[self.offset..self.offset + n]);
+        self.offset += n;
+        Ok(n)
+    }
+}
+
+impl Seek for ReadAhead {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(v) => Some(v),
+            SeekFrom::End(v) => self.len.checked_add_signed(v),
+            SeekFrom::Current(v) => self.position().checked_add_signed(v),
+        }
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Seek before start"))?;
+        // decoders probing the format seek back within the first chunk a lot
+        let chunk_end = self.chunk_start + self.chunk.len() as u64;
+        if (self.chunk_start..=chunk_end).contains(&target) {
+            self.offset = (target - self.chunk_start) as usize;
+            return Ok(target);
+        }
+        self.chunks = Some(prefetch(&self.path, target)?);
+        self.chunk = Vec::new();
+        self.chunk_start = target;
+        self.offset = 0;
+        Ok(target)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::prelude::*;
+
+    #[test]
+    fn test_read_ahead() {
+        let path = std::env::temp_dir().join("audio_wrench_test_readahead.bin");
+        let mut data = vec![0u8; CHUNK_SIZE * 3 + 1234];
+        rand::thread_rng().fill_bytes(&mut data);
+        std::fs::write(&path, &data).unwrap();
+
+        let mut reader = ReadAhead::open(&path).unwrap();
+        assert_eq!(reader.len(), data.len() as u64);
+        let mut header = [0u8; 64];
+        reader.read_exact(&mut header).unwrap();
+        assert_eq!(header[..], data[..64]);
+        // seek within the current chunk
+        reader.seek(SeekFrom::Start(10)).unwrap();
+        let mut all = Vec::new();
+        reader.read_to_end(&mut all).unwrap();
+        assert_eq!(all[..], data[10..]);
+        let pos = reader.seek(SeekFrom::End(-100)).unwrap();
+        assert_eq!(pos, data.len() as u64 - 100);
+        let mut tail = Vec::new();
+        reader.read_to_end(&mut tail).unwrap();
+        assert_eq!(tail[..], data[data.len() - 100..]);
+        // seek outside the current chunk, restarting the prefetch
+        reader.seek(SeekFrom::Start(CHUNK_SIZE as u64 + 5)).unwrap();
+        reader.seek(SeekFrom::Current(-10)).unwrap();
+        let mut middle = [0u8; 20];
+        reader.read_exact(&mut middle).unwrap();
+        assert_eq!(middle[..], data[CHUNK_SIZE - 5..CHUNK_SIZE + 15]);
+        assert!(reader
+            .seek(SeekFrom::Current(-(data.len() as i64)))
+            .is_err());
+        let _ = std::fs::remove_file(path);
+    }
+}
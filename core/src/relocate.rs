@@ -0,0 +1,131 @@
+//! Re-matching of moved tracks by file name below a new root folder
+
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::prelude::*;
+
+/// Files below a root folder by lowercase file name
+pub struct Index {
+    files: HashMap<OsString, Vec<PathBuf>>,
+}
+
+impl Index {
+    /// Scan `root` recursively, unreadable folders are skipped
+    pub fn scan(root: &Path) -> Result<Self> {
+        if !root.is_dir() {
+            return Err(eyre!("{:?} is no folder", root));
+        }
+        let mut files = Vec::new();
+        let mut folders = vec![root.to_path_buf()];
+        while let Some(folder) = folders.pop() {
+            let entries = match fs::read_dir(&folder) {
+                Ok(v) => v,
+                Err(e) => {
+                    warn!("Can't read {:?}: {}", folder, e);
+                    continue;
+                }
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                match entry.file_type() {
+                    Ok(t) if t.is_dir() => folders.push(path),
+                    Ok(_) => files.push(path),
+                    Err(_) => (),
+                }
+            }
+        }
+        Ok(Self::from_paths(files))
+    }
+
+    fn from_paths(paths: Vec<PathBuf>) -> Self {
+        let mut files: HashMap<OsString, Vec<PathBuf>> = HashMap::new();
+        for path in paths {
+            if let Some(name) = file_key(&path) {
+                files.entry(name).or_default().push(path);
+            }
+        }
+        Self { files }
+    }
+
+    /// New location of `old`, candidates with the same name are told apart by their parent folders
+    pub fn find(&self, old: &Path) -> Option<&PathBuf> {
+        let candidates = self.files.get(&file_key(old)?)?;
+        let mut best: Option<(&PathBuf, usize)> = None;
+        let mut tie = false;
+        for candidate in candidates {
+            let score = common_suffix(old, candidate);
+            match best {
+                Some((_, v)) if v > score => (),
+                Some((_, v)) if v == score => tie = true,
+                _ => {
+                    best = Some((candidate, score));
+                    tie = false;
+                }
+            }
+        }
+        match tie {
+            true => None,
+            false => best.map(|(v, _)| v),
+        }
+    }
+
+    /// New locations of all missing local tracks that could be found
+    pub fn relocate<'a>(
+        &self,
+        tracks: impl Iterator<Item = &'a String>,
+    ) -> HashMap<String, String> {
+        tracks
+            .filter(|t| !t.contains("://") && !Path::new(t.as_str()).exists())
+            .filter_map(|t| {
+                let new = self.find(Path::new(t.as_str()))?;
+                Some((t.clone(), new.to_string_lossy().into_owned()))
+            })
+            .collect()
+    }
+}
+
+fn file_key(path: &Path) -> Option<OsString> {
+    path.file_name()
+        .map(|v| v.to_string_lossy().to_lowercase().into())
+}
+
+/// Amount of equal trailing path components
+fn common_suffix(a: &Path, b: &Path) -> usize {
+    a.components()
+        .rev()
+        .zip(b.components().rev())
+        .take_while(|(a, b)| a == b)
+        .count()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_find() {
+        let index = Index::from_paths(vec![
+            PathBuf::from("/new/rock/album/01.mp3"),
+            PathBuf::from("/new/jazz/album/01.mp3"),
+            PathBuf::from("/new/jazz/Single.MP3"),
+            PathBuf::from("/new/a/same.mp3"),
+            PathBuf::from("/new/b/same.mp3"),
+        ]);
+        assert_eq!(
+            index.find(Path::new("/old/jazz/album/01.mp3")),
+            Some(&PathBuf::from("/new/jazz/album/01.mp3"))
+        );
+        assert_eq!(
+            index.find(Path::new("/old/single.mp3")),
+            Some(&PathBuf::from("/new/jazz/Single.MP3"))
+        );
+        // ambiguous
+        assert_eq!(index.find(Path::new("/old/c/same.mp3")), None);
+        assert_eq!(index.find(Path::new("/old/missing.mp3")), None);
+    }
+}
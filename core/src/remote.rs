@@ -0,0 +1,469 @@
+//! HTTP access for remote libraries and streaming
+
+use std::{
+    fmt,
+    io::{self, Read, Seek, SeekFrom},
+    sync::{Arc, Condvar, Mutex},
+    thread,
+    time::Duration,
+};
+
+use base64::Engine;
+use rand::{distributions::Alphanumeric, Rng};
+use serde_json::{json, Value};
+use ureq::{Agent, AgentBuilder, Request};
+use url::Url;
+
+use crate::prelude::*;
+
+/// Timeout for API requests
+const API_TIMEOUT: Duration = Duration::from_secs(10);
+/// Maximum track size to download, in bytes
+const MAX_DOWNLOAD: u64 = 1024 * 1024 * 1024;
+/// Bytes read from the response at once
+const DOWNLOAD_CHUNK: usize = 64 * 1024;
+
+/// Prefix of Subsonic track ids, followed by the song id
+pub const SUBSONIC: &str = "subsonic://";
+/// Prefix of Jellyfin track ids, followed by the item id
+pub const JELLYFIN: &str = "jellyfin://";
+const SUBSONIC_API_VERSION: &str = "1.16.1";
+const CLIENT_NAME: &str = "audio-wrench";
+
+/// HTTP agent used for API requests
+pub fn agent() -> Agent {
+    AgentBuilder::new()
+        .timeout_connect(API_TIMEOUT)
+        .timeout_read(API_TIMEOUT)
+        .user_agent(concat!("audio-wrench/", env!("CARGO_PKG_VERSION")))
+        .build()
+}
+
+/// Remote library whose tracks are stored without credentials,
+/// they are only added to the requests fetching the tracks
+#[derive(Clone, PartialEq, Eq)]
+pub enum Server {
+    /// Tracks stored as `subsonic://<song id>`, authenticated with salted tokens
+    Subsonic {
+        url: String,
+        user: String,
+        password: String,
+    },
+    /// Tracks stored as `jellyfin://<item id>`, fetched with the token of a login
+    Jellyfin {
+        url: String,
+        user: String,
+        password: String,
+    },
+    /// Basic auth for all URLs starting with `prefix`, e.g. WebDAV folders
+    Basic {
+        prefix: String,
+        user: String,
+        password: String,
+    },
+}
+
+impl fmt::Debug for Server {
+    // without the password, commands are logged
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Server::Subsonic { url, user, .. } => write!(f, "Subsonic({}@{})", user, url),
+            Server::Jellyfin { url, user, .. } => write!(f, "Jellyfin({}@{})", user, url),
+            Server::Basic { prefix, user, .. } => write!(f, "Basic({}@{})", user, prefix),
+        }
+    }
+}
+
+/// Credentials of the configured remote libraries, handed to whoever fetches their tracks
+#[derive(Clone, Default)]
+pub struct Credentials {
+    servers: Vec<Server>,
+    /// Access token of the Jellyfin login, logged in on the first track
+    jellyfin_token: Option<String>,
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("servers", &self.servers)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Credentials {
+    pub fn new(servers: Vec<Server>) -> Self {
+        Self {
+            servers,
+            jellyfin_token: None,
+        }
+    }
+
+    /// Drop sessions, e.g. after a request failed with an expired token
+    pub fn forget_sessions(&mut self) {
+        self.jellyfin_token = None;
+    }
+
+    /// Request downloading `track`, None for local tracks
+    pub fn track_request(&mut self, track: &str) -> Option<Result<Request>> {
+        if let Some(id) = track.strip_prefix(JELLYFIN) {
+            return Some(self.jellyfin_request(id));
+        }
+        if let Some(id) = track.strip_prefix(SUBSONIC) {
+            let server = self.servers.iter().find_map(|s| match s {
+                Server::Subsonic {
+                    url,
+                    user,
+                    password,
+                } => Some((url, user, password)),
+                _ => None,
+            });
+            return Some(match server {
+                Some((url, user, password)) => {
+                    subsonic_url(url, user, password, "stream", &[("id", id)])
+                        .map(|v| agent().get(v.as_str()))
+                }
+                None => Err(eyre!("No Subsonic server configured for {}", track)),
+            });
+        }
+        match Url::parse(track) {
+            Ok(v) if v.scheme() == "http" || v.scheme() == "https" => {
+                Some(Ok(self.request("GET", track)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Request with basic auth if a server covers `url`
+    pub fn request(&self, method: &str, url: &str) -> Request {
+        let request = agent().request(method, url);
+        let basic = self.servers.iter().find_map(|s| match s {
+            Server::Basic {
+                prefix,
+                user,
+                password,
+            } if url.starts_with(prefix.as_str()) => Some((user, password)),
+            _ => None,
+        });
+        match basic {
+            Some((user, password)) => {
+                let token = base64::engine::general_purpose::STANDARD
+                    .encode(format!("{}:{}", user, password));
+                request.set("Authorization", &format!("Basic {}", token))
+            }
+            None => request,
+        }
+    }
+
+    /// Fetch text document, e.g. a playlist
+    pub fn fetch_string(&self, url: &str) -> Result<String> {
+        self.request("GET", url)
+            .call()
+            .wrap_err_with(|| format!("Can't fetch {}", url))?
+            .into_string()
+            .wrap_err("Invalid text document")
+    }
+
+    /// Stream request of Jellyfin item `id`, the token is sent as header
+    fn jellyfin_request(&mut self, id: &str) -> Result<Request> {
+        let (url, user, password) = self
+            .servers
+            .iter()
+            .find_map(|s| match s {
+                Server::Jellyfin {
+                    url,
+                    user,
+                    password,
+                } => Some((url, user, password)),
+                _ => None,
+            })
+            .ok_or_else(|| eyre!("No Jellyfin server configured for {}{}", JELLYFIN, id))?;
+        let token = match self.jellyfin_token {
+            Some(ref v) => v.clone(),
+            None => jellyfin_login(url, user, password)?.token,
+        };
+        let mut stream = Url::parse(url)?.join(&format!("Audio/{}/stream", id))?;
+        stream.query_pairs_mut().append_pair("static", "true");
+        let request = agent().get(stream.as_str()).set("X-Emby-Token", &token);
+        self.jellyfin_token = Some(token);
+        Ok(request)
+    }
+}
+
+/// Session of a Jellyfin login
+#[derive(Debug, Clone)]
+pub struct JellyfinSession {
+    pub token: String,
+    pub user_id: String,
+}
+
+/// `X-Emby-Authorization` header of Jellyfin requests, including the token once logged in
+pub fn jellyfin_authorization(user: &str, token: Option<&str>) -> String {
+    let mut header = format!(
+        "MediaBrowser Client=\"{}\", Device=\"desktop\", DeviceId=\"{}-{}\", Version=\"{}\"",
+        CLIENT_NAME,
+        CLIENT_NAME,
+        user,
+        env!("CARGO_PKG_VERSION")
+    );
+    if let Some(token) = token {
+        header.push_str(&format!(", Token=\"{}\"", token));
+    }
+    header
+}
+
+/// Login at the Jellyfin server at `url`
+pub fn jellyfin_login(url: &str, user: &str, password: &str) -> Result<JellyfinSession> {
+    let response: Value = agent()
+        .post(Url::parse(url)?.join("Users/AuthenticateByName")?.as_str())
+        .set("X-Emby-Authorization", &jellyfin_authorization(user, None))
+        .send_json(json!({
+            "Username": user,
+            "Pw": password,
+        }))
+        .wrap_err("Jellyfin login failed")?
+        .into_json()?;
+    let token = response["AccessToken"]
+        .as_str()
+        .ok_or_else(|| eyre!("No access token in jellyfin login response"))?;
+    let user_id = response["User"]["Id"]
+        .as_str()
+        .ok_or_else(|| eyre!("No user in jellyfin login response"))?;
+    Ok(JellyfinSession {
+        token: token.to_string(),
+        user_id: user_id.to_string(),
+    })
+}
+
+/// Subsonic API URL of `method`, authenticated with a salted token of `password`
+pub fn subsonic_url(
+    url: &str,
+    user: &str,
+    password: &str,
+    method: &str,
+    params: &[(&str, &str)],
+) -> Result<Url> {
+    let mut url = Url::parse(url)?.join(&format!("rest/{}", method))?;
+    let salt: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(12)
+        .map(char::from)
+        .collect();
+    let token = format!("{:x}", md5::compute(format!("{}{}", password, salt)));
+    url.query_pairs_mut()
+        .append_pair("u", user)
+        .append_pair("t", &token)
+        .append_pair("s", &salt)
+        .append_pair("v", SUBSONIC_API_VERSION)
+        .append_pair("c", CLIENT_NAME)
+        .append_pair("f", "json")
+        .extend_pairs(params);
+    Ok(url)
+}
+
+/// Track downloaded on a worker thread, readable while the download is running
+#[derive(Clone)]
+pub struct Download {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    state: Mutex<DownloadState>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct DownloadState {
+    data: Vec<u8>,
+    done: bool,
+    error: Option<String>,
+}
+
+impl Download {
+    /// Send `request` and download the body in the background.
+    /// `location` names the track in errors and must not contain credentials.
+    pub fn start(request: Request, location: &str) -> Result<Self> {
+        let response = request
+            .call()
+            .wrap_err_with(|| format!("Can't fetch {}", location))?;
+        let length: Option<u64> = response
+            .header("Content-Length")
+            .and_then(|v| v.parse().ok());
+        if length.is_some_and(|v| v > MAX_DOWNLOAD) {
+            return Err(eyre!(
+                "{} is larger than the download limit of {} MiB",
+                location,
+                MAX_DOWNLOAD / 1024 / 1024
+            ));
+        }
+        let shared = Arc::new(Shared {
+            state: Mutex::new(DownloadState::default()),
+            changed: Condvar::new(),
+        });
+        let worker = shared.clone();
+        let location = location.to_string();
+        thread::Builder::new()
+            .name("download".to_string())
+            .spawn(move || {
+                let result = fetch_body(response.into_reader(), &worker);
+                if let Err(ref e) = result {
+                    warn!("Download of {} failed: {}", location, e);
+                }
+                let mut state = worker.state.lock().expect("Can't lock download");
+                state.done = true;
+                state.error = result.err();
+                worker.changed.notify_all();
+            })?;
+        Ok(Self { shared })
+    }
+
+    pub fn reader(&self) -> DownloadReader {
+        DownloadReader {
+            shared: self.shared.clone(),
+            position: 0,
+        }
+    }
+}
+
+/// Read `body` into the shared buffer, stops once no reader is left
+fn fetch_body(mut body: impl Read, shared: &Arc<Shared>) -> std::result::Result<(), String> {
+    let mut chunk = vec![0; DOWNLOAD_CHUNK];
+    loop {
+        let n = match body.read(&mut chunk) {
+            Ok(0) => return Ok(()),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.to_string()),
+        };
+        let mut state = shared.state.lock().expect("Can't lock download");
+        if state.data.len() as u64 + n as u64 > MAX_DOWNLOAD {
+            return Err(format!(
+                "Track is larger than the download limit of {} MiB",
+                MAX_DOWNLOAD / 1024 / 1024
+            ));
+        }
+        state.data.extend_from_slice(&chunk[..n]);
+        shared.changed.notify_all();
+        drop(state);
+        if Arc::strong_count(shared) == 1 {
+            return Err(String::from("Download no longer needed"));
+        }
+    }
+}
+
+/// Reader of a [`Download`], waiting for data that isn't there yet
+pub struct DownloadReader {
+    shared: Arc<Shared>,
+    position: u64,
+}
+
+impl Read for DownloadReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut state = self.shared.state.lock().expect("Can't lock download");
+        loop {
+            if (self.position as usize) < state.data.len() {
+                let available = &state.data[self.position as usize..];
+                let n = buf.len().min(available.len());
+                buf[..n].copy_from_slice(&available[..n]);
+                self.position += n as u64;
+                return Ok(n);
+            }
+            if let Some(ref e) = state.error {
+                return Err(io::Error::other(e.clone()));
+            }
+            if state.done {
+                return Ok(0);
+            }
+            state = self
+                .shared
+                .changed
+                .wait(state)
+                .expect("Can't lock download");
+        }
+    }
+}
+
+impl Seek for DownloadReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(v) => Some(v),
+            SeekFrom::Current(v) => self.position.checked_add_signed(v),
+            SeekFrom::End(v) => {
+                // the length is only known once everything arrived
+                let mut state = self.shared.state.lock().expect("Can't lock download");
+                while !state.done {
+                    state = self
+                        .shared
+                        .changed
+                        .wait(state)
+                        .expect("Can't lock download");
+                }
+                if let Some(ref e) = state.error {
+                    return Err(io::Error::other(e.clone()));
+                }
+                (state.data.len() as u64).checked_add_signed(v)
+            }
+        }
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Seek before start"))?;
+        self.position = target;
+        Ok(target)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_download_reader() {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(DownloadState::default()),
+            changed: Condvar::new(),
+        });
+        let mut reader = Download {
+            shared: shared.clone(),
+        }
+        .reader();
+        let worker = shared.clone();
+        let handle = thread::spawn(move || {
+            let result = fetch_body(&b"0123456789"[..], &worker);
+            let mut state = worker.state.lock().unwrap();
+            state.done = true;
+            state.error = result.err();
+            worker.changed.notify_all();
+        });
+        assert_eq!(reader.seek(SeekFrom::End(-4)).unwrap(), 6);
+        let mut tail = String::new();
+        reader.read_to_string(&mut tail).unwrap();
+        assert_eq!(tail, "6789");
+        reader.seek(SeekFrom::Start(2)).unwrap();
+        let mut part = [0u8; 3];
+        reader.read_exact(&mut part).unwrap();
+        assert_eq!(&part, b"234");
+        handle.join().unwrap();
+
+        let mut credentials = Credentials::new(vec![Server::Subsonic {
+            url: String::from("https://music.example.com/"),
+            user: String::from("me"),
+            password: String::from("secret"),
+        }]);
+        let request = credentials.track_request("subsonic://42").unwrap().unwrap();
+        assert!(request
+            .url()
+            .starts_with("https://music.example.com/rest/stream?u=me&t="));
+        assert!(!format!("{:?}", credentials).contains("secret"));
+        assert!(credentials.track_request("/music/song.mp3").is_none());
+        let credentials = Credentials::new(vec![Server::Basic {
+            prefix: String::from("https://dav.example.com/music/"),
+            user: String::from("me"),
+            password: String::from("secret"),
+        }]);
+        let request = credentials.request("GET", "https://dav.example.com/music/a.mp3");
+        assert_eq!(request.header("Authorization"), Some("Basic bWU6c2VjcmV0"));
+        let request = credentials.request("GET", "https://dav.example.com/other.mp3");
+        assert_eq!(request.header("Authorization"), None);
+        assert!(Credentials::default()
+            .track_request("subsonic://42")
+            .unwrap()
+            .is_err());
+    }
+}
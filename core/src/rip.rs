@@ -0,0 +1,422 @@
+//! Ripping audio CD tracks to FLAC or MP3 in the background.
+//! Tracks are read with `cdparanoia` and encoded with `ffmpeg`. With verification every
+//! track is read twice and kept only if the AccurateRip checksums of both reads match.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, Receiver, Sender},
+        Arc,
+    },
+    thread,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/// Samples per CD frame, AccurateRip skips 5 frames at the start and end of the disc
+const FRAME_SAMPLES: usize = 588;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum RipFormat {
+    #[default]
+    Flac,
+    Mp3V0,
+    Mp3_320,
+    Mp3_192,
+}
+
+impl RipFormat {
+    pub const ALL: [RipFormat; 4] = [
+        RipFormat::Flac,
+        RipFormat::Mp3V0,
+        RipFormat::Mp3_320,
+        RipFormat::Mp3_192,
+    ];
+
+    fn extension(self) -> &'static str {
+        match self {
+            RipFormat::Flac => "flac",
+            _ => "mp3",
+        }
+    }
+
+    /// Codec arguments of ffmpeg
+    fn codec(self) -> &'static [&'static str] {
+        match self {
+            RipFormat::Flac => &["-c:a", "flac", "-compression_level", "8"],
+            RipFormat::Mp3V0 => &["-c:a", "libmp3lame", "-q:a", "0"],
+            RipFormat::Mp3_320 => &["-c:a", "libmp3lame", "-b:a", "320k"],
+            RipFormat::Mp3_192 => &["-c:a", "libmp3lame", "-b:a", "192k"],
+        }
+    }
+}
+
+impl std::fmt::Display for RipFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RipFormat::Flac => "FLAC",
+            RipFormat::Mp3V0 => "MP3 V0",
+            RipFormat::Mp3_320 => "MP3 320 kbit/s",
+            RipFormat::Mp3_192 => "MP3 192 kbit/s",
+        })
+    }
+}
+
+/// What to rip and where to
+#[derive(Debug, Clone)]
+pub struct RipJob {
+    pub device: PathBuf,
+    /// Track numbers and titles
+    pub tracks: Vec<(u32, String)>,
+    /// Number of tracks on the disc, for the checksum of the last track
+    pub disc_tracks: u32,
+    pub artist: String,
+    pub album: String,
+    pub format: RipFormat,
+    pub verify: bool,
+    /// Files are written to `<folder>/<artist>/<album>`
+    pub folder: PathBuf,
+}
+
+/// Update of a running [`Ripper`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RipProgress {
+    /// Tracks done so far out of all tracks
+    Ripping {
+        done: usize,
+        total: usize,
+    },
+    Finished(RipSummary),
+    /// Ripping didn't start or stopped early
+    Failed(String),
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RipSummary {
+    /// Encoded files
+    pub files: Vec<PathBuf>,
+    /// Tracks whose two reads matched
+    pub verified: usize,
+    /// Tracks that were read differently twice and left out
+    pub mismatched: Vec<u32>,
+}
+
+/// Rip running on its own thread
+pub struct Ripper {
+    rx: Receiver<RipProgress>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Ripper {
+    pub fn start(job: RipJob) -> Self {
+        let (tx, rx) = channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let cancelled = cancel.clone();
+        thread::Builder::new()
+            .name("rip".to_string())
+            .spawn(move || {
+                if let Err(e) = run(&job, &cancelled, &tx) {
+                    warn!("Ripping failed: {:?}", e);
+                    let _ = tx.send(RipProgress::Failed(e.to_string()));
+                }
+            })
+            .expect("Can't spawn rip thread");
+        Self { rx, cancel }
+    }
+
+    /// Updates since the last poll
+    pub fn poll(&self) -> Vec<RipProgress> {
+        self.rx.try_iter().collect()
+    }
+
+    /// Stop after the track that is ripped right now
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+fn run(job: &RipJob, cancel: &AtomicBool, tx: &Sender<RipProgress>) -> Result<()> {
+    let folder = job
+        .folder
+        .join(file_name(&job.artist))
+        .join(file_name(&job.album));
+    fs::create_dir_all(&folder).wrap_err_with(|| format!("Can't create {:?}", folder))?;
+    let wav = std::env::temp_dir().join("audio_wrench_rip.wav");
+    let mut summary = RipSummary::default();
+    let mut log = String::new();
+    let total = job.tracks.len();
+    for (done, (number, title)) in job.tracks.iter().enumerate() {
+        if tx.send(RipProgress::Ripping { done, total }).is_err() || cancel.load(Ordering::Relaxed)
+        {
+            return Err(eyre!("Ripping cancelled"));
+        }
+        let first = *number == 1;
+        let last = *number == job.disc_tracks;
+        let read = |file: &Path| read_track(&job.device, *number, file);
+        let crc = match checked_read(read, &wav, first, last, job.verify)
+            .wrap_err_with(|| format!("Can't read track {}", number))?
+        {
+            Checked::Read(crc) => crc,
+            Checked::Verified(crc) => {
+                summary.verified += 1;
+                crc
+            }
+            Checked::Mismatched(crc) => {
+                warn!("Track {} was read differently twice", number);
+                log.push_str(&format!(
+                    "Track {:02}  {:08X}  read differently twice\n",
+                    number, crc
+                ));
+                summary.mismatched.push(*number);
+                continue;
+            }
+        };
+        log.push_str(&format!("Track {:02}  {:08X}  {}\n", number, crc, title));
+        let file = folder.join(format!(
+            "{:02} {}.{}",
+            number,
+            file_name(title),
+            job.format.extension()
+        ));
+        encode(&wav, &file, job, *number, title)?;
+        summary.files.push(file);
+    }
+    let _ = fs::remove_file(&wav);
+    // checksums for comparing with the AccurateRip database by hand
+    log.insert_str(
+        0,
+        &format!(
+            "{} - {}\nAccurateRip v1 checksums\n\n",
+            job.artist, job.album
+        ),
+    );
+    fs::write(folder.join("rip.log"), log)?;
+    let _ = tx.send(RipProgress::Finished(summary));
+    Ok(())
+}
+
+/// AccurateRip checksum of a read track and the outcome of its verification
+#[derive(Debug, PartialEq, Eq)]
+enum Checked {
+    /// Read once without verification
+    Read(u32),
+    /// Second read had the same checksum
+    Verified(u32),
+    /// Second read differed, the track can't be trusted
+    Mismatched(u32),
+}
+
+/// Read a track with `read` into `wav` and checksum it. With `verify` it's read a second
+/// time next to it and both checksums are compared. `first` and `last` tell whether it's the
+/// first or last track of the disc.
+fn checked_read(
+    read: impl Fn(&Path) -> Result<()>,
+    wav: &Path,
+    first: bool,
+    last: bool,
+    verify: bool,
+) -> Result<Checked> {
+    read(wav)?;
+    let crc = accuraterip_crc(&fs::read(wav)?, first, last).ok_or_else(|| eyre!("Invalid WAV"))?;
+    if !verify {
+        return Ok(Checked::Read(crc));
+    }
+    let check = wav.with_extension("check.wav");
+    let second = read(&check).and_then(|_| Ok(fs::read(&check)?));
+    let _ = fs::remove_file(&check);
+    match accuraterip_crc(&second?, first, last) == Some(crc) {
+        true => Ok(Checked::Verified(crc)),
+        false => Ok(Checked::Mismatched(crc)),
+    }
+}
+
+/// Read track `number` of the disc in `device` into a WAV file
+fn read_track(device: &Path, number: u32, wav: &Path) -> Result<()> {
+    let output = Command::new("cdparanoia")
+        .arg("-q")
+        .arg("-d")
+        .arg(device)
+        .arg("-w")
+        .arg(number.to_string())
+        .arg(wav)
+        .output()
+        .wrap_err("Can't run cdparanoia")?;
+    match output.status.success() {
+        true => Ok(()),
+        false => Err(eyre!(
+            "cdparanoia failed on track {}: {}",
+            number,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+    }
+}
+
+fn encode(wav: &Path, file: &Path, job: &RipJob, number: u32, title: &str) -> Result<()> {
+    let output = Command::new("ffmpeg")
+        .args(["-nostdin", "-y", "-loglevel", "error", "-i"])
+        .arg(wav)
+        .args(job.format.codec())
+        .arg("-metadata")
+        .arg(format!("title={}", title))
+        .arg("-metadata")
+        .arg(format!("artist={}", job.artist))
+        .arg("-metadata")
+        .arg(format!("album={}", job.album))
+        .arg("-metadata")
+        .arg(format!("track={}/{}", number, job.disc_tracks))
+        .arg(file)
+        .output()
+        .wrap_err("Can't run ffmpeg")?;
+    if !output.status.success() {
+        let _ = fs::remove_file(file);
+        return Err(eyre!(
+            "ffmpeg failed on track {}: {}",
+            number,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Name usable as file name on all platforms
+fn file_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    // trailing dots and spaces aren't allowed on Windows
+    match name.trim().trim_end_matches('.') {
+        "" => String::from("Unknown"),
+        v => v.to_string(),
+    }
+}
+
+/// Audio data of a WAV file
+fn wav_data(wav: &[u8]) -> Option<&[u8]> {
+    if wav.get(0..4)? != b"RIFF" || wav.get(8..12)? != b"WAVE" {
+        return None;
+    }
+    let mut pos = 12;
+    while pos + 8 <= wav.len() {
+        let id = &wav[pos..pos + 4];
+        let len =
+            u32::from_le_bytes([wav[pos + 4], wav[pos + 5], wav[pos + 6], wav[pos + 7]]) as usize;
+        let start = pos + 8;
+        if id == b"data" {
+            return wav.get(start..(start + len).min(wav.len()));
+        }
+        // chunks are padded to an even length
+        pos = start + len + len % 2;
+    }
+    None
+}
+
+/// AccurateRip v1 checksum of the 16 bit stereo audio of a track. The first and the
+/// last track of the disc leave out 5 frames at the disc start or end.
+fn accuraterip_crc(wav: &[u8], first: bool, last: bool) -> Option<u32> {
+    let data = wav_data(wav)?;
+    let samples = data.len() / 4;
+    let skip_start = match first {
+        true => FRAME_SAMPLES * 5 - 1,
+        false => 0,
+    };
+    let skip_end = match last {
+        true => samples.saturating_sub(FRAME_SAMPLES * 5),
+        false => samples,
+    };
+    let crc = data
+        .chunks_exact(4)
+        .enumerate()
+        .filter(|(i, _)| *i >= skip_start && *i < skip_end)
+        .fold(0u32, |crc, (i, sample)| {
+            let sample = u32::from_le_bytes([sample[0], sample[1], sample[2], sample[3]]);
+            crc.wrapping_add(sample.wrapping_mul(i as u32 + 1))
+        });
+    Some(crc)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn wav(samples: &[u32]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(b"RIFF\0\0\0\0WAVE");
+        data.extend_from_slice(b"fmt \x10\0\0\0");
+        data.extend_from_slice(&[0; 16]);
+        data.extend_from_slice(b"data");
+        data.extend_from_slice(&(samples.len() as u32 * 4).to_le_bytes());
+        for sample in samples {
+            data.extend_from_slice(&sample.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn test_accuraterip_crc() {
+        assert_eq!(accuraterip_crc(&wav(&[1, 2, 3]), false, false), Some(14));
+        assert_eq!(accuraterip_crc(b"garbage", false, false), None);
+        // only the samples past the first 5 frames count on the first track
+        let mut samples = vec![7; FRAME_SAMPLES * 5 - 1];
+        samples.push(1);
+        assert_eq!(
+            accuraterip_crc(&wav(&samples), true, false),
+            Some(FRAME_SAMPLES as u32 * 5)
+        );
+        assert_eq!(accuraterip_crc(&wav(&samples), true, true), Some(0));
+        assert_eq!(file_name("AC/DC: Live?"), "AC_DC_ Live_");
+        assert_eq!(file_name(" ... "), "Unknown");
+    }
+
+    #[test]
+    fn test_checked_read() {
+        let dir = std::env::temp_dir().join(format!("audio_wrench_rip_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let wav_file = dir.join("track.wav");
+        let reads = std::cell::Cell::new(0);
+        // the drive returns `second` on the second read
+        let drive = |second: &'static [u32]| {
+            let reads = &reads;
+            move |file: &Path| {
+                reads.set(reads.get() + 1);
+                let samples: &[u32] = match reads.get() {
+                    1 => &[1, 2, 3],
+                    _ => second,
+                };
+                Ok(fs::write(file, wav(samples))?)
+            }
+        };
+        let checked = checked_read(drive(&[1, 2, 3]), &wav_file, false, false, true).unwrap();
+        assert_eq!(checked, Checked::Verified(14));
+        assert_eq!(reads.get(), 2);
+        // the verification read is cleaned up, the first one is kept for encoding
+        assert!(!wav_file.with_extension("check.wav").exists());
+        assert!(wav_file.exists());
+
+        reads.set(0);
+        let checked = checked_read(drive(&[1, 2, 4]), &wav_file, false, false, true).unwrap();
+        assert_eq!(checked, Checked::Mismatched(14));
+
+        reads.set(0);
+        let checked = checked_read(drive(&[1, 2, 4]), &wav_file, false, false, false).unwrap();
+        assert_eq!(checked, Checked::Read(14));
+        assert_eq!(reads.get(), 1);
+
+        // an unreadable second read is no mismatch but an error
+        let failing = |file: &Path| match file == wav_file {
+            true => Ok(fs::write(file, wav(&[1, 2, 3]))?),
+            false => Err(eyre!("Drive error")),
+        };
+        assert!(checked_read(failing, &wav_file, false, false, true).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
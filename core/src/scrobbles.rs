@@ -0,0 +1,149 @@
+//! Export of the play history for bulk scrobbling to Last.fm
+
+use std::{collections::HashMap, fs, path::Path};
+
+use chrono::{TimeZone, Utc};
+
+use crate::{metadata::TrackInfo, notes::escape, prelude::*, store::Listen};
+
+/// Written into the client line of the scrobbler log
+const CLIENT: &str = concat!("audio_wrench ", env!("CARGO_PKG_VERSION"));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScrobbleFormat {
+    /// Rockbox `.scrobbler.log`, read by most scrobbling tools
+    #[default]
+    ScrobblerLog,
+    Csv,
+}
+
+impl ScrobbleFormat {
+    pub const ALL: [ScrobbleFormat; 2] = [ScrobbleFormat::ScrobblerLog, ScrobbleFormat::Csv];
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            ScrobbleFormat::ScrobblerLog => ".scrobbler.log",
+            ScrobbleFormat::Csv => "scrobbles.csv",
+        }
+    }
+}
+
+impl std::fmt::Display for ScrobbleFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ScrobbleFormat::ScrobblerLog => "scrobbler.log",
+            ScrobbleFormat::Csv => "CSV",
+        })
+    }
+}
+
+/// Write `history` to `path`, tags are taken from `metadata`
+pub fn write(
+    path: &Path,
+    format: ScrobbleFormat,
+    history: &[Listen],
+    metadata: &HashMap<String, TrackInfo>,
+) -> Result<()> {
+    let data = match format {
+        ScrobbleFormat::ScrobblerLog => render_log(history, metadata),
+        ScrobbleFormat::Csv => render_csv(history, metadata),
+    };
+    fs::write(path, data).wrap_err_with(|| format!("Can't write {:?}", path))
+}
+
+/// Audioscrobbler 1.1 log in UTC, plays without artist or title tag can't be
+/// scrobbled and are left out
+fn render_log(history: &[Listen], metadata: &HashMap<String, TrackInfo>) -> String {
+    let mut log = format!("#AUDIOSCROBBLER/1.1\n#TZ/UTC\n#CLIENT/{}\n", CLIENT);
+    for listen in history {
+        let info = match metadata.get(&listen.track) {
+            Some(v) => v,
+            None => continue,
+        };
+        let (artist, title) = match (info.artist.as_deref(), info.title.as_deref()) {
+            (Some(artist), Some(title)) => (artist, title),
+            _ => continue,
+        };
+        let length = info.length.map_or(0, |v| v.as_secs());
+        // artist, album, title, track number, length, listened, timestamp, musicbrainz id
+        log.push_str(&format!(
+            "{}\t{}\t{}\t\t{}\tL\t{}\t\n",
+            log_field(artist),
+            log_field(info.album.as_deref().unwrap_or_default()),
+            log_field(title),
+            length,
+            listen.played
+        ));
+    }
+    log
+}
+
+/// Tabs and line breaks would start a new field or entry
+fn log_field(value: &str) -> String {
+    value.replace(['\t', '\n', '\r'], " ")
+}
+
+/// One line per play with its UTC time, tags are empty where unknown
+fn render_csv(history: &[Listen], metadata: &HashMap<String, TrackInfo>) -> String {
+    let mut csv = String::from("timestamp,artist,album,title,duration,track\n");
+    let empty = TrackInfo::default();
+    for listen in history {
+        let info = metadata.get(&listen.track).unwrap_or(&empty);
+        let time = Utc
+            .timestamp_opt(listen.played as i64, 0)
+            .single()
+            .map_or_else(String::new, |v| v.format("%Y-%m-%dT%H:%M:%SZ").to_string());
+        let fields = [
+            time,
+            escape(info.artist.as_deref().unwrap_or_default()),
+            escape(info.album.as_deref().unwrap_or_default()),
+            escape(info.title.as_deref().unwrap_or_default()),
+            info.length
+                .map_or_else(String::new, |v| v.as_secs().to_string()),
+            escape(&listen.track),
+        ];
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let listen = |track: &str, played| Listen {
+            track: track.to_string(),
+            played,
+        };
+        let history = [
+            listen("/m/a.mp3", 1_600_000_000),
+            listen("/m/b.mp3", 1_600_000_300),
+        ];
+        let mut metadata = HashMap::new();
+        metadata.insert(
+            String::from("/m/a.mp3"),
+            TrackInfo {
+                artist: Some(String::from("Artist")),
+                title: Some(String::from("Title\twith tab")),
+                album: Some(String::from("Album, Deluxe")),
+                length: Some(Duration::from_secs(215)),
+                ..Default::default()
+            },
+        );
+        let log = render_log(&history, &metadata);
+        assert!(log.starts_with("#AUDIOSCROBBLER/1.1\n#TZ/UTC\n#CLIENT/audio_wrench "));
+        assert!(log.ends_with("\nArtist\tAlbum, Deluxe\tTitle with tab\t\t215\tL\t1600000000\t\n"));
+        assert_eq!(log.lines().count(), 4);
+        assert_eq!(
+            render_csv(&history, &metadata),
+            "timestamp,artist,album,title,duration,track\n\
+            2020-09-13T12:26:40Z,Artist,\"Album, Deluxe\",Title\twith tab,215,/m/a.mp3\n\
+            2020-09-13T12:31:40Z,,,,,/m/b.mp3\n"
+        );
+    }
+}
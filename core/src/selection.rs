@@ -0,0 +1,97 @@
+//! Multi-selection of list entries by index, with Ctrl and Shift clicks like file managers
+
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Selection {
+    selected: BTreeSet<usize>,
+    /// Last clicked entry, ranges extend from here
+    anchor: Option<usize>,
+}
+
+impl Selection {
+    /// Click on entry `index`. Plain clicks select only it, `toggle` (Ctrl) adds or removes it
+    /// and `range` (Shift) selects everything from the last clicked entry.
+    pub fn click(&mut self, index: usize, toggle: bool, range: bool) {
+        match (self.anchor, range) {
+            (Some(anchor), true) => {
+                if !toggle {
+                    self.selected.clear();
+                }
+                self.selected.extend(anchor.min(index)..=anchor.max(index));
+                // the anchor stays, so the range can be changed by another shift click
+                return;
+            }
+            _ if toggle => {
+                if !self.selected.remove(&index) {
+                    self.selected.insert(index);
+                }
+            }
+            _ => {
+                self.selected.clear();
+                self.selected.insert(index);
+            }
+        }
+        self.anchor = Some(index);
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        self.selected.contains(&index)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.selected.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.selected.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.selected.clear();
+        self.anchor = None;
+    }
+
+    /// Selected indices in ascending order
+    pub fn indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.selected.iter().copied()
+    }
+
+    /// Selected entries of `list` in order, indices past its end are skipped
+    pub fn items<'a, T>(&'a self, list: &'a [T]) -> impl Iterator<Item = &'a T> + 'a {
+        self.indices().filter_map(move |i| list.get(i))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_click() {
+        let mut selection = Selection::default();
+        selection.click(2, false, false);
+        selection.click(5, false, true);
+        assert_eq!(selection.indices().collect::<Vec<_>>(), vec![2, 3, 4, 5]);
+        // shift again changes the range from the same anchor
+        selection.click(1, false, true);
+        assert_eq!(selection.indices().collect::<Vec<_>>(), vec![1, 2]);
+        selection.click(7, true, false);
+        selection.click(2, true, false);
+        assert_eq!(selection.indices().collect::<Vec<_>>(), vec![1, 7]);
+        // ctrl and shift adds the range to the selection
+        selection.click(5, true, true);
+        assert_eq!(
+            selection.indices().collect::<Vec<_>>(),
+            vec![1, 2, 3, 4, 5, 7]
+        );
+        assert_eq!(
+            selection.items(&["a", "b", "c"]).collect::<Vec<_>>(),
+            vec![&"b", &"c"]
+        );
+        selection.click(4, false, false);
+        assert_eq!(selection.indices().collect::<Vec<_>>(), vec![4]);
+        selection.clear();
+        assert!(selection.is_empty());
+    }
+}
@@ -0,0 +1,744 @@
+//! Library state in an embedded SQLite database: queues, favorites, ratings, play counts,
+//! play history, resume positions, cue points, skipped intros and content identities of tracks.
+//! The schema is upgraded on open through the migrations below.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use rusqlite::{
+    params, params_from_iter,
+    types::{Value, ValueRef},
+    Connection, OptionalExtension,
+};
+
+use crate::{cues::Cue, itunes::ItunesTrack, prelude::*};
+
+/// Schema changes in order, the database stores how many were applied as `user_version`
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE playlists (
+        path TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        track TEXT NOT NULL,
+        PRIMARY KEY (path, position)
+    );
+    CREATE TABLE favorites (
+        list TEXT NOT NULL,
+        track TEXT NOT NULL,
+        PRIMARY KEY (list, track)
+    );
+    CREATE TABLE ratings (
+        track TEXT PRIMARY KEY,
+        rating INTEGER NOT NULL
+    );
+    CREATE TABLE plays (
+        track TEXT PRIMARY KEY,
+        count INTEGER NOT NULL,
+        last_played INTEGER NOT NULL
+    );
+    CREATE TABLE resume (
+        track TEXT PRIMARY KEY,
+        position_ms INTEGER NOT NULL
+    );",
+    "CREATE TABLE identities (
+        path TEXT PRIMARY KEY,
+        hash TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        modified INTEGER NOT NULL
+    );
+    CREATE INDEX identities_hash ON identities (hash);",
+    "CREATE TABLE cues (
+        track TEXT NOT NULL,
+        position_ms INTEGER NOT NULL,
+        label TEXT NOT NULL,
+        PRIMARY KEY (track, position_ms)
+    );",
+    "CREATE TABLE intros (
+        track TEXT PRIMARY KEY,
+        offset_ms INTEGER NOT NULL
+    );
+    CREATE TABLE album_intros (
+        album TEXT PRIMARY KEY,
+        offset_ms INTEGER NOT NULL
+    );",
+    // earlier plays were only counted, their last one starts the history
+    "CREATE TABLE history (
+        track TEXT NOT NULL,
+        played INTEGER NOT NULL
+    );
+    CREATE INDEX history_played ON history (played);
+    INSERT INTO history (track, played) SELECT track, last_played FROM plays;",
+    // played through playlists, kept to be opened again
+    "CREATE TABLE complete_playlists (
+        path TEXT PRIMARY KEY
+    );",
+];
+
+/// Tables with data by track and their track column, moved along with the track
+const TRACK_TABLES: &[(&str, &str)] = &[
+    ("ratings", "track"),
+    ("plays", "track"),
+    ("resume", "track"),
+    ("cues", "track"),
+    ("intros", "track"),
+    ("history", "track"),
+    ("identities", "path"),
+];
+
+/// Tables with statistics of tracks and their columns, carried by settings archives
+const STATISTICS_TABLES: &[(&str, &[&str])] = &[
+    ("ratings", &["track", "rating"]),
+    ("plays", &["track", "count", "last_played"]),
+    ("resume", &["track", "position_ms"]),
+    ("cues", &["track", "position_ms", "label"]),
+    ("intros", &["track", "offset_ms"]),
+    ("album_intros", &["album", "offset_ms"]),
+    ("history", &["track", "played"]),
+];
+
+/// Rows of the statistics tables by table name, as written into settings archives
+pub type Statistics = BTreeMap<String, Vec<Vec<serde_json::Value>>>;
+
+/// Highest rating, ratings go from 1 to this
+pub const MAX_RATING: u8 = 5;
+
+pub struct Store {
+    conn: Connection,
+}
+
+/// Complete plays of a track
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Plays {
+    pub count: u64,
+    /// Unix time in seconds
+    pub last_played: u64,
+}
+
+/// Complete play of a track
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Listen {
+    pub track: String,
+    /// Unix time in seconds
+    pub played: u64,
+}
+
+/// Content hash of a file, with size and modification time it was hashed at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    pub hash: String,
+    pub size: u64,
+    /// Unix time in seconds
+    pub modified: u64,
+}
+
+impl Store {
+    /// Open or create the database at `path` and apply pending migrations
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn =
+            Connection::open(path).wrap_err_with(|| format!("Can't open database {:?}", path))?;
+        let mut store = Self { conn };
+        store.migrate()?;
+        Ok(store)
+    }
+
+    #[cfg(test)]
+    fn open_in_memory() -> Result<Self> {
+        let mut store = Self {
+            conn: Connection::open_in_memory()?,
+        };
+        store.migrate()?;
+        Ok(store)
+    }
+
+    fn migrate(&mut self) -> Result<()> {
+        let version: usize = self
+            .conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version > MIGRATIONS.len() {
+            return Err(eyre!(
+                "Database schema {} is newer than supported {}",
+                version,
+                MIGRATIONS.len()
+            ));
+        }
+        for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            debug!("Migrating database to schema {}", i + 1);
+            let tx = self.conn.transaction()?;
+            tx.execute_batch(migration)
+                .wrap_err_with(|| format!("Database migration {} failed", i + 1))?;
+            tx.pragma_update(None, "user_version", i + 1)?;
+            tx.commit()?;
+        }
+        Ok(())
+    }
+
+    /// Queues by playlist, complete playlists have an empty one
+    pub fn playlists(&self) -> Result<HashMap<PathBuf, Vec<String>>> {
+        let mut playlists: HashMap<PathBuf, Vec<String>> = HashMap::new();
+        let mut stmt = self.conn.prepare("SELECT path FROM complete_playlists")?;
+        for path in stmt.query_map([], |row| row.get::<_, String>(0))? {
+            playlists.insert(PathBuf::from(path?), Vec::new());
+        }
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, track FROM playlists ORDER BY path, position")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        for row in rows {
+            let (path, track) = row?;
+            playlists
+                .entry(PathBuf::from(path))
+                .or_default()
+                .push(track);
+        }
+        Ok(playlists)
+    }
+
+    /// Replace all stored queues
+    pub fn set_playlists(&mut self, playlists: &HashMap<PathBuf, Vec<String>>) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM playlists", [])?;
+        tx.execute("DELETE FROM complete_playlists", [])?;
+        {
+            let mut insert =
+                tx.prepare("INSERT INTO playlists (path, position, track) VALUES (?1, ?2, ?3)")?;
+            let mut complete = tx.prepare("INSERT INTO complete_playlists (path) VALUES (?1)")?;
+            for (path, tracks) in playlists {
+                let path = path.to_string_lossy();
+                if tracks.is_empty() {
+                    complete.execute(params![path])?;
+                }
+                for (position, track) in tracks.iter().enumerate() {
+                    insert.execute(params![path, position as i64, track])?;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn favorites(&self) -> Result<HashMap<String, HashSet<String>>> {
+        let mut stmt = self.conn.prepare("SELECT list, track FROM favorites")?;
+        let mut lists: HashMap<String, HashSet<String>> = HashMap::new();
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        for row in rows {
+            let (list, track) = row?;
+            lists.entry(list).or_default().insert(track);
+        }
+        Ok(lists)
+    }
+
+    /// Replace all favorite lists, lists without tracks aren't kept
+    pub fn set_favorites(&mut self, lists: &HashMap<String, HashSet<String>>) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM favorites", [])?;
+        {
+            let mut insert = tx.prepare("INSERT INTO favorites (list, track) VALUES (?1, ?2)")?;
+            for (list, tracks) in lists {
+                for track in tracks {
+                    insert.execute(params![list, track])?;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Rating from 1 to [`MAX_RATING`], None if unrated
+    pub fn rating(&self, track: &str) -> Result<Option<u8>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT rating FROM ratings WHERE track = ?1",
+                [track],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    /// Rate `track`, None removes the rating
+    pub fn set_rating(&self, track: &str, rating: Option<u8>) -> Result<()> {
+        match rating {
+            Some(v) => self.conn.execute(
+                "INSERT INTO ratings (track, rating) VALUES (?1, ?2)
+                ON CONFLICT(track) DO UPDATE SET rating = excluded.rating",
+                params![track, v.min(MAX_RATING)],
+            )?,
+            None => self
+                .conn
+                .execute("DELETE FROM ratings WHERE track = ?1", [track])?,
+        };
+        Ok(())
+    }
+
+    /// Count a complete play of `track` at unix time `now`
+    pub fn record_play(&mut self, track: &str, now: u64) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO plays (track, count, last_played) VALUES (?1, 1, ?2)
+            ON CONFLICT(track) DO UPDATE SET count = count + 1, last_played = excluded.last_played",
+            params![track, now as i64],
+        )?;
+        tx.execute(
+            "INSERT INTO history (track, played) VALUES (?1, ?2)",
+            params![track, now as i64],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// All recorded plays, oldest first
+    pub fn history(&self) -> Result<Vec<Listen>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT track, played FROM history ORDER BY played")?;
+        let rows = stmt.query_map([], |row| {
+            Ok(Listen {
+                track: row.get(0)?,
+                played: row.get::<_, i64>(1)? as u64,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn play_count(&self, track: &str) -> Result<u64> {
+        let count: Option<i64> = self
+            .conn
+            .query_row("SELECT count FROM plays WHERE track = ?1", [track], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        Ok(count.unwrap_or_default() as u64)
+    }
+
+    /// Plays of all played tracks
+    pub fn plays(&self) -> Result<HashMap<String, Plays>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT track, count, last_played FROM plays")?;
+        let rows = stmt.query_map([], |row| {
+            let plays = Plays {
+                count: row.get::<_, i64>(1)? as u64,
+                last_played: row.get::<_, i64>(2)? as u64,
+            };
+            Ok((row.get::<_, String>(0)?, plays))
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Merge ratings and plays of another player, existing ratings are kept
+    /// and play counts and times take the higher value
+    pub fn import_stats(&mut self, tracks: &[ItunesTrack]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut rate = tx.prepare(
+                "INSERT INTO ratings (track, rating) VALUES (?1, ?2)
+                ON CONFLICT(track) DO NOTHING",
+            )?;
+            let mut play = tx.prepare(
+                "INSERT INTO plays (track, count, last_played) VALUES (?1, ?2, ?3)
+                ON CONFLICT(track) DO UPDATE SET count = max(count, excluded.count),
+                last_played = max(last_played, excluded.last_played)",
+            )?;
+            for track in tracks {
+                if let Some(rating) = track.rating {
+                    rate.execute(params![track.location, rating.min(MAX_RATING)])?;
+                }
+                if track.play_count > 0 {
+                    let last_played = track.last_played.unwrap_or_default();
+                    play.execute(params![
+                        track.location,
+                        track.play_count as i64,
+                        last_played as i64
+                    ])?;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Position playback of `track` was left at
+    pub fn resume_position(&self, track: &str) -> Result<Option<Duration>> {
+        let position: Option<i64> = self
+            .conn
+            .query_row(
+                "SELECT position_ms FROM resume WHERE track = ?1",
+                [track],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(position.map(|v| Duration::from_millis(v as u64)))
+    }
+
+    /// Remember where `track` was left, None once it was played to the end
+    pub fn set_resume_position(&self, track: &str, position: Option<Duration>) -> Result<()> {
+        match position {
+            Some(v) => self.conn.execute(
+                "INSERT INTO resume (track, position_ms) VALUES (?1, ?2)
+                ON CONFLICT(track) DO UPDATE SET position_ms = excluded.position_ms",
+                params![track, v.as_millis() as i64],
+            )?,
+            None => self
+                .conn
+                .execute("DELETE FROM resume WHERE track = ?1", [track])?,
+        };
+        Ok(())
+    }
+
+    /// Cue points of `track` by position
+    pub fn cues(&self, track: &str) -> Result<Vec<Cue>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT position_ms, label FROM cues WHERE track = ?1 ORDER BY position_ms")?;
+        let rows = stmt.query_map([track], |row| {
+            Ok(Cue {
+                position: Duration::from_millis(row.get::<_, i64>(0)? as u64),
+                label: row.get(1)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Add a cue point to `track`, replacing one at the same position
+    pub fn add_cue(&self, track: &str, cue: &Cue) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO cues (track, position_ms, label) VALUES (?1, ?2, ?3)",
+            params![track, cue.position.as_millis() as i64, cue.label],
+        )?;
+        Ok(())
+    }
+
+    pub fn remove_cue(&self, track: &str, position: Duration) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM cues WHERE track = ?1 AND position_ms = ?2",
+            params![track, position.as_millis() as i64],
+        )?;
+        Ok(())
+    }
+
+    /// Intro skipped when `track` starts, falls back to the one of its album
+    pub fn intro(&self, track: &str, album: Option<&str>) -> Result<Option<Duration>> {
+        let mut offset: Option<i64> = self
+            .conn
+            .query_row(
+                "SELECT offset_ms FROM intros WHERE track = ?1",
+                [track],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if let (None, Some(album)) = (offset, album) {
+            offset = self
+                .conn
+                .query_row(
+                    "SELECT offset_ms FROM album_intros WHERE album = ?1",
+                    [album],
+                    |row| row.get(0),
+                )
+                .optional()?;
+        }
+        Ok(offset.map(|v| Duration::from_millis(v as u64)))
+    }
+
+    /// Skip the intro of `track` up to `offset`, None removes it
+    pub fn set_intro(&self, track: &str, offset: Option<Duration>) -> Result<()> {
+        match offset {
+            Some(v) => self.conn.execute(
+                "INSERT INTO intros (track, offset_ms) VALUES (?1, ?2)
+                ON CONFLICT(track) DO UPDATE SET offset_ms = excluded.offset_ms",
+                params![track, v.as_millis() as i64],
+            )?,
+            None => self
+                .conn
+                .execute("DELETE FROM intros WHERE track = ?1", [track])?,
+        };
+        Ok(())
+    }
+
+    /// Skip the intro of all tracks of `album` without their own, None removes it
+    pub fn set_album_intro(&self, album: &str, offset: Option<Duration>) -> Result<()> {
+        match offset {
+            Some(v) => self.conn.execute(
+                "INSERT INTO album_intros (album, offset_ms) VALUES (?1, ?2)
+                ON CONFLICT(album) DO UPDATE SET offset_ms = excluded.offset_ms",
+                params![album, v.as_millis() as i64],
+            )?,
+            None => self
+                .conn
+                .execute("DELETE FROM album_intros WHERE album = ?1", [album])?,
+        };
+        Ok(())
+    }
+
+    pub fn identity(&self, path: &str) -> Result<Option<Identity>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT hash, size, modified FROM identities WHERE path = ?1",
+                [path],
+                |row| {
+                    Ok(Identity {
+                        hash: row.get(0)?,
+                        size: row.get::<_, i64>(1)? as u64,
+                        modified: row.get::<_, i64>(2)? as u64,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    pub fn set_identity(&self, path: &str, identity: &Identity) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO identities (path, hash, size, modified) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                path,
+                identity.hash,
+                identity.size as i64,
+                identity.modified as i64
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn remove_identity(&self, path: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM identities WHERE path = ?1", [path])?;
+        Ok(())
+    }
+
+    /// All paths with content `hash`, copies of a file share it
+    pub fn paths_with_hash(&self, hash: &str) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path FROM identities WHERE hash = ?1")?;
+        let paths = stmt
+            .query_map([hash], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(paths)
+    }
+
+    /// Ratings, plays, resume positions, cues, intros and the play history
+    pub fn statistics(&self) -> Result<Statistics> {
+        let mut statistics = Statistics::new();
+        for (table, columns) in STATISTICS_TABLES {
+            let mut stmt =
+                self.conn
+                    .prepare(&format!("SELECT {} FROM {}", columns.join(", "), table))?;
+            let rows = stmt.query_map([], |row| {
+                (0..columns.len())
+                    .map(|i| row.get_ref(i).map(to_json))
+                    .collect::<rusqlite::Result<Vec<_>>>()
+            })?;
+            statistics.insert(table.to_string(), rows.collect::<rusqlite::Result<_>>()?);
+        }
+        Ok(statistics)
+    }
+
+    /// Replace the statistics by `statistics`, tables it doesn't contain are kept
+    pub fn set_statistics(&mut self, statistics: &Statistics) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for (table, columns) in STATISTICS_TABLES {
+            let rows = match statistics.get(*table) {
+                Some(v) => v,
+                None => continue,
+            };
+            tx.execute(&format!("DELETE FROM {}", table), [])?;
+            let placeholders: Vec<String> =
+                (1..=columns.len()).map(|i| format!("?{}", i)).collect();
+            let mut insert = tx.prepare(&format!(
+                "INSERT OR REPLACE INTO {} ({}) VALUES ({})",
+                table,
+                columns.join(", "),
+                placeholders.join(", ")
+            ))?;
+            for row in rows {
+                if row.len() != columns.len() {
+                    return Err(eyre!("Invalid row in {}: {:?}", table, row));
+                }
+                insert.execute(params_from_iter(row.iter().map(to_sql)))?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Move ratings, plays, resume position and identity of track `old` to `new`,
+    /// replacing any of `new`
+    pub fn rename_track(&mut self, old: &str, new: &str) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for (table, column) in TRACK_TABLES {
+            tx.execute(
+                &format!(
+                    "UPDATE OR REPLACE {} SET {} = ?2 WHERE {} = ?1",
+                    table, column, column
+                ),
+                [old, new],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+fn to_json(value: ValueRef<'_>) -> serde_json::Value {
+    match value {
+        ValueRef::Integer(v) => v.into(),
+        ValueRef::Real(v) => v.into(),
+        ValueRef::Text(v) => String::from_utf8_lossy(v).into(),
+        ValueRef::Null | ValueRef::Blob(_) => serde_json::Value::Null,
+    }
+}
+
+fn to_sql(value: &serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(v) => Value::Integer(*v as i64),
+        serde_json::Value::Number(v) => match v.as_i64() {
+            Some(v) => Value::Integer(v),
+            None => Value::Real(v.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(v) => Value::Text(v.clone()),
+        v => Value::Text(v.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_store() {
+        let mut store = Store::open_in_memory().unwrap();
+        // migrations aren't applied twice
+        store.migrate().unwrap();
+
+        let mut playlists = HashMap::new();
+        playlists.insert(
+            PathBuf::from("/p/a.m3u"),
+            vec![String::from("/m/2.mp3"), String::from("/m/1.mp3")],
+        );
+        playlists.insert(PathBuf::from("/p/done.m3u"), Vec::new());
+        store.set_playlists(&playlists).unwrap();
+        assert_eq!(store.playlists().unwrap(), playlists);
+
+        let mut favorites = HashMap::new();
+        favorites.insert(
+            String::from("Default"),
+            HashSet::from([String::from("/m/1.mp3")]),
+        );
+        store.set_favorites(&favorites).unwrap();
+        assert_eq!(store.favorites().unwrap(), favorites);
+
+        store.set_rating("/m/1.mp3", Some(9)).unwrap();
+        assert_eq!(store.rating("/m/1.mp3").unwrap(), Some(MAX_RATING));
+        store.set_rating("/m/1.mp3", None).unwrap();
+        assert_eq!(store.rating("/m/1.mp3").unwrap(), None);
+
+        store.record_play("/m/1.mp3", 10).unwrap();
+        store.record_play("/m/1.mp3", 20).unwrap();
+        assert_eq!(store.play_count("/m/1.mp3").unwrap(), 2);
+        assert_eq!(store.play_count("/m/2.mp3").unwrap(), 0);
+        let played: Vec<_> = store.history().unwrap().iter().map(|v| v.played).collect();
+        assert_eq!(played, vec![10, 20]);
+
+        let imported = |location: &str, rating, play_count| ItunesTrack {
+            location: location.to_string(),
+            rating,
+            play_count,
+            last_played: Some(15),
+        };
+        store.set_rating("/m/1.mp3", Some(2)).unwrap();
+        store
+            .import_stats(&[
+                imported("/m/1.mp3", Some(5), 1),
+                imported("/m/2.mp3", Some(3), 4),
+            ])
+            .unwrap();
+        assert_eq!(store.rating("/m/1.mp3").unwrap(), Some(2));
+        assert_eq!(store.rating("/m/2.mp3").unwrap(), Some(3));
+        let plays = store.plays().unwrap();
+        assert_eq!(
+            (plays["/m/1.mp3"].count, plays["/m/1.mp3"].last_played),
+            (2, 20)
+        );
+        assert_eq!(
+            (plays["/m/2.mp3"].count, plays["/m/2.mp3"].last_played),
+            (4, 15)
+        );
+
+        let position = Some(Duration::from_millis(61_500));
+        store.set_resume_position("/m/1.mp3", position).unwrap();
+        assert_eq!(store.resume_position("/m/1.mp3").unwrap(), position);
+        store.set_resume_position("/m/1.mp3", None).unwrap();
+        assert_eq!(store.resume_position("/m/1.mp3").unwrap(), None);
+
+        let cue = |ms, label: &str| Cue {
+            position: Duration::from_millis(ms),
+            label: label.to_string(),
+        };
+        store.add_cue("/m/1.mp3", &cue(9_000, "b")).unwrap();
+        store.add_cue("/m/1.mp3", &cue(1_000, "a")).unwrap();
+        assert_eq!(
+            store.cues("/m/1.mp3").unwrap(),
+            vec![cue(1_000, "a"), cue(9_000, "b")]
+        );
+        store
+            .remove_cue("/m/1.mp3", Duration::from_millis(1_000))
+            .unwrap();
+        assert_eq!(store.cues("/m/1.mp3").unwrap(), vec![cue(9_000, "b")]);
+
+        let secs = |v| Some(Duration::from_secs(v));
+        store.set_album_intro("Show", secs(45)).unwrap();
+        assert_eq!(store.intro("/m/1.mp3", Some("Show")).unwrap(), secs(45));
+        assert_eq!(store.intro("/m/1.mp3", None).unwrap(), None);
+        store.set_intro("/m/1.mp3", secs(10)).unwrap();
+        assert_eq!(store.intro("/m/1.mp3", Some("Show")).unwrap(), secs(10));
+        store.set_intro("/m/1.mp3", None).unwrap();
+        store.set_album_intro("Show", None).unwrap();
+        assert_eq!(store.intro("/m/1.mp3", Some("Show")).unwrap(), None);
+
+        // statistics move between databases through archives
+        store.set_intro("/m/2.mp3", secs(5)).unwrap();
+        let statistics = store.statistics().unwrap();
+        let mut other = Store::open_in_memory().unwrap();
+        other.set_rating("/m/3.mp3", Some(1)).unwrap();
+        other.set_statistics(&statistics).unwrap();
+        assert_eq!(other.statistics().unwrap(), statistics);
+        assert_eq!(other.rating("/m/3.mp3").unwrap(), None);
+        assert_eq!(other.play_count("/m/1.mp3").unwrap(), 2);
+        assert_eq!(other.cues("/m/1.mp3").unwrap(), vec![cue(9_000, "b")]);
+        assert_eq!(other.intro("/m/2.mp3", None).unwrap(), secs(5));
+        assert_eq!(other.history().unwrap(), store.history().unwrap());
+        // tables missing from older archives are kept
+        other.set_statistics(&Statistics::new()).unwrap();
+        assert_eq!(other.statistics().unwrap(), statistics);
+    }
+
+    #[test]
+    fn test_rename_track() {
+        let mut store = Store::open_in_memory().unwrap();
+        let identity = Identity {
+            hash: String::from("abc"),
+            size: 10,
+            modified: 20,
+        };
+        store.set_identity("/old.mp3", &identity).unwrap();
+        store.set_rating("/old.mp3", Some(4)).unwrap();
+        store.record_play("/old.mp3", 1).unwrap();
+        store.record_play("/new.mp3", 1).unwrap();
+        assert_eq!(store.paths_with_hash("abc").unwrap(), vec!["/old.mp3"]);
+
+        store.rename_track("/old.mp3", "/new.mp3").unwrap();
+        assert_eq!(store.rating("/new.mp3").unwrap(), Some(4));
+        assert_eq!(store.rating("/old.mp3").unwrap(), None);
+        assert_eq!(store.play_count("/new.mp3").unwrap(), 1);
+        assert_eq!(store.identity("/new.mp3").unwrap(), Some(identity));
+        store.remove_identity("/new.mp3").unwrap();
+        assert!(store.paths_with_hash("abc").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,104 @@
+//! User defined colored tags on tracks
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::notes::escape;
+use crate::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tag {
+    pub name: String,
+    /// RGB color, 0.0 to 1.0
+    pub color: [f32; 3],
+}
+
+/// Tags used when none are configured
+pub fn default_tags() -> Vec<Tag> {
+    let tag = |name: &str, color| Tag {
+        name: name.to_string(),
+        color,
+    };
+    vec![
+        tag("red", [0.9, 0.2, 0.2]),
+        tag("yellow", [0.9, 0.8, 0.1]),
+        tag("green", [0.2, 0.7, 0.2]),
+        tag("blue", [0.2, 0.4, 0.9]),
+    ]
+}
+
+/// Add `tag` to `track` or remove it if already set
+pub fn toggle(track_tags: &mut HashMap<String, BTreeSet<String>>, track: &str, tag: &str) {
+    let tags = track_tags.entry(track.to_string()).or_default();
+    if !tags.remove(tag) {
+        tags.insert(tag.to_string());
+    }
+    if tags.is_empty() {
+        track_tags.remove(track);
+    }
+}
+
+/// Add or remove `tag` of `track`, false if it already was that way
+pub fn set(
+    track_tags: &mut HashMap<String, BTreeSet<String>>,
+    track: &str,
+    tag: &str,
+    set: bool,
+) -> bool {
+    let tags = track_tags.entry(track.to_string()).or_default();
+    let changed = match set {
+        true => tags.insert(tag.to_string()),
+        false => tags.remove(tag),
+    };
+    if tags.is_empty() {
+        track_tags.remove(track);
+    }
+    changed
+}
+
+/// Write all tagged tracks as CSV with a track and a tags column, tags separated by `;`
+pub fn write_csv(track_tags: &HashMap<String, BTreeSet<String>>, path: &Path) -> Result<()> {
+    fs::write(path, render_csv(track_tags)).wrap_err_with(|| format!("Can't write {:?}", path))
+}
+
+fn render_csv(track_tags: &HashMap<String, BTreeSet<String>>) -> String {
+    let mut tracks: Vec<_> = track_tags.iter().collect();
+    tracks.sort();
+    let mut csv = String::from("track,tags\n");
+    for (track, tags) in tracks {
+        let tags: Vec<&str> = tags.iter().map(|v| v.as_str()).collect();
+        csv.push_str(&escape(track));
+        csv.push(',');
+        csv.push_str(&escape(&tags.join(";")));
+        csv.push('\n');
+    }
+    csv
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_toggle() {
+        let mut track_tags = HashMap::new();
+        toggle(&mut track_tags, "a.mp3", "red");
+        toggle(&mut track_tags, "a.mp3", "blue");
+        toggle(&mut track_tags, "b.mp3", "red");
+        assert_eq!(
+            render_csv(&track_tags),
+            "track,tags\na.mp3,blue;red\nb.mp3,red\n"
+        );
+        toggle(&mut track_tags, "b.mp3", "red");
+        assert!(!track_tags.contains_key("b.mp3"));
+        assert!(!set(&mut track_tags, "a.mp3", "red", true));
+        assert!(set(&mut track_tags, "a.mp3", "red", false));
+        assert!(!set(&mut track_tags, "b.mp3", "red", false));
+        assert!(!track_tags.contains_key("b.mp3"));
+    }
+}
@@ -0,0 +1,503 @@
+//! Copying tracks to a folder or USB drive in the background, with a check for free space
+//! beforehand. Files that are too big can be transcoded to MP3 with ffmpeg on the way.
+//! The folders of the tracks below their common folder are kept on the target. Files are
+//! written under a temporary name and renamed when complete.
+//! Targets in an MTP mount are written through [`mtp`].
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, Receiver, Sender},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::metadata;
+use crate::mtp;
+use crate::portable;
+use crate::prelude::*;
+
+/// Bitrate of transcoded files in kbit/s
+const TRANSCODE_BITRATE: u64 = 192;
+
+/// Update of a running [`Transfer`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Progress {
+    /// Files done so far out of all files
+    Copying { done: usize, total: usize },
+    /// Transfer is over, nothing more follows
+    Finished(Summary),
+    /// Transfer didn't start or stopped early
+    Failed(String),
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Summary {
+    pub copied: usize,
+    pub transcoded: usize,
+    /// Already on the target from an earlier transfer
+    pub existing: usize,
+    /// Missing files, remote tracks and failed copies
+    pub failed: usize,
+}
+
+/// Outcome of one file
+enum Sent {
+    /// Already on the target from an earlier transfer
+    Existing,
+    Copied,
+    Transcoded,
+}
+
+/// File of the transfer and its planned size on the target
+struct Item {
+    source: PathBuf,
+    transcode: bool,
+    size: u64,
+}
+
+impl Item {
+    /// Path of the file on the target relative to it, `base` is the common folder of all tracks
+    fn relative(&self, base: &Path) -> PathBuf {
+        let relative = match self.source.strip_prefix(base) {
+            // different drives on Windows have no common folder
+            Ok(v) if !v.has_root() => v.to_path_buf(),
+            _ => self
+                .source
+                .file_name()
+                .map(PathBuf::from)
+                .unwrap_or_default(),
+        };
+        match self.transcode {
+            true => relative.with_extension("mp3"),
+            false => relative,
+        }
+    }
+}
+
+/// Copy of tracks running on its own thread
+pub struct Transfer {
+    rx: Receiver<Progress>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Transfer {
+    /// Copy `tracks` into `target`, files bigger than `transcode_above` bytes are transcoded.
+    /// With a `playlist` name the sent files are listed in `<playlist>.m3u8` next to them.
+    pub fn start(
+        tracks: Vec<String>,
+        target: PathBuf,
+        transcode_above: Option<u64>,
+        playlist: Option<String>,
+    ) -> Self {
+        let (tx, rx) = channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let cancelled = cancel.clone();
+        thread::Builder::new()
+            .name("transfer".to_string())
+            .spawn(move || {
+                let result = run(
+                    &tracks,
+                    &target,
+                    transcode_above,
+                    playlist.as_deref(),
+                    &cancelled,
+                    &tx,
+                );
+                if let Err(e) = result {
+                    warn!("Transfer to {:?} failed: {:?}", target, e);
+                    let _ = tx.send(Progress::Failed(e.to_string()));
+                }
+            })
+            .expect("Can't spawn transfer thread");
+        Self { rx, cancel }
+    }
+
+    /// Updates since the last poll
+    pub fn poll(&self) -> Vec<Progress> {
+        self.rx.try_iter().collect()
+    }
+
+    /// Stop after the file that is copied right now
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+fn run(
+    tracks: &[String],
+    target: &Path,
+    transcode_above: Option<u64>,
+    playlist: Option<&str>,
+    cancel: &AtomicBool,
+    tx: &Sender<Progress>,
+) -> Result<()> {
+    let mut summary = Summary::default();
+    let mut items = Vec::with_capacity(tracks.len());
+    for track in tracks {
+        let source = match portable::local_file(track).filter(|v| v.is_file()) {
+            Some(v) => v,
+            None => {
+                debug!("Can't transfer {}, no local file", track);
+                summary.failed += 1;
+                continue;
+            }
+        };
+        let size = fs::metadata(&source)?.len();
+        // the length is only needed for files that get transcoded
+        let length = transcode_above
+            .is_some_and(|max| size > max)
+            .then(|| metadata::read(track).length)
+            .flatten();
+        items.push(plan(source, size, length, transcode_above));
+    }
+    let device = mtp::uri(target);
+    let free = match device.as_ref() {
+        Some(uri) => {
+            mtp::create_dir_all(uri)?;
+            mtp::free_space(uri)
+        }
+        None => {
+            fs::create_dir_all(target).wrap_err_with(|| format!("Can't create {:?}", target))?;
+            free_space(target)
+        }
+    };
+    check_space(target, &items, free)?;
+    let total = items.len();
+    let base = common_folder(items.iter().map(|v| v.source.as_path()));
+    let mut content = String::from("#EXTM3U\n");
+    for (done, item) in items.iter().enumerate() {
+        if tx.send(Progress::Copying { done, total }).is_err() || cancel.load(Ordering::Relaxed) {
+            return Err(eyre!("Transfer cancelled"));
+        }
+        let relative = item.relative(&base);
+        match transfer(item, &target.join(&relative)) {
+            Ok(Sent::Existing) => summary.existing += 1,
+            Ok(Sent::Copied) => summary.copied += 1,
+            Ok(Sent::Transcoded) => summary.transcoded += 1,
+            Err(e) => {
+                warn!("Can't transfer {:?}: {:?}", item.source, e);
+                summary.failed += 1;
+                continue;
+            }
+        }
+        // playlists use forward slashes on all platforms
+        let entry: Vec<_> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect();
+        content.push_str(&entry.join("/"));
+        content.push('\n');
+    }
+    if let Some(name) = playlist {
+        write_playlist(&content, &target.join(format!("{}.m3u8", name)))?;
+    }
+    let _ = tx.send(Progress::Finished(summary));
+    Ok(())
+}
+
+/// Item for `source` of `size` bytes, transcoded if it's bigger than `transcode_above`.
+/// Its size on the target is estimated from its `length`, without one it's counted in full.
+fn plan(
+    source: PathBuf,
+    size: u64,
+    length: Option<Duration>,
+    transcode_above: Option<u64>,
+) -> Item {
+    let transcode = transcode_above.is_some_and(|max| size > max);
+    let size = match transcode {
+        true => length.map_or(size, |v| transcoded_size(v.as_secs()).min(size)),
+        false => size,
+    };
+    Item {
+        source,
+        transcode,
+        size,
+    }
+}
+
+/// Refuse the transfer if `items` don't fit into `free` bytes, unknown free space is no reason
+fn check_space(target: &Path, items: &[Item], free: Option<u64>) -> Result<()> {
+    let needed: u64 = items.iter().map(|v| v.size).sum();
+    match free {
+        Some(free) if needed > free => Err(eyre!(
+            "Not enough space on {:?}: {} MB needed, {} MB free",
+            target,
+            needed / 1_000_000,
+            free / 1_000_000
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Expected size of a track of `seconds` after transcoding
+fn transcoded_size(seconds: u64) -> u64 {
+    seconds * TRANSCODE_BITRATE * 1000 / 8
+}
+
+/// Deepest folder containing all `files`
+fn common_folder<'a>(files: impl Iterator<Item = &'a Path>) -> PathBuf {
+    let mut base: Option<&Path> = None;
+    for file in files {
+        let folder = file.parent().unwrap_or_else(|| Path::new(""));
+        base = Some(match base {
+            None => folder,
+            Some(v) => v
+                .ancestors()
+                .find(|a| folder.starts_with(a))
+                .unwrap_or_else(|| Path::new("")),
+        });
+    }
+    base.map(Path::to_path_buf).unwrap_or_default()
+}
+
+/// Temporary name of `file` until it's written completely
+fn partial(file: &Path) -> PathBuf {
+    let name = file.file_name().unwrap_or_default().to_string_lossy();
+    file.with_file_name(format!(".{}.part", name))
+}
+
+/// Copy or transcode one file to `file` on the target
+fn transfer(item: &Item, file: &Path) -> Result<Sent> {
+    // only complete files have their final name, a copy of another size is outdated
+    if let Ok(existing) = fs::metadata(file) {
+        if item.transcode || existing.len() == item.size {
+            return Ok(Sent::Existing);
+        }
+    }
+    if let Some(folder) = file.parent() {
+        match mtp::uri(folder) {
+            Some(uri) => mtp::create_dir_all(&uri)?,
+            None => {
+                fs::create_dir_all(folder).wrap_err_with(|| format!("Can't create {:?}", folder))?
+            }
+        }
+    }
+    if !item.transcode {
+        put(&item.source, file)?;
+        return Ok(Sent::Copied);
+    }
+    // devices are written in one go, ffmpeg writes into a local file first
+    let device = mtp::uri(file).is_some();
+    let output_file = match device {
+        true => std::env::temp_dir().join("audio_wrench_transfer.mp3"),
+        false => partial(file),
+    };
+    let output = Command::new("ffmpeg")
+        .args(["-nostdin", "-y", "-loglevel", "error", "-i"])
+        .arg(&item.source)
+        .args(["-vn", "-b:a", &format!("{}k", TRANSCODE_BITRATE)])
+        // the temporary name has no extension ffmpeg knows
+        .args(["-f", "mp3"])
+        .arg(&output_file)
+        .output()
+        .wrap_err("Can't run ffmpeg")?;
+    if !output.status.success() {
+        let _ = fs::remove_file(&output_file);
+        return Err(eyre!(
+            "ffmpeg failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    match device {
+        true => {
+            let result = put(&output_file, file);
+            let _ = fs::remove_file(&output_file);
+            result?;
+        }
+        false => fs::rename(&output_file, file)
+            .wrap_err_with(|| format!("Can't rename to {:?}", file))?,
+    }
+    Ok(Sent::Transcoded)
+}
+
+/// Copy the local file `source` to `file` on the target through a temporary name
+fn put(source: &Path, file: &Path) -> Result<()> {
+    let partial = partial(file);
+    match (mtp::uri(&partial), mtp::uri(file)) {
+        (Some(partial), Some(file)) => {
+            mtp::put(source, &partial)?;
+            mtp::rename(&partial, &file)
+        }
+        _ => {
+            if let Err(e) = fs::copy(source, &partial) {
+                let _ = fs::remove_file(&partial);
+                return Err(e).wrap_err("Can't copy");
+            }
+            fs::rename(&partial, file).wrap_err_with(|| format!("Can't rename to {:?}", file))
+        }
+    }
+}
+
+fn write_playlist(content: &str, file: &Path) -> Result<()> {
+    match mtp::uri(file) {
+        Some(uri) => {
+            let local = std::env::temp_dir().join("audio_wrench_transfer.m3u8");
+            fs::write(&local, content)?;
+            let result = mtp::put(&local, &uri);
+            let _ = fs::remove_file(&local);
+            result
+        }
+        None => fs::write(file, content).wrap_err_with(|| format!("Can't write {:?}", file)),
+    }
+}
+
+/// Free bytes on the file system of `path`, None if unknown
+#[cfg(unix)]
+fn free_space(path: &Path) -> Option<u64> {
+    let output = match Command::new("df").arg("-Pk").arg(path).output() {
+        Ok(v) if v.status.success() => v,
+        Ok(v) => {
+            warn!("df failed: {}", v.status);
+            return None;
+        }
+        Err(e) => {
+            warn!("Can't run df: {}", e);
+            return None;
+        }
+    };
+    parse_df(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[cfg(windows)]
+fn free_space(path: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetDiskFreeSpaceExW(
+            directory: *const u16,
+            available: *mut u64,
+            total: *mut u64,
+            free: *mut u64,
+        ) -> i32;
+    }
+
+    let directory: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut available = 0;
+    // SAFETY: the name is null terminated, the sizes not asked for may be null
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            directory.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    match ok {
+        0 => {
+            warn!(
+                "Can't read the free space of {:?}: {}",
+                path,
+                std::io::Error::last_os_error()
+            );
+            None
+        }
+        _ => Some(available),
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn free_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Available bytes in POSIX `df -Pk` output
+#[cfg_attr(not(unix), allow(dead_code))]
+fn parse_df(output: &str) -> Option<u64> {
+    let fields: Vec<&str> = output.lines().nth(1)?.split_whitespace().collect();
+    // names and mount points may contain spaces, available is the field before the capacity
+    let capacity = fields.iter().position(|v| v.ends_with('%'))?;
+    let available: u64 = fields.get(capacity.checked_sub(1)?)?.parse().ok()?;
+    Some(available * 1024)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_df() {
+        let output = "Filesystem     1024-blocks      Used Available Capacity Mounted on\n\
+                      /dev/sdb1          7812096   1048576   6763520      14% /media/USB STICK\n";
+        assert_eq!(parse_df(output), Some(6763520 * 1024));
+        let output = "Filesystem     1024-blocks      Used Available Capacity Mounted on\n\
+                      /dev/sdb1          7812096   1048576   2048      100% /media/stick\n";
+        assert_eq!(parse_df(output), Some(2048 * 1024));
+        assert_eq!(parse_df(""), None);
+        // 4 minutes at 192 kbit/s
+        assert_eq!(transcoded_size(240), 5_760_000);
+    }
+
+    #[test]
+    fn test_too_big() {
+        let minutes = |v: u64| Some(Duration::from_secs(v * 60));
+        // 50 MB FLAC of 4 minutes above a 20 MB limit is sent as 192 kbit/s MP3
+        let item = plan("a.flac".into(), 50_000_000, minutes(4), Some(20_000_000));
+        assert!(item.transcode);
+        assert_eq!(item.size, 5_760_000);
+        // small files and transfers without limit are copied
+        assert!(!plan("b.mp3".into(), 5_000_000, minutes(4), Some(20_000_000)).transcode);
+        let item = plan("a.flac".into(), 50_000_000, minutes(4), None);
+        assert!(!item.transcode);
+        assert_eq!(item.size, 50_000_000);
+        // never planned bigger than the original, long files may not shrink
+        assert_eq!(
+            plan("c.wav".into(), 30_000_000, minutes(60), Some(20_000_000)).size,
+            30_000_000
+        );
+
+        let items = [
+            plan("a.flac".into(), 50_000_000, minutes(4), Some(20_000_000)),
+            plan("b.mp3".into(), 5_000_000, minutes(4), Some(20_000_000)),
+        ];
+        let target = Path::new("/usb");
+        assert!(check_space(target, &items, Some(10_760_000)).is_ok());
+        let error = check_space(target, &items, Some(10_000_000)).unwrap_err();
+        assert!(error.to_string().contains("Not enough space"));
+        assert!(check_space(target, &items, None).is_ok());
+    }
+
+    #[test]
+    fn test_relative() {
+        let files = [
+            Path::new("/music/A/First/01 Intro.flac"),
+            Path::new("/music/B/Second/01 Intro.flac"),
+            Path::new("/music/B/Second/02 Song.flac"),
+        ];
+        let base = common_folder(files.iter().copied());
+        assert_eq!(base, Path::new("/music"));
+        assert_eq!(
+            common_folder(files[1..].iter().copied()),
+            Path::new("/music/B/Second")
+        );
+        assert_eq!(common_folder(std::iter::empty()), PathBuf::new());
+        let item = |file: &Path, transcode| Item {
+            source: file.to_path_buf(),
+            transcode,
+            size: 0,
+        };
+        // same names of different albums don't collide
+        assert_eq!(
+            item(files[0], true).relative(&base),
+            Path::new("A/First/01 Intro.mp3")
+        );
+        assert_eq!(
+            item(files[1], true).relative(&base),
+            Path::new("B/Second/01 Intro.mp3")
+        );
+        assert_eq!(
+            item(files[2], false).relative(Path::new("/other")),
+            Path::new("02 Song.flac")
+        );
+        assert_eq!(
+            partial(Path::new("/usb/A/01 Intro.mp3")),
+            Path::new("/usb/A/.01 Intro.mp3.part")
+        );
+    }
+}
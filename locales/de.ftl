@@ -0,0 +1,228 @@
+play = Abspielen
+next = Weiter
+previous = Zurück
+restart = Neu starten
+pause = Pause
+resume = Fortsetzen
+favorite = Favorisieren
+favorited = Favorit ★
+trash-file = In Papierkorb
+reveal = Anzeigen
+copy-path = Pfad kopieren
+copy = Kopieren
+up-next = Als Nächstes
+move-up = Hoch
+preamp = Vorverstärker { $gain } dB
+clip = CLIP
+mono-to-stereo = Mono zu Stereo
+swap-channels = L/R tauschen
+downmix = 5.1 heruntermischen
+playlist-eq = Playlist-EQ
+no-eq = Kein EQ
+volume-offset = Lautstärke { $offset }
+plugins = Plugins
+browse = Durchsuchen
+output-buffer = Ausgabepuffer
+secret-failed = Passwortspeicher für { $name } fehlgeschlagen, ein Passwort in der Konfigurationsdatei bleibt dort: { $error }
+buffer-default = Standard
+buffer-frames = { $frames } Frames
+buffer-latency = ~{ $ms } ms
+buffer-restart = Wirkt nach Neustart
+underruns = Aussetzer: { $count }
+underruns-unknown = Aussetzer: nicht gemessen
+formats = Formate:
+format-needs-ffmpeg = { $format } (braucht ffmpeg)
+unrated = Unbewertet
+play-count = { $count } mal gespielt
+albums = Alben
+no-albums = Keine Alben, Titel brauchen einen Album-Tag
+previous-page = Zurück
+next-page = Weiter
+page = Seite { $page } von { $pages }
+play-all = Alle abspielen
+shuffle-artist = Künstler zufällig
+artist-tracks = { $count } Titel in der Bibliothek
+top-tracks = Meistgespielt
+search-library = Bibliothek durchsuchen
+facet-any = Alle
+enqueue-results = { $count } Ergebnisse einreihen
+more-results = { $count } weitere
+recently-added = Neu hinzugefügt
+last-days = Letzte { $days } Tage
+nothing-added = Nichts hinzugefügt in dieser Zeit
+endless-off = Anhalten wenn leer
+endless-random = Endlos: zufällig
+endless-least-played = Endlos: am längsten nicht gespielt
+endless-same-genre = Endlos: gleiches Genre
+start-radio = Radio starten
+stop-radio = Radio beenden
+radio-from = Radio ab { $track }
+mixes = Mixe
+no-mixes = Noch keine Mixe, erst ein paar Titel abspielen
+mix-rediscover = Wiederentdecken: Favoriten, 90 Tage nicht gespielt
+daily-mix = Täglicher Mix { $number }: { $genre }
+alarms = Wecker
+alarm-ringing = Wecker
+alarm-snoozed = Wecker schlummert
+snooze = Schlummern
+dismiss-alarm = Beenden
+weekday-mon = Mo
+weekday-tue = Di
+weekday-wed = Mi
+weekday-thu = Do
+weekday-fri = Fr
+weekday-sat = Sa
+weekday-sun = So
+quiet-hours = Ruhezeit: höchstens { $volume }%
+content-filter = Inhaltsfilter
+filter-password = Passwort
+set-password = Passwort setzen
+remove-password = Passwort entfernen
+wrong-password = Falsches Passwort
+follow-default-device = Dem Standardgerät des Systems folgen
+go-to-time = mm:ss
+invalid-time = Ungültige Zeit
+add-cue = Cue setzen
+cue-label = Cue { $number }
+export-cues = Cues exportieren
+scan-off = Titel ganz abspielen
+scan-start = Anspielen: Titelanfang
+scan-middle = Anspielen: Titelmitte
+skip-intro = Intro endet hier
+skip-album-intro = Intros des Albums enden hier
+intro-skipped = Beginnt bei { $position }
+clear-intro = Intro abspielen
+playlists = Playlisten
+ungrouped = Ohne Ordner
+new-folder = Neuer Ordner
+add-folder = Ordner anlegen
+pin = Anheften
+unpin = Lösen
+import-itunes = iTunes-Mediathek importieren
+itunes-imported = { $count } Playlists importiert
+playlist-charset = Playlist-Kodierung
+charset-detect = Erkennen
+export-scrobbles = Wiedergabeverlauf exportieren
+selected-tracks = { $count } ausgewählt
+remove = Entfernen
+enqueue-next = Als Nächstes
+tag = Tag
+move-target = In Ordner verschieben
+move-files = Dateien verschieben
+move-failed = { $count } Dateien nicht verschoben, { $error }
+move-no-folder = { $folder } ist kein Ordner
+clear-selection = Auswahl aufheben
+undo = Rückgängig
+to-playlist = In Playlist
+move-tracks = Verschieben
+queue-position = Titel { $track } von { $total }
+touch-mode = Touch-Layout
+touch-off = Aus
+touch-on = An
+touch-auto = Automatisch
+gamepad = Gamepad-Steuerung
+gamepad-unsupported = Gamepads werden nur unter Linux und Windows unterstützt
+gamepad-play-pause = Wiedergabe / Pause
+gamepad-next = Nächster Titel
+gamepad-previous = Vorheriger Titel
+gamepad-stop = Stopp
+gamepad-volume-up = Lauter
+gamepad-volume-down = Leiser
+gamepad-press = Taste drücken…
+gamepad-unassigned = Keine Taste
+gamepad-assign = Zuweisen
+gamepad-reset = Tasten zurücksetzen
+queue-end-repeat = Playlist wiederholen
+queue-end-quit = Beenden wenn leer
+quitting = Warteschlange zu Ende, beende in { $seconds } s
+session-queue = In dieser Sitzung eingereiht
+more-tracks = …und { $count } weitere
+portable-folder = Exportordner, z.B. ein USB-Stick
+portable-copy = Dateien kopieren
+export-portable = Warteschlange exportieren
+portable-exported = { $tracks } Titel exportiert, { $copied } kopiert, { $skipped } übersprungen
+portable-no-folder = Kein Exportordner gewählt
+send-folder = Zielordner oder USB-Laufwerk
+send-queue = Warteschlange
+send-favorites = Favoriten
+send-transcode = Dateien über { $mb } MB in MP3 umwandeln
+send-to-device = An Gerät senden
+send-progress = { $done } von { $total } Dateien
+send-finished = { $copied } kopiert, { $transcoded } umgewandelt, { $existing } schon vorhanden, { $failed } fehlgeschlagen
+send-no-folder = Kein Zielordner gewählt
+send-nothing = Nichts zu senden
+send-find-devices = Handys suchen
+send-no-devices = Keine MTP-Geräte gefunden, entsperre das Handy und erlaube die Dateiübertragung
+rip-load = CD laden
+rip-folder = Zielordner, leer für den Bibliotheksordner
+rip-verify = Prüfen
+rip-start = Rippen
+rip-progress = { $done } von { $total } Titeln gerippt
+rip-finished = { $ripped } Titel gerippt
+rip-finished-verified = { $ripped } Titel gerippt, { $verified } geprüft, { $mismatched } zweimal unterschiedlich gelesen und übersprungen
+rip-nothing = Keine Titel ausgewählt
+rip-no-drive = Kein CD-Laufwerk gefunden
+rip-no-disc = Keine Audio-CD in { $drive }
+rip-unsupported = Audio-CDs können nur unter Linux abgespielt und gerippt werden
+chapters = Kapitel
+zone-volume = { $device }: { $volume }% Lautstärke
+volume = { $volume }% Lautstärke
+filter-tracks = Titel filtern
+ui-scale = UI-Skalierung { $scale }%
+language = Sprache
+drop-hint = Playlist-Datei hierher ziehen zum Starten (.m3u/.pls/.xspf/.asx)
+export-favorites = Favoriten exportieren
+close = Schließen
+loading = Wird geladen…
+back = Zurück
+theme = Design
+theme-default = Standard
+theme-high-contrast = Hoher Kontrast
+text-size = Textgröße { $scale }%
+queue-left = { $time } übrig
+stop = Stopp
+clear-queue = Leeren
+clear-queue-confirm = { $count } anstehende Titel entfernen?
+pop-out-queue = Abdocken
+confirm = Ja
+cancel = Abbrechen
+skipped-files = Übersprungene Dateien ({ $count })
+retry = Erneut
+keep-broken = Nicht abspielbare Titel in der Warteschlange behalten
+relocate-root = Ordner mit verschobenen Titeln
+relocate = Fehlende suchen
+relocated = { $count } Titel gefunden
+archive-path = Einstellungsarchiv
+export = Exportieren
+import = Importieren
+exported = Einstellungen exportiert
+scrobbles-exported = Wiedergabeverlauf gespeichert in { $path }
+imported = Einstellungen importiert, Neustart zum Übernehmen
+sync-newer = Neuere Einstellungen von einem anderen Gerät synchronisiert
+sync-conflict = Synchronisationskonflikt, Einstellungen wurden daneben gespeichert
+dismiss = Verwerfen
+compare-a = Playlist A
+compare-b = Playlist B, leer für Favoriten
+compare = Vergleichen
+only-a = Nur in A: { $count }
+only-b = Nur in B: { $count }
+shared = In beiden: { $count }
+export-only-a = Nur A exportieren
+export-only-b = Nur B exportieren
+export-merged = Zusammengeführt exportieren
+list-exported = Gespeichert als { $path }
+new-list = Neue Liste
+add = Hinzufügen
+note = Notiz zu diesem Titel
+export-notes = Notizen exportieren
+all-tags = Alle Tags
+export-tags = Tags exportieren
+session-name = Sitzungsname
+save-session = Sitzung speichern
+restore-session = Wiederherstellen
+delete-session = Löschen
+tui-playing = Wiedergabe
+tui-paused = Pausiert
+tui-nothing = Keine Wiedergabe
+tui-queue = Warteschlange ({ $count })
+tui-help = Leertaste: Pause  n: Weiter  +/-: Lautstärke  q: Beenden
@@ -0,0 +1,228 @@
+play = Play
+next = Next
+previous = Previous
+restart = Restart
+pause = Pause
+resume = Resume
+favorite = Favorite
+favorited = Favorite ★
+trash-file = Trash File
+reveal = Reveal
+copy-path = Copy Path
+copy = Copy
+up-next = Up next
+move-up = Up
+preamp = Preamp { $gain } dB
+clip = CLIP
+mono-to-stereo = Mono to stereo
+swap-channels = Swap L/R
+downmix = Downmix 5.1
+playlist-eq = Playlist EQ
+no-eq = No EQ
+volume-offset = Volume { $offset }
+plugins = Plugins
+browse = Browse
+output-buffer = Output buffer
+secret-failed = Secret store failed for { $name }, a password in the config file stays there: { $error }
+buffer-default = Default
+buffer-frames = { $frames } frames
+buffer-latency = ~{ $ms } ms
+buffer-restart = Applies after restart
+underruns = Underruns: { $count }
+underruns-unknown = Underruns: not measured
+formats = Formats:
+format-needs-ffmpeg = { $format } (needs ffmpeg)
+unrated = Unrated
+play-count = Played { $count } times
+albums = Albums
+no-albums = No albums, tracks need an album tag
+previous-page = Previous
+next-page = Next
+page = Page { $page } of { $pages }
+play-all = Play all
+shuffle-artist = Shuffle artist
+artist-tracks = { $count } tracks in the library
+top-tracks = Most played
+search-library = Search library
+facet-any = Any
+enqueue-results = Enqueue { $count } results
+more-results = { $count } more
+recently-added = Recently added
+last-days = Last { $days } days
+nothing-added = Nothing added in this time
+endless-off = Stop when empty
+endless-random = Endless: random
+endless-least-played = Endless: least recently played
+endless-same-genre = Endless: same genre
+start-radio = Start radio
+stop-radio = Stop radio
+radio-from = Radio from { $track }
+mixes = Mixes
+no-mixes = No mixes yet, play some tracks first
+mix-rediscover = Rediscover: favorites not played for 90 days
+daily-mix = Daily Mix { $number }: { $genre }
+alarms = Alarms
+alarm-ringing = Alarm
+alarm-snoozed = Alarm snoozed
+snooze = Snooze
+dismiss-alarm = Dismiss
+weekday-mon = Mo
+weekday-tue = Tu
+weekday-wed = We
+weekday-thu = Th
+weekday-fri = Fr
+weekday-sat = Sa
+weekday-sun = Su
+quiet-hours = Quiet hours: at most { $volume }%
+content-filter = Content filter
+filter-password = Password
+set-password = Set password
+remove-password = Remove password
+wrong-password = Wrong password
+follow-default-device = Follow the system default device
+go-to-time = mm:ss
+invalid-time = Invalid time
+add-cue = Add cue
+cue-label = Cue { $number }
+export-cues = Export cues
+scan-off = Play tracks completely
+scan-start = Scan: track starts
+scan-middle = Scan: track middles
+skip-intro = Intro ends here
+skip-album-intro = Intros of the album end here
+intro-skipped = Starts at { $position }
+clear-intro = Play intro
+playlists = Playlists
+ungrouped = Ungrouped
+new-folder = New folder
+add-folder = Add folder
+pin = Pin
+unpin = Unpin
+import-itunes = Import iTunes library
+itunes-imported = Imported { $count } playlists
+playlist-charset = Playlist encoding
+charset-detect = Detect
+export-scrobbles = Export Play History
+selected-tracks = { $count } selected
+remove = Remove
+enqueue-next = Play next
+tag = Tag
+move-target = Move to folder
+move-files = Move files
+move-failed = { $count } files not moved, { $error }
+move-no-folder = { $folder } is no folder
+clear-selection = Clear selection
+undo = Undo
+to-playlist = To playlist
+move-tracks = Move
+queue-position = Track { $track } of { $total }
+touch-mode = Touch layout
+touch-off = Off
+touch-on = On
+touch-auto = Automatic
+gamepad = Gamepad control
+gamepad-unsupported = Gamepads are only supported on Linux and Windows
+gamepad-play-pause = Play / pause
+gamepad-next = Next track
+gamepad-previous = Previous track
+gamepad-stop = Stop
+gamepad-volume-up = Volume up
+gamepad-volume-down = Volume down
+gamepad-press = Press a button…
+gamepad-unassigned = No button
+gamepad-assign = Assign
+gamepad-reset = Reset buttons
+queue-end-repeat = Repeat playlist
+queue-end-quit = Quit when empty
+quitting = Queue ended, quitting in { $seconds } s
+session-queue = Enqueued this session
+more-tracks = …and { $count } more
+portable-folder = Export folder, e.g. a USB stick
+portable-copy = Copy files
+export-portable = Export queue
+portable-exported = { $tracks } tracks exported, { $copied } copied, { $skipped } skipped
+portable-no-folder = No export folder set
+send-folder = Target folder or USB drive
+send-queue = Queue
+send-favorites = Favorites
+send-transcode = Transcode files over { $mb } MB to MP3
+send-to-device = Send to device
+send-progress = { $done } of { $total } files
+send-finished = { $copied } copied, { $transcoded } transcoded, { $existing } already there, { $failed } failed
+send-no-folder = No target folder set
+send-nothing = Nothing to send
+send-find-devices = Find phones
+send-no-devices = No MTP devices found, unlock the phone and allow file transfer
+rip-load = Load CD
+rip-folder = Rip folder, empty for the library folder
+rip-verify = Verify
+rip-start = Rip
+rip-progress = { $done } of { $total } tracks ripped
+rip-finished = { $ripped } tracks ripped
+rip-finished-verified = { $ripped } tracks ripped, { $verified } verified, { $mismatched } read differently twice and skipped
+rip-nothing = No tracks selected
+rip-no-drive = No CD drive found
+rip-no-disc = No audio CD in { $drive }
+rip-unsupported = Audio CDs can only be played and ripped on Linux
+chapters = Chapters
+zone-volume = { $device }: { $volume }% Volume
+volume = { $volume }% Volume
+filter-tracks = Filter tracks
+ui-scale = UI Scale { $scale }%
+language = Language
+drop-hint = Drop a playlist file to start (.m3u/.pls/.xspf/.asx)
+export-favorites = Export Favorites
+close = Close
+loading = Loading…
+back = Back
+theme = Theme
+theme-default = Default
+theme-high-contrast = High contrast
+text-size = Text Size { $scale }%
+queue-left = { $time } left
+stop = Stop
+clear-queue = Clear
+clear-queue-confirm = Remove { $count } upcoming tracks?
+pop-out-queue = Pop out
+confirm = Yes
+cancel = Cancel
+skipped-files = Skipped files ({ $count })
+retry = Retry
+keep-broken = Keep unplayable tracks in the queue
+relocate-root = Folder with moved tracks
+relocate = Relocate missing
+relocated = { $count } tracks relocated
+archive-path = Settings archive file
+export = Export
+import = Import
+exported = Settings exported
+scrobbles-exported = Play history written to { $path }
+imported = Settings imported, restart to apply
+sync-newer = Newer settings were synced from another device
+sync-conflict = Sync conflict, settings were written next to the synced ones
+dismiss = Dismiss
+compare-a = Playlist A
+compare-b = Playlist B, empty for favorites
+compare = Compare
+only-a = Only in A: { $count }
+only-b = Only in B: { $count }
+shared = In both: { $count }
+export-only-a = Export only A
+export-only-b = Export only B
+export-merged = Export merged
+list-exported = Written to { $path }
+new-list = New list
+add = Add
+note = Note for this track
+export-notes = Export Notes
+all-tags = All tags
+export-tags = Export Tags
+session-name = Session name
+save-session = Save Session
+restore-session = Restore
+delete-session = Delete
+tui-playing = Playing
+tui-paused = Paused
+tui-nothing = Nothing playing
+tui-queue = Queue ({ $count })
+tui-help = space: pause  n: next  +/-: volume  q: quit
@@ -0,0 +1,192 @@
+//! Album grid over the library index, covers are loaded for the shown page only
+
+use std::collections::{HashMap, HashSet};
+
+use audio_wrench_core::library::Album;
+use iced::Element;
+use iced_native::{button, image, Align, Button, Column, Image, Length, Row, Text};
+
+use crate::focus::Focus;
+use crate::theme;
+use crate::Message;
+
+/// Albums per row
+const COLUMNS: usize = 4;
+/// Albums per page
+const PAGE: usize = COLUMNS * 3;
+/// Cover size in the grid
+const COVER_SIZE: u16 = 128;
+
+pub struct AlbumGrid {
+    albums: Vec<Album>,
+    album_buttons: Vec<button::State>,
+    /// Covers by first track of the album, None if it has none
+    covers: HashMap<String, Option<image::Handle>>,
+    /// Covers asked for, to not request them again
+    requested: HashSet<String>,
+    /// Album selected with the arrow keys
+    selected: usize,
+    close: button::State,
+    previous: button::State,
+    next: button::State,
+}
+
+impl AlbumGrid {
+    pub fn new(albums: Vec<Album>) -> Self {
+        Self {
+            album_buttons: vec![Default::default(); albums.len()],
+            albums,
+            covers: HashMap::new(),
+            requested: HashSet::new(),
+            selected: 0,
+            close: Default::default(),
+            previous: Default::default(),
+            next: Default::default(),
+        }
+    }
+
+    /// Replace the albums after more metadata was read, keeping the selection
+    pub fn refresh(&mut self, albums: Vec<Album>) {
+        let selected = self.albums.get(self.selected).cloned();
+        self.album_buttons = vec![Default::default(); albums.len()];
+        self.albums = albums;
+        self.selected = selected
+            .and_then(|s| {
+                self.albums
+                    .iter()
+                    .position(|a| a.artist == s.artist && a.title == s.title)
+            })
+            .unwrap_or(0)
+            .min(self.albums.len().saturating_sub(1));
+    }
+
+    pub fn album(&self, index: usize) -> Option<&Album> {
+        self.albums.get(index)
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    fn page(&self) -> usize {
+        self.selected / PAGE
+    }
+
+    /// Select the first album of page `page`
+    pub fn show_page(&mut self, page: usize) {
+        if page * PAGE < self.albums.len() {
+            self.selected = page * PAGE;
+        }
+    }
+
+    /// Move the selection by `columns` and `rows`, staying inside the grid
+    pub fn move_selection(&mut self, columns: isize, rows: isize) {
+        if self.albums.is_empty() {
+            return;
+        }
+        let target = self.selected as isize + columns + rows * COLUMNS as isize;
+        self.selected = target.clamp(0, self.albums.len() as isize - 1) as usize;
+    }
+
+    /// Tracks to load the covers of the current page from, each returned once
+    pub fn wanted_covers(&mut self) -> Vec<String> {
+        let start = self.page() * PAGE;
+        let mut wanted = Vec::new();
+        for album in self.albums.iter().skip(start).take(PAGE) {
+            if let Some(track) = album.tracks.first() {
+                if self.requested.insert(track.clone()) {
+                    wanted.push(track.clone());
+                }
+            }
+        }
+        wanted
+    }
+
+    pub fn cover_loaded(&mut self, track: &str, cover: Option<image::Handle>) {
+        if self.requested.contains(track) {
+            self.covers.insert(track.to_string(), cover);
+        }
+    }
+
+    pub fn view(&mut self, focus: &mut Focus) -> Element<'_, Message> {
+        let page = self.page();
+        let pages = self.albums.len().div_ceil(PAGE).max(1);
+        let mut previous = Button::new(
+            &mut self.previous,
+            Text::new(tr!("previous-page")).size(theme::text_size(14)),
+        );
+        if page > 0 {
+            previous = focus.button(previous, Message::AlbumPage(page - 1));
+        }
+        let mut next = Button::new(
+            &mut self.next,
+            Text::new(tr!("next-page")).size(theme::text_size(14)),
+        );
+        if page + 1 < pages {
+            next = focus.button(next, Message::AlbumPage(page + 1));
+        }
+        let controls = Row::new()
+            .spacing(10)
+            .align_items(Align::Center)
+            .push(focus.button(
+                Button::new(
+                    &mut self.close,
+                    Text::new(tr!("close")).size(theme::text_size(14)),
+                ),
+                Message::CloseAlbums,
+            ))
+            .push(previous)
+            .push(Text::new(tr!("page", page = page + 1, pages = pages)).size(theme::text_size(14)))
+            .push(next);
+        let mut grid = Column::new().spacing(10).push(controls);
+        if self.albums.is_empty() {
+            grid = grid.push(Text::new(tr!("no-albums")).size(theme::text_size(14)));
+        }
+        let start = page * PAGE;
+        let covers = &self.covers;
+        let mut row = Row::new().spacing(10);
+        for (i, (album, state)) in self
+            .albums
+            .iter()
+            .zip(self.album_buttons.iter_mut())
+            .enumerate()
+            .skip(start)
+            .take(PAGE)
+        {
+            let mut cell = Column::new().spacing(2).width(Length::Units(COVER_SIZE));
+            let cover = album
+                .tracks
+                .first()
+                .and_then(|t| covers.get(t))
+                .cloned()
+                .flatten();
+            cell = match cover {
+                Some(handle) => cell.push(
+                    Image::new(handle)
+                        .width(Length::Units(COVER_SIZE))
+                        .height(Length::Units(COVER_SIZE)),
+                ),
+                None => cell.push(
+                    Column::new()
+                        .width(Length::Units(COVER_SIZE))
+                        .height(Length::Units(COVER_SIZE)),
+                ),
+            };
+            let mut title = Text::new(album.title.as_str()).size(theme::text_size(14));
+            if i == self.selected {
+                title = title.color(theme::color([0.0, 0.3, 0.8]));
+            }
+            cell = cell.push(title).push(
+                Text::new(album.artist.as_str())
+                    .size(theme::text_size(12))
+                    .color(theme::color([0.4, 0.4, 0.4])),
+            );
+            row = row.push(focus.button(Button::new(state, cell), Message::EnqueueAlbum(i)));
+            if (i - start) % COLUMNS == COLUMNS - 1 {
+                grid = grid.push(row);
+                row = Row::new().spacing(10);
+            }
+        }
+        grid.push(row).into()
+    }
+}
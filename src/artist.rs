@@ -0,0 +1,121 @@
+//! Artist page with discography and most played tracks
+
+use audio_wrench_core::library::Artist;
+use iced::Element;
+use iced_native::{button, Align, Button, Column, Row, Text};
+
+use crate::focus::Focus;
+use crate::theme;
+use crate::Message;
+
+/// Most played tracks shown
+const TOP_TRACKS: usize = 10;
+
+pub struct ArtistPage {
+    artist: Artist,
+    /// Played tracks with label and play count, most played first
+    top: Vec<(String, String, u64)>,
+    close: button::State,
+    play_all: button::State,
+    shuffle: button::State,
+    album_buttons: Vec<button::State>,
+    top_buttons: Vec<button::State>,
+}
+
+impl ArtistPage {
+    /// `counts` are the play counts of the artist tracks, `label` names a track
+    pub fn new<F>(artist: Artist, counts: Vec<(String, u64)>, label: F) -> Self
+    where
+        F: Fn(&str) -> String,
+    {
+        let mut played: Vec<_> = counts.into_iter().filter(|(_, v)| *v > 0).collect();
+        played.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        let top: Vec<_> = played
+            .into_iter()
+            .take(TOP_TRACKS)
+            .map(|(track, count)| {
+                let label = label(&track);
+                (track, label, count)
+            })
+            .collect();
+        Self {
+            album_buttons: vec![Default::default(); artist.albums.len()],
+            top_buttons: vec![Default::default(); top.len()],
+            artist,
+            top,
+            close: Default::default(),
+            play_all: Default::default(),
+            shuffle: Default::default(),
+        }
+    }
+
+    pub fn artist(&self) -> &Artist {
+        &self.artist
+    }
+
+    pub fn top_track(&self, index: usize) -> Option<&str> {
+        self.top.get(index).map(|(t, _, _)| t.as_str())
+    }
+
+    pub fn view(&mut self, focus: &mut Focus) -> Element<'_, Message> {
+        let controls = Row::new()
+            .spacing(10)
+            .align_items(Align::Center)
+            .push(focus.button(
+                Button::new(
+                    &mut self.close,
+                    Text::new(tr!("close")).size(theme::text_size(14)),
+                ),
+                Message::CloseArtist,
+            ))
+            .push(focus.button(
+                Button::new(
+                    &mut self.play_all,
+                    Text::new(tr!("play-all")).size(theme::text_size(14)),
+                ),
+                Message::PlayArtist(false),
+            ))
+            .push(focus.button(
+                Button::new(
+                    &mut self.shuffle,
+                    Text::new(tr!("shuffle-artist")).size(theme::text_size(14)),
+                ),
+                Message::PlayArtist(true),
+            ));
+        let mut page = Column::new()
+            .spacing(5)
+            .push(Text::new(self.artist.name.as_str()).size(theme::text_size(20)))
+            .push(
+                Text::new(tr!("artist-tracks", count = self.artist.tracks.len()))
+                    .size(theme::text_size(14)),
+            )
+            .push(controls)
+            .push(Text::new(tr!("albums")).size(theme::text_size(16)));
+        for (i, (album, state)) in self
+            .artist
+            .albums
+            .iter()
+            .zip(self.album_buttons.iter_mut())
+            .enumerate()
+        {
+            let label = format!("+ {} ({})", album.title, album.tracks.len());
+            page = page.push(focus.button(
+                Button::new(state, Text::new(label).size(theme::text_size(14))),
+                Message::EnqueueArtistAlbum(i),
+            ));
+        }
+        if !self.top.is_empty() {
+            page = page.push(Text::new(tr!("top-tracks")).size(theme::text_size(16)));
+        }
+        for (i, ((_, label, count), state)) in
+            self.top.iter().zip(self.top_buttons.iter_mut()).enumerate()
+        {
+            let label = format!("+ {} ({})", label, count);
+            page = page.push(focus.button(
+                Button::new(state, Text::new(label).size(theme::text_size(14))),
+                Message::EnqueueTopTrack(i),
+            ));
+        }
+        page.into()
+    }
+}
@@ -0,0 +1,98 @@
+//! Audio CDs as track source, with album and track names from MusicBrainz.
+//! Only registered on Linux, drives are found through GVFS there.
+
+use audio_wrench_core::cd::{self, Lookups, Toc};
+
+use crate::plugin::{Entry, Listing, Plugin, TrackSource};
+use crate::prelude::*;
+
+/// Drives at the root, their tracks below
+pub struct AudioCd {
+    /// Shared with ripping
+    lookups: Lookups,
+}
+
+impl AudioCd {
+    pub fn new(lookups: Lookups) -> Self {
+        Self { lookups }
+    }
+}
+
+impl Plugin for AudioCd {
+    fn name(&self) -> &'static str {
+        "Audio CD"
+    }
+
+    fn source(&mut self) -> Option<&mut dyn TrackSource> {
+        Some(self)
+    }
+}
+
+impl TrackSource for AudioCd {
+    fn browse(&mut self, parent: Option<&str>) -> Listing {
+        let lookups = self.lookups.clone();
+        let parent = parent.map(str::to_string);
+        Box::new(move || list(&lookups, parent.as_deref()))
+    }
+}
+
+/// Drives or the tracks of drive `parent`
+fn list(lookups: &Lookups, parent: Option<&str>) -> Result<Vec<Entry>> {
+    let drives = cd::drives();
+    let name = match parent {
+        Some(v) => v,
+        None if drives.is_empty() => return Err(eyre!("No CD drive found")),
+        None => {
+            let mut entries = Vec::with_capacity(drives.len());
+            for drive in drives {
+                let title = match drive.mount {
+                    Some(_) => match Toc::read(&drive.device)
+                        .ok()
+                        .and_then(|toc| lookups.disc(&toc))
+                    {
+                        Some(disc) => {
+                            format!("{}: {} - {}", drive.name, disc.artist, disc.title)
+                        }
+                        None => format!("{}: Audio CD", drive.name),
+                    },
+                    None => format!("{}: no audio CD", drive.name),
+                };
+                entries.push(Entry {
+                    id: drive.name,
+                    title,
+                    track: None,
+                });
+            }
+            return Ok(entries);
+        }
+    };
+    let drive = drives
+        .into_iter()
+        .find(|d| d.name == name)
+        .ok_or_else(|| eyre!("Drive {} is gone", name))?;
+    let tracks = drive.tracks()?;
+    // names and lengths are optional, the tracks play without
+    let toc = Toc::read(&drive.device).map_err(|e| warn!("{:?}", e)).ok();
+    let disc = toc.as_ref().and_then(|toc| lookups.disc(toc));
+    Ok(tracks
+        .into_iter()
+        .enumerate()
+        .map(|(i, path)| {
+            let title = disc.as_ref().and_then(|d| d.tracks.get(i)).map_or_else(
+                || format!("Track {}", i + 1),
+                |title| format!("{:02}. {}", i + 1, title),
+            );
+            let location = path.to_string_lossy().into_owned();
+            Entry {
+                id: location.clone(),
+                title: match toc.as_ref().and_then(|toc| toc.length(i)) {
+                    Some(length) => {
+                        format!("{} ({})", title, crate::format_duration(Some(length)))
+                    }
+                    None => title,
+                },
+                track: Some(location),
+            }
+        })
+        .collect())
+}
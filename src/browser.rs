@@ -0,0 +1,132 @@
+//! Browsing view for plugin track sources
+
+use iced::Element;
+use iced_native::{button, Button, Column, Row, Text};
+
+use crate::focus::Focus;
+use crate::plugin::Entry;
+use crate::theme;
+use crate::Message;
+
+pub struct Browser {
+    /// Plugin index of the browsed source
+    pub plugin: usize,
+    /// Folder ids leading to the current folder, empty for the root
+    path: Vec<String>,
+    entries: Vec<Entry>,
+    entry_buttons: Vec<button::State>,
+    back: button::State,
+    close: button::State,
+    error: Option<String>,
+    /// Last listing request, earlier results are outdated
+    request: u64,
+    loading: bool,
+}
+
+impl Browser {
+    /// Browser of the root of `plugin`, load it with [Browser::load] and [Browser::listed]
+    pub fn open(plugin: usize) -> Self {
+        Self {
+            plugin,
+            path: Vec::new(),
+            entries: Vec::new(),
+            entry_buttons: Vec::new(),
+            back: Default::default(),
+            close: Default::default(),
+            error: None,
+            request: 0,
+            loading: true,
+        }
+    }
+
+    /// Folder to list, None for the root. Starts a new request, returns it too.
+    pub fn load(&mut self) -> (Option<String>, u64) {
+        self.request += 1;
+        self.loading = true;
+        self.entries.clear();
+        self.entry_buttons.clear();
+        (self.path.last().cloned(), self.request)
+    }
+
+    /// Entries listed for `request`, ignored if another folder was opened since
+    pub fn listed(&mut self, request: u64, result: std::result::Result<Vec<Entry>, String>) {
+        if request != self.request {
+            return;
+        }
+        self.loading = false;
+        match result {
+            Ok(entries) => {
+                self.entry_buttons = vec![Default::default(); entries.len()];
+                self.entries = entries;
+                self.error = None;
+            }
+            Err(e) => {
+                self.entries.clear();
+                self.entry_buttons.clear();
+                self.error = Some(e);
+            }
+        }
+    }
+
+    /// Open entry at `index`. Returns its track location if it isn't a folder,
+    /// folders have to be loaded.
+    pub fn enter(&mut self, index: usize) -> Option<String> {
+        let entry = self.entries.get(index)?;
+        match entry.track {
+            Some(ref track) => Some(track.clone()),
+            None => {
+                self.path.push(entry.id.clone());
+                None
+            }
+        }
+    }
+
+    /// Go to the parent folder, returns whether it has to be loaded
+    pub fn back(&mut self) -> bool {
+        self.path.pop().is_some()
+    }
+
+    pub fn view(&mut self, focus: &mut Focus) -> Element<'_, Message> {
+        let mut controls = Row::new().spacing(10).push(focus.button(
+            Button::new(
+                &mut self.close,
+                Text::new(tr!("close")).size(theme::text_size(14)),
+            ),
+            Message::CloseBrowser,
+        ));
+        let mut back = Button::new(
+            &mut self.back,
+            Text::new(tr!("back")).size(theme::text_size(14)),
+        );
+        if !self.path.is_empty() {
+            back = focus.button(back, Message::BrowseBack);
+        }
+        controls = controls.push(back);
+        let mut column = Column::new().spacing(5).push(controls);
+        if self.loading {
+            column = column.push(Text::new(tr!("loading")).size(theme::text_size(14)));
+        } else if let Some(ref e) = self.error {
+            column = column.push(
+                Text::new(e.as_str())
+                    .size(theme::text_size(14))
+                    .color(theme::color([0.8, 0.0, 0.0])),
+            );
+        }
+        for (i, (entry, state)) in self
+            .entries
+            .iter()
+            .zip(self.entry_buttons.iter_mut())
+            .enumerate()
+        {
+            let label = match entry.track {
+                Some(_) => format!("+ {}", entry.title),
+                None => format!("{}/", entry.title),
+            };
+            column = column.push(focus.button(
+                Button::new(state, Text::new(label).size(theme::text_size(14))),
+                Message::BrowseEntry(i),
+            ));
+        }
+        column.into()
+    }
+}
@@ -0,0 +1,205 @@
+//! Bulk actions on the tracks selected in the queue or the library search
+
+use std::path::PathBuf;
+
+use iced::Element;
+use iced_native::{
+    button, pick_list, text_input, Align, Button, Checkbox, PickList, Row, Text, TextInput,
+};
+
+use crate::focus::Focus;
+use crate::theme;
+use crate::Message;
+
+/// List the selection belongs to, selecting in one clears the other
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionList {
+    Queue,
+    Search,
+}
+
+/// Playlist offered as target, shown by its name
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PlaylistChoice(pub PathBuf);
+
+impl std::fmt::Display for PlaylistChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0.file_stem() {
+            Some(name) => f.write_str(&name.to_string_lossy()),
+            None => f.write_str(&self.0.to_string_lossy()),
+        }
+    }
+}
+
+/// Favorite lists, tags and playlists selected tracks can be added to
+pub struct Targets {
+    pub favorites: Vec<String>,
+    pub tags: Vec<String>,
+    /// Playlists other than the current one
+    pub playlists: Vec<PlaylistChoice>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BulkAction {
+    /// Remove from the queue
+    Remove,
+    /// Add to the favorite list
+    Favorite(String),
+    Tag(String),
+    /// Move the files to the folder entered in the bar
+    MoveFiles,
+    EnqueueNext,
+    /// Append to the queue of another playlist, removed from this one if moving.
+    /// Stands in for dragging between playlists, which iced has no support for.
+    ToPlaylist(PathBuf),
+}
+
+#[derive(Default)]
+pub struct BulkBar {
+    target: String,
+    target_input: text_input::State,
+    remove: button::State,
+    favorite_pick: pick_list::State<String>,
+    tag_pick: pick_list::State<String>,
+    playlist_pick: pick_list::State<PlaylistChoice>,
+    /// Move instead of copy to another playlist
+    move_tracks: bool,
+    move_files: button::State,
+    /// Why files of the last move stayed where they were
+    move_error: Option<String>,
+    enqueue_next: button::State,
+    clear: button::State,
+    undo: button::State,
+}
+
+impl BulkBar {
+    /// Folder selected files are moved to
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn set_target(&mut self, target: String) {
+        self.target = target;
+    }
+
+    pub fn set_move_error(&mut self, error: Option<String>) {
+        self.move_error = error;
+    }
+
+    pub fn move_tracks(&self) -> bool {
+        self.move_tracks
+    }
+
+    pub fn set_move_tracks(&mut self, move_tracks: bool) {
+        self.move_tracks = move_tracks;
+    }
+
+    pub fn text_focused(&self) -> bool {
+        self.target_input.is_focused()
+    }
+
+    /// Actions for `count` selected tracks of `list`, undo is offered if `can_undo`
+    pub fn view(
+        &mut self,
+        count: usize,
+        list: SelectionList,
+        targets: Targets,
+        can_undo: bool,
+        focus: &mut Focus,
+    ) -> Element<'_, Message> {
+        let mut row = Row::new().spacing(10).align_items(Align::Center);
+        if count > 0 {
+            row = row
+                .push(Text::new(tr!("selected-tracks", count = count)).size(theme::text_size(14)));
+            if list == SelectionList::Queue {
+                row = row.push(focus.button(
+                    Button::new(
+                        &mut self.remove,
+                        Text::new(tr!("remove")).size(theme::text_size(14)),
+                    ),
+                    Message::Bulk(BulkAction::Remove),
+                ));
+            }
+            row = row
+                .push(focus.button(
+                    Button::new(
+                        &mut self.enqueue_next,
+                        Text::new(tr!("enqueue-next")).size(theme::text_size(14)),
+                    ),
+                    Message::Bulk(BulkAction::EnqueueNext),
+                ))
+                .push(Text::new(tr!("favorite")).size(theme::text_size(14)))
+                .push(
+                    PickList::new(&mut self.favorite_pick, targets.favorites, None, |v| {
+                        Message::Bulk(BulkAction::Favorite(v))
+                    })
+                    .text_size(theme::text_size(14)),
+                )
+                .push(Text::new(tr!("tag")).size(theme::text_size(14)))
+                .push(
+                    PickList::new(&mut self.tag_pick, targets.tags, None, |v| {
+                        Message::Bulk(BulkAction::Tag(v))
+                    })
+                    .text_size(theme::text_size(14)),
+                )
+                .push(
+                    TextInput::new(
+                        &mut self.target_input,
+                        &tr!("move-target"),
+                        &self.target,
+                        Message::MoveTargetChanged,
+                    )
+                    .padding(5)
+                    .on_submit(Message::Bulk(BulkAction::MoveFiles)),
+                );
+            if !targets.playlists.is_empty() {
+                row = row
+                    .push(Text::new(tr!("to-playlist")).size(theme::text_size(14)))
+                    .push(
+                        PickList::new(&mut self.playlist_pick, targets.playlists, None, |v| {
+                            Message::Bulk(BulkAction::ToPlaylist(v.0))
+                        })
+                        .text_size(theme::text_size(14)),
+                    );
+                if list == SelectionList::Queue {
+                    row = row.push(Checkbox::new(
+                        self.move_tracks,
+                        tr!("move-tracks"),
+                        Message::MoveTracksToggled,
+                    ));
+                }
+            }
+            let mut move_files = Button::new(
+                &mut self.move_files,
+                Text::new(tr!("move-files")).size(theme::text_size(14)),
+            );
+            if !self.target.trim().is_empty() {
+                move_files = focus.button(move_files, Message::Bulk(BulkAction::MoveFiles));
+            }
+            row = row.push(move_files).push(focus.button(
+                Button::new(
+                    &mut self.clear,
+                    Text::new(tr!("clear-selection")).size(theme::text_size(14)),
+                ),
+                Message::ClearSelection,
+            ));
+        }
+        if let Some(ref error) = self.move_error {
+            row = row.push(
+                Text::new(error.as_str())
+                    .size(theme::text_size(14))
+                    .color(theme::color([0.8, 0.0, 0.0])),
+            );
+        }
+        if can_undo {
+            row = row.push(focus.button(
+                Button::new(
+                    &mut self.undo,
+                    Text::new(tr!("undo")).size(theme::text_size(14)),
+                ),
+                Message::Undo,
+            ));
+        }
+        row.into()
+    }
+}
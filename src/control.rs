@@ -0,0 +1,150 @@
+//! Remote control by other programs, transports like gRPC forward to the player through this
+
+use std::{
+    path::PathBuf,
+    sync::mpsc::{channel, Receiver, Sender},
+    time::Duration,
+};
+
+use tokio::sync::watch;
+
+/// Amount of upcoming tracks published in the status
+pub const STATUS_QUEUE: usize = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ControlCommand {
+    /// Resume, or start the queue if nothing plays
+    Play,
+    Pause,
+    Next,
+    Stop,
+    Volume(u8),
+    /// Tracks to be played after the current one
+    Enqueue(Vec<String>),
+    ClearQueue,
+    /// Continue with the queue of a loaded playlist
+    OpenPlaylist(PathBuf),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ControlState {
+    #[default]
+    Stopped,
+    Playing,
+    Paused,
+}
+
+/// Player state as seen by remote controls
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ControlStatus {
+    pub state: ControlState,
+    /// Empty if stopped
+    pub track: String,
+    /// Whole seconds, to not publish every progress report
+    pub position: u64,
+    pub duration: Option<Duration>,
+    pub volume: u8,
+    /// Upcoming tracks, at most [`STATUS_QUEUE`]
+    pub queue: Vec<String>,
+    /// Loaded playlists
+    pub playlists: Vec<PathBuf>,
+}
+
+/// Player side, polled on every tick
+pub struct Control {
+    /// For handles of transports started later
+    tx: Sender<ControlCommand>,
+    rx: Receiver<ControlCommand>,
+    status: watch::Sender<ControlStatus>,
+}
+
+/// Transport side, one clone per transport
+#[derive(Clone)]
+pub struct ControlHandle {
+    tx: Sender<ControlCommand>,
+    status: watch::Receiver<ControlStatus>,
+}
+
+impl Control {
+    pub fn new() -> (Self, ControlHandle) {
+        let (tx, rx) = channel();
+        let (status, status_rx) = watch::channel(ControlStatus::default());
+        (
+            Self {
+                tx: tx.clone(),
+                rx,
+                status,
+            },
+            ControlHandle {
+                tx,
+                status: status_rx,
+            },
+        )
+    }
+
+    /// Handle for another transport
+    pub fn handle(&self) -> ControlHandle {
+        ControlHandle {
+            tx: self.tx.clone(),
+            status: self.status.subscribe(),
+        }
+    }
+
+    /// Next pending command
+    pub fn poll(&self) -> Option<ControlCommand> {
+        self.rx.try_recv().ok()
+    }
+
+    /// Publish `status`, watchers are only woken up on changes
+    pub fn publish(&self, status: ControlStatus) {
+        self.status.send_if_modified(|current| {
+            let changed = *current != status;
+            if changed {
+                *current = status;
+            }
+            changed
+        });
+    }
+}
+
+impl ControlHandle {
+    /// Send command to the player, false if it isn't running anymore
+    pub fn send(&self, command: ControlCommand) -> bool {
+        self.tx.send(command).is_ok()
+    }
+
+    pub fn status(&self) -> ControlStatus {
+        self.status.borrow().clone()
+    }
+
+    /// Receiver of status changes
+    pub fn watch(&self) -> watch::Receiver<ControlStatus> {
+        self.status.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_control() {
+        let (control, handle) = Control::new();
+        assert!(handle.send(ControlCommand::Volume(20)));
+        assert_eq!(control.poll(), Some(ControlCommand::Volume(20)));
+        assert_eq!(control.poll(), None);
+
+        let mut watch = handle.watch();
+        watch.mark_unchanged();
+        control.publish(ControlStatus::default());
+        assert!(!watch.has_changed().unwrap());
+        let status = ControlStatus {
+            state: ControlState::Playing,
+            track: String::from("a.mp3"),
+            ..Default::default()
+        };
+        control.publish(status.clone());
+        assert!(watch.has_changed().unwrap());
+        assert_eq!(handle.status(), status);
+    }
+}
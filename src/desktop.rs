@@ -0,0 +1,69 @@
+//! Integration with the desktop: file manager and clipboard
+
+use std::{path::Path, process::Command};
+
+use crate::prelude::*;
+
+/// Show `file` in the platform file manager
+pub fn reveal(file: &Path) -> Result<()> {
+    let mut cmd = reveal_command(file)?;
+    trace!("Revealing {:?}: {:?}", file, cmd);
+    // file managers may keep running, don't wait
+    cmd.spawn().wrap_err("Can't start file manager")?;
+    Ok(())
+}
+
+#[cfg(windows)]
+fn reveal_command(file: &Path) -> Result<Command> {
+    let mut select = std::ffi::OsString::from("/select,");
+    select.push(file);
+    let mut cmd = Command::new("explorer");
+    cmd.arg(select);
+    Ok(cmd)
+}
+
+#[cfg(target_os = "macos")]
+fn reveal_command(file: &Path) -> Result<Command> {
+    let mut cmd = Command::new("open");
+    cmd.arg("-R").arg(file);
+    Ok(cmd)
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn reveal_command(file: &Path) -> Result<Command> {
+    // no generic way to select a file, open the folder instead
+    let folder = file
+        .parent()
+        .ok_or_else(|| eyre!("No containing folder for {:?}", file))?;
+    let mut cmd = Command::new("xdg-open");
+    cmd.arg(folder);
+    Ok(cmd)
+}
+
+/// Put `text` into the clipboard
+pub fn copy(text: &str) -> Result<()> {
+    arboard::Clipboard::new()
+        .and_then(|mut c| c.set_text(text))
+        .wrap_err("Can't write clipboard")
+}
+
+/// Text content of the clipboard
+pub fn paste() -> Result<String> {
+    arboard::Clipboard::new()
+        .and_then(|mut c| c.get_text())
+        .wrap_err("Can't read clipboard")
+}
+
+/// Text of the primary selection, pasted by middle clicking on X11 and Wayland
+#[cfg(not(any(windows, target_os = "macos")))]
+pub fn paste_selection() -> Result<String> {
+    use arboard::{GetExtLinux, LinuxClipboardKind};
+    arboard::Clipboard::new()
+        .and_then(|mut c| c.get().clipboard(LinuxClipboardKind::Primary).text())
+        .wrap_err("Can't read primary selection")
+}
+
+#[cfg(any(windows, target_os = "macos"))]
+pub fn paste_selection() -> Result<String> {
+    Err(eyre!("No primary selection on this platform"))
+}
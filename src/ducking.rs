@@ -0,0 +1,226 @@
+//! Pause or lower the volume while other applications play audio
+
+use std::{
+    sync::mpsc::{channel, Receiver},
+    thread,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/// Interval for checking other audio streams
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DuckMode {
+    #[default]
+    Off,
+    Pause,
+    /// Lower the volume by `Ducking::amount`
+    Duck,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ducking {
+    #[serde(default)]
+    pub mode: DuckMode,
+    /// Volume reduction in percent
+    #[serde(default = "default_amount")]
+    pub amount: u8,
+}
+
+fn default_amount() -> u8 {
+    50
+}
+
+impl Default for Ducking {
+    fn default() -> Self {
+        Self {
+            mode: DuckMode::Off,
+            amount: default_amount(),
+        }
+    }
+}
+
+impl Ducking {
+    /// Volume to use while ducked
+    pub fn apply(&self, volume: u8) -> u8 {
+        let amount = self.amount.min(100) as u16;
+        (volume as u16 * (100 - amount) / 100) as u8
+    }
+}
+
+/// Watches for audio streams of other applications
+pub struct Monitor {
+    rx: Receiver<bool>,
+}
+
+impl Monitor {
+    /// None on platforms where other streams can't be seen, PulseAudio and PipeWire
+    /// on Linux and the audio sessions on Windows list them
+    pub fn new() -> Option<Self> {
+        if !cfg!(any(target_os = "linux", windows)) {
+            warn!(
+                "Pausing or ducking for other applications is only supported on Linux and Windows"
+            );
+            return None;
+        }
+        let (tx, rx) = channel();
+        thread::spawn(move || {
+            if let Err(e) = init_thread() {
+                warn!("Can't check for other audio streams, disabling: {}", e);
+                return;
+            }
+            let own_pid = std::process::id();
+            let mut last = false;
+            loop {
+                match other_streams_active(own_pid) {
+                    Ok(active) => {
+                        if active != last {
+                            last = active;
+                            if tx.send(active).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        warn!("Can't check for other audio streams, disabling: {}", e);
+                        return;
+                    }
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+        });
+        Some(Self { rx })
+    }
+
+    /// Latest change of other application activity
+    pub fn poll(&self) -> Option<bool> {
+        self.rx.try_iter().last()
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn other_streams_active(own_pid: u32) -> Result<bool> {
+    let output = std::process::Command::new("pactl")
+        .args(["list", "sink-inputs"])
+        .output()
+        .wrap_err("Can't run pactl")?;
+    if !output.status.success() {
+        return Err(eyre!("pactl failed: {}", output.status));
+    }
+    Ok(parse_sink_inputs(&String::from_utf8_lossy(&output.stdout), own_pid) > 0)
+}
+
+#[cfg(not(windows))]
+fn init_thread() -> Result<()> {
+    Ok(())
+}
+
+/// COM is used by the polling thread until it ends
+#[cfg(windows)]
+fn init_thread() -> Result<()> {
+    use windows::Win32::System::Com::{CoInitializeEx, COINIT_MULTITHREADED};
+    unsafe { CoInitializeEx(None, COINIT_MULTITHREADED) }.wrap_err("Can't initialize COM")
+}
+
+/// Active audio sessions of other processes on the default output device
+#[cfg(windows)]
+fn other_streams_active(own_pid: u32) -> Result<bool> {
+    use windows::core::Interface;
+    use windows::Win32::Media::Audio::{
+        eMultimedia, eRender, AudioSessionStateActive, IAudioSessionControl2,
+        IAudioSessionManager2, IMMDeviceEnumerator, MMDeviceEnumerator,
+    };
+    use windows::Win32::System::Com::{CoCreateInstance, CLSCTX_ALL};
+
+    unsafe {
+        let enumerator: IMMDeviceEnumerator =
+            CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?;
+        let device = enumerator.GetDefaultAudioEndpoint(eRender, eMultimedia)?;
+        let manager: IAudioSessionManager2 = device.Activate(CLSCTX_ALL, None)?;
+        let sessions = manager.GetSessionEnumerator()?;
+        for i in 0..sessions.GetCount()? {
+            let session: IAudioSessionControl2 = sessions.GetSession(i)?.cast()?;
+            if session.GetState()? != AudioSessionStateActive {
+                continue;
+            }
+            // the system sounds session belongs to pid 0
+            let pid = session.GetProcessId().unwrap_or(0);
+            if pid != 0 && pid != own_pid {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// Never polled, [Monitor::new] doesn't start elsewhere
+#[cfg(not(any(target_os = "linux", windows)))]
+fn other_streams_active(_own_pid: u32) -> Result<bool> {
+    Err(eyre!("Not supported on this platform"))
+}
+
+/// Count uncorked sink inputs of other processes in `pactl list sink-inputs` output
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_sink_inputs(output: &str, own_pid: u32) -> usize {
+    let own_pid = format!("\"{}\"", own_pid);
+    output
+        .split("Sink Input #")
+        .skip(1)
+        .filter(|input| {
+            let mut corked = false;
+            let mut own = false;
+            for line in input.lines().map(str::trim) {
+                if line == "Corked: yes" {
+                    corked = true;
+                } else if let Some(pid) = line.strip_prefix("application.process.id = ") {
+                    own = pid == own_pid;
+                }
+            }
+            !corked && !own
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_sink_inputs() {
+        let output = "Sink Input #1
+	Driver: protocol-native.c
+	Corked: no
+	Properties:
+		application.name = \"audio_wrench\"
+		application.process.id = \"42\"
+
+Sink Input #2
+	Corked: yes
+	Properties:
+		application.process.id = \"7\"
+
+Sink Input #3
+	Corked: no
+	Properties:
+		application.process.id = \"8\"
+";
+        assert_eq!(parse_sink_inputs(output, 42), 1);
+        assert_eq!(parse_sink_inputs(output, 8), 1);
+        assert_eq!(parse_sink_inputs("", 8), 0);
+    }
+
+    #[test]
+    fn test_apply() {
+        let ducking = Ducking::default();
+        assert_eq!(ducking.apply(80), 40);
+        let ducking = Ducking {
+            mode: DuckMode::Duck,
+            amount: 200,
+        };
+        assert_eq!(ducking.apply(80), 0);
+    }
+}
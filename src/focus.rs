@@ -0,0 +1,110 @@
+//! Keyboard focus traversal for buttons, iced has no focus handling for them.
+//! Only keyboard use is covered: iced 0.2 exposes no accessibility tree, so screen readers
+//! like NVDA or Orca can't name the controls.
+
+use iced::{
+    button::{Style, StyleSheet},
+    Background, Button,
+};
+
+use crate::{theme, Message};
+
+/// Tracks focusable buttons in view order, rebuilt on every view
+#[derive(Default)]
+pub struct Focus {
+    /// Press action of every focusable button
+    order: Vec<Message>,
+    current: Option<usize>,
+    focused: Option<Message>,
+}
+
+impl Focus {
+    /// Start recording the focus order, call at the start of the view
+    pub fn begin(&mut self) {
+        self.focused = self.current.and_then(|i| self.order.get(i).cloned());
+        self.current = None;
+        self.order.clear();
+    }
+
+    /// Set `message` as press action and highlight the button if focused
+    pub fn button<'a>(
+        &mut self,
+        button: Button<'a, Message>,
+        message: Message,
+    ) -> Button<'a, Message> {
+        let focused = self.focused.as_ref() == Some(&message);
+        if focused {
+            // follow the button when the order changes
+            self.current = Some(self.order.len());
+        }
+        self.order.push(message.clone());
+        let button = button.on_press(message);
+        match (focused, theme::button()) {
+            (true, _) => button.style(Focused),
+            (false, Some(style)) => button.style(style),
+            (false, None) => button,
+        }
+    }
+
+    /// Move focus to the next or previous button, wrapping around
+    pub fn advance(&mut self, back: bool) {
+        let len = self.order.len();
+        if len == 0 {
+            return;
+        }
+        self.current = Some(match (self.current, back) {
+            (None, false) => 0,
+            (None, true) => len - 1,
+            (Some(i), false) => (i + 1) % len,
+            (Some(i), true) => (i + len - 1) % len,
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.current = None;
+    }
+
+    /// Press action of the focused button
+    pub fn activate(&self) -> Option<Message> {
+        self.current.and_then(|i| self.order.get(i).cloned())
+    }
+}
+
+struct Focused;
+
+impl StyleSheet for Focused {
+    fn active(&self) -> Style {
+        Style {
+            background: Some(Background::Color([0.87, 0.87, 0.87].into())),
+            border_radius: 2.0,
+            border_width: 3.0,
+            border_color: [0.0, 0.3, 0.9].into(),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use iced::Text;
+
+    #[test]
+    fn test_advance() {
+        let mut focus = Focus::default();
+        focus.advance(false);
+        assert_eq!(focus.activate(), None);
+        focus.order = vec![Message::PlayNext, Message::Pause, Message::TrashFile];
+        focus.advance(true);
+        assert_eq!(focus.activate(), Some(Message::TrashFile));
+        focus.advance(false);
+        assert_eq!(focus.activate(), Some(Message::PlayNext));
+        // focus follows a moved button
+        focus.advance(false);
+        focus.begin();
+        let (mut a, mut b) = Default::default();
+        focus.button(Button::new(&mut a, Text::new("")), Message::TrashFile);
+        focus.button(Button::new(&mut b, Text::new("")), Message::Pause);
+        assert_eq!(focus.activate(), Some(Message::Pause));
+    }
+}
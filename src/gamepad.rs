@@ -0,0 +1,408 @@
+//! Transport control by gamepad buttons, e.g. an Xbox controller used as couch remote.
+//! On Linux all gamepads are read from the joystick interface, which xpad and other
+//! drivers provide. On Windows XInput controllers are polled, their buttons numbered like
+//! xpad does so mappings carry over. macOS isn't supported.
+//! Gamepads plugged in later are picked up while it runs.
+
+use std::{
+    collections::BTreeMap,
+    sync::mpsc::{channel, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+
+use iced::Element;
+use iced_native::{button, Align, Button, Checkbox, Column, Row, Text};
+use serde::{Deserialize, Serialize};
+
+use crate::focus::Focus;
+use crate::prelude::*;
+use crate::theme;
+use crate::Message;
+
+/// Interval for looking for newly connected gamepads
+const CONNECT_INTERVAL: Duration = Duration::from_secs(3);
+/// Sent between connection attempts to notice when the gamepad got turned off
+const ALIVE: u8 = u8::MAX;
+
+/// Player action of a gamepad or remote control button
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ButtonAction {
+    PlayPause,
+    Next,
+    Previous,
+    Stop,
+    VolumeUp,
+    VolumeDown,
+}
+
+impl ButtonAction {
+    pub const ALL: [ButtonAction; 6] = [
+        ButtonAction::PlayPause,
+        ButtonAction::Next,
+        ButtonAction::Previous,
+        ButtonAction::Stop,
+        ButtonAction::VolumeUp,
+        ButtonAction::VolumeDown,
+    ];
+
+    fn label(self) -> String {
+        match self {
+            ButtonAction::PlayPause => tr!("gamepad-play-pause"),
+            ButtonAction::Next => tr!("gamepad-next"),
+            ButtonAction::Previous => tr!("gamepad-previous"),
+            ButtonAction::Stop => tr!("gamepad-stop"),
+            ButtonAction::VolumeUp => tr!("gamepad-volume-up"),
+            ButtonAction::VolumeDown => tr!("gamepad-volume-down"),
+        }
+    }
+}
+
+/// Button numbers mapped to actions
+pub type GamepadMapping = BTreeMap<u8, ButtonAction>;
+
+/// Xbox controller buttons as numbered by the xpad driver
+pub fn default_mapping() -> GamepadMapping {
+    let mut mapping = GamepadMapping::new();
+    // A
+    mapping.insert(0, ButtonAction::PlayPause);
+    // X and Y
+    mapping.insert(2, ButtonAction::VolumeDown);
+    mapping.insert(3, ButtonAction::VolumeUp);
+    // LB and RB
+    mapping.insert(4, ButtonAction::Previous);
+    mapping.insert(5, ButtonAction::Next);
+    mapping
+}
+
+/// Button presses of all connected gamepads
+pub struct Gamepad {
+    rx: Receiver<u8>,
+}
+
+impl Gamepad {
+    pub fn new() -> Self {
+        let (tx, rx) = channel();
+        thread::spawn(move || read_loop(tx));
+        Self { rx }
+    }
+
+    /// Next pressed button
+    pub fn poll(&self) -> Option<u8> {
+        self.rx.try_iter().find(|b| *b != ALIVE)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn read_loop(tx: Sender<u8>) {
+    use std::{
+        collections::HashSet,
+        path::PathBuf,
+        sync::{Arc, Mutex},
+    };
+
+    // devices read right now, each by its own thread
+    let open: Arc<Mutex<HashSet<PathBuf>>> = Default::default();
+    loop {
+        for device in joysticks() {
+            if !open
+                .lock()
+                .expect("Can't lock gamepads")
+                .insert(device.clone())
+            {
+                continue;
+            }
+            let tx = tx.clone();
+            let open = open.clone();
+            thread::spawn(move || {
+                read_device(&device, &tx);
+                open.lock().expect("Can't lock gamepads").remove(&device);
+            });
+        }
+        thread::sleep(CONNECT_INTERVAL);
+        // stop once the gamepad is turned off in the settings
+        if tx.send(ALIVE).is_err() {
+            return;
+        }
+    }
+}
+
+/// Joystick devices like `/dev/input/js0`
+#[cfg(target_os = "linux")]
+fn joysticks() -> Vec<std::path::PathBuf> {
+    match std::fs::read_dir("/dev/input") {
+        Ok(v) => v
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().starts_with("js"))
+            .map(|e| e.path())
+            .collect(),
+        Err(e) => {
+            debug!("Can't list input devices: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Send the presses of gamepad `device` until it's disconnected or the gamepad is turned off
+#[cfg(target_os = "linux")]
+fn read_device(device: &std::path::Path, tx: &Sender<u8>) {
+    use std::io::Read;
+
+    let mut file = match std::fs::File::open(device) {
+        Ok(v) => v,
+        Err(e) => return debug!("Can't open gamepad {:?}: {}", device, e),
+    };
+    info!("Gamepad {:?} connected", device);
+    let mut event = [0; 8];
+    // fails once the gamepad is disconnected
+    while file.read_exact(&mut event).is_ok() {
+        if let Some(button) = parse_event(&event).filter(|b| *b != ALIVE) {
+            trace!("Gamepad button {}", button);
+            if tx.send(button).is_err() {
+                return;
+            }
+        }
+    }
+    info!("Gamepad {:?} disconnected", device);
+}
+
+/// Interval for reading the state of XInput controllers
+#[cfg(windows)]
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+/// Amount of controllers XInput supports
+#[cfg(windows)]
+const XINPUT_USERS: u32 = 4;
+
+#[cfg(windows)]
+fn read_loop(tx: Sender<u8>) {
+    #[repr(C)]
+    #[derive(Default)]
+    #[allow(dead_code)]
+    struct XInputGamepad {
+        buttons: u16,
+        left_trigger: u8,
+        right_trigger: u8,
+        thumb_lx: i16,
+        thumb_ly: i16,
+        thumb_rx: i16,
+        thumb_ry: i16,
+    }
+    #[repr(C)]
+    #[derive(Default)]
+    #[allow(dead_code)]
+    struct XInputState {
+        packet: u32,
+        gamepad: XInputGamepad,
+    }
+    #[link(name = "xinput")]
+    extern "system" {
+        fn XInputGetState(user: u32, state: *mut XInputState) -> u32;
+    }
+
+    // buttons held by each controller, None while disconnected
+    let mut held: [Option<u16>; XINPUT_USERS as usize] = Default::default();
+    let mut last_connect: Option<std::time::Instant> = None;
+    loop {
+        // polling disconnected controllers is slow, only look for new ones now and then
+        let connect = last_connect.map_or(true, |v| v.elapsed() >= CONNECT_INTERVAL);
+        if connect {
+            last_connect = Some(std::time::Instant::now());
+            // stop once the gamepad is turned off in the settings
+            if tx.send(ALIVE).is_err() {
+                return;
+            }
+        }
+        for (user, held) in held.iter_mut().enumerate() {
+            if held.is_none() && !connect {
+                continue;
+            }
+            let mut state = XInputState::default();
+            // SAFETY: state is a valid XINPUT_STATE
+            if unsafe { XInputGetState(user as u32, &mut state) } != 0 {
+                if held.take().is_some() {
+                    info!("Gamepad {} disconnected", user);
+                }
+                continue;
+            }
+            let buttons = state.gamepad.buttons;
+            let previous = match held.replace(buttons) {
+                Some(v) => v,
+                None => {
+                    info!("Gamepad {} connected", user);
+                    // buttons held while connecting aren't presses
+                    continue;
+                }
+            };
+            for button in xinput_presses(previous, buttons) {
+                trace!("Gamepad button {}", button);
+                if tx.send(button).is_err() {
+                    return;
+                }
+            }
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Gamepads aren't supported on macOS, the settings say so
+#[cfg(not(any(target_os = "linux", windows)))]
+fn read_loop(_tx: Sender<u8>) {}
+
+/// XInput button bits and their xpad button numbers, the D-pad after them
+#[cfg_attr(not(windows), allow(dead_code))]
+const XINPUT_BUTTONS: [(u16, u8); 14] = [
+    // A, B, X, Y
+    (0x1000, 0),
+    (0x2000, 1),
+    (0x4000, 2),
+    (0x8000, 3),
+    // LB, RB
+    (0x0100, 4),
+    (0x0200, 5),
+    // back, start
+    (0x0020, 6),
+    (0x0010, 7),
+    // thumb sticks
+    (0x0040, 9),
+    (0x0080, 10),
+    // D-pad up, down, left, right
+    (0x0001, 11),
+    (0x0002, 12),
+    (0x0004, 13),
+    (0x0008, 14),
+];
+
+/// Button numbers pressed between two XInput button states
+#[cfg_attr(not(windows), allow(dead_code))]
+fn xinput_presses(previous: u16, current: u16) -> Vec<u8> {
+    let pressed = current & !previous;
+    XINPUT_BUTTONS
+        .iter()
+        .filter(|(bit, _)| pressed & bit != 0)
+        .map(|(_, number)| *number)
+        .collect()
+}
+
+/// Button number of a press in a joystick event: time u32, value i16, type u8, number u8.
+/// Synthetic events reporting the initial state have 0x80 set in their type and are skipped.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_event(event: &[u8; 8]) -> Option<u8> {
+    const BUTTON: u8 = 0x01;
+    let value = i16::from_ne_bytes([event[4], event[5]]);
+    match event[6] == BUTTON && value == 1 {
+        true => Some(event[7]),
+        false => None,
+    }
+}
+
+/// Mapping editor in the settings
+#[derive(Default)]
+pub struct GamepadSettings {
+    /// Action the next pressed button is assigned to
+    learning: Option<ButtonAction>,
+    assign: [button::State; ButtonAction::ALL.len()],
+    reset: button::State,
+}
+
+impl GamepadSettings {
+    pub fn learning(&self) -> Option<ButtonAction> {
+        self.learning
+    }
+
+    pub fn learn(&mut self, action: Option<ButtonAction>) {
+        self.learning = action;
+    }
+
+    pub fn view(
+        &mut self,
+        enabled: bool,
+        mapping: &GamepadMapping,
+        focus: &mut Focus,
+    ) -> Element<'_, Message> {
+        if !cfg!(any(target_os = "linux", windows)) {
+            return Text::new(tr!("gamepad-unsupported"))
+                .size(theme::text_size(14))
+                .into();
+        }
+        let mut column = Column::new().spacing(5).push(Checkbox::new(
+            enabled,
+            tr!("gamepad"),
+            Message::GamepadToggled,
+        ));
+        if !enabled {
+            return column.into();
+        }
+        let learning = self.learning;
+        for (action, state) in ButtonAction::ALL.iter().zip(self.assign.iter_mut()) {
+            let buttons: Vec<String> = mapping
+                .iter()
+                .filter(|(_, a)| *a == action)
+                .map(|(b, _)| b.to_string())
+                .collect();
+            let assigned = match (learning == Some(*action), buttons.is_empty()) {
+                (true, _) => tr!("gamepad-press"),
+                (false, true) => tr!("gamepad-unassigned"),
+                (false, false) => buttons.join(", "),
+            };
+            column = column.push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(Text::new(action.label()).size(theme::text_size(14)))
+                    .push(Text::new(assigned).size(theme::text_size(14)))
+                    .push(focus.button(
+                        Button::new(
+                            state,
+                            Text::new(tr!("gamepad-assign")).size(theme::text_size(14)),
+                        ),
+                        Message::LearnGamepadButton(*action),
+                    )),
+            );
+        }
+        column
+            .push(focus.button(
+                Button::new(
+                    &mut self.reset,
+                    Text::new(tr!("gamepad-reset")).size(theme::text_size(14)),
+                ),
+                Message::ResetGamepadMapping,
+            ))
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn event(value: i16, kind: u8, number: u8) -> [u8; 8] {
+        let mut event = [0; 8];
+        event[4..6].copy_from_slice(&value.to_ne_bytes());
+        event[6] = kind;
+        event[7] = number;
+        event
+    }
+
+    #[test]
+    fn test_parse_event() {
+        assert_eq!(parse_event(&event(1, 0x01, 5)), Some(5));
+        // release
+        assert_eq!(parse_event(&event(0, 0x01, 5)), None);
+        // initial state
+        assert_eq!(parse_event(&event(1, 0x81, 5)), None);
+        // axis
+        assert_eq!(parse_event(&event(1, 0x02, 0)), None);
+        assert_eq!(default_mapping().get(&5), Some(&ButtonAction::Next));
+    }
+
+    #[test]
+    fn test_xinput_presses() {
+        // A pressed while RB is held
+        assert_eq!(xinput_presses(0x0200, 0x1200), vec![0]);
+        // released
+        assert!(xinput_presses(0x1200, 0x0200).is_empty());
+        // X and LB together
+        assert_eq!(xinput_presses(0, 0x4100), vec![2, 4]);
+        assert_eq!(xinput_presses(0, 0x0008), vec![14]);
+    }
+}
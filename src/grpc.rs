@@ -0,0 +1,160 @@
+//! gRPC service for automation, see `proto/control.proto`
+// tonic::Status is large, but given by the generated service trait
+#![allow(clippy::result_large_err)]
+
+use std::{net::SocketAddr, pin::Pin, thread};
+
+use tokio_stream::{wrappers::WatchStream, Stream, StreamExt};
+use tonic::{transport::Server, Request, Response};
+
+use crate::control::{ControlCommand, ControlHandle, ControlState, ControlStatus};
+use crate::prelude::*;
+
+mod proto {
+    tonic::include_proto!("audio_wrench");
+}
+
+use proto::control_server::{self, ControlServer};
+use proto::{status::State, Empty, Status, Tracks, Volume};
+
+type RpcResult<T> = std::result::Result<Response<T>, tonic::Status>;
+
+struct Service {
+    control: ControlHandle,
+}
+
+impl Service {
+    fn send(&self, command: ControlCommand) -> RpcResult<Empty> {
+        match self.control.send(command) {
+            true => Ok(Response::new(Empty {})),
+            false => Err(tonic::Status::unavailable("Player is shutting down")),
+        }
+    }
+}
+
+impl From<ControlStatus> for Status {
+    fn from(v: ControlStatus) -> Self {
+        let state = match v.state {
+            ControlState::Stopped => State::Stopped,
+            ControlState::Playing => State::Playing,
+            ControlState::Paused => State::Paused,
+        };
+        Status {
+            state: state.into(),
+            track: v.track,
+            position_secs: v.position,
+            duration_secs: v.duration.map(|d| d.as_secs()),
+            volume: v.volume.into(),
+            queue: v.queue,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl control_server::Control for Service {
+    async fn play(&self, _: Request<Empty>) -> RpcResult<Empty> {
+        self.send(ControlCommand::Play)
+    }
+
+    async fn pause(&self, _: Request<Empty>) -> RpcResult<Empty> {
+        self.send(ControlCommand::Pause)
+    }
+
+    async fn next(&self, _: Request<Empty>) -> RpcResult<Empty> {
+        self.send(ControlCommand::Next)
+    }
+
+    async fn stop(&self, _: Request<Empty>) -> RpcResult<Empty> {
+        self.send(ControlCommand::Stop)
+    }
+
+    async fn set_volume(&self, request: Request<Volume>) -> RpcResult<Empty> {
+        let volume = request.into_inner().volume;
+        if volume > 100 {
+            return Err(tonic::Status::invalid_argument("Volume above 100"));
+        }
+        self.send(ControlCommand::Volume(volume as u8))
+    }
+
+    async fn enqueue(&self, request: Request<Tracks>) -> RpcResult<Empty> {
+        self.send(ControlCommand::Enqueue(request.into_inner().tracks))
+    }
+
+    async fn clear_queue(&self, _: Request<Empty>) -> RpcResult<Empty> {
+        self.send(ControlCommand::ClearQueue)
+    }
+
+    async fn get_status(&self, _: Request<Empty>) -> RpcResult<Status> {
+        Ok(Response::new(self.control.status().into()))
+    }
+
+    type WatchStatusStream =
+        Pin<Box<dyn Stream<Item = std::result::Result<Status, tonic::Status>> + Send>>;
+
+    async fn watch_status(&self, _: Request<Empty>) -> RpcResult<Self::WatchStatusStream> {
+        let stream = WatchStream::new(self.control.watch()).map(|v| Ok(v.into()));
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+/// Check the `authorization: Bearer <token>` metadata of a call if a token is set
+fn authorize(token: Option<&str>, request: &Request<()>) -> std::result::Result<(), tonic::Status> {
+    let token = match token {
+        Some(v) => v,
+        None => return Ok(()),
+    };
+    let sent = request
+        .metadata()
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    match sent == Some(token) {
+        true => Ok(()),
+        false => Err(tonic::Status::unauthenticated("Missing or wrong token")),
+    }
+}
+
+/// Serve on `addr` from a separate thread.
+/// Addresses other than loopback need a `token`, otherwise anyone on the network could
+/// enqueue or clear the queue.
+pub fn spawn(addr: SocketAddr, token: Option<String>, control: ControlHandle) -> Result<()> {
+    if token.is_none() && !addr.ip().is_loopback() {
+        return Err(eyre!(
+            "Listening on other addresses than loopback needs a grpc_token"
+        ));
+    }
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .wrap_err("Can't create gRPC runtime")?;
+    thread::Builder::new()
+        .name("grpc control".to_string())
+        .spawn(move || {
+            let service = ControlServer::with_interceptor(Service { control }, move |request| {
+                authorize(token.as_deref(), &request)?;
+                Ok(request)
+            });
+            info!("gRPC control listening on {}", addr);
+            if let Err(e) = runtime.block_on(Server::builder().add_service(service).serve(addr)) {
+                error!("gRPC control stopped: {}", e);
+            }
+        })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_authorize() {
+        let mut request = Request::new(());
+        assert!(authorize(None, &request).is_ok());
+        assert!(authorize(Some("secret"), &request).is_err());
+        request
+            .metadata_mut()
+            .insert("authorization", "Bearer secret".parse().unwrap());
+        assert!(authorize(Some("secret"), &request).is_ok());
+        assert!(authorize(Some("other"), &request).is_err());
+    }
+}
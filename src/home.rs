@@ -0,0 +1,73 @@
+//! Home screen with the generated mixes of the day
+
+use audio_wrench_core::mixes::{Mix, MixKind};
+use iced::Element;
+use iced_native::{button, Align, Button, Column, Row, Text};
+
+use crate::focus::Focus;
+use crate::theme;
+use crate::Message;
+
+pub struct HomeView {
+    /// Mix day the mixes were generated for
+    day: u64,
+    mixes: Vec<Mix>,
+    mix_buttons: Vec<button::State>,
+    close: button::State,
+}
+
+impl HomeView {
+    pub fn new(day: u64, mixes: Vec<Mix>) -> Self {
+        Self {
+            day,
+            mix_buttons: vec![Default::default(); mixes.len()],
+            mixes,
+            close: Default::default(),
+        }
+    }
+
+    pub fn day(&self) -> u64 {
+        self.day
+    }
+
+    pub fn mix(&self, index: usize) -> Option<&Mix> {
+        self.mixes.get(index)
+    }
+
+    pub fn view(&mut self, focus: &mut Focus) -> Element<'_, Message> {
+        let controls = Row::new()
+            .spacing(10)
+            .align_items(Align::Center)
+            .push(focus.button(
+                Button::new(
+                    &mut self.close,
+                    Text::new(tr!("close")).size(theme::text_size(14)),
+                ),
+                Message::CloseHome,
+            ))
+            .push(Text::new(tr!("mixes")).size(theme::text_size(16)));
+        let mut column = Column::new().spacing(5).push(controls);
+        if self.mixes.is_empty() {
+            column = column.push(Text::new(tr!("no-mixes")).size(theme::text_size(14)));
+        }
+        for (i, (mix, state)) in self
+            .mixes
+            .iter()
+            .zip(self.mix_buttons.iter_mut())
+            .enumerate()
+        {
+            let name = match mix.kind {
+                MixKind::Rediscover => tr!("mix-rediscover"),
+                MixKind::Daily { number, ref genre } => {
+                    tr!("daily-mix", number = number, genre = genre.as_str())
+                }
+            };
+            let label = format!("+ {} ({})", name, mix.tracks.len());
+            column = column.push(focus.button(
+                Button::new(state, Text::new(label).size(theme::text_size(14))),
+                Message::EnqueueMix(i),
+            ));
+        }
+        column.into()
+    }
+}
@@ -0,0 +1,126 @@
+//! User configured commands run on player events
+
+use std::{path::Path, process::Command, thread};
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/// Environment variables holding the values of `{path}` and `{title}`
+const PATH_VAR: &str = "AUDIO_WRENCH_PATH";
+const TITLE_VAR: &str = "AUDIO_WRENCH_TITLE";
+
+/// Shell commands per event, `{path}` and `{title}` are replaced with the track
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Hooks {
+    #[serde(default)]
+    pub track_started: Option<String>,
+    #[serde(default)]
+    pub track_ended: Option<String>,
+    #[serde(default)]
+    pub favorited: Option<String>,
+    #[serde(default)]
+    pub trashed: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    TrackStarted,
+    TrackEnded,
+    Favorited,
+    Trashed,
+}
+
+impl Hooks {
+    /// Run command configured for `event` in the background
+    pub fn run(&self, event: Event, track: &str) {
+        let template = match event {
+            Event::TrackStarted => &self.track_started,
+            Event::TrackEnded => &self.track_ended,
+            Event::Favorited => &self.favorited,
+            Event::Trashed => &self.trashed,
+        };
+        let template = match template {
+            Some(v) if !v.is_empty() => v,
+            _ => return,
+        };
+        trace!("Running hook for {:?}: {}", event, template);
+        match command(template, track).spawn() {
+            Ok(mut child) => {
+                // reap the process without blocking the UI
+                thread::spawn(move || match child.wait() {
+                    Ok(status) if !status.success() => warn!("Hook exited with {}", status),
+                    Err(e) => warn!("Can't wait for hook: {}", e),
+                    Ok(_) => (),
+                });
+            }
+            Err(e) => error!("Can't run hook {:?}: {}", template, e),
+        }
+    }
+}
+
+/// Shell command of `template` for `track`. The values are passed as environment variables
+/// and only referenced in the command, so file names are never parsed by the shell.
+fn command(template: &str, track: &str) -> Command {
+    let title = Path::new(track)
+        .file_stem()
+        .map(|v| v.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut command = shell(&render(template));
+    command.env(PATH_VAR, track).env(TITLE_VAR, title);
+    command
+}
+
+/// Replace template variables with references to their environment variables
+fn render(template: &str) -> String {
+    template
+        .replace("{path}", &variable(PATH_VAR))
+        .replace("{title}", &variable(TITLE_VAR))
+}
+
+/// Delayed expansion happens after the command is parsed, `%VAR%` would be parsed with it
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut cmd = Command::new("cmd");
+    cmd.arg("/V:ON").arg("/C").arg(command);
+    cmd
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(command);
+    cmd
+}
+
+#[cfg(windows)]
+fn variable(name: &str) -> String {
+    format!("\"!{}!\"", name)
+}
+
+#[cfg(not(windows))]
+fn variable(name: &str) -> String {
+    format!("\"${}\"", name)
+}
+
+#[cfg(all(test, not(windows)))]
+#[test]
+fn test_render() {
+    assert_eq!(
+        render("notify {title} {path}"),
+        "notify \"$AUDIO_WRENCH_TITLE\" \"$AUDIO_WRENCH_PATH\""
+    );
+    let dir = std::env::temp_dir().join("audio_wrench_test_hooks");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let track = "/music/{title}/$(touch PWNED)'`touch PWNED`.mp3";
+    let output = command("printf '%s|%s' {path} {title}", track)
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!("{}|$(touch PWNED)'`touch PWNED`", track)
+    );
+    assert!(!dir.join("PWNED").exists());
+}
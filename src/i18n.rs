@@ -0,0 +1,138 @@
+//! UI translations using Fluent bundles embedded at build time.
+//! Use the `tr!` macro for translated strings.
+
+use std::{cell::RefCell, fmt};
+
+use fluent_bundle::{FluentArgs, FluentBundle, FluentResource};
+use serde::{Deserialize, Serialize};
+use unic_langid::LanguageIdentifier;
+
+use crate::prelude::*;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::German];
+
+    fn id(self) -> &'static str {
+        match self {
+            Language::English => "en-US",
+            Language::German => "de",
+        }
+    }
+
+    fn source(self) -> &'static str {
+        match self {
+            Language::English => include_str!("../locales/en-US.ftl"),
+            Language::German => include_str!("../locales/de.ftl"),
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // always shown in the language itself
+        f.write_str(match self {
+            Language::English => "English",
+            Language::German => "Deutsch",
+        })
+    }
+}
+
+type Bundle = FluentBundle<FluentResource>;
+
+thread_local! {
+    static BUNDLE: RefCell<Bundle> = RefCell::new(bundle(Language::default()));
+    /// Used for messages missing in the selected language
+    static FALLBACK: Bundle = bundle(Language::English);
+}
+
+fn bundle(language: Language) -> Bundle {
+    let id: LanguageIdentifier = language.id().parse().expect("Invalid language id");
+    let mut bundle = FluentBundle::new(vec![id]);
+    // isolation marks would be rendered as visible characters
+    bundle.set_use_isolating(false);
+    let resource =
+        FluentResource::try_new(language.source().to_string()).unwrap_or_else(|(res, errors)| {
+            error!("Errors in {} translation: {:?}", language.id(), errors);
+            res
+        });
+    if let Err(errors) = bundle.add_resource(resource) {
+        error!("Errors in {} translation: {:?}", language.id(), errors);
+    }
+    bundle
+}
+
+/// Switch the language for all following translations of this thread
+pub fn set_language(language: Language) {
+    BUNDLE.with(|b| *b.borrow_mut() = bundle(language));
+}
+
+/// Translate message `id`, falls back to english and then to the id itself
+pub fn tr(id: &str, args: Option<&FluentArgs>) -> String {
+    BUNDLE
+        .with(|b| format(&b.borrow(), id, args))
+        .or_else(|| FALLBACK.with(|b| format(b, id, args)))
+        .unwrap_or_else(|| {
+            warn!("Missing translation for {}", id);
+            id.to_string()
+        })
+}
+
+fn format(bundle: &Bundle, id: &str, args: Option<&FluentArgs>) -> Option<String> {
+    let pattern = bundle.get_message(id)?.value()?;
+    let mut errors = Vec::new();
+    let value = bundle.format_pattern(pattern, args, &mut errors);
+    if !errors.is_empty() {
+        warn!("Errors formatting {}: {:?}", id, errors);
+    }
+    Some(value.into_owned())
+}
+
+/// Translated string, with optional `name = value` arguments
+macro_rules! tr {
+    ($id:expr) => {
+        $crate::i18n::tr($id, None)
+    };
+    ($id:expr, $($name:ident = $value:expr),+ $(,)?) => {{
+        let mut args = fluent_bundle::FluentArgs::new();
+        $(args.set(stringify!($name), $value);)+
+        $crate::i18n::tr($id, Some(&args))
+    }};
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_translate() {
+        set_language(Language::German);
+        assert_eq!(tr!("play"), "Abspielen");
+        assert_eq!(tr!("volume", volume = 5), "5% Lautstärke");
+        assert_eq!(tr!("missing-id"), "missing-id");
+        set_language(Language::English);
+        assert_eq!(tr!("preamp", gain = "+3"), "Preamp +3 dB");
+    }
+
+    #[test]
+    fn test_complete() {
+        // every english message needs a translation
+        let ids = Language::English
+            .source()
+            .lines()
+            .filter_map(|l| l.split_once(" = "))
+            .map(|(id, _)| id);
+        for language in Language::ALL.iter() {
+            let bundle = bundle(*language);
+            for id in ids.clone() {
+                assert!(bundle.has_message(id), "{} missing in {}", id, language);
+            }
+        }
+    }
+}
@@ -0,0 +1,361 @@
+//! Jellyfin music library client
+
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use url::Url;
+
+use crate::hooks::Event;
+use crate::plugin::{Entry, Listing, Plugin, TrackSource};
+use crate::prelude::*;
+use crate::remote::{self, JellyfinSession};
+use crate::secrets::Secret;
+
+/// Interval for reporting playback progress to the server
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(10);
+/// Jellyfin ticks per second
+const TICKS_PER_SECOND: u64 = 10_000_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JellyfinConfig {
+    /// Server base URL, e.g. `https://jellyfin.example.com`
+    pub url: String,
+    pub user: String,
+    /// Kept in the secret store, written into the config only while storing it fails
+    #[serde(default, skip_serializing_if = "Secret::skip_config")]
+    pub password: Secret,
+}
+
+#[derive(Clone)]
+pub struct Jellyfin {
+    config: JellyfinConfig,
+    /// Shared with the browsing jobs
+    session: Arc<Mutex<Option<JellyfinSession>>>,
+    last_progress: Option<Instant>,
+}
+
+impl Jellyfin {
+    pub fn new(config: JellyfinConfig) -> Self {
+        Self {
+            config,
+            session: Default::default(),
+            last_progress: None,
+        }
+    }
+
+    fn session(&self) -> Option<JellyfinSession> {
+        self.session.lock().expect("Can't lock session").clone()
+    }
+
+    fn authorization(&self) -> String {
+        remote::jellyfin_authorization(
+            &self.config.user,
+            self.session().as_ref().map(|v| v.token.as_str()),
+        )
+    }
+
+    fn endpoint(&self, path: &str) -> Result<Url> {
+        Ok(Url::parse(&self.config.url)?.join(path)?)
+    }
+
+    /// Login if no session exists
+    fn login(&self) -> Result<JellyfinSession> {
+        let mut session = self.session.lock().expect("Can't lock session");
+        if session.is_none() {
+            *session = Some(remote::jellyfin_login(
+                &self.config.url,
+                &self.config.user,
+                self.config.password.as_str(),
+            )?);
+        }
+        Ok(session.clone().expect("session set on login"))
+    }
+
+    /// Items of a query below the users library
+    fn items(&self, params: &[(&str, &str)]) -> Result<Vec<Value>> {
+        let user_id = self.login()?.user_id;
+        self.get(&format!("Users/{}/Items", user_id), params)
+    }
+
+    fn get(&self, path: &str, params: &[(&str, &str)]) -> Result<Vec<Value>> {
+        self.login()?;
+        let mut request = remote::agent()
+            .get(self.endpoint(path)?.as_str())
+            .set("X-Emby-Authorization", &self.authorization());
+        for (k, v) in params {
+            request = request.query(k, v);
+        }
+        Ok(items(request.call()?.into_json()?))
+    }
+
+    fn folders(items: &[Value], prefix: &str) -> Vec<Entry> {
+        items
+            .iter()
+            .map(|v| Entry {
+                id: format!("{}:{}", prefix, v["Id"].as_str().unwrap_or_default()),
+                title: v["Name"].as_str().unwrap_or_default().to_string(),
+                track: None,
+            })
+            .collect()
+    }
+
+    /// Song entries, the tracks are resolved to stream URLs on playback
+    fn songs(items: &[Value]) -> Vec<Entry> {
+        items
+            .iter()
+            .map(|item| {
+                let id = item["Id"].as_str().unwrap_or_default();
+                let name = item["Name"].as_str().unwrap_or(id);
+                let title = match item["AlbumArtist"].as_str() {
+                    Some(artist) => format!("{} - {}", artist, name),
+                    None => name.to_string(),
+                };
+                Entry {
+                    id: format!("song:{}", id),
+                    title,
+                    track: Some(format!("{}{}", remote::JELLYFIN, id)),
+                }
+            })
+            .collect()
+    }
+
+    /// Item id if `track` is a song of this server
+    fn item_id(&self, track: &str) -> Option<String> {
+        track
+            .strip_prefix(remote::JELLYFIN)
+            .map(str::to_string)
+            .or_else(|| legacy_item_id(&self.config.url, track))
+    }
+
+    /// Entries of folder `parent`
+    fn list(&self, parent: &str) -> Result<Vec<Entry>> {
+        let (kind, id) = parent.split_once(':').unwrap_or((parent, ""));
+        match kind {
+            "artists" => {
+                let user_id = self.login()?.user_id;
+                let items = self.get("Artists", &[("UserId", &user_id)])?;
+                Ok(Self::folders(&items, "artist"))
+            }
+            "artist" => {
+                let items = self.items(&[
+                    ("ArtistIds", id),
+                    ("IncludeItemTypes", "MusicAlbum"),
+                    ("Recursive", "true"),
+                    ("SortBy", "ProductionYear,SortName"),
+                ])?;
+                Ok(Self::folders(&items, "album"))
+            }
+            "albums" => {
+                let items = self.items(&[
+                    ("IncludeItemTypes", "MusicAlbum"),
+                    ("Recursive", "true"),
+                    ("SortBy", "SortName"),
+                ])?;
+                Ok(Self::folders(&items, "album"))
+            }
+            "album" => {
+                let items = self.items(&[
+                    ("ParentId", id),
+                    ("IncludeItemTypes", "Audio"),
+                    ("SortBy", "ParentIndexNumber,IndexNumber,SortName"),
+                ])?;
+                Ok(Self::songs(&items))
+            }
+            "playlists" => {
+                let items =
+                    self.items(&[("IncludeItemTypes", "Playlist"), ("Recursive", "true")])?;
+                Ok(Self::folders(&items, "playlist"))
+            }
+            "playlist" => {
+                let user_id = self.login()?.user_id;
+                let items =
+                    self.get(&format!("Playlists/{}/Items", id), &[("UserId", &user_id)])?;
+                Ok(Self::songs(&items))
+            }
+            _ => Err(eyre!("Unknown jellyfin entry {}", parent)),
+        }
+    }
+
+    /// Report playback state in the background
+    fn report(&self, path: &str, body: Value) {
+        let url = match self.endpoint(path) {
+            Ok(v) => v,
+            Err(e) => {
+                warn!("Invalid jellyfin URL: {}", e);
+                return;
+            }
+        };
+        let authorization = self.authorization();
+        thread::spawn(move || {
+            if let Err(e) = remote::agent()
+                .post(url.as_str())
+                .set("X-Emby-Authorization", &authorization)
+                .send_json(body)
+            {
+                warn!("Can't report playback to jellyfin: {}", e);
+            }
+        });
+    }
+}
+
+/// Items of a query response, empty without any
+fn items(mut response: Value) -> Vec<Value> {
+    match response["Items"].take() {
+        Value::Array(v) => v,
+        _ => Vec::new(),
+    }
+}
+
+/// Progress report of item `id` played up to `position`
+fn progress(id: &str, position: Duration) -> Value {
+    let ticks = position.as_millis() as u64 * (TICKS_PER_SECOND / 1000);
+    json!({ "ItemId": id, "PositionTicks": ticks })
+}
+
+/// Item id of a stream URL of the server at `url`, as stored by older versions
+/// together with the token
+pub fn legacy_item_id(url: &str, track: &str) -> Option<String> {
+    if !track.starts_with(url) {
+        return None;
+    }
+    let url = Url::parse(track).ok()?;
+    let mut segments = url.path_segments()?.rev();
+    match (segments.next(), segments.next()) {
+        (Some("stream"), Some(id)) => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+impl Plugin for Jellyfin {
+    fn name(&self) -> &'static str {
+        "Jellyfin"
+    }
+
+    fn on_event(&mut self, event: Event, track: &str) {
+        if self.session().is_none() {
+            return;
+        }
+        if let Some(id) = self.item_id(track) {
+            match event {
+                Event::TrackStarted => {
+                    self.last_progress = Some(Instant::now());
+                    self.report("Sessions/Playing", json!({ "ItemId": id }));
+                }
+                Event::TrackEnded => {
+                    self.last_progress = None;
+                    self.report("Sessions/Playing/Stopped", json!({ "ItemId": id }));
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn on_progress(&mut self, track: &str, position: Duration) {
+        if self.session().is_none()
+            || self
+                .last_progress
+                .is_some_and(|v| v.elapsed() < PROGRESS_INTERVAL)
+        {
+            return;
+        }
+        if let Some(id) = self.item_id(track) {
+            self.last_progress = Some(Instant::now());
+            self.report("Sessions/Playing/Progress", progress(&id, position));
+        }
+    }
+
+    fn source(&mut self) -> Option<&mut dyn TrackSource> {
+        Some(self)
+    }
+}
+
+impl TrackSource for Jellyfin {
+    fn browse(&mut self, parent: Option<&str>) -> Listing {
+        let source = self.clone();
+        let parent = parent.map(str::to_string);
+        Box::new(move || match parent {
+            Some(parent) => source.list(&parent),
+            None => Ok(["Artists", "Albums", "Playlists"]
+                .iter()
+                .map(|v| Entry {
+                    id: v.to_lowercase(),
+                    title: v.to_string(),
+                    track: None,
+                })
+                .collect()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn source() -> Jellyfin {
+        Jellyfin::new(JellyfinConfig {
+            url: String::from("https://jellyfin.example.com/"),
+            user: String::from("me"),
+            password: Secret::from(String::from("hunter2")),
+        })
+    }
+
+    #[test]
+    fn test_authorization() {
+        let source = source();
+        let header = source.authorization();
+        assert!(header.starts_with("MediaBrowser Client=\""));
+        assert!(header.contains("DeviceId=\""));
+        assert!(!header.contains("Token"));
+        *source.session.lock().unwrap() = Some(JellyfinSession {
+            token: String::from("abc"),
+            user_id: String::from("42"),
+        });
+        assert!(source.authorization().ends_with(", Token=\"abc\""));
+        assert!(!source.authorization().contains("hunter2"));
+    }
+
+    #[test]
+    fn test_parse_items() {
+        let response = json!({"Items": [
+            {"Id": "a1", "Name": "Intro", "AlbumArtist": "Band"},
+            {"Id": "a2"},
+        ], "TotalRecordCount": 2});
+        let songs = Jellyfin::songs(&items(response.clone()));
+        assert_eq!(
+            songs[0],
+            Entry {
+                id: String::from("song:a1"),
+                title: String::from("Band - Intro"),
+                track: Some(String::from("jellyfin://a1")),
+            }
+        );
+        // untitled items are shown by their id
+        assert_eq!(songs[1].title, "a2");
+        let albums = Jellyfin::folders(&items(response), "album");
+        assert_eq!(albums[0].id, "album:a1");
+        assert_eq!(albums[0].track, None);
+        assert!(items(json!({})).is_empty());
+    }
+
+    #[test]
+    fn test_progress() {
+        assert_eq!(
+            progress("a1", Duration::from_millis(1500)),
+            json!({"ItemId": "a1", "PositionTicks": 15_000_000})
+        );
+        let source = source();
+        assert_eq!(source.item_id("jellyfin://a1").as_deref(), Some("a1"));
+        assert_eq!(
+            source
+                .item_id("https://jellyfin.example.com/Audio/a1/stream?api_key=abc")
+                .as_deref(),
+            Some("a1")
+        );
+        assert_eq!(source.item_id("/music/song.mp3"), None);
+    }
+}
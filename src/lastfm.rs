@@ -0,0 +1,65 @@
+//! Last.fm API access, used for similar artists of the radio
+
+use std::{
+    sync::mpsc::{channel, Receiver},
+    thread,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::prelude::*;
+use crate::remote;
+
+const API_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+/// Similar artists requested per artist
+const SIMILAR_LIMIT: &str = "50";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LastFmConfig {
+    /// API key of a Last.fm API account
+    pub api_key: String,
+}
+
+/// Names of artists similar to `artist`, most similar first
+pub fn similar_artists(config: &LastFmConfig, artist: &str) -> Result<Vec<String>> {
+    let body: Value = remote::agent()
+        .get(API_URL)
+        .query("method", "artist.getsimilar")
+        .query("artist", artist)
+        .query("autocorrect", "1")
+        .query("limit", SIMILAR_LIMIT)
+        .query("api_key", &config.api_key)
+        .query("format", "json")
+        .call()?
+        .into_json()?;
+    if let Some(message) = body["message"].as_str() {
+        return Err(eyre!("Last.fm error: {}", message));
+    }
+    Ok(body["similarartists"]["artist"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v["name"].as_str())
+        .map(String::from)
+        .collect())
+}
+
+/// Fetch similar artists in the background, nothing is sent on failure
+pub fn spawn_similar_artists(config: LastFmConfig, artist: String) -> Receiver<Vec<String>> {
+    let (tx, rx) = channel();
+    let spawned =
+        thread::Builder::new().name("lastfm".into()).spawn(move || {
+            match similar_artists(&config, &artist) {
+                Ok(v) => {
+                    debug!("{} artists similar to {}", v.len(), artist);
+                    let _ = tx.send(v);
+                }
+                Err(e) => warn!("Can't fetch artists similar to {}: {:?}", artist, e),
+            }
+        });
+    if let Err(e) = spawned {
+        error!("Can't start Last.fm request: {}", e);
+    }
+    rx
+}
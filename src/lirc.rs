@@ -0,0 +1,163 @@
+//! Infrared remote controls through the LIRC daemon.
+//! lircd also forwards remotes decoded by the kernel with its devinput driver,
+//! so evdev remotes work through the same socket.
+
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::mpsc::{channel, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::gamepad::ButtonAction;
+use crate::prelude::*;
+
+/// Wait before reconnecting after the daemon went away
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Button names of a remote mapped to actions
+pub type RemoteMapping = BTreeMap<String, ButtonAction>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LircConfig {
+    #[serde(default = "default_socket")]
+    pub socket: PathBuf,
+    /// Mappings by remote name, remotes without one use the standard key names
+    #[serde(default)]
+    pub remotes: BTreeMap<String, RemoteMapping>,
+}
+
+fn default_socket() -> PathBuf {
+    PathBuf::from("/var/run/lirc/lircd")
+}
+
+impl LircConfig {
+    /// Action of a line sent by lircd: code, repeat count, button and remote
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    fn action(&self, line: &str) -> Option<ButtonAction> {
+        let mut fields = line.split_whitespace().skip(1);
+        let repeat = u32::from_str_radix(fields.next()?, 16).ok()?;
+        let button = fields.next()?;
+        let remote = fields.next()?;
+        let action = match self.remotes.get(remote) {
+            Some(mapping) => mapping.get(button).copied(),
+            None => standard_action(button),
+        }?;
+        // holding the button only changes the volume further
+        match repeat == 0 || matches!(action, ButtonAction::VolumeUp | ButtonAction::VolumeDown) {
+            true => Some(action),
+            false => None,
+        }
+    }
+}
+
+/// Action of the Linux input key names most remote configurations use
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn standard_action(button: &str) -> Option<ButtonAction> {
+    match button {
+        "KEY_PLAYPAUSE" | "KEY_PLAY" | "KEY_PAUSE" | "KEY_OK" => Some(ButtonAction::PlayPause),
+        "KEY_NEXT" | "KEY_NEXTSONG" | "KEY_FASTFORWARD" => Some(ButtonAction::Next),
+        "KEY_PREVIOUS" | "KEY_PREVIOUSSONG" | "KEY_REWIND" => Some(ButtonAction::Previous),
+        "KEY_STOP" | "KEY_STOPCD" => Some(ButtonAction::Stop),
+        "KEY_VOLUMEUP" => Some(ButtonAction::VolumeUp),
+        "KEY_VOLUMEDOWN" => Some(ButtonAction::VolumeDown),
+        _ => None,
+    }
+}
+
+/// Actions of the buttons pressed on any remote
+pub struct Lirc {
+    rx: Receiver<ButtonAction>,
+}
+
+impl Lirc {
+    pub fn new(config: LircConfig) -> Self {
+        let (tx, rx) = channel();
+        thread::spawn(move || read_loop(config, tx));
+        Self { rx }
+    }
+
+    /// Next pressed button
+    pub fn poll(&self) -> Option<ButtonAction> {
+        self.rx.try_recv().ok()
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn read_loop(config: LircConfig, tx: Sender<ButtonAction>) {
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::net::UnixStream;
+
+    let mut connected = true;
+    loop {
+        match UnixStream::connect(&config.socket) {
+            Ok(stream) => {
+                info!("Connected to lircd at {:?}", config.socket);
+                connected = true;
+                for line in BufReader::new(stream).lines() {
+                    let line = match line {
+                        Ok(v) => v,
+                        Err(e) => {
+                            warn!("Can't read from lircd: {}", e);
+                            break;
+                        }
+                    };
+                    trace!("lircd: {}", line);
+                    if let Some(action) = config.action(&line) {
+                        if tx.send(action).is_err() {
+                            return;
+                        }
+                    }
+                }
+                info!("Disconnected from lircd");
+            }
+            Err(e) if connected => {
+                warn!("Can't connect to lircd at {:?}: {}", config.socket, e);
+                connected = false;
+            }
+            Err(_) => (),
+        }
+        thread::sleep(RECONNECT_DELAY);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_loop(_config: LircConfig, _tx: Sender<ButtonAction>) {
+    warn!("LIRC is only supported on Linux");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_action() {
+        let mut mapping = RemoteMapping::new();
+        mapping.insert(String::from("KEY_RED"), ButtonAction::Stop);
+        let mut config = LircConfig {
+            socket: default_socket(),
+            remotes: BTreeMap::new(),
+        };
+        config.remotes.insert(String::from("tv"), mapping);
+        assert_eq!(
+            config.action("0000000000f40bf0 00 KEY_NEXT mceusb"),
+            Some(ButtonAction::Next)
+        );
+        // repeats of the same press
+        assert_eq!(config.action("0000000000f40bf0 01 KEY_NEXT mceusb"), None);
+        assert_eq!(
+            config.action("0000000000f40bf1 0a KEY_VOLUMEUP mceusb"),
+            Some(ButtonAction::VolumeUp)
+        );
+        assert_eq!(
+            config.action("0000000000000001 00 KEY_RED tv"),
+            Some(ButtonAction::Stop)
+        );
+        // remotes with an own mapping don't use the standard names
+        assert_eq!(config.action("0000000000000002 00 KEY_NEXT tv"), None);
+        assert_eq!(config.action("garbage"), None);
+    }
+}
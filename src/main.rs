@@ -3,21 +3,118 @@
 use dirs::data_local_dir;
 use iced::{executor, window, Align, Application, Element, Settings, Subscription};
 
+use albums::AlbumGrid;
+use artist::ArtistPage;
+use audio_wrench_core::alarm::{self, Alarm, NaiveDateTime, QuietHours, Weekday};
+use audio_wrench_core::archive::{self, Archive, SyncResult};
+use audio_wrench_core::{
+    cd,
+    charset::{self, Charset},
+    content_filter::{ContentFilter, Matcher},
+    covers,
+    cues::{self, Cue, CueFormat},
+    decoders, dsp, durations, favorites,
+    folders::PlaylistFolders,
+    identity, is_audio_file, is_playlist_file, itunes,
+    journal::{self, Journal},
+    library::{self, FillStrategy},
+    metadata::{self, TrackInfo},
+    mixes, mtp, notes, ogg, output, pathutil, player, playlist, portable, queue, relocate, remote,
+    rip,
+    scrobbles::{self, ScrobbleFormat},
+    selection::Selection,
+    store::{self, Store},
+    tags,
+};
+use browser::Browser;
+use bulk::{BulkAction, BulkBar, PlaylistChoice, SelectionList, Targets};
+use control::{Control, ControlCommand, ControlState, ControlStatus};
+use dsp::{ChannelMapping, DspStage, EqPreset, Levels};
+use ducking::{DuckMode, Ducking};
+use favorites::Favorites;
+use focus::Focus;
+use gamepad::{ButtonAction, Gamepad, GamepadMapping, GamepadSettings};
+use home::HomeView;
+use hooks::{Event, Hooks};
+use i18n::Language;
+use jellyfin::{Jellyfin, JellyfinConfig};
+use lastfm::LastFmConfig;
+use lirc::{Lirc, LircConfig};
 use log::{log_enabled, LevelFilter};
-use player::{PlayerCommand, PlayerStatus};
+use media_session::{MediaControlEvent, MediaSession};
+use mqtt::MqttConfig;
+use now_playing::NowPlayingExport;
+use output::AudioRouting;
+use pins::{Pin, PinBar};
+use player::{PlaybackState, PlayerCommand, PlayerStatus};
+use plugin::Plugins;
+use radio::Radio;
+use recent::{Days, RecentView};
+use ripping::{RipPanel, RipSettings};
+use search::{Choice, Decade, LibrarySearch};
+use secrets::Secret;
+use send::{SendPanel, SendSettings, SendSource};
+use sidebar::{FolderChoice, Sidebar};
+use subsonic::{Subsonic, SubsonicConfig};
+use tags::Tag;
+use theme::Theme;
+use touch::{Swipe, SwipeArea, SwipeTracker, TouchMode};
+use url::Url;
+use webdav::{WebDav, WebDavConfig};
+use wheel::WheelArea;
 
-pub mod prelude {
-    pub use log::{debug, error, info, trace, warn};
-    pub use stable_eyre::eyre::{eyre, Report, WrapErr};
-    pub type Result<T> = std::result::Result<T, Report>;
-}
-mod player;
-mod playlist;
+pub use audio_wrench_core::prelude;
+// macros have to be defined before use
+#[macro_use]
+mod i18n;
+mod albums;
+mod artist;
+#[cfg(target_os = "linux")]
+mod audio_cd;
+mod browser;
+mod bulk;
+mod control;
+mod desktop;
+mod ducking;
+mod focus;
+mod gamepad;
+mod grpc;
+mod home;
+mod hooks;
+mod jellyfin;
+mod lastfm;
+mod lirc;
+mod media_session;
+mod mqtt;
+mod now_playing;
+mod osd;
+mod pins;
+mod plugin;
+mod queue_window;
+mod radio;
+mod recent;
+mod ripping;
+mod search;
+#[cfg(target_os = "linux")]
+mod secret_service;
+mod secrets;
+mod send;
+mod sidebar;
+mod subsonic;
+mod theme;
+mod timing;
+mod touch;
+mod tui;
+mod voice;
+mod webdav;
+mod wheel;
 
 use prelude::*;
 
 use iced_native::{
-    button, slider, Button, Column, Command, HorizontalAlignment, Length, Row, Slider, Text,
+    button, image, mouse::ScrollDelta, pick_list, slider, text_input, Button, Checkbox, Column,
+    Command, HorizontalAlignment, Image, Length, PickList, ProgressBar, Row, Slider, Text,
+    TextInput,
 };
 use rand::prelude::*;
 
@@ -29,419 +126,6889 @@ use std::{
     collections::HashMap,
     fs::File,
     io::Write,
-    path::PathBuf,
-    sync::mpsc::{Receiver, Sender},
-    time::Duration,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    process::Child,
+    sync::{
+        mpsc::{Receiver, Sender},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use std::{
+    collections::{BTreeSet, HashSet},
+    thread::JoinHandle,
 };
-use std::{collections::HashSet, thread::JoinHandle};
 
 const SAVE_INTERVAL: Duration = Duration::from_secs(60 * 30);
+/// How long the clipping indicator stays lit
+const CLIP_HOLD: Duration = Duration::from_secs(1);
+/// UI scale range in percent, applied on top of the system DPI scaling
+const UI_SCALE_RANGE: std::ops::RangeInclusive<u16> = 50..=300;
+/// Text size range in percent
+const TEXT_SCALE_RANGE: std::ops::RangeInclusive<u16> = 50..=200;
+/// Default progress report interval while the window is visible
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+/// Progress report interval while the position is shown with milliseconds
+const PRECISE_INTERVAL: Duration = Duration::from_millis(20);
+/// Default progress report interval while the window is minimized
+const BACKGROUND_INTERVAL: Duration = Duration::from_secs(1);
+/// Progress report interval while minimized and paused, the title can't change
+const BACKGROUND_IDLE_INTERVAL: Duration = Duration::from_secs(5);
+/// Amount of tracks shown per section of a playlist comparison
+const DIFF_PREVIEW: usize = 10;
+/// Amount of upcoming tracks shown in the queue view
+const QUEUE_PREVIEW: usize = 5;
+
+/// Small frequent changes, journaled between full config saves
+#[derive(Debug, Serialize, Deserialize)]
+enum Change {
+    Favorite {
+        list: String,
+        track: String,
+        favorite: bool,
+    },
+    /// Empty note removes it
+    Note { track: String, note: String },
+    Tag {
+        track: String,
+        tag: String,
+        set: bool,
+    },
+    /// Track found under a new path
+    Moved { from: String, to: String },
+}
+
+/// Inverse of the last bulk action, undone as a whole
+#[derive(Default)]
+struct Undo {
+    /// Changed queues as they were before, the playing one is dropped once it advances
+    queues: HashMap<PathBuf, Vec<String>>,
+    /// Changes restoring favorites and tags
+    changes: Vec<Change>,
+    /// Moved files by their new path, with the path they came from
+    moved: HashMap<String, PathBuf>,
+}
+
+/// Default size limit of the cover cache in MiB
+const COVER_CACHE_SIZE: u64 = 64;
+
+/// Journaled changes before a full save is done
+const JOURNAL_COMPACT: usize = 1000;
+/// Tracks at least this long continue where they were left, like audiobooks and podcasts
+const RESUME_MIN_LENGTH: Duration = Duration::from_secs(20 * 60);
+/// Default volume change per mouse wheel step in percent
+const WHEEL_VOLUME_STEP: u8 = 2;
+/// Default seek per mouse wheel step
+const WHEEL_SEEK_STEP: Duration = Duration::from_secs(5);
+/// Pixels scrolled on touchpads counting as one wheel step
+const WHEEL_STEP_PIXELS: f32 = 20.0;
+/// Positions this close to the start aren't worth resuming
+const RESUME_MIN_POSITION: Duration = Duration::from_secs(30);
+/// Previous restarts the current track once it played longer than this
+const RESTART_AFTER: Duration = Duration::from_secs(3);
+/// Played tracks kept for going back
+const PREVIOUS_TRACKS: usize = 100;
+
+/// Queues by playlist path
+type Playlists = HashMap<PathBuf, Vec<String>>;
 
 #[derive(Serialize, Deserialize, Default)]
 struct ConfigData<'a> {
-    playlists: Cow<'a, HashMap<PathBuf, Vec<String>>>,
-    favorites: Cow<'a, HashSet<String>>,
+    /// Shared with the UI state, so saving doesn't copy all queues.
+    /// Kept in the database, only written here if it can't be opened.
+    #[serde(default)]
+    playlists: Arc<Playlists>,
+    /// Single favorites list of older versions, moved into the default list on load
+    #[serde(default, skip_serializing)]
+    favorites: HashSet<String>,
+    /// Favorite lists by name, kept in the database like the queues
+    #[serde(default)]
+    favorite_lists: Arc<HashMap<String, HashSet<String>>>,
+    /// Notes by track, shared with the UI state like the queues
+    #[serde(default)]
+    notes: Arc<HashMap<String, String>>,
+    /// Available tags, toggled by the number keys in this order
+    #[serde(default)]
+    tags: Cow<'a, [Tag]>,
+    /// Tag names by track
+    #[serde(default)]
+    track_tags: Arc<HashMap<String, BTreeSet<String>>>,
+    /// Saved sessions by name
+    #[serde(default)]
+    snapshots: Cow<'a, HashMap<String, Snapshot>>,
+    /// Folders scanned for the library views besides queued and favorite tracks
+    #[serde(default)]
+    library_folders: Cow<'a, [PathBuf]>,
+    #[serde(default)]
+    alarms: Cow<'a, [Alarm]>,
+    /// Volume ceiling at night, none if unset
+    #[serde(default)]
+    quiet_hours: Option<QuietHours>,
+    #[serde(default)]
+    content_filter: Cow<'a, ContentFilter>,
+    #[serde(default)]
+    playlist_folders: Cow<'a, PlaylistFolders>,
+    #[serde(default)]
+    pins: Cow<'a, [Pin]>,
+    /// Tracks taken from the front of each playlist's queue
+    #[serde(default)]
+    queue_played: Cow<'a, HashMap<PathBuf, usize>>,
+    /// Last volume per output device name
+    #[serde(default)]
+    device_volumes: Cow<'a, HashMap<String, u8>>,
     volume: u8,
     path: PathBuf,
     current_playlist: Cow<'a, String>,
+    /// Only play tracks containing this text, empty for no filter
+    #[serde(default)]
+    filter: Cow<'a, str>,
+    #[serde(default)]
+    routing: Cow<'a, AudioRouting>,
+    /// Preamp gain in dB
+    #[serde(default)]
+    preamp: i16,
+    /// Equalizer presets by name
+    #[serde(default)]
+    eq_presets: Cow<'a, HashMap<String, EqPreset>>,
+    #[serde(default)]
+    playlist_settings: Cow<'a, HashMap<PathBuf, PlaylistSettings>>,
+    /// External DSP stages, applied in order
+    #[serde(default)]
+    dsp_chain: Cow<'a, [DspStage]>,
+    #[serde(default)]
+    hooks: Cow<'a, Hooks>,
+    /// Reaction to audio of other applications
+    #[serde(default)]
+    ducking: Cow<'a, Ducking>,
+    /// Show overlay on track change
+    #[serde(default)]
+    osd: bool,
+    /// UI scale in percent
+    #[serde(default = "default_ui_scale")]
+    ui_scale: u16,
+    /// Double size layout with swipe gestures
+    #[serde(default)]
+    touch_mode: TouchMode,
+    #[serde(default)]
+    language: Language,
+    #[serde(default)]
+    theme: Theme,
+    #[serde(default)]
+    time_display: TimeDisplay,
+    #[serde(default)]
+    cue_format: CueFormat,
+    /// Encoding playlist files are read in, detected if None
+    #[serde(default)]
+    playlist_charset: Option<Charset>,
+    #[serde(default)]
+    title_info: bool,
+    /// Keep unplayable tracks in the queue, marked as broken
+    #[serde(default)]
+    keep_broken: bool,
+    /// Follow moved tracks by content hash, hashing queued tracks in the background
+    #[serde(default)]
+    track_identity: bool,
+    /// Refill the queue from the library once it ran out, None to stop playing.
+    /// Only read from configs without `queue_end`.
+    #[serde(default)]
+    endless: Option<FillStrategy>,
+    #[serde(default)]
+    queue_end: Option<QueueEnd>,
+    #[serde(default)]
+    broken: Arc<HashSet<String>>,
+    /// Progress report interval in ms
+    #[serde(default)]
+    progress_interval: Option<u64>,
+    /// Progress report interval in ms while minimized
+    #[serde(default)]
+    background_interval: Option<u64>,
+    /// Played seconds per track in scan mode
+    #[serde(default)]
+    scan_length: Option<u64>,
+    /// Volume change per mouse wheel step in percent
+    #[serde(default)]
+    wheel_volume_step: Option<u8>,
+    /// Seek per horizontal mouse wheel step in seconds
+    #[serde(default)]
+    wheel_seek_step: Option<u64>,
+    /// Size limit of the cover cache in MiB
+    #[serde(default)]
+    cover_cache_size: Option<u64>,
+    /// Text size in percent, independent of the UI scale
+    #[serde(default = "default_ui_scale")]
+    text_scale: u16,
+    #[serde(default)]
+    now_playing: Option<Cow<'a, NowPlayingExport>>,
+    #[serde(default)]
+    disabled_plugins: Cow<'a, HashSet<String>>,
+    #[serde(default)]
+    subsonic: Option<Cow<'a, SubsonicConfig>>,
+    #[serde(default)]
+    jellyfin: Option<Cow<'a, JellyfinConfig>>,
+    #[serde(default)]
+    webdav: Option<Cow<'a, WebDavConfig>>,
+    /// Folder the settings archive is synced to
+    #[serde(default)]
+    sync_folder: Option<PathBuf>,
+    /// Playlist of all favorite lists, rewritten whenever the favorites change
+    #[serde(default)]
+    favorites_export: Option<PathBuf>,
+    /// Base folder of the last portable queue export, e.g. a USB stick
+    #[serde(default)]
+    portable_folder: Option<PathBuf>,
+    /// Copy tracks outside of the portable folder next to the exported playlist
+    #[serde(default)]
+    portable_copy: bool,
+    /// Playlist format of portable exports
+    #[serde(default)]
+    portable_profile: portable::Profile,
+    /// Random id of this installation, to tell synced archives apart
+    #[serde(default)]
+    device_id: u64,
+    /// Unix time of the last sync
+    #[serde(default)]
+    last_sync: u64,
+    /// Address of the gRPC control service, disabled if unset
+    #[serde(default)]
+    grpc_listen: Option<SocketAddr>,
+    /// Token gRPC clients have to send, required for addresses other than loopback
+    #[serde(default)]
+    grpc_token: Option<String>,
+    /// Address of the line based voice assistant interface, disabled if unset
+    #[serde(default)]
+    voice_listen: Option<SocketAddr>,
+    #[serde(default)]
+    mqtt: Option<Cow<'a, MqttConfig>>,
+    /// Similar artists for the radio, only used if set
+    #[serde(default)]
+    lastfm: Option<Cow<'a, LastFmConfig>>,
+    /// Transport control by gamepad buttons
+    #[serde(default)]
+    gamepad: bool,
+    /// Default Xbox controller mapping if unset
+    #[serde(default)]
+    gamepad_mapping: Option<Cow<'a, GamepadMapping>>,
+    /// Infrared remotes, only used if set
+    #[serde(default)]
+    lirc: Option<Cow<'a, LircConfig>>,
+    #[serde(default)]
+    send_to_device: Option<Cow<'a, SendSettings>>,
+    #[serde(default)]
+    rip_cd: Option<Cow<'a, RipSettings>>,
+}
+
+impl ConfigData<'_> {
+    /// Owned copy for serializing on another thread, the queues stay shared
+    fn into_owned(self) -> ConfigData<'static> {
+        fn own<B: ToOwned + ?Sized + 'static>(v: Cow<'_, B>) -> Cow<'static, B> {
+            Cow::Owned(v.into_owned())
+        }
+        ConfigData {
+            playlists: self.playlists,
+            favorites: self.favorites,
+            favorite_lists: self.favorite_lists,
+            notes: self.notes,
+            tags: own(self.tags),
+            track_tags: self.track_tags,
+            library_folders: own(self.library_folders),
+            alarms: own(self.alarms),
+            quiet_hours: self.quiet_hours,
+            content_filter: own(self.content_filter),
+            playlist_folders: own(self.playlist_folders),
+            pins: own(self.pins),
+            queue_played: own(self.queue_played),
+            device_volumes: own(self.device_volumes),
+            snapshots: own(self.snapshots),
+            volume: self.volume,
+            path: self.path,
+            current_playlist: own(self.current_playlist),
+            filter: own(self.filter),
+            routing: own(self.routing),
+            preamp: self.preamp,
+            eq_presets: own(self.eq_presets),
+            playlist_settings: own(self.playlist_settings),
+            dsp_chain: own(self.dsp_chain),
+            hooks: own(self.hooks),
+            ducking: own(self.ducking),
+            osd: self.osd,
+            ui_scale: self.ui_scale,
+            touch_mode: self.touch_mode,
+            language: self.language,
+            theme: self.theme,
+            time_display: self.time_display,
+            cue_format: self.cue_format,
+            playlist_charset: self.playlist_charset,
+            title_info: self.title_info,
+            keep_broken: self.keep_broken,
+            track_identity: self.track_identity,
+            endless: self.endless,
+            queue_end: self.queue_end,
+            broken: self.broken,
+            progress_interval: self.progress_interval,
+            background_interval: self.background_interval,
+            scan_length: self.scan_length,
+            wheel_volume_step: self.wheel_volume_step,
+            wheel_seek_step: self.wheel_seek_step,
+            cover_cache_size: self.cover_cache_size,
+            text_scale: self.text_scale,
+            now_playing: self.now_playing.map(own),
+            disabled_plugins: own(self.disabled_plugins),
+            subsonic: self.subsonic.map(own),
+            jellyfin: self.jellyfin.map(own),
+            webdav: self.webdav.map(own),
+            sync_folder: self.sync_folder,
+            favorites_export: self.favorites_export,
+            portable_folder: self.portable_folder,
+            portable_copy: self.portable_copy,
+            portable_profile: self.portable_profile,
+            device_id: self.device_id,
+            last_sync: self.last_sync,
+            grpc_listen: self.grpc_listen,
+            grpc_token: self.grpc_token,
+            voice_listen: self.voice_listen,
+            mqtt: self.mqtt.map(own),
+            lastfm: self.lastfm.map(own),
+            gamepad: self.gamepad,
+            gamepad_mapping: self.gamepad_mapping.map(own),
+            lirc: self.lirc.map(own),
+            send_to_device: self.send_to_device.map(own),
+            rip_cd: self.rip_cd.map(own),
+        }
+    }
+
+    /// Apply a journaled change, entries hold the resulting state so replaying twice is harmless
+    fn apply(&mut self, change: Change) {
+        match change {
+            Change::Favorite {
+                list,
+                track,
+                favorite,
+            } => {
+                let list = Arc::make_mut(&mut self.favorite_lists)
+                    .entry(list)
+                    .or_default();
+                match favorite {
+                    true => {
+                        list.insert(track);
+                    }
+                    false => {
+                        let key = pathutil::key(&track);
+                        list.retain(|t| pathutil::key(t) != key);
+                    }
+                }
+            }
+            Change::Note { track, note } => {
+                match note.is_empty() {
+                    true => Arc::make_mut(&mut self.notes).remove(&track),
+                    false => Arc::make_mut(&mut self.notes).insert(track, note),
+                };
+            }
+            Change::Tag { track, tag, set } => {
+                let track_tags = Arc::make_mut(&mut self.track_tags);
+                let tags = track_tags.entry(track.clone()).or_default();
+                match set {
+                    true => tags.insert(tag),
+                    false => tags.remove(&tag),
+                };
+                if tags.is_empty() {
+                    track_tags.remove(&track);
+                }
+            }
+            Change::Moved { from, to } => {
+                for list in Arc::make_mut(&mut self.favorite_lists).values_mut() {
+                    if list.remove(&from) {
+                        list.insert(to.clone());
+                    }
+                }
+                let notes = Arc::make_mut(&mut self.notes);
+                if let Some(note) = notes.remove(&from) {
+                    notes.insert(to.clone(), note);
+                }
+                let track_tags = Arc::make_mut(&mut self.track_tags);
+                if let Some(tags) = track_tags.remove(&from) {
+                    track_tags.insert(to, tags);
+                }
+            }
+        }
+    }
+
+    /// Queue end, configs of older versions only had endless play
+    fn queue_end(&self) -> QueueEnd {
+        self.queue_end.unwrap_or(match self.endless {
+            Some(strategy) => QueueEnd::Fill(strategy),
+            None => QueueEnd::Stop,
+        })
+    }
+
+    /// Move favorites of older versions into the default list, they aren't serialized anymore
+    fn migrate_favorites(&mut self) {
+        if !self.favorites.is_empty() {
+            let legacy = std::mem::take(&mut self.favorites);
+            Arc::make_mut(&mut self.favorite_lists)
+                .entry(favorites::DEFAULT_LIST.to_string())
+                .or_default()
+                .extend(legacy);
+        }
+    }
+}
+
+/// Settings applied whenever a playlist becomes active
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+struct PlaylistSettings {
+    /// Name of the equalizer preset
+    #[serde(default)]
+    eq: Option<String>,
+    /// Added to the volume while the playlist is active
+    #[serde(default)]
+    volume_offset: i16,
+}
+
+/// Saved queues and filters, restorable by name
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+struct Snapshot {
+    playlists: Arc<Playlists>,
+    path: PathBuf,
+    #[serde(default)]
+    filter: String,
+    #[serde(default)]
+    tag_filter: Option<String>,
+    /// Position in the current track
+    #[serde(default)]
+    position: Option<Duration>,
+}
+
+/// Presets available if none are configured
+fn default_eq_presets() -> HashMap<String, EqPreset> {
+    let mut presets = HashMap::new();
+    presets.insert(String::from("Flat"), EqPreset::default());
+    presets.insert(
+        String::from("Spoken Word"),
+        EqPreset {
+            gains: [-6, -2, 2, 3, -2],
+        },
+    );
+    presets.insert(
+        String::from("Music"),
+        EqPreset {
+            gains: [3, 0, 0, 1, 2],
+        },
+    );
+    presets
 }
 
 struct PlaybackControl {
     path: PathBuf,
     play_next: button::State,
+    play_previous: button::State,
+    restart: button::State,
+    /// Tracks played this session, the last one most recently
+    previous: Vec<String>,
+    /// Tracks enqueued this session from the library, favorites or dropped files.
+    /// Played before the playlist continues, never saved to a playlist queue.
+    up_next: Vec<String>,
+    /// The current track is the first of `up_next` instead of the playlist head
+    from_up_next: bool,
+    time_toggle: button::State,
+    time_display: TimeDisplay,
     is_paused: bool,
     pause: button::State,
     favorite: button::State,
     export_favorites: button::State,
     trash_current: button::State,
-    data_favorites: HashSet<String>,
+    stop: button::State,
+    reveal_current: button::State,
+    copy_current: button::State,
+    favorites: Favorites,
+    /// Current track is in any favorite list
     is_favorite: bool,
+    /// Favorite lists shown for adding or removing the current track
+    favorite_picker: bool,
+    new_list_name: String,
+    new_list_input: text_input::State,
+    new_list_add: button::State,
+    /// Notes by track
+    notes: Arc<HashMap<String, String>>,
+    note_input: text_input::State,
+    export_notes: button::State,
+    tags: Vec<Tag>,
+    track_tags: Arc<HashMap<String, BTreeSet<String>>>,
+    library_folders: Vec<PathBuf>,
+    /// Library folders were scanned this session
+    library_scanned: bool,
+    alarms: Vec<Alarm>,
+    quiet_hours: Option<QuietHours>,
+    /// Quiet hours are active, capping the output volume
+    quiet: bool,
+    content_filter: ContentFilter,
+    device_volumes: HashMap<String, u8>,
+    playlist_folders: PlaylistFolders,
+    sidebar: Sidebar,
+    /// Quick access bar on top of the window
+    pins: Vec<Pin>,
+    /// Tracks taken from the front of each playlist's queue, for the position in it
+    queue_played: HashMap<PathBuf, usize>,
+    pin_bar: PinBar,
+    /// Pin per favorite list in the picker
+    favorite_pins: Vec<button::State>,
+    /// Device the main output plays on, reported by the player
+    output_device: Option<String>,
+    /// Compiled content filter, None if turned off
+    content_matcher: Option<Matcher>,
+    /// Entered to turn the content filter off or change its password
+    filter_password: String,
+    filter_password_input: text_input::State,
+    /// The entered password was wrong
+    filter_locked: bool,
+    set_filter_password: button::State,
+    /// Local time alarms were last checked at
+    alarms_checked: NaiveDateTime,
+    /// Alarm currently ringing
+    ringing: Option<Ringing>,
+    /// Snoozed alarm and when it rings again
+    snoozed: Option<(usize, Instant)>,
+    snooze_button: button::State,
+    dismiss_button: button::State,
+    /// Only show queue entries with this tag
+    tag_filter: Option<String>,
+    tag_pick: pick_list::State<String>,
+    export_tags: button::State,
+    export_scrobbles: button::State,
+    scrobble_format: ScrobbleFormat,
+    /// Outcome of the last play history export
+    scrobble_result: Option<String>,
+    scrobble_format_pick: pick_list::State<ScrobbleFormat>,
     volume_input: slider::State,
     volume: u8,
+    preamp_input: slider::State,
+    ui_scale: u16,
+    ui_scale_input: slider::State,
+    touch_mode: TouchMode,
+    touch_pick: pick_list::State<TouchMode>,
+    /// Touch layout in use, detected once for auto mode
+    touch: bool,
+    /// Swipes starting on the current track
+    swipe_track: SwipeTracker,
+    /// Swipes starting on the queue
+    swipe_queue: SwipeTracker,
+    language: Language,
+    language_pick: pick_list::State<Language>,
+    theme: Theme,
+    theme_pick: pick_list::State<Theme>,
+    text_scale: u16,
+    text_scale_input: slider::State,
+    preamp: i16,
+    eq_presets: HashMap<String, EqPreset>,
+    /// Kept to be written back to the config
+    dsp_chain: Vec<DspStage>,
+    hooks: Hooks,
+    ducking: Ducking,
+    /// None if ducking is off
+    duck_monitor: Option<ducking::Monitor>,
+    /// Paused or lowered volume due to other applications
+    ducked: bool,
+    osd: bool,
+    /// Currently shown overlay process
+    osd_child: Option<Child>,
+    /// Overlay of the current track waits for its cover
+    osd_pending: bool,
+    /// Queue popped out into its own window
+    queue_window: Option<Child>,
+    pop_out_queue: button::State,
+    now_playing: Option<NowPlayingExport>,
+    plugins: Plugins,
+    /// Kept to be written back to the config
+    subsonic: Option<SubsonicConfig>,
+    jellyfin: Option<JellyfinConfig>,
+    webdav: Option<WebDavConfig>,
+    /// Credentials of the remote libraries, for fetching remote playlists
+    credentials: remote::Credentials,
+    sync_folder: Option<PathBuf>,
+    favorites_export: Option<PathBuf>,
+    device_id: u64,
+    last_sync: u64,
+    /// Newer archive synced by another device, offered for import
+    sync_notice: Option<Archive>,
+    sync_import: button::State,
+    sync_dismiss: button::State,
+    /// Config file was overwritten by an import, the current state must not be saved anymore
+    config_replaced: bool,
+    /// Changes since the last full save, None if it couldn't be opened
+    journal: Option<Journal>,
+    /// Ratings, play counts and resume positions, None if the database couldn't be opened
+    store: Option<Store>,
+    /// Rating and play count of the current track
+    rating: Option<u8>,
+    play_count: u64,
+    rating_pick: pick_list::State<Rating>,
+    /// Cue points of the current track
+    cues: Vec<Cue>,
+    /// Jump to and remove per cue
+    cue_buttons: Vec<(button::State, button::State)>,
+    add_cue: button::State,
+    /// Chapters embedded in the current track
+    chapters: Vec<Cue>,
+    chapter_buttons: Vec<button::State>,
+    cue_format: CueFormat,
+    cue_format_pick: pick_list::State<CueFormat>,
+    playlist_charset: Option<Charset>,
+    charset_pick: pick_list::State<PlaylistCharset>,
+    export_cues: button::State,
+    /// Written sidecar file or export error
+    cue_result: Option<String>,
+    /// Skipped intro of the current track, its own or the one of its album
+    intro: Option<Duration>,
+    set_intro: button::State,
+    set_album_intro: button::State,
+    clear_intro: button::State,
+    archive_path: String,
+    archive_input: text_input::State,
+    export_button: button::State,
+    import_button: button::State,
+    import_itunes_button: button::State,
+    archive_result: Option<String>,
+    /// Base folder of portable queue exports
+    portable_folder: String,
+    portable_input: text_input::State,
+    portable_copy: bool,
+    portable_profile: portable::Profile,
+    portable_profile_pick: pick_list::State<portable::Profile>,
+    export_portable: button::State,
+    /// Exported track counts or export error
+    portable_result: Option<String>,
+    snapshots: HashMap<String, Snapshot>,
+    snapshot_name: String,
+    snapshot_input: text_input::State,
+    save_snapshot: button::State,
+    snapshot_pick: pick_list::State<String>,
+    snapshot_selected: Option<String>,
+    restore_snapshot: button::State,
+    delete_snapshot: button::State,
+    /// Playlist files compared, favorites if B is empty
+    diff_a: String,
+    diff_b: String,
+    diff_a_input: text_input::State,
+    diff_b_input: text_input::State,
+    diff_compare: button::State,
+    /// Tracks of A and the comparison result
+    diff: Option<(Vec<String>, playlist::Diff)>,
+    diff_export_a: button::State,
+    diff_export_b: button::State,
+    diff_merge: button::State,
+    diff_result: Option<String>,
+    plugin_buttons: Vec<button::State>,
+    browser: Option<Browser>,
+    /// Album grid, None if closed
+    albums: Option<AlbumGrid>,
+    open_albums: button::State,
+    /// Artist page, None if closed
+    artist_page: Option<ArtistPage>,
+    artist_button: button::State,
+    /// Library search, None if closed
+    search: Option<LibrarySearch>,
+    open_search: button::State,
+    /// Mixes of the day, None if closed
+    home: Option<HomeView>,
+    open_home: button::State,
+    /// Recently added albums, None if closed
+    recent: Option<RecentView>,
+    open_recent: button::State,
+    focus: Focus,
+    playlist_settings: HashMap<PathBuf, PlaylistSettings>,
+    eq_pick: pick_list::State<String>,
+    volume_offset_input: slider::State,
+    /// Last time the preamp clipped
+    clipped_at: Option<Instant>,
     length: Option<Duration>,
+    /// New track started, its length is reported with the next progress
+    track_changed: bool,
     playtime: Option<Duration>,
-    total_playtime: Option<Duration>,
+    seek_input: slider::State,
+    /// Position in seconds while dragging the seek bar
+    seek_preview: Option<u32>,
+    goto_input: text_input::State,
+    /// Entered mm:ss timestamp to seek to
+    goto_text: String,
+    /// The entered timestamp isn't valid for the current track
+    goto_invalid: bool,
+    /// Remaining length of the queue, whether all track lengths are known
+    total_playtime: (Duration, bool),
+    durations: durations::Durations,
+    /// Tags of queued tracks, filled in batches by the extractor
+    metadata: HashMap<String, TrackInfo>,
+    /// None if its workers couldn't be started
+    extractor: Option<metadata::Extractor>,
+    /// Show track and time in the window title
+    title_info: bool,
+    keep_broken: bool,
+    /// Hashing is started with the deferred init if set
+    track_identity: bool,
+    queue_end: QueueEnd,
+    queue_end_pick: pick_list::State<QueueEnd>,
+    /// Time to quit at after the queue ran out
+    quit_at: Option<Instant>,
+    cancel_quit: button::State,
+    /// Only an excerpt of each track is played, None plays them completely
+    scan: Option<ScanFrom>,
+    scan_pick: pick_list::State<Scan>,
+    scan_length: Option<u64>,
+    /// Where the excerpt of the current track started, None until it is positioned
+    scan_start: Option<Duration>,
+    /// Background hashing of queued tracks, None if track identity is off
+    hasher: Option<identity::Hasher>,
+    /// Queued tracks that couldn't be played, skipped until retried
+    broken: Arc<HashSet<String>>,
+    /// Kept to be written back to the config
+    progress_interval: Option<u64>,
+    background_interval: Option<u64>,
+    wheel_volume_step: Option<u8>,
+    wheel_seek_step: Option<u64>,
+    /// Partial mouse wheel steps of touchpads, horizontal and vertical
+    wheel_scrolled: (f32, f32),
+    /// Shift is held, turning vertical scrolling into seeking and extending selections
+    shift: bool,
+    /// Ctrl or Cmd is held, adding to selections
+    command: bool,
+    cover_cache_size: Option<u64>,
+    /// Cover lookup, None if the cache couldn't be opened
+    covers: Option<covers::Loader>,
+    /// Cover of the current track
+    cover: Option<image::Handle>,
+    /// Progress interval last sent to the player
+    sent_interval: Option<Duration>,
+    /// Window is minimized, only reported as zero size resize on some platforms
+    minimized: bool,
     tx: Sender<PlayerCommand>,
     rx: Receiver<PlayerStatus>,
     current_playlist: String,
+    /// Generation of the last track sent to the player, statuses of older ones are stale
+    generation: u64,
+    /// Play was sent for the first playlist entry, but the player didn't confirm it yet
+    starting: bool,
     /// Displayed current file,
     /// also used by play_next to remove the current file from the playlist, if this is not empty
     current_file: String,
-    playlists: HashMap<PathBuf, Vec<String>>,
+    /// Playlists with their remaining tracks, in playback order.
+    /// Manual reordering and "play next" insertions are applied directly to this.
+    playlists: Arc<Playlists>,
+    filter: String,
+    filter_input: text_input::State,
+    queue_up: Vec<button::State>,
+    clear_queue: button::State,
+    /// Tracks skipped this session because they couldn't be played, with the reason
+    skipped: Vec<(String, String)>,
+    show_skipped: bool,
+    skipped_toggle: button::State,
+    /// Folder searched for moved tracks
+    relocate_root: String,
+    relocate_input: text_input::State,
+    relocate_button: button::State,
+    relocate_result: Option<String>,
+    /// Confirm and cancel buttons, shown while asking to clear the queue
+    clear_confirm: Option<(button::State, button::State)>,
+    /// Reveal and copy path buttons of queue entries
+    /// Reveal, copy and select per shown queue entry
+    queue_actions: Vec<(button::State, button::State, button::State)>,
+    /// Selected queue positions
+    queue_selection: Selection,
+    bulk_bar: BulkBar,
+    undo: Option<Undo>,
+    routing: AudioRouting,
+    zone_volume_input: Vec<slider::State>,
+    /// Source rate, output rate, channels of the current track
+    format: Option<(u32, u32, u16)>,
+    levels: Levels,
+    /// Last output device error
+    output_error: Option<String>,
+    /// Passwords that couldn't be read from or moved into the secret store
+    secret_errors: Vec<String>,
+    /// Output underruns since start, None if the output doesn't count them
+    underruns: Option<u64>,
+    /// Audio formats and whether they can be played here, empty until probed
+    formats: Vec<(&'static str, bool)>,
+    buffer_pick: pick_list::State<BufferFrames>,
+    /// Buffer size the output was opened with, changes apply after a restart
+    active_buffer_frames: Option<u32>,
+    /// OS media session, None if unavailable
+    media_session: Option<MediaSession>,
+    grpc_listen: Option<SocketAddr>,
+    grpc_token: Option<String>,
+    voice_listen: Option<SocketAddr>,
+    mqtt: Option<MqttConfig>,
+    lastfm: Option<LastFmConfig>,
+    /// None if gamepad control is off
+    gamepad: Option<Gamepad>,
+    gamepad_mapping: GamepadMapping,
+    gamepad_settings: GamepadSettings,
+    lirc_config: Option<LircConfig>,
+    lirc: Option<Lirc>,
+    send: SendPanel,
+    rip: RipPanel,
+    /// Radio refilling the queue with similar tracks, None if not started
+    radio: Option<Radio>,
+    radio_button: button::State,
+    /// Remote control, None without any transport configured
+    control: Option<Control>,
     child: JoinHandle<()>,
 }
 
 impl PlaybackControl {
     fn play_next(&mut self) {
         let mut remove = false;
-        if let Some(v) = self.playlists.get_mut(&self.path) {
+        self.scan_start = None;
+        // positions move with the queue
+        self.queue_selection.clear();
+        if let Some(undo) = self.undo.as_mut() {
+            undo.queues.remove(&self.path);
+        }
+        let head_started = self.head_started();
+        if head_started {
+            self.remember_position();
+        }
+        let playlist_started = self.playlist_started();
+        if head_started && self.from_up_next && !self.up_next.is_empty() {
+            let removed = self.up_next.remove(0);
+            trace!("Removing {} from the session queue", removed);
+            self.push_previous(removed);
+        }
+        self.from_up_next = false;
+        // tracks enqueued in this session play before the playlist continues
+        if !self.up_next.is_empty() {
+            if playlist_started {
+                if let Some(v) = Arc::make_mut(&mut self.playlists).get_mut(&self.path) {
+                    let broken = &self.broken;
+                    if let Some(removed) = queue::pop_head(v, |t| broken.contains(t)) {
+                        match v.is_empty() {
+                            true => self.queue_played.remove(&self.path),
+                            false => {
+                                *self.queue_played.entry(self.path.clone()).or_default() += 1;
+                                None
+                            }
+                        };
+                        self.push_previous(removed);
+                    }
+                }
+            }
+            let broken = &self.broken;
+            if queue::promote(&mut self.up_next, |t| !broken.contains(t)) {
+                self.from_up_next = true;
+                self.start_track(self.up_next[0].clone());
+                return;
+            }
+            info!("No playable track in the session queue");
+            self.up_next.clear();
+        }
+        let mut next = None;
+        let playlists = Arc::make_mut(&mut self.playlists);
+        if self.queue_end.fill().is_some() || self.radio.is_some() {
+            playlists.entry(self.path.clone()).or_default();
+        }
+        if let Some(v) = playlists.get_mut(&self.path) {
             if !v.is_empty() {
-                if !self.current_file.is_empty() {
-                    let removed = v.remove(0);
-                    trace!("Removing {}", removed);
+                if playlist_started {
+                    let broken = &self.broken;
+                    if let Some(removed) = queue::pop_head(v, |t| broken.contains(t)) {
+                        trace!("Removing {}", removed);
+                        if v.is_empty() {
+                            // done, the next fill starts over
+                            self.queue_played.remove(&self.path);
+                        } else {
+                            *self.queue_played.entry(self.path.clone()).or_default() += 1;
+                        }
+                        if self.previous.len() >= PREVIOUS_TRACKS {
+                            self.previous.remove(0);
+                        }
+                        self.previous.push(removed);
+                    }
                 }
+                self.starting = false;
+            }
+            let broken = &self.broken;
+            let blocked = content_blocker(
+                self.content_matcher.as_ref(),
+                &self.metadata,
+                &self.track_tags,
+            );
+            let playable = |t: &str| !broken.contains(t) && !blocked(t);
+            if let Some(radio) = self.radio.as_mut().filter(|_| v.is_empty()) {
+                v.extend(radio.next_tracks(&self.metadata, playable, ENDLESS_TRACKS));
+                debug!("Radio added {} tracks", v.len());
+            }
+            if v.is_empty() {
+                let source = QueueSource {
+                    path: &self.path,
+                    charset: self.playlist_charset,
+                    metadata: &self.metadata,
+                    store: self.store.as_ref(),
+                };
+                let last = Some(self.current_file.as_str()).filter(|v| !v.is_empty());
+                self.queue_end
+                    .refill(v, &source, playable, last, head_started);
             }
+            let filter = self.filter.as_str();
             if !v.is_empty() {
-                self.tx
-                    .send(PlayerCommand::Play(v[0].clone(), self.volume))
-                    .expect("Can't send playback command!");
-                self.current_playlist = self.path.to_string_lossy().into_owned();
+                // move the first playable track to the front, keeping the order of all others
+                let broken = &self.broken;
+                let blocked = content_blocker(
+                    self.content_matcher.as_ref(),
+                    &self.metadata,
+                    &self.track_tags,
+                );
+                let playable =
+                    |t: &String| !broken.contains(t) && t.contains(filter) && !blocked(t);
+                if !queue::promote(v, playable) {
+                    info!("No playable track matching filter {:?}", filter);
+                    return;
+                }
+            }
+            if !v.is_empty() {
+                next = Some(v[0].clone());
             } else {
                 remove = true;
+                if head_started && self.queue_end == QueueEnd::Quit {
+                    info!("Queue ended, quitting in {:?}", QUIT_COUNTDOWN);
+                    self.quit_at = Some(Instant::now() + QUIT_COUNTDOWN);
+                }
             }
         }
+        if let Some(track) = next {
+            self.start_track(track);
+        }
         if remove {
-            debug!("Removing playlist");
-            self.playlists.remove(&self.path);
+            match is_playlist_file(&self.path) {
+                // kept to be played again from the sidebar
+                true => debug!("Playlist {:?} complete", self.path),
+                false => {
+                    debug!("Removing queue");
+                    Arc::make_mut(&mut self.playlists).remove(&self.path);
+                }
+            }
         }
     }
 
-    fn store_state(&self) {
-        let data = ConfigData {
-            playlists: Cow::Borrowed(&self.playlists),
-            volume: self.volume,
-            current_playlist: Cow::Borrowed(&self.current_playlist),
-            path: self.path.clone(),
-            favorites: Cow::Borrowed(&self.data_favorites),
-        };
-        match serde_json::to_string(&data) {
-            Err(e) => warn!("Can't serialize data! {}", e),
-            Ok(v) => {
-                thread::spawn(move || {
-                    let file = config_path(true);
-                    match File::create(&file) {
-                        Err(e) => warn!("Can't create config file {:?}: {}", file, e),
-                        Ok(mut file) => match file.write_all(v.as_bytes()) {
-                            Err(e) => warn!("Error writing config {}", e),
-                            Ok(_) => match std::fs::rename(config_path(true), config_path(false)) {
-                                Ok(_) => info!("Config saved"),
-                                Err(e) => error!("Can't move file over backup: {}", e),
-                            },
-                        },
-                    }
-                });
-            }
+    /// Send `track` to the player, with the equalizer of the current playlist
+    fn start_track(&mut self, track: String) {
+        let eq = self
+            .playlist_settings
+            .get(&self.path)
+            .and_then(|s| s.eq.as_ref())
+            .and_then(|name| self.eq_presets.get(name))
+            .copied();
+        self.tx
+            .send(PlayerCommand::Equalizer(eq))
+            .expect("Can't send playback command!");
+        self.generation += 1;
+        self.starting = true;
+        self.tx
+            .send(PlayerCommand::Play(
+                track,
+                self.output_volume(),
+                self.generation,
+            ))
+            .expect("Can't send playback command!");
+        self.current_playlist = self.path.to_string_lossy().into_owned();
+        self.quit_at = None;
+    }
+
+    /// Remember a played track for going back
+    fn push_previous(&mut self, track: String) {
+        if self.previous.len() >= PREVIOUS_TRACKS {
+            self.previous.remove(0);
         }
+        self.previous.push(track);
     }
 
-    /// Handle time tick for updating UI from player state updates
-    fn handle_tick(&mut self) {
-        if let Ok(msg) = self.rx.try_recv() {
-            if log_enabled!(log::Level::Trace) {
-                match msg {
-                    PlayerStatus::Playtime(_) => (),
-                    _ => trace!("Player state: {:?}", msg),
-                }
+    /// Manual volume change, stops the fade in of a ringing alarm
+    fn set_volume(&mut self, volume: u8) {
+        self.volume = volume;
+        if let Some(ringing) = self.ringing.as_mut() {
+            ringing.fading = false;
+        }
+        self.tx
+            .send(PlayerCommand::Volume(self.output_volume()))
+            .expect("Can't send playback command!");
+    }
+
+    /// Change the volume on vertical and seek on horizontal or shift scrolling
+    fn wheel_scrolled(&mut self, delta: ScrollDelta) {
+        let (mut x, mut y) = wheel_steps(delta, &mut self.wheel_scrolled);
+        if self.shift {
+            x += y;
+            y = 0;
+        }
+        if y != 0 {
+            let step = self.wheel_volume_step.unwrap_or(WHEEL_VOLUME_STEP) as i32;
+            self.set_volume((self.volume as i32 + y * step).clamp(0, 100) as u8);
+        }
+        let (playtime, length) = match (self.playtime, self.length) {
+            (Some(playtime), Some(length)) if x != 0 && !self.current_file.is_empty() => {
+                (playtime, length)
             }
-            match msg {
-                PlayerStatus::Playing(f, length) => {
-                    self.current_file = f;
-                    self.is_paused = false;
-                    self.is_favorite = self.data_favorites.contains(&self.current_file);
-                    debug!("Length {:?}", length);
-                    self.length = length;
-                }
-                PlayerStatus::Ended => {
-                    debug!("Playback ended");
-                    self.play_next();
-                    self.current_file = String::new();
-                }
-                PlayerStatus::Paused => {
-                    self.is_paused = true;
-                }
-                PlayerStatus::Playtime(time) => {
-                    self.playtime = time;
-                }
-                PlayerStatus::InvalidFile(f) => {
-                    dbg!(&f);
-                    // set as file, so play_next removes it
-                    self.current_file = f;
-                    self.play_next();
-                    self.current_file = String::new();
+            _ => return,
+        };
+        let step = self
+            .wheel_seek_step
+            .map_or(WHEEL_SEEK_STEP, Duration::from_secs);
+        let target = match x > 0 {
+            true => (playtime + step * x as u32).min(length),
+            false => playtime.saturating_sub(step * x.unsigned_abs()),
+        };
+        self.playtime = Some(target);
+        self.tx
+            .send(PlayerCommand::Seek(target))
+            .expect("Can't send playback command!");
+    }
+
+    /// Position the excerpt of the current track in scan mode and advance once it played
+    fn check_scan(&mut self) {
+        let (from, playtime) = match (self.scan, self.playtime) {
+            // progress of the previous track may still arrive while starting
+            (Some(from), Some(playtime)) if !self.current_file.is_empty() && !self.starting => {
+                (from, playtime)
+            }
+            _ => return,
+        };
+        let length = self.scan_length.map_or(SCAN_LENGTH, Duration::from_secs);
+        let start = match self.scan_start {
+            Some(v) => v,
+            None => {
+                // streams without a known length are scanned from where they start
+                let start = match (from, self.length) {
+                    (ScanFrom::Middle, Some(total)) => total.saturating_sub(length) / 2,
+                    _ => playtime,
+                };
+                if start > playtime {
+                    self.playtime = Some(start);
+                    self.tx
+                        .send(PlayerCommand::Seek(start))
+                        .expect("Can't send playback command!");
                 }
+                self.scan_start = Some(start);
+                return;
             }
+        };
+        if playtime >= start + length {
+            debug!("Scanned {}", self.current_file);
+            self.play_next();
         }
     }
 
-    fn file_dropped(&mut self, file: PathBuf) {
-        match std::fs::read_to_string(&file) {
-            Ok(data) => match playlist_decoder::decode(&data) {
-                Ok(mut playlist) => {
-                    if let Some(v) = self.playlists.get_mut(&file) {
-                        if v.is_empty() {
-                            v.append(&mut playlist);
-                        }
-                    } else {
-                        playlist.shuffle(&mut thread_rng());
-                        self.playlists.insert(file.clone(), playlist);
-                    }
-                    self.path = file;
-                    // reset current_file to not remove this file from playback
-                    self.current_file = String::new();
-                    self.play_next();
-                }
-                Err(e) => error!("{}", e),
-            },
-            Err(e) => warn!("Can't open dropped file {}", e),
+    /// Leave scan mode, continuing the current track normally
+    fn end_scan(&mut self) {
+        if self.scan.take().is_some() {
+            info!("Scan mode ended");
         }
+        self.scan_start = None;
     }
 
-    fn trash_file(&mut self) {
-        if !self.current_file.is_empty() {
-            match trash::delete(&self.current_file) {
-                Ok(_) => info!("Trashed {}", self.current_file),
-                Err(e) => error!("Can't trash file {}: {}", self.current_file, e),
+    /// Play the current track from the start
+    fn restart_track(&mut self) {
+        if !self.head_started() {
+            return;
+        }
+        self.playtime = Some(Duration::ZERO);
+        self.tx
+            .send(PlayerCommand::Seek(Duration::ZERO))
+            .expect("Can't send playback command!");
+    }
+
+    /// Restart the current track, or go back to the previously played one near its start
+    fn play_previous(&mut self) {
+        if self.playtime.is_some_and(|v| v > RESTART_AFTER) {
+            self.restart_track();
+            return;
+        }
+        let track = match self.previous.pop() {
+            Some(v) => v,
+            None => {
+                self.restart_track();
+                return;
             }
+        };
+        // the current track stays in front of the queue
+        self.stop();
+        if !self.up_next.is_empty() {
+            // would be played first otherwise
+            self.up_next.insert(0, track);
+            self.play_next();
+            return;
         }
+        let queue = Arc::make_mut(&mut self.playlists)
+            .entry(self.path.clone())
+            .or_default();
+        queue::insert_next(queue, false, vec![track]);
+        if let Some(played) = self.queue_played.get_mut(&self.path) {
+            *played = played.saturating_sub(1);
+        }
+        self.play_next();
     }
-}
 
-#[derive(Debug, Clone)]
-pub enum Message {
-    PlayNext,
-    Pause,
-    SliderChanged(u8),
-    Window(iced_native::Event),
-    Tick,
-    ToggleFavorite,
-    ExportFavorites,
-    SaveConfig,
-    TrashFile,
-}
+    /// Start due alarms and fade in the volume of a ringing one
+    fn check_alarms(&mut self) {
+        let now = alarm::local_now();
+        let checked = std::mem::replace(&mut self.alarms_checked, now);
+        let due = self
+            .alarms
+            .iter()
+            .position(|a| a.rings_between(checked, now))
+            .or_else(|| {
+                self.snoozed
+                    .filter(|(_, at)| Instant::now() >= *at)
+                    .map(|(i, _)| i)
+            });
+        if let Some(index) = due {
+            self.start_alarm(index);
+        }
+        let volume = match self.ringing {
+            Some(ref ringing) if ringing.fading => match self.alarms.get(ringing.alarm) {
+                Some(alarm) => alarm.volume_at(ringing.started.elapsed()),
+                None => return,
+            },
+            _ => return,
+        };
+        if volume != self.volume {
+            self.volume = volume;
+            self.tx
+                .send(PlayerCommand::Volume(self.output_volume()))
+                .expect("Can't send playback command!");
+        }
+    }
 
-/// Config file, temp specifies if a .bak version should be used
-fn config_path(temp: bool) -> PathBuf {
-    let mut file = data_local_dir().unwrap();
-    match temp {
-        true => file.push("audio_wrench.json.bak"),
-        false => file.push("audio_wrench.json"),
+    /// Apply the volume cap when quiet hours start or end
+    fn check_quiet_hours(&mut self) {
+        let quiet = self
+            .quiet_hours
+            .as_ref()
+            .is_some_and(|q| q.active(alarm::local_now().time()));
+        if quiet != self.quiet {
+            info!("Quiet hours {}", if quiet { "started" } else { "ended" });
+            self.quiet = quiet;
+            self.tx
+                .send(PlayerCommand::Volume(self.output_volume()))
+                .expect("Can't send playback command!");
+        }
     }
-    file
-}
 
-impl Default for PlaybackControl {
-    fn default() -> Self {
-        let file = config_path(false);
-        let data: ConfigData = if file.is_file() {
-            match std::fs::read_to_string(&file)
-                .map_err(Report::from)
-                .and_then(|v| serde_json::from_str(&v).map_err(Report::from))
-            {
-                Ok(v) => v,
-                Err(e) => {
-                    error!("Unable to read config at {:?}: {}", file, e);
-                    Default::default()
+    fn start_alarm(&mut self, index: usize) {
+        let playlist = match self.alarms.get(index) {
+            Some(v) => v.playlist.clone(),
+            None => return,
+        };
+        info!("Alarm ringing, starting {:?}", playlist);
+        self.snoozed = None;
+        self.volume = 0;
+        self.tx
+            .send(PlayerCommand::Volume(self.output_volume()))
+            .expect("Can't send playback command!");
+        self.file_dropped(playlist);
+        self.ringing = Some(Ringing {
+            alarm: index,
+            started: Instant::now(),
+            fading: true,
+        });
+    }
+
+    /// Store where a long current track was left, to continue there when it plays again
+    fn remember_position(&self) {
+        let (store, position) = match (self.store.as_ref(), self.playtime) {
+            (Some(store), Some(position)) if !self.current_file.is_empty() => (store, position),
+            _ => return,
+        };
+        if self.length.is_none_or(|v| v < RESUME_MIN_LENGTH) || position < RESUME_MIN_POSITION {
+            return;
+        }
+        if let Err(e) = store.set_resume_position(&self.current_file, Some(position)) {
+            error!("Can't store resume position: {}", e);
+        }
+    }
+
+    /// Load rating, play count and cues of a newly started `track` and continue where it was
+    /// left or after its intro
+    fn load_track_stats(&mut self, track: &str) {
+        let store = match self.store.as_ref() {
+            Some(v) => v,
+            None => return,
+        };
+        let album = self.metadata.get(track).and_then(|v| v.album.as_deref());
+        let stats = store.rating(track).and_then(|rating| {
+            Ok((
+                rating,
+                store.play_count(track)?,
+                store.resume_position(track)?,
+                store.cues(track)?,
+                store.intro(track, album)?,
+            ))
+        });
+        self.cue_result = None;
+        match stats {
+            Ok((rating, play_count, resume, cues, intro)) => {
+                self.rating = rating;
+                self.play_count = play_count;
+                self.set_cues(cues);
+                self.intro = intro;
+                let start = match (resume, intro) {
+                    (Some(resume), Some(intro)) => Some(resume.max(intro)),
+                    (resume, intro) => resume.or(intro),
+                };
+                if let Some(position) = start {
+                    debug!("Starting {} at {:?}", track, position);
+                    self.playtime = Some(position);
+                    self.tx
+                        .send(PlayerCommand::Seek(position))
+                        .expect("Can't send playback command!");
                 }
             }
-        } else {
-            Default::default()
+            Err(e) => error!("Can't load stats of {}: {}", track, e),
+        }
+    }
+
+    /// Read the chapters of Ogg files, other formats have none yet
+    fn load_chapters(&mut self, track: &str) {
+        let path = Path::new(track);
+        self.chapters = match ogg::is_ogg(path) {
+            true => ogg::headers(path)
+                .map(|v| v.chapters())
+                .unwrap_or_else(|e| {
+                    debug!("Can't read chapters of {}: {}", track, e);
+                    Vec::new()
+                }),
+            false => Vec::new(),
         };
-        let (tx, rx, child) = player::Player::new().expect("Can't start audio controller");
-        // TODO: don't use into_owned, avoid copy
-        Self {
-            path: data.path,
-            play_next: Default::default(),
-            pause: Default::default(),
-            volume_input: Default::default(),
-            favorite: Default::default(),
-            trash_current: Default::default(),
-            export_favorites: Default::default(),
-            volume: data.volume,
-            tx,
-            rx,
-            playlists: data.playlists.into_owned(),
-            current_playlist: data.current_playlist.into_owned(),
-            current_file: Default::default(),
-            is_favorite: false,
-            is_paused: false,
-            data_favorites: data.favorites.into_owned(),
-            length: None,
-            total_playtime: None,
-            playtime: None,
-            child,
+        self.chapter_buttons
+            .resize_with(self.chapters.len(), Default::default);
+    }
+
+    fn set_cues(&mut self, cues: Vec<Cue>) {
+        self.cue_buttons.resize_with(cues.len(), Default::default);
+        self.cues = cues;
+    }
+
+    /// Mark the current position of the current track
+    fn add_cue(&mut self) {
+        let (store, position) = match (self.store.as_ref(), self.playtime) {
+            (Some(store), Some(position)) if !self.current_file.is_empty() => (store, position),
+            _ => return,
+        };
+        let cue = Cue {
+            position,
+            label: tr!("cue-label", number = self.cues.len() + 1),
+        };
+        let cues = store
+            .add_cue(&self.current_file, &cue)
+            .and_then(|_| store.cues(&self.current_file));
+        match cues {
+            Ok(v) => self.set_cues(v),
+            Err(e) => error!("Can't store cue: {}", e),
         }
     }
-}
 
-impl Drop for PlaybackControl {
-    fn drop(&mut self) {
-        self.store_state();
+    /// Whether the first playlist entry is playing or about to
+    fn head_started(&self) -> bool {
+        !self.current_file.is_empty() || self.starting
     }
-}
 
-impl Application for PlaybackControl {
-    type Executor = executor::Default;
-    type Message = Message;
-    type Flags = ();
-    fn view(&mut self) -> Element<Self::Message> {
-        let fav_text = match self.is_favorite {
-            true => "Unfavorite",
-            false => "Favorite",
+    /// Whether the first entry of the playlist queue is the current track
+    fn playlist_started(&self) -> bool {
+        self.head_started() && !self.from_up_next
+    }
+
+    /// Notify hooks and plugins of an event
+    fn emit(&mut self, event: Event, track: &str) {
+        self.hooks.run(event, track);
+        self.plugins.event(event, track);
+    }
+
+    /// Progress report and UI tick interval, slower while minimized
+    fn progress_interval(&self) -> Duration {
+        let interval = match self.minimized {
+            true if self.is_paused || self.current_file.is_empty() => self
+                .background_interval
+                .map_or(BACKGROUND_IDLE_INTERVAL, Duration::from_millis)
+                .max(BACKGROUND_IDLE_INTERVAL),
+            true => self
+                .background_interval
+                .map_or(BACKGROUND_INTERVAL, Duration::from_millis),
+            false if self.time_display == TimeDisplay::Precise => PRECISE_INTERVAL,
+            false => self
+                .progress_interval
+                .map_or(PROGRESS_INTERVAL, Duration::from_millis),
         };
-        let play_text = match self.current_file.is_empty() {
-            true => "Play",
-            false => "Next",
+        interval.max(Duration::from_millis(10))
+    }
+
+    /// Remembered device volumes including the current volume for the active device
+    fn device_volumes(&self) -> HashMap<String, u8> {
+        let mut volumes = self.device_volumes.clone();
+        if let Some(device) = self.output_device.clone() {
+            volumes.insert(device, self.volume);
+        }
+        volumes
+    }
+
+    /// Remember the volume of the previous device and apply the last one of `device`
+    fn switch_device_volume(&mut self, device: String) {
+        if self.output_device.as_ref() == Some(&device) {
+            return;
+        }
+        self.device_volumes = self.device_volumes();
+        if let Some(volume) = self.device_volumes.get(&device) {
+            info!("Output device {}, restoring volume {}", device, volume);
+            self.volume = *volume;
+            self.tx
+                .send(PlayerCommand::Volume(self.output_volume()))
+                .expect("Can't send playback command!");
+        }
+        self.output_device = Some(device);
+    }
+
+    /// Volume including the offset of the active playlist, capped during quiet hours
+    fn output_volume(&self) -> u8 {
+        let offset = self
+            .playlist_settings
+            .get(&self.path)
+            .map_or(0, |s| s.volume_offset);
+        let mut volume = (self.volume as i16 + offset).clamp(0, 100) as u8;
+        if let Some(quiet) = self.quiet_hours.as_ref().filter(|_| self.quiet) {
+            volume = volume.min(quiet.max_volume);
+        }
+        match self.ducked && self.ducking.mode == DuckMode::Duck {
+            true => self.ducking.apply(volume),
+            false => volume,
+        }
+    }
+
+    /// Session queue and playlist queue, including the current track
+    fn queue_tracks(&self) -> Vec<String> {
+        self.up_next
+            .iter()
+            .chain(self.playlists.get(&self.path).into_iter().flatten())
+            .cloned()
+            .collect()
+    }
+
+    /// File name for exports of the queue
+    fn queue_name(&self) -> String {
+        self.path.file_stem().map_or_else(
+            || String::from("queue"),
+            |v| v.to_string_lossy().into_owned(),
+        )
+    }
+
+    /// Write the queue as playlist with relative paths into the portable folder
+    fn export_portable(&self) -> Result<portable::Export> {
+        if self.portable_folder.trim().is_empty() {
+            return Err(eyre!(tr!("portable-no-folder")));
+        }
+        let tracks = self.queue_tracks();
+        let name = self.queue_name();
+        portable::export(
+            &tracks,
+            Path::new(self.portable_folder.trim()),
+            &name,
+            self.portable_copy,
+            self.portable_profile,
+        )
+    }
+
+    /// Config and statistics of the database for export and sync
+    fn archive(&self) -> Result<Archive> {
+        let config = serde_json::to_value(self.config_data())?;
+        let statistics = self.store.as_ref().map(Store::statistics).transpose()?;
+        Ok(Archive::new(config, statistics, self.device_id))
+    }
+
+    fn export_archive(&self, path: &Path) -> Result<()> {
+        self.archive()?.write(path)
+    }
+
+    /// Import playlists, ratings and play counts of an iTunes library XML on a worker,
+    /// the merged queues are applied by [Self::itunes_imported]
+    fn import_itunes(&self, path: PathBuf) -> Command<Message> {
+        let playlists = self.playlists.clone();
+        Command::perform(
+            on_worker(move || import_itunes(&path, &playlists).map_err(|e| format!("{:?}", e))),
+            |v| Message::ItunesImported(v.unwrap_or_else(|| Err(String::from("Import failed")))),
+        )
+    }
+
+    /// Show the queues of an iTunes import, returns how many playlists were imported
+    fn itunes_imported(&mut self, import: ItunesImport) -> usize {
+        self.playlist_folders.add(itunes::FOLDER);
+        for (file, queue) in import.queues {
+            Arc::make_mut(&mut self.playlists).insert(file.clone(), queue);
+            if self.playlist_folders.folder_of(&file).is_none() {
+                self.playlist_folders.move_to(&file, Some(itunes::FOLDER));
+            }
+        }
+        self.identify(&import.tracks);
+        self.extract_metadata(&import.tracks);
+        import.count
+    }
+
+    /// Write the play history to `path`, tags of tracks not loaded yet are read here
+    /// Write the play history into the documents folder on a worker,
+    /// tags of tracks not seen yet are read there
+    fn export_scrobbles(&self) -> Result<Command<Message>> {
+        let store = self
+            .store
+            .as_ref()
+            .ok_or_else(|| eyre!("No database for the play history"))?;
+        let history = store.history()?;
+        let mut infos: HashMap<String, TrackInfo> = history
+            .iter()
+            .filter_map(|l| Some((l.track.clone(), self.metadata.get(&l.track)?.clone())))
+            .collect();
+        let format = self.scrobble_format;
+        let dir = dirs::document_dir()
+            .or_else(dirs::home_dir)
+            .ok_or_else(|| eyre!("No documents folder"))?;
+        Ok(Command::perform(
+            on_worker(move || {
+                for listen in &history {
+                    if !infos.contains_key(&listen.track) {
+                        infos.insert(listen.track.clone(), metadata::read(&listen.track));
+                    }
+                }
+                let path = dir.join(format.file_name());
+                scrobbles::write(&path, format, &history, &infos)
+                    .map(|_| path)
+                    .map_err(|e| e.to_string())
+            }),
+            |v| Message::ScrobblesExported(v.unwrap_or_else(|| Err(String::from("Export failed")))),
+        ))
+    }
+
+    /// Replace the config file by the archived config, applied on the next start
+    fn import_archive(&mut self, archive: Archive) -> Result<()> {
+        let mut data: ConfigData =
+            serde_json::from_value(archive.config).wrap_err("Invalid config in archive")?;
+        data.migrate_favorites();
+        // keep our identity, the archive may come from another device
+        data.device_id = self.device_id;
+        data.sync_folder = self.sync_folder.clone();
+        data.last_sync = archive.saved;
+        let file = config_path(false);
+        std::fs::write(&file, serde_json::to_string(&data)?)
+            .wrap_err_with(|| format!("Can't write config {:?}", file))?;
+        // stored queues and favorites would otherwise come back where the archive has none
+        if let Some(store) = self.store.as_mut() {
+            store.set_playlists(&data.playlists)?;
+            store.set_favorites(&data.favorite_lists)?;
+            if let Some(statistics) = archive.statistics.as_ref() {
+                store.set_statistics(statistics)?;
+            }
+        }
+        self.config_replaced = true;
+        // journaled changes belong to the replaced config
+        self.journal = None;
+        for file in journal::files(&journal_path()) {
+            if let Err(e) = std::fs::remove_file(&file) {
+                warn!("Can't remove journal {:?}: {}", file, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the settings archive to the sync folder, if one is set
+    fn sync(&mut self) {
+        let folder = match self.sync_folder {
+            Some(ref v) if !self.config_replaced => v.clone(),
+            _ => return,
         };
-        let pause_text = match self.is_paused {
-            true => "Resume",
-            false => "Pause",
+        let archive = match self.archive() {
+            Ok(v) => v,
+            Err(e) => return warn!("Can't serialize data! {}", e),
         };
+        match archive::sync(&folder, &archive, self.last_sync) {
+            Ok(SyncResult::Written) => {
+                debug!("Synced to {:?}", folder);
+                self.last_sync = archive.saved;
+            }
+            Ok(SyncResult::Conflict(path)) => {
+                warn!("Sync conflict, written to {:?}", path);
+                self.archive_result = Some(tr!("sync-conflict"));
+                self.sync_notice = archive::newer_synced(&folder, self.device_id, self.last_sync);
+            }
+            Err(e) => warn!("Can't sync to {:?}: {}", folder, e),
+        }
+    }
+
+    /// Config to be stored, borrowing from the current state
+    fn config_data(&self) -> ConfigData<'_> {
+        ConfigData {
+            playlists: self.playlists.clone(),
+            volume: self.volume,
+            current_playlist: Cow::Borrowed(&self.current_playlist),
+            path: self.path.clone(),
+            favorites: HashSet::new(),
+            favorite_lists: self.favorites.lists().clone(),
+            notes: self.notes.clone(),
+            tags: Cow::Borrowed(&self.tags),
+            track_tags: self.track_tags.clone(),
+            library_folders: Cow::Borrowed(&self.library_folders),
+            alarms: Cow::Borrowed(&self.alarms),
+            quiet_hours: self.quiet_hours.clone(),
+            content_filter: Cow::Borrowed(&self.content_filter),
+            playlist_folders: Cow::Borrowed(&self.playlist_folders),
+            pins: Cow::Borrowed(&self.pins),
+            queue_played: Cow::Borrowed(&self.queue_played),
+            device_volumes: Cow::Owned(self.device_volumes()),
+            snapshots: Cow::Borrowed(&self.snapshots),
+            filter: Cow::Borrowed(&self.filter),
+            routing: Cow::Borrowed(&self.routing),
+            preamp: self.preamp,
+            eq_presets: Cow::Borrowed(&self.eq_presets),
+            playlist_settings: Cow::Borrowed(&self.playlist_settings),
+            dsp_chain: Cow::Borrowed(&self.dsp_chain),
+            hooks: Cow::Borrowed(&self.hooks),
+            ducking: Cow::Borrowed(&self.ducking),
+            osd: self.osd,
+            ui_scale: self.ui_scale,
+            touch_mode: self.touch_mode,
+            language: self.language,
+            theme: self.theme,
+            time_display: self.time_display,
+            cue_format: self.cue_format,
+            playlist_charset: self.playlist_charset,
+            title_info: self.title_info,
+            keep_broken: self.keep_broken,
+            track_identity: self.hasher.is_some(),
+            endless: self.queue_end.fill(),
+            queue_end: Some(self.queue_end),
+            broken: self.broken.clone(),
+            progress_interval: self.progress_interval,
+            background_interval: self.background_interval,
+            scan_length: self.scan_length,
+            wheel_volume_step: self.wheel_volume_step,
+            wheel_seek_step: self.wheel_seek_step,
+            cover_cache_size: self.cover_cache_size,
+            text_scale: self.text_scale,
+            now_playing: self.now_playing.as_ref().map(Cow::Borrowed),
+            disabled_plugins: Cow::Borrowed(self.plugins.disabled()),
+            subsonic: self.subsonic.as_ref().map(Cow::Borrowed),
+            jellyfin: self.jellyfin.as_ref().map(Cow::Borrowed),
+            webdav: self.webdav.as_ref().map(Cow::Borrowed),
+            sync_folder: self.sync_folder.clone(),
+            favorites_export: self.favorites_export.clone(),
+            portable_folder: match self.portable_folder.is_empty() {
+                true => None,
+                false => Some(PathBuf::from(&self.portable_folder)),
+            },
+            portable_copy: self.portable_copy,
+            portable_profile: self.portable_profile,
+            device_id: self.device_id,
+            last_sync: self.last_sync,
+            grpc_listen: self.grpc_listen,
+            grpc_token: self.grpc_token.clone(),
+            voice_listen: self.voice_listen,
+            mqtt: self.mqtt.as_ref().map(Cow::Borrowed),
+            lastfm: self.lastfm.as_ref().map(Cow::Borrowed),
+            gamepad: self.gamepad.is_some(),
+            gamepad_mapping: Some(Cow::Borrowed(&self.gamepad_mapping)),
+            lirc: self.lirc_config.as_ref().map(Cow::Borrowed),
+            send_to_device: Some(Cow::Borrowed(&self.send.settings)),
+            rip_cd: Some(Cow::Borrowed(&self.rip.settings)),
+        }
+    }
+
+    /// Serialize and write the config on a separate thread, to not stall the UI on large libraries
+    fn store_state(&mut self) -> Option<JoinHandle<()>> {
+        if self.config_replaced {
+            info!("Config was replaced by an import, not saving");
+            return None;
+        }
+        // the saved config contains all changes journaled up to now
+        let rotated = self.journal.as_mut().and_then(|v| match v.rotate() {
+            Ok(v) => v,
+            Err(e) => {
+                warn!("{}", e);
+                None
+            }
+        });
+        let data = self.config_data().into_owned();
+        let result = thread::Builder::new()
+            .name("config save".to_string())
+            .spawn(move || save_compacted(&data, rotated));
+        match result {
+            Ok(v) => Some(v),
+            Err(e) => {
+                error!("Can't start saving the config: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Record `change` without writing the whole config
+    fn journal(&mut self, change: Change) {
+        if self.config_replaced {
+            return;
+        }
+        let journal = match self.journal.as_mut() {
+            Some(v) => v,
+            None => return,
+        };
+        if let Err(e) = journal.append(&change) {
+            warn!("Can't journal {:?}: {}", change, e);
+        }
+        if journal.len() >= JOURNAL_COMPACT {
+            self.store_state();
+        }
+    }
+
+    /// Replace stream URLs queued by older versions, which included the authentication,
+    /// by the ids of the remote tracks
+    fn migrate_stream_urls(&mut self) {
+        let subsonic = self.subsonic.as_ref().map(|v| v.url.as_str());
+        let jellyfin = self.jellyfin.as_ref().map(|v| v.url.as_str());
+        let moved: HashMap<String, String> = self
+            .playlists
+            .values()
+            .flatten()
+            .chain(self.up_next.iter())
+            .filter_map(|track| {
+                let id = match (subsonic, jellyfin) {
+                    (Some(url), _) if track.starts_with(url) => {
+                        format!(
+                            "{}{}",
+                            remote::SUBSONIC,
+                            subsonic::legacy_song_id(url, track)?
+                        )
+                    }
+                    (_, Some(url)) if track.starts_with(url) => {
+                        format!(
+                            "{}{}",
+                            remote::JELLYFIN,
+                            jellyfin::legacy_item_id(url, track)?
+                        )
+                    }
+                    _ => return None,
+                };
+                Some((track.clone(), id))
+            })
+            .collect();
+        self.tracks_moved(&moved);
+    }
+
+    /// Startup work not needed for the first frame: plugins, OS integrations and the library.
+    /// Secrets, remote controls and the sync folder are handled on a worker,
+    /// see [Self::deferred_loaded].
+    fn init_deferred(&mut self) -> Command<Message> {
+        self.media_session = match MediaSession::new() {
+            Ok(v) => Some(v),
+            Err(e) => {
+                warn!("{}", e);
+                None
+            }
+        };
+        timing::phase("media session");
+        if let Some(queue) = self.playlists.get(&self.path).cloned() {
+            self.extract_metadata(&queue);
+        }
+        // start on the home screen, its mixes and endless play pick from the whole library
+        self.index_library();
+        self.home = Some(self.home_view(archive::now()));
+        let limit = self.cover_cache_size.unwrap_or(COVER_CACHE_SIZE) * 1024 * 1024;
+        self.covers = match covers::CoverCache::open(cover_cache_path(), limit)
+            .and_then(covers::Loader::spawn)
+        {
+            Ok(v) => Some(v),
+            Err(e) => {
+                error!("{:?}", e);
+                None
+            }
+        };
+        // started before the loader was
+        if let Some(loader) = self
+            .covers
+            .as_ref()
+            .filter(|_| !self.current_file.is_empty())
+        {
+            loader.request(&self.current_file);
+        }
+        if self.track_identity {
+            match identity::Hasher::spawn(store_path()) {
+                Ok(hasher) => {
+                    let favorites = self.favorites.all();
+                    for track in self.playlists.values().flatten().chain(favorites) {
+                        hasher.queue(track);
+                    }
+                    self.hasher = Some(hasher);
+                }
+                Err(e) => error!("{:?}", e),
+            }
+        }
+
+        let mut secrets = Vec::new();
+        if let Some(ref config) = self.subsonic {
+            let name = secrets::name("subsonic", &config.user, &config.url);
+            secrets.push((name, config.password.clone()));
+        }
+        if let Some(ref config) = self.jellyfin {
+            let name = secrets::name("jellyfin", &config.user, &config.url);
+            secrets.push((name, config.password.clone()));
+        }
+        if let Some(ref config) = self.webdav {
+            let name = secrets::name("webdav", &config.user, &config.url);
+            secrets.push((name, config.password.clone()));
+        }
+        let mqtt = self.mqtt.clone();
+        if let Some(config) = mqtt.as_ref().filter(|c| !c.user.is_empty()) {
+            let name = secrets::name("mqtt", &config.user, &config.host);
+            secrets.push((name, config.password.clone()));
+        }
+        let handle =
+            match self.grpc_listen.is_some() || self.voice_listen.is_some() || mqtt.is_some() {
+                true => {
+                    let (control, handle) = Control::new();
+                    self.control = Some(control);
+                    Some(handle)
+                }
+                false => None,
+            };
+        let grpc = self.grpc_listen.map(|addr| (addr, self.grpc_token.clone()));
+        let voice = self.voice_listen;
+        let sync = self
+            .sync_folder
+            .clone()
+            .map(|folder| (folder, self.device_id, self.last_sync));
+        Command::perform(
+            on_worker(move || {
+                let mut loaded = Vec::new();
+                let mut errors = Vec::new();
+                for (name, mut secret) in secrets {
+                    if let Err(e) = secret.load(&name) {
+                        error!("Can't load secret for {}: {:?}", name, e);
+                        errors.push((name.clone(), e.to_string()));
+                    }
+                    loaded.push((name, secret));
+                }
+                if let Some(handle) = handle {
+                    if let Some((addr, token)) = grpc {
+                        if let Err(e) = grpc::spawn(addr, token, handle.clone()) {
+                            error!("Can't start gRPC control on {}: {}", addr, e);
+                        }
+                    }
+                    if let Some(addr) = voice {
+                        if let Err(e) = voice::spawn(addr, handle.clone()) {
+                            error!("Can't start voice commands on {}: {}", addr, e);
+                        }
+                    }
+                    if let Some(mut config) = mqtt {
+                        let name = secrets::name("mqtt", &config.user, &config.host);
+                        if let Some((_, secret)) = loaded.iter().find(|(n, _)| *n == name) {
+                            config.password = secret.clone();
+                        }
+                        if let Err(e) = mqtt::spawn(config, handle) {
+                            error!("Can't start MQTT integration: {}", e);
+                        }
+                    }
+                }
+                let sync_notice = sync.and_then(|(folder, device, last_sync)| {
+                    archive::newer_synced(&folder, device, last_sync)
+                });
+                Deferred {
+                    secrets: loaded,
+                    secret_errors: errors,
+                    sync_notice,
+                }
+            }),
+            |v| Message::DeferredLoaded(Box::new(v.unwrap_or_default())),
+        )
+    }
+
+    /// Apply the startup work done on a worker: register the remote sources with their
+    /// passwords and offer a newer synced config
+    fn deferred_loaded(&mut self, deferred: Deferred) {
+        let secret = |name: String, secret: &mut Secret| {
+            if let Some((_, v)) = deferred.secrets.iter().find(|(n, _)| *n == name) {
+                *secret = v.clone();
+            }
+        };
+        let mut servers = Vec::new();
+        if let Some(ref mut config) = self.subsonic {
+            secret(
+                secrets::name("subsonic", &config.user, &config.url),
+                &mut config.password,
+            );
+            self.plugins
+                .register(Box::new(Subsonic::new(config.clone())));
+            servers.push(remote::Server::Subsonic {
+                url: config.url.clone(),
+                user: config.user.clone(),
+                password: config.password.as_str().to_string(),
+            });
+        }
+        if let Some(ref mut config) = self.jellyfin {
+            secret(
+                secrets::name("jellyfin", &config.user, &config.url),
+                &mut config.password,
+            );
+            self.plugins
+                .register(Box::new(Jellyfin::new(config.clone())));
+            servers.push(remote::Server::Jellyfin {
+                url: config.url.clone(),
+                user: config.user.clone(),
+                password: config.password.as_str().to_string(),
+            });
+        }
+        if let Some(ref mut config) = self.webdav {
+            secret(
+                secrets::name("webdav", &config.user, &config.url),
+                &mut config.password,
+            );
+            self.plugins.register(Box::new(WebDav::new(config)));
+            servers.push(webdav::server(config));
+        }
+        if let Some(ref mut config) = self.mqtt {
+            secret(
+                secrets::name("mqtt", &config.user, &config.host),
+                &mut config.password,
+            );
+        }
+        self.secret_errors = deferred
+            .secret_errors
+            .iter()
+            .map(|(name, error)| tr!("secret-failed", name = name.clone(), error = error.clone()))
+            .collect();
+        self.migrate_stream_urls();
+        self.credentials = remote::Credentials::new(servers);
+        self.tx
+            .send(PlayerCommand::Credentials(self.credentials.clone()))
+            .expect("Can't send playback command!");
+        self.plugin_buttons
+            .resize(self.plugins.len(), Default::default());
+        timing::phase("plugins registered");
+        self.sync_notice = deferred.sync_notice;
+        timing::finish("deferred init");
+    }
+
+    /// Handle time tick for updating UI from player state updates
+    fn handle_tick(&mut self) {
+        if self.quit_at.is_some_and(|at| at <= Instant::now()) {
+            self.quit();
+        }
+        self.check_alarms();
+        self.check_quiet_hours();
+        osd::reap(&mut self.osd_child);
+        queue_window::reap(&mut self.queue_window);
+        if let Some(radio) = self.radio.as_mut() {
+            radio.poll();
+        }
+        if let Some(moved) = self.hasher.as_ref().map(identity::Hasher::moved) {
+            self.tracks_moved(&moved);
+        }
+        if let Some(loader) = self.covers.as_ref() {
+            for (track, cover) in loader.loaded() {
+                // the track may have changed meanwhile
+                if track == self.current_file && std::mem::take(&mut self.osd_pending) {
+                    osd::show(&mut self.osd_child, &track, cover.as_deref());
+                }
+                let cover = cover.map(image::Handle::from_memory);
+                if track == self.current_file {
+                    self.cover = cover.clone();
+                }
+                if let Some(grid) = self.albums.as_mut() {
+                    grid.cover_loaded(&track, cover);
+                }
+            }
+        }
+        if let Some(extractor) = self.extractor.as_ref() {
+            let mut read = false;
+            for batch in extractor.batches() {
+                read = true;
+                for (track, info) in batch {
+                    self.durations.insert(&track, info.length);
+                    self.metadata.insert(track, info);
+                }
+            }
+            if read && self.albums.is_some() {
+                let albums = library::albums(self.metadata.iter());
+                if let Some(grid) = self.albums.as_mut() {
+                    grid.refresh(albums);
+                }
+                self.request_album_covers();
+            }
+            if let Some(name) = self
+                .artist_page
+                .as_ref()
+                .filter(|_| read)
+                .map(|p| p.artist().name.clone())
+            {
+                self.artist_page = Some(self.artist_page(&name));
+            }
+            if let Some(search) = self.search.as_mut().filter(|_| read) {
+                search.update(&self.metadata);
+            }
+            // regenerated each morning and as the library is read
+            let now = archive::now();
+            if self
+                .home
+                .as_ref()
+                .is_some_and(|h| read || h.day() != mixes::day(now))
+            {
+                self.home = Some(self.home_view(now));
+            }
+            if let Some(recent) = self.recent.as_mut().filter(|_| read) {
+                recent.update(&self.metadata, archive::now());
+            }
+        }
+        let interval = self.progress_interval();
+        if self.sent_interval != Some(interval) {
+            self.sent_interval = Some(interval);
+            self.tx
+                .send(PlayerCommand::ProgressInterval(interval))
+                .expect("Can't send playback command!");
+        }
+        for msg in coalesce(self.rx.try_iter().collect()) {
+            if log_enabled!(log::Level::Trace) {
+                match msg {
+                    PlayerStatus::Progress(_) | PlayerStatus::Levels(_) => (),
+                    _ => trace!("Player state: {:?}", msg),
+                }
+            }
+            match msg {
+                PlayerStatus::Playing(_, generation)
+                | PlayerStatus::Ended(generation)
+                | PlayerStatus::InvalidFile(_, _, generation)
+                    if generation != self.generation =>
+                {
+                    // already skipped to another track before this arrived
+                    debug!("Ignoring stale status {:?}", msg);
+                }
+                PlayerStatus::Playing(f, _) => {
+                    // also sent on resume
+                    self.starting = false;
+                    if f != self.current_file {
+                        self.track_changed = true;
+                        self.emit(Event::TrackStarted, &f);
+                        if self.osd {
+                            match self.covers.is_some() {
+                                // shown once the cover is looked up
+                                true => self.osd_pending = true,
+                                false => osd::show(&mut self.osd_child, &f, None),
+                            }
+                        }
+                        if let Some(export) = self.now_playing.as_ref() {
+                            export.write(&f);
+                        }
+                        self.load_track_stats(&f);
+                        self.load_chapters(&f);
+                        self.cover = None;
+                        if let Some(loader) = self.covers.as_ref() {
+                            loader.request(&f);
+                        }
+                    }
+                    self.current_file = f;
+                    self.is_paused = false;
+                    self.is_favorite = self.favorites.contains_any(&self.current_file);
+                    if let Some(session) = self.media_session.as_mut() {
+                        session.set_playback(Some(false), self.playtime);
+                    }
+                }
+                PlayerStatus::Ended(_) => {
+                    debug!("Playback ended");
+                    if !self.current_file.is_empty() {
+                        let track = self.current_file.clone();
+                        self.emit(Event::TrackEnded, &track);
+                        if let Some(store) = self.store.as_mut() {
+                            if let Err(e) = store
+                                .record_play(&track, archive::now())
+                                .and_then(|_| store.set_resume_position(&track, None))
+                            {
+                                error!("Can't store play of {}: {}", track, e);
+                            }
+                        }
+                    }
+                    // played to the end, nothing to resume
+                    self.playtime = None;
+                    self.play_next();
+                    self.current_file = String::new();
+                    self.cover = None;
+                    if let Some(session) = self.media_session.as_mut() {
+                        session.set_playback(None, None);
+                    }
+                    // nothing left to play
+                    if self.playlists.get(&self.path).is_none_or(|v| v.is_empty()) {
+                        if let Some(export) = self.now_playing.as_ref() {
+                            export.write("");
+                        }
+                    }
+                }
+                PlayerStatus::Paused => {
+                    self.is_paused = true;
+                    if let Some(session) = self.media_session.as_mut() {
+                        session.set_playback(Some(true), self.playtime);
+                    }
+                }
+                PlayerStatus::Progress(progress) => {
+                    self.playtime = match progress.state {
+                        PlaybackState::Idle | PlaybackState::Stopped => None,
+                        _ => Some(progress.position),
+                    };
+                    if self.track_changed || progress.duration != self.length {
+                        self.track_changed = false;
+                        debug!("Length {:?}", progress.duration);
+                        self.length = progress.duration;
+                        self.durations.insert(&self.current_file, self.length);
+                        if let Some(session) = self.media_session.as_mut() {
+                            session.set_track(&self.current_file, self.length);
+                            session.set_playback(Some(self.is_paused), self.playtime);
+                        }
+                    }
+                    let active = matches!(
+                        progress.state,
+                        PlaybackState::Playing | PlaybackState::Paused
+                    );
+                    if active && !self.current_file.is_empty() {
+                        self.plugins.progress(&self.current_file, progress.position);
+                    }
+                    self.check_scan();
+                }
+                PlayerStatus::Format {
+                    source_rate,
+                    output_rate,
+                    channels,
+                } => {
+                    self.format = Some((source_rate, output_rate, channels));
+                }
+                PlayerStatus::Levels(levels) => {
+                    self.levels = levels;
+                }
+                PlayerStatus::Clipped => {
+                    self.clipped_at = Some(Instant::now());
+                }
+                PlayerStatus::Underruns(count) => {
+                    self.underruns = Some(count);
+                }
+                PlayerStatus::OutputError(e) => {
+                    self.output_error = Some(e);
+                }
+                PlayerStatus::OutputDevice(device) => self.switch_device_volume(device),
+                PlayerStatus::InvalidFile(f, reason, _) => {
+                    info!("Skipping {}: {}", f, reason);
+                    self.skipped.push((f.clone(), reason));
+                    if self.keep_broken {
+                        Arc::make_mut(&mut self.broken).insert(f.clone());
+                    }
+                    self.starting = false;
+                    // set as file, so play_next removes it
+                    self.current_file = f;
+                    self.play_next();
+                    self.current_file = String::new();
+                }
+            }
+        }
+        self.handle_media_session();
+        self.handle_control();
+        self.handle_ducking();
+        self.handle_gamepad();
+        self.handle_lirc();
+        self.send.poll();
+        if let Some(files) = self.rip.poll() {
+            self.add_ripped(files);
+        }
+        self.update_queue_time();
+    }
+
+    /// Sum up the remaining length of the current playlist
+    fn update_queue_time(&mut self) {
+        let first = match self.playlist_started() {
+            true => 1,
+            false => 0,
+        };
+        let up_next = self.up_next.iter().skip(usize::from(self.from_up_next));
+        let (mut total, complete) = match self.playlists.get(&self.path) {
+            Some(v) => self.durations.total(up_next.chain(v.iter().skip(first))),
+            None => self.durations.total(up_next),
+        };
+        if let (Some(length), Some(playtime)) = (self.length, self.playtime) {
+            total += length.saturating_sub(playtime);
+        }
+        self.total_playtime = (total, complete);
+    }
+
+    /// Pause or lower the volume while other applications play audio
+    fn handle_ducking(&mut self) {
+        let active = match self.duck_monitor.as_ref().and_then(|m| m.poll()) {
+            Some(v) => v,
+            None => return,
+        };
+        debug!("Other audio active: {}", active);
+        match self.ducking.mode {
+            DuckMode::Off => (),
+            DuckMode::Pause => {
+                let pause = active && !self.is_paused && !self.current_file.is_empty();
+                let resume = !active && self.ducked && self.is_paused;
+                if pause || resume {
+                    self.tx
+                        .send(PlayerCommand::Pause)
+                        .expect("Can't send playback command!");
+                }
+                self.ducked = pause;
+            }
+            DuckMode::Duck => {
+                self.ducked = active;
+                self.tx
+                    .send(PlayerCommand::Volume(self.output_volume()))
+                    .expect("Can't send playback command!");
+            }
+        }
+    }
+
+    /// Apply pressed gamepad buttons, or assign one in the mapping editor
+    fn handle_gamepad(&mut self) {
+        let mut buttons = Vec::new();
+        if let Some(gamepad) = self.gamepad.as_ref() {
+            while let Some(button) = gamepad.poll() {
+                buttons.push(button);
+            }
+        }
+        for button in buttons {
+            if let Some(action) = self.gamepad_settings.learning() {
+                debug!("Gamepad button {} assigned to {:?}", button, action);
+                self.gamepad_mapping.insert(button, action);
+                self.gamepad_settings.learn(None);
+                continue;
+            }
+            let action = match self.gamepad_mapping.get(&button) {
+                Some(v) => *v,
+                None => continue,
+            };
+            debug!("Gamepad action {:?}", action);
+            self.button_action(action);
+        }
+    }
+
+    /// Apply commands from LIRC remote controls
+    fn handle_lirc(&mut self) {
+        let mut actions = Vec::new();
+        if let Some(lirc) = self.lirc.as_ref() {
+            while let Some(action) = lirc.poll() {
+                actions.push(action);
+            }
+        }
+        for action in actions {
+            debug!("Remote control action {:?}", action);
+            self.button_action(action);
+        }
+    }
+
+    /// Action of a gamepad or remote control button
+    fn button_action(&mut self, action: ButtonAction) {
+        if action != ButtonAction::Next {
+            self.end_scan();
+        }
+        match action {
+            ButtonAction::PlayPause if self.current_file.is_empty() => self.play_next(),
+            ButtonAction::PlayPause => self
+                .tx
+                .send(PlayerCommand::Pause)
+                .expect("Can't send playback command!"),
+            ButtonAction::Next => self.play_next(),
+            ButtonAction::Previous => self.play_previous(),
+            ButtonAction::Stop => self.stop(),
+            ButtonAction::VolumeUp => self.set_volume(self.volume.saturating_add(5).min(100)),
+            ButtonAction::VolumeDown => self.set_volume(self.volume.saturating_sub(5)),
+        }
+    }
+
+    /// Pause and go back to the start of the current track, forgetting where it was left
+    fn pause_at_start(&mut self) {
+        if self.current_file.is_empty() {
+            return;
+        }
+        if !self.is_paused {
+            self.tx
+                .send(PlayerCommand::Pause)
+                .expect("Can't send playback command!");
+        }
+        self.playtime = Some(Duration::ZERO);
+        self.tx
+            .send(PlayerCommand::Seek(Duration::ZERO))
+            .expect("Can't send playback command!");
+        if let Some(store) = self.store.as_ref() {
+            if let Err(e) = store.set_resume_position(&self.current_file, None) {
+                error!("Can't clear resume position: {}", e);
+            }
+        }
+    }
+
+    /// Apply control events from the OS media session
+    fn handle_media_session(&mut self) {
+        let mut events = Vec::new();
+        if let Some(session) = self.media_session.as_ref() {
+            while let Some(event) = session.poll() {
+                events.push(event);
+            }
+        }
+        for event in events {
+            debug!("Media session event {:?}", event);
+            if !matches!(event, MediaControlEvent::Next) {
+                self.end_scan();
+            }
+            let toggle = match event {
+                MediaControlEvent::Toggle => true,
+                MediaControlEvent::Play => self.is_paused,
+                MediaControlEvent::Pause => !self.is_paused && !self.current_file.is_empty(),
+                MediaControlEvent::Stop => {
+                    self.pause_at_start();
+                    false
+                }
+                MediaControlEvent::Next => {
+                    self.play_next();
+                    false
+                }
+                MediaControlEvent::Previous => {
+                    self.play_previous();
+                    false
+                }
+                _ => false,
+            };
+            if toggle {
+                self.tx
+                    .send(PlayerCommand::Pause)
+                    .expect("Can't send playback command!");
+            }
+        }
+    }
+
+    /// Apply remote control commands and publish the current state
+    fn handle_control(&mut self) {
+        let mut commands = Vec::new();
+        if let Some(control) = self.control.as_ref() {
+            while let Some(command) = control.poll() {
+                commands.push(command);
+            }
+        } else {
+            return;
+        }
+        for command in commands {
+            debug!("Control command {:?}", command);
+            match command {
+                ControlCommand::Play if !self.head_started() => self.play_next(),
+                ControlCommand::Play => {
+                    if self.is_paused {
+                        self.tx
+                            .send(PlayerCommand::Pause)
+                            .expect("Can't send playback command!");
+                    }
+                }
+                ControlCommand::Pause => {
+                    if !self.is_paused && !self.current_file.is_empty() {
+                        self.tx
+                            .send(PlayerCommand::Pause)
+                            .expect("Can't send playback command!");
+                    }
+                }
+                ControlCommand::Next => self.play_next(),
+                ControlCommand::Stop => self.stop(),
+                ControlCommand::Volume(v) => {
+                    self.volume = v.min(100);
+                    self.tx
+                        .send(PlayerCommand::Volume(self.output_volume()))
+                        .expect("Can't send playback command!");
+                }
+                ControlCommand::Enqueue(tracks) => self.enqueue_tracks(tracks),
+                ControlCommand::ClearQueue => self.clear_queue(),
+                ControlCommand::OpenPlaylist(path) if self.playlists.contains_key(&path) => {
+                    self.open_playlist(path)
+                }
+                ControlCommand::OpenPlaylist(path) => warn!("Playlist {:?} isn't loaded", path),
+            }
+        }
+        let state = match (self.current_file.is_empty(), self.is_paused) {
+            (true, _) => ControlState::Stopped,
+            (false, true) => ControlState::Paused,
+            (false, false) => ControlState::Playing,
+        };
+        let first = if self.playlist_started() { 1 } else { 0 };
+        let playlist = self.playlists.get(&self.path).map_or(&[][..], |v| v);
+        let queue = self
+            .up_next
+            .iter()
+            .skip(usize::from(self.from_up_next))
+            .chain(playlist.iter().skip(first))
+            .take(control::STATUS_QUEUE)
+            .cloned()
+            .collect();
+        let status = ControlStatus {
+            state,
+            track: self.current_file.clone(),
+            position: self.playtime.map_or(0, |v| v.as_secs()),
+            duration: self.length,
+            volume: self.volume,
+            queue,
+            playlists: self.playlists.keys().cloned().collect(),
+        };
+        if let Some(control) = self.control.as_ref() {
+            control.publish(status);
+        }
+    }
+
+    /// Show the queue in its own window, controlling the player like the other transports
+    fn pop_out_queue(&mut self) {
+        if self.queue_window.is_some() {
+            return;
+        }
+        let control = self.control.get_or_insert_with(|| Control::new().0);
+        match queue_window::open(control.handle(), self.language) {
+            Ok(child) => self.queue_window = Some(child),
+            Err(e) => error!("{:?}", e),
+        }
+    }
+
+    /// Insert a track to be played after the current one
+    fn enqueue_next(&mut self, track: String) {
+        self.enqueue_tracks(vec![track]);
+    }
+
+    /// Insert tracks in order to be played after the current one
+    fn enqueue_tracks(&mut self, tracks: Vec<String>) {
+        if tracks.is_empty() {
+            return;
+        }
+        let playing = self.head_started();
+        self.identify(&tracks);
+        self.extract_metadata(&tracks);
+        // after the current track if it came from the session queue, before the playlist otherwise
+        queue::insert_next(&mut self.up_next, playing && self.from_up_next, tracks);
+        if !playing {
+            self.play_next();
+        }
+    }
+
+    /// Stop playback, keeping the current track first in the queue to start over
+    fn stop(&mut self) {
+        if !self.head_started() {
+            return;
+        }
+        self.remember_position();
+        self.tx
+            .send(PlayerCommand::Stop)
+            .expect("Can't send playback command!");
+        // statuses still underway belong to the stopped track
+        self.generation += 1;
+        self.starting = false;
+        self.current_file = String::new();
+        self.is_paused = false;
+        self.length = None;
+        self.playtime = None;
+        self.cover = None;
+        if let Some(session) = self.media_session.as_mut() {
+            session.set_playback(None, None);
+        }
+        if let Some(export) = self.now_playing.as_ref() {
+            export.write("");
+        }
+    }
+
+    /// Save all queues and filters under `snapshot_name`, replacing an older session of that name
+    fn save_snapshot(&mut self) {
+        let name = self.snapshot_name.trim();
+        if name.is_empty() {
+            return;
+        }
+        let snapshot = Snapshot {
+            playlists: self.playlists.clone(),
+            path: self.path.clone(),
+            filter: self.filter.clone(),
+            tag_filter: self.tag_filter.clone(),
+            position: self.playtime.filter(|_| !self.current_file.is_empty()),
+        };
+        info!("Saving session {}", name);
+        self.snapshots.insert(name.to_string(), snapshot);
+        self.snapshot_selected = Some(name.to_string());
+        self.snapshot_name = String::new();
+    }
+
+    /// Replace all queues and filters by the saved session `name` and continue its track
+    fn restore_snapshot(&mut self, name: &str) {
+        let snapshot = match self.snapshots.get(name) {
+            Some(v) => v.clone(),
+            None => return,
+        };
+        info!("Restoring session {}", name);
+        self.stop();
+        self.playlists = snapshot.playlists;
+        self.path = snapshot.path;
+        self.filter = snapshot.filter;
+        self.tag_filter = snapshot
+            .tag_filter
+            .filter(|v| self.tags.iter().any(|t| &t.name == v));
+        self.play_next();
+        if let Some(position) = snapshot.position.filter(|_| self.head_started()) {
+            self.playtime = Some(position);
+            self.tx
+                .send(PlayerCommand::Seek(position))
+                .expect("Can't send playback command!");
+        }
+    }
+
+    /// Remove all upcoming tracks, the current one keeps playing
+    fn clear_queue(&mut self) {
+        self.up_next
+            .truncate(usize::from(self.from_up_next && self.head_started()));
+        let first = if self.playlist_started() { 1 } else { 0 };
+        if let Some(v) = Arc::make_mut(&mut self.playlists).get_mut(&self.path) {
+            debug!("Clearing {} queued tracks", v.len().saturating_sub(first));
+            v.truncate(first);
+        }
+    }
+
+    /// Move upcoming track at queue position `pos` one entry up
+    fn move_up(&mut self, pos: usize) {
+        // first entry is the current track when playing
+        let first = if self.playlist_started() { 1 } else { 0 };
+        if let Some(v) = Arc::make_mut(&mut self.playlists).get_mut(&self.path) {
+            if pos > first && pos < v.len() {
+                v.swap(pos, pos - 1);
+            }
+        }
+    }
+
+    fn select_track(&mut self, list: SelectionList, index: usize) {
+        match list {
+            SelectionList::Queue => {
+                if let Some(search) = self.search.as_mut() {
+                    search.selection.clear();
+                }
+                self.queue_selection.click(index, self.command, self.shift);
+            }
+            SelectionList::Search => {
+                self.queue_selection.clear();
+                if let Some(search) = self.search.as_mut() {
+                    search.selection.click(index, self.command, self.shift);
+                }
+            }
+        }
+    }
+
+    fn clear_selection(&mut self) {
+        self.queue_selection.clear();
+        if let Some(search) = self.search.as_mut() {
+            search.selection.clear();
+        }
+    }
+
+    /// Run `action` on the selected tracks, replacing what can be undone by its inverse
+    fn bulk(&mut self, action: BulkAction) {
+        let (list, tracks) = match self.search.as_ref() {
+            Some(search) if !search.selection.is_empty() => {
+                (SelectionList::Search, search.selected())
+            }
+            _ => {
+                let queue = self.playlists.get(&self.path).map_or(&[][..], |v| v);
+                let tracks = self.queue_selection.items(queue).cloned().collect();
+                (SelectionList::Queue, tracks)
+            }
+        };
+        if tracks.is_empty() {
+            return;
+        }
+        let playing = self.playlist_started();
+        // the current track isn't part of the upcoming queue
+        let positions: Vec<usize> = self
+            .queue_selection
+            .indices()
+            .filter(|pos| !playing || *pos > 0)
+            .collect();
+        let mut undo = Undo::default();
+        if let Some(v) = self.playlists.get(&self.path) {
+            undo.queues.insert(self.path.clone(), v.clone());
+        }
+        match action {
+            BulkAction::Remove => {
+                if let Some(v) = Arc::make_mut(&mut self.playlists).get_mut(&self.path) {
+                    let removed = queue::remove_positions(v, &positions);
+                    debug!("Removed {} tracks from the queue", removed.len());
+                }
+            }
+            BulkAction::EnqueueNext => match list {
+                SelectionList::Queue => {
+                    if let Some(v) = Arc::make_mut(&mut self.playlists).get_mut(&self.path) {
+                        queue::move_next(v, playing, &positions);
+                    }
+                }
+                SelectionList::Search => self.enqueue_tracks(tracks),
+            },
+            BulkAction::ToPlaylist(path) => {
+                let playlists = Arc::make_mut(&mut self.playlists);
+                let target = match playlists.get_mut(&path) {
+                    Some(v) if path != self.path => v,
+                    _ => return,
+                };
+                undo.queues.insert(path.clone(), target.clone());
+                // appended, so the playlist continues where it was
+                target.extend(tracks);
+                if self.bulk_bar.move_tracks() && list == SelectionList::Queue {
+                    if let Some(v) = playlists.get_mut(&self.path) {
+                        queue::remove_positions(v, &positions);
+                    }
+                }
+            }
+            BulkAction::Favorite(name) => {
+                undo.queues.clear();
+                for track in tracks {
+                    if self.favorites.lists_containing(&track).contains(&name) {
+                        continue;
+                    }
+                    self.favorites.set(&name, &track, true);
+                    self.journal(Change::Favorite {
+                        list: name.clone(),
+                        track: track.clone(),
+                        favorite: true,
+                    });
+                    self.emit(Event::Favorited, &track);
+                    undo.changes.push(Change::Favorite {
+                        list: name.clone(),
+                        track,
+                        favorite: false,
+                    });
+                }
+                self.is_favorite = self.favorites.contains_any(&self.current_file);
+                self.favorites_changed();
+            }
+            BulkAction::Tag(tag) => {
+                undo.queues.clear();
+                for track in tracks {
+                    if tags::set(Arc::make_mut(&mut self.track_tags), &track, &tag, true) {
+                        self.journal(Change::Tag {
+                            track: track.clone(),
+                            tag: tag.clone(),
+                            set: true,
+                        });
+                        undo.changes.push(Change::Tag {
+                            track,
+                            tag: tag.clone(),
+                            set: false,
+                        });
+                    }
+                }
+            }
+            BulkAction::MoveFiles => {
+                undo.queues.clear();
+                let target = PathBuf::from(self.bulk_bar.target().trim());
+                if !target.is_dir() {
+                    error!("Can't move files, {:?} is no folder", target);
+                    self.bulk_bar.set_move_error(Some(tr!(
+                        "move-no-folder",
+                        folder = target.display().to_string()
+                    )));
+                    return;
+                }
+                let moves = tracks
+                    .iter()
+                    .filter_map(|t| Some((t.clone(), target.join(Path::new(t).file_name()?))))
+                    .collect();
+                let moved = self.move_files(moves);
+                undo.moved = moved
+                    .into_iter()
+                    .map(|(from, to)| (to, PathBuf::from(from)))
+                    .collect();
+            }
+        }
+        // starting playback moved the head, the old queue can't be restored anymore
+        if self.playlist_started() != playing {
+            undo.queues.remove(&self.path);
+        }
+        self.undo = Some(undo);
+        self.clear_selection();
+    }
+
+    /// Move files to their new path, returns the new path of the moved ones by their old path.
+    /// Files that couldn't be moved are reported in the bulk bar.
+    fn move_files(&mut self, moves: HashMap<String, PathBuf>) -> HashMap<String, String> {
+        let mut moved = HashMap::new();
+        let mut failed = Vec::new();
+        for (from, to) in moves {
+            if to.exists() {
+                warn!("Not moving {}, {:?} exists", from, to);
+                failed.push(format!("{} exists", to.display()));
+                continue;
+            }
+            match move_file(Path::new(&from), &to) {
+                Ok(_) => {
+                    info!("Moved {} to {:?}", from, to);
+                    moved.insert(from, to.to_string_lossy().into_owned());
+                }
+                Err(e) => {
+                    error!("Can't move {}: {}", from, e);
+                    failed.push(format!("{}: {}", from, e));
+                }
+            }
+        }
+        self.bulk_bar.set_move_error(
+            failed
+                .first()
+                .map(|error| tr!("move-failed", count = failed.len(), error = error.clone())),
+        );
+        if let Some(new) = moved.get(&self.current_file) {
+            self.current_file = new.clone();
+        }
+        self.tracks_moved(&moved);
+        moved
+    }
+
+    /// Revert the last bulk action
+    fn undo(&mut self) {
+        let undo = match self.undo.take() {
+            Some(v) => v,
+            None => return,
+        };
+        for (path, queue) in undo.queues {
+            if let Some(v) = Arc::make_mut(&mut self.playlists).get_mut(&path) {
+                *v = queue;
+            }
+        }
+        for change in undo.changes.into_iter().rev() {
+            match change {
+                Change::Favorite {
+                    ref list,
+                    ref track,
+                    favorite,
+                } => self.favorites.set(list, track, favorite),
+                Change::Tag {
+                    ref track,
+                    ref tag,
+                    set,
+                } => {
+                    tags::set(Arc::make_mut(&mut self.track_tags), track, tag, set);
+                }
+                _ => (),
+            }
+            self.journal(change);
+        }
+        self.is_favorite = self.favorites.contains_any(&self.current_file);
+        self.favorites_changed();
+        self.move_files(undo.moved);
+        self.clear_selection();
+    }
+
+    /// Rewrite the favorites export, if one is configured
+    fn favorites_changed(&self) {
+        if let Some(ref path) = self.favorites_export {
+            let file = path.to_string_lossy();
+            match playlist::write_playlist(
+                self.favorites.all().into_iter(),
+                "Audio-Wrench Favorites",
+                &file,
+            ) {
+                Ok(_) => debug!("Favorites written to {}", file),
+                Err(e) => error!("Can't write favorites to {}: {}", file, e),
+            }
+        }
+    }
+
+    /// Read playlist A and compare it with playlist B or the favorites
+    fn compare_lists(&self) -> Result<(Vec<String>, playlist::Diff)> {
+        let a = playlist::read_playlist(Path::new(&self.diff_a), self.playlist_charset)?;
+        let b = match self.diff_b.is_empty() {
+            true => self.favorites.all().into_iter().cloned().collect(),
+            false => playlist::read_playlist(Path::new(&self.diff_b), self.playlist_charset)?,
+        };
+        let diff = playlist::Diff::new(&a, &b);
+        Ok((a, diff))
+    }
+
+    /// Whether a text input takes keyboard input
+    fn text_focused(&self) -> bool {
+        // every text input of the main view, shortcuts must not fire while typing into them
+        let inputs = [
+            &self.filter_input,
+            &self.filter_password_input,
+            &self.goto_input,
+            &self.relocate_input,
+            &self.archive_input,
+            &self.portable_input,
+            &self.diff_a_input,
+            &self.diff_b_input,
+            &self.new_list_input,
+            &self.note_input,
+            &self.snapshot_input,
+        ];
+        inputs.iter().any(|v| v.is_focused())
+            || self.sidebar.text_focused()
+            || self.send.text_focused()
+            || self.rip.text_focused()
+            || self
+                .search
+                .as_ref()
+                .is_some_and(LibrarySearch::text_focused)
+            || self.bulk_bar.text_focused()
+    }
+
+    /// Add or remove the current track from the favorite list `name`
+    fn set_favorite(&mut self, name: &str, favorite: bool) {
+        if self.current_file.is_empty() {
+            return;
+        }
+        let track = self.current_file.clone();
+        self.favorites.set(name, &track, favorite);
+        self.journal(Change::Favorite {
+            list: name.to_string(),
+            track: track.clone(),
+            favorite,
+        });
+        if favorite {
+            self.emit(Event::Favorited, &track);
+        }
+        self.is_favorite = self.favorites.contains_any(&track);
+        self.favorites_changed();
+    }
+
+    /// Toggle tag number `index` on the current track
+    fn toggle_tag(&mut self, index: usize) {
+        if let Some(tag) = self.tags.get(index) {
+            if !self.current_file.is_empty() {
+                tags::toggle(
+                    Arc::make_mut(&mut self.track_tags),
+                    &self.current_file,
+                    &tag.name,
+                );
+                let set = self
+                    .track_tags
+                    .get(&self.current_file)
+                    .is_some_and(|v| v.contains(&tag.name));
+                let change = Change::Tag {
+                    track: self.current_file.clone(),
+                    tag: tag.name.clone(),
+                    set,
+                };
+                self.journal(change);
+            }
+        }
+    }
+
+    /// Replace missing tracks in all playlists and favorites by files found below the relocation root,
+    /// the folders are scanned on a worker
+    fn relocate(&mut self) -> Command<Message> {
+        let root = PathBuf::from(&self.relocate_root);
+        let favorites = self.favorites.all();
+        let tracks: Vec<String> = self
+            .playlists
+            .values()
+            .flatten()
+            .chain(favorites)
+            .cloned()
+            .collect();
+        self.relocate_result = Some(tr!("loading"));
+        let scan = move || {
+            let index = relocate::Index::scan(&root).map_err(|e| e.to_string())?;
+            Ok(index.relocate(tracks.iter()))
+        };
+        Command::perform(on_worker(scan), |v| {
+            Message::Relocated(v.unwrap_or_else(|| Err(String::from("Relocation failed"))))
+        })
+    }
+
+    fn relocated(&mut self, result: std::result::Result<HashMap<String, String>, String>) {
+        match result {
+            Ok(moved) => {
+                info!("Relocated {} tracks", moved.len());
+                self.tracks_moved(&moved);
+                self.relocate_result = Some(tr!("relocated", count = moved.len()));
+            }
+            Err(e) => self.relocate_result = Some(e),
+        }
+    }
+
+    /// Read the disc in `drive` for ripping
+    fn read_cd(&mut self, drive: cd::Drive) -> Command<Message> {
+        let job = self.rip.select_drive(drive.clone());
+        Command::perform(on_worker(job), move |v| {
+            let result = v.unwrap_or_else(|| Err(String::from("Reading the disc failed")));
+            Message::CdRead(drive.clone(), result)
+        })
+    }
+
+    /// Update queues and all data about tracks to their new path, `moved` is by old path
+    fn tracks_moved(&mut self, moved: &HashMap<String, String>) {
+        if moved.is_empty() {
+            return;
+        }
+        let queues = Arc::make_mut(&mut self.playlists).values_mut().flatten();
+        for track in queues.chain(self.up_next.iter_mut()) {
+            if let Some(new) = moved.get(track) {
+                *track = new.clone();
+            }
+        }
+        self.favorites.relocate(moved);
+        self.favorites_changed();
+        // found again, worth another try
+        Arc::make_mut(&mut self.broken).retain(|t| !moved.contains_key(t));
+        for (from, to) in moved {
+            if let Some(store) = self.store.as_mut() {
+                if let Err(e) = store.rename_track(from, to) {
+                    error!("Can't move stats of {}: {}", from, e);
+                }
+            }
+            let notes = Arc::make_mut(&mut self.notes);
+            if let Some(note) = notes.remove(from) {
+                notes.insert(to.clone(), note);
+            }
+            let track_tags = Arc::make_mut(&mut self.track_tags);
+            if let Some(tags) = track_tags.remove(from) {
+                track_tags.insert(to.clone(), tags);
+            }
+            self.journal(Change::Moved {
+                from: from.clone(),
+                to: to.clone(),
+            });
+        }
+    }
+
+    /// Queue `tracks` for hashing if track identity is on
+    fn identify(&self, tracks: &[String]) {
+        if let Some(hasher) = self.hasher.as_ref() {
+            for track in tracks {
+                hasher.queue(track);
+            }
+        }
+    }
+
+    /// Home screen with the mixes of the day at unix time `now`
+    fn home_view(&self, now: u64) -> HomeView {
+        let plays = match self.store.as_ref().map(Store::plays) {
+            Some(Ok(v)) => v,
+            Some(Err(e)) => {
+                error!("Can't load play history: {}", e);
+                HashMap::new()
+            }
+            None => HashMap::new(),
+        };
+        let day = mixes::day(now);
+        let favorites = self.favorites.all();
+        HomeView::new(
+            day,
+            mixes::generate(day, now, &favorites, &self.metadata, &plays),
+        )
+    }
+
+    /// Read the tags of all queued and favorite tracks and the library folders, if not done yet
+    fn index_library(&mut self) {
+        let mut tracks: Vec<String> = self.playlists.values().flatten().cloned().collect();
+        tracks.extend(self.favorites.all().into_iter().cloned());
+        self.extract_metadata(&tracks);
+        if let Some(extractor) = self.extractor.as_ref().filter(|_| !self.library_scanned) {
+            self.library_scanned = true;
+            let mut known: HashSet<String> = self.metadata.keys().cloned().collect();
+            known.extend(tracks);
+            extractor.scan(self.library_folders.clone(), known);
+        }
+    }
+
+    /// Show the albums of the library
+    fn open_albums(&mut self) {
+        self.index_library();
+        self.albums = Some(AlbumGrid::new(library::albums(self.metadata.iter())));
+        self.request_album_covers();
+    }
+
+    /// Change the search facets and search again
+    fn update_search<F: FnOnce(&mut library::Facets)>(&mut self, change: F) {
+        if let Some(search) = self.search.as_mut() {
+            change(&mut search.facets);
+            search.update(&self.metadata);
+        }
+    }
+
+    /// Artist page of `name` with the play counts of the stored statistics
+    fn artist_page(&self, name: &str) -> ArtistPage {
+        let artist = library::artist(name, self.metadata.iter());
+        let counts = match self.store.as_ref() {
+            Some(store) => artist
+                .tracks
+                .iter()
+                .map(|t| (t.clone(), store.play_count(t).unwrap_or_default()))
+                .collect(),
+            None => Vec::new(),
+        };
+        ArtistPage::new(artist, counts, |t| track_label(&self.metadata, t))
+    }
+
+    /// Load covers of the albums shown in the grid
+    fn request_album_covers(&mut self) {
+        if let (Some(grid), Some(loader)) = (self.albums.as_mut(), self.covers.as_ref()) {
+            for track in grid.wanted_covers() {
+                loader.request(&track);
+            }
+        }
+    }
+
+    /// Add ripped `files` to the library, and their folder to the library folders
+    fn add_ripped(&mut self, files: Vec<PathBuf>) {
+        // files are ripped to <folder>/<artist>/<album>
+        let folder = match files.first().and_then(|f| f.ancestors().nth(3)) {
+            Some(v) => v.to_path_buf(),
+            None => return,
+        };
+        if !self.library_folders.iter().any(|f| folder.starts_with(f)) {
+            info!("Adding {:?} to the library folders", folder);
+            self.library_folders.push(folder);
+        }
+        let tracks: Vec<String> = files
+            .iter()
+            .map(|f| f.to_string_lossy().into_owned())
+            .collect();
+        self.extract_metadata(&tracks);
+    }
+
+    /// Read tags and lengths of `tracks` not read yet in the background
+    fn extract_metadata(&mut self, tracks: &[String]) {
+        let extractor = match self.extractor.as_ref() {
+            Some(v) => v,
+            None => return,
+        };
+        let metadata = &self.metadata;
+        let new: Vec<String> = tracks
+            .iter()
+            .filter(|t| !metadata.contains_key(*t))
+            .cloned()
+            .collect();
+        self.durations.requested(new.iter());
+        extractor.extract(new);
+    }
+
+    /// Unmark broken track at queue position `pos` and move it up next
+    fn retry(&mut self, pos: usize) {
+        let first = if self.playlist_started() { 1 } else { 0 };
+        if let Some(v) = Arc::make_mut(&mut self.playlists).get_mut(&self.path) {
+            if pos >= first && pos < v.len() {
+                Arc::make_mut(&mut self.broken).remove(&v[pos]);
+                let track = v.remove(pos);
+                v.insert(first, track);
+            }
+        }
+    }
+
+    fn file_dropped(&mut self, file: PathBuf) {
+        if is_audio_file(&file) {
+            self.enqueue_next(file.to_string_lossy().into_owned());
+            return;
+        }
+        match charset::read(&file, self.playlist_charset) {
+            Ok(data) => self.load_playlist(file, &data, None),
+            Err(e) => warn!("Can't open dropped file {:?}", e),
+        }
+    }
+
+    /// Open a dropped or pasted file, iTunes library exports are imported
+    fn open_file(&mut self, file: PathBuf) -> Command<Message> {
+        let xml = file
+            .extension()
+            .is_some_and(|v| v.eq_ignore_ascii_case("xml"));
+        if xml && itunes::sniff(&file) {
+            return self.import_itunes(file);
+        }
+        self.file_dropped(file);
+        Command::none()
+    }
+
+    /// Enqueue track or open playlist, local or remote
+    fn open_track(&mut self, track: String) -> Command<Message> {
+        if !is_playlist_file(Path::new(&track)) {
+            self.enqueue_next(track);
+        } else if track.starts_with("http://") || track.starts_with("https://") {
+            return self.open_remote_playlist(&track);
+        } else {
+            return self.open_file(PathBuf::from(track));
+        }
+        Command::none()
+    }
+
+    /// Open paths, folders or URLs from the clipboard
+    fn paste(&mut self) -> Command<Message> {
+        match desktop::paste() {
+            Ok(text) => self.paste_text(&text),
+            Err(e) => {
+                warn!("{:?}", e);
+                Command::none()
+            }
+        }
+    }
+
+    /// Enqueue the primary selection on middle click
+    fn paste_selection(&mut self) -> Command<Message> {
+        match desktop::paste_selection() {
+            Ok(text) => self.paste_text(&text),
+            Err(e) => {
+                warn!("{:?}", e);
+                Command::none()
+            }
+        }
+    }
+
+    /// List the current folder of the browser on a worker
+    fn browse(&mut self) -> Command<Message> {
+        let browser = match self.browser.as_mut() {
+            Some(v) => v,
+            None => return Command::none(),
+        };
+        let source = match self.plugins.source(browser.plugin) {
+            Some(v) => v,
+            None => return Command::none(),
+        };
+        let (parent, request) = browser.load();
+        let listing = source.browse(parent.as_deref());
+        Command::perform(on_worker(listing), move |result| {
+            let result = result
+                .unwrap_or_else(|| Err(eyre!("Listing failed")))
+                .map_err(|e| format!("{}", e));
+            Message::Listed(request, result)
+        })
+    }
+
+    /// Open pasted paths, folders or URLs, one per line
+    fn paste_text(&mut self, text: &str) -> Command<Message> {
+        let mut tracks = Vec::new();
+        let mut fetches = Vec::new();
+        for line in text.lines().map(str::trim).filter(|v| !v.is_empty()) {
+            // file managers copy file:// URIs
+            let line = match Url::parse(line) {
+                Ok(url) if url.scheme() == "file" => match url.to_file_path() {
+                    Ok(v) => v.to_string_lossy().into_owned(),
+                    Err(_) => continue,
+                },
+                Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {
+                    match is_playlist_file(Path::new(url.path())) {
+                        true => fetches.push(self.open_remote_playlist(line)),
+                        false => tracks.push(line.to_string()),
+                    }
+                    continue;
+                }
+                _ => line.to_string(),
+            };
+            let path = Path::new(&line);
+            if path.is_dir() {
+                match std::fs::read_dir(path) {
+                    Ok(dir) => {
+                        let mut files: Vec<_> = dir
+                            .filter_map(|e| e.ok())
+                            .map(|e| e.path())
+                            .filter(|p| is_audio_file(p))
+                            .collect();
+                        files.sort();
+                        tracks.extend(files.iter().map(|f| f.to_string_lossy().into_owned()));
+                    }
+                    Err(e) => warn!("Can't read pasted folder {:?}: {}", path, e),
+                }
+            } else if is_audio_file(path) && path.is_file() {
+                tracks.push(line);
+            } else if path.is_file() {
+                fetches.push(self.open_file(path.to_path_buf()));
+            } else {
+                debug!("Ignoring pasted text {}", line);
+            }
+        }
+        self.enqueue_tracks(tracks);
+        Command::batch(fetches)
+    }
+
+    /// Fetch remote playlist on a worker, it is played by [Message::RemotePlaylistFetched]
+    fn open_remote_playlist(&self, url: &str) -> Command<Message> {
+        if let Err(e) = Url::parse(url) {
+            warn!("Invalid playlist URL {}: {}", url, e);
+            return Command::none();
+        }
+        let credentials = self.credentials.clone();
+        let url = url.to_string();
+        let fetch = {
+            let url = url.clone();
+            move || {
+                credentials
+                    .fetch_string(&url)
+                    .map_err(|e| format!("{:#}", e))
+            }
+        };
+        Command::perform(on_worker(fetch), move |result| {
+            let result = result.unwrap_or_else(|| Err(String::from("Fetching failed")));
+            Message::RemotePlaylistFetched(url.clone(), result)
+        })
+    }
+
+    /// Play a fetched remote playlist, relative entries are resolved against its `url`
+    fn remote_playlist_fetched(
+        &mut self,
+        url: String,
+        result: std::result::Result<String, String>,
+    ) {
+        let data = match result {
+            Ok(v) => v,
+            Err(e) => {
+                warn!("Can't open remote playlist: {}", e);
+                return;
+            }
+        };
+        match Url::parse(&url) {
+            Ok(base) => self.load_playlist(PathBuf::from(&url), &data, Some(&base)),
+            Err(e) => warn!("Invalid playlist URL {}: {}", url, e),
+        }
+    }
+
+    /// Load playlist and start playing it.
+    /// `base` is used to resolve relative entries of remote playlists.
+    fn load_playlist(&mut self, file: PathBuf, data: &str, base: Option<&Url>) {
+        match playlist_decoder::decode(data) {
+            Ok(mut playlist) => {
+                if let Some(base) = base {
+                    for entry in playlist.iter_mut() {
+                        if Url::parse(entry).is_err() {
+                            if let Ok(v) = base.join(entry) {
+                                *entry = v.to_string();
+                            }
+                        }
+                    }
+                }
+                self.identify(&playlist);
+                self.extract_metadata(&playlist);
+                if let Some(v) = Arc::make_mut(&mut self.playlists).get_mut(&file) {
+                    if v.is_empty() {
+                        playlist.shuffle(&mut thread_rng());
+                        self.queue_played.remove(&file);
+                        v.append(&mut playlist);
+                    }
+                } else {
+                    playlist.shuffle(&mut thread_rng());
+                    self.queue_played.remove(&file);
+                    Arc::make_mut(&mut self.playlists).insert(file.clone(), playlist);
+                }
+                self.switch_playlist(file);
+            }
+            Err(e) => error!("{}", e),
+        }
+    }
+
+    /// Stop the current track and switch to the queue of the playlist at `path`
+    fn open_playlist(&mut self, path: PathBuf) {
+        if self.playlists.get(&path).is_some_and(Vec::is_empty) {
+            // played through, start over from the file
+            self.stop();
+            self.file_dropped(path);
+            return;
+        }
+        if path != self.path {
+            self.stop();
+            self.switch_playlist(path);
+        }
+    }
+
+    /// Drop the queue of the playlist at `path` from the sidebar and the saved state
+    fn forget_playlist(&mut self, path: &Path) {
+        if path == self.path {
+            self.stop();
+        }
+        info!("Forgetting playlist {:?}", path);
+        Arc::make_mut(&mut self.playlists).remove(path);
+        self.queue_played.remove(path);
+        if let Some(undo) = self.undo.as_mut() {
+            undo.queues.remove(path);
+        }
+    }
+
+    /// Continue with the queue of the playlist at `path`
+    fn switch_playlist(&mut self, path: PathBuf) {
+        self.path = path;
+        // reset current_file to not remove this file from playback
+        self.current_file = String::new();
+        self.starting = false;
+        self.play_next();
+    }
+
+    fn trash_file(&mut self) {
+        if !self.current_file.is_empty() {
+            match trash::delete(&self.current_file) {
+                Ok(_) => {
+                    info!("Trashed {}", self.current_file);
+                    let track = self.current_file.clone();
+                    self.emit(Event::Trashed, &track);
+                }
+                Err(e) => error!("Can't trash file {}: {}", self.current_file, e),
+            }
+        }
+    }
+}
+
+/// Artist and title from the tags of `track`, its path while unknown
+fn track_label(metadata: &HashMap<String, TrackInfo>, track: &str) -> String {
+    metadata
+        .get(track)
+        .and_then(TrackInfo::label)
+        .unwrap_or_else(|| track.to_string())
+}
+
+/// Whole wheel steps of `delta` horizontally and vertically, touchpad pixels are collected in `partial`
+fn wheel_steps(delta: ScrollDelta, partial: &mut (f32, f32)) -> (i32, i32) {
+    let (x, y) = match delta {
+        ScrollDelta::Lines { x, y } => (x, y),
+        ScrollDelta::Pixels { x, y } => (x / WHEEL_STEP_PIXELS, y / WHEEL_STEP_PIXELS),
+    };
+    partial.0 += x;
+    partial.1 += y;
+    let steps = (partial.0.trunc(), partial.1.trunc());
+    partial.0 -= steps.0;
+    partial.1 -= steps.1;
+    (steps.0 as i32, steps.1 as i32)
+}
+
+fn default_ui_scale() -> u16 {
+    100
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    PlayNext,
+    PlayPrevious,
+    Restart,
+    Pause,
+    SliderChanged(u8),
+    Window(iced_native::Event),
+    Tick,
+    /// Startup work left for after the first frame
+    DeferredInit,
+    /// Startup work done on a worker
+    DeferredLoaded(Box<Deferred>),
+    /// Swipe on the current track or the queue in the touch layout
+    Swiped(Swipe),
+    /// Mouse wheel over the current track or the volume
+    WheelScrolled(ScrollDelta),
+    /// Audio formats and whether they can be played here
+    FormatsProbed(Vec<(&'static str, bool)>),
+    /// Show or hide the favorite lists
+    FavoritePicker,
+    /// Add or remove the current track from the named list
+    FavoriteToggled(String, bool),
+    NewListNameChanged(String),
+    /// Create a list with the current track
+    AddFavoriteList,
+    /// Note of the current track edited
+    NoteChanged(String),
+    ExportNotes,
+    /// Tag name to filter the queue by, or the entry for all tracks
+    TagFilterSelected(String),
+    ExportTags,
+    /// Write the play history for importing into Last.fm
+    ExportScrobbles,
+    /// File the play history was written to
+    ScrobblesExported(std::result::Result<PathBuf, String>),
+    ScrobbleFormatSelected(ScrobbleFormat),
+    ExportFavorites,
+    SaveConfig,
+    ArchivePathChanged(String),
+    PortableFolderChanged(String),
+    PortableCopyToggled(bool),
+    PortableProfileSelected(portable::Profile),
+    ExportPortable,
+    SendFolderChanged(String),
+    SendSourceSelected(SendSource),
+    SendTranscodeToggled(bool),
+    SendToDevice,
+    FindDevices,
+    DeviceSelected(mtp::Storage),
+    CancelSend,
+    LoadCd,
+    RipDriveSelected(cd::Drive),
+    /// TOC and names of the disc in the drive, read on a worker
+    CdRead(
+        cd::Drive,
+        std::result::Result<(cd::Toc, Option<cd::Disc>), String>,
+    ),
+    RipTrackToggled(usize, bool),
+    RipFolderChanged(String),
+    RipFormatSelected(rip::RipFormat),
+    RipVerifyToggled(bool),
+    RipCd,
+    CancelRip,
+    SnapshotNameChanged(String),
+    SaveSnapshot,
+    SnapshotSelected(String),
+    RestoreSnapshot,
+    DeleteSnapshot,
+    ExportArchive,
+    ImportArchive,
+    PlaylistCharsetSelected(PlaylistCharset),
+    /// Import the iTunes library XML at the archive path
+    ImportItunes,
+    ItunesImported(std::result::Result<ItunesImport, String>),
+    /// Import the newer archive found in the sync folder
+    ImportSynced,
+    DismissSynced,
+    DiffAChanged(String),
+    DiffBChanged(String),
+    /// Compare playlist A with B or the favorites
+    CompareLists,
+    ExportOnlyA,
+    ExportOnlyB,
+    ExportMerged,
+    TrashFile,
+    Stop,
+    /// Ask for confirmation to clear the queue
+    ClearQueue,
+    /// Open the queue in its own window
+    PopOutQueue,
+    ClearQueueConfirmed,
+    ClearQueueCancelled,
+    ToggleSkipped,
+    /// Show track in the file manager
+    Reveal(String),
+    CopyPath(String),
+    FilterChanged(String),
+    MoveUp(usize),
+    /// Click on an entry of a list, with the held modifiers deciding how the selection changes
+    SelectTrack(SelectionList, usize),
+    ClearSelection,
+    Bulk(BulkAction),
+    MoveTargetChanged(String),
+    /// Move instead of copy tracks to another playlist
+    MoveTracksToggled(bool),
+    /// Revert the last bulk action
+    Undo,
+    /// Unmark broken track at queue position
+    Retry(usize),
+    KeepBrokenToggled(bool),
+    RelocateRootChanged(String),
+    /// Search missing tracks below the relocation root
+    Relocate,
+    /// New paths of missing tracks by old path, or why the scan failed
+    Relocated(std::result::Result<HashMap<String, String>, String>),
+    ZoneVolumeChanged(usize, u8),
+    ContentFilterToggled(bool),
+    FilterPasswordChanged(String),
+    /// Set the entered password or remove it, if it is the current one
+    SetFilterPassword,
+    /// Stop the ringing alarm and ring again later
+    SnoozeAlarm,
+    DismissAlarm,
+    AlarmWeekdayToggled(usize, Weekday, bool),
+    BufferFramesSelected(BufferFrames),
+    FollowDefaultToggled(bool),
+    RatingSelected(Rating),
+    AddCue,
+    JumpToCue(usize),
+    JumpToChapter(usize),
+    RemoveCue(usize),
+    CueFormatSelected(CueFormat),
+    ExportCues,
+    /// Skip the intro up to the current position, for the whole album if true
+    SetIntro(bool),
+    ClearIntro,
+    QueueEndSelected(QueueEnd),
+    /// Keep running after the queue ran out
+    CancelQuit,
+    ScanSelected(Scan),
+    PreampChanged(i16),
+    UiScaleChanged(u16),
+    GamepadToggled(bool),
+    /// Assign the next pressed gamepad button to the action
+    LearnGamepadButton(ButtonAction),
+    ResetGamepadMapping,
+    TouchModeSelected(TouchMode),
+    /// Seek bar dragged to position in seconds
+    SeekPreview(u32),
+    SeekReleased,
+    GotoChanged(String),
+    GotoSubmitted,
+    ToggleTimeDisplay,
+    LanguageSelected(Language),
+    ThemeSelected(Theme),
+    TextScaleChanged(u16),
+    ChannelMappingChanged(ChannelMapping),
+    EqPresetSelected(String),
+    VolumeOffsetChanged(i16),
+    PluginToggled(&'static str, bool),
+    OpenSource(usize),
+    BrowseEntry(usize),
+    BrowseBack,
+    /// Entries listed by a track source for a browser request
+    Listed(u64, std::result::Result<Vec<plugin::Entry>, String>),
+    /// Remote playlist fetched by URL, or why not
+    RemotePlaylistFetched(String, std::result::Result<String, String>),
+    CloseBrowser,
+    OpenAlbums,
+    CloseAlbums,
+    AlbumPage(usize),
+    /// Enqueue the album at this grid index
+    EnqueueAlbum(usize),
+    OpenArtist(String),
+    /// Start a radio of tracks similar to the current one
+    StartRadio,
+    StopRadio,
+    CloseArtist,
+    /// Enqueue all tracks of the shown artist, shuffled if set
+    PlayArtist(bool),
+    EnqueueArtistAlbum(usize),
+    EnqueueTopTrack(usize),
+    OpenSearch,
+    CloseSearch,
+    SearchTextChanged(String),
+    GenreSelected(Choice<String>),
+    DecadeSelected(Choice<Decade>),
+    FormatSelected(Choice<String>),
+    /// Enqueue all tracks found by the library search
+    EnqueueResults,
+    OpenHome,
+    CloseHome,
+    OpenPlaylist(PathBuf),
+    /// Remove the playlist from the sidebar, its queue is lost
+    ForgetPlaylist(PathBuf),
+    MovePlaylist(PathBuf, FolderChoice),
+    ToggleFolder(String),
+    RemoveFolder(String),
+    NewFolderChanged(String),
+    AddFolder,
+    OpenPin(Pin),
+    TogglePin(Pin),
+    EnqueueMix(usize),
+    OpenRecent,
+    CloseRecent,
+    RecentDays(Days),
+    EnqueueRecent(usize),
+}
+
+/// Write `tracks` as playlist, returns the result to show
+fn export_list(tracks: &[String], path: &str) -> String {
+    match playlist::write_playlist(tracks.iter(), "Audio-Wrench Comparison", path) {
+        Ok(_) => tr!("list-exported", path = path),
+        Err(e) => format!("{}", e),
+    }
+}
+
+/// Run `job` on its own thread, for slow work started by `update` through `Command::perform`
+async fn on_worker<T: Send + 'static>(job: impl FnOnce() -> T + Send + 'static) -> Option<T> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    thread::spawn(move || {
+        let _ = tx.send(job());
+    });
+    rx.await.ok()
+}
+
+/// Drop progress and level updates superseded by a later one of the same kind
+fn coalesce(statuses: Vec<PlayerStatus>) -> Vec<PlayerStatus> {
+    let last_progress = statuses
+        .iter()
+        .rposition(|v| matches!(v, PlayerStatus::Progress(_)));
+    let last_levels = statuses
+        .iter()
+        .rposition(|v| matches!(v, PlayerStatus::Levels(_)));
+    statuses
+        .into_iter()
+        .enumerate()
+        .filter(|(i, v)| match v {
+            PlayerStatus::Progress(_) => Some(*i) == last_progress,
+            PlayerStatus::Levels(_) => Some(*i) == last_levels,
+            _ => true,
+        })
+        .map(|(_, v)| v)
+        .collect()
+}
+
+/// Format as mm:ss.mmm or hh:mm:ss.mmm
+fn format_precise(duration: Option<Duration>) -> String {
+    match duration {
+        None => String::from("--:--.---"),
+        Some(v) => format!("{}.{:03}", format_duration(Some(v)), v.subsec_millis()),
+    }
+}
+
+/// Sample offset of `position` at `rate`
+fn sample_offset(position: Duration, rate: u32) -> u64 {
+    (position.as_nanos() * rate as u128 / 1_000_000_000) as u64
+}
+
+/// Parse ss, mm:ss or hh:mm:ss
+fn parse_timestamp(text: &str) -> Option<Duration> {
+    let parts: Vec<&str> = text.trim().split(':').collect();
+    if parts.len() > 3 {
+        return None;
+    }
+    let mut secs: u64 = 0;
+    for (i, part) in parts.iter().enumerate() {
+        let value: u64 = part.parse().ok()?;
+        // only the leading part may exceed a minute or hour
+        if i > 0 && value >= 60 {
+            return None;
+        }
+        secs = secs.checked_mul(60)?.checked_add(value)?;
+    }
+    Some(Duration::from_secs(secs))
+}
+
+/// Named chapter or cue point closest to `target`
+fn nearest_cue<'a>(cues: impl Iterator<Item = &'a Cue>, target: Duration) -> Option<&'a Cue> {
+    cues.filter(|v| !v.label.is_empty())
+        .min_by_key(|v| match v.position > target {
+            true => v.position - target,
+            false => target - v.position,
+        })
+}
+
+/// Format as mm:ss or hh:mm:ss, placeholder for unknown durations
+fn format_duration(duration: Option<Duration>) -> String {
+    match duration {
+        None => String::from("--:--"),
+        Some(v) => {
+            let secs = v.as_secs();
+            match secs >= 3600 {
+                true => format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60),
+                false => format!("{:02}:{:02}", secs / 60, secs % 60),
+            }
+        }
+    }
+}
+
+/// Window title while minimized, like "▶ Title — 02:10/04:30"
+fn compact_title(
+    title: &str,
+    paused: bool,
+    playtime: Option<Duration>,
+    length: Option<Duration>,
+) -> String {
+    format!(
+        "{} {} — {}/{}",
+        if paused { "⏸" } else { "▶" },
+        title,
+        format_duration(playtime),
+        format_duration(length)
+    )
+}
+
+/// Output buffer size offered in the diagnostics row, None is the host default
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferFrames(Option<u32>);
+
+const BUFFER_CHOICES: [BufferFrames; 7] = [
+    BufferFrames(None),
+    BufferFrames(Some(256)),
+    BufferFrames(Some(512)),
+    BufferFrames(Some(1024)),
+    BufferFrames(Some(2048)),
+    BufferFrames(Some(4096)),
+    BufferFrames(Some(8192)),
+];
+
+impl std::fmt::Display for BufferFrames {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&match self.0 {
+            None => tr!("buffer-default"),
+            Some(frames) => tr!("buffer-frames", frames = frames),
+        })
+    }
+}
+
+/// Short localized name of `day`
+fn weekday_name(day: Weekday) -> String {
+    match day {
+        Weekday::Mon => tr!("weekday-mon"),
+        Weekday::Tue => tr!("weekday-tue"),
+        Weekday::Wed => tr!("weekday-wed"),
+        Weekday::Thu => tr!("weekday-thu"),
+        Weekday::Fri => tr!("weekday-fri"),
+        Weekday::Sat => tr!("weekday-sat"),
+        Weekday::Sun => tr!("weekday-sun"),
+    }
+}
+
+/// Alarm started by [`alarm::Alarm::rings_between`]
+struct Ringing {
+    /// Index in the configured alarms
+    alarm: usize,
+    started: Instant,
+    /// Volume is still rising, stopped by changing it manually
+    fading: bool,
+}
+
+/// Whether a track is kept out of playback by the content filter `matcher`
+pub(crate) fn content_blocker<'a>(
+    matcher: Option<&'a Matcher>,
+    metadata: &'a HashMap<String, TrackInfo>,
+    track_tags: &'a HashMap<String, BTreeSet<String>>,
+) -> impl Fn(&str) -> bool + 'a {
+    move |track| {
+        matcher.is_some_and(|m| m.blocks(track, metadata.get(track), track_tags.get(track)))
+    }
+}
+
+/// Wait before quitting once the queue ran out, if set to quit
+const QUIT_COUNTDOWN: Duration = Duration::from_secs(30);
+/// Tracks appended per refill of endless play
+const ENDLESS_TRACKS: usize = 5;
+/// Default excerpt length of scan mode
+const SCAN_LENGTH: Duration = Duration::from_secs(15);
+
+/// Pick tracks from the library to refill the queue, `last` is the track that just played
+fn endless_tracks(
+    strategy: FillStrategy,
+    metadata: &HashMap<String, TrackInfo>,
+    store: Option<&Store>,
+    playable: impl Fn(&str) -> bool,
+    last: Option<&str>,
+) -> Vec<String> {
+    let last_played = match store.map(Store::plays) {
+        Some(Ok(v)) => v.into_iter().map(|(t, p)| (t, p.last_played)).collect(),
+        Some(Err(e)) => {
+            error!("Can't load play history: {}", e);
+            HashMap::new()
+        }
+        None => HashMap::new(),
+    };
+    let tracks = metadata.iter().filter(|(t, _)| playable(t));
+    library::fill(
+        strategy,
+        tracks,
+        last,
+        &last_played,
+        ENDLESS_TRACKS,
+        &mut thread_rng(),
+    )
+}
+
+/// What happens once the queue ran out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum QueueEnd {
+    /// Stop playing, the playlist starts over when opened again
+    #[default]
+    Stop,
+    /// Start the playlist over, shuffled again
+    Repeat,
+    /// Endless play from the library
+    Fill(FillStrategy),
+    /// Quit after [`QUIT_COUNTDOWN`], unless cancelled
+    Quit,
+}
+
+impl QueueEnd {
+    const CHOICES: [QueueEnd; 6] = [
+        QueueEnd::Stop,
+        QueueEnd::Repeat,
+        QueueEnd::Fill(FillStrategy::Random),
+        QueueEnd::Fill(FillStrategy::LeastRecentlyPlayed),
+        QueueEnd::Fill(FillStrategy::SameGenre),
+        QueueEnd::Quit,
+    ];
+
+    fn fill(self) -> Option<FillStrategy> {
+        match self {
+            QueueEnd::Fill(strategy) => Some(strategy),
+            _ => None,
+        }
+    }
+
+    /// Refill the ran out `queue`, `last` is the track that just played.
+    /// Repeating needs a track of the queue to have `started`, so an unplayable playlist doesn't loop.
+    fn refill(
+        self,
+        queue: &mut Vec<String>,
+        source: &QueueSource<'_>,
+        playable: impl Fn(&str) -> bool,
+        last: Option<&str>,
+        started: bool,
+    ) {
+        match self {
+            QueueEnd::Fill(strategy) => {
+                queue.extend(endless_tracks(
+                    strategy,
+                    source.metadata,
+                    source.store,
+                    playable,
+                    last,
+                ));
+                debug!("Endless play added {} tracks", queue.len());
+            }
+            QueueEnd::Repeat if started => {
+                match playlist::read_playlist(source.path, source.charset) {
+                    Ok(mut tracks) => {
+                        tracks.shuffle(&mut thread_rng());
+                        debug!("Repeating playlist with {} tracks", tracks.len());
+                        queue.append(&mut tracks);
+                    }
+                    Err(e) => warn!("Can't repeat playlist: {:?}", e),
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// What a ran out queue is refilled from, see [QueueEnd::refill]
+struct QueueSource<'a> {
+    /// Playlist of the queue, read again to repeat it
+    path: &'a Path,
+    charset: Option<Charset>,
+    /// Library for endless play
+    metadata: &'a HashMap<String, TrackInfo>,
+    store: Option<&'a Store>,
+}
+
+impl std::fmt::Display for QueueEnd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&match self {
+            QueueEnd::Stop => tr!("endless-off"),
+            QueueEnd::Repeat => tr!("queue-end-repeat"),
+            QueueEnd::Fill(FillStrategy::Random) => tr!("endless-random"),
+            QueueEnd::Fill(FillStrategy::LeastRecentlyPlayed) => tr!("endless-least-played"),
+            QueueEnd::Fill(FillStrategy::SameGenre) => tr!("endless-same-genre"),
+            QueueEnd::Quit => tr!("queue-end-quit"),
+        })
+    }
+}
+
+/// Part of each track played in scan mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanFrom {
+    Start,
+    Middle,
+}
+
+/// Scan mode offered in the picker, None is off
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scan(Option<ScanFrom>);
+
+impl Scan {
+    const CHOICES: [Scan; 3] = [
+        Scan(None),
+        Scan(Some(ScanFrom::Start)),
+        Scan(Some(ScanFrom::Middle)),
+    ];
+}
+
+impl std::fmt::Display for Scan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&match self.0 {
+            None => tr!("scan-off"),
+            Some(ScanFrom::Start) => tr!("scan-start"),
+            Some(ScanFrom::Middle) => tr!("scan-middle"),
+        })
+    }
+}
+
+/// Playlist encoding offered in the picker, None detects it per file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlaylistCharset(Option<Charset>);
+
+impl PlaylistCharset {
+    fn choices() -> Vec<PlaylistCharset> {
+        std::iter::once(PlaylistCharset(None))
+            .chain(Charset::ALL.iter().map(|v| PlaylistCharset(Some(*v))))
+            .collect()
+    }
+}
+
+impl std::fmt::Display for PlaylistCharset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            None => f.write_str(&tr!("charset-detect")),
+            Some(v) => v.fmt(f),
+        }
+    }
+}
+
+/// Rating of the current track offered in the picker, None is unrated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rating(Option<u8>);
+
+impl Rating {
+    fn choices() -> Vec<Rating> {
+        std::iter::once(Rating(None))
+            .chain((1..=store::MAX_RATING).map(|v| Rating(Some(v))))
+            .collect()
+    }
+}
+
+impl std::fmt::Display for Rating {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            None => f.write_str(&tr!("unrated")),
+            Some(v) => f.write_str(&"★".repeat(v as usize)),
+        }
+    }
+}
+
+/// What the time display shows, toggled by clicking it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+enum TimeDisplay {
+    #[default]
+    Elapsed,
+    Remaining,
+    Both,
+    /// Elapsed with milliseconds, reported more often
+    Precise,
+}
+
+impl TimeDisplay {
+    fn next(self) -> Self {
+        match self {
+            TimeDisplay::Elapsed => TimeDisplay::Remaining,
+            TimeDisplay::Remaining => TimeDisplay::Both,
+            TimeDisplay::Both => TimeDisplay::Precise,
+            TimeDisplay::Precise => TimeDisplay::Elapsed,
+        }
+    }
+
+    fn format(self, playtime: Option<Duration>, length: Option<Duration>) -> String {
+        let remaining = match (playtime, length) {
+            (Some(playtime), Some(length)) => {
+                format!(
+                    "-{}",
+                    format_duration(Some(length.saturating_sub(playtime)))
+                )
+            }
+            _ => String::from("--:--"),
+        };
+        match self {
+            TimeDisplay::Elapsed => {
+                format!("{}/{}", format_duration(playtime), format_duration(length))
+            }
+            TimeDisplay::Remaining => format!("{}/{}", remaining, format_duration(length)),
+            TimeDisplay::Both => format!("{} {}", format_duration(playtime), remaining),
+            TimeDisplay::Precise => {
+                format!("{}/{}", format_precise(playtime), format_duration(length))
+            }
+        }
+    }
+}
+
+/// Map linear level to meter range of -60..=0 dBFS
+fn meter_scale(level: f32) -> f32 {
+    ((dsp::to_db(level) + 60.0) / 60.0).clamp(0.0, 1.0)
+}
+
+/// Config file, temp specifies if a .bak version should be used
+fn config_path(temp: bool) -> PathBuf {
+    let mut file = data_local_dir().unwrap();
+    match temp {
+        true => file.push("audio_wrench.json.bak"),
+        false => file.push("audio_wrench.json"),
+    }
+    file
+}
+
+fn journal_path() -> PathBuf {
+    let mut file = data_local_dir().unwrap();
+    file.push("audio_wrench.journal");
+    file
+}
+
+fn cover_cache_path() -> PathBuf {
+    let mut folder = data_local_dir().unwrap();
+    folder.push("audio_wrench_covers");
+    folder
+}
+
+/// Folder imported iTunes playlists are written to
+/// Move file `from` to `to`, copying it if they're on different file systems
+fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    match std::fs::rename(from, to) {
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => copy_and_remove(from, to),
+        v => v,
+    }
+}
+
+/// Copy `from` to the new file `to` and remove `from`, the copy is removed again if either fails
+fn copy_and_remove(from: &Path, to: &Path) -> std::io::Result<()> {
+    let mut source = std::fs::File::open(from)?;
+    // never replaces an existing file
+    let mut target = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(to)?;
+    let result = std::io::copy(&mut source, &mut target)
+        .and_then(|_| target.set_permissions(source.metadata()?.permissions()))
+        .and_then(|_| target.sync_all())
+        .and_then(|_| std::fs::remove_file(from));
+    if result.is_err() {
+        let _ = std::fs::remove_file(to);
+    }
+    result
+}
+
+/// iTunes playlists merged into the queues and written on a worker
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItunesImport {
+    /// Merged queue by playlist file
+    queues: Vec<(PathBuf, Vec<String>)>,
+    /// Tracks of the library
+    tracks: Vec<String>,
+    /// Imported playlists
+    count: usize,
+}
+
+/// Read the iTunes library at `path` and store its ratings and play counts. Its playlists
+/// are merged into the queues of the same name in `playlists` and written to the imported
+/// playlist folder.
+fn import_itunes(path: &Path, playlists: &Playlists) -> Result<ItunesImport> {
+    let library = itunes::read(path)?;
+    if let Some(mut store) = open_store() {
+        store.import_stats(&library.tracks)?;
+    }
+    let folder = itunes_playlist_path();
+    std::fs::create_dir_all(&folder).wrap_err_with(|| format!("Can't create {:?}", folder))?;
+    let mut queues: Vec<(PathBuf, Vec<String>)> = Vec::new();
+    for list in &library.playlists {
+        let file = folder.join(itunes::playlist_file_name(&list.name));
+        // playlists of the same name end up in one queue
+        let index = match queues.iter().position(|(f, _)| *f == file) {
+            Some(v) => v,
+            None => {
+                let queue = playlists.get(&file).cloned().unwrap_or_default();
+                queues.push((file.clone(), queue));
+                queues.len() - 1
+            }
+        };
+        let queue = &mut queues[index].1;
+        for track in &list.tracks {
+            if !queue.contains(track) {
+                queue.push(track.clone());
+            }
+        }
+        playlist::write_playlist(queue.iter(), &list.name, &file.to_string_lossy())?;
+    }
+    Ok(ItunesImport {
+        queues,
+        tracks: library.tracks.into_iter().map(|t| t.location).collect(),
+        count: library.playlists.len(),
+    })
+}
+
+fn itunes_playlist_path() -> PathBuf {
+    let mut folder = data_local_dir().unwrap();
+    folder.push("audio_wrench_itunes");
+    folder
+}
+
+fn store_path() -> PathBuf {
+    let mut file = data_local_dir().unwrap();
+    file.push("audio_wrench.sqlite");
+    file
+}
+
+fn open_store() -> Option<Store> {
+    match Store::open(&store_path()) {
+        Ok(v) => Some(v),
+        Err(e) => {
+            error!("{:?}", e);
+            None
+        }
+    }
+}
+
+/// Results of the startup work done on a worker
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Deferred {
+    /// Loaded passwords by secret name
+    secrets: Vec<(String, Secret)>,
+    /// Secret name and error of passwords that couldn't be loaded or stored
+    secret_errors: Vec<(String, String)>,
+    sync_notice: Option<Archive>,
+}
+
+/// Stored config with journaled changes applied, defaults if missing or unreadable
+fn load_config() -> ConfigData<'static> {
+    let file = config_path(false);
+    let mut data: ConfigData = match file.is_file() {
+        false => Default::default(),
+        true => match std::fs::read_to_string(&file)
+            .map_err(Report::from)
+            .and_then(|v| serde_json::from_str(&v).map_err(Report::from))
+        {
+            Ok(v) => v,
+            Err(e) => {
+                error!("Unable to read config at {:?}: {}", file, e);
+                Default::default()
+            }
+        },
+    };
+    // queues and favorites of older versions or imports are still in the config and win
+    if let Some(store) = open_store() {
+        if data.playlists.is_empty() {
+            match store.playlists() {
+                Ok(v) => data.playlists = Arc::new(v),
+                Err(e) => error!("Can't load queues: {}", e),
+            }
+        }
+        if data.favorite_lists.is_empty() {
+            match store.favorites() {
+                Ok(v) => data.favorite_lists = Arc::new(v),
+                Err(e) => error!("Can't load favorites: {}", e),
+            }
+        }
+    }
+    for change in journal::read(&journal_path()) {
+        data.apply(change);
+    }
+    data
+}
+
+/// Write the full config, then remove the `rotated` journal whose changes it contains.
+/// Queues and favorites go to the database, the config only keeps them if that fails.
+fn save_compacted(data: &ConfigData, rotated: Option<PathBuf>) {
+    let stored = open_store().is_some_and(|mut store| {
+        match store
+            .set_playlists(&data.playlists)
+            .and_then(|_| store.set_favorites(&data.favorite_lists))
+        {
+            Ok(_) => true,
+            Err(e) => {
+                error!("Can't store queues and favorites: {}", e);
+                false
+            }
+        }
+    });
+    let serialized = serde_json::to_value(data).and_then(|mut v| {
+        if let (true, Some(map)) = (stored, v.as_object_mut()) {
+            map.remove("playlists");
+            map.remove("favorite_lists");
+        }
+        serde_json::to_string(&v)
+    });
+    match serialized {
+        Err(e) => warn!("Can't serialize data! {}", e),
+        Ok(v) => {
+            if let Some(file) = rotated.filter(|_| save_config(&v)) {
+                if let Err(e) = std::fs::remove_file(&file) {
+                    warn!("Can't remove journal {:?}: {}", file, e);
+                }
+            }
+        }
+    }
+}
+
+/// Write serialized config through a temporary file, false on failure
+fn save_config(data: &str) -> bool {
+    // saves run on separate threads, don't let them share the temporary file
+    static SAVING: Mutex<()> = Mutex::new(());
+    let _saving = SAVING.lock().unwrap_or_else(|e| e.into_inner());
+    let file = config_path(true);
+    match File::create(&file) {
+        Err(e) => warn!("Can't create config file {:?}: {}", file, e),
+        Ok(mut file) => match file.write_all(data.as_bytes()) {
+            Err(e) => warn!("Error writing config {}", e),
+            Ok(_) => match std::fs::rename(config_path(true), config_path(false)) {
+                Ok(_) => {
+                    info!("Config saved");
+                    return true;
+                }
+                Err(e) => error!("Can't move file over backup: {}", e),
+            },
+        },
+    }
+    false
+}
+
+impl PlaybackControl {
+    fn from_config(data: ConfigData<'static>) -> Self {
+        let queue_end = data.queue_end();
+        let routing = data.routing.into_owned();
+        let cd_lookups = cd::Lookups::default();
+        let plugins = Plugins::new(data.disabled_plugins.into_owned(), cd_lookups.clone());
+        let device_id = match data.device_id {
+            0 => rand::random(),
+            v => v,
+        };
+        let last_sync = data.last_sync;
+        let mut eq_presets = data.eq_presets.into_owned();
+        if eq_presets.is_empty() {
+            eq_presets = default_eq_presets();
+        }
+        let (tx, rx, child) =
+            player::Player::new(routing.clone()).expect("Can't start audio controller");
+        tx.send(PlayerCommand::Preamp(data.preamp))
+            .expect("Can't send playback command!");
+        let content_filter = data.content_filter.into_owned();
+        let content_matcher = content_filter.enabled.then(|| content_filter.matcher());
+        let dsp_chain = data.dsp_chain.into_owned();
+        tx.send(PlayerCommand::DspChain(dsp_chain.clone()))
+            .expect("Can't send playback command!");
+        timing::phase("player started");
+        i18n::set_language(data.language);
+        // also 0 without config file
+        let text_scale = match data.text_scale {
+            0 => default_ui_scale(),
+            v => v,
+        };
+        theme::set(data.theme, text_scale);
+        let ducking = data.ducking.into_owned();
+        let duck_monitor = match ducking.mode {
+            DuckMode::Off => None,
+            _ => ducking::Monitor::new(),
+        };
+        Self {
+            path: data.path,
+            play_next: Default::default(),
+            play_previous: Default::default(),
+            restart: Default::default(),
+            previous: Vec::new(),
+            up_next: Vec::new(),
+            from_up_next: false,
+            time_toggle: Default::default(),
+            time_display: data.time_display,
+            pause: Default::default(),
+            volume_input: Default::default(),
+            favorite: Default::default(),
+            trash_current: Default::default(),
+            stop: Default::default(),
+            reveal_current: Default::default(),
+            copy_current: Default::default(),
+            export_favorites: Default::default(),
+            volume: data.volume,
+            preamp_input: Default::default(),
+            // also 0 without config file
+            ui_scale: match data.ui_scale {
+                0 => default_ui_scale(),
+                v => v,
+            },
+            ui_scale_input: Default::default(),
+            touch_mode: data.touch_mode,
+            touch_pick: Default::default(),
+            touch: data.touch_mode.active(),
+            swipe_track: Default::default(),
+            swipe_queue: Default::default(),
+            language: data.language,
+            language_pick: Default::default(),
+            theme: data.theme,
+            theme_pick: Default::default(),
+            text_scale,
+            text_scale_input: Default::default(),
+            preamp: data.preamp,
+            clipped_at: None,
+            eq_presets,
+            dsp_chain,
+            hooks: data.hooks.into_owned(),
+            duck_monitor,
+            ducking,
+            ducked: false,
+            osd: data.osd,
+            osd_child: None,
+            osd_pending: false,
+            queue_window: None,
+            pop_out_queue: Default::default(),
+            now_playing: data.now_playing.map(Cow::into_owned),
+            plugin_buttons: vec![Default::default(); plugins.len()],
+            plugins,
+            subsonic: data.subsonic.map(Cow::into_owned),
+            jellyfin: data.jellyfin.map(Cow::into_owned),
+            webdav: data.webdav.map(Cow::into_owned),
+            credentials: Default::default(),
+            sync_folder: data.sync_folder,
+            favorites_export: data.favorites_export,
+            device_id,
+            last_sync,
+            sync_notice: None,
+            sync_import: Default::default(),
+            sync_dismiss: Default::default(),
+            config_replaced: false,
+            journal: match Journal::open(&journal_path()) {
+                Ok(v) => Some(v),
+                Err(e) => {
+                    warn!("{}, saving changes only with the full config", e);
+                    None
+                }
+            },
+            store: open_store(),
+            rating: None,
+            play_count: 0,
+            rating_pick: Default::default(),
+            cues: Vec::new(),
+            cue_buttons: Vec::new(),
+            add_cue: Default::default(),
+            chapters: Vec::new(),
+            chapter_buttons: Vec::new(),
+            cue_format: data.cue_format,
+            cue_format_pick: Default::default(),
+            playlist_charset: data.playlist_charset,
+            charset_pick: Default::default(),
+            export_cues: Default::default(),
+            cue_result: None,
+            intro: None,
+            set_intro: Default::default(),
+            set_album_intro: Default::default(),
+            clear_intro: Default::default(),
+            archive_path: String::new(),
+            archive_input: Default::default(),
+            export_button: Default::default(),
+            import_button: Default::default(),
+            import_itunes_button: Default::default(),
+            archive_result: None,
+            portable_folder: data
+                .portable_folder
+                .map(|v| v.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            portable_input: Default::default(),
+            portable_copy: data.portable_copy,
+            portable_profile: data.portable_profile,
+            portable_profile_pick: Default::default(),
+            export_portable: Default::default(),
+            portable_result: None,
+            snapshots: data.snapshots.into_owned(),
+            snapshot_name: String::new(),
+            snapshot_input: Default::default(),
+            save_snapshot: Default::default(),
+            snapshot_pick: Default::default(),
+            snapshot_selected: None,
+            restore_snapshot: Default::default(),
+            delete_snapshot: Default::default(),
+            diff_a: String::new(),
+            diff_b: String::new(),
+            diff_a_input: Default::default(),
+            diff_b_input: Default::default(),
+            diff_compare: Default::default(),
+            diff: None,
+            diff_export_a: Default::default(),
+            diff_export_b: Default::default(),
+            diff_merge: Default::default(),
+            diff_result: None,
+            browser: None,
+            albums: None,
+            open_albums: Default::default(),
+            artist_page: None,
+            artist_button: Default::default(),
+            search: None,
+            open_search: Default::default(),
+            home: None,
+            open_home: Default::default(),
+            recent: None,
+            open_recent: Default::default(),
+            focus: Default::default(),
+            playlist_settings: data.playlist_settings.into_owned(),
+            eq_pick: Default::default(),
+            volume_offset_input: Default::default(),
+            tx,
+            rx,
+            playlists: data.playlists,
+            current_playlist: data.current_playlist.into_owned(),
+            generation: 0,
+            starting: false,
+            current_file: Default::default(),
+            is_favorite: false,
+            is_paused: false,
+            favorites: Favorites::new(data.favorite_lists, data.favorites),
+            favorite_picker: false,
+            new_list_name: String::new(),
+            new_list_input: Default::default(),
+            new_list_add: Default::default(),
+            notes: data.notes,
+            note_input: Default::default(),
+            export_notes: Default::default(),
+            tags: match data.tags.is_empty() {
+                true => tags::default_tags(),
+                false => data.tags.into_owned(),
+            },
+            track_tags: data.track_tags,
+            library_folders: data.library_folders.into_owned(),
+            alarms: data.alarms.into_owned(),
+            quiet: data
+                .quiet_hours
+                .as_ref()
+                .is_some_and(|q| q.active(alarm::local_now().time())),
+            quiet_hours: data.quiet_hours,
+            content_filter,
+            device_volumes: data.device_volumes.into_owned(),
+            playlist_folders: data.playlist_folders.into_owned(),
+            sidebar: Default::default(),
+            pins: data.pins.into_owned(),
+            queue_played: data.queue_played.into_owned(),
+            pin_bar: Default::default(),
+            favorite_pins: Vec::new(),
+            output_device: None,
+            content_matcher,
+            filter_password: String::new(),
+            filter_password_input: Default::default(),
+            filter_locked: false,
+            set_filter_password: Default::default(),
+            alarms_checked: alarm::local_now(),
+            ringing: None,
+            snoozed: None,
+            snooze_button: Default::default(),
+            dismiss_button: Default::default(),
+            library_scanned: false,
+            tag_filter: None,
+            tag_pick: Default::default(),
+            export_tags: Default::default(),
+            export_scrobbles: Default::default(),
+            scrobble_format: Default::default(),
+            scrobble_result: None,
+            scrobble_format_pick: Default::default(),
+            filter: data.filter.into_owned(),
+            filter_input: Default::default(),
+            queue_up: vec![Default::default(); QUEUE_PREVIEW],
+            clear_queue: Default::default(),
+            skipped: Vec::new(),
+            show_skipped: false,
+            skipped_toggle: Default::default(),
+            relocate_root: String::new(),
+            relocate_input: Default::default(),
+            relocate_button: Default::default(),
+            relocate_result: None,
+            clear_confirm: None,
+            queue_actions: vec![Default::default(); QUEUE_PREVIEW],
+            queue_selection: Default::default(),
+            bulk_bar: Default::default(),
+            undo: None,
+            zone_volume_input: vec![Default::default(); routing.zones.len()],
+            output_error: None,
+            secret_errors: Vec::new(),
+            underruns: None,
+            formats: Vec::new(),
+            buffer_pick: Default::default(),
+            active_buffer_frames: routing.buffer_frames,
+            media_session: None,
+            grpc_listen: data.grpc_listen,
+            grpc_token: data.grpc_token,
+            voice_listen: data.voice_listen,
+            mqtt: data.mqtt.map(Cow::into_owned),
+            lastfm: data.lastfm.map(Cow::into_owned),
+            gamepad: data.gamepad.then(Gamepad::new),
+            gamepad_mapping: data
+                .gamepad_mapping
+                .map_or_else(gamepad::default_mapping, Cow::into_owned),
+            gamepad_settings: Default::default(),
+            lirc: data.lirc.as_deref().cloned().map(Lirc::new),
+            lirc_config: data.lirc.map(Cow::into_owned),
+            send: SendPanel::new(data.send_to_device.map(Cow::into_owned).unwrap_or_default()),
+            rip: RipPanel::new(
+                data.rip_cd.map(Cow::into_owned).unwrap_or_default(),
+                cd_lookups,
+            ),
+            radio: None,
+            radio_button: Default::default(),
+            control: None,
+            format: None,
+            levels: Default::default(),
+            routing,
+            length: None,
+            track_changed: false,
+            total_playtime: (Duration::ZERO, true),
+            durations: durations::Durations::new(),
+            metadata: HashMap::new(),
+            extractor: match metadata::Extractor::new() {
+                Ok(v) => Some(v),
+                Err(e) => {
+                    error!("{:?}", e);
+                    None
+                }
+            },
+            title_info: data.title_info,
+            keep_broken: data.keep_broken,
+            track_identity: data.track_identity,
+            queue_end,
+            queue_end_pick: Default::default(),
+            quit_at: None,
+            cancel_quit: Default::default(),
+            scan: None,
+            scan_pick: Default::default(),
+            scan_start: None,
+            hasher: None,
+            broken: data.broken,
+            progress_interval: data.progress_interval,
+            background_interval: data.background_interval,
+            scan_length: data.scan_length,
+            wheel_volume_step: data.wheel_volume_step,
+            wheel_seek_step: data.wheel_seek_step,
+            wheel_scrolled: (0.0, 0.0),
+            shift: false,
+            command: false,
+            cover_cache_size: data.cover_cache_size,
+            covers: None,
+            cover: None,
+            sent_interval: None,
+            minimized: false,
+            playtime: None,
+            seek_input: Default::default(),
+            seek_preview: None,
+            goto_input: Default::default(),
+            goto_text: String::new(),
+            goto_invalid: false,
+            child,
+        }
+    }
+}
+
+impl PlaybackControl {
+    /// Save everything and exit, iced has no way to close the window
+    fn quit(&mut self) -> ! {
+        info!("Quitting after the queue ended");
+        self.remember_position();
+        self.sync();
+        if let Some(save) = self.store_state() {
+            let _ = save.join();
+        }
+        std::process::exit(0);
+    }
+}
+
+impl Drop for PlaybackControl {
+    fn drop(&mut self) {
+        self.remember_position();
+        self.sync();
+        // finish writing before the process exits
+        if let Some(save) = self.store_state() {
+            let _ = save.join();
+        }
+    }
+}
+
+impl Application for PlaybackControl {
+    type Executor = executor::Default;
+    type Message = Message;
+    /// Config read while the window is set up
+    type Flags = std::thread::JoinHandle<ConfigData<'static>>;
+    fn view(&mut self) -> Element<Self::Message> {
+        timing::phase("first view");
+        self.focus.begin();
+        let plugins: Vec<_> = self.plugins.list().collect();
+        let has_source: Vec<bool> = (0..plugins.len())
+            .map(|i| self.plugins.has_source(i))
+            .collect();
+        let fav_text = match self.is_favorite {
+            true => tr!("favorited"),
+            false => tr!("favorite"),
+        };
+        let play_text = match self.current_file.is_empty() {
+            true => tr!("play"),
+            false => tr!("next"),
+        };
+        let pause_text = match self.is_paused {
+            true => tr!("resume"),
+            false => tr!("pause"),
+        };
+        let has_previous = self.head_started() || !self.previous.is_empty();
+        let position = queue::position(
+            self.queue_played
+                .get(&self.path)
+                .copied()
+                .unwrap_or_default(),
+            self.playlists.get(&self.path).map_or(0, Vec::len),
+        )
+        .filter(|_| !self.from_up_next);
+        let has_album = self
+            .metadata
+            .get(&self.current_file)
+            .is_some_and(|v| v.album.is_some());
+
+        let mut timer_text = self.time_display.format(self.playtime, self.length);
+        if self.time_display == TimeDisplay::Precise {
+            // sample offsets are only exact for uncompressed and lossless files
+            let lossless = Path::new(&self.current_file)
+                .extension()
+                .and_then(|v| v.to_str())
+                .is_some_and(|ext| {
+                    ext.eq_ignore_ascii_case("wav") || ext.eq_ignore_ascii_case("flac")
+                });
+            if let (Some(position), Some((rate, _, _)), true) =
+                (self.playtime, self.format, lossless)
+            {
+                timer_text.push_str(&format!(" @{}", sample_offset(position, rate)));
+            }
+        }
+        let mut seek_bar = Row::new().spacing(10).align_items(Align::Center);
+        if let Some(length) = self.length.filter(|_| !self.current_file.is_empty()) {
+            let position = self
+                .seek_preview
+                .or_else(|| self.playtime.map(|v| v.as_secs() as u32))
+                .unwrap_or(0);
+            seek_bar = seek_bar.push(
+                Slider::new(
+                    &mut self.seek_input,
+                    0..=length.as_secs() as u32,
+                    position,
+                    Message::SeekPreview,
+                )
+                .on_release(Message::SeekReleased),
+            );
+            seek_bar = seek_bar.push(
+                TextInput::new(
+                    &mut self.goto_input,
+                    &tr!("go-to-time"),
+                    &self.goto_text,
+                    Message::GotoChanged,
+                )
+                .width(Length::Units(80))
+                .padding(5)
+                .on_submit(Message::GotoSubmitted),
+            );
+            if self.goto_invalid {
+                seek_bar = seek_bar.push(Text::new(tr!("invalid-time")).size(theme::text_size(14)));
+            }
+            // no tooltips in iced, show the target next to the bar while dragging
+            if let Some(target) = self.seek_preview {
+                let target = Duration::from_secs(target as u64);
+                let mut preview = format_duration(Some(target));
+                if let Some(cue) = nearest_cue(self.chapters.iter().chain(&self.cues), target) {
+                    preview = format!("{} {}", preview, cue.label);
+                }
+                seek_bar = seek_bar.push(Text::new(preview).size(theme::text_size(16)));
+            }
+        }
+        let format_text = match self.format {
+            None => String::new(),
+            Some((source, output, channels)) if source != output => format!(
+                "{:.1} kHz -> {:.1} kHz, {} ch",
+                source as f32 / 1000.0,
+                output as f32 / 1000.0,
+                channels
+            ),
+            Some((source, _, channels)) => {
+                format!("{:.1} kHz, {} ch", source as f32 / 1000.0, channels)
+            }
+        };
+        let mut row_controls = Row::new().align_items(Align::Center).spacing(20);
+        if has_previous {
+            row_controls = row_controls.push(self.focus.button(
+                Button::new(
+                    &mut self.play_previous,
+                    Text::new(tr!("previous")).size(theme::text_size(20)),
+                ),
+                Message::PlayPrevious,
+            ));
+        }
+        row_controls = row_controls
+            .push(self.focus.button(
+                Button::new(
+                    &mut self.play_next,
+                    Text::new(play_text).size(theme::text_size(20)),
+                ),
+                Message::PlayNext,
+            ))
+            .push(self.focus.button(
+                Button::new(
+                    &mut self.pause,
+                    Text::new(pause_text).size(theme::text_size(20)),
+                ),
+                Message::Pause,
+            ));
+
+        if !self.current_file.is_empty() {
+            row_controls = row_controls
+                .push(self.focus.button(
+                    Button::new(
+                        &mut self.stop,
+                        Text::new(tr!("stop")).size(theme::text_size(20)),
+                    ),
+                    Message::Stop,
+                ))
+                .push(self.focus.button(
+                    Button::new(
+                        &mut self.restart,
+                        Text::new(tr!("restart")).size(theme::text_size(20)),
+                    ),
+                    Message::Restart,
+                ))
+                .push(self.focus.button(
+                    Button::new(
+                        &mut self.favorite,
+                        Text::new(fav_text).size(theme::text_size(20)),
+                    ),
+                    Message::FavoritePicker,
+                ))
+                .push(self.focus.button(
+                    Button::new(
+                        &mut self.trash_current,
+                        Text::new(tr!("trash-file")).size(theme::text_size(20)),
+                    ),
+                    Message::TrashFile,
+                ));
+            let mut reveal = Button::new(
+                &mut self.reveal_current,
+                Text::new(tr!("reveal")).size(theme::text_size(20)),
+            );
+            if Path::new(&self.current_file).is_file() {
+                reveal = self
+                    .focus
+                    .button(reveal, Message::Reveal(self.current_file.clone()));
+            }
+            row_controls = row_controls.push(reveal).push(self.focus.button(
+                Button::new(
+                    &mut self.copy_current,
+                    Text::new(tr!("copy-path")).size(theme::text_size(20)),
+                ),
+                Message::CopyPath(self.current_file.clone()),
+            ));
+        }
+        if let Some(position) = position {
+            row_controls = row_controls.push(
+                Text::new(tr!(
+                    "queue-position",
+                    track = position.track,
+                    total = position.total
+                ))
+                .size(theme::text_size(16)),
+            );
+        }
+
+        let mut note = Row::new();
+        if !self.current_file.is_empty() {
+            note = note.push(
+                TextInput::new(
+                    &mut self.note_input,
+                    &tr!("note"),
+                    self.notes
+                        .get(&self.current_file)
+                        .map_or("", |v| v.as_str()),
+                    Message::NoteChanged,
+                )
+                .padding(5),
+            );
+        }
+
+        let mut tag_row = Row::new().spacing(10).align_items(Align::Center);
+        if !self.current_file.is_empty() {
+            let assigned = self.track_tags.get(&self.current_file);
+            for (i, tag) in self.tags.iter().enumerate() {
+                let color = match assigned.is_some_and(|v| v.contains(&tag.name)) {
+                    true => tag.color,
+                    false => [0.6, 0.6, 0.6],
+                };
+                // number keys toggle the tags
+                let text = match i < 9 {
+                    true => format!("{} {}", i + 1, tag.name),
+                    false => tag.name.clone(),
+                };
+                tag_row = tag_row.push(
+                    Text::new(text)
+                        .size(theme::text_size(14))
+                        .color(theme::color(color)),
+                );
+            }
+        }
+
+        let mut cover = Row::new();
+        if let Some(handle) = self.cover.clone() {
+            cover = cover.push(
+                Image::new(handle)
+                    .width(Length::Units(covers::THUMBNAIL_SIZE as u16))
+                    .height(Length::Units(covers::THUMBNAIL_SIZE as u16)),
+            );
+        }
+
+        let mut stats_row = Row::new().spacing(10).align_items(Align::Center);
+        if !self.current_file.is_empty() && self.store.is_some() {
+            stats_row = stats_row
+                .push(PickList::new(
+                    &mut self.rating_pick,
+                    Rating::choices(),
+                    Some(Rating(self.rating)),
+                    Message::RatingSelected,
+                ))
+                .push(
+                    Text::new(tr!("play-count", count = self.play_count))
+                        .size(theme::text_size(14)),
+                )
+                .push(self.focus.button(
+                    Button::new(
+                        &mut self.add_cue,
+                        Text::new(tr!("add-cue")).size(theme::text_size(14)),
+                    ),
+                    Message::AddCue,
+                ))
+                .push(self.focus.button(
+                    Button::new(
+                        &mut self.set_intro,
+                        Text::new(tr!("skip-intro")).size(theme::text_size(14)),
+                    ),
+                    Message::SetIntro(false),
+                ));
+            if has_album {
+                stats_row = stats_row.push(self.focus.button(
+                    Button::new(
+                        &mut self.set_album_intro,
+                        Text::new(tr!("skip-album-intro")).size(theme::text_size(14)),
+                    ),
+                    Message::SetIntro(true),
+                ));
+            }
+            if let Some(intro) = self.intro {
+                stats_row = stats_row
+                    .push(
+                        Text::new(tr!(
+                            "intro-skipped",
+                            position = format_duration(Some(intro))
+                        ))
+                        .size(theme::text_size(14)),
+                    )
+                    .push(self.focus.button(
+                        Button::new(
+                            &mut self.clear_intro,
+                            Text::new(tr!("clear-intro")).size(theme::text_size(14)),
+                        ),
+                        Message::ClearIntro,
+                    ));
+            }
+        }
+
+        let mut chapter_row = Row::new().spacing(10).align_items(Align::Center);
+        if !self.current_file.is_empty() && !self.chapters.is_empty() {
+            chapter_row = chapter_row.push(Text::new(tr!("chapters")).size(theme::text_size(14)));
+            for (i, (chapter, jump)) in self
+                .chapters
+                .iter()
+                .zip(self.chapter_buttons.iter_mut())
+                .enumerate()
+            {
+                let label = format!(
+                    "{} {}",
+                    format_precise(Some(chapter.position)),
+                    chapter.label
+                );
+                chapter_row = chapter_row.push(self.focus.button(
+                    Button::new(jump, Text::new(label).size(theme::text_size(14))),
+                    Message::JumpToChapter(i),
+                ));
+            }
+        }
+
+        let mut cue_row = Row::new().spacing(10).align_items(Align::Center);
+        if !self.current_file.is_empty() && !self.cues.is_empty() {
+            for (i, (cue, (jump, remove))) in self
+                .cues
+                .iter()
+                .zip(self.cue_buttons.iter_mut())
+                .enumerate()
+            {
+                let label = format!("{} {}", format_precise(Some(cue.position)), cue.label);
+                cue_row = cue_row
+                    .push(self.focus.button(
+                        Button::new(jump, Text::new(label).size(theme::text_size(14))),
+                        Message::JumpToCue(i),
+                    ))
+                    .push(self.focus.button(
+                        Button::new(remove, Text::new("×").size(theme::text_size(14))),
+                        Message::RemoveCue(i),
+                    ));
+            }
+            cue_row = cue_row
+                .push(PickList::new(
+                    &mut self.cue_format_pick,
+                    &CueFormat::ALL[..],
+                    Some(self.cue_format),
+                    Message::CueFormatSelected,
+                ))
+                .push(self.focus.button(
+                    Button::new(
+                        &mut self.export_cues,
+                        Text::new(tr!("export-cues")).size(theme::text_size(14)),
+                    ),
+                    Message::ExportCues,
+                ));
+            if let Some(ref result) = self.cue_result {
+                cue_row = cue_row.push(Text::new(result.as_str()).size(theme::text_size(14)));
+            }
+        }
+
+        let mut favorite_picker = Row::new().spacing(10).align_items(Align::Center);
+        if self.favorite_picker && !self.current_file.is_empty() {
+            let containing = self.favorites.lists_containing(&self.current_file);
+            let names = self.favorites.names();
+            self.favorite_pins
+                .resize_with(names.len(), Default::default);
+            for (name, pin) in names.into_iter().zip(self.favorite_pins.iter_mut()) {
+                let list = name.clone();
+                let pin_label = match self.pins.contains(&Pin::Favorites(name.clone())) {
+                    true => tr!("unpin"),
+                    false => tr!("pin"),
+                };
+                favorite_picker = favorite_picker
+                    .push(Checkbox::new(
+                        containing.contains(name),
+                        name.as_str(),
+                        move |v| Message::FavoriteToggled(list.clone(), v),
+                    ))
+                    .push(self.focus.button(
+                        Button::new(pin, Text::new(pin_label).size(theme::text_size(12))),
+                        Message::TogglePin(Pin::Favorites(name.clone())),
+                    ));
+            }
+            favorite_picker = favorite_picker
+                .push(
+                    TextInput::new(
+                        &mut self.new_list_input,
+                        &tr!("new-list"),
+                        &self.new_list_name,
+                        Message::NewListNameChanged,
+                    )
+                    .padding(5),
+                )
+                .push(self.focus.button(
+                    Button::new(
+                        &mut self.new_list_add,
+                        Text::new(tr!("add")).size(theme::text_size(14)),
+                    ),
+                    Message::AddFavoriteList,
+                ));
+        }
+
+        // the playlist head is the current track unless it came from the session queue
+        let first = match (self.current_file.is_empty() && !self.starting) || self.from_up_next {
+            true => 0,
+            false => 1,
+        };
+        let mut queue_header = Row::new()
+            .spacing(10)
+            .align_items(Align::Center)
+            .push(Text::new(tr!("up-next")).size(theme::text_size(16)));
+        let session = &self.up_next[usize::from(self.from_up_next).min(self.up_next.len())..];
+        let upcoming = session.len()
+            + self
+                .playlists
+                .get(&self.path)
+                .map_or(0, |v| v.len().saturating_sub(first));
+        match self.clear_confirm {
+            Some((ref mut confirm, ref mut cancel)) => {
+                queue_header = queue_header
+                    .push(
+                        Text::new(tr!("clear-queue-confirm", count = upcoming))
+                            .size(theme::text_size(14)),
+                    )
+                    .push(self.focus.button(
+                        Button::new(
+                            confirm,
+                            Text::new(tr!("confirm")).size(theme::text_size(14)),
+                        ),
+                        Message::ClearQueueConfirmed,
+                    ))
+                    .push(self.focus.button(
+                        Button::new(cancel, Text::new(tr!("cancel")).size(theme::text_size(14))),
+                        Message::ClearQueueCancelled,
+                    ));
+            }
+            None => {
+                let mut clear = Button::new(
+                    &mut self.clear_queue,
+                    Text::new(tr!("clear-queue")).size(theme::text_size(14)),
+                );
+                if upcoming > 0 {
+                    clear = self.focus.button(clear, Message::ClearQueue);
+                }
+                queue_header = queue_header.push(clear);
+            }
+        }
+        let mut pop_out = Button::new(
+            &mut self.pop_out_queue,
+            Text::new(tr!("pop-out-queue")).size(theme::text_size(14)),
+        );
+        if self.queue_window.is_none() {
+            pop_out = self.focus.button(pop_out, Message::PopOutQueue);
+        }
+        queue_header = queue_header.push(pop_out);
+        let all_tags = tr!("all-tags");
+        let mut tag_names = vec![all_tags.clone()];
+        tag_names.extend(self.tags.iter().map(|t| t.name.clone()));
+        queue_header = queue_header
+            .push(PickList::new(
+                &mut self.tag_pick,
+                tag_names,
+                Some(self.tag_filter.clone().unwrap_or(all_tags)),
+                Message::TagFilterSelected,
+            ))
+            .push(PickList::new(
+                &mut self.queue_end_pick,
+                &QueueEnd::CHOICES[..],
+                Some(self.queue_end),
+                Message::QueueEndSelected,
+            ))
+            .push(PickList::new(
+                &mut self.scan_pick,
+                &Scan::CHOICES[..],
+                Some(Scan(self.scan)),
+                Message::ScanSelected,
+            ));
+        let (selected, selection_list) = match self.search.as_ref() {
+            Some(search) if !search.selection.is_empty() => {
+                (search.selection.len(), SelectionList::Search)
+            }
+            _ => (self.queue_selection.len(), SelectionList::Queue),
+        };
+        let current_path = &self.path;
+        let mut playlists: Vec<PlaylistChoice> = self
+            .playlists
+            .keys()
+            .filter(|p| *p != current_path)
+            .cloned()
+            .map(PlaylistChoice)
+            .collect();
+        playlists.sort();
+        let targets = Targets {
+            favorites: self.favorites.names().into_iter().cloned().collect(),
+            tags: self.tags.iter().map(|t| t.name.clone()).collect(),
+            playlists,
+        };
+        let mut queue = Column::new()
+            .spacing(5)
+            .push(queue_header)
+            .push(self.bulk_bar.view(
+                selected,
+                selection_list,
+                targets,
+                self.undo.is_some(),
+                &mut self.focus,
+            ));
+        let track_tags = &self.track_tags;
+        let tag_list = &self.tags;
+        let tag_filter = self.tag_filter.as_ref();
+        let metadata = &self.metadata;
+        let blocked = content_blocker(self.content_matcher.as_ref(), metadata, track_tags);
+        if !session.is_empty() {
+            queue = queue.push(Text::new(tr!("session-queue")).size(theme::text_size(14)));
+            for track in session.iter().take(QUEUE_PREVIEW) {
+                queue = queue.push(
+                    Text::new(format!("• {}", track_label(metadata, track)))
+                        .size(theme::text_size(14)),
+                );
+            }
+            if session.len() > QUEUE_PREVIEW {
+                queue = queue.push(
+                    Text::new(tr!("more-tracks", count = session.len() - QUEUE_PREVIEW))
+                        .size(theme::text_size(14)),
+                );
+            }
+        }
+        if let Some(v) = self.playlists.get(&self.path) {
+            for (((pos, track), state), (reveal_state, copy_state, select_state)) in v
+                .iter()
+                .enumerate()
+                .skip(first)
+                .filter(|(_, t)| {
+                    tag_filter.is_none_or(|tag| track_tags.get(*t).is_some_and(|v| v.contains(tag)))
+                })
+                .zip(self.queue_up.iter_mut())
+                .zip(self.queue_actions.iter_mut())
+            {
+                let broken = self.broken.contains(track);
+                let up = match broken {
+                    true => self.focus.button(
+                        Button::new(state, Text::new(tr!("retry")).size(theme::text_size(14))),
+                        Message::Retry(pos),
+                    ),
+                    false => {
+                        let up = Button::new(
+                            state,
+                            Text::new(tr!("move-up")).size(theme::text_size(14)),
+                        );
+                        match pos > first {
+                            true => self.focus.button(up, Message::MoveUp(pos)),
+                            false => up,
+                        }
+                    }
+                };
+                // no tooltips in iced, notes are shown next to the track
+                let mut label = match self.notes.get(track) {
+                    Some(note) => format!("{} - {}", track_label(metadata, track), note),
+                    None => track_label(metadata, track),
+                };
+                if self.queue_selection.contains(pos) {
+                    label = format!("✓ {}", label);
+                }
+                let mut label = Text::new(label).size(theme::text_size(14));
+                let tag = track_tags
+                    .get(track)
+                    .and_then(|assigned| tag_list.iter().find(|t| assigned.contains(&t.name)));
+                if broken || blocked(track) {
+                    label = label.color(theme::color([0.5, 0.5, 0.5]));
+                } else if let Some(tag) = tag {
+                    label = label.color(theme::color(tag.color));
+                }
+                let mut reveal = Button::new(
+                    reveal_state,
+                    Text::new(tr!("reveal")).size(theme::text_size(14)),
+                );
+                if Path::new(track).is_file() {
+                    reveal = self.focus.button(reveal, Message::Reveal(track.clone()));
+                }
+                let copy = self.focus.button(
+                    Button::new(
+                        copy_state,
+                        Text::new(tr!("copy")).size(theme::text_size(14)),
+                    ),
+                    Message::CopyPath(track.clone()),
+                );
+                queue = queue.push(
+                    Row::new()
+                        .spacing(10)
+                        .align_items(Align::Center)
+                        .push(up)
+                        .push(reveal)
+                        .push(copy)
+                        .push(self.focus.button(
+                            Button::new(select_state, label),
+                            Message::SelectTrack(SelectionList::Queue, pos),
+                        )),
+                );
+            }
+        }
+
+        let mut meter = Column::new().spacing(2);
+        for (side, name) in ["L", "R"].iter().enumerate() {
+            meter = meter.push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(Text::new(*name).size(theme::text_size(14)))
+                    .push(
+                        ProgressBar::new(0.0..=1.0, meter_scale(self.levels.rms[side]))
+                            .height(Length::Units(10)),
+                    )
+                    .push(
+                        Text::new(format!("{:.0} dB", dsp::to_db(self.levels.peak[side])))
+                            .size(theme::text_size(14))
+                            .width(Length::Units(50)),
+                    ),
+            );
+        }
+
+        let clipping = self.clipped_at.is_some_and(|v| v.elapsed() < CLIP_HOLD);
+        let preamp = Row::new()
+            .spacing(10)
+            .align_items(Align::Center)
+            .push(
+                Text::new(tr!("preamp", gain = format!("{:+}", self.preamp)))
+                    .size(theme::text_size(16)),
+            )
+            .push(Slider::new(
+                &mut self.preamp_input,
+                dsp::PREAMP_RANGE,
+                self.preamp,
+                Message::PreampChanged,
+            ))
+            .push(
+                Text::new(tr!("clip"))
+                    .size(theme::text_size(16))
+                    .color(match clipping {
+                        true => theme::color([0.9, 0.0, 0.0]),
+                        false => theme::color([0.8, 0.8, 0.8]),
+                    }),
+            );
+
+        let mapping = self.routing.channels;
+        let channel_mapping = Row::new()
+            .spacing(10)
+            .push(Checkbox::new(
+                mapping.mono_to_stereo,
+                tr!("mono-to-stereo"),
+                move |v| {
+                    Message::ChannelMappingChanged(ChannelMapping {
+                        mono_to_stereo: v,
+                        ..mapping
+                    })
+                },
+            ))
+            .push(Checkbox::new(
+                mapping.swap,
+                tr!("swap-channels"),
+                move |v| Message::ChannelMappingChanged(ChannelMapping { swap: v, ..mapping }),
+            ))
+            .push(Checkbox::new(mapping.downmix, tr!("downmix"), move |v| {
+                Message::ChannelMappingChanged(ChannelMapping {
+                    downmix: v,
+                    ..mapping
+                })
+            }));
+
+        let mut diagnostics = Row::new()
+            .spacing(10)
+            .align_items(Align::Center)
+            .push(Text::new(tr!("output-buffer")).size(theme::text_size(14)))
+            .push(PickList::new(
+                &mut self.buffer_pick,
+                &BUFFER_CHOICES[..],
+                Some(BufferFrames(self.routing.buffer_frames)),
+                Message::BufferFramesSelected,
+            ));
+        if self.routing.device.is_none() {
+            diagnostics = diagnostics.push(Checkbox::new(
+                self.routing.follow_default,
+                tr!("follow-default-device"),
+                Message::FollowDefaultToggled,
+            ));
+        }
+        if let (Some(frames), Some((_, rate, _))) = (self.active_buffer_frames, self.format) {
+            let ms = frames as f32 * 1000.0 / rate as f32;
+            diagnostics = diagnostics.push(
+                Text::new(tr!("buffer-latency", ms = format!("{:.1}", ms)))
+                    .size(theme::text_size(14)),
+            );
+        }
+        if self.routing.buffer_frames != self.active_buffer_frames {
+            diagnostics =
+                diagnostics.push(Text::new(tr!("buffer-restart")).size(theme::text_size(14)));
+        }
+        diagnostics = diagnostics.push(
+            Text::new(match self.underruns {
+                Some(count) => tr!("underruns", count = count),
+                None => tr!("underruns-unknown"),
+            })
+            .size(theme::text_size(14))
+            .color(match self.underruns {
+                Some(count) if count > 0 => theme::color([0.9, 0.5, 0.0]),
+                _ => theme::color([0.5, 0.5, 0.5]),
+            }),
+        );
+
+        let mut formats = Row::new()
+            .spacing(10)
+            .align_items(Align::Center)
+            .push(Text::new(tr!("formats")).size(theme::text_size(14)));
+        for (name, available) in self.formats.iter() {
+            formats = formats.push(match available {
+                true => Text::new(*name).size(theme::text_size(14)),
+                false => Text::new(tr!("format-needs-ffmpeg", format = *name))
+                    .size(theme::text_size(14))
+                    .color(theme::color([0.5, 0.5, 0.5])),
+            });
+        }
+
+        let mut playlist_row = Row::new().spacing(10).align_items(Align::Center);
+        if self.playlists.contains_key(&self.path) {
+            let settings = self.playlist_settings.get(&self.path);
+            let mut presets: Vec<String> = self.eq_presets.keys().cloned().collect();
+            presets.sort();
+            let no_eq = tr!("no-eq");
+            presets.insert(0, no_eq.clone());
+            let selected = settings.and_then(|s| s.eq.clone()).unwrap_or(no_eq);
+            let offset = settings.map_or(0, |s| s.volume_offset);
+            playlist_row = playlist_row
+                .push(Text::new(tr!("playlist-eq")).size(theme::text_size(16)))
+                .push(PickList::new(
+                    &mut self.eq_pick,
+                    presets,
+                    Some(selected),
+                    Message::EqPresetSelected,
+                ))
+                .push(
+                    Text::new(tr!("volume-offset", offset = format!("{:+}", offset)))
+                        .size(theme::text_size(16)),
+                )
+                .push(Slider::new(
+                    &mut self.volume_offset_input,
+                    -50..=50,
+                    offset,
+                    Message::VolumeOffsetChanged,
+                ));
+        }
+
+        let mut skipped = Column::new().spacing(5).push(Checkbox::new(
+            self.keep_broken,
+            tr!("keep-broken"),
+            Message::KeepBrokenToggled,
+        ));
+        if !self.skipped.is_empty() {
+            skipped = skipped.push(
+                self.focus.button(
+                    Button::new(
+                        &mut self.skipped_toggle,
+                        Text::new(tr!("skipped-files", count = self.skipped.len()))
+                            .size(theme::text_size(16)),
+                    ),
+                    Message::ToggleSkipped,
+                ),
+            );
+            if self.show_skipped {
+                for (track, reason) in self.skipped.iter() {
+                    skipped = skipped.push(
+                        Text::new(format!("{}: {}", track, reason))
+                            .size(theme::text_size(14))
+                            .color(theme::color([0.8, 0.0, 0.0])),
+                    );
+                }
+            }
+        }
+
+        let mut quit_notice = Row::new().spacing(10).align_items(Align::Center);
+        if let Some(at) = self.quit_at {
+            let secs = at.saturating_duration_since(Instant::now()).as_secs();
+            quit_notice = quit_notice
+                .push(Text::new(tr!("quitting", seconds = secs)).size(theme::text_size(16)))
+                .push(self.focus.button(
+                    Button::new(
+                        &mut self.cancel_quit,
+                        Text::new(tr!("cancel")).size(theme::text_size(14)),
+                    ),
+                    Message::CancelQuit,
+                ));
+        }
+
+        let mut settings_archive = Column::new().spacing(5);
+        if self.sync_notice.is_some() {
+            settings_archive = settings_archive.push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(Text::new(tr!("sync-newer")).size(theme::text_size(14)))
+                    .push(self.focus.button(
+                        Button::new(
+                            &mut self.sync_import,
+                            Text::new(tr!("import")).size(theme::text_size(14)),
+                        ),
+                        Message::ImportSynced,
+                    ))
+                    .push(self.focus.button(
+                        Button::new(
+                            &mut self.sync_dismiss,
+                            Text::new(tr!("dismiss")).size(theme::text_size(14)),
+                        ),
+                        Message::DismissSynced,
+                    )),
+            );
+        }
+        let mut archive_row = Row::new()
+            .spacing(10)
+            .align_items(Align::Center)
+            .push(
+                TextInput::new(
+                    &mut self.archive_input,
+                    &tr!("archive-path"),
+                    &self.archive_path,
+                    Message::ArchivePathChanged,
+                )
+                .padding(5),
+            )
+            .push(self.focus.button(
+                Button::new(
+                    &mut self.export_button,
+                    Text::new(tr!("export")).size(theme::text_size(14)),
+                ),
+                Message::ExportArchive,
+            ))
+            .push(self.focus.button(
+                Button::new(
+                    &mut self.import_button,
+                    Text::new(tr!("import")).size(theme::text_size(14)),
+                ),
+                Message::ImportArchive,
+            ))
+            .push(self.focus.button(
+                Button::new(
+                    &mut self.import_itunes_button,
+                    Text::new(tr!("import-itunes")).size(theme::text_size(14)),
+                ),
+                Message::ImportItunes,
+            ));
+        if let Some(ref result) = self.archive_result {
+            archive_row = archive_row.push(Text::new(result.as_str()).size(theme::text_size(14)));
+        }
+        let mut portable_row = Row::new()
+            .spacing(10)
+            .align_items(Align::Center)
+            .push(
+                TextInput::new(
+                    &mut self.portable_input,
+                    &tr!("portable-folder"),
+                    &self.portable_folder,
+                    Message::PortableFolderChanged,
+                )
+                .padding(5),
+            )
+            .push(Checkbox::new(
+                self.portable_copy,
+                tr!("portable-copy"),
+                Message::PortableCopyToggled,
+            ))
+            .push(
+                PickList::new(
+                    &mut self.portable_profile_pick,
+                    &portable::Profile::ALL[..],
+                    Some(self.portable_profile),
+                    Message::PortableProfileSelected,
+                )
+                .text_size(theme::text_size(14)),
+            )
+            .push(self.focus.button(
+                Button::new(
+                    &mut self.export_portable,
+                    Text::new(tr!("export-portable")).size(theme::text_size(14)),
+                ),
+                Message::ExportPortable,
+            ));
+        if let Some(ref result) = self.portable_result {
+            portable_row = portable_row.push(Text::new(result.as_str()).size(theme::text_size(14)));
+        }
+        settings_archive = settings_archive
+            .push(archive_row)
+            .push(portable_row)
+            .push(self.send.view(&mut self.focus))
+            .push(self.rip.view(&mut self.focus))
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(Text::new(tr!("playlist-charset")).size(theme::text_size(14)))
+                    .push(
+                        PickList::new(
+                            &mut self.charset_pick,
+                            PlaylistCharset::choices(),
+                            Some(PlaylistCharset(self.playlist_charset)),
+                            Message::PlaylistCharsetSelected,
+                        )
+                        .text_size(theme::text_size(14)),
+                    ),
+            );
+
+        let mut snapshot_row = Row::new()
+            .spacing(10)
+            .align_items(Align::Center)
+            .push(
+                TextInput::new(
+                    &mut self.snapshot_input,
+                    &tr!("session-name"),
+                    &self.snapshot_name,
+                    Message::SnapshotNameChanged,
+                )
+                .padding(5)
+                .on_submit(Message::SaveSnapshot),
+            )
+            .push(self.focus.button(
+                Button::new(
+                    &mut self.save_snapshot,
+                    Text::new(tr!("save-session")).size(theme::text_size(14)),
+                ),
+                Message::SaveSnapshot,
+            ));
+        if !self.snapshots.is_empty() {
+            let mut names: Vec<String> = self.snapshots.keys().cloned().collect();
+            names.sort();
+            snapshot_row = snapshot_row
+                .push(PickList::new(
+                    &mut self.snapshot_pick,
+                    names,
+                    self.snapshot_selected.clone(),
+                    Message::SnapshotSelected,
+                ))
+                .push(self.focus.button(
+                    Button::new(
+                        &mut self.restore_snapshot,
+                        Text::new(tr!("restore-session")).size(theme::text_size(14)),
+                    ),
+                    Message::RestoreSnapshot,
+                ))
+                .push(self.focus.button(
+                    Button::new(
+                        &mut self.delete_snapshot,
+                        Text::new(tr!("delete-session")).size(theme::text_size(14)),
+                    ),
+                    Message::DeleteSnapshot,
+                ));
+        }
+
+        let mut comparison = Column::new().spacing(5).push(
+            Row::new()
+                .spacing(10)
+                .align_items(Align::Center)
+                .push(
+                    TextInput::new(
+                        &mut self.diff_a_input,
+                        &tr!("compare-a"),
+                        &self.diff_a,
+                        Message::DiffAChanged,
+                    )
+                    .padding(5),
+                )
+                .push(
+                    TextInput::new(
+                        &mut self.diff_b_input,
+                        &tr!("compare-b"),
+                        &self.diff_b,
+                        Message::DiffBChanged,
+                    )
+                    .padding(5),
+                )
+                .push(self.focus.button(
+                    Button::new(
+                        &mut self.diff_compare,
+                        Text::new(tr!("compare")).size(theme::text_size(14)),
+                    ),
+                    Message::CompareLists,
+                )),
+        );
+        if let Some((_, ref diff)) = self.diff {
+            let sections = [
+                (tr!("only-a", count = diff.only_a.len()), &diff.only_a),
+                (tr!("only-b", count = diff.only_b.len()), &diff.only_b),
+                (tr!("shared", count = diff.shared.len()), &diff.shared),
+            ];
+            for (title, tracks) in sections.iter() {
+                comparison = comparison.push(Text::new(title.as_str()).size(theme::text_size(14)));
+                for track in tracks.iter().take(DIFF_PREVIEW) {
+                    comparison =
+                        comparison.push(Text::new(track.as_str()).size(theme::text_size(12)));
+                }
+            }
+            comparison = comparison.push(
+                Row::new()
+                    .spacing(10)
+                    .push(self.focus.button(
+                        Button::new(
+                            &mut self.diff_export_a,
+                            Text::new(tr!("export-only-a")).size(theme::text_size(14)),
+                        ),
+                        Message::ExportOnlyA,
+                    ))
+                    .push(self.focus.button(
+                        Button::new(
+                            &mut self.diff_export_b,
+                            Text::new(tr!("export-only-b")).size(theme::text_size(14)),
+                        ),
+                        Message::ExportOnlyB,
+                    ))
+                    .push(self.focus.button(
+                        Button::new(
+                            &mut self.diff_merge,
+                            Text::new(tr!("export-merged")).size(theme::text_size(14)),
+                        ),
+                        Message::ExportMerged,
+                    )),
+            );
+        }
+        if let Some(ref result) = self.diff_result {
+            comparison = comparison.push(Text::new(result.as_str()).size(theme::text_size(14)));
+        }
+
+        let mut relocation = Row::new()
+            .spacing(10)
+            .align_items(Align::Center)
+            .push(
+                TextInput::new(
+                    &mut self.relocate_input,
+                    &tr!("relocate-root"),
+                    &self.relocate_root,
+                    Message::RelocateRootChanged,
+                )
+                .padding(5),
+            )
+            .push(self.focus.button(
+                Button::new(
+                    &mut self.relocate_button,
+                    Text::new(tr!("relocate")).size(theme::text_size(14)),
+                ),
+                Message::Relocate,
+            ));
+        if let Some(ref result) = self.relocate_result {
+            relocation = relocation.push(Text::new(result.as_str()).size(theme::text_size(14)));
+        }
+
+        let mut plugin_list = Column::new()
+            .spacing(5)
+            .push(Text::new(tr!("plugins")).size(theme::text_size(16)));
+        for (i, ((name, enabled), state)) in plugins
+            .into_iter()
+            .zip(self.plugin_buttons.iter_mut())
+            .enumerate()
+        {
+            let mut row = Row::new()
+                .spacing(10)
+                .align_items(Align::Center)
+                .push(Checkbox::new(enabled, name, move |v| {
+                    Message::PluginToggled(name, v)
+                }));
+            if has_source[i] {
+                row = row.push(self.focus.button(
+                    Button::new(state, Text::new(tr!("browse")).size(theme::text_size(14))),
+                    Message::OpenSource(i),
+                ));
+            }
+            plugin_list = plugin_list.push(row);
+        }
+        for panel in self.plugins.panels() {
+            let mut column = Column::new()
+                .spacing(2)
+                .push(Text::new(panel.title).size(theme::text_size(16)));
+            for line in panel.lines {
+                column = column.push(Text::new(line).size(theme::text_size(14)));
+            }
+            plugin_list = plugin_list.push(column);
+        }
+        if let Some(ref mut browser) = self.browser {
+            plugin_list = plugin_list.push(browser.view(&mut self.focus));
+        }
+        let gamepad_settings = self.gamepad_settings.view(
+            self.gamepad.is_some(),
+            &self.gamepad_mapping,
+            &mut self.focus,
+        );
+
+        let mut library_buttons = Row::new().spacing(10);
+        if self.home.is_none() {
+            library_buttons = library_buttons.push(self.focus.button(
+                Button::new(
+                    &mut self.open_home,
+                    Text::new(tr!("mixes")).size(theme::text_size(14)),
+                ),
+                Message::OpenHome,
+            ));
+        }
+        if self.albums.is_none() {
+            library_buttons = library_buttons.push(self.focus.button(
+                Button::new(
+                    &mut self.open_albums,
+                    Text::new(tr!("albums")).size(theme::text_size(14)),
+                ),
+                Message::OpenAlbums,
+            ));
+        }
+        if self.search.is_none() {
+            library_buttons = library_buttons.push(self.focus.button(
+                Button::new(
+                    &mut self.open_search,
+                    Text::new(tr!("search-library")).size(theme::text_size(14)),
+                ),
+                Message::OpenSearch,
+            ));
+        }
+        if self.recent.is_none() {
+            library_buttons = library_buttons.push(self.focus.button(
+                Button::new(
+                    &mut self.open_recent,
+                    Text::new(tr!("recently-added")).size(theme::text_size(14)),
+                ),
+                Message::OpenRecent,
+            ));
+        }
+        let mut album_view = Column::new().spacing(10).push(library_buttons);
+        if let Some(ref mut home) = self.home {
+            album_view = album_view.push(home.view(&mut self.focus));
+        }
+        if let Some(ref mut recent) = self.recent {
+            album_view = album_view.push(recent.view(&mut self.focus));
+        }
+        if let Some(ref mut grid) = self.albums {
+            album_view = album_view.push(grid.view(&mut self.focus));
+        }
+        if let Some(ref mut search) = self.search {
+            album_view = album_view.push(search.view(&mut self.focus));
+        }
+        if let Some(ref mut page) = self.artist_page {
+            album_view = album_view.push(page.view(&mut self.focus));
+        }
 
-        let length_text = match self.length {
-            None => String::from("--:--"),
-            Some(v) => {
-                let secs_total = v.as_secs();
-                let minutes = secs_total / 60;
-                format!("{:02}:{:02}", minutes, secs_total - (minutes * 60))
+        // clicking the artist opens their page
+        let mut now_playing_artist = Row::new().spacing(10).align_items(Align::Center);
+        if let Some(name) = self
+            .metadata
+            .get(&self.current_file)
+            .and_then(|v| v.artist.clone())
+        {
+            now_playing_artist = now_playing_artist.push(self.focus.button(
+                Button::new(
+                    &mut self.artist_button,
+                    Text::new(name.as_str()).size(theme::text_size(16)),
+                ),
+                Message::OpenArtist(name),
+            ));
+        }
+        match self.radio {
+            Some(ref radio) => {
+                let seed = track_label(&self.metadata, radio.seed());
+                now_playing_artist = now_playing_artist
+                    .push(Text::new(tr!("radio-from", track = seed)).size(theme::text_size(14)))
+                    .push(self.focus.button(
+                        Button::new(
+                            &mut self.radio_button,
+                            Text::new(tr!("stop-radio")).size(theme::text_size(14)),
+                        ),
+                        Message::StopRadio,
+                    ));
             }
-        };
-        let playtime_text = match self.playtime {
-            None => String::from("--:--"),
-            Some(v) => {
-                let secs_total = v.as_secs();
-                let minutes = secs_total / 60;
-                format!("{:02}:{:02}", minutes, secs_total - (minutes * 60))
+            None if !self.current_file.is_empty() => {
+                now_playing_artist = now_playing_artist.push(self.focus.button(
+                    Button::new(
+                        &mut self.radio_button,
+                        Text::new(tr!("start-radio")).size(theme::text_size(14)),
+                    ),
+                    Message::StartRadio,
+                ));
             }
-        };
-        let timer_text = format!("{}/{}", playtime_text, length_text);
-        let mut row_controls = Row::new()
+            None => (),
+        }
+
+        let mut quiet_badge = Row::new();
+        let quiet_active = self.quiet;
+        if let Some(quiet) = self.quiet_hours.as_ref().filter(|_| quiet_active) {
+            quiet_badge = quiet_badge.push(
+                Text::new(tr!("quiet-hours", volume = quiet.max_volume))
+                    .size(theme::text_size(14))
+                    .color(theme::color([0.0, 0.3, 0.8])),
+            );
+        }
+
+        let mut alarm_banner = Row::new().spacing(10).align_items(Align::Center);
+        if self.ringing.is_some() || self.snoozed.is_some() {
+            let text = match self.ringing {
+                Some(_) => tr!("alarm-ringing"),
+                None => tr!("alarm-snoozed"),
+            };
+            alarm_banner = alarm_banner.push(Text::new(text).size(theme::text_size(20)));
+            if self.ringing.is_some() {
+                alarm_banner = alarm_banner.push(self.focus.button(
+                    Button::new(
+                        &mut self.snooze_button,
+                        Text::new(tr!("snooze")).size(theme::text_size(16)),
+                    ),
+                    Message::SnoozeAlarm,
+                ));
+            }
+            alarm_banner = alarm_banner.push(self.focus.button(
+                Button::new(
+                    &mut self.dismiss_button,
+                    Text::new(tr!("dismiss-alarm")).size(theme::text_size(16)),
+                ),
+                Message::DismissAlarm,
+            ));
+        }
+
+        let mut alarm_list = Column::new().spacing(5);
+        if !self.alarms.is_empty() {
+            alarm_list = alarm_list.push(Text::new(tr!("alarms")).size(theme::text_size(16)));
+        }
+        for (i, alarm) in self.alarms.iter().enumerate() {
+            let name = alarm
+                .playlist
+                .file_stem()
+                .map(|v| v.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let mut row = Row::new().spacing(10).align_items(Align::Center).push(
+                Text::new(format!("{} {}", alarm.time.format("%H:%M"), name))
+                    .size(theme::text_size(14)),
+            );
+            for day in alarm::WEEKDAYS.iter().copied() {
+                row = row.push(Checkbox::new(
+                    alarm.weekdays.contains(&day),
+                    weekday_name(day),
+                    move |v| Message::AlarmWeekdayToggled(i, day, v),
+                ));
+            }
+            alarm_list = alarm_list.push(row);
+        }
+
+        let mut content_filter = Row::new()
+            .spacing(10)
             .align_items(Align::Center)
-            .spacing(20)
+            .push(Checkbox::new(
+                self.content_filter.enabled,
+                tr!("content-filter"),
+                Message::ContentFilterToggled,
+            ))
             .push(
-                Button::new(&mut self.play_next, Text::new(play_text)).on_press(Message::PlayNext),
-            )
-            .push(Button::new(&mut self.pause, Text::new(pause_text)).on_press(Message::Pause));
+                TextInput::new(
+                    &mut self.filter_password_input,
+                    &tr!("filter-password"),
+                    &self.filter_password,
+                    Message::FilterPasswordChanged,
+                )
+                .password()
+                .padding(5),
+            );
+        let password_label = match self.content_filter.password {
+            Some(_) => tr!("remove-password"),
+            None => tr!("set-password"),
+        };
+        let mut set_password = Button::new(
+            &mut self.set_filter_password,
+            Text::new(password_label).size(theme::text_size(14)),
+        );
+        if !self.filter_password.is_empty() {
+            set_password = self.focus.button(set_password, Message::SetFilterPassword);
+        }
+        content_filter = content_filter.push(set_password);
+        if self.filter_locked {
+            content_filter = content_filter.push(
+                Text::new(tr!("wrong-password"))
+                    .size(theme::text_size(14))
+                    .color(theme::color([0.8, 0.0, 0.0])),
+            );
+        }
 
-        if !self.current_file.is_empty() {
-            row_controls = row_controls
+        let mut zones = Column::new().spacing(5);
+        for (i, (zone, state)) in self
+            .routing
+            .zones
+            .iter()
+            .zip(self.zone_volume_input.iter_mut())
+            .enumerate()
+        {
+            zones = zones
                 .push(
-                    Button::new(&mut self.favorite, Text::new(fav_text))
-                        .on_press(Message::ToggleFavorite),
+                    Text::new(tr!(
+                        "zone-volume",
+                        device = zone.device.as_str(),
+                        volume = zone.volume
+                    ))
+                    .size(theme::text_size(16)),
                 )
-                .push(
-                    Button::new(&mut self.trash_current, Text::new("Trash File"))
-                        .on_press(Message::TrashFile),
-                );
+                .push(Slider::new(state, 0..=100, zone.volume, move |v| {
+                    Message::ZoneVolumeChanged(i, v)
+                }));
         }
 
-        Column::new()
+        let mut main = Column::new()
             .max_width(800)
             .spacing(20)
             .align_items(Align::Center)
+            .push(self.pin_bar.view(&self.pins, &mut self.focus))
+            .push(alarm_banner)
+            .push(WheelArea::new(
+                SwipeArea::new(
+                    &mut self.swipe_track,
+                    Column::new()
+                        .spacing(20)
+                        .align_items(Align::Center)
+                        .push(
+                            Text::new(&self.current_playlist.to_string())
+                                .size(theme::text_size(20))
+                                .width(Length::Fill)
+                                .horizontal_alignment(HorizontalAlignment::Center),
+                        )
+                        .push(
+                            Text::new(&self.current_file.to_string())
+                                .size(theme::text_size(20))
+                                .width(Length::Fill)
+                                .horizontal_alignment(HorizontalAlignment::Center),
+                        )
+                        .push(now_playing_artist)
+                        .push(cover),
+                    Message::Swiped,
+                ),
+                Message::WheelScrolled,
+            ))
+            .push(note)
+            .push(tag_row)
+            .push(stats_row)
+            .push(chapter_row)
+            .push(cue_row)
+            .push(row_controls)
+            .push(favorite_picker)
             .push(
-                Text::new(&self.current_playlist.to_string())
-                    .size(20)
-                    .width(Length::Fill)
-                    .horizontal_alignment(HorizontalAlignment::Center),
+                self.focus.button(
+                    Button::new(
+                        &mut self.time_toggle,
+                        Text::new(timer_text)
+                            .size(theme::text_size(20))
+                            .width(Length::Fill)
+                            .horizontal_alignment(HorizontalAlignment::Center),
+                    )
+                    .width(Length::Fill),
+                    Message::ToggleTimeDisplay,
+                ),
             )
+            .push(seek_bar)
+            .push(meter)
             .push(
-                Text::new(&self.current_file.to_string())
-                    .size(20)
+                Text::new(format_text)
+                    .size(theme::text_size(14))
                     .width(Length::Fill)
                     .horizontal_alignment(HorizontalAlignment::Center),
             )
-            .push(row_controls)
+            .push(WheelArea::new(
+                Column::new()
+                    .width(Length::Fill)
+                    .spacing(20)
+                    .align_items(Align::Center)
+                    .push(
+                        Text::new(tr!("volume", volume = self.volume))
+                            .size(theme::text_size(20))
+                            .width(Length::Fill)
+                            .horizontal_alignment(HorizontalAlignment::Center),
+                    )
+                    .push(quiet_badge)
+                    // TODO: use https://crates.io/crates/iced_audio control elements
+                    .push(Slider::new(
+                        &mut self.volume_input,
+                        0..=100,
+                        self.volume,
+                        Message::SliderChanged,
+                    )),
+                Message::WheelScrolled,
+            ))
+            .push(preamp)
+            .push(playlist_row)
+            .push(channel_mapping)
+            .push(zones)
+            .push(diagnostics)
+            .push(formats)
+            .push(quit_notice)
             .push(
-                Text::new(timer_text)
-                    .size(20)
+                Text::new(self.output_error.as_deref().unwrap_or_default())
+                    .size(theme::text_size(16))
+                    .color(theme::color([0.8, 0.0, 0.0]))
                     .width(Length::Fill)
                     .horizontal_alignment(HorizontalAlignment::Center),
             )
             .push(
-                Text::new(format!("{}% Volume", self.volume))
-                    .size(20)
+                Text::new(self.secret_errors.join("\n"))
+                    .size(theme::text_size(16))
+                    .color(theme::color([0.8, 0.0, 0.0]))
                     .width(Length::Fill)
                     .horizontal_alignment(HorizontalAlignment::Center),
             )
-            // TODO: use https://crates.io/crates/iced_audio control elements
-            .push(Slider::new(
-                &mut self.volume_input,
-                0..=100,
-                self.volume,
-                Message::SliderChanged,
+            .push(
+                TextInput::new(
+                    &mut self.filter_input,
+                    &tr!("filter-tracks"),
+                    &self.filter,
+                    Message::FilterChanged,
+                )
+                .padding(5),
+            )
+            .push(SwipeArea::new(
+                &mut self.swipe_queue,
+                queue,
+                Message::Swiped,
             ))
+            .push(skipped)
+            .push(relocation)
+            .push(comparison)
+            .push(snapshot_row)
+            .push(settings_archive)
+            .push(album_view)
+            .push(alarm_list)
+            .push(content_filter)
+            .push(plugin_list)
+            .push(gamepad_settings)
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(
+                        Text::new(tr!("ui-scale", scale = self.ui_scale))
+                            .size(theme::text_size(16)),
+                    )
+                    .push(
+                        Slider::new(
+                            &mut self.ui_scale_input,
+                            UI_SCALE_RANGE,
+                            self.ui_scale,
+                            Message::UiScaleChanged,
+                        )
+                        .step(10),
+                    )
+                    .push(Text::new(tr!("touch-mode")).size(theme::text_size(16)))
+                    .push(
+                        PickList::new(
+                            &mut self.touch_pick,
+                            &TouchMode::ALL[..],
+                            Some(self.touch_mode),
+                            Message::TouchModeSelected,
+                        )
+                        .text_size(theme::text_size(16)),
+                    ),
+            )
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(Text::new(tr!("language")).size(theme::text_size(16)))
+                    .push(
+                        PickList::new(
+                            &mut self.language_pick,
+                            &Language::ALL[..],
+                            Some(self.language),
+                            Message::LanguageSelected,
+                        )
+                        .text_size(theme::text_size(16)),
+                    ),
+            )
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(Text::new(tr!("theme")).size(theme::text_size(16)))
+                    .push(
+                        PickList::new(
+                            &mut self.theme_pick,
+                            &Theme::ALL[..],
+                            Some(self.theme),
+                            Message::ThemeSelected,
+                        )
+                        .text_size(theme::text_size(16)),
+                    )
+                    .push(
+                        Text::new(tr!("text-size", scale = self.text_scale))
+                            .size(theme::text_size(16)),
+                    )
+                    .push(
+                        Slider::new(
+                            &mut self.text_scale_input,
+                            TEXT_SCALE_RANGE,
+                            self.text_scale,
+                            Message::TextScaleChanged,
+                        )
+                        .step(10),
+                    ),
+            )
             .padding(20)
             .push(
-                Text::new("Drop a playlist file to start (.m3u/.pls/.xspf/.asx)")
-                    .size(20)
+                Text::new(tr!("drop-hint"))
+                    .size(theme::text_size(20))
                     .width(Length::Fill)
                     .horizontal_alignment(HorizontalAlignment::Center),
             )
             .padding(20)
             .push(
-                Button::new(&mut self.export_favorites, Text::new("Export Favorites"))
-                    .on_press(Message::ExportFavorites),
-            )
+                Row::new()
+                    .spacing(20)
+                    .push(self.focus.button(
+                        Button::new(
+                            &mut self.export_favorites,
+                            Text::new(tr!("export-favorites")).size(theme::text_size(20)),
+                        ),
+                        Message::ExportFavorites,
+                    ))
+                    .push(self.focus.button(
+                        Button::new(
+                            &mut self.export_notes,
+                            Text::new(tr!("export-notes")).size(theme::text_size(20)),
+                        ),
+                        Message::ExportNotes,
+                    ))
+                    .push(self.focus.button(
+                        Button::new(
+                            &mut self.export_tags,
+                            Text::new(tr!("export-tags")).size(theme::text_size(20)),
+                        ),
+                        Message::ExportTags,
+                    ))
+                    .push(self.focus.button(
+                        Button::new(
+                            &mut self.export_scrobbles,
+                            Text::new(tr!("export-scrobbles")).size(theme::text_size(20)),
+                        ),
+                        Message::ExportScrobbles,
+                    ))
+                    .push(
+                        PickList::new(
+                            &mut self.scrobble_format_pick,
+                            &ScrobbleFormat::ALL[..],
+                            Some(self.scrobble_format),
+                            Message::ScrobbleFormatSelected,
+                        )
+                        .text_size(theme::text_size(20)),
+                    ),
+            );
+        if let Some(ref result) = self.scrobble_result {
+            main = main.push(Text::new(result.as_str()).size(theme::text_size(14)));
+        }
+        if self.playlists.is_empty() && self.playlist_folders.folders().is_empty() {
+            return main.into();
+        }
+        let complete: Vec<&PathBuf> = self
+            .playlists
+            .iter()
+            .filter(|(_, queue)| queue.is_empty())
+            .map(|(path, _)| path)
+            .collect();
+        Row::new()
+            .spacing(20)
+            .push(self.sidebar.view(
+                &self.playlist_folders,
+                self.playlists.keys(),
+                &complete,
+                &self.path,
+                &self.pins,
+                &mut self.focus,
+            ))
+            .push(main)
             .into()
     }
 
     fn update(&mut self, message: Message) -> Command<Self::Message> {
+        let transport = matches!(
+            message,
+            Message::Pause
+                | Message::Stop
+                | Message::SeekReleased
+                | Message::GotoSubmitted
+                | Message::PlayPrevious
+                | Message::Restart
+                | Message::JumpToCue(_)
+                | Message::JumpToChapter(_)
+        );
+        if transport {
+            self.end_scan();
+        }
         match message {
             Message::PlayNext => {
                 self.play_next();
             }
+            Message::PlayPrevious => self.play_previous(),
+            Message::Restart => self.restart_track(),
             Message::Pause => {
+                // manual pause or resume overrides ducking
+                if self.ducking.mode == DuckMode::Pause {
+                    self.ducked = false;
+                }
                 self.tx
                     .send(PlayerCommand::Pause)
                     .expect("Can't send playback command!");
             }
-            Message::SliderChanged(v) => {
-                self.volume = v;
-                self.tx
-                    .send(PlayerCommand::Volume(v))
-                    .expect("Can't send playback command!");
-            }
+            Message::SliderChanged(v) => self.set_volume(v),
             Message::Window(iced_native::Event::Window(
                 iced_native::window::Event::FileDropped(f),
-            )) => self.file_dropped(f),
+            )) => return self.open_file(f),
+            Message::Window(iced_native::Event::Window(iced_native::window::Event::Resized {
+                width,
+                height,
+            })) => {
+                self.minimized = width == 0 || height == 0;
+                self.handle_tick();
+            }
+            Message::Window(iced_native::Event::Keyboard(
+                iced_native::keyboard::Event::KeyPressed {
+                    key_code: iced_native::keyboard::KeyCode::V,
+                    modifiers,
+                },
+            )) if modifiers.is_command_pressed() && !self.text_focused() => return self.paste(),
+            Message::Window(iced_native::Event::Keyboard(
+                iced_native::keyboard::Event::KeyPressed {
+                    key_code: iced_native::keyboard::KeyCode::Z,
+                    modifiers,
+                },
+            )) if modifiers.is_command_pressed() && !self.text_focused() => self.undo(),
+            Message::Window(iced_native::Event::Keyboard(
+                iced_native::keyboard::Event::KeyPressed {
+                    key_code,
+                    modifiers,
+                },
+            )) if !self.text_focused() => {
+                use iced_native::keyboard::KeyCode;
+                match key_code {
+                    KeyCode::Tab => self.focus.advance(modifiers.shift),
+                    KeyCode::Key1 => self.toggle_tag(0),
+                    KeyCode::Key2 => self.toggle_tag(1),
+                    KeyCode::Key3 => self.toggle_tag(2),
+                    KeyCode::Key4 => self.toggle_tag(3),
+                    KeyCode::Key5 => self.toggle_tag(4),
+                    KeyCode::Key6 => self.toggle_tag(5),
+                    KeyCode::Key7 => self.toggle_tag(6),
+                    KeyCode::Key8 => self.toggle_tag(7),
+                    KeyCode::Key9 => self.toggle_tag(8),
+                    KeyCode::Escape => {
+                        self.focus.clear();
+                        self.clear_selection();
+                    }
+                    KeyCode::G if !self.current_file.is_empty() => self.goto_input.focus(),
+                    KeyCode::C => self.add_cue(),
+                    KeyCode::Enter | KeyCode::Space => {
+                        if let Some(message) = self.focus.activate() {
+                            return self.update(message);
+                        }
+                        // without a focused button the selected album is enqueued
+                        if let Some(i) = self.albums.as_ref().map(AlbumGrid::selected) {
+                            return self.update(Message::EnqueueAlbum(i));
+                        }
+                    }
+                    KeyCode::Left | KeyCode::Right | KeyCode::Up | KeyCode::Down => {
+                        if let Some(grid) = self.albums.as_mut() {
+                            match key_code {
+                                KeyCode::Left => grid.move_selection(-1, 0),
+                                KeyCode::Right => grid.move_selection(1, 0),
+                                KeyCode::Up => grid.move_selection(0, -1),
+                                _ => grid.move_selection(0, 1),
+                            }
+                            self.request_album_covers();
+                        }
+                    }
+                    _ => (),
+                }
+            }
             Message::Tick => self.handle_tick(),
+            Message::DeferredInit => {
+                let deferred = self.init_deferred();
+                // checking for ffmpeg starts a process
+                let formats = Command::perform(
+                    on_worker(|| {
+                        decoders::FORMATS
+                            .iter()
+                            .map(|f| (f.name, f.available()))
+                            .collect()
+                    }),
+                    |v| Message::FormatsProbed(v.unwrap_or_default()),
+                );
+                return Command::batch(vec![deferred, formats]);
+            }
+            Message::DeferredLoaded(deferred) => self.deferred_loaded(*deferred),
+            Message::FormatsProbed(v) => self.formats = v,
+            Message::Window(iced_native::Event::Keyboard(
+                iced_native::keyboard::Event::ModifiersChanged(modifiers),
+            )) => {
+                self.shift = modifiers.shift;
+                self.command = modifiers.is_command_pressed();
+            }
+            Message::WheelScrolled(delta) => self.wheel_scrolled(delta),
+            Message::Window(iced_native::Event::Mouse(
+                iced_native::mouse::Event::ButtonPressed(iced_native::mouse::Button::Middle),
+            )) if cfg!(not(any(windows, target_os = "macos"))) => return self.paste_selection(),
+            Message::Swiped(swipe) if self.touch => match swipe {
+                Swipe::Left => return self.update(Message::PlayNext),
+                Swipe::Down if !self.current_file.is_empty() => return self.update(Message::Pause),
+                _ => (),
+            },
+            Message::Swiped(_) => (),
             Message::Window(_) => (),
-            Message::ToggleFavorite => {
+            Message::FavoritePicker => self.favorite_picker = !self.favorite_picker,
+            Message::FavoriteToggled(name, v) => self.set_favorite(&name, v),
+            Message::NewListNameChanged(v) => self.new_list_name = v,
+            Message::NoteChanged(v) => {
                 if !self.current_file.is_empty() {
-                    if self.is_favorite {
-                        self.data_favorites.remove(&self.current_file);
-                    } else {
-                        self.data_favorites.insert(self.current_file.clone());
+                    match v.is_empty() {
+                        true => Arc::make_mut(&mut self.notes).remove(&self.current_file),
+                        false => Arc::make_mut(&mut self.notes)
+                            .insert(self.current_file.clone(), v.clone()),
+                    };
+                    self.journal(Change::Note {
+                        track: self.current_file.clone(),
+                        note: v,
+                    });
+                }
+            }
+            Message::TagFilterSelected(v) => {
+                self.tag_filter = Some(v).filter(|v| self.tags.iter().any(|t| &t.name == v));
+            }
+            Message::ExportTags => {
+                let path = Path::new("tags.csv");
+                match tags::write_csv(&self.track_tags, path) {
+                    Ok(_) => info!("Tags written to {:?}", path),
+                    Err(e) => error!("{}", e),
+                }
+            }
+            Message::ExportScrobbles => match self.export_scrobbles() {
+                Ok(command) => {
+                    self.scrobble_result = None;
+                    return command;
+                }
+                Err(e) => self.scrobble_result = Some(e.to_string()),
+            },
+            Message::ScrobblesExported(result) => {
+                self.scrobble_result = Some(match result {
+                    Ok(path) => {
+                        info!("Play history written to {:?}", path);
+                        tr!("scrobbles-exported", path = path.display().to_string())
+                    }
+                    Err(e) => {
+                        error!("Can't export play history: {}", e);
+                        e
                     }
-                    self.is_favorite = !self.is_favorite;
+                });
+            }
+            Message::ScrobbleFormatSelected(v) => self.scrobble_format = v,
+            Message::ExportNotes => {
+                let path = Path::new("notes.csv");
+                match notes::write_csv(&self.notes, path) {
+                    Ok(_) => info!("Notes written to {:?}", path),
+                    Err(e) => error!("{}", e),
+                }
+            }
+            Message::AddFavoriteList => {
+                let name = self.new_list_name.trim().to_string();
+                if !name.is_empty() {
+                    self.set_favorite(&name, true);
+                    self.new_list_name.clear();
                 }
             }
             Message::ExportFavorites => {
-                let path = "favorites.xspf";
-                match playlist::write_playlist(self.data_favorites.iter(), path) {
-                    Ok(_) => info!("Favorites written to {}", path),
-                    Err(e) => error!("Can't write favorites to {}: {}", path, e),
+                // each list to its own file
+                for (name, tracks) in self.favorites.lists().iter() {
+                    let path = favorites::export_name(name);
+                    match playlist::write_playlist(tracks.iter(), name, &path) {
+                        Ok(_) => info!("Favorites written to {}", path),
+                        Err(e) => error!("Can't write favorites to {}: {}", path, e),
+                    }
                 }
             }
             Message::SaveConfig => {
+                self.sync();
                 self.store_state();
             }
+            Message::ArchivePathChanged(v) => self.archive_path = v,
+            Message::PortableFolderChanged(v) => self.portable_folder = v,
+            Message::PortableCopyToggled(v) => self.portable_copy = v,
+            Message::PortableProfileSelected(v) => self.portable_profile = v,
+            Message::SendFolderChanged(v) => self.send.settings.folder = v,
+            Message::SendSourceSelected(v) => self.send.settings.source = v,
+            Message::SendTranscodeToggled(v) => self.send.settings.transcode = v,
+            Message::SendToDevice => {
+                let (tracks, playlist) = match self.send.settings.source {
+                    SendSource::Queue => (self.queue_tracks(), self.queue_name()),
+                    SendSource::Favorites => {
+                        let mut tracks: Vec<String> =
+                            self.favorites.all().into_iter().cloned().collect();
+                        tracks.sort();
+                        (tracks, String::from("favorites"))
+                    }
+                };
+                self.send.start(tracks, playlist);
+            }
+            Message::FindDevices => self.send.find_devices(),
+            Message::DeviceSelected(v) => self.send.select_device(v),
+            Message::CancelSend => self.send.cancel(),
+            Message::LoadCd => {
+                if let Some(drive) = self.rip.load() {
+                    return self.read_cd(drive);
+                }
+            }
+            Message::RipDriveSelected(v) => return self.read_cd(v),
+            Message::CdRead(drive, result) => self.rip.disc_read(drive, result),
+            Message::RipTrackToggled(i, v) => self.rip.toggle_track(i, v),
+            Message::RipFolderChanged(v) => self.rip.settings.folder = v,
+            Message::RipFormatSelected(v) => self.rip.settings.format = v,
+            Message::RipVerifyToggled(v) => self.rip.settings.verify = v,
+            Message::RipCd => {
+                let library = self.library_folders.first().cloned();
+                self.rip.start(library.as_deref());
+            }
+            Message::CancelRip => self.rip.cancel(),
+            Message::ExportPortable => {
+                self.portable_result = Some(match self.export_portable() {
+                    Ok(v) => tr!(
+                        "portable-exported",
+                        tracks = v.tracks,
+                        copied = v.copied,
+                        skipped = v.skipped
+                    ),
+                    Err(e) => e.to_string(),
+                });
+            }
+            Message::SnapshotNameChanged(v) => self.snapshot_name = v,
+            Message::SaveSnapshot => self.save_snapshot(),
+            Message::SnapshotSelected(v) => self.snapshot_selected = Some(v),
+            Message::RestoreSnapshot => {
+                if let Some(name) = self.snapshot_selected.clone() {
+                    self.restore_snapshot(&name);
+                }
+            }
+            Message::DeleteSnapshot => {
+                if let Some(name) = self.snapshot_selected.take() {
+                    self.snapshots.remove(&name);
+                }
+            }
+            Message::DiffAChanged(v) => self.diff_a = v,
+            Message::DiffBChanged(v) => self.diff_b = v,
+            Message::CompareLists => match self.compare_lists() {
+                Ok(v) => {
+                    self.diff = Some(v);
+                    self.diff_result = None;
+                }
+                Err(e) => {
+                    self.diff = None;
+                    self.diff_result = Some(e.to_string());
+                }
+            },
+            Message::ExportOnlyA => {
+                if let Some((_, ref diff)) = self.diff {
+                    self.diff_result = Some(export_list(&diff.only_a, "diff-only-a.xspf"));
+                }
+            }
+            Message::ExportOnlyB => {
+                if let Some((_, ref diff)) = self.diff {
+                    self.diff_result = Some(export_list(&diff.only_b, "diff-only-b.xspf"));
+                }
+            }
+            Message::ExportMerged => {
+                if let Some((ref a, ref diff)) = self.diff {
+                    self.diff_result = Some(export_list(&diff.merged(a), "merged.xspf"));
+                }
+            }
+            Message::ExportArchive => {
+                let result = self.export_archive(Path::new(&self.archive_path));
+                self.archive_result = Some(match result {
+                    Ok(_) => tr!("exported"),
+                    Err(e) => e.to_string(),
+                });
+            }
+            Message::ImportArchive => {
+                let result = Archive::read(Path::new(&self.archive_path))
+                    .and_then(|archive| self.import_archive(archive));
+                self.archive_result = Some(match result {
+                    Ok(_) => tr!("imported"),
+                    Err(e) => e.to_string(),
+                });
+            }
+            Message::ImportItunes => {
+                self.archive_result = None;
+                return self.import_itunes(PathBuf::from(&self.archive_path));
+            }
+            Message::ItunesImported(result) => {
+                self.archive_result = Some(match result {
+                    Ok(import) => {
+                        let count = self.itunes_imported(import);
+                        info!("Imported {} iTunes playlists", count);
+                        tr!("itunes-imported", count = count)
+                    }
+                    Err(e) => {
+                        error!("{}", e);
+                        e
+                    }
+                });
+            }
+            Message::ImportSynced => {
+                if let Some(archive) = self.sync_notice.take() {
+                    let result = self.import_archive(archive);
+                    self.archive_result = Some(match result {
+                        Ok(_) => tr!("imported"),
+                        Err(e) => e.to_string(),
+                    });
+                }
+            }
+            Message::DismissSynced => {
+                // ours replaces it on the next sync
+                if let Some(archive) = self.sync_notice.take() {
+                    self.last_sync = archive.saved;
+                }
+            }
             Message::TrashFile => self.trash_file(),
+            Message::Stop => self.stop(),
+            Message::ClearQueue => self.clear_confirm = Some(Default::default()),
+            Message::PopOutQueue => self.pop_out_queue(),
+            Message::ClearQueueConfirmed => {
+                self.clear_confirm = None;
+                self.clear_queue();
+            }
+            Message::ClearQueueCancelled => self.clear_confirm = None,
+            Message::ToggleSkipped => self.show_skipped = !self.show_skipped,
+            Message::Reveal(track) => {
+                if let Err(e) = desktop::reveal(Path::new(&track)) {
+                    warn!("Can't reveal {}: {:?}", track, e);
+                }
+            }
+            Message::CopyPath(track) => {
+                if let Err(e) = desktop::copy(&track) {
+                    warn!("Can't copy path {}: {:?}", track, e);
+                }
+            }
+            Message::FilterChanged(v) => self.filter = v,
+            Message::RelocateRootChanged(v) => self.relocate_root = v,
+            Message::Relocate => return self.relocate(),
+            Message::Relocated(result) => self.relocated(result),
+            Message::UiScaleChanged(v) => self.ui_scale = v,
+            Message::GamepadToggled(v) => {
+                // the reading thread ends with the dropped receiver
+                self.gamepad = v.then(Gamepad::new);
+                self.gamepad_settings.learn(None);
+            }
+            Message::LearnGamepadButton(action) => self.gamepad_settings.learn(Some(action)),
+            Message::ResetGamepadMapping => {
+                self.gamepad_mapping = gamepad::default_mapping();
+                self.gamepad_settings.learn(None);
+            }
+            Message::TouchModeSelected(v) => {
+                self.touch_mode = v;
+                self.touch = v.active();
+            }
+            Message::SeekPreview(v) => self.seek_preview = Some(v),
+            Message::ToggleTimeDisplay => self.time_display = self.time_display.next(),
+            Message::SeekReleased => {
+                // seek only once on release, restarting the decoder is expensive
+                if let Some(target) = self.seek_preview.take() {
+                    let target = Duration::from_secs(target as u64);
+                    self.playtime = Some(target);
+                    self.tx
+                        .send(PlayerCommand::Seek(target))
+                        .expect("Can't send playback command!");
+                }
+            }
+            Message::GotoChanged(v) => {
+                // also drops the G typed by the shortcut focusing the input
+                self.goto_text = v
+                    .chars()
+                    .filter(|c| c.is_ascii_digit() || *c == ':')
+                    .collect();
+                self.goto_invalid = false;
+            }
+            Message::GotoSubmitted => {
+                match parse_timestamp(&self.goto_text)
+                    .filter(|v| self.length.is_some_and(|length| *v <= length))
+                {
+                    Some(target) if !self.current_file.is_empty() => {
+                        self.playtime = Some(target);
+                        self.tx
+                            .send(PlayerCommand::Seek(target))
+                            .expect("Can't send playback command!");
+                        self.goto_text.clear();
+                        self.goto_input.unfocus();
+                    }
+                    _ => self.goto_invalid = true,
+                }
+            }
+            Message::ThemeSelected(v) => {
+                self.theme = v;
+                theme::set(self.theme, self.text_scale);
+            }
+            Message::TextScaleChanged(v) => {
+                self.text_scale = v;
+                theme::set(self.theme, self.text_scale);
+            }
+            Message::LanguageSelected(v) => {
+                self.language = v;
+                i18n::set_language(v);
+            }
+            Message::MoveUp(pos) => self.move_up(pos),
+            Message::SelectTrack(list, index) => self.select_track(list, index),
+            Message::ClearSelection => self.clear_selection(),
+            Message::Bulk(action) => self.bulk(action),
+            Message::MoveTargetChanged(v) => self.bulk_bar.set_target(v),
+            Message::MoveTracksToggled(v) => self.bulk_bar.set_move_tracks(v),
+            Message::Undo => self.undo(),
+            Message::Retry(pos) => self.retry(pos),
+            Message::KeepBrokenToggled(v) => {
+                self.keep_broken = v;
+                if !v {
+                    Arc::make_mut(&mut self.broken).clear();
+                }
+            }
+            Message::PreampChanged(v) => {
+                self.preamp = v;
+                self.tx
+                    .send(PlayerCommand::Preamp(v))
+                    .expect("Can't send playback command!");
+            }
+            Message::EqPresetSelected(name) => {
+                let settings = self.playlist_settings.entry(self.path.clone()).or_default();
+                settings.eq = match name == tr!("no-eq") {
+                    true => None,
+                    false => Some(name),
+                };
+            }
+            Message::VolumeOffsetChanged(v) => {
+                self.playlist_settings
+                    .entry(self.path.clone())
+                    .or_default()
+                    .volume_offset = v;
+                self.tx
+                    .send(PlayerCommand::Volume(self.output_volume()))
+                    .expect("Can't send playback command!");
+            }
+            Message::PluginToggled(name, v) => {
+                self.plugins.set_enabled(name, v);
+                if let Some(plugin) = self.browser.as_ref().map(|b| b.plugin) {
+                    if !self.plugins.has_source(plugin) {
+                        self.browser = None;
+                    }
+                }
+            }
+            Message::OpenSource(i) => {
+                if self.plugins.has_source(i) {
+                    self.browser = Some(Browser::open(i));
+                    return self.browse();
+                }
+            }
+            Message::BrowseEntry(i) => {
+                let track = match self.browser.as_mut() {
+                    Some(browser) => browser.enter(i),
+                    None => return Command::none(),
+                };
+                return match track {
+                    Some(track) => self.open_track(track),
+                    None => self.browse(),
+                };
+            }
+            Message::BrowseBack => {
+                if self.browser.as_mut().is_some_and(Browser::back) {
+                    return self.browse();
+                }
+            }
+            Message::Listed(request, result) => {
+                if let Some(ref mut browser) = self.browser {
+                    browser.listed(request, result);
+                }
+            }
+            Message::RemotePlaylistFetched(url, result) => {
+                self.remote_playlist_fetched(url, result)
+            }
+            Message::CloseBrowser => self.browser = None,
+            Message::OpenAlbums => self.open_albums(),
+            Message::OpenArtist(name) => {
+                self.index_library();
+                self.artist_page = Some(self.artist_page(&name));
+            }
+            Message::CloseArtist => self.artist_page = None,
+            Message::OpenSearch => {
+                self.index_library();
+                let mut search = LibrarySearch::default();
+                search.update(&self.metadata);
+                self.search = Some(search);
+            }
+            Message::CloseSearch => self.search = None,
+            Message::OpenHome => {
+                self.index_library();
+                self.home = Some(self.home_view(archive::now()));
+            }
+            Message::CloseHome => self.home = None,
+            Message::OpenPlaylist(path) => self.open_playlist(path),
+            Message::OpenPin(Pin::Playlist(path)) => match self.playlists.contains_key(&path) {
+                true => self.open_playlist(path),
+                // forgotten playlists are loaded from the file again
+                false => self.file_dropped(path),
+            },
+            Message::ForgetPlaylist(path) => self.forget_playlist(&path),
+            Message::OpenPin(Pin::Favorites(name)) => {
+                if let Some(list) = self.favorites.lists().get(&name) {
+                    let mut tracks: Vec<String> = list.iter().cloned().collect();
+                    tracks.sort();
+                    self.enqueue_tracks(tracks);
+                }
+            }
+            Message::TogglePin(pin) => pins::toggle(&mut self.pins, pin),
+            Message::MovePlaylist(path, FolderChoice(folder)) => {
+                self.playlist_folders.move_to(&path, folder.as_deref())
+            }
+            Message::ToggleFolder(name) => self.playlist_folders.toggle(&name),
+            Message::RemoveFolder(name) => self.playlist_folders.remove(&name),
+            Message::NewFolderChanged(v) => self.sidebar.set_new_folder(v),
+            Message::AddFolder => {
+                if self.playlist_folders.add(self.sidebar.new_folder()) {
+                    self.sidebar.set_new_folder(String::new());
+                }
+            }
+            Message::EnqueueMix(i) => {
+                if let Some(mix) = self.home.as_ref().and_then(|h| h.mix(i)) {
+                    let tracks = mix.tracks.clone();
+                    self.enqueue_tracks(tracks);
+                }
+            }
+            Message::OpenRecent => {
+                self.index_library();
+                self.recent = Some(RecentView::new(&self.metadata, archive::now()));
+            }
+            Message::CloseRecent => self.recent = None,
+            Message::RecentDays(days) => {
+                if let Some(recent) = self.recent.as_mut() {
+                    recent.set_days(days);
+                    recent.update(&self.metadata, archive::now());
+                }
+            }
+            Message::EnqueueRecent(i) => {
+                if let Some(album) = self.recent.as_ref().and_then(|r| r.album(i)) {
+                    let tracks = album.tracks.clone();
+                    self.enqueue_tracks(tracks);
+                }
+            }
+            Message::SearchTextChanged(v) => self.update_search(|f| f.text = v),
+            Message::GenreSelected(v) => self.update_search(|f| f.genre = v.0),
+            Message::DecadeSelected(v) => self.update_search(|f| f.decade = v.0.map(|d| d.0)),
+            Message::FormatSelected(v) => self.update_search(|f| f.format = v.0),
+            Message::EnqueueResults => {
+                if let Some(search) = self.search.as_ref() {
+                    let tracks = search.results().cloned().collect();
+                    self.enqueue_tracks(tracks);
+                }
+            }
+            Message::PlayArtist(shuffle) => {
+                if let Some(page) = self.artist_page.as_ref() {
+                    let mut tracks: Vec<String> = page
+                        .artist()
+                        .albums
+                        .iter()
+                        .flat_map(|a| a.tracks.iter())
+                        .cloned()
+                        .collect();
+                    // tracks without album after the albums
+                    let on_albums: HashSet<&String> = tracks.iter().collect();
+                    let rest: Vec<String> = page
+                        .artist()
+                        .tracks
+                        .iter()
+                        .filter(|t| !on_albums.contains(t))
+                        .cloned()
+                        .collect();
+                    tracks.extend(rest);
+                    if shuffle {
+                        tracks.shuffle(&mut thread_rng());
+                    }
+                    self.enqueue_tracks(tracks);
+                }
+            }
+            Message::EnqueueArtistAlbum(i) => {
+                if let Some(album) = self
+                    .artist_page
+                    .as_ref()
+                    .and_then(|p| p.artist().albums.get(i))
+                {
+                    let tracks = album.tracks.clone();
+                    self.enqueue_tracks(tracks);
+                }
+            }
+            Message::StartRadio => {
+                self.index_library();
+                let info = self
+                    .metadata
+                    .get(&self.current_file)
+                    .cloned()
+                    .unwrap_or_default();
+                let mut radio = Radio::new(self.current_file.clone(), info, self.lastfm.as_ref());
+                let tracks = {
+                    let broken = &self.broken;
+                    let blocked = content_blocker(
+                        self.content_matcher.as_ref(),
+                        &self.metadata,
+                        &self.track_tags,
+                    );
+                    let playable = |t: &str| !broken.contains(t) && !blocked(t);
+                    radio.next_tracks(&self.metadata, playable, ENDLESS_TRACKS)
+                };
+                self.radio = Some(radio);
+                self.enqueue_tracks(tracks);
+            }
+            Message::StopRadio => self.radio = None,
+            Message::EnqueueTopTrack(i) => {
+                if let Some(track) = self.artist_page.as_ref().and_then(|p| p.top_track(i)) {
+                    let tracks = vec![track.to_string()];
+                    self.enqueue_tracks(tracks);
+                }
+            }
+            Message::CloseAlbums => self.albums = None,
+            Message::AlbumPage(page) => {
+                if let Some(grid) = self.albums.as_mut() {
+                    grid.show_page(page);
+                }
+                self.request_album_covers();
+            }
+            Message::EnqueueAlbum(i) => {
+                if let Some(album) = self.albums.as_ref().and_then(|g| g.album(i)) {
+                    info!("Enqueueing album {} - {}", album.artist, album.title);
+                    let tracks = album.tracks.clone();
+                    self.enqueue_tracks(tracks);
+                }
+            }
+            Message::ChannelMappingChanged(v) => {
+                self.routing.channels = v;
+                self.tx
+                    .send(PlayerCommand::ChannelMapping(v))
+                    .expect("Can't send playback command!");
+            }
+            Message::BufferFramesSelected(v) => {
+                self.routing.buffer_frames = v.0;
+            }
+            Message::FollowDefaultToggled(v) => {
+                self.routing.follow_default = v;
+                self.tx
+                    .send(PlayerCommand::FollowDefaultDevice(v))
+                    .expect("Can't send playback command!");
+            }
+            Message::ScanSelected(Scan(from)) => {
+                self.scan = from;
+                self.scan_start = None;
+            }
+            Message::QueueEndSelected(v) => {
+                self.queue_end = v;
+                if v.fill().is_some() {
+                    self.index_library();
+                }
+                if v != QueueEnd::Quit {
+                    self.quit_at = None;
+                }
+            }
+            Message::CancelQuit => self.quit_at = None,
+            Message::RatingSelected(v) => {
+                if let Some(store) = self
+                    .store
+                    .as_ref()
+                    .filter(|_| !self.current_file.is_empty())
+                {
+                    match store.set_rating(&self.current_file, v.0) {
+                        Ok(_) => self.rating = v.0,
+                        Err(e) => error!("Can't store rating: {}", e),
+                    }
+                }
+            }
+            Message::AddCue => self.add_cue(),
+            Message::JumpToCue(i) => {
+                if let Some(cue) = self.cues.get(i).filter(|_| self.head_started()) {
+                    self.playtime = Some(cue.position);
+                    self.tx
+                        .send(PlayerCommand::Seek(cue.position))
+                        .expect("Can't send playback command!");
+                }
+            }
+            Message::JumpToChapter(i) => {
+                if let Some(chapter) = self.chapters.get(i).filter(|_| self.head_started()) {
+                    self.playtime = Some(chapter.position);
+                    self.tx
+                        .send(PlayerCommand::Seek(chapter.position))
+                        .expect("Can't send playback command!");
+                }
+            }
+            Message::RemoveCue(i) => {
+                if let (Some(store), Some(cue)) = (self.store.as_ref(), self.cues.get(i)) {
+                    let cues = store
+                        .remove_cue(&self.current_file, cue.position)
+                        .and_then(|_| store.cues(&self.current_file));
+                    match cues {
+                        Ok(v) => self.set_cues(v),
+                        Err(e) => error!("Can't remove cue: {}", e),
+                    }
+                }
+            }
+            Message::CueFormatSelected(v) => self.cue_format = v,
+            Message::PlaylistCharsetSelected(PlaylistCharset(v)) => self.playlist_charset = v,
+            Message::SetIntro(album) => {
+                let album = match album {
+                    true => self
+                        .metadata
+                        .get(&self.current_file)
+                        .and_then(|v| v.album.clone()),
+                    false => None,
+                };
+                if let (Some(store), Some(position)) = (self.store.as_ref(), self.playtime) {
+                    let stored = match album {
+                        Some(ref album) => store.set_album_intro(album, Some(position)),
+                        None => store.set_intro(&self.current_file, Some(position)),
+                    };
+                    match stored {
+                        Ok(_) => self.intro = Some(position),
+                        Err(e) => error!("Can't store intro: {}", e),
+                    }
+                }
+            }
+            Message::ClearIntro => {
+                // the intro may come from the album, remove both
+                let album = self
+                    .metadata
+                    .get(&self.current_file)
+                    .and_then(|v| v.album.clone());
+                if let Some(store) = self.store.as_ref() {
+                    let removed =
+                        store
+                            .set_intro(&self.current_file, None)
+                            .and_then(|_| match album {
+                                Some(ref album) => store.set_album_intro(album, None),
+                                None => Ok(()),
+                            });
+                    match removed {
+                        Ok(_) => self.intro = None,
+                        Err(e) => error!("Can't remove intro: {}", e),
+                    }
+                }
+            }
+            Message::ExportCues => {
+                let result =
+                    cues::export(Path::new(&self.current_file), &self.cues, self.cue_format);
+                self.cue_result = Some(match result {
+                    Ok(path) => tr!("list-exported", path = path.to_string_lossy().into_owned()),
+                    Err(e) => e.to_string(),
+                });
+            }
+            Message::ContentFilterToggled(on) => {
+                // turning it on never needs the password
+                if on || self.content_filter.unlocks(&self.filter_password) {
+                    self.content_filter.enabled = on;
+                    self.content_matcher = on.then(|| self.content_filter.matcher());
+                    self.filter_password.clear();
+                    self.filter_locked = false;
+                } else {
+                    self.filter_locked = true;
+                }
+            }
+            Message::FilterPasswordChanged(v) => self.filter_password = v,
+            Message::SetFilterPassword => {
+                if self.content_filter.password.is_none() {
+                    self.content_filter.set_password(&self.filter_password);
+                    self.filter_password.clear();
+                    self.filter_locked = false;
+                } else if self.content_filter.unlocks(&self.filter_password) {
+                    self.content_filter.set_password("");
+                    self.filter_password.clear();
+                    self.filter_locked = false;
+                } else {
+                    self.filter_locked = true;
+                }
+            }
+            Message::SnoozeAlarm => {
+                if let Some(ringing) = self.ringing.take() {
+                    self.stop();
+                    self.snoozed = Some((ringing.alarm, Instant::now() + alarm::SNOOZE));
+                }
+            }
+            Message::DismissAlarm => {
+                self.ringing = None;
+                self.snoozed = None;
+            }
+            Message::AlarmWeekdayToggled(index, day, on) => {
+                if let Some(alarm) = self.alarms.get_mut(index) {
+                    alarm.weekdays.retain(|v| *v != day);
+                    if on {
+                        alarm.weekdays.push(day);
+                        alarm.weekdays.sort_by_key(|v| v.num_days_from_monday());
+                    }
+                }
+            }
+            Message::ZoneVolumeChanged(zone, v) => {
+                if let Some(z) = self.routing.zones.get_mut(zone) {
+                    z.volume = v;
+                    self.tx
+                        .send(PlayerCommand::ZoneVolume(zone, v))
+                        .expect("Can't send playback command!");
+                }
+            }
         }
         Command::none()
     }
 
-    fn new(_flags: ()) -> (PlaybackControl, Command<Message>) {
-        (PlaybackControl::default(), Command::none())
+    fn new(config: Self::Flags) -> (PlaybackControl, Command<Message>) {
+        let data = config.join().expect("Can't load config");
+        timing::phase("config loaded");
+        let app = PlaybackControl::from_config(data);
+        timing::phase("state ready");
+        (app, Command::perform(async {}, |_| Message::DeferredInit))
     }
 
     fn title(&self) -> String {
-        String::from("Audio Wrench")
+        if self.minimized && !self.current_file.is_empty() {
+            // shown when hovering the taskbar entry
+            let title = self
+                .metadata
+                .get(&self.current_file)
+                .and_then(|v| v.title.clone())
+                .or_else(|| {
+                    Path::new(&self.current_file)
+                        .file_stem()
+                        .map(|v| v.to_string_lossy().into_owned())
+                })
+                .unwrap_or_else(|| self.current_file.clone());
+            return compact_title(&title, self.is_paused, self.playtime, self.length);
+        }
+        if !self.title_info || self.current_file.is_empty() {
+            return String::from("Audio Wrench");
+        }
+        let track = match self
+            .metadata
+            .get(&self.current_file)
+            .and_then(TrackInfo::label)
+        {
+            Some(v) => v,
+            None => Path::new(&self.current_file).file_stem().map_or_else(
+                || self.current_file.clone(),
+                |v| v.to_string_lossy().into_owned(),
+            ),
+        };
+        let (total, complete) = self.total_playtime;
+        let left = format!(
+            "{}{}",
+            format_duration(Some(total)),
+            if complete { "" } else { "+" }
+        );
+        format!(
+            "{} [{}/{}] | {} - Audio Wrench",
+            track,
+            format_duration(self.playtime),
+            format_duration(self.length),
+            tr!("queue-left", time = left)
+        )
     }
 
     fn subscription(&self) -> Subscription<Self::Message> {
-        let timer_ticks = iced::time::every(Duration::from_millis(100)).map(|_| Message::Tick);
+        let timer_ticks = iced::time::every(self.progress_interval()).map(|_| Message::Tick);
         let timer_save = iced::time::every(SAVE_INTERVAL).map(|_| Message::SaveConfig);
         let window_ticks = iced_native::subscription::events().map(Message::Window);
         Subscription::batch(vec![window_ticks, timer_ticks, timer_save])
     }
 
     fn background_color(&self) -> iced_native::Color {
-        iced_native::Color::WHITE
+        theme::background()
     }
 
     fn scale_factor(&self) -> f64 {
-        1.0
+        // system DPI scaling is applied by winit, per monitor
+        let scale = self
+            .ui_scale
+            .clamp(*UI_SCALE_RANGE.start(), *UI_SCALE_RANGE.end()) as f64
+            / 100.0;
+        match self.touch {
+            true => scale * 2.0,
+            false => scale,
+        }
     }
 
     fn mode(&self) -> iced::window::Mode {
@@ -460,7 +7027,28 @@ fn main() -> Result<()> {
     builder.parse_env("RUST_LOG");
     builder.init();
 
-    let mut settings: Settings<()> = Settings::default();
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(i) = args.iter().position(|v| v == timing::TIMING_ARG) {
+        args.remove(i);
+        timing::enable();
+    }
+    let mut args = args.into_iter();
+    match args.next().as_deref() {
+        Some(osd::OSD_ARG) => {
+            let title = args.next().unwrap_or_default();
+            return osd::run(title, args.next().as_deref() == Some(osd::COVER_ARG));
+        }
+        Some(tui::TUI_ARG) => return tui::run(),
+        Some(queue_window::QUEUE_ARG) => {
+            return queue_window::run(&args.next().unwrap_or_default())
+        }
+        _ => (),
+    }
+
+    let config = std::thread::Builder::new()
+        .name("config".to_string())
+        .spawn(load_config)?;
+    let mut settings = Settings::with_flags(config);
     let mut window_settings = window::Settings::default();
     window_settings.size = (500, 500);
     settings.window = window_settings;
@@ -468,3 +7056,187 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_move_file() {
+        let dir = std::env::temp_dir().join(format!("audio_wrench_move_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (a, b, c) = (dir.join("a.mp3"), dir.join("b.mp3"), dir.join("c.mp3"));
+        std::fs::write(&a, b"audio").unwrap();
+        move_file(&a, &b).unwrap();
+        assert!(!a.exists());
+        // fallback for moves between file systems
+        copy_and_remove(&b, &c).unwrap();
+        assert!(!b.exists());
+        assert_eq!(std::fs::read(&c).unwrap(), b"audio");
+        // existing files are kept
+        std::fs::write(&a, b"other").unwrap();
+        assert!(copy_and_remove(&c, &a).is_err());
+        assert_eq!(std::fs::read(&a).unwrap(), b"other");
+        assert!(c.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        let secs = |v| Some(Duration::from_secs(v));
+        assert_eq!(parse_timestamp("42"), secs(42));
+        assert_eq!(parse_timestamp("01:15"), secs(75));
+        assert_eq!(parse_timestamp("90:00"), secs(90 * 60));
+        assert_eq!(parse_timestamp("1:02:03"), secs(3723));
+        assert_eq!(parse_timestamp("1:75"), None);
+        assert_eq!(parse_timestamp("1::3"), None);
+        assert_eq!(parse_timestamp(""), None);
+        assert_eq!(parse_timestamp("1:2:3:4"), None);
+        assert_eq!(parse_timestamp("99999999999999999999:00"), None);
+        assert_eq!(parse_timestamp("999999999999999999:00:00"), None);
+    }
+
+    #[test]
+    fn test_nearest_cue() {
+        let cue = |secs, label: &str| Cue {
+            position: Duration::from_secs(secs),
+            label: label.to_string(),
+        };
+        let cues = [cue(0, "Intro"), cue(60, ""), cue(90, "Verse")];
+        let nearest =
+            |secs| nearest_cue(cues.iter(), Duration::from_secs(secs)).map(|v| v.label.as_str());
+        assert_eq!(nearest(30), Some("Intro"));
+        assert_eq!(nearest(62), Some("Verse"));
+        assert_eq!(nearest(500), Some("Verse"));
+        assert_eq!(nearest_cue([].iter(), Duration::ZERO), None);
+    }
+
+    #[test]
+    fn test_wheel_steps() {
+        let mut partial = (0.0, 0.0);
+        let lines = ScrollDelta::Lines { x: 0.0, y: -2.0 };
+        assert_eq!(wheel_steps(lines, &mut partial), (0, -2));
+        let pixels = ScrollDelta::Pixels { x: 30.0, y: 0.0 };
+        assert_eq!(wheel_steps(pixels, &mut partial), (1, 0));
+        assert_eq!(wheel_steps(pixels, &mut partial), (2, 0));
+        assert_eq!(partial, (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_time_display() {
+        let secs = |v| Some(Duration::from_secs(v));
+        assert_eq!(format_duration(secs(75)), "01:15");
+        assert_eq!(format_duration(secs(75 * 60 + 30)), "01:15:30");
+        assert_eq!(format_duration(None), "--:--");
+        assert_eq!(
+            compact_title("Song", false, secs(130), secs(270)),
+            "▶ Song — 02:10/04:30"
+        );
+        assert_eq!(
+            compact_title("Song", true, None, None),
+            "⏸ Song — --:--/--:--"
+        );
+        let (playtime, length) = (secs(30), secs(200));
+        assert_eq!(TimeDisplay::Elapsed.format(playtime, length), "00:30/03:20");
+        assert_eq!(
+            TimeDisplay::Remaining.format(playtime, length),
+            "-02:50/03:20"
+        );
+        assert_eq!(TimeDisplay::Both.format(playtime, length), "00:30 -02:50");
+        assert_eq!(TimeDisplay::Both.format(playtime, None), "00:30 --:--");
+        let playtime = Some(Duration::from_millis(30_045));
+        assert_eq!(
+            TimeDisplay::Precise.format(playtime, length),
+            "00:30.045/03:20"
+        );
+        assert_eq!(
+            sample_offset(Duration::from_millis(30_045), 44100),
+            1_324_984
+        );
+    }
+
+    #[test]
+    fn test_coalesce() {
+        let progress = |secs| {
+            PlayerStatus::Progress(player::Progress {
+                position: Duration::from_secs(secs),
+                duration: None,
+                state: PlaybackState::Playing,
+            })
+        };
+        let statuses = coalesce(vec![
+            progress(1),
+            PlayerStatus::Playing(String::from("a"), 1),
+            progress(2),
+            PlayerStatus::Ended(1),
+            progress(3),
+        ]);
+        assert_eq!(statuses.len(), 3);
+        assert!(matches!(statuses[0], PlayerStatus::Playing(..)));
+        assert!(matches!(statuses[1], PlayerStatus::Ended(_)));
+        match statuses[2] {
+            PlayerStatus::Progress(ref v) => assert_eq!(v.position, Duration::from_secs(3)),
+            _ => panic!("expected progress"),
+        }
+    }
+
+    #[test]
+    fn test_config_into_owned() {
+        let mut playlists = Playlists::new();
+        playlists.insert(PathBuf::from("a.m3u"), vec![String::from("/m/1.mp3")]);
+        let playlists = Arc::new(playlists);
+        let notes = Arc::new(HashMap::from([(
+            String::from("/m/1.mp3"),
+            String::from("intro"),
+        )]));
+        let data = ConfigData {
+            playlists: playlists.clone(),
+            notes: notes.clone(),
+            ..Default::default()
+        };
+        let expected = serde_json::to_string(&data).unwrap();
+        let owned = data.into_owned();
+        // queues and other large collections are shared, not copied
+        assert!(Arc::ptr_eq(&owned.playlists, &playlists));
+        assert!(Arc::ptr_eq(&owned.notes, &notes));
+        assert_eq!(serde_json::to_string(&owned).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_apply_changes() {
+        let mut data = ConfigData::default();
+        let tag = |set| Change::Tag {
+            track: String::from("/m/1.mp3"),
+            tag: String::from("red"),
+            set,
+        };
+        // replaying twice gives the same state
+        data.apply(tag(true));
+        data.apply(tag(true));
+        assert_eq!(data.track_tags["/m/1.mp3"].len(), 1);
+        data.apply(tag(false));
+        assert!(data.track_tags.is_empty());
+        data.apply(Change::Favorite {
+            list: String::from("Default"),
+            track: String::from("/m/1.mp3"),
+            favorite: true,
+        });
+        data.apply(Change::Favorite {
+            list: String::from("Default"),
+            track: String::from("file:///m/1.mp3"),
+            favorite: false,
+        });
+        assert!(data.favorite_lists["Default"].is_empty());
+        data.apply(Change::Note {
+            track: String::from("/m/1.mp3"),
+            note: String::from("intro"),
+        });
+        assert_eq!(data.notes["/m/1.mp3"], "intro");
+        data.apply(Change::Moved {
+            from: String::from("/m/1.mp3"),
+            to: String::from("/n/1.mp3"),
+        });
+        assert_eq!(data.notes["/n/1.mp3"], "intro");
+        assert!(!data.notes.contains_key("/m/1.mp3"));
+    }
+}
@@ -0,0 +1,77 @@
+//! Platform media session: MPRIS on linux, now playing info on macOS.
+//! Bluetooth devices receive metadata and send AVRCP commands through it.
+// TODO: windows requires the window handle, which iced doesn't expose
+
+use std::{
+    path::Path,
+    sync::mpsc::{channel, Receiver},
+    time::Duration,
+};
+
+pub use souvlaki::MediaControlEvent;
+use souvlaki::{MediaControls, MediaMetadata, MediaPlayback, MediaPosition, PlatformConfig};
+
+use crate::prelude::*;
+
+pub struct MediaSession {
+    controls: MediaControls,
+    rx: Receiver<MediaControlEvent>,
+}
+
+impl MediaSession {
+    #[cfg(not(windows))]
+    pub fn new() -> Result<Self> {
+        let config = PlatformConfig {
+            dbus_name: "audio_wrench",
+            display_name: "Audio Wrench",
+            hwnd: None,
+        };
+        let mut controls =
+            MediaControls::new(config).map_err(|e| eyre!("Can't create media session: {:?}", e))?;
+        let (tx, rx) = channel();
+        controls
+            .attach(move |event| {
+                let _ = tx.send(event);
+            })
+            .map_err(|e| eyre!("Can't attach media session: {:?}", e))?;
+        Ok(Self { controls, rx })
+    }
+
+    #[cfg(windows)]
+    pub fn new() -> Result<Self> {
+        Err(eyre!("Media session not supported on windows"))
+    }
+
+    /// Publish metadata of a new track
+    pub fn set_track(&mut self, track: &str, length: Option<Duration>) {
+        let title = Path::new(track)
+            .file_stem()
+            .map(|v| v.to_string_lossy().into_owned());
+        let metadata = MediaMetadata {
+            title: title.as_deref(),
+            duration: length,
+            ..Default::default()
+        };
+        if let Err(e) = self.controls.set_metadata(metadata) {
+            warn!("Can't update media session metadata: {:?}", e);
+        }
+    }
+
+    /// Publish playback state, None for stopped
+    pub fn set_playback(&mut self, paused: Option<bool>, position: Option<Duration>) {
+        let progress = position.map(MediaPosition);
+        let playback = match paused {
+            None => MediaPlayback::Stopped,
+            Some(true) => MediaPlayback::Paused { progress },
+            Some(false) => MediaPlayback::Playing { progress },
+        };
+        if let Err(e) = self.controls.set_playback(playback) {
+            warn!("Can't update media session playback: {:?}", e);
+        }
+    }
+
+    /// Next pending control event
+    pub fn poll(&self) -> Option<MediaControlEvent> {
+        self.rx.try_recv().ok()
+    }
+}
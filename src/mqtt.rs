@@ -0,0 +1,388 @@
+//! MQTT integration for home automation.
+//! Publishes the player state below the base topic and accepts commands:
+//! - `<topic>/command`: `play`, `pause`, `next`, `stop` or `clear`
+//! - `<topic>/volume/set`: volume from 0 to 100
+//! - `<topic>/enqueue`: track to be played next
+//!
+//! Home Assistant has no MQTT media player, with discovery enabled the player
+//! shows up as device with sensors, a volume control, transport buttons and the cover image.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use rumqttc::{Client, Event, LastWill, MqttOptions, Packet, QoS};
+use serde::{Deserialize, Serialize};
+
+use crate::control::{ControlCommand, ControlHandle, ControlState, ControlStatus};
+use crate::prelude::*;
+use crate::secrets::Secret;
+
+/// Wait before reconnecting after a connection error
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MqttConfig {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub user: String,
+    /// Kept in the secret store, written into the config only while storing it fails
+    #[serde(default, skip_serializing_if = "Secret::skip_config")]
+    pub password: Secret,
+    /// Base topic of all published and subscribed topics
+    #[serde(default = "default_topic")]
+    pub topic: String,
+    /// Announce entities through Home Assistant MQTT discovery
+    #[serde(default)]
+    pub home_assistant: bool,
+    #[serde(default = "default_discovery_prefix")]
+    pub discovery_prefix: String,
+}
+
+fn default_port() -> u16 {
+    1883
+}
+
+fn default_topic() -> String {
+    String::from("audio_wrench")
+}
+
+fn default_discovery_prefix() -> String {
+    String::from("homeassistant")
+}
+
+/// Connect to the broker from separate threads, reconnecting on errors
+pub fn spawn(config: MqttConfig, control: ControlHandle) -> Result<()> {
+    let topic = config.topic.trim_end_matches('/').to_string();
+    let mut options = MqttOptions::new(
+        format!("audio_wrench-{}", std::process::id()),
+        config.host.as_str(),
+        config.port,
+    );
+    options.set_keep_alive(Duration::from_secs(30));
+    if !config.user.is_empty() {
+        options.set_credentials(config.user.as_str(), config.password.as_str());
+    }
+    options.set_last_will(LastWill::new(
+        format!("{}/available", topic),
+        "offline",
+        QoS::AtLeastOnce,
+        true,
+    ));
+    let (client, mut connection) = Client::new(options, 10);
+
+    let announcements = match config.home_assistant {
+        true => discovery(&config.discovery_prefix, &topic),
+        false => Vec::new(),
+    };
+    let subscriber = client.clone();
+    let base = topic.clone();
+    let commands = control.clone();
+    thread::Builder::new()
+        .name("mqtt connection".to_string())
+        .spawn(move || {
+            for event in connection.iter() {
+                match event {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        info!("Connected to MQTT broker");
+                        let result = subscriber
+                            .subscribe(format!("{}/command", base), QoS::AtLeastOnce)
+                            .and_then(|_| {
+                                subscriber
+                                    .subscribe(format!("{}/volume/set", base), QoS::AtLeastOnce)
+                            })
+                            .and_then(|_| {
+                                subscriber.subscribe(format!("{}/enqueue", base), QoS::AtLeastOnce)
+                            })
+                            .and_then(|_| {
+                                subscriber.publish(
+                                    format!("{}/available", base),
+                                    QoS::AtLeastOnce,
+                                    true,
+                                    "online",
+                                )
+                            });
+                        if let Err(e) = result {
+                            warn!("Can't subscribe to MQTT commands: {}", e);
+                        }
+                        for (topic, payload) in announcements.iter() {
+                            if let Err(e) =
+                                subscriber.publish(topic, QoS::AtLeastOnce, true, payload.as_str())
+                            {
+                                warn!("Can't publish discovery to {}: {}", topic, e);
+                            }
+                        }
+                    }
+                    Ok(Event::Incoming(Packet::Publish(msg))) => {
+                        let payload = String::from_utf8_lossy(&msg.payload);
+                        let suffix = msg.topic.strip_prefix(base.as_str()).unwrap_or_default();
+                        match parse_command(suffix, payload.trim()) {
+                            Some(command) => {
+                                if !commands.send(command) {
+                                    return;
+                                }
+                            }
+                            None => warn!("Invalid MQTT command on {}: {}", msg.topic, payload),
+                        }
+                    }
+                    Ok(_) => (),
+                    Err(e) => {
+                        warn!("MQTT connection error: {}", e);
+                        thread::sleep(RECONNECT_DELAY);
+                    }
+                }
+            }
+        })?;
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .wrap_err("Can't create MQTT runtime")?;
+    thread::Builder::new()
+        .name("mqtt status".to_string())
+        .spawn(move || {
+            let mut status = control.watch();
+            let mut last_state = None;
+            let mut last_track = None;
+            loop {
+                let current = status.borrow_and_update().clone();
+                if last_track.as_ref() != Some(&current.track) {
+                    last_track = Some(current.track.clone());
+                    // empty payload clears the image
+                    let artwork = find_artwork(&current.track)
+                        .and_then(|path| fs::read(path).ok())
+                        .unwrap_or_default();
+                    let result = client.publish(
+                        format!("{}/artwork", topic),
+                        QoS::AtLeastOnce,
+                        true,
+                        artwork,
+                    );
+                    if let Err(e) = result {
+                        warn!("Can't publish MQTT artwork: {}", e);
+                    }
+                }
+                if last_state != Some(current.state) {
+                    last_state = Some(current.state);
+                    let result = client.publish(
+                        format!("{}/state", topic),
+                        QoS::AtLeastOnce,
+                        true,
+                        state_name(current.state),
+                    );
+                    if let Err(e) = result {
+                        warn!("Can't publish MQTT state: {}", e);
+                    }
+                }
+                let result = client.publish(
+                    format!("{}/status", topic),
+                    QoS::AtMostOnce,
+                    true,
+                    status_json(&current),
+                );
+                if let Err(e) = result {
+                    warn!("Can't publish MQTT status: {}", e);
+                }
+                if runtime.block_on(status.changed()).is_err() {
+                    // player closed
+                    return;
+                }
+            }
+        })?;
+    Ok(())
+}
+
+fn state_name(state: ControlState) -> &'static str {
+    match state {
+        ControlState::Stopped => "stopped",
+        ControlState::Playing => "playing",
+        ControlState::Paused => "paused",
+    }
+}
+
+fn status_json(status: &ControlStatus) -> String {
+    serde_json::json!({
+        "state": state_name(status.state),
+        "track": status.track,
+        "title": Path::new(&status.track).file_stem().map(|v| v.to_string_lossy()),
+        "position": status.position,
+        "duration": status.duration.map(|d| d.as_secs()),
+        "volume": status.volume,
+        "queue": status.queue.len(),
+    })
+    .to_string()
+}
+
+/// Cover image next to a local track, only JPEG as the image entity has a fixed content type
+fn find_artwork(track: &str) -> Option<PathBuf> {
+    const NAMES: &[&str] = &["cover.jpg", "cover.jpeg", "folder.jpg", "folder.jpeg"];
+    let folder = Path::new(track).parent()?;
+    fs::read_dir(folder)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.file_name()
+                .and_then(|v| v.to_str())
+                .is_some_and(|name| NAMES.iter().any(|v| v.eq_ignore_ascii_case(name)))
+        })
+}
+
+/// Home Assistant discovery topics and configs of all entities
+fn discovery(prefix: &str, topic: &str) -> Vec<(String, String)> {
+    let node = topic.replace('/', "_");
+    let device = serde_json::json!({
+        "identifiers": [node],
+        "name": "Audio Wrench",
+    });
+    let status = format!("{}/status", topic);
+    let entity = |component: &str, id: &str, name: &str, mut config: serde_json::Value| {
+        config["name"] = name.into();
+        config["unique_id"] = format!("{}_{}", node, id).into();
+        config["availability_topic"] = format!("{}/available", topic).into();
+        config["device"] = device.clone();
+        (
+            format!("{}/{}/{}/{}/config", prefix, component, node, id),
+            config.to_string(),
+        )
+    };
+    let mut entities = vec![
+        entity(
+            "sensor",
+            "state",
+            "State",
+            serde_json::json!({ "state_topic": format!("{}/state", topic) }),
+        ),
+        entity(
+            "sensor",
+            "title",
+            "Title",
+            serde_json::json!({ "state_topic": status, "value_template": "{{ value_json.title }}" }),
+        ),
+        entity(
+            "sensor",
+            "position",
+            "Position",
+            serde_json::json!({
+                "state_topic": status,
+                "value_template": "{{ value_json.position }}",
+                "device_class": "duration",
+                "unit_of_measurement": "s",
+            }),
+        ),
+        entity(
+            "sensor",
+            "duration",
+            "Duration",
+            serde_json::json!({
+                "state_topic": status,
+                "value_template": "{{ value_json.duration }}",
+                "device_class": "duration",
+                "unit_of_measurement": "s",
+            }),
+        ),
+        entity(
+            "number",
+            "volume",
+            "Volume",
+            serde_json::json!({
+                "state_topic": status,
+                "value_template": "{{ value_json.volume }}",
+                "command_topic": format!("{}/volume/set", topic),
+                "min": 0,
+                "max": 100,
+                "unit_of_measurement": "%",
+            }),
+        ),
+        entity(
+            "image",
+            "artwork",
+            "Artwork",
+            serde_json::json!({
+                "image_topic": format!("{}/artwork", topic),
+                "content_type": "image/jpeg",
+            }),
+        ),
+    ];
+    for (command, name) in [
+        ("play", "Play"),
+        ("pause", "Pause"),
+        ("next", "Next"),
+        ("stop", "Stop"),
+    ] {
+        entities.push(entity(
+            "button",
+            command,
+            name,
+            serde_json::json!({
+                "command_topic": format!("{}/command", topic),
+                "payload_press": command,
+            }),
+        ));
+    }
+    entities
+}
+
+/// Command for a message on the topic below the base topic
+fn parse_command(topic: &str, payload: &str) -> Option<ControlCommand> {
+    match (topic, payload.to_lowercase().as_str()) {
+        ("/command", "play") => Some(ControlCommand::Play),
+        ("/command", "pause") => Some(ControlCommand::Pause),
+        ("/command", "next") => Some(ControlCommand::Next),
+        ("/command", "stop") => Some(ControlCommand::Stop),
+        ("/command", "clear") => Some(ControlCommand::ClearQueue),
+        ("/volume/set", v) => {
+            // home assistant sends volumes as floats
+            let volume = v
+                .parse::<f32>()
+                .ok()
+                .filter(|v| (0.0..=100.0).contains(v))?;
+            Some(ControlCommand::Volume(volume.round() as u8))
+        }
+        ("/enqueue", _) if !payload.is_empty() => {
+            Some(ControlCommand::Enqueue(vec![payload.to_string()]))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(
+            parse_command("/command", "PLAY"),
+            Some(ControlCommand::Play)
+        );
+        assert_eq!(
+            parse_command("/volume/set", "42.4"),
+            Some(ControlCommand::Volume(42))
+        );
+        assert_eq!(parse_command("/volume/set", "120"), None);
+        assert_eq!(
+            parse_command("/enqueue", "/music/A.mp3"),
+            Some(ControlCommand::Enqueue(vec![String::from("/music/A.mp3")]))
+        );
+        assert_eq!(parse_command("/command", "rewind"), None);
+    }
+
+    #[test]
+    fn test_discovery() {
+        let entities = discovery("homeassistant", "home/audio_wrench");
+        let (topic, config) = entities
+            .iter()
+            .find(|(topic, _)| topic.contains("/button/"))
+            .unwrap();
+        assert_eq!(topic, "homeassistant/button/home_audio_wrench/play/config");
+        let config: serde_json::Value = serde_json::from_str(config).unwrap();
+        assert_eq!(config["command_topic"], "home/audio_wrench/command");
+        assert_eq!(config["unique_id"], "home_audio_wrench_play");
+        assert_eq!(config["device"]["identifiers"][0], "home_audio_wrench");
+    }
+}
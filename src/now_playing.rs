@@ -0,0 +1,78 @@
+//! Export of the current track to a file, for streaming overlays
+
+use std::{fs, path::Path, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExportFormat {
+    /// Title only
+    #[default]
+    Text,
+    /// Title and track location
+    Json,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NowPlayingExport {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+#[derive(Serialize)]
+struct NowPlaying<'a> {
+    title: &'a str,
+    track: &'a str,
+}
+
+impl NowPlayingExport {
+    /// Write `track`, empty when stopped
+    pub fn write(&self, track: &str) {
+        if let Err(e) = self.try_write(track) {
+            warn!("Can't export now playing to {:?}: {}", self.path, e);
+        }
+    }
+
+    fn try_write(&self, track: &str) -> Result<()> {
+        let content = render(self.format, track)?;
+        // rename for readers to never see a partial file
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, content).wrap_err("Can't write file")?;
+        fs::rename(&tmp, &self.path).wrap_err("Can't move file")?;
+        Ok(())
+    }
+}
+
+fn render(format: ExportFormat, track: &str) -> Result<String> {
+    let title = Path::new(track)
+        .file_stem()
+        .map_or_else(String::new, |v| v.to_string_lossy().into_owned());
+    Ok(match format {
+        ExportFormat::Text => title,
+        ExportFormat::Json => serde_json::to_string(&NowPlaying {
+            title: &title,
+            track,
+        })?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            render(ExportFormat::Text, "/music/Some Song.mp3").unwrap(),
+            "Some Song"
+        );
+        assert_eq!(render(ExportFormat::Text, "").unwrap(), "");
+        assert_eq!(
+            render(ExportFormat::Json, "/music/a.ogg").unwrap(),
+            r#"{"title":"a","track":"/music/a.ogg"}"#
+        );
+    }
+}
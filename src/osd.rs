@@ -0,0 +1,153 @@
+//! Borderless track change overlay with title and cover, run as separate process
+//! as iced only supports one window per process.
+//! iced can't position windows, placement is up to the window manager.
+//! The cover is passed on stdin, so it's never written to a file.
+
+use std::{
+    io::{Read, Write},
+    path::Path,
+    process::{Child, Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+use iced::{executor, window, Application, Element, Settings, Subscription};
+use iced_native::{image, Align, Command as IcedCommand, Container, Image, Length, Row, Text};
+
+use crate::prelude::*;
+
+/// Argument starting the overlay instead of the player
+pub const OSD_ARG: &str = "--osd";
+/// Argument after the title telling the overlay to read its cover from stdin
+pub const COVER_ARG: &str = "--cover";
+/// How long the overlay is shown
+const OSD_DURATION: Duration = Duration::from_secs(3);
+/// Side of the shown cover in pixels
+const COVER_SIZE: u16 = 64;
+
+/// Show overlay for `track` with its PNG `cover`, replacing the previous one
+pub fn show(previous: &mut Option<Child>, track: &str, cover: Option<&[u8]>) {
+    if let Some(mut child) = previous.take() {
+        let _ = child.kill();
+        // reaped in the background, the UI doesn't wait for it to exit
+        thread::spawn(move || child.wait());
+    }
+    let title = Path::new(track)
+        .file_stem()
+        .map_or_else(|| track.to_string(), |v| v.to_string_lossy().into_owned());
+    let exe = match std::env::current_exe() {
+        Ok(v) => v,
+        Err(e) => {
+            warn!("Can't find executable for overlay: {}", e);
+            return;
+        }
+    };
+    let mut command = Command::new(exe);
+    command.arg(OSD_ARG).arg(title);
+    if cover.is_some() {
+        command.arg(COVER_ARG).stdin(Stdio::piped());
+    }
+    let mut child = match command.spawn() {
+        Ok(v) => v,
+        Err(e) => return warn!("Can't start overlay: {}", e),
+    };
+    if let (Some(cover), Some(mut stdin)) = (cover, child.stdin.take()) {
+        let cover = cover.to_vec();
+        // larger than the pipe buffer, written while the overlay starts
+        thread::spawn(move || {
+            if let Err(e) = stdin.write_all(&cover) {
+                debug!("Can't pass cover to overlay: {}", e);
+            }
+        });
+    }
+    *previous = Some(child);
+}
+
+/// Forget the overlay process once it closed itself
+pub fn reap(child: &mut Option<Child>) {
+    if let Some(process) = child.as_mut() {
+        match process.try_wait() {
+            Ok(None) => (),
+            Ok(Some(_)) => *child = None,
+            Err(e) => {
+                debug!("Can't check overlay process: {}", e);
+                *child = None;
+            }
+        }
+    }
+}
+
+/// Run overlay window, blocks until closed. With `cover` the PNG is read from stdin.
+pub fn run(title: String, cover: bool) -> Result<()> {
+    let cover = match cover {
+        true => {
+            let mut data = Vec::new();
+            std::io::stdin()
+                .read_to_end(&mut data)
+                .wrap_err("Can't read cover")?;
+            Some(data)
+        }
+        false => None,
+    };
+    let mut settings = Settings::with_flags((title, cover));
+    settings.window = window::Settings {
+        size: (400, 80),
+        resizable: false,
+        decorations: false,
+        always_on_top: true,
+        ..Default::default()
+    };
+    Osd::run(settings).map_err(|e| eyre!("Failed to run overlay: {}", e))
+}
+
+struct Osd {
+    title: String,
+    cover: Option<image::Handle>,
+}
+
+#[derive(Debug, Clone)]
+enum Message {
+    Close,
+}
+
+impl Application for Osd {
+    type Executor = executor::Default;
+    type Message = Message;
+    type Flags = (String, Option<Vec<u8>>);
+
+    fn new((title, cover): (String, Option<Vec<u8>>)) -> (Self, IcedCommand<Message>) {
+        let cover = cover.map(image::Handle::from_memory);
+        (Self { title, cover }, IcedCommand::none())
+    }
+
+    fn title(&self) -> String {
+        String::from("Audio Wrench - Now Playing")
+    }
+
+    fn update(&mut self, message: Message) -> IcedCommand<Message> {
+        match message {
+            Message::Close => std::process::exit(0),
+        }
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        iced::time::every(OSD_DURATION).map(|_| Message::Close)
+    }
+
+    fn view(&mut self) -> Element<'_, Message> {
+        let mut row = Row::new().spacing(10).align_items(Align::Center);
+        if let Some(cover) = self.cover.clone() {
+            row = row.push(
+                Image::new(cover)
+                    .width(Length::Units(COVER_SIZE))
+                    .height(Length::Units(COVER_SIZE)),
+            );
+        }
+        Container::new(row.push(Text::new(&self.title).size(24)))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x()
+            .center_y()
+            .into()
+    }
+}
@@ -0,0 +1,82 @@
+//! Quick access bar with pinned playlists and favorite lists
+
+use std::path::PathBuf;
+
+use iced::Element;
+use iced_native::{button, Align, Button, Row, Text};
+use serde::{Deserialize, Serialize};
+
+use crate::focus::Focus;
+use crate::theme;
+use crate::Message;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Pin {
+    /// Switches to the playlist
+    Playlist(PathBuf),
+    /// Enqueues the favorite list
+    Favorites(String),
+}
+
+impl Pin {
+    fn label(&self) -> String {
+        match self {
+            Pin::Playlist(path) => path
+                .file_stem()
+                .map_or_else(|| path.to_string_lossy(), |v| v.to_string_lossy())
+                .into_owned(),
+            Pin::Favorites(name) => format!("★ {}", name),
+        }
+    }
+}
+
+/// Pin `pin`, or unpin it if it is pinned already
+pub fn toggle(pins: &mut Vec<Pin>, pin: Pin) {
+    match pins.iter().position(|v| *v == pin) {
+        Some(i) => {
+            pins.remove(i);
+        }
+        None => pins.push(pin),
+    }
+}
+
+#[derive(Default)]
+pub struct PinBar {
+    /// Open and unpin per pin
+    buttons: Vec<(button::State, button::State)>,
+}
+
+impl PinBar {
+    pub fn view<'a>(&'a mut self, pins: &[Pin], focus: &mut Focus) -> Element<'a, Message> {
+        self.buttons.resize_with(pins.len(), Default::default);
+        let mut row = Row::new().spacing(10).align_items(Align::Center);
+        for (pin, (open, unpin)) in pins.iter().zip(self.buttons.iter_mut()) {
+            row = row
+                .push(focus.button(
+                    Button::new(open, Text::new(pin.label()).size(theme::text_size(14))),
+                    Message::OpenPin(pin.clone()),
+                ))
+                .push(focus.button(
+                    Button::new(unpin, Text::new("×").size(theme::text_size(12))),
+                    Message::TogglePin(pin.clone()),
+                ));
+        }
+        row.into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_toggle() {
+        let mut pins = vec![Pin::Favorites(String::from("Daily"))];
+        let playlist = Pin::Playlist(PathBuf::from("/p/work.m3u"));
+        toggle(&mut pins, playlist.clone());
+        assert_eq!(pins.len(), 2);
+        assert_eq!(playlist.label(), "work");
+        toggle(&mut pins, Pin::Favorites(String::from("Daily")));
+        assert_eq!(pins, vec![playlist]);
+    }
+}
@@ -0,0 +1,422 @@
+//! Plugin API for side panels and track sources.
+//!
+//! Plugins are compiled in and registered in [Plugins::new], or loaded from dynamic libraries
+//! in the plugin folder, see [NativePlugin] for the C ABI they have to export.
+
+use audio_wrench_core::cd::Lookups;
+use libloading::Library;
+use std::{
+    collections::{HashSet, VecDeque},
+    ffi::{CStr, CString},
+    os::raw::c_char,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+#[cfg(target_os = "linux")]
+use crate::audio_cd::AudioCd;
+use crate::hooks::Event;
+use crate::prelude::*;
+
+/// Entry of a track source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Source specific id, passed to [TrackSource::browse] for folders
+    pub id: String,
+    pub title: String,
+    /// Playable location, None for folders
+    pub track: Option<String>,
+}
+
+/// Job listing the entries of a folder, run on a worker thread so slow sources don't block the UI
+pub type Listing = Box<dyn FnOnce() -> Result<Vec<Entry>> + Send>;
+
+/// Browsable source of tracks
+pub trait TrackSource {
+    /// Job listing the entries below `parent`, None for the root
+    fn browse(&mut self, parent: Option<&str>) -> Listing;
+}
+
+/// Side panel content
+pub struct Panel {
+    pub title: String,
+    pub lines: Vec<String>,
+}
+
+pub trait Plugin {
+    /// Unique name, also used to store the enabled state
+    fn name(&self) -> &'static str;
+
+    /// Called on player events
+    fn on_event(&mut self, _event: Event, _track: &str) {}
+
+    /// Called periodically with the playback position of `track`
+    fn on_progress(&mut self, _track: &str, _position: Duration) {}
+
+    /// Side panel to show, if any
+    fn panel(&self) -> Option<Panel> {
+        None
+    }
+
+    /// Track source provided by this plugin
+    fn source(&mut self) -> Option<&mut dyn TrackSource> {
+        None
+    }
+}
+
+/// Registered plugins and their enabled state
+pub struct Plugins {
+    plugins: Vec<Box<dyn Plugin>>,
+    disabled: HashSet<String>,
+}
+
+impl Plugins {
+    /// `lookups` are the disc names shared with ripping
+    #[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+    pub fn new(disabled: HashSet<String>, lookups: Lookups) -> Self {
+        #[allow(unused_mut)]
+        let mut plugins: Vec<Box<dyn Plugin>> = vec![
+            Box::new(RecentTracks::default()),
+            Box::new(FolderSource::new()),
+        ];
+        // TODO: drives on Windows and macOS, see cd::drives
+        #[cfg(target_os = "linux")]
+        plugins.push(Box::new(AudioCd::new(lookups)));
+        plugins.extend(load_native(&plugin_dir()));
+        Self { plugins, disabled }
+    }
+
+    pub fn register(&mut self, plugin: Box<dyn Plugin>) {
+        self.plugins.push(plugin);
+    }
+
+    /// Names of disabled plugins, for storing in the config
+    pub fn disabled(&self) -> &HashSet<String> {
+        &self.disabled
+    }
+
+    pub fn len(&self) -> usize {
+        self.plugins.len()
+    }
+
+    /// Name and enabled state of all plugins
+    pub fn list(&self) -> impl Iterator<Item = (&'static str, bool)> + '_ {
+        self.plugins
+            .iter()
+            .map(move |p| (p.name(), !self.disabled.contains(p.name())))
+    }
+
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) {
+        if enabled {
+            self.disabled.remove(name);
+        } else {
+            self.disabled.insert(name.to_string());
+        }
+    }
+
+    fn enabled(&mut self) -> impl Iterator<Item = &mut Box<dyn Plugin>> {
+        let disabled = &self.disabled;
+        self.plugins
+            .iter_mut()
+            .filter(move |p| !disabled.contains(p.name()))
+    }
+
+    /// Forward event to all enabled plugins
+    pub fn event(&mut self, event: Event, track: &str) {
+        for plugin in self.enabled() {
+            plugin.on_event(event, track);
+        }
+    }
+
+    /// Forward playback progress to all enabled plugins
+    pub fn progress(&mut self, track: &str, position: Duration) {
+        for plugin in self.enabled() {
+            plugin.on_progress(track, position);
+        }
+    }
+
+    /// Panels of all enabled plugins
+    pub fn panels(&self) -> Vec<Panel> {
+        self.plugins
+            .iter()
+            .filter(|p| !self.disabled.contains(p.name()))
+            .filter_map(|p| p.panel())
+            .collect()
+    }
+
+    /// Whether plugin at `index` is enabled and provides a track source
+    pub fn has_source(&mut self, index: usize) -> bool {
+        self.source(index).is_some()
+    }
+
+    /// Track source of enabled plugin at `index`
+    pub fn source(&mut self, index: usize) -> Option<&mut dyn TrackSource> {
+        let plugin = self.plugins.get_mut(index)?;
+        if self.disabled.contains(plugin.name()) {
+            return None;
+        }
+        plugin.source()
+    }
+}
+
+/// Version of the C ABI, returned by `audio_wrench_plugin_api_version`
+pub const API_VERSION: u32 = 1;
+
+/// Folder native plugins are loaded from
+pub fn plugin_dir() -> PathBuf {
+    let mut folder = dirs::data_local_dir().unwrap_or_default();
+    folder.push("audio_wrench_plugins");
+    folder
+}
+
+/// Load all dynamic libraries in `dir`, skipping those that can't be loaded
+fn load_native(dir: &Path) -> Vec<Box<dyn Plugin>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(v) => v,
+        Err(_) => return Vec::new(),
+    };
+    let mut plugins: Vec<Box<dyn Plugin>> = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|v| v.to_str()) != Some(std::env::consts::DLL_EXTENSION) {
+            continue;
+        }
+        match NativePlugin::load(&path) {
+            Ok(plugin) => {
+                info!("Loaded plugin {} from {:?}", plugin.name, path);
+                plugins.push(Box::new(plugin));
+            }
+            Err(e) => warn!("Can't load plugin {:?}: {:?}", path, e),
+        }
+    }
+    plugins
+}
+
+/// Plugin loaded from a dynamic library.
+///
+/// Version 1 of the ABI consists of these `extern "C"` functions:
+/// - `uint32_t audio_wrench_plugin_api_version()` returning [API_VERSION]
+/// - `const char *audio_wrench_plugin_name()` returning a static, unique name
+/// - optional `void audio_wrench_plugin_on_event(uint32_t event, const char *track)`,
+///   event being 0 for track started, 1 track ended, 2 favorited and 3 trashed
+/// - optional `void audio_wrench_plugin_on_progress(const char *track, uint64_t position_ms)`
+/// - optional `const char *audio_wrench_plugin_panel()` returning the panel title and lines
+///   separated by newlines, or NULL for no panel. The string has to stay valid until the next call.
+///
+/// All strings are UTF-8 and NUL terminated. Functions are only called from the UI thread.
+struct NativePlugin {
+    name: &'static str,
+    on_event: Option<unsafe extern "C" fn(u32, *const c_char)>,
+    on_progress: Option<unsafe extern "C" fn(*const c_char, u64)>,
+    panel: Option<unsafe extern "C" fn() -> *const c_char>,
+    /// Keeps the functions above loaded
+    _library: Library,
+}
+
+impl NativePlugin {
+    fn load(path: &Path) -> Result<Self> {
+        // SAFETY: running the initializers of a library the user put into the plugin folder
+        let library = unsafe { Library::new(path) }?;
+        // SAFETY: signatures as documented for the ABI version checked first
+        unsafe {
+            let version = library
+                .get::<unsafe extern "C" fn() -> u32>(b"audio_wrench_plugin_api_version\0")
+                .wrap_err("Not an audio wrench plugin")?;
+            let version = version();
+            if version != API_VERSION {
+                return Err(eyre!(
+                    "Plugin API version {} not supported, expected {}",
+                    version,
+                    API_VERSION
+                ));
+            }
+            let name = library
+                .get::<unsafe extern "C" fn() -> *const c_char>(b"audio_wrench_plugin_name\0")?(
+            );
+            if name.is_null() {
+                return Err(eyre!("Plugin has no name"));
+            }
+            // loaded once at startup and kept until exit
+            let name: &'static str =
+                Box::leak(CStr::from_ptr(name).to_str()?.to_owned().into_boxed_str());
+            Ok(Self {
+                name,
+                on_event: library
+                    .get(b"audio_wrench_plugin_on_event\0")
+                    .ok()
+                    .map(|v| *v),
+                on_progress: library
+                    .get(b"audio_wrench_plugin_on_progress\0")
+                    .ok()
+                    .map(|v| *v),
+                panel: library.get(b"audio_wrench_plugin_panel\0").ok().map(|v| *v),
+                _library: library,
+            })
+        }
+    }
+}
+
+/// Code of `event` in the plugin ABI
+fn event_code(event: Event) -> u32 {
+    match event {
+        Event::TrackStarted => 0,
+        Event::TrackEnded => 1,
+        Event::Favorited => 2,
+        Event::Trashed => 3,
+    }
+}
+
+/// Panel of the newline separated title and lines
+fn parse_panel(text: &str) -> Panel {
+    let mut lines = text.lines().map(str::to_string);
+    Panel {
+        title: lines.next().unwrap_or_default(),
+        lines: lines.collect(),
+    }
+}
+
+impl Plugin for NativePlugin {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn on_event(&mut self, event: Event, track: &str) {
+        if let (Some(on_event), Ok(track)) = (self.on_event, CString::new(track)) {
+            // SAFETY: ABI function, string valid for the call
+            unsafe { on_event(event_code(event), track.as_ptr()) }
+        }
+    }
+
+    fn on_progress(&mut self, track: &str, position: Duration) {
+        if let (Some(on_progress), Ok(track)) = (self.on_progress, CString::new(track)) {
+            // SAFETY: ABI function, string valid for the call
+            unsafe { on_progress(track.as_ptr(), position.as_millis() as u64) }
+        }
+    }
+
+    fn panel(&self) -> Option<Panel> {
+        let panel = self.panel?;
+        // SAFETY: ABI function, returned string stays valid until the next call
+        let text = unsafe {
+            let text = panel();
+            if text.is_null() {
+                return None;
+            }
+            CStr::from_ptr(text).to_string_lossy().into_owned()
+        };
+        Some(parse_panel(&text))
+    }
+}
+
+/// Amount of tracks shown by [RecentTracks]
+const RECENT_TRACKS: usize = 5;
+
+/// Panel showing the last played tracks
+#[derive(Default)]
+struct RecentTracks {
+    tracks: VecDeque<String>,
+}
+
+impl Plugin for RecentTracks {
+    fn name(&self) -> &'static str {
+        "Recent Tracks"
+    }
+
+    fn on_event(&mut self, event: Event, track: &str) {
+        if event == Event::TrackStarted {
+            self.tracks.push_front(track.to_string());
+            self.tracks.truncate(RECENT_TRACKS);
+        }
+    }
+
+    fn panel(&self) -> Option<Panel> {
+        Some(Panel {
+            title: String::from("Recently played"),
+            lines: self.tracks.iter().cloned().collect(),
+        })
+    }
+}
+
+/// Local folder browser, starting at the users music directory
+struct FolderSource {
+    root: Option<PathBuf>,
+}
+
+impl FolderSource {
+    fn new() -> Self {
+        Self {
+            root: dirs::audio_dir().or_else(dirs::home_dir),
+        }
+    }
+}
+
+impl Plugin for FolderSource {
+    fn name(&self) -> &'static str {
+        "Folders"
+    }
+
+    fn source(&mut self) -> Option<&mut dyn TrackSource> {
+        Some(self)
+    }
+}
+
+impl TrackSource for FolderSource {
+    fn browse(&mut self, parent: Option<&str>) -> Listing {
+        let dir = parent.map(PathBuf::from).or_else(|| self.root.clone());
+        Box::new(move || {
+            let dir = dir.ok_or_else(|| eyre!("No music directory found"))?;
+            list_folder(&dir)
+        })
+    }
+}
+
+fn list_folder(dir: &Path) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    for entry in std::fs::read_dir(dir).wrap_err_with(|| format!("Can't read {:?}", dir))? {
+        let path = entry?.path();
+        let title = match path.file_name() {
+            Some(v) => v.to_string_lossy().into_owned(),
+            None => continue,
+        };
+        let id = path.to_string_lossy().into_owned();
+        if path.is_dir() {
+            entries.push(Entry {
+                id,
+                title,
+                track: None,
+            });
+        } else if crate::is_audio_file(&path) || crate::is_playlist_file(&path) {
+            entries.push(Entry {
+                track: Some(id.clone()),
+                id,
+                title,
+            });
+        }
+    }
+    entries.sort_by(|a, b| (a.track.is_some(), &a.title).cmp(&(b.track.is_some(), &b.title)));
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_native() {
+        let dir = std::env::temp_dir().join(format!("audio_wrench_plugins_{}", std::process::id()));
+        assert!(load_native(&dir).is_empty());
+        std::fs::create_dir_all(&dir).unwrap();
+        let fake = dir.join(format!("fake.{}", std::env::consts::DLL_EXTENSION));
+        std::fs::write(&fake, b"not a library").unwrap();
+        std::fs::write(dir.join("notes.txt"), b"ignored").unwrap();
+        assert!(NativePlugin::load(&fake).is_err());
+        assert!(load_native(&dir).is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let panel = parse_panel("Title\nfirst\nsecond");
+        assert_eq!(panel.title, "Title");
+        assert_eq!(panel.lines, ["first", "second"]);
+        assert!(parse_panel("").title.is_empty());
+        assert_eq!(event_code(Event::Trashed), 3);
+    }
+}
@@ -0,0 +1,339 @@
+//! Queue popped out into its own window, run as separate process like the overlay
+//! as iced only supports one window per process.
+//! The window is another remote control: the player status arrives as JSON lines on its
+//! stdin, pressed buttons go back as command lines on its stdout.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::mpsc::{channel, Receiver},
+    thread,
+    time::Duration,
+};
+
+use iced::{executor, window, Application, Element, Settings, Subscription};
+use iced_native::{
+    button, scrollable, Align, Button, Column, Command as IcedCommand, Length, Row, Scrollable,
+    Text,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::control::{ControlCommand, ControlHandle, ControlState, ControlStatus};
+use crate::i18n::{self, Language};
+use crate::prelude::*;
+use crate::theme;
+
+/// Argument starting the queue window instead of the player
+pub const QUEUE_ARG: &str = "--queue-window";
+/// Interval for checking status changes on both sides
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Part of the player status shown in the window
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct QueueStatus {
+    /// Empty if stopped
+    track: String,
+    paused: bool,
+    queue: Vec<String>,
+}
+
+impl From<ControlStatus> for QueueStatus {
+    fn from(v: ControlStatus) -> Self {
+        Self {
+            track: v.track,
+            paused: v.state == ControlState::Paused,
+            queue: v.queue,
+        }
+    }
+}
+
+/// Line sent by the window for a pressed button
+fn command_line(command: &ControlCommand) -> Option<&'static str> {
+    match command {
+        ControlCommand::Play => Some("play"),
+        ControlCommand::Pause => Some("pause"),
+        ControlCommand::Next => Some("next"),
+        ControlCommand::ClearQueue => Some("clear"),
+        _ => None,
+    }
+}
+
+/// Command of a line sent by the window, None for anything else
+fn parse_command(line: &str) -> Option<ControlCommand> {
+    match line.trim() {
+        "play" => Some(ControlCommand::Play),
+        "pause" => Some(ControlCommand::Pause),
+        "next" => Some(ControlCommand::Next),
+        "clear" => Some(ControlCommand::ClearQueue),
+        _ => None,
+    }
+}
+
+/// Start the queue window in `language`, connected to the player through `control`
+pub fn open(control: ControlHandle, language: Language) -> Result<Child> {
+    let exe = std::env::current_exe().wrap_err("Can't find executable for queue window")?;
+    let mut child = Command::new(exe)
+        .arg(QUEUE_ARG)
+        .arg(serde_json::to_string(&language)?)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .wrap_err("Can't start queue window")?;
+    let stdin = child.stdin.take().expect("stdin is piped");
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let status = control.clone();
+    thread::Builder::new()
+        .name("queue window status".to_string())
+        .spawn(move || {
+            if let Err(e) = send_status(status, stdin) {
+                debug!("Queue window closed: {}", e);
+            }
+        })?;
+    thread::Builder::new()
+        .name("queue window commands".to_string())
+        .spawn(move || receive_commands(control, stdout))?;
+    Ok(child)
+}
+
+/// Write every status change until the window or the player is gone
+fn send_status(control: ControlHandle, mut stdin: ChildStdin) -> Result<()> {
+    let mut watch = control.watch();
+    let mut last = None;
+    loop {
+        let status = QueueStatus::from(watch.borrow_and_update().clone());
+        if last.as_ref() != Some(&status) {
+            writeln!(stdin, "{}", serde_json::to_string(&status)?)?;
+            stdin.flush()?;
+            last = Some(status);
+        }
+        thread::sleep(POLL_INTERVAL);
+        // the player dropped the sender
+        watch.has_changed()?;
+    }
+}
+
+fn receive_commands(control: ControlHandle, stdout: ChildStdout) {
+    for line in BufReader::new(stdout).lines() {
+        let line = match line {
+            Ok(v) => v,
+            Err(_) => return,
+        };
+        match parse_command(&line) {
+            Some(command) => {
+                if !control.send(command) {
+                    return;
+                }
+            }
+            None => debug!("Unknown queue window command {}", line),
+        }
+    }
+}
+
+/// Forget the window process once it was closed
+pub fn reap(child: &mut Option<Child>) {
+    if let Some(process) = child.as_mut() {
+        match process.try_wait() {
+            Ok(None) => (),
+            Ok(Some(_)) => *child = None,
+            Err(e) => {
+                debug!("Can't check queue window process: {}", e);
+                *child = None;
+            }
+        }
+    }
+}
+
+/// Run the queue window, blocks until closed
+pub fn run(language: &str) -> Result<()> {
+    if let Ok(language) = serde_json::from_str(language) {
+        i18n::set_language(language);
+    }
+    let mut settings = Settings::with_flags(());
+    settings.window = window::Settings {
+        size: (400, 600),
+        ..Default::default()
+    };
+    QueueWindow::run(settings).map_err(|e| eyre!("Failed to run queue window: {}", e))
+}
+
+/// Status lines of the player, None once it is gone
+fn read_status() -> Receiver<Option<QueueStatus>> {
+    let (tx, rx) = channel();
+    thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let line = match line {
+                Ok(v) => v,
+                Err(_) => break,
+            };
+            match serde_json::from_str(&line) {
+                Ok(status) => {
+                    if tx.send(Some(status)).is_err() {
+                        return;
+                    }
+                }
+                Err(e) => warn!("Invalid queue window status: {}", e),
+            }
+        }
+        let _ = tx.send(None);
+    });
+    rx
+}
+
+struct QueueWindow {
+    status: QueueStatus,
+    rx: Receiver<Option<QueueStatus>>,
+    play_pause: button::State,
+    next: button::State,
+    clear: button::State,
+    scroll: scrollable::State,
+}
+
+#[derive(Debug, Clone)]
+enum Message {
+    Tick,
+    Send(ControlCommand),
+}
+
+impl Application for QueueWindow {
+    type Executor = executor::Default;
+    type Message = Message;
+    type Flags = ();
+
+    fn new(_: ()) -> (Self, IcedCommand<Message>) {
+        let window = Self {
+            status: QueueStatus::default(),
+            rx: read_status(),
+            play_pause: Default::default(),
+            next: Default::default(),
+            clear: Default::default(),
+            scroll: Default::default(),
+        };
+        (window, IcedCommand::none())
+    }
+
+    fn title(&self) -> String {
+        String::from("Audio Wrench - Queue")
+    }
+
+    fn update(&mut self, message: Message) -> IcedCommand<Message> {
+        match message {
+            Message::Tick => {
+                for status in self.rx.try_iter() {
+                    match status {
+                        Some(v) => self.status = v,
+                        // the player quit
+                        None => std::process::exit(0),
+                    }
+                }
+            }
+            Message::Send(command) => {
+                if let Some(line) = command_line(&command) {
+                    let mut stdout = std::io::stdout().lock();
+                    if writeln!(stdout, "{}", line)
+                        .and_then(|_| stdout.flush())
+                        .is_err()
+                    {
+                        std::process::exit(0);
+                    }
+                }
+            }
+        }
+        IcedCommand::none()
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        iced::time::every(POLL_INTERVAL).map(|_| Message::Tick)
+    }
+
+    fn view(&mut self) -> Element<'_, Message> {
+        let (label, command) = match self.status.paused || self.status.track.is_empty() {
+            true => (tr!("play"), ControlCommand::Play),
+            false => (tr!("pause"), ControlCommand::Pause),
+        };
+        let controls = Row::new()
+            .spacing(10)
+            .align_items(Align::Center)
+            .push(
+                Button::new(
+                    &mut self.play_pause,
+                    Text::new(label).size(theme::text_size(14)),
+                )
+                .on_press(Message::Send(command)),
+            )
+            .push(
+                Button::new(
+                    &mut self.next,
+                    Text::new(tr!("next")).size(theme::text_size(14)),
+                )
+                .on_press(Message::Send(ControlCommand::Next)),
+            )
+            .push(
+                Button::new(
+                    &mut self.clear,
+                    Text::new(tr!("clear-queue")).size(theme::text_size(14)),
+                )
+                .on_press(Message::Send(ControlCommand::ClearQueue)),
+            );
+        let current = match self.status.track.is_empty() {
+            true => tr!("tui-nothing"),
+            false => title(&self.status.track),
+        };
+        let mut queue = Scrollable::new(&mut self.scroll)
+            .spacing(5)
+            .width(Length::Fill)
+            .height(Length::Fill);
+        for track in self.status.queue.iter() {
+            queue = queue.push(Text::new(title(track)).size(theme::text_size(14)));
+        }
+        Column::new()
+            .padding(10)
+            .spacing(10)
+            .push(Text::new(current).size(theme::text_size(20)))
+            .push(controls)
+            .push(
+                Text::new(tr!("tui-queue", count = self.status.queue.len()))
+                    .size(theme::text_size(16)),
+            )
+            .push(queue)
+            .into()
+    }
+}
+
+/// File name of a track without its extension
+fn title(track: &str) -> String {
+    Path::new(track)
+        .file_stem()
+        .map_or_else(|| track.to_string(), |v| v.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_protocol() {
+        for command in [
+            ControlCommand::Play,
+            ControlCommand::Pause,
+            ControlCommand::Next,
+            ControlCommand::ClearQueue,
+        ] {
+            let line = command_line(&command).unwrap();
+            assert_eq!(parse_command(line), Some(command));
+        }
+        assert_eq!(command_line(&ControlCommand::Volume(10)), None);
+        assert_eq!(parse_command("volume 10"), None);
+
+        let status = QueueStatus::from(ControlStatus {
+            state: ControlState::Paused,
+            track: String::from("/music/a.mp3"),
+            queue: vec![String::from("/music/b.mp3")],
+            ..Default::default()
+        });
+        assert!(status.paused);
+        let line = serde_json::to_string(&status).unwrap();
+        assert_eq!(serde_json::from_str::<QueueStatus>(&line).unwrap(), status);
+        assert_eq!(title("/music/b.mp3"), "b");
+    }
+}
@@ -0,0 +1,74 @@
+//! Radio started from a track, the queue is refilled with similar tracks as it plays
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::mpsc::Receiver,
+};
+
+use audio_wrench_core::library;
+use audio_wrench_core::metadata::TrackInfo;
+use rand::thread_rng;
+
+use crate::lastfm::{self, LastFmConfig};
+
+pub struct Radio {
+    seed: String,
+    info: TrackInfo,
+    /// From Last.fm, empty until fetched or if not configured
+    similar_artists: Vec<String>,
+    pending_artists: Option<Receiver<Vec<String>>>,
+    /// Tracks queued by the radio so far, not queued again
+    added: HashSet<String>,
+}
+
+impl Radio {
+    pub fn new(seed: String, info: TrackInfo, lastfm: Option<&LastFmConfig>) -> Self {
+        let pending_artists = lastfm
+            .zip(info.artist.clone())
+            .map(|(config, artist)| lastfm::spawn_similar_artists(config.clone(), artist));
+        let added = std::iter::once(seed.clone()).collect();
+        Self {
+            seed,
+            info,
+            similar_artists: Vec::new(),
+            pending_artists,
+            added,
+        }
+    }
+
+    pub fn seed(&self) -> &str {
+        &self.seed
+    }
+
+    /// Take similar artists once fetched
+    pub fn poll(&mut self) {
+        if let Some(artists) = self
+            .pending_artists
+            .as_ref()
+            .and_then(|rx| rx.try_recv().ok())
+        {
+            self.similar_artists = artists;
+            self.pending_artists = None;
+        }
+    }
+
+    /// Up to `count` similar `playable` tracks not queued before by this radio
+    pub fn next_tracks(
+        &mut self,
+        metadata: &HashMap<String, TrackInfo>,
+        playable: impl Fn(&str) -> bool,
+        count: usize,
+    ) -> Vec<String> {
+        let tracks = metadata.iter().filter(|(t, _)| playable(t));
+        let found = library::similar(
+            &self.info,
+            &self.similar_artists,
+            tracks,
+            &self.added,
+            count,
+            &mut thread_rng(),
+        );
+        self.added.extend(found.iter().cloned());
+        found
+    }
+}
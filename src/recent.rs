@@ -0,0 +1,108 @@
+//! Albums recently added to the library, by file modification time
+
+use std::{collections::HashMap, fmt};
+
+use audio_wrench_core::library::{self, Album};
+use audio_wrench_core::metadata::TrackInfo;
+use iced::Element;
+use iced_native::{button, pick_list, Align, Button, Column, PickList, Row, Text};
+
+use crate::focus::Focus;
+use crate::theme;
+use crate::Message;
+
+/// Time span offered in the picker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Days(pub u64);
+
+impl Days {
+    const CHOICES: [Days; 4] = [Days(7), Days(30), Days(90), Days(365)];
+}
+
+impl fmt::Display for Days {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&tr!("last-days", days = self.0))
+    }
+}
+
+pub struct RecentView {
+    days: Days,
+    albums: Vec<Album>,
+    album_buttons: Vec<button::State>,
+    days_pick: pick_list::State<Days>,
+    close: button::State,
+}
+
+impl RecentView {
+    pub fn new(metadata: &HashMap<String, TrackInfo>, now: u64) -> Self {
+        let mut view = Self {
+            days: Days(30),
+            albums: Vec::new(),
+            album_buttons: Vec::new(),
+            days_pick: Default::default(),
+            close: Default::default(),
+        };
+        view.update(metadata, now);
+        view
+    }
+
+    pub fn set_days(&mut self, days: Days) {
+        self.days = days;
+    }
+
+    /// Collect the albums again after the time span or metadata changed
+    pub fn update(&mut self, metadata: &HashMap<String, TrackInfo>, now: u64) {
+        let since = now.saturating_sub(self.days.0 * 24 * 60 * 60);
+        self.albums = library::recently_added(metadata.iter(), since);
+        self.album_buttons = vec![Default::default(); self.albums.len()];
+    }
+
+    pub fn album(&self, index: usize) -> Option<&Album> {
+        self.albums.get(index)
+    }
+
+    pub fn view(&mut self, focus: &mut Focus) -> Element<'_, Message> {
+        let controls = Row::new()
+            .spacing(10)
+            .align_items(Align::Center)
+            .push(focus.button(
+                Button::new(
+                    &mut self.close,
+                    Text::new(tr!("close")).size(theme::text_size(14)),
+                ),
+                Message::CloseRecent,
+            ))
+            .push(Text::new(tr!("recently-added")).size(theme::text_size(16)))
+            .push(PickList::new(
+                &mut self.days_pick,
+                &Days::CHOICES[..],
+                Some(self.days),
+                Message::RecentDays,
+            ));
+        let mut column = Column::new().spacing(5).push(controls);
+        if self.albums.is_empty() {
+            column = column.push(Text::new(tr!("nothing-added")).size(theme::text_size(14)));
+        }
+        for (i, (album, state)) in self
+            .albums
+            .iter()
+            .zip(self.album_buttons.iter_mut())
+            .enumerate()
+        {
+            let label = match album.artist.is_empty() {
+                true => format!("+ {} ({})", album.title, album.tracks.len()),
+                false => format!(
+                    "+ {} - {} ({})",
+                    album.artist,
+                    album.title,
+                    album.tracks.len()
+                ),
+            };
+            column = column.push(focus.button(
+                Button::new(state, Text::new(label).size(theme::text_size(14))),
+                Message::EnqueueRecent(i),
+            ));
+        }
+        column.into()
+    }
+}
@@ -0,0 +1,385 @@
+//! Ripping audio CDs into the library: pick tracks, format and quality, then rip in the background
+
+use std::path::{Path, PathBuf};
+
+use iced::Element;
+use iced_native::{
+    button, pick_list, text_input, Align, Button, Checkbox, Column, Length, PickList, ProgressBar,
+    Row, Text, TextInput,
+};
+use serde::{Deserialize, Serialize};
+
+use audio_wrench_core::{
+    cd::{self, Disc, Drive, Lookups, Toc},
+    rip::{RipFormat, RipJob, RipProgress, RipSummary, Ripper},
+};
+
+use crate::focus::Focus;
+use crate::prelude::*;
+use crate::theme;
+use crate::Message;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RipSettings {
+    /// Empty for the first library folder
+    #[serde(default)]
+    pub folder: String,
+    #[serde(default)]
+    pub format: RipFormat,
+    /// Read every track twice and compare the checksums
+    #[serde(default = "default_verify")]
+    pub verify: bool,
+}
+
+fn default_verify() -> bool {
+    true
+}
+
+impl Default for RipSettings {
+    fn default() -> Self {
+        Self {
+            folder: String::new(),
+            format: RipFormat::default(),
+            verify: default_verify(),
+        }
+    }
+}
+
+/// Loaded disc and the tracks selected for ripping
+struct LoadedDisc {
+    drive: Drive,
+    artist: String,
+    album: String,
+    /// Title and whether to rip it, by track
+    tracks: Vec<(String, bool)>,
+}
+
+/// Panel in the settings and the running rip
+#[derive(Default)]
+pub struct RipPanel {
+    pub settings: RipSettings,
+    drives: Vec<Drive>,
+    /// Drive whose disc is read right now
+    reading: Option<Drive>,
+    disc: Option<LoadedDisc>,
+    /// Disc names, shared with the audio CD plugin
+    lookups: Lookups,
+    ripper: Option<Ripper>,
+    /// Tracks done and total of the running rip
+    progress: (usize, usize),
+    /// Outcome of the last rip
+    result: Option<String>,
+    drive_pick: pick_list::State<Drive>,
+    load: button::State,
+    folder_input: text_input::State,
+    format_pick: pick_list::State<RipFormat>,
+    start: button::State,
+    cancel: button::State,
+}
+
+impl RipPanel {
+    pub fn new(settings: RipSettings, lookups: Lookups) -> Self {
+        Self {
+            settings,
+            lookups,
+            ..Default::default()
+        }
+    }
+
+    pub fn text_focused(&self) -> bool {
+        self.folder_input.is_focused()
+    }
+
+    /// Find the drives, returns the first one to read the disc of
+    pub fn load(&mut self) -> Option<Drive> {
+        self.drives = cd::drives();
+        let drive = self.drives.first().cloned();
+        if drive.is_none() {
+            self.result = Some(tr!("rip-no-drive"));
+        }
+        drive
+    }
+
+    /// Job reading the tracks of the disc in `drive` and looking up their names,
+    /// pass its result to [RipPanel::disc_read]
+    pub fn select_drive(
+        &mut self,
+        drive: Drive,
+    ) -> impl FnOnce() -> std::result::Result<(Toc, Option<Disc>), String> + Send + 'static {
+        self.disc = None;
+        self.result = Some(tr!("loading"));
+        self.reading = Some(drive.clone());
+        let lookups = self.lookups.clone();
+        move || {
+            let toc = Toc::read(&drive.device).map_err(|e| {
+                warn!("{:?}", e);
+                e.to_string()
+            })?;
+            // names are optional, the tracks rip without
+            let disc = lookups.disc(&toc);
+            Ok((toc, disc))
+        }
+    }
+
+    /// Show the disc read in `drive`, unless another drive was picked meanwhile
+    pub fn disc_read(
+        &mut self,
+        drive: Drive,
+        result: std::result::Result<(Toc, Option<Disc>), String>,
+    ) {
+        if self.reading.as_ref() != Some(&drive) {
+            return;
+        }
+        self.reading = None;
+        let (toc, disc) = match result {
+            Ok(v) => v,
+            Err(_) => {
+                self.result = Some(tr!("rip-no-disc", drive = drive.name));
+                return;
+            }
+        };
+        let tracks = (0..toc.offsets.len())
+            .map(|i| {
+                let title = disc
+                    .as_ref()
+                    .and_then(|d| d.tracks.get(i).cloned())
+                    .unwrap_or_else(|| format!("Track {}", i + 1));
+                (title, true)
+            })
+            .collect();
+        let (artist, album) = match disc {
+            Some(v) => (v.artist, v.title),
+            None => (String::from("Unknown Artist"), toc.musicbrainz_id()),
+        };
+        self.result = None;
+        self.disc = Some(LoadedDisc {
+            drive,
+            artist,
+            album,
+            tracks,
+        });
+    }
+
+    pub fn toggle_track(&mut self, index: usize, rip: bool) {
+        if let Some(track) = self.disc.as_mut().and_then(|d| d.tracks.get_mut(index)) {
+            track.1 = rip;
+        }
+    }
+
+    /// Start ripping the selected tracks, into `library` if no folder is set
+    pub fn start(&mut self, library: Option<&Path>) {
+        if self.ripper.is_some() {
+            return;
+        }
+        let disc = match self.disc.as_ref() {
+            Some(v) => v,
+            None => return,
+        };
+        let folder = match self.settings.folder.trim() {
+            "" => match library
+                .map(Path::to_path_buf)
+                .or_else(dirs::audio_dir)
+                .or_else(dirs::home_dir)
+            {
+                Some(v) => v,
+                None => {
+                    self.result = Some(tr!("send-no-folder"));
+                    return;
+                }
+            },
+            v => PathBuf::from(v),
+        };
+        let tracks: Vec<(u32, String)> = disc
+            .tracks
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, rip))| *rip)
+            .map(|(i, (title, _))| (i as u32 + 1, title.clone()))
+            .collect();
+        if tracks.is_empty() {
+            self.result = Some(tr!("rip-nothing"));
+            return;
+        }
+        info!("Ripping {} tracks to {:?}", tracks.len(), folder);
+        self.progress = (0, tracks.len());
+        self.result = None;
+        self.ripper = Some(Ripper::start(RipJob {
+            device: disc.drive.device.clone(),
+            tracks,
+            disc_tracks: disc.tracks.len() as u32,
+            artist: disc.artist.clone(),
+            album: disc.album.clone(),
+            format: self.settings.format,
+            verify: self.settings.verify,
+            folder,
+        }));
+    }
+
+    pub fn cancel(&mut self) {
+        if let Some(ripper) = self.ripper.as_ref() {
+            ripper.cancel();
+        }
+    }
+
+    /// Read updates of the running rip, returns the files of a finished rip
+    pub fn poll(&mut self) -> Option<Vec<PathBuf>> {
+        let updates = match self.ripper.as_ref() {
+            Some(v) => v.poll(),
+            None => return None,
+        };
+        let mut files = None;
+        for update in updates {
+            match update {
+                RipProgress::Ripping { done, total } => self.progress = (done, total),
+                RipProgress::Finished(summary) => {
+                    self.result = Some(summary_text(&summary, self.settings.verify));
+                    files = Some(summary.files);
+                    self.ripper = None;
+                }
+                RipProgress::Failed(e) => {
+                    self.result = Some(e);
+                    self.ripper = None;
+                }
+            }
+        }
+        files
+    }
+
+    pub fn view(&mut self, focus: &mut Focus) -> Element<'_, Message> {
+        if !cfg!(target_os = "linux") {
+            return Text::new(tr!("rip-unsupported"))
+                .size(theme::text_size(14))
+                .into();
+        }
+        let mut row = Row::new()
+            .spacing(10)
+            .align_items(Align::Center)
+            .push(focus.button(
+                Button::new(
+                    &mut self.load,
+                    Text::new(tr!("rip-load")).size(theme::text_size(14)),
+                ),
+                Message::LoadCd,
+            ));
+        if self.drives.len() > 1 {
+            row = row.push(
+                PickList::new(
+                    &mut self.drive_pick,
+                    &self.drives[..],
+                    self.disc.as_ref().map(|d| d.drive.clone()),
+                    Message::RipDriveSelected,
+                )
+                .text_size(theme::text_size(14)),
+            );
+        }
+        row = row
+            .push(
+                TextInput::new(
+                    &mut self.folder_input,
+                    &tr!("rip-folder"),
+                    &self.settings.folder,
+                    Message::RipFolderChanged,
+                )
+                .padding(5),
+            )
+            .push(
+                PickList::new(
+                    &mut self.format_pick,
+                    &RipFormat::ALL[..],
+                    Some(self.settings.format),
+                    Message::RipFormatSelected,
+                )
+                .text_size(theme::text_size(14)),
+            )
+            .push(Checkbox::new(
+                self.settings.verify,
+                tr!("rip-verify"),
+                Message::RipVerifyToggled,
+            ));
+        if self.disc.is_some() {
+            row = match self.ripper.is_some() {
+                true => row.push(focus.button(
+                    Button::new(
+                        &mut self.cancel,
+                        Text::new(tr!("cancel")).size(theme::text_size(14)),
+                    ),
+                    Message::CancelRip,
+                )),
+                false => row.push(focus.button(
+                    Button::new(
+                        &mut self.start,
+                        Text::new(tr!("rip-start")).size(theme::text_size(14)),
+                    ),
+                    Message::RipCd,
+                )),
+            };
+        }
+        let mut column = Column::new().spacing(5).push(row);
+        if let Some(disc) = self.disc.as_ref() {
+            column = column.push(
+                Text::new(format!("{} - {}", disc.artist, disc.album)).size(theme::text_size(14)),
+            );
+            for (i, (title, rip)) in disc.tracks.iter().enumerate() {
+                column = column.push(Checkbox::new(
+                    *rip,
+                    format!("{:02}. {}", i + 1, title),
+                    move |v| Message::RipTrackToggled(i, v),
+                ));
+            }
+        }
+        if self.ripper.is_some() {
+            let (done, total) = self.progress;
+            column = column.push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(
+                        ProgressBar::new(0.0..=total.max(1) as f32, done as f32)
+                            .height(Length::Units(10)),
+                    )
+                    .push(
+                        Text::new(tr!("rip-progress", done = done, total = total))
+                            .size(theme::text_size(14)),
+                    ),
+            );
+        } else if let Some(ref result) = self.result {
+            column = column.push(Text::new(result.as_str()).size(theme::text_size(14)));
+        }
+        column.into()
+    }
+}
+
+fn summary_text(summary: &RipSummary, verify: bool) -> String {
+    match verify {
+        true => tr!(
+            "rip-finished-verified",
+            ripped = summary.files.len(),
+            verified = summary.verified,
+            mismatched = summary.mismatched.len()
+        ),
+        false => tr!("rip-finished", ripped = summary.files.len()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        crate::i18n::set_language(crate::i18n::Language::English);
+        let summary = RipSummary {
+            files: vec![
+                PathBuf::from("01 Intro.flac"),
+                PathBuf::from("03 Outro.flac"),
+            ],
+            verified: 2,
+            mismatched: vec![2],
+        };
+        assert_eq!(
+            summary_text(&summary, true),
+            "2 tracks ripped, 2 verified, 1 read differently twice and skipped"
+        );
+        assert_eq!(summary_text(&summary, false), "2 tracks ripped");
+    }
+}
@@ -0,0 +1,178 @@
+//! Library search by text and facets, the results can be enqueued at once
+//! or selected for bulk actions
+
+use std::{collections::HashMap, fmt};
+
+use audio_wrench_core::library::{self, FacetValues, Facets};
+use audio_wrench_core::metadata::TrackInfo;
+use audio_wrench_core::selection::Selection;
+use iced::Element;
+use iced_native::{
+    button, pick_list, text_input, Align, Button, Column, PickList, Row, Text, TextInput,
+};
+
+use crate::bulk::SelectionList;
+use crate::focus::Focus;
+use crate::theme;
+use crate::{track_label, Message};
+
+/// Results listed, all are enqueued
+const SHOWN_RESULTS: usize = 20;
+
+/// Facet value offered in a picker, None matches everything
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Choice<T>(pub Option<T>);
+
+impl<T: fmt::Display> fmt::Display for Choice<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(ref v) => v.fmt(f),
+            None => f.write_str(&tr!("facet-any")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decade(pub u16);
+
+impl fmt::Display for Decade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}s", self.0)
+    }
+}
+
+fn choices<T: Clone>(values: &[T]) -> Vec<Choice<T>> {
+    std::iter::once(Choice(None))
+        .chain(values.iter().cloned().map(|v| Choice(Some(v))))
+        .collect()
+}
+
+#[derive(Default)]
+pub struct LibrarySearch {
+    pub facets: Facets,
+    values: FacetValues,
+    /// Matching tracks with their label
+    results: Vec<(String, String)>,
+    /// Selected results, cleared when they change
+    pub selection: Selection,
+    result_buttons: Vec<button::State>,
+    text_input: text_input::State,
+    genre_pick: pick_list::State<Choice<String>>,
+    decade_pick: pick_list::State<Choice<Decade>>,
+    format_pick: pick_list::State<Choice<String>>,
+    enqueue: button::State,
+    close: button::State,
+}
+
+impl LibrarySearch {
+    /// Search again after facets or metadata changed
+    pub fn update(&mut self, metadata: &HashMap<String, TrackInfo>) {
+        self.values = library::facet_values(metadata.iter());
+        let results: Vec<(String, String)> = library::search(&self.facets, metadata.iter())
+            .into_iter()
+            .map(|t| {
+                let label = track_label(metadata, &t);
+                (t, label)
+            })
+            .collect();
+        if results.iter().map(|(t, _)| t).ne(self.results()) {
+            self.selection.clear();
+        }
+        self.results = results;
+    }
+
+    /// Selected results in order
+    pub fn selected(&self) -> Vec<String> {
+        self.selection
+            .items(&self.results)
+            .map(|(t, _)| t.clone())
+            .collect()
+    }
+
+    pub fn results(&self) -> impl Iterator<Item = &String> {
+        self.results.iter().map(|(t, _)| t)
+    }
+
+    pub fn text_focused(&self) -> bool {
+        self.text_input.is_focused()
+    }
+
+    pub fn view(&mut self, focus: &mut Focus) -> Element<'_, Message> {
+        let filters = Row::new()
+            .spacing(10)
+            .align_items(Align::Center)
+            .push(focus.button(
+                Button::new(
+                    &mut self.close,
+                    Text::new(tr!("close")).size(theme::text_size(14)),
+                ),
+                Message::CloseSearch,
+            ))
+            .push(
+                TextInput::new(
+                    &mut self.text_input,
+                    &tr!("search-library"),
+                    &self.facets.text,
+                    Message::SearchTextChanged,
+                )
+                .padding(5),
+            )
+            .push(PickList::new(
+                &mut self.genre_pick,
+                choices(&self.values.genres),
+                Some(Choice(self.facets.genre.clone())),
+                Message::GenreSelected,
+            ))
+            .push(PickList::new(
+                &mut self.decade_pick,
+                choices(&self.values.decades)
+                    .into_iter()
+                    .map(|v| Choice(v.0.map(Decade)))
+                    .collect::<Vec<_>>(),
+                Some(Choice(self.facets.decade.map(Decade))),
+                Message::DecadeSelected,
+            ))
+            .push(PickList::new(
+                &mut self.format_pick,
+                choices(&self.values.formats),
+                Some(Choice(self.facets.format.clone())),
+                Message::FormatSelected,
+            ));
+        let mut enqueue = Button::new(
+            &mut self.enqueue,
+            Text::new(tr!("enqueue-results", count = self.results.len()))
+                .size(theme::text_size(14)),
+        );
+        if !self.results.is_empty() {
+            enqueue = focus.button(enqueue, Message::EnqueueResults);
+        }
+        let mut column = Column::new().spacing(5).push(filters).push(enqueue);
+        let shown = self.results.len().min(SHOWN_RESULTS);
+        self.result_buttons.resize_with(shown, Default::default);
+        for (i, ((_, label), state)) in self
+            .results
+            .iter()
+            .zip(self.result_buttons.iter_mut())
+            .enumerate()
+        {
+            let label = match self.selection.contains(i) {
+                true => format!("✓ {}", label),
+                false => label.clone(),
+            };
+            column = column.push(focus.button(
+                Button::new(state, Text::new(label).size(theme::text_size(14))),
+                Message::SelectTrack(SelectionList::Search, i),
+            ));
+        }
+        if self.results.len() > SHOWN_RESULTS {
+            column = column.push(
+                Text::new(tr!(
+                    "more-results",
+                    count = self.results.len() - SHOWN_RESULTS
+                ))
+                .size(theme::text_size(14)),
+            );
+        }
+        column.into()
+    }
+}
@@ -0,0 +1,156 @@
+//! Secrets in the freedesktop Secret Service (GNOME Keyring, KWallet), used on Linux.
+//!
+//! Secrets are exchanged in a `plain` session, so they're only protected by the session bus.
+//! Locked collections are unlocked with the prompt of the service.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use dbus::{
+    arg::{PropMap, RefArg, Variant},
+    blocking::{Connection, Proxy},
+    message::MatchRule,
+    Path,
+};
+
+use crate::prelude::*;
+
+const BUS_NAME: &str = "org.freedesktop.secrets";
+const SERVICE_PATH: &str = "/org/freedesktop/secrets";
+const DEFAULT_COLLECTION: &str = "/org/freedesktop/secrets/aliases/default";
+const SERVICE_INTERFACE: &str = "org.freedesktop.Secret.Service";
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// Time the user gets for entering the keyring password
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Secret as transferred over dbus: session, parameters, value and content type
+type SecretStruct = (Path<'static>, Vec<u8>, Vec<u8>, String);
+
+pub struct SecretService {
+    connection: Connection,
+    session: Path<'static>,
+}
+
+impl SecretService {
+    pub fn connect() -> Result<Self> {
+        let connection = Connection::new_session()?;
+        let (_, session): (Variant<Box<dyn RefArg>>, Path<'static>) =
+            Proxy::new(BUS_NAME, SERVICE_PATH, TIMEOUT, &connection).method_call(
+                SERVICE_INTERFACE,
+                "OpenSession",
+                ("plain", Variant("")),
+            )?;
+        Ok(Self {
+            connection,
+            session,
+        })
+    }
+
+    fn proxy<'a>(&'a self, path: &'a Path<'a>) -> Proxy<'a, &'a Connection> {
+        Proxy::new(BUS_NAME, path.clone(), TIMEOUT, &self.connection)
+    }
+
+    pub fn get(&self, service: &str, name: &str) -> Result<Option<String>> {
+        let (unlocked, locked): (Vec<Path<'static>>, Vec<Path<'static>>) =
+            self.proxy(&Path::from(SERVICE_PATH)).method_call(
+                SERVICE_INTERFACE,
+                "SearchItems",
+                (attributes(service, name),),
+            )?;
+        let item = match (unlocked.into_iter().next(), locked.into_iter().next()) {
+            (Some(item), _) => item,
+            (None, Some(item)) => {
+                self.unlock(item.clone())?;
+                item
+            }
+            (None, None) => return Ok(None),
+        };
+        let (secret,): (SecretStruct,) = self.proxy(&item).method_call(
+            "org.freedesktop.Secret.Item",
+            "GetSecret",
+            (self.session.clone(),),
+        )?;
+        Ok(Some(String::from_utf8(secret.2)?))
+    }
+
+    pub fn set(&self, service: &str, name: &str, secret: &str) -> Result<()> {
+        let collection = Path::from(DEFAULT_COLLECTION);
+        self.unlock(collection.clone())?;
+        let mut properties = PropMap::new();
+        properties.insert(
+            "org.freedesktop.Secret.Item.Label".to_string(),
+            Variant(Box::new(format!("Audio Wrench {}", name))),
+        );
+        properties.insert(
+            "org.freedesktop.Secret.Item.Attributes".to_string(),
+            Variant(Box::new(attributes(service, name))),
+        );
+        let secret: SecretStruct = (
+            self.session.clone(),
+            Vec::new(),
+            secret.as_bytes().to_vec(),
+            String::from("text/plain"),
+        );
+        let (_, prompt): (Path<'static>, Path<'static>) = self.proxy(&collection).method_call(
+            "org.freedesktop.Secret.Collection",
+            "CreateItem",
+            (properties, secret, true),
+        )?;
+        self.prompt(prompt)
+    }
+
+    /// Unlock `object`, asking the user if needed
+    fn unlock(&self, object: Path<'static>) -> Result<()> {
+        let (_, prompt): (Vec<Path<'static>>, Path<'static>) = self
+            .proxy(&Path::from(SERVICE_PATH))
+            .method_call(SERVICE_INTERFACE, "Unlock", (vec![object],))?;
+        self.prompt(prompt)
+    }
+
+    /// Show `prompt` and wait for the user, "/" is no prompt
+    fn prompt(&self, prompt: Path<'static>) -> Result<()> {
+        if &*prompt == "/" {
+            return Ok(());
+        }
+        let dismissed = Arc::new(Mutex::new(None));
+        let result = dismissed.clone();
+        let rule = MatchRule::new_signal("org.freedesktop.Secret.Prompt", "Completed")
+            .with_path(prompt.clone());
+        let token = self.connection.add_match(
+            rule,
+            move |(v, _): (bool, Variant<Box<dyn RefArg>>), _, _| {
+                *result.lock().unwrap() = Some(v);
+                false
+            },
+        )?;
+        self.proxy(&prompt).method_call::<(), _, _, _>(
+            "org.freedesktop.Secret.Prompt",
+            "Prompt",
+            ("",),
+        )?;
+        let start = std::time::Instant::now();
+        while dismissed.lock().unwrap().is_none() && start.elapsed() < PROMPT_TIMEOUT {
+            self.connection.process(Duration::from_millis(500))?;
+        }
+        let dismissed = *dismissed.lock().unwrap();
+        match dismissed {
+            Some(false) => Ok(()),
+            Some(true) => Err(eyre!("Unlocking the keyring was dismissed")),
+            None => {
+                let _ = self.connection.remove_match(token);
+                Err(eyre!("Unlocking the keyring timed out"))
+            }
+        }
+    }
+}
+
+/// Attributes identifying secret `name`
+fn attributes(service: &str, name: &str) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+    attributes.insert("service".to_string(), service.to_string());
+    attributes.insert("name".to_string(), name.to_string());
+    attributes
+}
@@ -0,0 +1,267 @@
+//! Credential storage in the OS keychain or the Secret Service on Linux, with an encrypted
+//! file store as fallback.
+//!
+//! The file store keeps secrets out of the plaintext config, but its key is stored next to it.
+//! Secrets in it are only obfuscated against someone reading the config, not protected from
+//! anyone who can read the data directory.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use base64::Engine;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use rand::RngCore;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::prelude::*;
+#[cfg(target_os = "linux")]
+use crate::secret_service::SecretService;
+
+#[cfg(any(windows, target_os = "macos", target_os = "linux"))]
+const SERVICE: &str = "audio-wrench";
+const KEY_FILE: &str = "audio_wrench.key";
+const STORE_FILE: &str = "audio_wrench.secrets.json";
+const NONCE_LEN: usize = 12;
+
+/// Name of the secret of `user` on `server` for service `kind`, like `subsonic:me@https://host`
+pub fn name(kind: &str, user: &str, server: &str) -> String {
+    format!("{}:{}@{}", kind, user, server)
+}
+
+/// Password kept in the secret store. It's only written into the config while it couldn't be
+/// stored, so a failing store doesn't lose it.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret {
+    value: String,
+    /// In the secret store, left out of the config
+    stored: bool,
+}
+
+impl Secret {
+    pub fn as_str(&self) -> &str {
+        &self.value
+    }
+
+    /// Whether the config leaves it out, it's stored or there's nothing to keep
+    pub fn skip_config(&self) -> bool {
+        self.stored || self.value.is_empty()
+    }
+
+    /// Load secret `name` from the store.
+    /// A password still in the config is moved into the store instead.
+    pub fn load(&mut self, name: &str) -> Result<()> {
+        if self.value.is_empty() {
+            match get(name)? {
+                Some(v) => {
+                    self.value = v;
+                    self.stored = true;
+                }
+                None => warn!("No secret stored for {}", name),
+            }
+        } else {
+            set(name, &self.value)?;
+            info!("Moved secret for {} out of the config", name);
+            self.stored = true;
+        }
+        Ok(())
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self {
+            value,
+            stored: false,
+        }
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Secret")
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.value)
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Secret::from)
+    }
+}
+
+pub fn get(name: &str) -> Result<Option<String>> {
+    #[cfg(any(windows, target_os = "macos"))]
+    match keyring::Entry::new(SERVICE, name).and_then(|e| e.get_password()) {
+        Ok(v) => return Ok(Some(v)),
+        Err(keyring::Error::NoEntry) => (),
+        Err(e) => warn!("Can't read {} from keyring: {}", name, e),
+    }
+    #[cfg(target_os = "linux")]
+    match SecretService::connect().and_then(|s| s.get(SERVICE, name)) {
+        Ok(Some(v)) => return Ok(Some(v)),
+        Ok(None) => (),
+        Err(e) => warn!("Can't read {} from secret service: {:?}", name, e),
+    }
+    FileStore::open(&store_dir()?)?.get(name)
+}
+
+pub fn set(name: &str, secret: &str) -> Result<()> {
+    #[cfg(any(windows, target_os = "macos"))]
+    match keyring::Entry::new(SERVICE, name).and_then(|e| e.set_password(secret)) {
+        Ok(_) => return Ok(()),
+        Err(e) => warn!("Can't store {} in keyring, using file store: {}", name, e),
+    }
+    #[cfg(target_os = "linux")]
+    match SecretService::connect().and_then(|s| s.set(SERVICE, name, secret)) {
+        Ok(_) => return Ok(()),
+        Err(e) => warn!(
+            "Can't store {} in secret service, using file store: {:?}",
+            name, e
+        ),
+    }
+    let mut store = FileStore::open(&store_dir()?)?;
+    store.set(name, secret)?;
+    store.save()
+}
+
+fn store_dir() -> Result<PathBuf> {
+    dirs::data_local_dir().ok_or_else(|| eyre!("No data directory"))
+}
+
+/// Secrets encrypted with a local key
+struct FileStore {
+    path: PathBuf,
+    cipher: ChaCha20Poly1305,
+    /// Base64 encoded nonce and ciphertext by name
+    secrets: HashMap<String, String>,
+}
+
+impl FileStore {
+    fn open(dir: &Path) -> Result<Self> {
+        let key_path = dir.join(KEY_FILE);
+        let key = if key_path.is_file() {
+            let key = fs::read(&key_path)?;
+            if key.len() != 32 {
+                return Err(eyre!("Invalid secret key in {:?}", key_path));
+            }
+            key
+        } else {
+            let mut key = vec![0u8; 32];
+            rand::thread_rng().fill_bytes(&mut key);
+            write_private(&key_path, &key)?;
+            key
+        };
+        let path = dir.join(STORE_FILE);
+        let secrets = if path.is_file() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            HashMap::new()
+        };
+        Ok(Self {
+            path,
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&key)),
+            secrets,
+        })
+    }
+
+    fn get(&self, name: &str) -> Result<Option<String>> {
+        let data = match self.secrets.get(name) {
+            Some(v) => base64::engine::general_purpose::STANDARD.decode(v)?,
+            None => return Ok(None),
+        };
+        if data.len() < NONCE_LEN {
+            return Err(eyre!("Invalid secret for {}", name));
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        let plain = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| eyre!("Can't decrypt secret for {}", name))?;
+        Ok(Some(String::from_utf8(plain)?))
+    }
+
+    fn set(&mut self, name: &str, secret: &str) -> Result<()> {
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let mut data = nonce.to_vec();
+        data.extend(
+            self.cipher
+                .encrypt(Nonce::from_slice(&nonce), secret.as_bytes())
+                .map_err(|_| eyre!("Can't encrypt secret for {}", name))?,
+        );
+        self.secrets.insert(
+            name.to_string(),
+            base64::engine::general_purpose::STANDARD.encode(data),
+        );
+        Ok(())
+    }
+
+    fn save(&self) -> Result<()> {
+        write_private(&self.path, serde_json::to_string(&self.secrets)?.as_bytes())
+    }
+}
+
+/// Write file only readable by the current user
+fn write_private(path: &Path, data: &[u8]) -> Result<()> {
+    use std::io::Write;
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(path)
+        .and_then(|mut f| f.write_all(data))
+        .wrap_err_with(|| format!("Can't write {:?}", path))
+}
+
+#[test]
+fn test_secret_config() {
+    #[derive(Serialize, Deserialize)]
+    struct Config {
+        #[serde(default, skip_serializing_if = "Secret::skip_config")]
+        password: Secret,
+    }
+    let config: Config = serde_json::from_str(r#"{"password": "hunter2"}"#).unwrap();
+    assert_eq!(config.password.as_str(), "hunter2");
+    // kept in the config until it's stored
+    assert_eq!(
+        serde_json::to_string(&config).unwrap(),
+        r#"{"password":"hunter2"}"#
+    );
+    let stored = Config {
+        password: Secret {
+            value: String::from("hunter2"),
+            stored: true,
+        },
+    };
+    assert_eq!(serde_json::to_string(&stored).unwrap(), "{}");
+    assert_eq!(format!("{:?}", stored.password), "Secret");
+    assert_eq!(name("mqtt", "me", "host"), "mqtt:me@host");
+}
+
+#[test]
+fn test_file_store() {
+    let dir = std::env::temp_dir().join(format!("audio_wrench_secrets_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut store = FileStore::open(&dir).unwrap();
+    store.set("test", "hunter2").unwrap();
+    store.save().unwrap();
+    assert!(!fs::read_to_string(dir.join(STORE_FILE))
+        .unwrap()
+        .contains("hunter2"));
+    let store = FileStore::open(&dir).unwrap();
+    assert_eq!(store.get("test").unwrap().as_deref(), Some("hunter2"));
+    assert_eq!(store.get("missing").unwrap(), None);
+    fs::remove_dir_all(&dir).unwrap();
+}
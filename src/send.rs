@@ -0,0 +1,282 @@
+//! "Send to device": copy the queue or the favorites to a folder, USB drive or MTP phone
+
+use iced::Element;
+use iced_native::{
+    button, pick_list, text_input, Align, Button, Checkbox, Column, Length, PickList, ProgressBar,
+    Row, Text, TextInput,
+};
+use serde::{Deserialize, Serialize};
+
+use audio_wrench_core::{
+    mtp::{self, Storage},
+    transfer::{Progress, Summary, Transfer},
+};
+
+use crate::focus::Focus;
+use crate::prelude::*;
+use crate::theme;
+use crate::Message;
+
+/// Tracks that are sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum SendSource {
+    #[default]
+    Queue,
+    Favorites,
+}
+
+impl SendSource {
+    pub const ALL: [SendSource; 2] = [SendSource::Queue, SendSource::Favorites];
+}
+
+impl std::fmt::Display for SendSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&match self {
+            SendSource::Queue => tr!("send-queue"),
+            SendSource::Favorites => tr!("send-favorites"),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SendSettings {
+    #[serde(default)]
+    pub folder: String,
+    #[serde(default)]
+    pub source: SendSource,
+    /// Transcode files above `transcode_mb` to MP3
+    #[serde(default)]
+    pub transcode: bool,
+    #[serde(default = "default_transcode_mb")]
+    pub transcode_mb: u64,
+}
+
+fn default_transcode_mb() -> u64 {
+    20
+}
+
+impl SendSettings {
+    /// Size in bytes above which files are transcoded, None if they never are
+    pub fn transcode_above(&self) -> Option<u64> {
+        self.transcode.then(|| self.transcode_mb * 1_000_000)
+    }
+}
+
+impl Default for SendSettings {
+    fn default() -> Self {
+        Self {
+            folder: String::new(),
+            source: SendSource::default(),
+            transcode: false,
+            transcode_mb: default_transcode_mb(),
+        }
+    }
+}
+
+/// Panel in the settings and the running transfer
+#[derive(Default)]
+pub struct SendPanel {
+    pub settings: SendSettings,
+    transfer: Option<Transfer>,
+    /// Files done and total of the running transfer
+    progress: (usize, usize),
+    /// Outcome of the last transfer
+    result: Option<String>,
+    /// Connected MTP devices, found on request
+    devices: Vec<Storage>,
+    device_pick: pick_list::State<Storage>,
+    find_devices: button::State,
+    folder_input: text_input::State,
+    source_pick: pick_list::State<SendSource>,
+    start: button::State,
+    cancel: button::State,
+}
+
+impl SendPanel {
+    pub fn new(settings: SendSettings) -> Self {
+        Self {
+            settings,
+            ..Default::default()
+        }
+    }
+
+    pub fn text_focused(&self) -> bool {
+        self.folder_input.is_focused()
+    }
+
+    pub fn find_devices(&mut self) {
+        self.devices = mtp::storages();
+        if self.devices.is_empty() {
+            self.result = Some(tr!("send-no-devices"));
+        }
+    }
+
+    /// Send to the music folder of a phone
+    pub fn select_device(&mut self, storage: Storage) {
+        self.settings.folder = storage.path.join("Music").to_string_lossy().into_owned();
+    }
+
+    /// Start copying `tracks` and listing them in `playlist`, unless a transfer is running already
+    pub fn start(&mut self, tracks: Vec<String>, playlist: String) {
+        if self.transfer.is_some() {
+            return;
+        }
+        let folder = self.settings.folder.trim();
+        if folder.is_empty() {
+            self.result = Some(tr!("send-no-folder"));
+            return;
+        }
+        if tracks.is_empty() {
+            self.result = Some(tr!("send-nothing"));
+            return;
+        }
+        let transcode_above = self.settings.transcode_above();
+        info!("Sending {} tracks to {}", tracks.len(), folder);
+        self.progress = (0, tracks.len());
+        self.result = None;
+        self.transfer = Some(Transfer::start(
+            tracks,
+            folder.into(),
+            transcode_above,
+            Some(playlist),
+        ));
+    }
+
+    pub fn cancel(&mut self) {
+        if let Some(transfer) = self.transfer.as_ref() {
+            transfer.cancel();
+        }
+    }
+
+    /// Read updates of the running transfer
+    pub fn poll(&mut self) {
+        let updates = match self.transfer.as_ref() {
+            Some(v) => v.poll(),
+            None => return,
+        };
+        for update in updates {
+            match update {
+                Progress::Copying { done, total } => self.progress = (done, total),
+                Progress::Finished(summary) => {
+                    self.result = Some(summary_text(summary));
+                    self.transfer = None;
+                }
+                Progress::Failed(e) => {
+                    self.result = Some(e);
+                    self.transfer = None;
+                }
+            }
+        }
+    }
+
+    pub fn view(&mut self, focus: &mut Focus) -> Element<'_, Message> {
+        let mut row = Row::new().spacing(10).align_items(Align::Center).push(
+            TextInput::new(
+                &mut self.folder_input,
+                &tr!("send-folder"),
+                &self.settings.folder,
+                Message::SendFolderChanged,
+            )
+            .padding(5),
+        );
+        // MTP devices are only found through GVFS
+        if cfg!(target_os = "linux") {
+            row = row.push(focus.button(
+                Button::new(
+                    &mut self.find_devices,
+                    Text::new(tr!("send-find-devices")).size(theme::text_size(14)),
+                ),
+                Message::FindDevices,
+            ));
+        }
+        if !self.devices.is_empty() {
+            row = row.push(
+                PickList::new(
+                    &mut self.device_pick,
+                    &self.devices[..],
+                    None,
+                    Message::DeviceSelected,
+                )
+                .text_size(theme::text_size(14)),
+            );
+        }
+        row = row
+            .push(
+                PickList::new(
+                    &mut self.source_pick,
+                    &SendSource::ALL[..],
+                    Some(self.settings.source),
+                    Message::SendSourceSelected,
+                )
+                .text_size(theme::text_size(14)),
+            )
+            .push(Checkbox::new(
+                self.settings.transcode,
+                tr!("send-transcode", mb = self.settings.transcode_mb),
+                Message::SendTranscodeToggled,
+            ));
+        row = match self.transfer.is_some() {
+            true => row.push(focus.button(
+                Button::new(
+                    &mut self.cancel,
+                    Text::new(tr!("cancel")).size(theme::text_size(14)),
+                ),
+                Message::CancelSend,
+            )),
+            false => row.push(focus.button(
+                Button::new(
+                    &mut self.start,
+                    Text::new(tr!("send-to-device")).size(theme::text_size(14)),
+                ),
+                Message::SendToDevice,
+            )),
+        };
+        let mut column = Column::new().spacing(5).push(row);
+        if self.transfer.is_some() {
+            let (done, total) = self.progress;
+            column = column.push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(
+                        ProgressBar::new(0.0..=total.max(1) as f32, done as f32)
+                            .height(Length::Units(10)),
+                    )
+                    .push(
+                        Text::new(tr!("send-progress", done = done, total = total))
+                            .size(theme::text_size(14)),
+                    ),
+            );
+        } else if let Some(ref result) = self.result {
+            column = column.push(Text::new(result.as_str()).size(theme::text_size(14)));
+        }
+        column.into()
+    }
+}
+
+fn summary_text(summary: Summary) -> String {
+    tr!(
+        "send-finished",
+        copied = summary.copied,
+        transcoded = summary.transcoded,
+        existing = summary.existing,
+        failed = summary.failed
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_transcode_above() {
+        let mut settings: SendSettings = serde_json::from_str("{}").unwrap();
+        // copied as they are unless turned on
+        assert_eq!(settings.transcode_above(), None);
+        settings.transcode = true;
+        assert_eq!(settings.transcode_above(), Some(20_000_000));
+        let settings: SendSettings =
+            serde_json::from_str(r#"{"transcode": true, "transcode_mb": 5}"#).unwrap();
+        assert_eq!(settings.transcode_above(), Some(5_000_000));
+    }
+}
@@ -0,0 +1,202 @@
+//! Sidebar with the saved playlists grouped in folders
+
+use std::path::{Path, PathBuf};
+
+use audio_wrench_core::folders::PlaylistFolders;
+use iced::Element;
+use iced_native::{
+    button, pick_list, text_input, Align, Button, Column, Length, PickList, Row, Text, TextInput,
+};
+
+use crate::focus::Focus;
+use crate::pins::Pin;
+use crate::theme;
+use crate::Message;
+
+/// Target folder offered when moving a playlist, None is ungrouped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FolderChoice(pub Option<String>);
+
+impl std::fmt::Display for FolderChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            None => f.write_str(&tr!("ungrouped")),
+            Some(ref name) => f.write_str(name),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Sidebar {
+    /// Per folder, the ungrouped section has none
+    folder_buttons: Vec<(button::State, button::State)>,
+    /// Open, pin, move and forget per playlist in display order
+    entries: Vec<(
+        button::State,
+        button::State,
+        pick_list::State<FolderChoice>,
+        button::State,
+    )>,
+    new_folder: String,
+    new_folder_input: text_input::State,
+    add_folder: button::State,
+}
+
+impl Sidebar {
+    pub fn new_folder(&self) -> &str {
+        &self.new_folder
+    }
+
+    pub fn set_new_folder(&mut self, name: String) {
+        self.new_folder = name;
+    }
+
+    pub fn text_focused(&self) -> bool {
+        self.new_folder_input.is_focused()
+    }
+
+    /// Playlists in `complete` were played through and start over when opened
+    pub fn view<'a, I>(
+        &'a mut self,
+        folders: &'a PlaylistFolders,
+        playlists: I,
+        complete: &[&PathBuf],
+        current: &Path,
+        pins: &[Pin],
+        focus: &mut Focus,
+    ) -> Element<'a, Message>
+    where
+        I: IntoIterator<Item = &'a PathBuf>,
+    {
+        let tree = folders.tree(playlists);
+        let choices: Vec<FolderChoice> = std::iter::once(FolderChoice(None))
+            .chain(
+                folders
+                    .folders()
+                    .iter()
+                    .map(|f| FolderChoice(Some(f.name.clone()))),
+            )
+            .collect();
+        let entry_count = tree
+            .iter()
+            .filter(|(f, _)| !f.is_some_and(|f| f.collapsed))
+            .map(|(_, entries)| entries.len())
+            .sum();
+        self.folder_buttons
+            .resize_with(folders.folders().len(), Default::default);
+        self.entries.resize_with(entry_count, Default::default);
+
+        let mut column = Column::new()
+            .spacing(5)
+            .width(Length::Units(220))
+            .push(Text::new(tr!("playlists")).size(theme::text_size(16)));
+        let mut folder_buttons = self.folder_buttons.iter_mut();
+        let mut entries = self.entries.iter_mut();
+        for (folder, playlists) in tree {
+            let collapsed = match folder {
+                Some(folder) => {
+                    let (toggle, remove) = folder_buttons.next().expect("state per folder");
+                    let arrow = match folder.collapsed {
+                        true => "▸",
+                        false => "▾",
+                    };
+                    let label = format!("{} {} ({})", arrow, folder.name, playlists.len());
+                    column = column.push(
+                        Row::new()
+                            .spacing(5)
+                            .align_items(Align::Center)
+                            .push(
+                                focus.button(
+                                    Button::new(
+                                        toggle,
+                                        Text::new(label).size(theme::text_size(14)),
+                                    )
+                                    .width(Length::Fill),
+                                    Message::ToggleFolder(folder.name.clone()),
+                                ),
+                            )
+                            .push(focus.button(
+                                Button::new(remove, Text::new("×").size(theme::text_size(14))),
+                                Message::RemoveFolder(folder.name.clone()),
+                            )),
+                    );
+                    folder.collapsed
+                }
+                None if playlists.is_empty() => continue,
+                None => {
+                    column = column.push(Text::new(tr!("ungrouped")).size(theme::text_size(14)));
+                    false
+                }
+            };
+            if collapsed {
+                continue;
+            }
+            for playlist in playlists {
+                let (open, pin, pick, forget) = entries.next().expect("state per playlist");
+                let name = playlist
+                    .file_stem()
+                    .map_or_else(|| playlist.to_string_lossy(), |v| v.to_string_lossy());
+                let name = match (
+                    playlist == current,
+                    complete.iter().any(|p| p.as_path() == playlist),
+                ) {
+                    (true, _) => format!("▶ {}", name),
+                    (false, true) => format!("✓ {}", name),
+                    (false, false) => name.into_owned(),
+                };
+                let target = playlist.to_path_buf();
+                let pin_label = match pins.contains(&Pin::Playlist(target.clone())) {
+                    true => tr!("unpin"),
+                    false => tr!("pin"),
+                };
+                let selected = FolderChoice(folders.folder_of(playlist).map(str::to_string));
+                column = column.push(
+                    Row::new()
+                        .spacing(5)
+                        .align_items(Align::Center)
+                        .push(
+                            focus.button(
+                                Button::new(open, Text::new(name).size(theme::text_size(14)))
+                                    .width(Length::Fill),
+                                Message::OpenPlaylist(target.clone()),
+                            ),
+                        )
+                        .push(focus.button(
+                            Button::new(pin, Text::new(pin_label).size(theme::text_size(12))),
+                            Message::TogglePin(Pin::Playlist(target.clone())),
+                        ))
+                        .push(
+                            PickList::new(pick, choices.clone(), Some(selected), {
+                                let target = target.clone();
+                                move |v| Message::MovePlaylist(target.clone(), v)
+                            })
+                            .text_size(theme::text_size(14)),
+                        )
+                        .push(focus.button(
+                            Button::new(forget, Text::new("×").size(theme::text_size(14))),
+                            Message::ForgetPlaylist(target),
+                        )),
+                );
+            }
+        }
+        column
+            .push(
+                TextInput::new(
+                    &mut self.new_folder_input,
+                    &tr!("new-folder"),
+                    &self.new_folder,
+                    Message::NewFolderChanged,
+                )
+                .padding(5)
+                .on_submit(Message::AddFolder),
+            )
+            .push(focus.button(
+                Button::new(
+                    &mut self.add_folder,
+                    Text::new(tr!("add-folder")).size(theme::text_size(14)),
+                ),
+                Message::AddFolder,
+            ))
+            .into()
+    }
+}
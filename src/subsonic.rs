@@ -0,0 +1,294 @@
+//! Subsonic API client, also works with Navidrome and other compatible servers
+
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use url::Url;
+
+use crate::hooks::Event;
+use crate::plugin::{Entry, Listing, Plugin, TrackSource};
+use crate::prelude::*;
+use crate::remote;
+use crate::secrets::Secret;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubsonicConfig {
+    /// Server base URL, e.g. `https://music.example.com`
+    pub url: String,
+    pub user: String,
+    /// Kept in the secret store, written into the config only while storing it fails
+    #[serde(default, skip_serializing_if = "Secret::skip_config")]
+    pub password: Secret,
+    /// Report played tracks back to the server
+    #[serde(default)]
+    pub scrobble: bool,
+}
+
+#[derive(Clone)]
+pub struct Subsonic {
+    config: SubsonicConfig,
+}
+
+impl Subsonic {
+    pub fn new(config: SubsonicConfig) -> Self {
+        Self { config }
+    }
+
+    /// URL for `method` including authentication
+    fn url(&self, method: &str, params: &[(&str, &str)]) -> Result<Url> {
+        remote::subsonic_url(
+            &self.config.url,
+            &self.config.user,
+            self.config.password.as_str(),
+            method,
+            params,
+        )
+    }
+
+    /// Call API method, returns the inner response object
+    fn call(&self, method: &str, params: &[(&str, &str)]) -> Result<Value> {
+        let url = self.url(method, params)?;
+        response(remote::agent().get(url.as_str()).call()?.into_json()?)
+    }
+
+    /// Entries of folder `parent`
+    fn list(&self, parent: &str) -> Result<Vec<Entry>> {
+        let (kind, id) = parent.split_once(':').unwrap_or((parent, ""));
+        match kind {
+            "artists" => {
+                let response = self.call("getArtists", &[])?;
+                Ok(response["artists"]["index"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .flat_map(|index| folders(&index["artist"], "artist"))
+                    .collect())
+            }
+            "artist" => {
+                let response = self.call("getArtist", &[("id", id)])?;
+                Ok(folders(&response["artist"]["album"], "album"))
+            }
+            "album" => {
+                let response = self.call("getAlbum", &[("id", id)])?;
+                Ok(self.songs(&response["album"]["song"]))
+            }
+            "playlists" => {
+                let response = self.call("getPlaylists", &[])?;
+                Ok(folders(&response["playlists"]["playlist"], "playlist"))
+            }
+            "playlist" => {
+                let response = self.call("getPlaylist", &[("id", id)])?;
+                Ok(self.songs(&response["playlist"]["entry"]))
+            }
+            _ => Err(eyre!("Unknown subsonic entry {}", parent)),
+        }
+    }
+
+    /// Song entries of an album or playlist listing, the tracks are authenticated on playback
+    fn songs(&self, songs: &Value) -> Vec<Entry> {
+        let mut entries = Vec::new();
+        for song in songs.as_array().into_iter().flatten() {
+            let id = song["id"].as_str().unwrap_or_default();
+            let title = match song["artist"].as_str() {
+                Some(artist) => format!("{} - {}", artist, song["title"].as_str().unwrap_or(id)),
+                None => song["title"].as_str().unwrap_or(id).to_string(),
+            };
+            entries.push(Entry {
+                id: format!("song:{}", id),
+                title,
+                track: Some(format!("{}{}", remote::SUBSONIC, id)),
+            });
+        }
+        entries
+    }
+
+    /// Song id if `track` is a song of this server
+    fn song_id(&self, track: &str) -> Option<String> {
+        track
+            .strip_prefix(remote::SUBSONIC)
+            .map(str::to_string)
+            .or_else(|| legacy_song_id(&self.config.url, track))
+    }
+}
+
+/// Song id of a stream URL of the server at `url`, as stored by older versions
+/// together with the authentication
+pub fn legacy_song_id(url: &str, track: &str) -> Option<String> {
+    if !track.starts_with(url) {
+        return None;
+    }
+    let url = Url::parse(track).ok()?;
+    if !url.path().ends_with("/rest/stream") {
+        return None;
+    }
+    url.query_pairs()
+        .find(|(k, _)| k == "id")
+        .map(|(_, v)| v.into_owned())
+}
+
+/// Inner response object of `body`, errors reported by the server are returned as such
+fn response(mut body: Value) -> Result<Value> {
+    let response = body["subsonic-response"].take();
+    if response["status"] != "ok" {
+        return Err(eyre!(
+            "Subsonic error: {}",
+            response["error"]["message"].as_str().unwrap_or("unknown")
+        ));
+    }
+    Ok(response)
+}
+
+/// Folder entries of a listing, with `prefix` added to the id
+fn folders(list: &Value, prefix: &str) -> Vec<Entry> {
+    list.as_array()
+        .into_iter()
+        .flatten()
+        .map(|v| Entry {
+            id: format!("{}:{}", prefix, v["id"].as_str().unwrap_or_default()),
+            title: v["name"].as_str().unwrap_or_default().to_string(),
+            track: None,
+        })
+        .collect()
+}
+
+impl Plugin for Subsonic {
+    fn name(&self) -> &'static str {
+        "Subsonic"
+    }
+
+    fn on_event(&mut self, event: Event, track: &str) {
+        if !self.config.scrobble {
+            return;
+        }
+        let submission = match event {
+            Event::TrackStarted => "false",
+            Event::TrackEnded => "true",
+            _ => return,
+        };
+        if let Some(id) = self.song_id(track) {
+            match self.url("scrobble", &[("id", &id), ("submission", submission)]) {
+                Ok(url) => {
+                    thread::spawn(move || {
+                        if let Err(e) = remote::agent().get(url.as_str()).call() {
+                            warn!("Can't scrobble to subsonic: {}", e);
+                        }
+                    });
+                }
+                Err(e) => warn!("Invalid subsonic URL: {}", e),
+            }
+        }
+    }
+
+    fn source(&mut self) -> Option<&mut dyn TrackSource> {
+        Some(self)
+    }
+}
+
+impl TrackSource for Subsonic {
+    fn browse(&mut self, parent: Option<&str>) -> Listing {
+        let source = self.clone();
+        let parent = parent.map(str::to_string);
+        Box::new(move || match parent {
+            Some(parent) => source.list(&parent),
+            None => Ok(vec![
+                Entry {
+                    id: String::from("artists"),
+                    title: String::from("Artists"),
+                    track: None,
+                },
+                Entry {
+                    id: String::from("playlists"),
+                    title: String::from("Playlists"),
+                    track: None,
+                },
+            ]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn test_source() -> Subsonic {
+        Subsonic::new(SubsonicConfig {
+            url: String::from("https://music.example.com/"),
+            user: String::from("me"),
+            password: Secret::from(String::from("hunter2")),
+            scrobble: true,
+        })
+    }
+
+    #[test]
+    fn test_auth_params() {
+        let url = test_source().url("getAlbum", &[("id", "7")]).unwrap();
+        assert_eq!(url.path(), "/rest/getAlbum");
+        let params: std::collections::HashMap<_, _> = url.query_pairs().into_owned().collect();
+        assert_eq!(params["u"], "me");
+        assert_eq!(params["f"], "json");
+        assert_eq!(params["id"], "7");
+        // salted token instead of the password
+        let salt = &params["s"];
+        assert_eq!(salt.len(), 12);
+        assert_eq!(
+            params["t"],
+            format!("{:x}", md5::compute(format!("hunter2{}", salt)))
+        );
+        assert!(!url.as_str().contains("hunter2"));
+        // a new salt for every request
+        let other = test_source().url("getAlbum", &[]).unwrap();
+        assert_ne!(
+            other.query_pairs().find(|(k, _)| k == "s").unwrap().1,
+            *salt
+        );
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let album = response(serde_json::json!({"subsonic-response": {
+            "status": "ok",
+            "album": {"song": [
+                {"id": "1", "title": "Intro", "artist": "Band"},
+                {"id": "2", "title": "Outro"},
+            ]},
+        }}))
+        .unwrap();
+        let songs = test_source().songs(&album["album"]["song"]);
+        assert_eq!(
+            songs[0],
+            Entry {
+                id: String::from("song:1"),
+                title: String::from("Band - Intro"),
+                track: Some(String::from("subsonic://1")),
+            }
+        );
+        assert_eq!(songs[1].title, "Outro");
+
+        let artists = serde_json::json!([{"id": "3", "name": "Band"}]);
+        assert_eq!(folders(&artists, "artist")[0].id, "artist:3");
+        assert!(folders(&Value::Null, "artist").is_empty());
+
+        let error = response(serde_json::json!({"subsonic-response": {
+            "status": "failed",
+            "error": {"code": 40, "message": "Wrong username or password"},
+        }}))
+        .unwrap_err();
+        assert!(error.to_string().contains("Wrong username or password"));
+    }
+
+    #[test]
+    fn test_strip_credentials() {
+        let source = test_source();
+        // stored by older versions with the authentication in the URL
+        let legacy = "https://music.example.com/rest/stream?u=me&t=abc&s=def&v=1.16.1&c=aw&id=42";
+        assert_eq!(source.song_id(legacy).as_deref(), Some("42"));
+        assert_eq!(source.song_id("subsonic://42").as_deref(), Some("42"));
+        assert_eq!(legacy_song_id("https://other.example.com/", legacy), None);
+        assert_eq!(
+            source.song_id("https://music.example.com/rest/getCoverArt?id=42"),
+            None
+        );
+        assert_eq!(source.song_id("/music/song.mp3"), None);
+    }
+}
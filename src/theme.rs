@@ -0,0 +1,145 @@
+//! Appearance settings: color theme and text size.
+//! Like translations these are global for the UI thread.
+
+use std::{cell::Cell, fmt};
+
+use iced::{
+    button::{Style, StyleSheet},
+    Background, Color,
+};
+use serde::{Deserialize, Serialize};
+
+/// Minimum contrast ratio against the background for high contrast, WCAG AAA
+const MIN_CONTRAST: f32 = 7.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Theme {
+    #[default]
+    Default,
+    HighContrast,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 2] = [Theme::Default, Theme::HighContrast];
+}
+
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&match self {
+            Theme::Default => tr!("theme-default"),
+            Theme::HighContrast => tr!("theme-high-contrast"),
+        })
+    }
+}
+
+thread_local! {
+    static THEME: Cell<Theme> = const { Cell::new(Theme::Default) };
+    /// Text size in percent
+    static TEXT_SCALE: Cell<u16> = const { Cell::new(100) };
+}
+
+/// Change theme and text scale in percent for all following views
+pub fn set(theme: Theme, text_scale: u16) {
+    THEME.with(|v| v.set(theme));
+    TEXT_SCALE.with(|v| v.set(text_scale));
+}
+
+/// Scaled text size
+pub fn text_size(base: u16) -> u16 {
+    let scale = TEXT_SCALE.with(Cell::get) as u32;
+    (base as u32 * scale / 100).max(1) as u16
+}
+
+pub fn background() -> Color {
+    Color::WHITE
+}
+
+/// Color for text, adjusted to the contrast requirements of the theme
+pub fn color(color: [f32; 3]) -> Color {
+    let color = Color::from(color);
+    match THEME.with(Cell::get) {
+        Theme::Default => color,
+        Theme::HighContrast => with_contrast(color, background(), MIN_CONTRAST),
+    }
+}
+
+/// Button style of the theme, None for the default
+pub fn button() -> Option<Box<dyn StyleSheet>> {
+    match THEME.with(Cell::get) {
+        Theme::Default => None,
+        Theme::HighContrast => Some(Box::new(HighContrastButton)),
+    }
+}
+
+struct HighContrastButton;
+
+impl StyleSheet for HighContrastButton {
+    fn active(&self) -> Style {
+        Style {
+            background: Some(Background::Color(Color::WHITE)),
+            border_radius: 2.0,
+            border_width: 2.0,
+            border_color: Color::BLACK,
+            text_color: Color::BLACK,
+            ..Default::default()
+        }
+    }
+}
+
+/// Relative luminance as defined by WCAG
+fn luminance(color: Color) -> f32 {
+    let channel = |c: f32| match c <= 0.03928 {
+        true => c / 12.92,
+        false => ((c + 0.055) / 1.055).powf(2.4),
+    };
+    0.2126 * channel(color.r) + 0.7152 * channel(color.g) + 0.0722 * channel(color.b)
+}
+
+/// Contrast ratio as defined by WCAG, 1 to 21
+fn contrast(a: Color, b: Color) -> f32 {
+    let (a, b) = (luminance(a), luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// Darken or lighten `color` until it reaches `min` contrast to `background`
+fn with_contrast(mut color: Color, background: Color, min: f32) -> Color {
+    let darken = luminance(background) > 0.5;
+    for _ in 0..20 {
+        if contrast(color, background) >= min {
+            break;
+        }
+        let step = |c: f32| match darken {
+            true => c * 0.85,
+            false => c + (1.0 - c) * 0.15,
+        };
+        color = Color::from_rgb(step(color.r), step(color.g), step(color.b));
+    }
+    color
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_contrast() {
+        assert!((contrast(Color::BLACK, Color::WHITE) - 21.0).abs() < 0.01);
+        let grey = Color::from_rgb(0.8, 0.8, 0.8);
+        assert!(contrast(grey, Color::WHITE) < MIN_CONTRAST);
+        let adjusted = with_contrast(grey, Color::WHITE, MIN_CONTRAST);
+        assert!(contrast(adjusted, Color::WHITE) >= MIN_CONTRAST);
+        // already sufficient colors stay untouched
+        assert_eq!(
+            with_contrast(Color::BLACK, Color::WHITE, MIN_CONTRAST),
+            Color::BLACK
+        );
+    }
+
+    #[test]
+    fn test_text_size() {
+        set(Theme::Default, 150);
+        assert_eq!(text_size(20), 30);
+        set(Theme::Default, 100);
+        assert_eq!(text_size(14), 14);
+    }
+}
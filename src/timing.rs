@@ -0,0 +1,53 @@
+//! Startup phase durations, logged when started with `--timing`
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::Instant,
+};
+
+use crate::prelude::*;
+
+pub const TIMING_ARG: &str = "--timing";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// Start of measuring, end and name of the last phase
+static MARKS: Mutex<Option<(Instant, Instant, &str)>> = Mutex::new(None);
+
+/// Start measuring, phases are logged from now on
+pub fn enable() {
+    let now = Instant::now();
+    *MARKS.lock().expect("Can't lock startup timing") = Some((now, now, ""));
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Log the duration of the phase `name` ending now, repeated calls are logged once
+pub fn phase(name: &'static str) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let now = Instant::now();
+    if let Some((start, ref mut last, ref mut last_name)) =
+        *MARKS.lock().expect("Can't lock startup timing")
+    {
+        if *last_name == name {
+            return;
+        }
+        info!(
+            "Startup {}: {:?} (at {:?})",
+            name,
+            now.duration_since(*last),
+            now.duration_since(start)
+        );
+        *last = now;
+        *last_name = name;
+    }
+}
+
+/// Stop measuring after the last phase, so later calls don't log
+pub fn finish(name: &'static str) {
+    phase(name);
+    ENABLED.store(false, Ordering::Relaxed);
+}
@@ -0,0 +1,225 @@
+//! Touch layout for tablets: everything at double size and swipe gestures.
+//! iced has no touch events, touches arrive as emulated left mouse button drags.
+
+use std::hash::Hash;
+
+use iced_native::{
+    event, layout, mouse, overlay, Clipboard, Element, Event, Hasher, Layout, Length, Point,
+    Rectangle, Widget,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/// Minimum distance in logical pixels for a drag to count as a swipe
+const SWIPE_DISTANCE: f32 = 80.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum TouchMode {
+    #[default]
+    Off,
+    On,
+    /// On if a touchscreen is found at startup
+    Auto,
+}
+
+impl TouchMode {
+    pub const ALL: [TouchMode; 3] = [TouchMode::Off, TouchMode::On, TouchMode::Auto];
+
+    /// Whether the touch layout is used
+    pub fn active(self) -> bool {
+        match self {
+            TouchMode::Off => false,
+            TouchMode::On => true,
+            TouchMode::Auto => has_touchscreen(),
+        }
+    }
+}
+
+impl std::fmt::Display for TouchMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&match self {
+            TouchMode::Off => tr!("touch-off"),
+            TouchMode::On => tr!("touch-on"),
+            TouchMode::Auto => tr!("touch-auto"),
+        })
+    }
+}
+
+/// Input devices the kernel reports, touchscreens are named as such by their drivers
+#[cfg(target_os = "linux")]
+fn has_touchscreen() -> bool {
+    match std::fs::read_to_string("/proc/bus/input/devices") {
+        Ok(v) => v
+            .lines()
+            .filter(|l| l.starts_with("N: Name="))
+            .any(|l| l.to_lowercase().contains("touchscreen")),
+        Err(e) => {
+            debug!("Can't list input devices: {}", e);
+            false
+        }
+    }
+}
+
+/// No detection without platform APIs, touch mode has to be turned on manually
+#[cfg(not(target_os = "linux"))]
+fn has_touchscreen() -> bool {
+    false
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Swipe {
+    /// Next track
+    Left,
+    /// Pause or resume
+    Down,
+}
+
+/// Detects swipes from the drags of the left mouse button, state of a [SwipeArea]
+#[derive(Debug, Default)]
+pub struct SwipeTracker {
+    start: Option<Point>,
+}
+
+impl SwipeTracker {
+    pub fn pressed(&mut self, position: Point) {
+        self.start = Some(position);
+    }
+
+    /// Swipe ended by the release at `position`, if the drag was long and straight enough
+    pub fn released(&mut self, position: Point) -> Option<Swipe> {
+        let start = self.start.take()?;
+        let dx = position.x - start.x;
+        let dy = position.y - start.y;
+        // mostly in one direction, diagonal drags are ignored
+        if -dx >= SWIPE_DISTANCE && -dx > dy.abs() * 2.0 {
+            Some(Swipe::Left)
+        } else if dy >= SWIPE_DISTANCE && dy > dx.abs() * 2.0 {
+            Some(Swipe::Down)
+        } else {
+            None
+        }
+    }
+}
+
+/// Part of the view where swipes start, drags starting elsewhere are ignored.
+/// Must not contain sliders, dragging them would count as swipe.
+pub struct SwipeArea<'a, Message, Renderer> {
+    state: &'a mut SwipeTracker,
+    content: Element<'a, Message, Renderer>,
+    on_swipe: fn(Swipe) -> Message,
+}
+
+impl<'a, Message, Renderer> SwipeArea<'a, Message, Renderer> {
+    pub fn new(
+        state: &'a mut SwipeTracker,
+        content: impl Into<Element<'a, Message, Renderer>>,
+        on_swipe: fn(Swipe) -> Message,
+    ) -> Self {
+        Self {
+            state,
+            content: content.into(),
+            on_swipe,
+        }
+    }
+}
+
+impl<'a, Message, Renderer: iced_native::Renderer> Widget<Message, Renderer>
+    for SwipeArea<'a, Message, Renderer>
+{
+    fn width(&self) -> Length {
+        self.content.width()
+    }
+
+    fn height(&self) -> Length {
+        self.content.height()
+    }
+
+    fn layout(&self, renderer: &Renderer, limits: &layout::Limits) -> layout::Node {
+        self.content.layout(renderer, limits)
+    }
+
+    fn on_event(
+        &mut self,
+        event: Event,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        messages: &mut Vec<Message>,
+        renderer: &Renderer,
+        clipboard: Option<&dyn Clipboard>,
+    ) -> event::Status {
+        match event {
+            // buttons below capture the press, a swipe moves off them and doesn't click
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))
+                if layout.bounds().contains(cursor_position) =>
+            {
+                self.state.pressed(cursor_position)
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                if let Some(swipe) = self.state.released(cursor_position) {
+                    messages.push((self.on_swipe)(swipe));
+                }
+            }
+            _ => (),
+        }
+        self.content.on_event(
+            event,
+            layout,
+            cursor_position,
+            messages,
+            renderer,
+            clipboard,
+        )
+    }
+
+    fn draw(
+        &self,
+        renderer: &mut Renderer,
+        defaults: &Renderer::Defaults,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        viewport: &Rectangle,
+    ) -> Renderer::Output {
+        self.content
+            .draw(renderer, defaults, layout, cursor_position, viewport)
+    }
+
+    fn hash_layout(&self, state: &mut Hasher) {
+        struct Marker;
+        std::any::TypeId::of::<Marker>().hash(state);
+        self.content.hash_layout(state);
+    }
+
+    fn overlay(&mut self, layout: Layout<'_>) -> Option<overlay::Element<'_, Message, Renderer>> {
+        self.content.overlay(layout)
+    }
+}
+
+impl<'a, Message: 'a, Renderer: iced_native::Renderer + 'a> From<SwipeArea<'a, Message, Renderer>>
+    for Element<'a, Message, Renderer>
+{
+    fn from(area: SwipeArea<'a, Message, Renderer>) -> Self {
+        Element::new(area)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_swipe() {
+        let mut tracker = SwipeTracker::default();
+        let mut drag = |from: (f32, f32), to: (f32, f32)| {
+            tracker.pressed(Point::new(from.0, from.1));
+            tracker.released(Point::new(to.0, to.1))
+        };
+        assert_eq!(drag((300.0, 200.0), (100.0, 220.0)), Some(Swipe::Left));
+        assert_eq!(drag((300.0, 100.0), (310.0, 300.0)), Some(Swipe::Down));
+        assert_eq!(drag((300.0, 100.0), (320.0, 130.0)), None);
+        assert_eq!(drag((100.0, 100.0), (300.0, 100.0)), None);
+        assert_eq!(drag((300.0, 100.0), (150.0, 250.0)), None);
+        // release without press
+        assert_eq!(tracker.released(Point::new(0.0, 0.0)), None);
+    }
+}
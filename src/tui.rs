@@ -0,0 +1,322 @@
+//! Terminal frontend for headless machines, shares the config and queues with the GUI.
+//! Log output goes to stderr and should be redirected, e.g. `--tui 2>log.txt`.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        mpsc::{Receiver, Sender},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use audio_wrench_core::journal::Journal;
+use audio_wrench_core::metadata::{self, TrackInfo};
+use audio_wrench_core::player::{PlaybackState, Player, PlayerCommand, PlayerStatus};
+use audio_wrench_core::queue;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Style},
+    widgets::{Block, Gauge, List, ListItem, Paragraph},
+    DefaultTerminal, Frame,
+};
+
+use crate::prelude::*;
+use crate::{
+    content_blocker, format_duration, journal_path, load_config, open_store, save_compacted,
+    timing, ConfigData, QueueEnd, QueueSource, SAVE_INTERVAL,
+};
+
+/// Argument starting the terminal frontend instead of the GUI
+pub const TUI_ARG: &str = "--tui";
+/// How long to wait for input before handling player updates
+const INPUT_POLL: Duration = Duration::from_millis(100);
+/// Volume change per key press
+const VOLUME_STEP: u8 = 5;
+
+struct Tui {
+    data: ConfigData<'static>,
+    tx: Sender<PlayerCommand>,
+    rx: Receiver<PlayerStatus>,
+    /// Generation of the last started track, see [`PlayerCommand::Play`]
+    generation: u64,
+    /// First queue entry is playing or about to
+    head_started: bool,
+    current_file: Option<String>,
+    paused: bool,
+    playtime: Option<Duration>,
+    length: Option<Duration>,
+    /// Last unplayable track with the reason
+    error: Option<String>,
+    /// Tags of the tracks of all queues and favorites, read once the content filter or
+    /// endless play needs them
+    metadata: HashMap<String, TrackInfo>,
+    last_save: Instant,
+    quit: bool,
+}
+
+/// Run the terminal frontend, blocks until quit
+pub fn run() -> Result<()> {
+    let mut data = load_config();
+    data.migrate_favorites();
+    timing::phase("config loaded");
+    crate::i18n::set_language(data.language);
+    let (tx, rx, _child) = Player::new(data.routing.clone().into_owned())?;
+    tx.send(PlayerCommand::Preamp(data.preamp))
+        .expect("Can't send playback command!");
+    tx.send(PlayerCommand::DspChain(data.dsp_chain.to_vec()))
+        .expect("Can't send playback command!");
+    let mut tui = Tui {
+        data,
+        tx,
+        rx,
+        generation: 0,
+        head_started: false,
+        current_file: None,
+        paused: false,
+        playtime: None,
+        length: None,
+        error: None,
+        metadata: HashMap::new(),
+        last_save: Instant::now(),
+        quit: false,
+    };
+    tui.play_next();
+
+    timing::phase("player started");
+    let mut terminal = ratatui::init();
+    timing::finish("terminal ready");
+    let result = tui.run(&mut terminal);
+    ratatui::restore();
+    tui.save();
+    result
+}
+
+impl Tui {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        while !self.quit {
+            terminal.draw(|frame| self.draw(frame))?;
+            if event::poll(INPUT_POLL)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press {
+                        self.key(key.code);
+                    }
+                }
+            }
+            while let Ok(status) = self.rx.try_recv() {
+                self.status(status);
+            }
+            if self.last_save.elapsed() >= SAVE_INTERVAL {
+                self.save();
+            }
+        }
+        Ok(())
+    }
+
+    fn key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Char(' ') | KeyCode::Char('p') => self
+                .tx
+                .send(PlayerCommand::Pause)
+                .expect("Can't send playback command!"),
+            KeyCode::Char('n') | KeyCode::Right => self.play_next(),
+            KeyCode::Char('+') | KeyCode::Up => {
+                self.set_volume(self.data.volume.saturating_add(VOLUME_STEP))
+            }
+            KeyCode::Char('-') | KeyCode::Down => {
+                self.set_volume(self.data.volume.saturating_sub(VOLUME_STEP))
+            }
+            _ => (),
+        }
+    }
+
+    fn set_volume(&mut self, volume: u8) {
+        self.data.volume = volume.min(100);
+        self.tx
+            .send(PlayerCommand::Volume(self.data.volume))
+            .expect("Can't send playback command!");
+    }
+
+    fn status(&mut self, status: PlayerStatus) {
+        match status {
+            PlayerStatus::Playing(_, generation)
+            | PlayerStatus::Ended(generation)
+            | PlayerStatus::InvalidFile(_, _, generation)
+                if generation != self.generation => {}
+            PlayerStatus::Playing(file, _) => {
+                self.current_file = Some(file);
+                self.paused = false;
+            }
+            PlayerStatus::Ended(_) => self.play_next(),
+            PlayerStatus::InvalidFile(file, reason, _) => {
+                warn!("Can't play {}: {}", file, reason);
+                if self.data.keep_broken {
+                    Arc::make_mut(&mut self.data.broken).insert(file.clone());
+                }
+                self.error = Some(format!("{}: {}", file, reason));
+                self.play_next();
+            }
+            PlayerStatus::Paused => self.paused = true,
+            PlayerStatus::Progress(progress) => {
+                self.playtime = Some(progress.position);
+                self.length = progress.duration;
+                self.paused = progress.state == PlaybackState::Paused;
+            }
+            _ => (),
+        }
+    }
+
+    /// Remove the started track and play the first playable one matching the filter,
+    /// a ran out queue continues as set for its end like in the GUI
+    fn play_next(&mut self) {
+        let started = std::mem::take(&mut self.head_started);
+        let last = self.current_file.take();
+        self.playtime = None;
+        self.length = None;
+        let queue_end = self.data.queue_end();
+        let matcher = self
+            .data
+            .content_filter
+            .enabled
+            .then(|| self.data.content_filter.matcher());
+        if matcher.is_some() || queue_end.fill().is_some() {
+            self.read_metadata();
+        }
+        let data = &mut self.data;
+        let store = queue_end.fill().and_then(|_| open_store());
+        let playlists = Arc::make_mut(&mut data.playlists);
+        if queue_end.fill().is_some() {
+            playlists.entry(data.path.clone()).or_default();
+        }
+        let queue = match playlists.get_mut(&data.path) {
+            Some(v) => v,
+            None => return,
+        };
+        let broken = &data.broken;
+        if started {
+            queue::pop_head(queue, |t| broken.contains(t));
+        }
+        let blocked = content_blocker(matcher.as_ref(), &self.metadata, &data.track_tags);
+        let playable = |t: &str| !broken.contains(t) && !blocked(t);
+        if queue.is_empty() {
+            let source = QueueSource {
+                path: &data.path,
+                charset: data.playlist_charset,
+                metadata: &self.metadata,
+                store: store.as_ref(),
+            };
+            queue_end.refill(queue, &source, playable, last.as_deref(), started);
+        }
+        let filter = data.filter.as_ref();
+        if !queue::promote(queue, |t| playable(t) && t.contains(filter)) {
+            match queue.is_empty() && started && queue_end == QueueEnd::Quit {
+                true => {
+                    info!("Queue ended, quitting");
+                    self.quit = true;
+                }
+                false => info!("No playable track matching filter {:?}", filter),
+            }
+            self.tx
+                .send(PlayerCommand::Stop)
+                .expect("Can't send playback command!");
+            return;
+        }
+        self.generation += 1;
+        self.head_started = true;
+        self.tx
+            .send(PlayerCommand::Play(
+                queue[0].clone(),
+                data.volume,
+                self.generation,
+            ))
+            .expect("Can't send playback command!");
+    }
+
+    /// Read the tags of all tracks of queues and favorites not read yet
+    fn read_metadata(&mut self) {
+        let favorites = self.data.favorite_lists.values().flatten();
+        for track in self.data.playlists.values().flatten().chain(favorites) {
+            if !track.contains("://") && !self.metadata.contains_key(track) {
+                self.metadata.insert(track.clone(), metadata::read(track));
+            }
+        }
+    }
+
+    fn save(&mut self) {
+        self.last_save = Instant::now();
+        // changes journaled by the GUI were applied on load
+        let rotated = Journal::open(&journal_path())
+            .and_then(|mut v| v.rotate())
+            .unwrap_or_else(|e| {
+                warn!("{}", e);
+                None
+            });
+        save_compacted(&self.data, rotated);
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [current, progress, queue, help] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let state = match self.paused {
+            true => tr!("tui-paused"),
+            false => tr!("tui-playing"),
+        };
+        let title = format!("Audio Wrench - {} - {}%", state, self.data.volume);
+        let text = match (&self.current_file, &self.error) {
+            (Some(file), _) => file.clone(),
+            (None, Some(error)) => error.clone(),
+            (None, None) => tr!("tui-nothing"),
+        };
+        frame.render_widget(
+            Paragraph::new(text).block(Block::bordered().title(title)),
+            current,
+        );
+
+        let ratio = match (self.playtime, self.length) {
+            (Some(playtime), Some(length)) if !length.is_zero() => {
+                (playtime.as_secs_f64() / length.as_secs_f64()).min(1.0)
+            }
+            _ => 0.0,
+        };
+        let label = format!(
+            "{}/{}",
+            format_duration(self.playtime),
+            format_duration(self.length)
+        );
+        frame.render_widget(
+            Gauge::default()
+                .block(Block::bordered())
+                .gauge_style(Style::default().fg(Color::Cyan))
+                .ratio(ratio)
+                .label(label),
+            progress,
+        );
+
+        let tracks = self.data.playlists.get(&self.data.path);
+        let skip = if self.head_started { 1 } else { 0 };
+        let items: Vec<ListItem> = tracks
+            .into_iter()
+            .flatten()
+            .skip(skip)
+            .take(queue.height as usize)
+            .map(|t| match self.data.broken.contains(t) {
+                true => ListItem::new(t.as_str()).style(Style::default().fg(Color::DarkGray)),
+                false => ListItem::new(t.as_str()),
+            })
+            .collect();
+        let count = tracks.map_or(0, |v| v.len().saturating_sub(skip));
+        frame.render_widget(
+            List::new(items).block(Block::bordered().title(tr!("tui-queue", count = count))),
+            queue,
+        );
+        frame.render_widget(Paragraph::new(tr!("tui-help")), help);
+    }
+}
@@ -0,0 +1,244 @@
+//! Line based interface for voice assistants and push-to-talk scripts.
+//! Each line is a spoken command like `next`, `pause`, `volume 40` or `play playlist jazz`,
+//! answered with `ok`, `ok <playlist>` or `error <reason>`. Playlists are found by
+//! fuzzy matching of their file names, speech recognition rarely gets names exactly.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use audio_wrench_core::fuzzy;
+
+use crate::control::{ControlCommand, ControlHandle};
+use crate::prelude::*;
+
+/// Volume change of `louder` and `quieter`
+const VOLUME_STEP: i16 = 10;
+/// Longest line in bytes, spoken commands are short
+const MAX_LINE: u64 = 1024;
+/// Connections served at the same time, more are closed right away
+const MAX_CONNECTIONS: usize = 4;
+
+#[derive(Debug, Clone, PartialEq)]
+enum VoiceCommand {
+    Control(ControlCommand),
+    /// Relative volume change
+    VolumeStep(i16),
+    /// Spoken name of a playlist
+    Playlist(String),
+}
+
+/// Command of a spoken line, None if not understood
+fn parse(line: &str) -> Option<VoiceCommand> {
+    let text = line
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let text = text.strip_prefix("please ").unwrap_or(&text);
+    let command = match text {
+        "play" | "resume" | "continue" => ControlCommand::Play,
+        "pause" => ControlCommand::Pause,
+        "stop" => ControlCommand::Stop,
+        "next" | "skip" | "next track" | "next song" | "skip track" | "skip song" => {
+            ControlCommand::Next
+        }
+        "clear queue" | "clear the queue" => ControlCommand::ClearQueue,
+        "louder" | "volume up" => return Some(VoiceCommand::VolumeStep(VOLUME_STEP)),
+        "quieter" | "volume down" => return Some(VoiceCommand::VolumeStep(-VOLUME_STEP)),
+        _ => {
+            if let Some(volume) = text
+                .strip_prefix("set volume to ")
+                .or_else(|| text.strip_prefix("volume "))
+            {
+                let volume = volume.trim_end_matches(" percent").trim_end_matches('%');
+                return volume
+                    .parse::<u8>()
+                    .ok()
+                    .map(|v| VoiceCommand::Control(ControlCommand::Volume(v.min(100))));
+            }
+            let name = text.strip_prefix("play ")?;
+            let name = name.strip_prefix("the ").unwrap_or(name);
+            let name = name
+                .strip_prefix("playlist ")
+                .or_else(|| name.strip_suffix(" playlist"))
+                .unwrap_or(name);
+            return Some(VoiceCommand::Playlist(name.to_string()));
+        }
+    };
+    Some(VoiceCommand::Control(command))
+}
+
+/// Loaded playlist matching the spoken `name`
+fn find_playlist(name: &str, playlists: &[PathBuf]) -> Option<PathBuf> {
+    let names = playlists
+        .iter()
+        .filter_map(|p| p.file_stem().and_then(|v| v.to_str()).map(|stem| (p, stem)));
+    fuzzy::best_match(name, names).cloned()
+}
+
+/// Answer to a line
+fn execute(line: &str, control: &ControlHandle) -> String {
+    let command = match parse(line) {
+        Some(v) => v,
+        None => return String::from("error unknown command"),
+    };
+    debug!("Voice command {:?}", command);
+    let mut answer = String::from("ok");
+    let command = match command {
+        VoiceCommand::Control(v) => v,
+        VoiceCommand::VolumeStep(step) => {
+            let volume = control.status().volume as i16 + step;
+            ControlCommand::Volume(volume.clamp(0, 100) as u8)
+        }
+        VoiceCommand::Playlist(name) => match find_playlist(&name, &control.status().playlists) {
+            Some(path) => {
+                if let Some(stem) = path.file_stem() {
+                    answer = format!("ok {}", stem.to_string_lossy());
+                }
+                ControlCommand::OpenPlaylist(path)
+            }
+            None => return format!("error no playlist matches {}", name),
+        },
+    };
+    match control.send(command) {
+        true => answer,
+        false => String::from("error player is shutting down"),
+    }
+}
+
+/// Next line of `reader` without the line break, None at the end.
+/// Fails on lines longer than [MAX_LINE] instead of buffering them.
+fn read_line(reader: &mut impl BufRead) -> Result<Option<String>> {
+    let mut line = String::new();
+    let read = reader.take(MAX_LINE + 1).read_line(&mut line)?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if !line.ends_with('\n') && read as u64 > MAX_LINE {
+        return Err(eyre!("Line longer than {} bytes", MAX_LINE));
+    }
+    Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+fn serve(stream: TcpStream, control: ControlHandle) -> Result<()> {
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    loop {
+        let line = match read_line(&mut reader) {
+            Ok(Some(v)) => v,
+            Ok(None) => return Ok(()),
+            Err(e) => {
+                writeln!(writer, "error line too long")?;
+                return Err(e);
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        writeln!(writer, "{}", execute(&line, &control))?;
+    }
+}
+
+/// Counts a served connection until dropped
+struct Connection(Arc<AtomicUsize>);
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Accept connections on `addr` from a separate thread.
+/// Only loopback addresses are allowed, the commands aren't authenticated.
+pub fn spawn(addr: SocketAddr, control: ControlHandle) -> Result<()> {
+    if !addr.ip().is_loopback() {
+        return Err(eyre!("Voice commands only listen on loopback addresses"));
+    }
+    let listener = TcpListener::bind(addr).wrap_err("Can't bind voice command socket")?;
+    thread::Builder::new()
+        .name("voice commands".to_string())
+        .spawn(move || {
+            info!("Voice commands listening on {}", addr);
+            let connections = Arc::new(AtomicUsize::new(0));
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(v) => v,
+                    Err(e) => {
+                        warn!("Can't accept voice command connection: {}", e);
+                        continue;
+                    }
+                };
+                if connections.fetch_add(1, Ordering::Relaxed) >= MAX_CONNECTIONS {
+                    connections.fetch_sub(1, Ordering::Relaxed);
+                    debug!("Too many voice command connections");
+                    continue;
+                }
+                let connection = Connection(connections.clone());
+                let control = control.clone();
+                thread::spawn(move || {
+                    let _connection = connection;
+                    if let Err(e) = serve(stream, control) {
+                        debug!("Voice command connection closed: {}", e);
+                    }
+                });
+            }
+        })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let control = |v| Some(VoiceCommand::Control(v));
+        assert_eq!(parse("Next"), control(ControlCommand::Next));
+        assert_eq!(parse("please  pause"), control(ControlCommand::Pause));
+        assert_eq!(
+            parse("set volume to 40 percent"),
+            control(ControlCommand::Volume(40))
+        );
+        assert_eq!(parse("volume loud"), None);
+        assert_eq!(parse("louder"), Some(VoiceCommand::VolumeStep(10)));
+        assert_eq!(
+            parse("play playlist Morning Coffee"),
+            Some(VoiceCommand::Playlist(String::from("morning coffee")))
+        );
+        assert_eq!(
+            parse("play the jazz playlist"),
+            Some(VoiceCommand::Playlist(String::from("jazz")))
+        );
+        assert_eq!(parse("make coffee"), None);
+
+        let playlists = [
+            PathBuf::from("/music/Jazz Classics.m3u"),
+            PathBuf::from("/music/morning_coffee.xspf"),
+        ];
+        assert_eq!(
+            find_playlist("morning coffee", &playlists),
+            Some(playlists[1].clone())
+        );
+        assert_eq!(
+            find_playlist("jazz classic", &playlists),
+            Some(playlists[0].clone())
+        );
+        assert_eq!(find_playlist("heavy metal", &playlists), None);
+
+        let mut input = &b"next\r\n\nlouder"[..];
+        assert_eq!(read_line(&mut input).unwrap().as_deref(), Some("next"));
+        assert_eq!(read_line(&mut input).unwrap().as_deref(), Some(""));
+        assert_eq!(read_line(&mut input).unwrap().as_deref(), Some("louder"));
+        assert_eq!(read_line(&mut input).unwrap(), None);
+        let long = vec![b'a'; MAX_LINE as usize + 10];
+        assert!(read_line(&mut &long[..]).is_err());
+    }
+}
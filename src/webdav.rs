@@ -0,0 +1,178 @@
+//! WebDAV folders as track and playlist source, e.g. Nextcloud
+
+use percent_encoding::percent_decode_str;
+use quick_xml::events::Event;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::plugin::{Entry, Listing, Plugin, TrackSource};
+use crate::prelude::*;
+use crate::remote::{Credentials, Server};
+use crate::secrets::Secret;
+
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:resourcetype/></d:prop></d:propfind>"#;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebDavConfig {
+    /// Root folder, e.g. `https://cloud.example.com/remote.php/dav/files/user/Music/`
+    pub url: String,
+    pub user: String,
+    /// Kept in the secret store, written into the config only while storing it fails
+    #[serde(default, skip_serializing_if = "Secret::skip_config")]
+    pub password: Secret,
+}
+
+pub struct WebDav {
+    root: String,
+    credentials: Credentials,
+}
+
+impl WebDav {
+    pub fn new(config: &WebDavConfig) -> Self {
+        let root = root(config);
+        Self {
+            credentials: Credentials::new(vec![server(config)]),
+            root,
+        }
+    }
+}
+
+/// Root folder URL ending with a slash
+fn root(config: &WebDavConfig) -> String {
+    let mut root = config.url.clone();
+    if !root.ends_with('/') {
+        root.push('/');
+    }
+    root
+}
+
+/// Credentials of the folder, for the player to fetch its tracks
+pub fn server(config: &WebDavConfig) -> Server {
+    Server::Basic {
+        prefix: root(config),
+        user: config.user.clone(),
+        password: config.password.as_str().to_string(),
+    }
+}
+
+impl Plugin for WebDav {
+    fn name(&self) -> &'static str {
+        "WebDAV"
+    }
+
+    fn source(&mut self) -> Option<&mut dyn TrackSource> {
+        Some(self)
+    }
+}
+
+impl TrackSource for WebDav {
+    fn browse(&mut self, parent: Option<&str>) -> Listing {
+        let folder = parent.unwrap_or(&self.root).to_string();
+        let credentials = self.credentials.clone();
+        Box::new(move || list(&credentials, &folder))
+    }
+}
+
+/// Files and folders in `folder`
+fn list(credentials: &Credentials, folder: &str) -> Result<Vec<Entry>> {
+    let folder = Url::parse(folder)?;
+    let response = credentials
+        .request("PROPFIND", folder.as_str())
+        .set("Depth", "1")
+        .set("Content-Type", "application/xml")
+        .send_string(PROPFIND_BODY)
+        .wrap_err("WebDAV listing failed")?
+        .into_string()?;
+    let mut entries = Vec::new();
+    for (url, is_dir) in parse_multistatus(&response, &folder)? {
+        if url.path().trim_end_matches('/') == folder.path().trim_end_matches('/') {
+            continue;
+        }
+        let title = url
+            .path_segments()
+            .and_then(|mut v| v.rfind(|s| !s.is_empty()))
+            .map(|v| percent_decode_str(v).decode_utf8_lossy().into_owned())
+            .unwrap_or_default();
+        let location = url.to_string();
+        if is_dir {
+            entries.push(Entry {
+                id: location,
+                title,
+                track: None,
+            });
+        } else if crate::is_audio_file(title.as_ref()) || crate::is_playlist_file(title.as_ref()) {
+            entries.push(Entry {
+                id: location.clone(),
+                title,
+                track: Some(location),
+            });
+        }
+    }
+    entries.sort_by(|a, b| (a.track.is_some(), &a.title).cmp(&(b.track.is_some(), &b.title)));
+    Ok(entries)
+}
+
+/// Parse PROPFIND multistatus response into entry URLs and whether they are folders
+fn parse_multistatus(xml: &str, base: &Url) -> Result<Vec<(Url, bool)>> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    reader.trim_text(true);
+    let mut buf = Vec::new();
+    let mut result = Vec::new();
+    let mut href: Option<String> = None;
+    let mut in_href = false;
+    let mut is_dir = false;
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Start(e) | Event::Empty(e) => match e.local_name() {
+                b"response" => {
+                    href = None;
+                    is_dir = false;
+                }
+                b"href" => in_href = true,
+                b"collection" => is_dir = true,
+                _ => (),
+            },
+            Event::Text(e) if in_href => {
+                href = Some(e.unescape_and_decode(&reader)?);
+            }
+            Event::End(e) => match e.local_name() {
+                b"href" => in_href = false,
+                b"response" => {
+                    if let Some(href) = href.take() {
+                        result.push((base.join(&href)?, is_dir));
+                    }
+                }
+                _ => (),
+            },
+            Event::Eof => break,
+            _ => (),
+        }
+        buf.clear();
+    }
+    Ok(result)
+}
+
+#[test]
+fn test_parse_multistatus() {
+    let xml = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:">
+  <d:response><d:href>/dav/Music/</d:href>
+    <d:propstat><d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop></d:propstat>
+  </d:response>
+  <d:response><d:href>/dav/Music/My%20Song.mp3</d:href>
+    <d:propstat><d:prop><d:resourcetype/></d:prop></d:propstat>
+  </d:response>
+</d:multistatus>"#;
+    let base = Url::parse("https://example.com/dav/Music/").unwrap();
+    let entries = parse_multistatus(xml, &base).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert!(entries[0].1);
+    assert_eq!(
+        entries[1],
+        (
+            Url::parse("https://example.com/dav/Music/My%20Song.mp3").unwrap(),
+            false
+        )
+    );
+}
@@ -0,0 +1,104 @@
+//! Mouse wheel volume and seeking, limited to the parts of the view showing the track and volume.
+//! Scrolling anywhere else scrolls the page as usual.
+
+use std::hash::Hash;
+
+use iced_native::{
+    event, layout, mouse, overlay, Clipboard, Element, Event, Hasher, Layout, Length, Point,
+    Rectangle, Widget,
+};
+
+/// Part of the view where scrolling the mouse wheel is turned into a message
+/// instead of scrolling the page
+pub struct WheelArea<'a, Message, Renderer> {
+    content: Element<'a, Message, Renderer>,
+    on_scroll: fn(mouse::ScrollDelta) -> Message,
+}
+
+impl<'a, Message, Renderer> WheelArea<'a, Message, Renderer> {
+    pub fn new(
+        content: impl Into<Element<'a, Message, Renderer>>,
+        on_scroll: fn(mouse::ScrollDelta) -> Message,
+    ) -> Self {
+        Self {
+            content: content.into(),
+            on_scroll,
+        }
+    }
+}
+
+impl<'a, Message, Renderer: iced_native::Renderer> Widget<Message, Renderer>
+    for WheelArea<'a, Message, Renderer>
+{
+    fn width(&self) -> Length {
+        self.content.width()
+    }
+
+    fn height(&self) -> Length {
+        self.content.height()
+    }
+
+    fn layout(&self, renderer: &Renderer, limits: &layout::Limits) -> layout::Node {
+        self.content.layout(renderer, limits)
+    }
+
+    fn on_event(
+        &mut self,
+        event: Event,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        messages: &mut Vec<Message>,
+        renderer: &Renderer,
+        clipboard: Option<&dyn Clipboard>,
+    ) -> event::Status {
+        let status = self.content.on_event(
+            event.clone(),
+            layout,
+            cursor_position,
+            messages,
+            renderer,
+            clipboard,
+        );
+        match event {
+            // content using the wheel itself keeps it
+            Event::Mouse(mouse::Event::WheelScrolled { delta })
+                if status == event::Status::Ignored
+                    && layout.bounds().contains(cursor_position) =>
+            {
+                messages.push((self.on_scroll)(delta));
+                event::Status::Captured
+            }
+            _ => status,
+        }
+    }
+
+    fn draw(
+        &self,
+        renderer: &mut Renderer,
+        defaults: &Renderer::Defaults,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        viewport: &Rectangle,
+    ) -> Renderer::Output {
+        self.content
+            .draw(renderer, defaults, layout, cursor_position, viewport)
+    }
+
+    fn hash_layout(&self, state: &mut Hasher) {
+        struct Marker;
+        std::any::TypeId::of::<Marker>().hash(state);
+        self.content.hash_layout(state);
+    }
+
+    fn overlay(&mut self, layout: Layout<'_>) -> Option<overlay::Element<'_, Message, Renderer>> {
+        self.content.overlay(layout)
+    }
+}
+
+impl<'a, Message: 'a, Renderer: iced_native::Renderer + 'a> From<WheelArea<'a, Message, Renderer>>
+    for Element<'a, Message, Renderer>
+{
+    fn from(area: WheelArea<'a, Message, Renderer>) -> Self {
+        Element::new(area)
+    }
+}